@@ -0,0 +1,82 @@
+//! Attribute macros for the app's Tauri commands.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, FnArg, GenericArgument, Ident, ItemFn, Pat, PathArguments, Type};
+
+/// Records each call of a command in `crate::telemetry`: its name, how long it took and whether
+/// it returned `Ok`. Goes below `#[tauri::command]`; the command name is the function's name.
+/// The timings live in `AppState`, reached through the command's `State<'_, AppState>` or
+/// `AppHandle` argument, so the command needs one of them.
+///
+/// The body is moved into an async block, so `return` and `?` keep working as before.
+#[proc_macro_attribute]
+pub fn timed(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut function = parse_macro_input!(item as ItemFn);
+    let name = function.sig.ident.to_string();
+    let output = match &function.sig.output {
+        syn::ReturnType::Default => quote!(()),
+        syn::ReturnType::Type(_, ty) => quote!(#ty),
+    };
+    let body = &function.block;
+
+    if function.sig.asyncness.is_none() {
+        return syn::Error::new_spanned(&function.sig.fn_token, "#[timed] only supports async commands")
+            .to_compile_error()
+            .into();
+    }
+    // A `State` hands out a reference that outlives it; an `AppHandle` is cloned, since the body may move it
+    let timed = match state_source(&function) {
+        Some(Source::State(state)) => quote!({
+            let __telemetry = &#state.inner().telemetry;
+            crate::telemetry::track::<_, #output>(__telemetry, #name, async move #body).await
+        }),
+        Some(Source::App(app)) => quote!({
+            let __app = #app.clone();
+            let __telemetry = &tauri::Manager::state::<crate::state::AppState>(&__app).inner().telemetry;
+            crate::telemetry::track::<_, #output>(__telemetry, #name, async move #body).await
+        }),
+        None => {
+            return syn::Error::new_spanned(&function.sig.inputs, "#[timed] needs a `State<'_, AppState>` or `AppHandle` argument")
+                .to_compile_error()
+                .into();
+        }
+    };
+    function.block = Box::new(syn::parse2(timed).expect("a block"));
+    quote!(#function).into()
+}
+
+enum Source {
+    State(Ident),
+    App(Ident),
+}
+
+// The command's state argument, else its app handle
+fn state_source(function: &ItemFn) -> Option<Source> {
+    let arguments: Vec<(Ident, String)> = function.sig.inputs.iter()
+        .filter_map(|input| match input {
+            FnArg::Typed(typed) => match (&*typed.pat, &*typed.ty) {
+                (Pat::Ident(pat), Type::Path(ty)) => {
+                    let last = ty.path.segments.last()?;
+                    // `State<'_, AppState>` as "State<AppState>"; other states don't hold the timings
+                    let managed = match &last.arguments {
+                        PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+                            GenericArgument::Type(Type::Path(ty)) => ty.path.segments.last().map(|s| s.ident.to_string()),
+                            _ => None,
+                        }),
+                        _ => None,
+                    };
+                    let kind = match managed {
+                        Some(managed) => format!("{}<{}>", last.ident, managed),
+                        None => last.ident.to_string(),
+                    };
+                    Some((pat.ident.clone(), kind))
+                }
+                _ => None,
+            },
+            FnArg::Receiver(_) => None,
+        })
+        .collect();
+    let find = |kind: &str| arguments.iter().find(|(_, ty)| ty == kind).map(|(ident, _)| ident.clone());
+    find("State<AppState>").map(Source::State).or_else(|| find("AppHandle").map(Source::App))
+}
@@ -0,0 +1,336 @@
+//! Human approval for destructive operations a swarm asks for: deletes, writes outside the
+//! project and commands that aren't in the project's `allowed_commands`. The swarm engine runs
+//! a task's work, including the actions its agent asks for in `agent_actions`, `on_behalf_of`
+//! its swarm, and that is how an operation knows who asked for it;
+//! outside of a task the user is acting and nothing is asked. With the project's
+//! `require_approval` setting on, the operation sends `approval:requested` and waits for
+//! `resolve_approval`, or for `APPROVAL_TIMEOUT`, after which it is refused. Decisions the user
+//! asked to remember are kept as rules in the `approval_rules` setting and answer later requests
+//! without asking.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::oneshot;
+use crate::commands::project_settings;
+use crate::database::Database;
+use crate::error::AppError;
+use crate::path_access;
+use crate::state::AppState;
+use crate::telemetry::timed;
+
+pub const APPROVAL_REQUESTED_EVENT: &str = "approval:requested";
+pub const APPROVAL_RESOLVED_EVENT: &str = "approval:resolved";
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(300);
+const RULES_SETTING: &str = "approval_rules";
+
+/// What the swarm asked for.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Operation {
+    Delete { path: String },
+    Write { path: String },
+    ExecuteCommand { command: String, args: Vec<String> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleKind {
+    Delete,
+    Write,
+    Command,
+}
+
+/// A remembered decision. `pattern` is a program name for commands and a directory, covering
+/// everything under it, for deletes and writes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalRule {
+    pub project_id: String,
+    pub kind: RuleKind,
+    pub pattern: String,
+    pub allow: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Payload of `approval:requested`, and what `get_pending_approvals` lists.
+#[derive(Debug, Clone, Serialize)]
+pub struct Approval {
+    pub id: String,
+    pub project_id: String,
+    pub swarm_id: String,
+    pub operation: Operation,
+    pub description: String,
+    pub requested_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Payload of `approval:resolved`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ApprovalResolved {
+    pub id: String,
+    pub allowed: bool,
+    pub remembered: bool,
+    pub timed_out: bool,
+}
+
+struct Pending {
+    approval: Approval,
+    decide: oneshot::Sender<bool>,
+}
+
+/// Approvals waiting for the user, by id.
+#[derive(Default)]
+pub struct PendingApprovals(Mutex<HashMap<String, Pending>>);
+
+tokio::task_local! {
+    // The swarm whose task is running
+    static REQUESTING_SWARM: String;
+}
+
+/// Runs `work` for `swarm_id`: the operations it asks for are authorized as that swarm's.
+pub async fn on_behalf_of<F: Future>(swarm_id: &str, work: F) -> F::Output {
+    REQUESTING_SWARM.scope(swarm_id.to_string(), work).await
+}
+
+/// Answers a pending approval. With `remember` the decision also covers later requests of the
+/// same kind in the project: the same program, or anything under the same directory.
+#[tauri::command]
+#[timed]
+pub async fn resolve_approval(app: AppHandle, state: State<'_, AppState>, approval_id: String, allow: bool, remember: bool) -> Result<(), AppError> {
+    log::info!("Resolving approval {}: {}", approval_id, if allow { "allowed" } else { "denied" });
+
+    let resolved = settle(&state, approval_id, allow, remember)?;
+    publish(&app, APPROVAL_RESOLVED_EVENT, &resolved);
+    Ok(())
+}
+
+fn settle(state: &AppState, approval_id: String, allow: bool, remember: bool) -> Result<ApprovalResolved, AppError> {
+    let pending = state.approvals.0.lock().unwrap().remove(&approval_id)
+        .ok_or_else(|| AppError::NotFound(format!("No pending approval {}", approval_id)))?;
+    if remember {
+        add_rule(&state.db, rule_for(&pending.approval, allow))?;
+    }
+    // The operation may have given up waiting in the meantime
+    let _ = pending.decide.send(allow);
+    Ok(ApprovalResolved { id: approval_id, allowed: allow, remembered: remember, timed_out: false })
+}
+
+/// Approvals still waiting for an answer, oldest first.
+#[tauri::command]
+#[timed]
+pub async fn get_pending_approvals(state: State<'_, AppState>, project_id: Option<String>) -> Result<Vec<Approval>, AppError> {
+    let mut approvals: Vec<Approval> = state.approvals.0.lock().unwrap()
+        .values()
+        .map(|pending| pending.approval.clone())
+        .filter(|approval| project_id.as_ref().is_none_or(|id| *id == approval.project_id))
+        .collect();
+    approvals.sort_by_key(|approval| approval.requested_at);
+    Ok(approvals)
+}
+
+/// Fails with `NotApproved` unless `operation` may go ahead. Returns at once when no swarm is
+/// asking, the project doesn't require approval or the operation isn't destructive; otherwise a
+/// rule or the user decides.
+pub async fn authorize(app: &AppHandle, operation: Operation) -> Result<(), AppError> {
+    let Ok(swarm_id) = REQUESTING_SWARM.try_with(|id| id.clone()) else {
+        return Ok(());
+    };
+    let state = app.state::<AppState>();
+    authorize_for(&state, swarm_id, operation, APPROVAL_TIMEOUT, |event, payload| publish(app, event, &payload)).await
+}
+
+// What `authorize` does once the swarm is known, with the events handed to `notify`
+async fn authorize_for(
+    state: &AppState,
+    swarm_id: String,
+    operation: Operation,
+    timeout: Duration,
+    notify: impl Fn(&str, serde_json::Value),
+) -> Result<(), AppError> {
+    let db = &state.db;
+    let swarm = db.get_swarm_by_id(&swarm_id)?
+        .ok_or_else(|| AppError::NotFound(format!("Swarm not found: {}", swarm_id)))?;
+    let project = db.get_project(&swarm.project_id)?
+        .ok_or_else(|| AppError::NotFound(format!("Project not found: {}", swarm.project_id)))?;
+    let settings = project_settings(&project);
+    if !settings.require_approval || !is_destructive(&operation, &project.path, &settings.allowed_commands) {
+        return Ok(());
+    }
+
+    let description = describe(&operation);
+    if let Some(rule) = matching_rule(db, &project.id, &operation)? {
+        log::info!("{} {} by a remembered rule", description, if rule.allow { "allowed" } else { "denied" });
+        return if rule.allow { Ok(()) } else { Err(AppError::NotApproved(description)) };
+    }
+
+    let now = Utc::now();
+    let approval = Approval {
+        id: uuid::Uuid::new_v4().to_string(),
+        project_id: project.id,
+        swarm_id,
+        operation,
+        description: description.clone(),
+        requested_at: now,
+        expires_at: now + chrono::Duration::from_std(timeout).unwrap_or_default(),
+    };
+    let id = approval.id.clone();
+    let (decide, decision) = oneshot::channel();
+    state.approvals.0.lock().unwrap().insert(id.clone(), Pending { approval: approval.clone(), decide });
+    log::info!("Waiting for approval {}: {}", id, description);
+    notify(APPROVAL_REQUESTED_EVENT, serde_json::to_value(&approval).unwrap_or_default());
+
+    match tokio::time::timeout(timeout, decision).await {
+        Ok(Ok(true)) => Ok(()),
+        Ok(_) => Err(AppError::NotApproved(description)),
+        Err(_) => {
+            state.approvals.0.lock().unwrap().remove(&id);
+            let resolved = ApprovalResolved { id, allowed: false, remembered: false, timed_out: true };
+            notify(APPROVAL_RESOLVED_EVENT, serde_json::to_value(&resolved).unwrap_or_default());
+            Err(AppError::NotApproved(format!("{} (no answer within {} seconds)", description, timeout.as_secs())))
+        }
+    }
+}
+
+fn publish<T: Serialize>(app: &AppHandle, event: &str, payload: &T) {
+    if let Err(e) = app.emit(event, payload) {
+        log::warn!("Failed to emit {}: {}", event, e);
+    }
+}
+
+fn is_destructive(operation: &Operation, project_path: &str, allowed_commands: &[String]) -> bool {
+    match operation {
+        Operation::Delete { .. } => true,
+        Operation::ExecuteCommand { command, .. } => !allowed_commands.iter().any(|allowed| allowed == program_name(command)),
+        Operation::Write { path } => !path_access::is_within(Path::new(path), Path::new(project_path)),
+    }
+}
+
+pub(crate) fn describe(operation: &Operation) -> String {
+    match operation {
+        Operation::Delete { path } => format!("Delete {}", path),
+        Operation::Write { path } => format!("Write {}", path),
+        Operation::ExecuteCommand { command, args } if args.is_empty() => format!("Run {}", command),
+        Operation::ExecuteCommand { command, args } => format!("Run {} {}", command, args.join(" ")),
+    }
+}
+
+// `cargo` for `/usr/bin/cargo`, so a rule for a program holds wherever it is run from
+fn program_name(command: &str) -> &str {
+    Path::new(command).file_name().and_then(|name| name.to_str()).unwrap_or(command)
+}
+
+fn rule_for(approval: &Approval, allow: bool) -> ApprovalRule {
+    let (kind, pattern) = match &approval.operation {
+        Operation::Delete { path } => (RuleKind::Delete, parent_dir(path)),
+        Operation::Write { path } => (RuleKind::Write, parent_dir(path)),
+        Operation::ExecuteCommand { command, .. } => (RuleKind::Command, program_name(command).to_string()),
+    };
+    ApprovalRule { project_id: approval.project_id.clone(), kind, pattern, allow, created_at: Utc::now() }
+}
+
+fn parent_dir(path: &str) -> String {
+    Path::new(path).parent()
+        .map(|parent| parent.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+// The newest rule that covers the operation
+fn matching_rule(db: &Database, project_id: &str, operation: &Operation) -> Result<Option<ApprovalRule>, AppError> {
+    let covers = |rule: &ApprovalRule| match operation {
+        Operation::Delete { path } => rule.kind == RuleKind::Delete && path_access::is_within(Path::new(path), Path::new(&rule.pattern)),
+        Operation::Write { path } => rule.kind == RuleKind::Write && path_access::is_within(Path::new(path), Path::new(&rule.pattern)),
+        Operation::ExecuteCommand { command, .. } => rule.kind == RuleKind::Command && rule.pattern == program_name(command),
+    };
+    Ok(rules(db)?.into_iter()
+        .rev()
+        .find(|rule| rule.project_id == project_id && covers(rule)))
+}
+
+fn rules(db: &Database) -> Result<Vec<ApprovalRule>, AppError> {
+    let Some(value) = db.get_setting(RULES_SETTING)? else {
+        return Ok(Vec::new());
+    };
+    serde_json::from_str(&value)
+        .map_err(|e| AppError::Internal(format!("Unreadable approval rules: {}", e)))
+}
+
+fn add_rule(db: &Database, rule: ApprovalRule) -> Result<(), AppError> {
+    let mut rules = rules(db)?;
+    // A new decision replaces the old one for the same thing
+    rules.retain(|existing| !(existing.project_id == rule.project_id && existing.kind == rule.kind && existing.pattern == rule.pattern));
+    rules.push(rule);
+    let value = serde_json::to_string(&rules).map_err(|e| AppError::Internal(e.to_string()))?;
+    db.set_setting(RULES_SETTING, &value)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::ProjectSettings;
+    use crate::database::{DbProject, DbSwarm};
+
+    // A swarm in a project that requires approval
+    fn gated_state() -> AppState {
+        let state = AppState::new(Database::open_in_memory().unwrap());
+        let settings = ProjectSettings { require_approval: true, ..Default::default() };
+        state.db.create_project(&DbProject {
+            id: "project-1".to_string(),
+            name: "project".to_string(),
+            path: std::env::temp_dir().to_string_lossy().to_string(),
+            description: None,
+            settings: Some(serde_json::to_string(&settings).unwrap()),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            last_opened_at: None,
+            archived: false,
+        }).unwrap();
+        state.db.create_swarm(&DbSwarm {
+            id: "swarm-1".to_string(),
+            name: "swarm".to_string(),
+            project_id: "project-1".to_string(),
+            objective: "tidy up".to_string(),
+            status: "running".to_string(),
+            config: "{}".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }).unwrap();
+        state
+    }
+
+    #[tokio::test]
+    async fn a_gated_operation_waits_for_the_user_and_is_refused_when_denied() {
+        let state = gated_state();
+        let delete = Operation::Delete { path: "/tmp/clauder-approvals/notes.txt".to_string() };
+        let waiting = authorize_for(&state, "swarm-1".to_string(), delete, Duration::from_secs(60), |_, _| {});
+        tokio::pin!(waiting);
+
+        assert!(tokio::time::timeout(Duration::from_millis(50), &mut waiting).await.is_err());
+        let id = state.approvals.0.lock().unwrap().keys().next().cloned().unwrap();
+        settle(&state, id, false, false).unwrap();
+
+        assert!(matches!(waiting.await, Err(AppError::NotApproved(_))));
+    }
+
+    #[tokio::test]
+    async fn a_gated_operation_nobody_answers_is_refused() {
+        let state = gated_state();
+        let command = Operation::ExecuteCommand { command: "rm".to_string(), args: vec!["-rf".to_string(), "build".to_string()] };
+        let requested = std::cell::Cell::new(0);
+
+        let result = authorize_for(&state, "swarm-1".to_string(), command, Duration::from_millis(50), |event, _| {
+            if event == APPROVAL_REQUESTED_EVENT {
+                requested.set(requested.get() + 1);
+            }
+        }).await;
+
+        assert!(matches!(result, Err(AppError::NotApproved(_))));
+        assert_eq!(requested.get(), 1);
+        assert!(state.approvals.0.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use crate::database::{DbActivityItem};
+use crate::error::AppError;
+use crate::state::AppState;
+use crate::telemetry::timed;
+use tauri::State;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityKind {
+    ChatMessage,
+    SwarmEvent,
+    TaskCompleted,
+    FileOperation,
+}
+
+/// Something that happened in one of the projects, with the ids the UI needs to open it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityItem {
+    pub kind: ActivityKind,
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub project_id: Option<String>,
+    pub project_name: Option<String>,
+    pub session_id: Option<String>,
+    pub session_name: Option<String>,
+    pub swarm_id: Option<String>,
+    pub swarm_name: Option<String>,
+    pub task_id: Option<String>,
+    pub detail: Option<String>,
+    pub summary: Option<String>,
+}
+
+/// A page of the feed; pass `next_cursor` back as `since` for the page after it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityPage {
+    pub items: Vec<ActivityItem>,
+    pub next_cursor: Option<DateTime<Utc>>,
+}
+
+const DEFAULT_ACTIVITY_LIMIT: i64 = 50;
+const MAX_ACTIVITY_LIMIT: i64 = 200;
+// Characters of each chat message shown in the feed
+const SUMMARY_CHARS: i64 = 200;
+
+/// What happened recently across all projects, newest first: chat messages, swarm events,
+/// completed tasks and file operations. `since` is the cursor of the previous page; only items
+/// older than it are returned.
+#[tauri::command]
+#[timed]
+pub async fn get_recent_activity(state: State<'_, AppState>, limit: Option<i64>, since: Option<DateTime<Utc>>) -> Result<ActivityPage, AppError> {
+    log::info!("Getting recent activity before {:?}", since);
+
+    let limit = limit.unwrap_or(DEFAULT_ACTIVITY_LIMIT).clamp(1, MAX_ACTIVITY_LIMIT);
+    let items: Vec<ActivityItem> = state.db.get_recent_activity(since, limit, SUMMARY_CHARS)?
+        .into_iter()
+        .filter_map(|row| match activity_item_from_row(row) {
+            Ok(item) => Some(item),
+            Err(e) => {
+                log::warn!("Skipping activity item: {}", e);
+                None
+            }
+        })
+        .collect();
+
+    // A short page means the feed has nothing older
+    let next_cursor = if items.len() as i64 == limit {
+        items.last().map(|item| item.timestamp)
+    } else {
+        None
+    };
+
+    Ok(ActivityPage { items, next_cursor })
+}
+
+fn activity_item_from_row(row: DbActivityItem) -> Result<ActivityItem, AppError> {
+    let kind = match row.kind.as_str() {
+        "chat_message" => ActivityKind::ChatMessage,
+        "swarm_event" => ActivityKind::SwarmEvent,
+        "task_completed" => ActivityKind::TaskCompleted,
+        "file_operation" => ActivityKind::FileOperation,
+        other => return Err(AppError::Internal(format!("Unknown activity kind: {}", other))),
+    };
+
+    Ok(ActivityItem {
+        kind,
+        id: row.id,
+        timestamp: row.timestamp,
+        project_id: row.project_id,
+        project_name: row.project_name,
+        session_id: row.session_id,
+        session_name: row.session_name,
+        swarm_id: row.swarm_id,
+        swarm_name: row.swarm_name,
+        task_id: row.task_id,
+        detail: row.detail,
+        summary: row.summary,
+    })
+}
@@ -0,0 +1,113 @@
+//! File and process operations a swarm agent asks for in its reply, one JSON object per
+//! ```clauder-action block. They are carried out by the file and process commands themselves, and
+//! as the swarm engine runs an agent's work `on_behalf_of` its swarm, each one passes
+//! `approvals::authorize` as the swarm's. Relative paths are taken from the project's folder.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use crate::approvals::{self, Operation};
+use crate::commands::chat::extract_code_blocks;
+use crate::commands::system::{delete_file_or_directory, execute_command, write_file_content, ProcessInfo};
+use crate::database::DbProject;
+use crate::error::AppError;
+use crate::state::AppState;
+use tauri::{AppHandle, Manager};
+
+const ACTION_LANGUAGE: &str = "clauder-action";
+
+/// Goes in every agent's prompt, so it knows how to ask.
+pub(crate) const ACTION_INSTRUCTIONS: &str = "To change files or run a program, add one ```clauder-action block per operation, holding one JSON object: {\"kind\": \"write\", \"path\": ..., \"content\": ...}, {\"kind\": \"delete\", \"path\": ...} or {\"kind\": \"execute_command\", \"command\": ..., \"args\": [...]}. Paths are relative to the project.";
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum AgentAction {
+    Write { path: String, content: String },
+    Delete { path: String },
+    ExecuteCommand {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
+/// How an action went, listed under `actions` in the task's output.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ActionOutcome {
+    pub description: String,
+    // For a command: how it ended and what it printed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub process: Option<ProcessInfo>,
+}
+
+/// The actions `reply` asks for, in order. A block that isn't a valid action fails the lot, so
+/// the agent isn't left thinking something ran that didn't.
+pub(crate) fn requested_actions(reply: &str) -> Result<Vec<AgentAction>, AppError> {
+    extract_code_blocks(reply).into_iter()
+        .filter(|block| block.language.as_deref() == Some(ACTION_LANGUAGE))
+        .map(|block| serde_json::from_str(&block.content)
+            .map_err(|e| AppError::Validation(format!("Invalid action in block {}: {}", block.index, e))))
+        .collect()
+}
+
+/// Carries out the actions `reply` asks for in `project`, in order. The first one refused or
+/// failing stops the rest and is the error; a command that exits with an error isn't one.
+pub(crate) async fn perform(app: &AppHandle, project: &DbProject, reply: &str) -> Result<Vec<ActionOutcome>, AppError> {
+    let mut outcomes = Vec::new();
+    for action in requested_actions(reply)? {
+        let description = approvals::describe(&operation(project, &action));
+        log::info!("Agent action in project {}: {}", project.id, description);
+        let process = match action {
+            AgentAction::Write { path, content } => {
+                let path = in_project(project, &path);
+                write_file_content(app.clone(), app.state::<AppState>(), path, content, None, None, None, None, Some(project.id.clone())).await?;
+                None
+            }
+            AgentAction::Delete { path } => {
+                let path = in_project(project, &path);
+                delete_file_or_directory(app.clone(), app.state::<AppState>(), path, Some(project.id.clone()), None).await?;
+                None
+            }
+            AgentAction::ExecuteCommand { command, args } => {
+                let info = execute_command(app.clone(), app.state::<AppState>(), command, args, Some(project.path.clone()), None, None, None, None).await?;
+                Some(info)
+            }
+        };
+        outcomes.push(ActionOutcome { description, process });
+    }
+    Ok(outcomes)
+}
+
+fn operation(project: &DbProject, action: &AgentAction) -> Operation {
+    match action {
+        AgentAction::Write { path, .. } => Operation::Write { path: in_project(project, path) },
+        AgentAction::Delete { path } => Operation::Delete { path: in_project(project, path) },
+        AgentAction::ExecuteCommand { command, args } => Operation::ExecuteCommand { command: command.clone(), args: args.clone() },
+    }
+}
+
+fn in_project(project: &DbProject, path: &str) -> String {
+    Path::new(&project.path).join(path).to_string_lossy().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_action_blocks_are_requests() {
+        let reply = "Done.\n\n```rust\nfn main() {}\n```\n\n```clauder-action\n{\"kind\": \"delete\", \"path\": \"old.txt\"}\n```\n\n```clauder-action\n{\"kind\": \"execute_command\", \"command\": \"cargo\", \"args\": [\"test\"]}\n```\n";
+
+        let actions = requested_actions(reply).unwrap();
+
+        assert_eq!(actions, vec![
+            AgentAction::Delete { path: "old.txt".to_string() },
+            AgentAction::ExecuteCommand { command: "cargo".to_string(), args: vec!["test".to_string()] },
+        ]);
+    }
+
+    #[test]
+    fn a_malformed_action_is_refused() {
+        let reply = "```clauder-action\n{\"kind\": \"format_disk\"}\n```\n";
+        assert!(matches!(requested_actions(reply), Err(AppError::Validation(_))));
+    }
+}
@@ -1,11 +1,29 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::process::{Child, Command, Stdio};
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
-use anyhow::{Result, Context};
+use anyhow::{anyhow, Result};
 use tokio::sync::Mutex;
 use std::sync::Arc;
+use crate::database::{Database, DbAIInteraction, DbAITool};
+use crate::error::AppError;
+use crate::events::{Action, DataChanged, Entity};
+use crate::notifications;
+use crate::secrets;
+use crate::tool_io::{self, IoLoggingSettings};
+use crate::commands::{env_vars, pricing, settings};
+use crate::commands::project::ToolPolicy;
+use crate::providers::{self, DeltaSink};
+use crate::providers::ModelInfo;
+use crate::providers::proxy::ProxySettings;
+use crate::providers::ollama::OllamaModel;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio_util::sync::CancellationToken;
+use crate::state::AppState;
+use crate::telemetry::timed;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AITool {
@@ -15,8 +33,24 @@ pub struct AITool {
     pub version: String,
     pub status: String, // 'connected' | 'disconnected' | 'error' | 'connecting'
     pub capabilities: Vec<Capability>,
+    #[serde(serialize_with = "serialize_masked_config")]
     pub config: ToolSpecificConfig,
     pub last_used: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    // The key itself stays in the keychain
+    #[serde(default)]
+    pub has_api_key: bool,
+    // Non-fatal problems found when the tool was saved, e.g. an unknown model
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+// Tools are only serialized for the webview, which must never see a usable key
+fn serialize_masked_config<S: serde::Serializer>(config: &ToolSpecificConfig, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut config = config.clone();
+    config.api_key = config.api_key.map(|key| secrets::mask_secret(&key));
+    config.serialize(serializer)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,141 +60,2000 @@ pub struct Capability {
     pub parameters: Vec<Parameter>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Parameter {
-    pub name: String,
-    pub param_type: String, // 'string' | 'number' | 'boolean' | 'array' | 'object'
-    pub required: bool,
-    pub description: Option<String>,
-    pub default_value: Option<serde_json::Value>,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Parameter {
+    pub name: String,
+    pub param_type: String, // 'string' | 'number' | 'boolean' | 'array' | 'object'
+    pub required: bool,
+    pub description: Option<String>,
+    pub default_value: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolSpecificConfig {
+    pub api_key: Option<String>,
+    pub endpoint: Option<String>,
+    pub max_tokens: Option<i32>,
+    pub temperature: Option<f32>,
+    pub model: Option<String>,
+    pub additional_config: HashMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub transport: Option<String>, // 'cli' (default) | 'http'
+    // Default for commands that don't set their own
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    // Tools tried in turn by send_ai_command_with_fallback when this one can't serve a command
+    #[serde(default)]
+    pub fallback_order: Vec<String>,
+    // Used when neither the chat session nor the agent's role sets one
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Connection {
+    pub id: String,
+    pub tool_id: String,
+    pub status: String, // 'connected' | 'disconnected' | 'error' | 'connecting'
+    pub established_at: Option<DateTime<Utc>>,
+    pub last_activity: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AICommand {
+    pub id: String,
+    pub tool_id: String,
+    pub command_type: String,
+    pub payload: serde_json::Value,
+    pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    // Higher values are dispatched first; swarm-critical calls use it to skip the line
+    #[serde(default)]
+    pub priority: i32,
+    // Where the command came from, recorded with it in ai_interactions
+    #[serde(default)]
+    pub session_id: Option<String>,
+    #[serde(default)]
+    pub swarm_id: Option<String>,
+    #[serde(default)]
+    pub task_id: Option<String>,
+    #[serde(default)]
+    pub broadcast_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AIResponse {
+    pub id: String,
+    pub command_id: String,
+    pub success: bool,
+    pub data: Option<serde_json::Value>,
+    pub error: Option<String>,
+    pub timestamp: DateTime<Utc>,
+    // Set by send_ai_command_with_fallback: the tool that answered and the ones passed over
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub served_by: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped_tools: Vec<SkippedTool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedTool {
+    pub tool_id: String,
+    pub kind: String,
+    pub reason: String,
+}
+
+/// Responses of a broadcast by tool id; they are recorded in ai_interactions under `broadcast_id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BroadcastResult {
+    pub broadcast_id: String,
+    pub responses: HashMap<String, AIResponse>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolHealth {
+    pub tool_id: String,
+    pub status: String,
+    pub healthy: bool,
+    pub reason: Option<String>,
+    pub checked_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetectedTool {
+    pub tool_id: String,
+    pub tool_type: String,
+    pub name: String,
+    pub binary: String,
+    pub path: Option<String>,
+    pub version: Option<String>,
+    // Set for tools found through a running local server
+    pub endpoint: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolProcessInfo {
+    pub tool_id: String,
+    pub tool_type: String,
+    pub pid: Option<u32>,
+    pub project_id: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub uptime_secs: u64,
+    // Resident memory; only known on Linux
+    pub memory_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionTestResult {
+    pub tool_id: String,
+    pub status: String, // 'ok' | 'invalid_key' | 'network_error' | 'proxy_error' | 'tool_not_found' | 'error'
+    pub message: Option<String>,
+    pub latency_ms: u64,
+}
+
+// A spawned tool process together with the connection handed out for it
+struct ToolProcess {
+    child: Child,
+    connection: Connection,
+    tool_type: String,
+    config: ToolSpecificConfig,
+    project_id: Option<String>,
+    // Sends lock this so request/response pairs on the pipes never interleave
+    io: Arc<Mutex<ToolIo>>,
+}
+
+// Where a tool process runs for a project: the project directory, with the project's env for the tool
+struct ProcessScope {
+    cwd: std::path::PathBuf,
+    env: HashMap<String, String>,
+}
+
+struct ToolIo {
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolQueueStatus {
+    pub tool_id: String,
+    pub queued: usize,
+    pub running: usize,
+    pub max_concurrent: usize,
+    pub min_interval_ms: u64,
+    // Remaining rate-limit pause; 0 when the queue is not cooling down
+    pub cooldown_ms: u64,
+}
+
+// A tool reached over its provider's HTTP API instead of a local process
+struct HttpTool {
+    connection: Connection,
+    tool_type: String,
+    config: ToolSpecificConfig,
+}
+
+/// The running CLI tool processes, one per tool id. Kept in `AppState`.
+#[derive(Default)]
+pub struct ProcessRegistry(Mutex<HashMap<String, ToolProcess>>);
+
+// Model list of a tool, with when it was fetched
+type CachedModels = (std::time::Instant, Vec<ModelInfo>);
+
+/// How each tool is connected, its queue and the commands in flight. Kept in `AppState`.
+#[derive(Default)]
+pub struct ToolRegistry {
+    // Commands being executed, so cancel_ai_command can abort them by id
+    in_flight: std::sync::Mutex<HashMap<String, CancellationToken>>,
+    queues: std::sync::Mutex<HashMap<String, ToolQueue>>,
+    http_tools: std::sync::Mutex<HashMap<String, HttpTool>>,
+    model_cache: std::sync::Mutex<HashMap<String, CachedModels>>,
+    // Config each live tool was connected with, so the supervisor can respawn it
+    connect_configs: std::sync::Mutex<HashMap<String, ToolSpecificConfig>>,
+    // ...and the project it was connected for, if any
+    connect_projects: std::sync::Mutex<HashMap<String, Option<String>>>,
+    reconnects: std::sync::Mutex<HashMap<String, ReconnectState>>,
+}
+
+impl ToolRegistry {
+    /// Cancels every AI command in flight, as `cancel_ai_command` would. Used on shutdown.
+    pub fn cancel_all_commands(&self) {
+        for token in self.in_flight.lock().unwrap().values() {
+            token.cancel();
+        }
+    }
+
+    // Drops what a reconnection needs, which also stops one that is running
+    fn forget_connection(&self, tool_id: &str) {
+        self.connect_configs.lock().unwrap().remove(tool_id);
+        self.connect_projects.lock().unwrap().remove(tool_id);
+        self.reconnects.lock().unwrap().remove(tool_id);
+    }
+
+    fn connected_config(&self, tool_id: &str) -> Option<ToolSpecificConfig> {
+        self.connect_configs.lock().unwrap().get(tool_id).cloned()
+    }
+}
+
+// Per-tool dispatch queue: at most `max_concurrent` commands run at once, started at least
+// `min_interval_ms` apart, and none start while a rate-limit cooldown is active
+#[derive(Default)]
+struct ToolQueue {
+    running: usize,
+    waiting: Vec<(u64, i32)>, // (ticket, priority)
+    next_ticket: u64,
+    last_start: Option<std::time::Instant>,
+    cooldown_until: Option<std::time::Instant>,
+    changed: Arc<tokio::sync::Notify>,
+}
+
+const TOOL_STATUSES: [&str; 4] = ["connected", "disconnected", "error", "connecting"];
+// (binary, tool type, display name) of the CLIs detect_ai_tools looks for
+const KNOWN_BINARIES: [(&str, &str, &str); 5] = [
+    ("claude", "claude-code", "Claude Code"),
+    ("gemini", "gemini-cli", "Gemini CLI"),
+    ("cursor", "cursor-cli", "Cursor CLI"),
+    ("codex", "codex-cli", "Codex CLI"),
+    ("ollama", "ollama", "Ollama"),
+];
+const CONNECTION_TEST_TIMEOUT: tokio::time::Duration = tokio::time::Duration::from_secs(10);
+const OLLAMA_PROBE_TIMEOUT: tokio::time::Duration = tokio::time::Duration::from_secs(2);
+const MODEL_CACHE_TTL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+// (tool type, model id, display name, context window) for CLIs that can't list their models
+const CLI_MODELS: [(&str, &str, &str, u32); 10] = [
+    ("claude-code", "sonnet", "Claude Sonnet (latest)", 200_000),
+    ("claude-code", "opus", "Claude Opus (latest)", 200_000),
+    ("claude-code", "claude-3-5-sonnet-latest", "Claude 3.5 Sonnet", 200_000),
+    ("claude-code", "claude-3-5-haiku-latest", "Claude 3.5 Haiku", 200_000),
+    ("claude-code", "claude-3-opus-latest", "Claude 3 Opus", 200_000),
+    ("gemini-cli", "gemini-1.5-pro", "Gemini 1.5 Pro", 2_097_152),
+    ("gemini-cli", "gemini-1.5-flash", "Gemini 1.5 Flash", 1_048_576),
+    ("cursor-cli", "gpt-4o", "GPT-4o", 128_000),
+    ("codex-cli", "o4-mini", "o4-mini", 200_000),
+    ("codex-cli", "gpt-4o", "GPT-4o", 128_000),
+];
+const VERSION_PROBE_TIMEOUT: tokio::time::Duration = tokio::time::Duration::from_secs(5);
+const DEFAULT_IO_LOG_TAIL: usize = 200;
+const TOOL_STATUS_EVENT: &str = "tool:status-changed";
+// How long a command waits for an in-progress reconnection before giving up
+const RECONNECT_WAIT: tokio::time::Duration = tokio::time::Duration::from_secs(30);
+const MAX_RECONNECT_BACKOFF_MS: u64 = 60_000;
+
+/// Read from `additional_config.reconnect` of the tool's config.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReconnectPolicy {
+    pub enabled: bool,
+    pub max_attempts: u32,
+    pub base_backoff_ms: u64,
+    // Uptime after which a reconnected tool gets a fresh set of attempts
+    pub stable_after_secs: u64,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_attempts: 5,
+            base_backoff_ms: 1000,
+            stable_after_secs: 300,
+        }
+    }
+}
+
+#[derive(Default)]
+struct ReconnectState {
+    attempts: u32,
+    last_success: Option<std::time::Instant>,
+    // Present while a supervisor runs; flips to true when it is done
+    done: Option<tokio::sync::watch::Receiver<bool>>,
+}
+
+// How long tool processes get to exit on their own when the app quits
+const SHUTDOWN_GRACE_PERIOD: tokio::time::Duration = tokio::time::Duration::from_secs(2);
+// How long a freshly spawned process must stay alive to count as connected
+const SPAWN_GRACE_PERIOD: tokio::time::Duration = tokio::time::Duration::from_millis(500);
+// Plain-text tools have no end marker by default; a pause this long ends their response
+const RESPONSE_QUIET_PERIOD: tokio::time::Duration = tokio::time::Duration::from_millis(1500);
+const DEFAULT_RESPONSE_TIMEOUT_SECS: u64 = 120;
+const STREAM_CHUNK_EVENT: &str = "ai:stream-chunk";
+const STREAM_END_EVENT: &str = "ai:stream-end";
+const RESPONSE_EVENT: &str = "ai:response";
+const FALLBACK_EVENT: &str = "ai:fallback";
+const DEFAULT_MAX_CONCURRENT: usize = 4;
+// Used when a 429 does not say how long to wait
+const DEFAULT_RATE_LIMIT_COOLDOWN_SECS: u64 = 30;
+
+#[tauri::command]
+#[timed]
+pub async fn initialize_ai_tool(app: AppHandle, state: State<'_, AppState>, tool: AITool) -> Result<AITool, String> {
+    log::info!("Initializing AI tool: {}", tool.name);
+    
+    let initialized_tool = register_tool(&state, tool)
+        .map_err(|e| format!("Failed to initialize tool: {}", e))?;
+    DataChanged::new(Entity::AiTool, Action::Created, &initialized_tool.id)
+        .with_data(&initialized_tool)
+        .emit(&app);
+    
+    Ok(initialized_tool)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn connect_ai_tool(app: AppHandle, state: State<'_, AppState>, tool_id: String, config: ToolSpecificConfig, project_id: Option<String>) -> Result<Connection, String> {
+    log::info!("Connecting AI tool: {} (project: {:?})", tool_id, project_id);
+    
+    let connection = connect_tool(&state, tool_id.clone(), config, project_id).await
+        .map_err(|e| {
+            set_tool_error(&state.db, &tool_id, &e.to_string());
+            emit_tool_status(&app, &tool_id, "error", Some(&e.to_string()));
+            format!("Failed to connect tool: {}", e)
+        })?;
+    set_tool_status(&state.db, &tool_id, "connected");
+    emit_tool_status(&app, &tool_id, "connected", None);
+    
+    Ok(connection)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn disconnect_ai_tool(app: AppHandle, state: State<'_, AppState>, tool_id: String) -> Result<(), String> {
+    log::info!("Disconnecting AI tool: {}", tool_id);
+    
+    // Stop the process if it exists
+    let mut processes = state.processes.0.lock().await;
+    if let Some(mut process) = processes.remove(&tool_id) {
+        let _ = process.child.kill().await;
+    }
+    state.tools.http_tools.lock().unwrap().remove(&tool_id);
+    state.tools.forget_connection(&tool_id);
+    set_tool_status(&state.db, &tool_id, "disconnected");
+    emit_tool_status(&app, &tool_id, "disconnected", None);
+    
+    Ok(())
+}
+
+#[tauri::command]
+#[timed]
+pub async fn list_tool_processes(state: State<'_, AppState>) -> Result<Vec<ToolProcessInfo>, String> {
+    log::info!("Listing AI tool processes");
+    
+    let processes = state.processes.0.lock().await;
+    let mut infos: Vec<ToolProcessInfo> = processes.iter()
+        .map(|(tool_id, process)| {
+            let pid = process.child.id();
+            let started_at = process.connection.established_at;
+            ToolProcessInfo {
+                tool_id: tool_id.clone(),
+                tool_type: process.tool_type.clone(),
+                pid,
+                project_id: process.project_id.clone(),
+                started_at,
+                uptime_secs: started_at.map_or(0, |t| (Utc::now() - t).num_seconds().max(0) as u64),
+                memory_bytes: pid.and_then(process_memory),
+            }
+        })
+        .collect();
+    infos.sort_by(|a, b| a.tool_id.cmp(&b.tool_id));
+    
+    Ok(infos)
+}
+
+/// Kills the tool's process outright, without the graceful stop used on exit, and disconnects the tool.
+#[tauri::command]
+#[timed]
+pub async fn force_kill_tool_process(app: AppHandle, state: State<'_, AppState>, tool_id: String) -> Result<(), String> {
+    log::warn!("Force killing process of AI tool: {}", tool_id);
+    
+    // Stop the supervisor first so it doesn't respawn the process
+    state.tools.forget_connection(&tool_id);
+    
+    let mut process = state.processes.0.lock().await.remove(&tool_id)
+        .ok_or_else(|| format!("AI tool {} has no running process", tool_id))?;
+    process.child.kill().await
+        .map_err(|e| format!("Failed to kill process: {}", e))?;
+    set_tool_status(&state.db, &tool_id, "disconnected");
+    emit_tool_status(&app, &tool_id, "disconnected", None);
+    
+    Ok(())
+}
+
+#[tauri::command]
+#[timed]
+pub async fn send_ai_command(app: AppHandle, tool_id: String, command: AICommand) -> Result<AIResponse, AppError> {
+    log::info!("Sending command to AI tool: {} - {}", tool_id, command.command_type);
+    
+    // Also called from chat and swarm code, which hold only the app handle
+    let state = app.state::<AppState>();
+    if let Some(policy) = command_policy(&state.db, &command)? {
+        policy.check(&tool_id)?;
+    }
+    
+    let started = std::time::Instant::now();
+    let token = CancellationToken::new();
+    state.tools.in_flight.lock().unwrap().insert(command.id.clone(), token.clone());
+    
+    let result = tokio::select! {
+        result = dispatch_command(&app, &tool_id, &command) => result,
+        _ = token.cancelled() => Err(AppError::Cancelled(format!("command {} was cancelled", command.id))),
+    };
+    state.tools.in_flight.lock().unwrap().remove(&command.id);
+    
+    if let Err(e @ (AppError::Timeout(_) | AppError::Cancelled(_))) = &result {
+        abort_command(&app, &tool_id, &command, e).await;
+    }
+    record_response(&app, &tool_id, &command, &result, started.elapsed());
+    // Swarm agents' commands are covered by the swarm's own notifications
+    if command.swarm_id.is_none() {
+        notifications::command_finished(&app, &tool_id, command.session_id.as_deref(), result.is_ok(), started.elapsed());
+    }
+    
+    result
+}
+
+/// Like send_ai_command, but when the tool is down, times out or the provider fails on its
+/// side, the command goes to the next tool in `fallback_order`: the caller's, else that of the
+/// project the command's session or swarm belongs to, else the tool's own. Errors about the
+/// request itself are returned as is.
+#[tauri::command]
+#[timed]
+pub async fn send_ai_command_with_fallback(app: AppHandle, state: State<'_, AppState>, tool_id: String, command: AICommand, fallback_order: Option<Vec<String>>) -> Result<AIResponse, AppError> {
+    log::info!("Sending command with fallback, starting at AI tool: {} - {}", tool_id, command.command_type);
+
+    // A project's own preferences come before the tool's, and its disabled tools are never tried
+    let policy = command_policy(&state.db, &command)?;
+    let fallback_order = fallback_order.unwrap_or_else(|| match &policy {
+        Some(policy) => policy.preferred_tools(),
+        None => tool_config(&state, &tool_id)
+            .map(|config| config.fallback_order)
+            .unwrap_or_default(),
+    });
+    let mut candidates = vec![tool_id.clone()];
+    for fallback in fallback_order {
+        if !candidates.contains(&fallback) && policy.as_ref().is_none_or(|policy| policy.allows(&fallback)) {
+            candidates.push(fallback);
+        }
+    }
+
+    let mut skipped: Vec<SkippedTool> = Vec::new();
+    let mut last_error = None;
+    for candidate in candidates {
+        let attempt = AICommand { tool_id: candidate.clone(), ..command.clone() };
+        match send_ai_command(app.clone(), candidate.clone(), attempt).await {
+            Ok(mut response) => {
+                if !skipped.is_empty() {
+                    log::warn!("Command {} was served by fallback tool {} instead of {}", command.id, candidate, tool_id);
+                    emit_tool_event(&app, FALLBACK_EVENT, serde_json::json!({
+                        "command_id": command.id,
+                        "requested_tool": tool_id,
+                        "served_by": candidate,
+                        "skipped_tools": skipped,
+                    }));
+                }
+                response.served_by = Some(candidate);
+                response.skipped_tools = skipped;
+                return Ok(response);
+            }
+            Err(e) if e.allows_fallback() => {
+                log::warn!("AI tool {} could not serve command {}: {}", candidate, command.id, e);
+                skipped.push(SkippedTool { tool_id: candidate, kind: e.kind().to_string(), reason: e.to_string() });
+                last_error = Some(e);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| AppError::ToolUnavailable(format!("no tool could serve command {}", command.id))))
+}
+
+/// Sends the same command to every tool at once. Each tool gets its own command id and
+/// timeout; a tool that fails shows up as an unsuccessful response instead of failing the call.
+#[tauri::command]
+#[timed]
+pub async fn broadcast_ai_command(app: AppHandle, tool_ids: Vec<String>, command: AICommand) -> Result<BroadcastResult, String> {
+    log::info!("Broadcasting command to AI tools: {:?} - {}", tool_ids, command.command_type);
+
+    if tool_ids.is_empty() {
+        return Err("No tools to broadcast to".to_string());
+    }
+
+    let broadcast_id = command.broadcast_id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
+    let mut tasks = tokio::task::JoinSet::new();
+    for tool_id in tool_ids.into_iter().collect::<std::collections::BTreeSet<_>>() {
+        let app = app.clone();
+        let attempt = AICommand {
+            id: Uuid::new_v4().to_string(),
+            tool_id: tool_id.clone(),
+            broadcast_id: Some(broadcast_id.clone()),
+            ..command.clone()
+        };
+        tasks.spawn(async move {
+            let command_id = attempt.id.clone();
+            let response = send_ai_command(app, tool_id.clone(), attempt).await
+                .unwrap_or_else(|e| command_response(&command_id, false, None, Some(e.to_string())));
+            (tool_id, response)
+        });
+    }
+
+    let mut responses = HashMap::new();
+    while let Some(joined) = tasks.join_next().await {
+        let (tool_id, response) = joined.map_err(|e| format!("Broadcast task failed: {}", e))?;
+        responses.insert(tool_id, response);
+    }
+
+    Ok(BroadcastResult { broadcast_id, responses })
+}
+
+#[tauri::command]
+#[timed]
+pub async fn cancel_ai_command(state: State<'_, AppState>, command_id: String) -> Result<(), String> {
+    log::info!("Cancelling AI command: {}", command_id);
+    
+    let token = state.tools.in_flight.lock().unwrap().get(&command_id).cloned()
+        .ok_or_else(|| format!("No AI command in flight with id {}", command_id))?;
+    token.cancel();
+    
+    Ok(())
+}
+
+#[tauri::command]
+#[timed]
+pub async fn get_tool_queue_status(state: State<'_, AppState>, tool_id: String) -> Result<ToolQueueStatus, String> {
+    log::info!("Getting queue status of AI tool: {}", tool_id);
+    
+    Ok(queue_status(&state.tools, &tool_id))
+}
+
+#[tauri::command]
+#[timed]
+pub async fn get_ai_tools(state: State<'_, AppState>) -> Result<Vec<AITool>, String> {
+    log::info!("Getting AI tools");
+    
+    let tools = list_tools(&state.db)
+        .map_err(|e| format!("Failed to get tools: {}", e))?;
+    
+    Ok(tools)
+}
+
+// What `get_ai_tools` hands out: keys are left out, only `has_api_key` tells of them
+fn list_tools(db: &Database) -> Result<Vec<AITool>> {
+    db.get_ai_tools()?.into_iter().map(tool_from_row).collect()
+}
+
+#[tauri::command]
+#[timed]
+pub async fn check_tool_health(app: AppHandle, tool_id: String) -> Result<ToolHealth, String> {
+    log::info!("Checking health of AI tool: {}", tool_id);
+    
+    let health = check_tool(&app, &tool_id).await
+        .map_err(|e| format!("Failed to check tool health: {}", e))?;
+    
+    Ok(health)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn set_health_check_interval(app: AppHandle, state: State<'_, AppState>, seconds: u64) -> Result<(), String> {
+    log::info!("Setting tool health check interval to {}s", seconds);
+    
+    let mut partial = serde_json::Map::new();
+    partial.insert("tool_health_interval_secs".to_string(), seconds.into());
+    settings::update(&app, &state, partial)
+        .map_err(|e| format!("Failed to save health check interval: {}", e))?;
+    
+    Ok(())
+}
+
+#[tauri::command]
+#[timed]
+pub async fn detect_ai_tools(app: AppHandle, state: State<'_, AppState>) -> Result<Vec<DetectedTool>, String> {
+    log::info!("Detecting installed AI tools");
+    
+    let detected = detect_and_register_tools(&state.db).await
+        .map_err(|e| format!("Failed to detect tools: {}", e))?;
+    for tool in &detected {
+        DataChanged::new(Entity::AiTool, Action::Updated, &tool.tool_id)
+            .with_data(tool)
+            .emit(&app);
+    }
+    
+    Ok(detected)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn test_ai_tool_connection(state: State<'_, AppState>, tool_id: String, config: ToolSpecificConfig) -> Result<ConnectionTestResult, String> {
+    log::info!("Testing connection of AI tool: {}", tool_id);
+    
+    let result = test_connection(&state.db, &tool_id, &config).await
+        .map_err(|e| format!("Failed to test connection: {}", e))?;
+    
+    Ok(result)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn set_tool_api_key(app: AppHandle, state: State<'_, AppState>, tool_name: String, key: String) -> Result<(), String> {
+    log::info!("Setting API key of AI tool: {}", tool_name);
+    
+    let key = key.trim();
+    if key.is_empty() {
+        return Err("API key cannot be empty".to_string());
+    }
+    
+    secrets::store_api_key(&tool_name, key)
+        .and_then(|_| secrets::mark_stored_configs(&state.db, &tool_name, true))
+        .map_err(|e| format!("Failed to set API key: {}", e))?;
+    DataChanged::new(Entity::AiTool, Action::Updated, &tool_name)
+        .with_data(&serde_json::json!({ "tool_name": tool_name, "has_api_key": true }))
+        .emit(&app);
+    
+    Ok(())
+}
+
+#[tauri::command]
+#[timed]
+pub async fn clear_tool_api_key(app: AppHandle, state: State<'_, AppState>, tool_name: String) -> Result<(), String> {
+    log::info!("Clearing API key of AI tool: {}", tool_name);
+    
+    secrets::delete_api_key(&tool_name)
+        .and_then(|_| secrets::mark_stored_configs(&state.db, &tool_name, false))
+        .map_err(|e| format!("Failed to clear API key: {}", e))?;
+    DataChanged::new(Entity::AiTool, Action::Updated, &tool_name)
+        .with_data(&serde_json::json!({ "tool_name": tool_name, "has_api_key": false }))
+        .emit(&app);
+    
+    Ok(())
+}
+
+#[tauri::command]
+#[timed]
+pub async fn get_available_models(state: State<'_, AppState>, tool_id: String, refresh: Option<bool>) -> Result<Vec<ModelInfo>, String> {
+    log::info!("Getting available models of AI tool: {}", tool_id);
+    
+    let models = available_models(&state, &tool_id, refresh.unwrap_or(false)).await
+        .map_err(|e| format!("Failed to get available models: {}", e))?;
+    
+    Ok(models)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn get_tool_io_logging(state: State<'_, AppState>) -> Result<IoLoggingSettings, String> {
+    Ok(tool_io::settings(&state.db))
+}
+
+#[tauri::command]
+#[timed]
+pub async fn set_tool_io_logging(state: State<'_, AppState>, settings: IoLoggingSettings) -> Result<(), String> {
+    log::info!("Setting tool I/O logging: {:?}", settings);
+    
+    tool_io::save_settings(&state.db, &settings)
+        .map_err(|e| format!("Failed to save tool I/O logging settings: {}", e))?;
+    
+    Ok(())
+}
+
+/// The proxy tools use unless their config sets one under `additional_config.proxy`.
+#[tauri::command]
+#[timed]
+pub async fn get_proxy_settings(state: State<'_, AppState>) -> Result<Option<ProxySettings>, AppError> {
+    Ok(providers::proxy::global_settings())
+}
+
+/// `None` turns the global proxy off. A password is stored in the keychain for the username.
+#[tauri::command]
+#[timed]
+pub async fn set_proxy_settings(state: State<'_, AppState>, settings: Option<ProxySettings>, password: Option<String>) -> Result<(), AppError> {
+    log::info!("Setting proxy: {:?}", settings);
+    
+    providers::proxy::save_global_settings(&state.db, settings.as_ref(), password.as_deref())
+}
+
+/// Tail of the tool I/O log of a chat session or swarm (or of a tool, for commands sent outside both).
+#[tauri::command]
+#[timed]
+pub async fn get_tool_io_log(state: State<'_, AppState>, id: String, tail_lines: Option<usize>) -> Result<Vec<String>, String> {
+    log::info!("Getting tool I/O log: {}", id);
+    
+    tool_io::tail(&id, tail_lines.unwrap_or(DEFAULT_IO_LOG_TAIL))
+        .map_err(|e| format!("Failed to read tool I/O log: {}", e))
+}
+
+#[tauri::command]
+#[timed]
+pub async fn clear_tool_io_logs(state: State<'_, AppState>, older_than_days: i64) -> Result<usize, String> {
+    log::info!("Clearing tool I/O logs older than {} days", older_than_days);
+    
+    if older_than_days < 0 {
+        return Err("older_than_days cannot be negative".to_string());
+    }
+    tool_io::clear_older_than(Utc::now() - chrono::Duration::days(older_than_days))
+        .map_err(|e| format!("Failed to clear tool I/O logs: {}", e))
+}
+
+#[tauri::command]
+#[timed]
+pub async fn update_ai_tool_status(app: AppHandle, state: State<'_, AppState>, tool_id: String, status: String) -> Result<(), String> {
+    log::info!("Updating AI tool status: {} -> {}", tool_id, status);
+    
+    if !TOOL_STATUSES.contains(&status.as_str()) {
+        return Err(format!("Invalid tool status: {}", status));
+    }
+    
+    state.db.update_ai_tool_status(&tool_id, &status, None)
+        .map_err(|e| format!("Failed to update tool status: {}", e))?;
+    emit_tool_status(&app, &tool_id, &status, None);
+    
+    Ok(())
+}
+
+/// With a project, a CLI tool runs in the project's directory with its env; a tool already
+/// running for another project is restarted, since there is one process per tool.
+async fn connect_tool(state: &AppState, tool_id: String, config: ToolSpecificConfig, project_id: Option<String>) -> Result<Connection> {
+    let db = &state.db;
+    let tool_type = resolve_tool_type(db, &tool_id)?;
+    let config = with_stored_api_key(&tool_id, &tool_type, config);
+    let policy = project_id.as_deref().map(|id| ToolPolicy::load(db, id)).transpose()?.flatten();
+    if let Some(policy) = &policy {
+        policy.check(&tool_id)?;
+    }
+    if uses_http(&tool_type, &config) {
+        return connect_http_tool(state, tool_id, tool_type, config).await;
+    }
+    let config = match &policy {
+        Some(policy) => policy.apply(&tool_id, config),
+        None => config,
+    };
+    
+    // Held across the spawn so two concurrent connects can't both start a process
+    let mut processes = state.processes.0.lock().await;
+    
+    if let Some(process) = processes.get_mut(&tool_id) {
+        let running = process.child.try_wait()?.is_none();
+        if running && process.project_id == project_id {
+            log::info!("AI tool {} is already connected", tool_id);
+            return Ok(process.connection.clone());
+        }
+        if running {
+            log::info!("Restarting AI tool {} for project {:?}", tool_id, project_id);
+            let _ = process.child.kill().await;
+        }
+        processes.remove(&tool_id);
+    }
+    let scope = match &project_id {
+        Some(project_id) => Some(process_scope(db, project_id, &tool_id)?),
+        None => None,
+    };
+    
+    let mut config = config;
+    if !config.additional_config.contains_key("binary_path") {
+        // Fall back to the path found by detect_ai_tools
+        let stored = stored_config(db, &tool_id)?
+            .and_then(|c| c.additional_config.get("binary_path").cloned());
+        if let Some(path) = stored {
+            config.additional_config.insert("binary_path".to_string(), path);
+        }
+    }
+    
+    let mut child = spawn_ai_tool_process(state, &tool_type, &config, scope.as_ref()).await?;
+    
+    tokio::time::sleep(SPAWN_GRACE_PERIOD).await;
+    if let Some(status) = child.try_wait()? {
+        let stderr = read_stderr(&mut child).await;
+        return Err(anyhow!("{} exited right after starting ({}): {}", tool_type, status, stderr.trim()));
+    }
+    
+    let stdin = child.stdin.take().ok_or_else(|| anyhow!("{} has no stdin", tool_type))?;
+    let stdout = child.stdout.take().ok_or_else(|| anyhow!("{} has no stdout", tool_type))?;
+    
+    let now = Utc::now();
+    let connection = Connection {
+        id: Uuid::new_v4().to_string(),
+        tool_id: tool_id.clone(),
+        status: "connected".to_string(),
+        established_at: Some(now),
+        last_activity: Some(now),
+        error: None,
+    };
+    state.tools.connect_configs.lock().unwrap().insert(tool_id.clone(), config.clone());
+    state.tools.connect_projects.lock().unwrap().insert(tool_id.clone(), project_id.clone());
+    processes.insert(tool_id, ToolProcess {
+        child,
+        connection: connection.clone(),
+        tool_type,
+        config,
+        project_id,
+        io: Arc::new(Mutex::new(ToolIo { stdin, stdout: BufReader::new(stdout) })),
+    });
+    
+    Ok(connection)
+}
+
+/// No process is started for an HTTP tool; connecting checks the API key with one cheap request.
+async fn connect_http_tool(state: &AppState, tool_id: String, tool_type: String, config: ToolSpecificConfig) -> Result<Connection> {
+    let db = &state.db;
+    if let Some(tool) = state.tools.http_tools.lock().unwrap().get(&tool_id) {
+        log::info!("AI tool {} is already connected", tool_id);
+        return Ok(tool.connection.clone());
+    }
+    
+    let mut config = config;
+    if config.endpoint.is_none() {
+        config.endpoint = stored_config(db, &tool_id)?.and_then(|c| c.endpoint);
+    }
+    if tool_type == "ollama" {
+        // Listing the models both verifies the server and refreshes the capabilities
+        let models = providers::ollama::list_models(&config).await?;
+        remember_models(db, &tool_id, &models);
+    } else {
+        http_ping(&tool_type, &config).await?;
+    }
+    
+    // A CLI process left over from an earlier connection is no longer used
+    if let Some(mut process) = state.processes.0.lock().await.remove(&tool_id) {
+        let _ = process.child.kill().await;
+    }
+    
+    let now = Utc::now();
+    let connection = Connection {
+        id: Uuid::new_v4().to_string(),
+        tool_id: tool_id.clone(),
+        status: "connected".to_string(),
+        established_at: Some(now),
+        last_activity: Some(now),
+        error: None,
+    };
+    state.tools.connect_configs.lock().unwrap().insert(tool_id.clone(), config.clone());
+    state.tools.http_tools.lock().unwrap().insert(tool_id, HttpTool {
+        connection: connection.clone(),
+        tool_type,
+        config,
+    });
+    
+    Ok(connection)
+}
+
+async fn send_command(app: &AppHandle, tool_id: &str, command: AICommand) -> Result<AIResponse, AppError> {
+    let state = app.state::<AppState>();
+    let db = &state.db;
+    wait_for_reconnect(&state.tools, tool_id).await;
+    
+    let http_tool = state.tools.http_tools.lock().unwrap().get_mut(tool_id).map(|tool| {
+        tool.connection.last_activity = Some(Utc::now());
+        (tool.tool_type.clone(), tool.config.clone())
+    });
+    if let Some((tool_type, config)) = http_tool {
+        // Processes get the project's settings when they start; requests over HTTP get them here
+        let config = match command_policy(db, &command)? {
+            Some(policy) => policy.apply(tool_id, config),
+            None => config,
+        };
+        return send_http_command(app, tool_id, &tool_type, &config, command).await;
+    }
+    
+    let (io, tool_type, config) = {
+        let mut processes = state.processes.0.lock().await;
+        let process = processes.get_mut(tool_id)
+            .ok_or_else(|| AppError::ToolUnavailable(format!("{} is not connected", tool_id)))?;
+        
+        let exited = process.child.try_wait()
+            .map_err(|e| AppError::Internal(format!("Failed to check {} process: {}", tool_id, e)))?;
+        if let Some(status) = exited {
+            let mut process = processes.remove(tool_id).expect("process present");
+            drop(processes);
+            let stderr = read_stderr(&mut process.child).await;
+            return Err(tool_unavailable(&state, tool_id, format!("process exited ({}): {}", status, stderr.trim())).await);
+        }
+        
+        process.connection.last_activity = Some(Utc::now());
+        (process.io.clone(), process.tool_type.clone(), process.config.clone())
+    };
+    
+    // Liveness was checked above; a ping needs no round trip
+    if command.command_type == "ping" {
+        return Ok(command_response(&command.id, true, Some(serde_json::json!({ "status": "alive" })), None));
+    }
+    
+    let mut io = io.lock().await;
+    let request = encode_request(&tool_type, &command.payload);
+    log::debug!("AI tool {} request: {}", tool_id, redact_secrets(request.trim_end(), &config));
+    
+    let written = async {
+        io.stdin.write_all(request.as_bytes()).await?;
+        io.stdin.flush().await
+    }.await;
+    if let Err(e) = written {
+        return Err(tool_unavailable(&state, tool_id, format!("failed to write to process: {}", e)).await);
+    }
+    
+    let (success, data) = match read_response(&tool_type, &config, &mut io.stdout).await {
+        Ok(Some(reply)) => reply,
+        Ok(None) => return Err(tool_unavailable(&state, tool_id, "process closed its output".to_string()).await),
+        Err(e) => return Err(tool_unavailable(&state, tool_id, format!("failed to read from process: {}", e)).await),
+    };
+    log::debug!("AI tool {} response: {}", tool_id, redact_secrets(&data.to_string(), &config));
+    
+    if success {
+        if let Err(e) = db.touch_ai_tool(tool_id) {
+            log::warn!("Failed to record use of AI tool {}: {}", tool_id, e);
+        }
+    }
+    
+    let error = if success { None } else { Some(format!("{} reported an error", tool_id)) };
+    Ok(command_response(&command.id, success, Some(data), error))
+}
+
+/// `stream` commands are sent as streaming requests whose text arrives as `ai:stream-chunk`
+/// events, followed by one `ai:stream-end`; the response still carries the whole reply.
+async fn send_http_command(app: &AppHandle, tool_id: &str, tool_type: &str, config: &ToolSpecificConfig, command: AICommand) -> Result<AIResponse, AppError> {
+    let db = app.state::<AppState>().db.clone();
+    if command.command_type == "ping" {
+        http_ping(tool_type, config).await?;
+        return Ok(command_response(&command.id, true, Some(serde_json::json!({ "status": "alive" })), None));
+    }
+    
+    let streaming = command.command_type == "stream";
+    let mut emit_delta = |delta: &str| emit_tool_event(app, STREAM_CHUNK_EVENT, serde_json::json!({
+        "tool_id": tool_id,
+        "command_id": command.id,
+        "delta": delta,
+    }));
+    let on_delta: Option<DeltaSink> = if streaming { Some(&mut emit_delta) } else { None };
+    
+    let result = http_send(tool_type, config, &command.payload, on_delta).await;
+    if streaming {
+        emit_tool_event(app, STREAM_END_EVENT, serde_json::json!({
+            "tool_id": tool_id,
+            "command_id": command.id,
+            "success": result.is_ok(),
+            "error": result.as_ref().err().map(|e| e.to_string()),
+        }));
+    }
+    
+    let data = match result {
+        Ok(data) => data,
+        Err(e) => {
+            if matches!(e, AppError::InvalidApiKey(_)) {
+                set_tool_error(&db, tool_id, &e.to_string());
+            }
+            return Err(e);
+        }
+    };
+    log::debug!("AI tool {} response: {}", tool_id, redact_secrets(&data.to_string(), config));
+    
+    if let Err(e) = db.touch_ai_tool(tool_id) {
+        log::warn!("Failed to record use of AI tool {}: {}", tool_id, e);
+    }
+    Ok(command_response(&command.id, true, Some(data), None))
+}
+
+async fn http_send(tool_type: &str, config: &ToolSpecificConfig, payload: &serde_json::Value, on_delta: Option<DeltaSink<'_>>) -> Result<serde_json::Value, AppError> {
+    match tool_type {
+        "claude-code" => providers::anthropic::send_message(config, payload, on_delta).await,
+        "gemini-cli" => providers::gemini::generate_content(config, payload, on_delta).await,
+        "ollama" => providers::ollama::generate(config, payload, on_delta).await,
+        _ => Err(AppError::ToolUnavailable(format!("{} has no HTTP transport", tool_type))),
+    }
+}
+
+async fn http_ping(tool_type: &str, config: &ToolSpecificConfig) -> Result<(), AppError> {
+    match tool_type {
+        "claude-code" => providers::anthropic::ping(config).await,
+        "gemini-cli" => providers::gemini::ping(config).await,
+        "ollama" => providers::ollama::list_models(config).await.map(|_| ()),
+        _ => Err(AppError::ToolUnavailable(format!("{} has no HTTP transport", tool_type))),
+    }
+}
+
+/// Dry run for the settings dialog: makes the cheapest call the backend allows with the given
+/// config and reports how it went. Nothing is stored and no connection is kept.
+async fn test_connection(db: &Database, tool_id: &str, config: &ToolSpecificConfig) -> Result<ConnectionTestResult> {
+    // A tool being set up may not be saved yet; built-in ids are their type
+    let tool_type = resolve_tool_type(db, tool_id).or_else(|e| {
+        KNOWN_BINARIES.iter()
+            .find(|(_, tool_type, _)| *tool_type == tool_id)
+            .map(|(_, tool_type, _)| tool_type.to_string())
+            .ok_or(e)
+    })?;
+    
+    let config = &with_stored_api_key(tool_id, &tool_type, config.clone());
+    
+    let started = std::time::Instant::now();
+    let outcome = tokio::time::timeout(CONNECTION_TEST_TIMEOUT, async {
+        if uses_http(&tool_type, config) {
+            return match http_ping(&tool_type, config).await {
+                Ok(()) => ("ok", None),
+                Err(e) => {
+                    let status = match e {
+                        AppError::InvalidApiKey(_) => "invalid_key",
+                        AppError::Network(_) | AppError::Timeout(_) => "network_error",
+                        AppError::Proxy(_) => "proxy_error",
+                        _ => "error",
+                    };
+                    (status, Some(e.to_string()))
+                }
+            };
+        }
+        
+        // CLIs can't check a key without spending a prompt; confirm the binary runs instead
+        let binary = config.additional_config.get("binary_path")
+            .and_then(|p| p.as_str())
+            .map(std::path::PathBuf::from)
+            .filter(|p| p.exists())
+            .or_else(|| KNOWN_BINARIES.iter()
+                .find(|(_, known_type, _)| *known_type == tool_type)
+                .and_then(|(binary, _, _)| crate::commands::system::resolve_binary(binary)));
+        let Some(binary) = binary else {
+            return ("tool_not_found", Some(format!("No {} executable found", tool_type)));
+        };
+        match probe_version(&binary).await {
+            Some(version) => ("ok", Some(version)),
+            None => ("error", Some(format!("{:?} --version failed", binary))),
+        }
+    }).await;
+    
+    let (status, message) = outcome.unwrap_or_else(|_| {
+        ("network_error", Some(format!("No answer within {}s", CONNECTION_TEST_TIMEOUT.as_secs())))
+    });
+    Ok(ConnectionTestResult {
+        tool_id: tool_id.to_string(),
+        status: status.to_string(),
+        message,
+        latency_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+/// Ollama is only reachable over its local API; other tools opt in with `transport: "http"`.
+fn uses_http(tool_type: &str, config: &ToolSpecificConfig) -> bool {
+    tool_type == "ollama" || config.transport.as_deref() == Some("http")
+}
+
+pub(crate) fn is_http_tool(tools: &ToolRegistry, tool_id: &str) -> bool {
+    tools.http_tools.lock().unwrap().contains_key(tool_id)
+}
+
+/// The config the tool is connected with, or the stored one while it isn't connected.
+/// The policy of the project the command's session or swarm belongs to.
+fn command_policy(db: &Database, command: &AICommand) -> Result<Option<ToolPolicy>, AppError> {
+    let project_id = match (&command.session_id, &command.swarm_id) {
+        (Some(session_id), _) => db.get_chat_session(session_id)?.and_then(|session| session.project_id),
+        (None, Some(swarm_id)) => db.get_swarm_by_id(swarm_id)?.map(|swarm| swarm.project_id),
+        (None, None) => None,
+    };
+    match project_id {
+        Some(project_id) => Ok(ToolPolicy::load(db, &project_id)?),
+        None => Ok(None),
+    }
+}
+
+pub(crate) fn tool_config(state: &AppState, tool_id: &str) -> Option<ToolSpecificConfig> {
+    state.tools.connected_config(tool_id).or_else(|| stored_config(&state.db, tool_id).ok().flatten())
+}
+
+/// Fills in the key kept in the keychain unless the caller passed a new one. Tools are looked
+/// up by id first, then by type, which is the name stored configs use.
+fn with_stored_api_key(tool_id: &str, tool_type: &str, config: ToolSpecificConfig) -> ToolSpecificConfig {
+    let mut config = config;
+    if secrets::is_placeholder(config.api_key.as_deref()) {
+        config.api_key = [tool_id, tool_type].iter().find_map(|name| {
+            secrets::load_api_key(name).unwrap_or_else(|e| {
+                log::warn!("{}", e);
+                None
+            })
+        });
+    }
+    config
+}
+
+fn stored_config(db: &Database, tool_id: &str) -> Result<Option<ToolSpecificConfig>> {
+    Ok(db.get_ai_tool(tool_id)?
+        .and_then(|t| serde_json::from_str::<ToolSpecificConfig>(&t.config).ok()))
+}
+
+/// Asks the provider when the tool talks to one directly (Ollama always does); CLI tools get
+/// a built-in list. Results are cached per tool for an hour unless `refresh` is set.
+async fn available_models(state: &AppState, tool_id: &str, refresh: bool) -> Result<Vec<ModelInfo>> {
+    let db = &state.db;
+    if !refresh {
+        if let Some(models) = cached_models(&state.tools, tool_id) {
+            return Ok(models);
+        }
+    }
+    
+    let tool_type = resolve_tool_type(db, tool_id)?;
+    let config = match state.tools.connected_config(tool_id) {
+        Some(config) => config,
+        None => with_stored_api_key(tool_id, &tool_type, stored_config(db, tool_id)?.unwrap_or_default()),
+    };
+    let models = match tool_type.as_str() {
+        "ollama" => {
+            let models = providers::ollama::list_models(&config).await?;
+            remember_models(db, tool_id, &models);
+            models.iter().map(ModelInfo::from).collect()
+        }
+        "claude-code" if uses_http(&tool_type, &config) => providers::anthropic::list_models(&config).await?,
+        "gemini-cli" if uses_http(&tool_type, &config) => providers::gemini::list_models(&config).await?,
+        _ => {
+            let models = builtin_models(&tool_type);
+            if models.is_empty() {
+                return Err(anyhow!("{} cannot list its models", tool_type));
+            }
+            models
+        }
+    };
+    
+    state.tools.model_cache.lock().unwrap().insert(tool_id.to_string(), (std::time::Instant::now(), models.clone()));
+    Ok(models)
+}
+
+fn cached_models(tools: &ToolRegistry, tool_id: &str) -> Option<Vec<ModelInfo>> {
+    tools.model_cache.lock().unwrap().get(tool_id)
+        .filter(|(fetched_at, _)| fetched_at.elapsed() < MODEL_CACHE_TTL)
+        .map(|(_, models)| models.clone())
+}
+
+fn builtin_models(tool_type: &str) -> Vec<ModelInfo> {
+    CLI_MODELS.iter()
+        .filter(|(tool, ..)| *tool == tool_type)
+        .map(|(_, id, display_name, context_window)| ModelInfo {
+            id: id.to_string(),
+            display_name: display_name.to_string(),
+            context_window: Some(*context_window),
+            supports_streaming: true,
+        })
+        .collect()
+}
+
+/// Model lists lag behind releases, so an unknown model is only a warning. Just cached and
+/// built-in lists are checked; saving a tool never waits on the network.
+fn unknown_model_warning(tools: &ToolRegistry, tool_id: &str, tool_type: &str, model: Option<&str>) -> Option<String> {
+    let model = model.filter(|m| !m.is_empty())?;
+    let known = cached_models(tools, tool_id)
+        .or_else(|| Some(builtin_models(tool_type)))
+        .filter(|models| !models.is_empty())?;
+    if known.iter().any(|m| m.id == model) {
+        return None;
+    }
+    Some(format!("Model '{}' is not among the known models of {}; check the name for typos", model, tool_type))
+}
+
+/// Keeps the model names in the stored config, where `tool_capabilities` reads them.
+fn remember_models(db: &Database, tool_id: &str, models: &[OllamaModel]) {
+    if let Err(e) = store_models(db, tool_id, models) {
+        log::warn!("Failed to store models of AI tool {}: {}", tool_id, e);
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ToolSpecificConfig {
-    pub api_key: Option<String>,
-    pub endpoint: Option<String>,
-    pub max_tokens: Option<i32>,
-    pub temperature: Option<f32>,
-    pub model: Option<String>,
-    pub additional_config: HashMap<String, serde_json::Value>,
+fn store_models(db: &Database, tool_id: &str, models: &[OllamaModel]) -> Result<()> {
+    let Some(mut tool) = db.get_ai_tool(tool_id)? else {
+        return Ok(());
+    };
+    let mut config: ToolSpecificConfig = serde_json::from_str(&tool.config).unwrap_or_default();
+    let names: Vec<&str> = models.iter().map(|m| m.name.as_str()).collect();
+    config.additional_config.insert("models".to_string(), serde_json::json!(names));
+    tool.config = serde_json::to_string(&config)?;
+    tool.updated_at = Utc::now();
+    db.save_ai_tool(&tool)
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Connection {
-    pub id: String,
-    pub tool_id: String,
-    pub status: String, // 'connected' | 'disconnected' | 'error' | 'connecting'
-    pub established_at: Option<DateTime<Utc>>,
-    pub last_activity: Option<DateTime<Utc>>,
-    pub error: Option<String>,
+/// Waits for a queue slot, then runs the command. The timeout starts once the command is
+/// dispatched, so time spent waiting on a rate limit doesn't count against it.
+async fn dispatch_command(app: &AppHandle, tool_id: &str, command: &AICommand) -> Result<AIResponse, AppError> {
+    let tools = &app.state::<AppState>().inner().tools;
+    let _slot = acquire_slot(tools, tool_id, command.priority).await;
+    
+    let timeout = command_timeout(tools, tool_id, command);
+    let result = tokio::time::timeout(timeout, send_command(app, tool_id, command.clone())).await
+        .unwrap_or_else(|_| Err(AppError::Timeout(format!("{} did not answer within {}ms", tool_id, timeout.as_millis()))));
+    
+    if let Err(AppError::RateLimited { retry_after_secs, .. }) = &result {
+        let cooldown = retry_after_secs.unwrap_or(DEFAULT_RATE_LIMIT_COOLDOWN_SECS);
+        log::warn!("AI tool {} is rate limited; pausing its queue for {}s", tool_id, cooldown);
+        start_cooldown(tools, tool_id, std::time::Duration::from_secs(cooldown));
+        emit_tool_event(app, "tool:rate-limited", serde_json::json!({ "tool_id": tool_id, "cooldown_ms": cooldown * 1000 }));
+    }
+    result
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AICommand {
-    pub id: String,
-    pub tool_id: String,
-    pub command_type: String,
-    pub payload: serde_json::Value,
-    pub timestamp: DateTime<Utc>,
+/// `max_concurrent` and `min_interval_ms` come from the tool's `additional_config`.
+fn queue_limits(tools: &ToolRegistry, tool_id: &str) -> (usize, std::time::Duration) {
+    let config = tools.connected_config(tool_id).unwrap_or_default();
+    let setting = |key: &str| config.additional_config.get(key).and_then(|v| v.as_u64());
+    (
+        setting("max_concurrent").map_or(DEFAULT_MAX_CONCURRENT, |n| n.max(1) as usize),
+        std::time::Duration::from_millis(setting("min_interval_ms").unwrap_or(0)),
+    )
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AIResponse {
-    pub id: String,
-    pub command_id: String,
-    pub success: bool,
-    pub data: Option<serde_json::Value>,
-    pub error: Option<String>,
-    pub timestamp: DateTime<Utc>,
+// Held while a command runs; frees its slot when dropped
+struct QueueSlot<'a> {
+    tools: &'a ToolRegistry,
+    tool_id: String,
 }
 
-// Global state for managing AI tool processes
-type ProcessMap = Arc<Mutex<HashMap<String, Child>>>;
-static PROCESSES: once_cell::sync::Lazy<ProcessMap> = once_cell::sync::Lazy::new(|| {
-    Arc::new(Mutex::new(HashMap::new()))
-});
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        if let Some(queue) = self.tools.queues.lock().unwrap().get_mut(&self.tool_id) {
+            queue.running -= 1;
+            queue.changed.notify_waiters();
+        }
+    }
+}
 
-#[tauri::command]
-pub async fn initialize_ai_tool(tool: AITool) -> Result<AITool, String> {
-    log::info!("Initializing AI tool: {}", tool.name);
+// A place in line; leaves the line if the caller gives up (timeout, cancel) before its turn
+struct QueueTicket<'a> {
+    tools: &'a ToolRegistry,
+    tool_id: String,
+    ticket: u64,
+    admitted: bool,
+}
+
+impl Drop for QueueTicket<'_> {
+    fn drop(&mut self) {
+        if self.admitted {
+            return;
+        }
+        if let Some(queue) = self.tools.queues.lock().unwrap().get_mut(&self.tool_id) {
+            queue.waiting.retain(|(ticket, _)| *ticket != self.ticket);
+            queue.changed.notify_waiters();
+        }
+    }
+}
+
+/// FIFO within a priority, higher priorities first.
+async fn acquire_slot<'a>(tools: &'a ToolRegistry, tool_id: &str, priority: i32) -> QueueSlot<'a> {
+    let (max_concurrent, min_interval) = queue_limits(tools, tool_id);
+    let (mut ticket, changed) = {
+        let mut queues = tools.queues.lock().unwrap();
+        let queue = queues.entry(tool_id.to_string()).or_default();
+        let ticket = queue.next_ticket;
+        queue.next_ticket += 1;
+        queue.waiting.push((ticket, priority));
+        (QueueTicket { tools, tool_id: tool_id.to_string(), ticket, admitted: false }, queue.changed.clone())
+    };
     
-    // TODO: Replace with actual tool initialization
-    let initialized_tool = mock_initialize_tool(tool).await
-        .map_err(|e| format!("Failed to initialize tool: {}", e))?;
+    loop {
+        // Registered before looking at the queue so a release in between isn't missed
+        let notified = changed.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+        
+        let wait = {
+            let mut queues = tools.queues.lock().unwrap();
+            let queue = queues.get_mut(tool_id).expect("queue present");
+            let now = std::time::Instant::now();
+            let head = queue.waiting.iter()
+                .max_by_key(|(ticket, priority)| (*priority, std::cmp::Reverse(*ticket)))
+                .map(|(ticket, _)| *ticket);
+            let ready_at = [queue.cooldown_until, queue.last_start.map(|at| at + min_interval)]
+                .into_iter()
+                .flatten()
+                .max()
+                .filter(|at| *at > now);
+            
+            if head == Some(ticket.ticket) && queue.running < max_concurrent && ready_at.is_none() {
+                queue.waiting.retain(|(t, _)| *t != ticket.ticket);
+                queue.running += 1;
+                queue.last_start = Some(now);
+                ticket.admitted = true;
+                // The next in line may be able to start too
+                queue.changed.notify_waiters();
+                return QueueSlot { tools, tool_id: tool_id.to_string() };
+            }
+            ready_at.map(|at| at - now)
+        };
+        
+        match wait {
+            Some(delay) => {
+                tokio::select! {
+                    _ = &mut notified => {}
+                    _ = tokio::time::sleep(delay) => {}
+                }
+            }
+            None => notified.await,
+        }
+    }
+}
+
+fn start_cooldown(tools: &ToolRegistry, tool_id: &str, duration: std::time::Duration) {
+    let mut queues = tools.queues.lock().unwrap();
+    let queue = queues.entry(tool_id.to_string()).or_default();
+    let until = std::time::Instant::now() + duration;
+    queue.cooldown_until = Some(queue.cooldown_until.map_or(until, |current| current.max(until)));
+    queue.changed.notify_waiters();
+}
+
+fn queue_status(tools: &ToolRegistry, tool_id: &str) -> ToolQueueStatus {
+    let (max_concurrent, min_interval) = queue_limits(tools, tool_id);
+    let queues = tools.queues.lock().unwrap();
+    let queue = queues.get(tool_id);
+    let cooldown = queue.and_then(|q| q.cooldown_until)
+        .map(|until| until.saturating_duration_since(std::time::Instant::now()))
+        .unwrap_or_default();
     
-    Ok(initialized_tool)
+    ToolQueueStatus {
+        tool_id: tool_id.to_string(),
+        queued: queue.map_or(0, |q| q.waiting.len()),
+        running: queue.map_or(0, |q| q.running),
+        max_concurrent,
+        min_interval_ms: min_interval.as_millis() as u64,
+        cooldown_ms: cooldown.as_millis() as u64,
+    }
 }
 
-#[tauri::command]
-pub async fn connect_ai_tool(tool_id: String, config: ToolSpecificConfig) -> Result<Connection, String> {
-    log::info!("Connecting AI tool: {}", tool_id);
+/// The command's own `timeout_ms`, else the tool's, else the older `response_timeout_secs` setting.
+fn command_timeout(tools: &ToolRegistry, tool_id: &str, command: &AICommand) -> tokio::time::Duration {
+    let config = tools.connected_config(tool_id).unwrap_or_default();
+    command.timeout_ms
+        .or(config.timeout_ms)
+        .map(tokio::time::Duration::from_millis)
+        .unwrap_or_else(|| tokio::time::Duration::from_secs(
+            config.additional_config.get("response_timeout_secs")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(DEFAULT_RESPONSE_TIMEOUT_SECS),
+        ))
+}
+
+/// Cleans up after a command that was cut off. HTTP requests end when their future is dropped,
+/// but a CLI may still be writing the abandoned answer, so its process is replaced.
+async fn abort_command(app: &AppHandle, tool_id: &str, command: &AICommand, error: &AppError) {
+    log::warn!("AI command {} on {} aborted: {}", command.id, tool_id, error);
     
-    // TODO: Replace with actual connection logic
-    let connection = mock_connect_tool(tool_id, config).await
-        .map_err(|e| format!("Failed to connect tool: {}", e))?;
+    if command.command_type == "stream" {
+        emit_tool_event(app, STREAM_END_EVENT, serde_json::json!({
+            "tool_id": tool_id,
+            "command_id": command.id,
+            "success": false,
+            "error": error.to_string(),
+        }));
+    }
     
-    Ok(connection)
+    let removed = app.state::<AppState>().processes.0.lock().await.remove(tool_id);
+    if let Some(mut process) = removed {
+        let _ = process.child.kill().await;
+        start_reconnect(app, tool_id);
+    }
 }
 
-#[tauri::command]
-pub async fn disconnect_ai_tool(tool_id: String) -> Result<(), String> {
-    log::info!("Disconnecting AI tool: {}", tool_id);
+/// Every command ends with a response, failed ones included, so the history has no gaps.
+fn record_response(app: &AppHandle, tool_id: &str, command: &AICommand, result: &Result<AIResponse, AppError>, latency: std::time::Duration) {
+    let state = app.state::<AppState>();
+    let db = state.db.clone();
+    let response = match result {
+        Ok(response) => response.clone(),
+        Err(e) => command_response(&command.id, false, None, Some(e.to_string())),
+    };
     
-    // Stop the process if it exists
-    let mut processes = PROCESSES.lock().await;
-    if let Some(mut process) = processes.remove(&tool_id) {
-        let _ = process.kill();
+    let config = state.tools.connected_config(tool_id).unwrap_or_default();
+    let reported = response.data.as_ref().and_then(pricing::usage_from_response);
+    // Tools that report nothing are counted from the text so usage totals still add up
+    let usage = reported.or_else(|| {
+        let data = response.data.as_ref().filter(|_| response.success)?;
+        Some(pricing::estimate_usage(&command.payload, data))
+    });
+    let interaction = DbAIInteraction {
+        id: Uuid::new_v4().to_string(),
+        tool_id: tool_id.to_string(),
+        command_id: command.id.clone(),
+        command_type: command.command_type.clone(),
+        payload: redact_secrets(&command.payload.to_string(), &config),
+        response: response.data.as_ref().map(|d| redact_secrets(&d.to_string(), &config)),
+        error: response.error.clone(),
+        success: response.success,
+        model: response.data.as_ref()
+            .and_then(|d| d.get("model"))
+            .and_then(|m| m.as_str())
+            .map(str::to_string)
+            .or_else(|| config.model.clone()),
+        prompt_tokens: usage.map(|u| u.prompt_tokens),
+        completion_tokens: usage.map(|u| u.completion_tokens),
+        tokens_estimated: reported.is_none() && usage.is_some(),
+        latency_ms: latency.as_millis() as i64,
+        session_id: command.session_id.clone(),
+        swarm_id: command.swarm_id.clone(),
+        task_id: command.task_id.clone(),
+        broadcast_id: command.broadcast_id.clone(),
+        created_at: response.timestamp,
+    };
+    log::debug!(
+        tool = tool_id, command = command.command_type.as_str(), duration_ms = interaction.latency_ms, success = response.success;
+        "AI command {} finished", command.id
+    );
+    if let Err(e) = db.create_ai_interaction(&interaction) {
+        log::warn!("Failed to record AI interaction for command {}: {}", command.id, e);
     }
+    log_exchange(&db, &config, &interaction);
     
-    Ok(())
+    emit_tool_event(app, RESPONSE_EVENT, serde_json::json!({ "tool_id": tool_id, "response": response }));
 }
 
-#[tauri::command]
-pub async fn send_ai_command(tool_id: String, command: AICommand) -> Result<AIResponse, String> {
-    log::info!("Sending command to AI tool: {} - {}", tool_id, command.command_type);
+// Commands outside a session or swarm are logged under the tool's id
+fn log_exchange(db: &Database, config: &ToolSpecificConfig, interaction: &DbAIInteraction) {
+    let settings = tool_io::settings(db);
+    if !settings.enabled {
+        return;
+    }
+    let id = interaction.session_id.as_deref()
+        .or(interaction.swarm_id.as_deref())
+        .unwrap_or(&interaction.tool_id);
+    let response = interaction.response.as_deref()
+        .or(interaction.error.as_deref())
+        .unwrap_or_default();
+    let exchange = tool_io::Exchange {
+        tool_id: &interaction.tool_id,
+        command_id: &interaction.command_id,
+        command_type: &interaction.command_type,
+        request: &interaction.payload,
+        response: &redact_secrets(response, config),
+        success: interaction.success,
+        latency_ms: interaction.latency_ms,
+        timestamp: interaction.created_at,
+    };
+    if let Err(e) = tool_io::append(id, &exchange, &settings) {
+        log::warn!("Failed to write tool I/O log for {}: {}", id, e);
+    }
+}
+
+/// Claude Code speaks newline-delimited JSON; the other CLIs take the prompt as a plain line of text.
+fn encode_request(tool_type: &str, payload: &serde_json::Value) -> String {
+    let prompt = ["prompt", "message", "content"].iter()
+        .find_map(|key| payload.get(*key).and_then(|v| v.as_str()))
+        .map(str::to_string)
+        .unwrap_or_else(|| payload.to_string());
+    
+    match tool_type {
+        "claude-code" => format!("{}\n", serde_json::json!({
+            "type": "user",
+            "message": { "role": "user", "content": prompt },
+        })),
+        _ => format!("{}\n", prompt),
+    }
+}
+
+/// Reads one response. `Ok(None)` means the process closed stdout before answering.
+async fn read_response(tool_type: &str, config: &ToolSpecificConfig, stdout: &mut BufReader<ChildStdout>) -> std::io::Result<Option<(bool, serde_json::Value)>> {
+    if tool_type == "claude-code" {
+        // Progress messages stream by until the final `result` message
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if stdout.read_line(&mut line).await? == 0 {
+                return Ok(None);
+            }
+            let Ok(message) = serde_json::from_str::<serde_json::Value>(line.trim()) else {
+                log::debug!("Skipping non-JSON output: {}", line.trim());
+                continue;
+            };
+            if message.get("type").and_then(|t| t.as_str()) == Some("result") {
+                let is_error = message.get("is_error").and_then(|e| e.as_bool()).unwrap_or(false);
+                return Ok(Some((!is_error, message)));
+            }
+        }
+    }
+    
+    let terminator = config.additional_config.get("response_terminator").and_then(|t| t.as_str());
+    let mut collected: Vec<u8> = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        let read = if collected.is_empty() {
+            stdout.read(&mut chunk).await?
+        } else {
+            match tokio::time::timeout(RESPONSE_QUIET_PERIOD, stdout.read(&mut chunk)).await {
+                Ok(read) => read?,
+                Err(_) => break,
+            }
+        };
+        if read == 0 {
+            if collected.is_empty() {
+                return Ok(None);
+            }
+            break;
+        }
+        collected.extend_from_slice(&chunk[..read]);
+        
+        if let Some(terminator) = terminator {
+            let text = String::from_utf8_lossy(&collected).to_string();
+            if let Some(end) = text.lines().position(|l| l.trim() == terminator) {
+                let output: Vec<&str> = text.lines().take(end).collect();
+                return Ok(Some((true, serde_json::json!({ "output": output.join("\n") }))));
+            }
+        }
+    }
+    
+    let output = String::from_utf8_lossy(&collected).trim_end().to_string();
+    Ok(Some((true, serde_json::json!({ "output": output }))))
+}
+
+fn command_response(command_id: &str, success: bool, data: Option<serde_json::Value>, error: Option<String>) -> AIResponse {
+    AIResponse {
+        id: Uuid::new_v4().to_string(),
+        command_id: command_id.to_string(),
+        success,
+        data,
+        error,
+        timestamp: Utc::now(),
+        served_by: None,
+        skipped_tools: Vec::new(),
+    }
+}
+
+/// Drops the tool's process, records the tool as errored and builds the error for the caller.
+async fn tool_unavailable(state: &AppState, tool_id: &str, reason: String) -> AppError {
+    log::warn!("AI tool {} became unavailable: {}", tool_id, reason);
     
-    // TODO: Replace with actual command sending
-    let response = mock_send_command(tool_id, command).await
-        .map_err(|e| format!("Failed to send command: {}", e))?;
+    if let Some(mut process) = state.processes.0.lock().await.remove(tool_id) {
+        let _ = process.child.kill().await;
+    }
+    set_tool_error(&state.db, tool_id, &reason);
     
-    Ok(response)
+    AppError::ToolUnavailable(format!("{}: {}", tool_id, reason))
 }
 
-#[tauri::command]
-pub async fn get_ai_tools() -> Result<Vec<AITool>, String> {
-    log::info!("Getting AI tools");
+fn redact_secrets(text: &str, config: &ToolSpecificConfig) -> String {
+    secrets::redact(text, config.api_key.as_deref())
+}
+
+/// Tool ids are `ai_tools` ids (built-in tools use their type, as the frontend adapters do)
+/// or, for older callers, the id of a stored tool config.
+fn resolve_tool_type(db: &Database, tool_id: &str) -> Result<String> {
+    if let Some(tool) = db.get_ai_tool(tool_id)? {
+        return Ok(tool.tool_type);
+    }
     
-    // TODO: Replace with actual database query
-    let tools = mock_get_tools().await
-        .map_err(|e| format!("Failed to get tools: {}", e))?;
+    db.get_ai_tool_configs()?
+        .into_iter()
+        .find(|c| c.id == tool_id)
+        .map(|c| c.tool_name)
+        .ok_or_else(|| anyhow!("Unknown AI tool: {}", tool_id))
+}
+
+/// Probes PATH for known CLIs and records what it finds. Existing tools only get their
+/// version refreshed and a missing binary path filled in; user settings are left alone.
+pub async fn detect_and_register_tools(db: &Database) -> Result<Vec<DetectedTool>> {
+    let mut detected = Vec::new();
     
-    Ok(tools)
+    for (binary, tool_type, name) in KNOWN_BINARIES {
+        let existing = db.get_ai_tools()?.into_iter().find(|t| t.tool_type == tool_type);
+        let binary_path = crate::commands::system::resolve_binary(binary);
+        // Ollama may run as a service without its CLI on PATH, so a live server counts too
+        let endpoint = match tool_type {
+            "ollama" => probe_ollama_server(existing.as_ref()).await,
+            _ => None,
+        };
+        if binary_path.is_none() && endpoint.is_none() {
+            continue;
+        }
+        let version = match &binary_path {
+            Some(path) => probe_version(path).await,
+            None => None,
+        };
+        let path = binary_path.map(|p| p.to_string_lossy().to_string());
+        
+        let tool = match existing {
+            Some(mut tool) => {
+                let mut config: ToolSpecificConfig = serde_json::from_str(&tool.config).unwrap_or_default();
+                let stored_path = config.additional_config.get("binary_path").and_then(|p| p.as_str());
+                let stale = stored_path.is_none_or(|p| !std::path::Path::new(p).exists());
+                if let Some(path) = path.as_ref().filter(|_| stale) {
+                    config.additional_config.insert("binary_path".to_string(), serde_json::json!(path));
+                }
+                if let Some(version) = &version {
+                    tool.version = version.clone();
+                }
+                tool.config = serde_json::to_string(&config)?;
+                tool.updated_at = Utc::now();
+                tool
+            }
+            None => {
+                let mut config = ToolSpecificConfig::default();
+                if let Some(path) = &path {
+                    config.additional_config.insert("binary_path".to_string(), serde_json::json!(path));
+                }
+                let now = Utc::now();
+                DbAITool {
+                    id: tool_type.to_string(),
+                    tool_type: tool_type.to_string(),
+                    name: name.to_string(),
+                    version: version.clone().unwrap_or_else(|| "unknown".to_string()),
+                    status: "disconnected".to_string(),
+                    config: serde_json::to_string(&config)?,
+                    last_used: None,
+                    last_error: None,
+                    created_at: now,
+                    updated_at: now,
+                }
+            }
+        };
+        db.save_ai_tool(&tool)?;
+        
+        log::info!("Detected {} at {} ({})", name,
+            path.as_deref().or(endpoint.as_deref()).unwrap_or_default(),
+            version.as_deref().unwrap_or("unknown version"));
+        detected.push(DetectedTool {
+            tool_id: tool.id,
+            tool_type: tool_type.to_string(),
+            name: name.to_string(),
+            binary: binary.to_string(),
+            path,
+            version,
+            endpoint,
+        });
+    }
+    
+    Ok(detected)
 }
 
-#[tauri::command]
-pub async fn update_ai_tool_status(tool_id: String, status: String) -> Result<(), String> {
-    log::info!("Updating AI tool status: {} -> {}", tool_id, status);
+/// Returns the endpoint when an Ollama server answers there (the stored one, else the default).
+async fn probe_ollama_server(existing: Option<&DbAITool>) -> Option<String> {
+    let config: ToolSpecificConfig = existing
+        .and_then(|t| serde_json::from_str(&t.config).ok())
+        .unwrap_or_default();
+    match tokio::time::timeout(OLLAMA_PROBE_TIMEOUT, providers::ollama::list_models(&config)).await {
+        Ok(Ok(_)) => Some(providers::ollama::endpoint(&config)),
+        Ok(Err(e)) => {
+            log::debug!("No Ollama server found: {}", e);
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+/// First non-empty line of `<binary> --version`, from stdout or stderr.
+async fn probe_version(path: &std::path::Path) -> Option<String> {
+    let output = Command::new(path)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
     
-    // TODO: Replace with actual database update
-    mock_update_tool_status(tool_id, status).await
-        .map_err(|e| format!("Failed to update tool status: {}", e))?;
+    let output = match tokio::time::timeout(VERSION_PROBE_TIMEOUT, output).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => {
+            log::warn!("Failed to run {:?} --version: {}", path, e);
+            return None;
+        }
+        Err(_) => {
+            log::warn!("{:?} --version did not finish in time", path);
+            return None;
+        }
+    };
     
+    [&output.stdout, &output.stderr].iter()
+        .flat_map(|bytes| String::from_utf8_lossy(bytes).lines().map(|l| l.trim().to_string()).collect::<Vec<_>>())
+        .find(|line| !line.is_empty())
+}
+
+fn set_tool_status(db: &Database, tool_id: &str, status: &str) {
+    if let Err(e) = db.update_ai_tool_status(tool_id, status, None) {
+        log::warn!("Failed to update status of AI tool {}: {}", tool_id, e);
+    }
+}
+
+fn set_tool_error(db: &Database, tool_id: &str, reason: &str) {
+    if let Err(e) = db.update_ai_tool_status(tool_id, "error", Some(reason)) {
+        log::warn!("Failed to update status of AI tool {}: {}", tool_id, e);
+    }
+}
+
+/// Checks every connected tool; run by the scheduler each `tool_health_interval_secs`.
+pub async fn check_connected_tools(app: AppHandle) -> Result<()> {
+    let db = app.state::<AppState>().db.clone();
+    let tools = db.get_ai_tools()?;
+    for tool in tools.into_iter().filter(|t| t.status == "connected") {
+        if let Err(e) = check_tool(&app, &tool.id).await {
+            log::warn!("Health check of AI tool {} failed to run: {}", tool.id, e);
+        }
+    }
     Ok(())
 }
 
+/// A connected tool that fails the check is flipped to 'error' and a status event is emitted.
+async fn check_tool(app: &AppHandle, tool_id: &str) -> Result<ToolHealth> {
+    let state = app.state::<AppState>();
+    let db = &state.db;
+    let tool = db.get_ai_tool(tool_id)?
+        .ok_or_else(|| anyhow!("AI tool not found: {}", tool_id))?;
+    
+    let failure = probe_tool(&state, tool_id).await;
+    let mut status = tool.status.clone();
+    
+    if let Some(reason) = &failure {
+        if tool.status == "connected" {
+            log::warn!("AI tool {} failed its health check: {}", tool_id, reason);
+            set_tool_error(db, tool_id, reason);
+            status = "error".to_string();
+            emit_tool_status(app, tool_id, &status, Some(reason));
+            start_reconnect(app, tool_id);
+        }
+    }
+    
+    Ok(ToolHealth {
+        tool_id: tool_id.to_string(),
+        status,
+        healthy: failure.is_none(),
+        reason: failure,
+        checked_at: Utc::now(),
+    })
+}
+
+/// Returns why the tool is unhealthy, or `None` when it is fine.
+async fn probe_tool(state: &AppState, tool_id: &str) -> Option<String> {
+    let http_tool = state.tools.http_tools.lock().unwrap().get(tool_id).map(|t| (t.tool_type.clone(), t.config.clone()));
+    if let Some((tool_type, config)) = http_tool {
+        let failure = http_ping(&tool_type, &config).await.err()?;
+        // Dropped so a reconnect really re-validates instead of reusing the stale entry
+        state.tools.http_tools.lock().unwrap().remove(tool_id);
+        return Some(failure.to_string());
+    }
+    
+    let mut processes = state.processes.0.lock().await;
+    let Some(process) = processes.get_mut(tool_id) else {
+        return Some("process is not running".to_string());
+    };
+    
+    match process.child.try_wait() {
+        Ok(None) => None,
+        Ok(Some(status)) => {
+            let mut process = processes.remove(tool_id).expect("process present");
+            drop(processes);
+            let stderr = read_stderr(&mut process.child).await;
+            Some(format!("process exited ({}): {}", status, stderr.trim()))
+        }
+        Err(e) => Some(format!("failed to check process: {}", e)),
+    }
+}
+
+/// Starts a supervisor that respawns a crashed tool, unless its policy disables it,
+/// one is already running, or the attempts since the tool was last stable are used up.
+fn start_reconnect(app: &AppHandle, tool_id: &str) {
+    let tools = &app.state::<AppState>().inner().tools;
+    let Some(config) = tools.connected_config(tool_id) else {
+        return;
+    };
+    let policy: ReconnectPolicy = config.additional_config.get("reconnect")
+        .and_then(|p| serde_json::from_value(p.clone()).ok())
+        .unwrap_or_default();
+    if !policy.enabled || policy.max_attempts == 0 {
+        return;
+    }
+    
+    let done = {
+        let mut reconnects = tools.reconnects.lock().unwrap();
+        let state = reconnects.entry(tool_id.to_string()).or_default();
+        if state.done.as_ref().is_some_and(|done| !*done.borrow()) {
+            return;
+        }
+        let stable = state.last_success
+            .is_none_or(|at| at.elapsed().as_secs() >= policy.stable_after_secs);
+        if stable {
+            state.attempts = 0;
+        }
+        let (done, receiver) = tokio::sync::watch::channel(false);
+        state.done = Some(receiver);
+        done
+    };
+    
+    let app = app.clone();
+    let tool_id = tool_id.to_string();
+    tauri::async_runtime::spawn(async move {
+        supervise_reconnect(&app, &tool_id, config, policy).await;
+        let _ = done.send(true);
+    });
+}
+
+async fn supervise_reconnect(app: &AppHandle, tool_id: &str, config: ToolSpecificConfig, policy: ReconnectPolicy) {
+    let app_state = app.state::<AppState>();
+    let db = &app_state.db;
+    let mut last_error = String::new();
+    
+    loop {
+        let attempt = {
+            let mut reconnects = app_state.tools.reconnects.lock().unwrap();
+            let Some(state) = reconnects.get_mut(tool_id) else {
+                return; // Disconnected by the user
+            };
+            if state.attempts >= policy.max_attempts {
+                break;
+            }
+            state.attempts += 1;
+            state.attempts
+        };
+        
+        let delay = policy.base_backoff_ms
+            .saturating_mul(1u64 << (attempt - 1).min(16))
+            .min(MAX_RECONNECT_BACKOFF_MS);
+        set_tool_status(db, tool_id, "connecting");
+        emit_tool_event(app, "tool:reconnecting", serde_json::json!({
+            "tool_id": tool_id,
+            "attempt": attempt,
+            "max_attempts": policy.max_attempts,
+            "delay_ms": delay,
+        }));
+        tokio::time::sleep(tokio::time::Duration::from_millis(delay)).await;
+        
+        if app_state.tools.connected_config(tool_id).is_none() {
+            return;
+        }
+        let project_id = app_state.tools.connect_projects.lock().unwrap().get(tool_id).cloned().flatten();
+        match connect_tool(&app_state, tool_id.to_string(), config.clone(), project_id).await {
+            Ok(_) => {
+                if let Some(state) = app_state.tools.reconnects.lock().unwrap().get_mut(tool_id) {
+                    state.last_success = Some(std::time::Instant::now());
+                }
+                set_tool_status(db, tool_id, "connected");
+                log::info!("Reconnected AI tool {} after {} attempt(s)", tool_id, attempt);
+                emit_tool_event(app, "tool:reconnected", serde_json::json!({ "tool_id": tool_id, "attempt": attempt }));
+                emit_tool_status(app, tool_id, "connected", None);
+                return;
+            }
+            Err(e) => {
+                log::warn!("Reconnect attempt {} for AI tool {} failed: {}", attempt, tool_id, e);
+                last_error = e.to_string();
+            }
+        }
+    }
+    
+    set_tool_error(db, tool_id, &format!("reconnection failed: {}", last_error));
+    emit_tool_event(app, "tool:reconnect-failed", serde_json::json!({
+        "tool_id": tool_id,
+        "attempts": policy.max_attempts,
+        "error": last_error,
+    }));
+    emit_tool_status(app, tool_id, "error", Some(&last_error));
+}
+
+/// Lets a command sent during a reconnection wait for it (bounded) instead of failing at once.
+async fn wait_for_reconnect(tools: &ToolRegistry, tool_id: &str) {
+    let done = tools.reconnects.lock().unwrap()
+        .get(tool_id)
+        .and_then(|state| state.done.clone());
+    let Some(mut done) = done else {
+        return;
+    };
+    
+    let finished = async {
+        while !*done.borrow_and_update() {
+            if done.changed().await.is_err() {
+                break;
+            }
+        }
+    };
+    if tokio::time::timeout(RECONNECT_WAIT, finished).await.is_err() {
+        log::warn!("Gave up waiting for AI tool {} to reconnect", tool_id);
+    }
+}
+
+fn emit_tool_event(app: &AppHandle, event: &str, payload: serde_json::Value) {
+    if let Err(e) = app.emit(event, payload) {
+        log::warn!("Failed to emit {}: {}", event, e);
+    }
+}
+
+fn emit_tool_status(app: &AppHandle, tool_id: &str, status: &str, reason: Option<&str>) {
+    let payload = serde_json::json!({ "tool_id": tool_id, "status": status, "reason": reason });
+    emit_tool_event(app, TOOL_STATUS_EVENT, payload.clone());
+    DataChanged::new(Entity::AiTool, Action::Updated, tool_id)
+        .with_data(&payload)
+        .emit(app);
+}
+
+/// Stores the tool, keeping the status and creation time of an existing row.
+fn register_tool(state: &AppState, tool: AITool) -> Result<AITool> {
+    let db = &state.db;
+    if tool.tool_type.trim().is_empty() || tool.name.trim().is_empty() {
+        return Err(anyhow!("Tool type and name are required"));
+    }
+    
+    let now = Utc::now();
+    let id = if tool.id.is_empty() { Uuid::new_v4().to_string() } else { tool.id.clone() };
+    let existing = db.get_ai_tool(&id)?;
+    
+    let mut config = tool.config;
+    if !secrets::is_placeholder(config.api_key.as_deref()) {
+        secrets::store_api_key(&id, config.api_key.as_deref().unwrap_or_default())?;
+        config.api_key = Some(secrets::API_KEY_PLACEHOLDER.to_string());
+    } else {
+        // Tools come back from the frontend without the key (or masked); keep pointing at the keychain
+        config.api_key = existing.as_ref()
+            .and_then(|t| serde_json::from_str::<ToolSpecificConfig>(&t.config).ok())
+            .and_then(|c| c.api_key);
+    }
+    
+    let warning = unknown_model_warning(&state.tools, &id, &tool.tool_type, config.model.as_deref());
+    if let Some(warning) = &warning {
+        log::warn!("{}", warning);
+    }
+    
+    let row = DbAITool {
+        id,
+        tool_type: tool.tool_type,
+        name: tool.name,
+        version: tool.version,
+        status: existing.as_ref().map(|t| t.status.clone()).unwrap_or_else(|| "disconnected".to_string()),
+        config: serde_json::to_string(&config)?,
+        last_used: existing.as_ref().and_then(|t| t.last_used),
+        last_error: existing.as_ref().and_then(|t| t.last_error.clone()),
+        created_at: existing.as_ref().map(|t| t.created_at).unwrap_or(now),
+        updated_at: now,
+    };
+    db.save_ai_tool(&row)?;
+    
+    let mut tool = tool_from_row(row)?;
+    tool.warnings.extend(warning);
+    Ok(tool)
+}
+
+fn tool_from_row(row: DbAITool) -> Result<AITool> {
+    let mut config: ToolSpecificConfig = serde_json::from_str(&row.config)?;
+    let has_api_key = config.api_key.take().is_some_and(|key| !key.is_empty());
+    Ok(AITool {
+        capabilities: tool_capabilities(&row.tool_type, &config),
+        config,
+        id: row.id,
+        tool_type: row.tool_type,
+        name: row.name,
+        version: row.version,
+        status: row.status,
+        last_used: row.last_used,
+        last_error: row.last_error,
+        has_api_key,
+        warnings: Vec::new(),
+    })
+}
+
+/// Collects whatever a dead process left on stderr, without waiting on it forever.
+async fn read_stderr(child: &mut Child) -> String {
+    let mut output = String::new();
+    if let Some(mut stderr) = child.stderr.take() {
+        let read = stderr.read_to_string(&mut output);
+        if tokio::time::timeout(tokio::time::Duration::from_secs(1), read).await.is_err() {
+            log::warn!("Timed out reading stderr of exited AI tool process");
+        }
+    }
+    output
+}
+
 // Utility function to spawn AI tool processes
-async fn spawn_ai_tool_process(tool_type: &str, config: &ToolSpecificConfig) -> Result<Child> {
+/// The project's directory must exist; its env for the tool is read fresh so edits apply on reconnect.
+fn process_scope(db: &Database, project_id: &str, tool_id: &str) -> Result<ProcessScope> {
+    let project = db.get_project(project_id)?
+        .ok_or_else(|| anyhow!("Project not found: {}", project_id))?;
+    let cwd = std::path::PathBuf::from(&project.path);
+    if !cwd.is_dir() {
+        return Err(anyhow!("Working directory of project {} does not exist: {}", project.name, cwd.display()));
+    }
+    
+    let env = db.get_project_ai_tool(project_id, tool_id)?
+        .map(|tool| serde_json::from_str(&tool.env))
+        .transpose()
+        .map_err(|e| anyhow!("Invalid env for {} in project {}: {}", tool_id, project.name, e))?
+        .unwrap_or_default();
+    
+    Ok(ProcessScope { cwd, env })
+}
+
+async fn spawn_ai_tool_process(state: &AppState, tool_type: &str, config: &ToolSpecificConfig, scope: Option<&ProcessScope>) -> Result<Child> {
+    let binary_path = config.additional_config.get("binary_path").and_then(|p| p.as_str());
+    // The user's variables go first, so the tool's own settings below take precedence
+    let app_env = env_vars::app_env(state);
+    let program = |default: &str| {
+        let mut command = Command::new(binary_path.unwrap_or(default));
+        command.envs(app_env.iter().map(|(name, value)| (name, value)));
+        command
+    };
+    
     let mut cmd = match tool_type {
         "claude-code" => {
-            let mut command = Command::new("claude");
-            command.arg("--api-mode");
+            let mut command = program("claude");
+            command.args(["-p", "--input-format", "stream-json", "--output-format", "stream-json", "--verbose"]);
             if let Some(api_key) = &config.api_key {
                 command.env("ANTHROPIC_API_KEY", api_key);
             }
             command
         },
         "gemini-cli" => {
-            let mut command = Command::new("gemini");
+            let mut command = program("gemini");
             command.arg("--interactive");
             if let Some(api_key) = &config.api_key {
                 command.env("GOOGLE_API_KEY", api_key);
@@ -168,115 +2061,113 @@ async fn spawn_ai_tool_process(tool_type: &str, config: &ToolSpecificConfig) ->
             command
         },
         "cursor-cli" => {
-            let mut command = Command::new("cursor");
+            let mut command = program("cursor");
             command.arg("--api");
             command
         },
         _ => return Err(anyhow::anyhow!("Unknown tool type: {}", tool_type)),
     };
     
+    let proxy_env = providers::proxy::env_vars(config)?;
+    cmd.envs(proxy_env.iter().map(|(name, value)| (name, value)));
+    if let Some(scope) = scope {
+        cmd.current_dir(&scope.cwd);
+        cmd.envs(&scope.env);
+    }
+    
+    // The Debug form includes the environment, so it carries the key, proxy credentials and the project's values
+    let mut secrets: Vec<&str> = scope.map(|s| s.env.values().map(String::as_str).collect()).unwrap_or_default();
+    secrets.extend(config.api_key.as_deref());
+    secrets.extend(proxy_env.iter().map(|(_, value)| value.as_str()));
+    let app_secrets = env_vars::app_env_secrets(state);
+    secrets.extend(app_secrets.iter().map(String::as_str));
+    log::debug!("Spawning {}: {}", tool_type, secrets::redact(&format!("{:?}", cmd), secrets));
+    
     let child = cmd
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
+        .kill_on_drop(true)
         .spawn()
-        .context("Failed to spawn AI tool process")?;
+        .map_err(|e| anyhow!("Failed to spawn {} process: {}", tool_type, e))?;
     
     Ok(child)
 }
 
-// Mock implementations
-async fn mock_initialize_tool(mut tool: AITool) -> Result<AITool> {
-    tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
+/// Stops every tool process on exit: each is asked to terminate, and whatever is still running
+/// after a grace period is killed. Reconnection is switched off first so nothing respawns.
+pub async fn shutdown_tool_processes(state: &AppState) {
+    state.tools.connect_configs.lock().unwrap().clear();
+    state.tools.connect_projects.lock().unwrap().clear();
+    state.tools.reconnects.lock().unwrap().clear();
     
-    tool.status = "disconnected".to_string();
-    tool.capabilities = get_mock_capabilities(&tool.tool_type);
+    let processes: Vec<(String, ToolProcess)> = state.processes.0.lock().await.drain().collect();
+    if processes.is_empty() {
+        return;
+    }
+    log::info!("Stopping {} AI tool process(es)", processes.len());
     
-    Ok(tool)
+    for (_, process) in &processes {
+        if let Some(pid) = process.child.id() {
+            terminate_process(pid);
+        }
+    }
+    let deadline = tokio::time::Instant::now() + SHUTDOWN_GRACE_PERIOD;
+    for (tool_id, mut process) in processes {
+        if tokio::time::timeout_at(deadline, process.child.wait()).await.is_err() {
+            log::warn!("AI tool {} did not stop in time, killing it", tool_id);
+            let _ = process.child.kill().await;
+        }
+    }
 }
 
-async fn mock_connect_tool(tool_id: String, _config: ToolSpecificConfig) -> Result<Connection> {
-    tokio::time::sleep(tokio::time::Duration::from_millis(1500)).await;
-    
-    let connection = Connection {
-        id: Uuid::new_v4().to_string(),
-        tool_id,
-        status: "connected".to_string(),
-        established_at: Some(Utc::now()),
-        last_activity: Some(Utc::now()),
-        error: None,
-    };
-    
-    Ok(connection)
+/// Last-resort cleanup for when the app unwinds without reaching the exit handler (a panic on
+/// the main thread): kills whatever is still in the process map.
+pub struct ToolProcessGuard(pub Arc<ProcessRegistry>);
+
+impl Drop for ToolProcessGuard {
+    fn drop(&mut self) {
+        // A panic may have left the map locked; the children then die with the process via kill_on_drop
+        if let Ok(mut processes) = self.0.0.try_lock() {
+            for (tool_id, process) in processes.iter_mut() {
+                if let Err(e) = process.child.start_kill() {
+                    log::warn!("Failed to kill AI tool {}: {}", tool_id, e);
+                }
+            }
+            processes.clear();
+        }
+    }
 }
 
-async fn mock_send_command(tool_id: String, command: AICommand) -> Result<AIResponse> {
-    tokio::time::sleep(tokio::time::Duration::from_millis(2000)).await;
-    
-    let response = AIResponse {
-        id: Uuid::new_v4().to_string(),
-        command_id: command.id,
-        success: true,
-        data: Some(serde_json::json!({
-            "message": format!("Command executed successfully on {}", tool_id),
-            "result": "Mock response data"
-        })),
-        error: None,
-        timestamp: Utc::now(),
-    };
-    
-    Ok(response)
+// SIGTERM on Unix; Windows has no signal a piped child reliably handles, so it is killed after the grace period
+#[cfg(unix)]
+fn terminate_process(pid: u32) {
+    // SAFETY: kill(2) only sends a signal; the pid belongs to a child we have not reaped yet
+    if unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) } != 0 {
+        log::debug!("SIGTERM to {} failed: {}", pid, std::io::Error::last_os_error());
+    }
 }
 
-async fn mock_get_tools() -> Result<Vec<AITool>> {
-    tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
-    
-    let tools = vec![
-        AITool {
-            id: Uuid::new_v4().to_string(),
-            tool_type: "claude-code".to_string(),
-            name: "Claude Code".to_string(),
-            version: "1.0.0".to_string(),
-            status: "disconnected".to_string(),
-            capabilities: get_mock_capabilities("claude-code"),
-            config: ToolSpecificConfig {
-                api_key: None,
-                endpoint: Some("https://api.anthropic.com".to_string()),
-                max_tokens: Some(4096),
-                temperature: Some(0.7),
-                model: Some("claude-3-sonnet".to_string()),
-                additional_config: HashMap::new(),
-            },
-            last_used: None,
-        },
-        AITool {
-            id: Uuid::new_v4().to_string(),
-            tool_type: "gemini-cli".to_string(),
-            name: "Gemini CLI".to_string(),
-            version: "1.0.0".to_string(),
-            status: "disconnected".to_string(),
-            capabilities: get_mock_capabilities("gemini-cli"),
-            config: ToolSpecificConfig {
-                api_key: None,
-                endpoint: Some("https://generativelanguage.googleapis.com".to_string()),
-                max_tokens: Some(8192),
-                temperature: Some(0.9),
-                model: Some("gemini-pro".to_string()),
-                additional_config: HashMap::new(),
-            },
-            last_used: None,
-        },
-    ];
-    
-    Ok(tools)
+#[cfg(not(unix))]
+fn terminate_process(_pid: u32) {}
+
+#[cfg(target_os = "linux")]
+fn process_memory(pid: u32) -> Option<u64> {
+    // Second field of statm is the resident set size in pages
+    let statm = std::fs::read_to_string(format!("/proc/{}/statm", pid)).ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    // SAFETY: sysconf has no preconditions
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    Some(pages * page_size.max(0) as u64)
 }
 
-async fn mock_update_tool_status(_tool_id: String, _status: String) -> Result<()> {
-    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-    Ok(())
+#[cfg(not(target_os = "linux"))]
+fn process_memory(_pid: u32) -> Option<u64> {
+    None
 }
 
-fn get_mock_capabilities(tool_type: &str) -> Vec<Capability> {
+// Capabilities are fixed per tool type, except for Ollama where each installed model is one
+fn tool_capabilities(tool_type: &str, config: &ToolSpecificConfig) -> Vec<Capability> {
     match tool_type {
         "claude-code" => vec![
             Capability {
@@ -328,6 +2219,62 @@ fn get_mock_capabilities(tool_type: &str) -> Vec<Capability> {
                 ],
             },
         ],
+        "ollama" => config.additional_config.get("models")
+            .and_then(|m| m.as_array())
+            .map(|models| models.iter()
+                .filter_map(|m| m.as_str())
+                .map(|model| Capability {
+                    name: model.to_string(),
+                    description: format!("Generate text with the local {} model", model),
+                    parameters: vec![
+                        Parameter {
+                            name: "prompt".to_string(),
+                            param_type: "string".to_string(),
+                            required: true,
+                            description: Some("Text prompt".to_string()),
+                            default_value: None,
+                        },
+                        Parameter {
+                            name: "system".to_string(),
+                            param_type: "string".to_string(),
+                            required: false,
+                            description: Some("System prompt".to_string()),
+                            default_value: None,
+                        },
+                    ],
+                })
+                .collect())
+            .unwrap_or_default(),
         _ => vec![],
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listed_tools_leave_out_the_api_key() {
+        let db = Database::open_in_memory().unwrap();
+        let key = "sk-ant-REDACTED";
+        let config = ToolSpecificConfig { api_key: Some(key.to_string()), ..Default::default() };
+        db.save_ai_tool(&DbAITool {
+            id: "claude".to_string(),
+            tool_type: "claude-code".to_string(),
+            name: "Claude".to_string(),
+            version: "1.0.0".to_string(),
+            status: "disconnected".to_string(),
+            config: serde_json::to_string(&config).unwrap(),
+            last_used: None,
+            last_error: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }).unwrap();
+
+        let tools = list_tools(&db).unwrap();
+        let json = serde_json::to_string(&tools).unwrap();
+
+        assert!(tools[0].has_api_key);
+        assert!(!json.contains(key), "the key leaked: {}", json);
+    }
+}
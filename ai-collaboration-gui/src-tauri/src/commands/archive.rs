@@ -0,0 +1,258 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Context, Result};
+use tauri::{AppHandle, Emitter, State};
+use tokio_util::sync::CancellationToken;
+use crate::commands::emit_file_changed;
+use crate::commands::search::SearchGuard;
+use crate::events::Action;
+use crate::error::AppError;
+use crate::path_access;
+use crate::state::AppState;
+use crate::telemetry::timed;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveResult {
+    pub path: String,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Payload of `archive:progress`; `total_files` is only known when extracting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveProgress {
+    pub operation_id: Option<String>,
+    pub files: usize,
+    pub bytes: u64,
+    pub total_files: Option<usize>,
+}
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Zips the directory `source_path` into `dest_zip`, file by file without loading them whole.
+/// Ignored files and `.git` are left out unless `include_ignored`. Progress is reported through
+/// `archive:progress`; `cancel_search` with the `operation_id` stops it and removes the partial zip.
+#[tauri::command]
+#[timed]
+pub async fn create_archive(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    source_path: String,
+    dest_zip: String,
+    include_ignored: bool,
+    operation_id: Option<String>,
+    project_id: Option<String>,
+) -> Result<ArchiveResult, AppError> {
+    log::info!("Archiving {} to {}", source_path, dest_zip);
+
+    let (source, dest) = (PathBuf::from(&source_path), PathBuf::from(&dest_zip));
+    path_access::check(&state, &source, project_id.as_deref())?;
+    path_access::check(&state, &dest, project_id.as_deref())?;
+    if !source.is_dir() {
+        return Err(AppError::Internal("Source is not a directory".to_string()));
+    }
+
+    let operation = SearchGuard::start(&state.searches, operation_id.clone());
+    let cancel = operation.cancel.clone();
+    let mut progress = ProgressReporter::new(app.clone(), operation_id, None);
+    let result = tokio::task::spawn_blocking(move || {
+        let result = zip_directory(&source, &dest, include_ignored, &cancel, &mut progress);
+        if result.is_err() {
+            let _ = fs::remove_file(&dest);
+        }
+        result
+    })
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to create archive: {}", e)))?;
+
+    let result = result.map_err(|e| match e.downcast::<AppError>() {
+        Ok(app_error) => app_error,
+        Err(e) => AppError::Internal(format!("Failed to create archive: {}", e)),
+    })?;
+    emit_file_changed(&app, Action::Created, &dest_zip, project_id.as_deref());
+    Ok(result)
+}
+
+/// Unpacks `zip_path` into `dest_dir`. Every entry is checked before anything is written: one
+/// whose path would land outside `dest_dir` fails the whole extraction, as does one that exists
+/// already unless `overwrite`. Cancelling leaves the files extracted so far in place.
+#[tauri::command]
+#[timed]
+pub async fn extract_archive(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    zip_path: String,
+    dest_dir: String,
+    overwrite: bool,
+    operation_id: Option<String>,
+    project_id: Option<String>,
+) -> Result<ArchiveResult, AppError> {
+    log::info!("Extracting {} to {}", zip_path, dest_dir);
+
+    let (archive, dest) = (PathBuf::from(&zip_path), PathBuf::from(&dest_dir));
+    path_access::check(&state, &archive, project_id.as_deref())?;
+    path_access::check(&state, &dest, project_id.as_deref())?;
+
+    let operation = SearchGuard::start(&state.searches, operation_id.clone());
+    let cancel = operation.cancel.clone();
+    let reporter = app.clone();
+    let result = tokio::task::spawn_blocking(move || unzip(&archive, &dest, overwrite, &cancel, reporter, operation_id))
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to extract archive: {}", e)))?;
+
+    let result = result.map_err(|e| match e.downcast::<AppError>() {
+        Ok(app_error) => app_error,
+        Err(e) => AppError::Internal(format!("Failed to extract archive: {}", e)),
+    })?;
+    emit_file_changed(&app, Action::Updated, &dest_dir, project_id.as_deref());
+    Ok(result)
+}
+
+struct ProgressReporter {
+    app: AppHandle,
+    progress: ArchiveProgress,
+    last_emit: Instant,
+}
+
+impl ProgressReporter {
+    fn new(app: AppHandle, operation_id: Option<String>, total_files: Option<usize>) -> Self {
+        Self {
+            app,
+            progress: ArchiveProgress { operation_id, files: 0, bytes: 0, total_files },
+            last_emit: Instant::now(),
+        }
+    }
+
+    fn add(&mut self, bytes: u64) {
+        self.progress.files += 1;
+        self.progress.bytes += bytes;
+        if self.last_emit.elapsed() >= PROGRESS_INTERVAL {
+            self.emit();
+        }
+    }
+
+    fn emit(&mut self) {
+        self.last_emit = Instant::now();
+        if let Err(e) = self.app.emit("archive:progress", &self.progress) {
+            log::warn!("Failed to emit archive:progress: {}", e);
+        }
+    }
+}
+
+fn zip_directory(source: &Path, dest: &Path, include_ignored: bool, cancel: &CancellationToken, progress: &mut ProgressReporter) -> Result<ArchiveResult> {
+    let file = fs::File::create(dest).with_context(|| format!("Failed to create {}", dest.display()))?;
+    // Resolved once it exists, so it is recognised when the walk reaches it
+    let dest_resolved = fs::canonicalize(dest)?;
+    let mut zip = zip::ZipWriter::new(io::BufWriter::new(file));
+
+    let walker = ignore::WalkBuilder::new(source)
+        .require_git(false)
+        .hidden(false)
+        .git_ignore(!include_ignored)
+        .git_exclude(!include_ignored)
+        .git_global(!include_ignored)
+        .ignore(!include_ignored)
+        .parents(!include_ignored)
+        .filter_entry(move |entry| include_ignored || entry.file_name() != ".git")
+        .build();
+    for entry in walker {
+        if cancel.is_cancelled() {
+            return Err(AppError::Cancelled("archive creation was cancelled".to_string()).into());
+        }
+        let entry = entry?;
+        if entry.depth() == 0 {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(source)?;
+        // Zip entry names always use forward slashes
+        let name = relative.components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let metadata = entry.metadata()?;
+        let mut options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+            .large_file(metadata.len() >= u32::MAX as u64);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            options = options.unix_permissions(metadata.permissions().mode());
+        }
+
+        let file_type = metadata.file_type();
+        if file_type.is_dir() {
+            zip.add_directory(name, options)?;
+        } else if file_type.is_file() {
+            if fs::canonicalize(entry.path()).is_ok_and(|p| p == dest_resolved) {
+                continue;
+            }
+            zip.start_file(name, options)?;
+            let mut input = fs::File::open(entry.path())
+                .with_context(|| format!("Failed to read {}", entry.path().display()))?;
+            let copied = io::copy(&mut input, &mut zip)?;
+            progress.add(copied);
+        } else {
+            log::debug!("Not archiving {}: not a regular file", entry.path().display());
+        }
+    }
+
+    zip.finish()?;
+    progress.emit();
+    Ok(ArchiveResult {
+        path: dest.to_string_lossy().to_string(),
+        files: progress.progress.files,
+        bytes: progress.progress.bytes,
+    })
+}
+
+fn unzip(archive_path: &Path, dest: &Path, overwrite: bool, cancel: &CancellationToken, app: AppHandle, operation_id: Option<String>) -> Result<ArchiveResult> {
+    let file = fs::File::open(archive_path).with_context(|| format!("Failed to open {}", archive_path.display()))?;
+    let mut archive = zip::ZipArchive::new(io::BufReader::new(file))?;
+
+    // Checked up front so a bad archive writes nothing
+    let mut targets = Vec::with_capacity(archive.len());
+    for index in 0..archive.len() {
+        let entry = archive.by_index_raw(index)?;
+        let relative = entry.enclosed_name()
+            .ok_or_else(|| anyhow!("Entry {:?} would be extracted outside the destination", entry.name()))?;
+        let target = dest.join(relative);
+        if !overwrite && !entry.is_dir() && target.exists() {
+            return Err(anyhow!("{} already exists", target.display()));
+        }
+        targets.push(target);
+    }
+
+    let mut progress = ProgressReporter::new(app, operation_id, Some(targets.len()));
+    for (index, target) in targets.into_iter().enumerate() {
+        if cancel.is_cancelled() {
+            return Err(AppError::Cancelled("extraction was cancelled; files extracted so far were kept".to_string()).into());
+        }
+        let mut entry = archive.by_index(index)?;
+        if entry.is_dir() {
+            fs::create_dir_all(&target)?;
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut output = fs::File::create(&target).with_context(|| format!("Failed to create {}", target.display()))?;
+        let copied = io::copy(&mut entry, &mut output)?;
+        #[cfg(unix)]
+        if let Some(mode) = entry.unix_mode() {
+            use std::os::unix::fs::PermissionsExt;
+            // Only the permission bits; never setuid and the like
+            fs::set_permissions(&target, fs::Permissions::from_mode(mode & 0o777))?;
+        }
+        progress.add(copied);
+    }
+
+    progress.emit();
+    Ok(ArchiveResult {
+        path: dest.to_string_lossy().to_string(),
+        files: progress.progress.files,
+        bytes: progress.progress.bytes,
+    })
+}
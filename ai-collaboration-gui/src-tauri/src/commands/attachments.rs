@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use chrono::Utc;
+use uuid::Uuid;
+use crate::commands::system::looks_binary;
+use crate::database::DbMessageAttachment;
+use crate::error::AppError;
+use crate::events::{Action, DataChanged, Entity};
+use crate::path_access;
+use crate::state::AppState;
+use crate::telemetry::timed;
+use tauri::{AppHandle, Manager, State};
+
+/// Bytes `start` up to, not including, `end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+// A snapshot keeps at most this much of the attached content
+const MAX_SNAPSHOT_BYTES: u64 = 256 * 1024;
+// and the prompt quotes at most this much of each snapshot
+const MAX_INLINED_SNAPSHOT_CHARS: usize = 8000;
+
+/// Records that the message refers to `path`, or to `byte_range` of it, with a hash of its
+/// current content so a later change can be noticed. With `include_snapshot` a copy of the text
+/// is kept too, and sent along with the message when the conversation continues.
+#[tauri::command]
+#[timed]
+pub async fn attach_file_to_message(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    message_id: String,
+    path: String,
+    include_snapshot: bool,
+    byte_range: Option<ByteRange>,
+) -> Result<DbMessageAttachment, AppError> {
+    log::info!("Attaching {} to message {}", path, message_id);
+
+    let message = state.db.get_chat_message(&message_id)?
+        .ok_or_else(|| AppError::Internal(format!("Chat message not found: {}", message_id)))?;
+    let project_id = state.db.get_chat_session(&message.session_id)?
+        .and_then(|session| session.project_id);
+
+    let handle = app.clone();
+    let project = project_id.clone();
+    let attachment = tokio::task::spawn_blocking(move || attach(&handle.state::<AppState>(), &message_id, &path, project.as_deref(), include_snapshot, byte_range))
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to attach file: {}", e)))??;
+    DataChanged::new(Entity::ChatMessage, Action::Updated, &attachment.message_id)
+        .in_project(project_id.as_deref())
+        .with_data(&attachment)
+        .emit(&app);
+    Ok(attachment)
+}
+
+pub(crate) fn attach(
+    state: &AppState,
+    message_id: &str,
+    path: &str,
+    project_id: Option<&str>,
+    include_snapshot: bool,
+    byte_range: Option<ByteRange>,
+) -> Result<DbMessageAttachment, AppError> {
+    let db = &state.db;
+    let file_path = PathBuf::from(path);
+    path_access::check(state, &file_path, project_id)?;
+    if !file_path.is_file() {
+        return Err(AppError::Internal(format!("Not a file: {}", path)));
+    }
+    if let Some(range) = byte_range {
+        if range.end <= range.start {
+            return Err(AppError::Validation("The byte range must end after it starts".to_string()));
+        }
+    }
+
+    let io_error = |e: std::io::Error| AppError::Internal(format!("Failed to read {}: {}", path, e));
+    let content_hash = hash_content(&file_path, byte_range).map_err(io_error)?;
+    let snapshot_text = if include_snapshot {
+        Some(snapshot(&file_path, byte_range).map_err(io_error)?)
+    } else {
+        None
+    };
+    if snapshot_text.as_ref().is_some_and(|(_, binary)| *binary) {
+        return Err(AppError::BinaryFile { size: fs::metadata(&file_path).map(|m| m.len()).unwrap_or(0) });
+    }
+
+    let attachment = DbMessageAttachment {
+        id: Uuid::new_v4().to_string(),
+        message_id: message_id.to_string(),
+        path: path.to_string(),
+        content_hash,
+        range_start: byte_range.map(|r| r.start as i64),
+        range_end: byte_range.map(|r| r.end as i64),
+        snapshot_text: snapshot_text.map(|(text, _)| text),
+        created_at: Utc::now(),
+        stale: false,
+    };
+    db.create_message_attachment(&attachment)?;
+
+    if let Some(message) = db.get_chat_message(message_id)? {
+        let mut metadata = message.metadata.unwrap_or_default();
+        if !metadata.attachments.contains(&attachment.path) {
+            metadata.attachments.push(attachment.path.clone());
+            db.update_chat_message_metadata(message_id, Some(&metadata))?;
+        }
+    }
+
+    Ok(attachment)
+}
+
+/// Sets `stale` on the attachments whose file no longer has the content it had when attached,
+/// including files that are gone. Each file and range is hashed once.
+pub(crate) fn mark_stale(attachments: &mut [DbMessageAttachment]) {
+    let mut current: HashMap<(String, Option<ByteRange>), Option<String>> = HashMap::new();
+    for attachment in attachments.iter_mut() {
+        let range = byte_range_of(attachment);
+        let hash = current.entry((attachment.path.clone(), range))
+            .or_insert_with(|| hash_content(Path::new(&attachment.path), range).ok());
+        attachment.stale = hash.as_deref() != Some(attachment.content_hash.as_str());
+    }
+}
+
+/// The message text followed by the snapshots attached to it, each cut to a size the prompt
+/// can afford.
+pub(crate) fn with_snapshots(content: &str, attachments: &[&DbMessageAttachment]) -> String {
+    let mut text = content.to_string();
+    for attachment in attachments {
+        let Some(snapshot) = &attachment.snapshot_text else {
+            continue;
+        };
+        let label = match byte_range_of(attachment) {
+            Some(range) => format!("{} (bytes {}-{})", attachment.path, range.start, range.end),
+            None => attachment.path.clone(),
+        };
+        let quoted: String = snapshot.chars().take(MAX_INLINED_SNAPSHOT_CHARS).collect();
+        let cut = if quoted.len() < snapshot.len() { "\n[... cut ...]" } else { "" };
+        text.push_str(&format!("\n\nAttached file {}:\n```\n{}{}\n```", label, quoted, cut));
+    }
+    text
+}
+
+fn byte_range_of(attachment: &DbMessageAttachment) -> Option<ByteRange> {
+    match (attachment.range_start, attachment.range_end) {
+        (Some(start), Some(end)) => Some(ByteRange { start: start.max(0) as u64, end: end.max(0) as u64 }),
+        _ => None,
+    }
+}
+
+// The part of the file an attachment covers, read without loading the rest
+fn open_range(path: &Path, range: Option<ByteRange>) -> std::io::Result<Box<dyn Read>> {
+    let mut file = fs::File::open(path)?;
+    match range {
+        Some(range) => {
+            file.seek(SeekFrom::Start(range.start))?;
+            Ok(Box::new(file.take(range.end.saturating_sub(range.start))))
+        }
+        None => Ok(Box::new(file)),
+    }
+}
+
+fn hash_content(path: &Path, range: Option<ByteRange>) -> std::io::Result<String> {
+    let mut hasher = sha2::Sha256::default();
+    std::io::copy(&mut open_range(path, range)?, &mut hasher)?;
+    Ok(format!("{:x}", sha2::Digest::finalize(hasher)))
+}
+
+// The text, cut to `MAX_SNAPSHOT_BYTES`, and whether it looks binary
+fn snapshot(path: &Path, range: Option<ByteRange>) -> std::io::Result<(String, bool)> {
+    let mut bytes = Vec::new();
+    open_range(path, range)?.take(MAX_SNAPSHOT_BYTES).read_to_end(&mut bytes)?;
+    Ok((String::from_utf8_lossy(&bytes).to_string(), looks_binary(&bytes)))
+}
@@ -0,0 +1,235 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::commands::get_project_by_id;
+use crate::commands::project::{emit_project_changed, project_at, write_marker, Project};
+use crate::database::{TableRows};
+use crate::error::AppError;
+use crate::events::Action;
+use crate::path_access;
+use crate::state::AppState;
+use crate::telemetry::timed;
+use tauri::{AppHandle, State};
+
+/// A project's app state without its files: the project row, its tool settings, chat sessions
+/// and messages, and its swarms with everything under them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectBundle {
+    pub schema_version: u32,
+    pub app_version: String,
+    pub exported_at: DateTime<Utc>,
+    pub tables: Vec<BundleTable>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleTable {
+    pub table: String,
+    pub rows: TableRows,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleSummary {
+    pub path: String,
+    // Rows written, by table
+    pub rows: BTreeMap<String, usize>,
+}
+
+const BUNDLE_SCHEMA_VERSION: u32 = 1;
+
+// Tables whose `id` is regenerated on import; references to those ids are rewritten to match
+const REGENERATED_IDS: [&str; 10] = [
+    "projects",
+    "swarms",
+    "chat_sessions",
+    "chat_messages",
+    "message_attachments",
+    "swarm_agents",
+    "swarm_tasks",
+    "task_results",
+    "swarm_events",
+    "swarm_memory",
+];
+
+/// Writes the project's app state to a JSON bundle at `dest_path`, to be imported elsewhere with
+/// `import_project_bundle`. The project's files are not included.
+#[tauri::command]
+#[timed]
+pub async fn export_project_bundle(state: State<'_, AppState>, project_id: String, dest_path: String) -> Result<BundleSummary, AppError> {
+    log::info!("Exporting project {} to {}", project_id, dest_path);
+
+    let dest = PathBuf::from(&dest_path);
+    path_access::check(&state, &dest, Some(&project_id))?;
+    state.db.get_project(&project_id)?
+        .ok_or_else(|| AppError::Internal(format!("Project not found: {}", project_id)))?;
+
+    let bundle = ProjectBundle {
+        schema_version: BUNDLE_SCHEMA_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at: Utc::now(),
+        tables: state.db.export_project_data(&project_id)?
+            .into_iter()
+            .map(|(table, rows)| BundleTable { table, rows })
+            .collect(),
+    };
+    let json = serde_json::to_vec_pretty(&bundle).map_err(|e| AppError::Internal(e.to_string()))?;
+    fs::write(&dest, json).map_err(|e| AppError::Internal(format!("Failed to write {}: {}", dest_path, e)))?;
+
+    Ok(BundleSummary { path: dest_path, rows: row_counts(&bundle) })
+}
+
+/// Creates a new project at `target_project_path` from a bundle. Every id is generated afresh and
+/// the references between the imported rows are rewritten to match, so the import can't collide
+/// with what is already there. Swarms that were running when exported come back paused.
+#[tauri::command]
+#[timed]
+pub async fn import_project_bundle(app: AppHandle, state: State<'_, AppState>, src_path: String, target_project_path: String) -> Result<Project, AppError> {
+    log::info!("Importing project bundle {} into {}", src_path, target_project_path);
+
+    let src = Path::new(&src_path);
+    path_access::check(&state, src, None)?;
+    let content = fs::read(src).map_err(|e| AppError::Internal(format!("Failed to read {}: {}", src_path, e)))?;
+    let bundle = parse_bundle(&content)?;
+
+    if !Path::new(&target_project_path).is_dir() {
+        return Err(AppError::Internal("Project path does not exist or is not a directory".to_string()));
+    }
+    if let Some(owner) = project_at(&state.db, &target_project_path)? {
+        return Err(AppError::Conflict {
+            message: format!("{} already belongs to the project {}", target_project_path, owner.name),
+            current_hash: None,
+        });
+    }
+
+    let tables = regenerate_ids(bundle.tables, &target_project_path)?;
+    let project = tables.iter()
+        .find(|(table, _)| table == "projects")
+        .and_then(|(_, rows)| rows.first())
+        .ok_or_else(|| AppError::Internal("The bundle holds no project".to_string()))?;
+    let project_id = project.get("id").and_then(|id| id.as_str()).unwrap_or_default().to_string();
+    state.db.import_project_data(&tables)?;
+
+    let project = get_project_by_id(state.clone(), project_id).await
+        .map_err(AppError::Internal)?
+        .ok_or_else(|| AppError::Internal("The imported project could not be loaded".to_string()))?;
+    if let Some(row) = state.db.get_project(&project.id)? {
+        if let Err(e) = write_marker(&row) {
+            log::warn!("Failed to write the project marker in {}: {}", row.path, e);
+        }
+    }
+    emit_project_changed(&app, Action::Created, &project);
+    Ok(project)
+}
+
+/// The version is read on its own first, so a bundle from a newer app fails by saying so.
+fn parse_bundle(content: &[u8]) -> Result<ProjectBundle, AppError> {
+    #[derive(Deserialize)]
+    struct Header {
+        schema_version: Option<u32>,
+    }
+
+    let header: Header = serde_json::from_slice(content)
+        .map_err(|e| AppError::Internal(format!("Not a project bundle: {}", e)))?;
+    match header.schema_version {
+        Some(BUNDLE_SCHEMA_VERSION) => serde_json::from_slice(content)
+            .map_err(|e| AppError::Internal(format!("Unreadable project bundle: {}", e))),
+        Some(version) => Err(AppError::Internal(format!(
+            "Unsupported project bundle schema version {} (this app reads version {})",
+            version, BUNDLE_SCHEMA_VERSION
+        ))),
+        None => Err(AppError::Internal("Not a project bundle: it has no schema version".to_string())),
+    }
+}
+
+fn regenerate_ids(tables: Vec<BundleTable>, project_path: &str) -> Result<Vec<(String, TableRows)>, AppError> {
+    let mut new_ids: HashMap<String, String> = HashMap::new();
+    for table in tables.iter().filter(|t| REGENERATED_IDS.contains(&t.table.as_str())) {
+        for row in &table.rows {
+            if let Some(id) = row.get("id").and_then(|id| id.as_str()) {
+                new_ids.insert(id.to_string(), Uuid::new_v4().to_string());
+            }
+        }
+    }
+
+    let now = serde_json::Value::from(Utc::now().to_rfc3339());
+    let mut result = Vec::with_capacity(tables.len());
+    for BundleTable { table, mut rows } in tables {
+        for row in &mut rows {
+            for value in row.values_mut() {
+                remap(value, &new_ids);
+            }
+            match table.as_str() {
+                "projects" => {
+                    row.insert("path".to_string(), project_path.into());
+                    row.insert("updated_at".to_string(), now.clone());
+                    row.insert("last_opened_at".to_string(), serde_json::Value::Null);
+                }
+                // A link to a swarm of another project would point nowhere on this machine
+                "chat_sessions" => {
+                    let linked = row.get("swarm_id")
+                        .and_then(|id| id.as_str())
+                        .is_some_and(|id| new_ids.values().any(|new_id| new_id == id));
+                    if !linked {
+                        row.insert("swarm_id".to_string(), serde_json::Value::Null);
+                    }
+                }
+                "swarms" => {
+                    let active = row.get("status")
+                        .and_then(|status| status.as_str())
+                        .is_some_and(|status| matches!(status, "initializing" | "running"));
+                    if active {
+                        row.insert("status".to_string(), "paused".into());
+                    }
+                }
+                _ => {}
+            }
+        }
+        result.push((table, rows));
+    }
+
+    if result.iter().filter(|(table, rows)| table == "projects" && !rows.is_empty()).count() != 1 {
+        return Err(AppError::Internal("The bundle must hold exactly one project".to_string()));
+    }
+    Ok(result)
+}
+
+/// Rewrites a value that is one of the old ids, including inside JSON stored as text, such as
+/// task dependencies and scheduler snapshots.
+fn remap(value: &mut serde_json::Value, new_ids: &HashMap<String, String>) {
+    match value {
+        serde_json::Value::String(text) => {
+            if let Some(new_id) = new_ids.get(text.as_str()) {
+                *text = new_id.clone();
+            } else if text.starts_with(['{', '[']) {
+                if let Ok(mut nested) = serde_json::from_str::<serde_json::Value>(text) {
+                    let before = nested.clone();
+                    remap(&mut nested, new_ids);
+                    if nested != before {
+                        *text = nested.to_string();
+                    }
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(|item| remap(item, new_ids)),
+        serde_json::Value::Object(map) => {
+            // Ids are also used as keys, e.g. results by task
+            let renamed: Vec<(String, serde_json::Value)> = std::mem::take(map)
+                .into_iter()
+                .map(|(key, mut item)| {
+                    remap(&mut item, new_ids);
+                    (new_ids.get(&key).cloned().unwrap_or(key), item)
+                })
+                .collect();
+            map.extend(renamed);
+        }
+        _ => {}
+    }
+}
+
+fn row_counts(bundle: &ProjectBundle) -> BTreeMap<String, usize> {
+    bundle.tables.iter()
+        .map(|table| (table.table.clone(), table.rows.len()))
+        .collect()
+}
@@ -0,0 +1,625 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use chrono::Utc;
+use uuid::Uuid;
+use anyhow::{anyhow, Result};
+use std::path::Path;
+use std::time::Instant;
+use tauri::{AppHandle, Manager, State};
+use crate::database::{Database, AppliedCodeBlock, DbChatMessage, DbChatSession, DbMessageAttachment, MessageMetadata};
+use crate::commands::ai_tools::{self, AICommand, ToolRegistry};
+use crate::commands::attachments;
+use crate::commands::pricing::{estimate_tokens, estimate_usage, usage_from_response};
+use crate::commands::database::{emit_chat_session_updated, emit_new_message};
+use crate::commands::project::{project_settings, ToolPolicy};
+use crate::commands::swarm;
+use crate::commands::system::{write_file, WriteMode};
+use crate::events::{Action, DataChanged, Entity};
+use crate::path_access;
+use crate::state::AppState;
+use crate::telemetry::timed;
+
+/// A fenced code block from an AI reply; `index` counts blocks from the start of the text.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeBlock {
+    pub index: usize,
+    pub language: Option<String>,
+    pub path_hint: Option<String>,
+    pub content: String,
+}
+
+/// The system prompt a conversation runs with and where it came from: `session`, `role` or `tool`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemPrompt {
+    pub source: String,
+    pub text: String,
+}
+
+/// What `send_chat_message` would send for a session, without sending it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptPreview {
+    pub tool_id: String,
+    pub system_prompt: Option<SystemPrompt>,
+    pub payload: serde_json::Value,
+    pub included_messages: usize,
+    pub omitted_messages: usize,
+    pub summarized: bool,
+    pub estimated_tokens: i64,
+    pub token_budget: i64,
+}
+
+// Overridable per tool through `additional_config.context_messages` / `context_token_budget`
+const DEFAULT_CONTEXT_MESSAGES: usize = 20;
+const DEFAULT_CONTEXT_TOKEN_BUDGET: i64 = 8000;
+// A message with this role stands in for everything said before it
+const SUMMARY_ROLE: &str = "summary";
+// Labels that introduce a file name, as in `// filename: src/main.rs`
+const PATH_LABELS: [&str; 3] = ["filename:", "file:", "path:"];
+// Outer blocks of these languages may contain complete fenced blocks of the same length
+const MARKDOWN_LANGUAGES: [&str; 3] = ["markdown", "md", "mdx"];
+const TITLE_PROMPT: &str = "Write a title of 3 to 6 words for the conversation below. \
+Reply with the title only, without quotes or a closing period.";
+const MAX_TITLE_CHARS: usize = 60;
+// How much of each opening message the title request quotes
+const TITLE_EXCERPT_CHARS: usize = 2000;
+
+/// Without a `tool_id`, the message goes to the tool the session's project prefers. Files in
+/// `attach_paths` are attached to the message with a snapshot, which is sent along with it.
+#[tauri::command]
+#[timed]
+pub async fn send_chat_message(app: AppHandle, state: State<'_, AppState>, session_id: String, tool_id: Option<String>, content: String, attach_paths: Option<Vec<String>>) -> Result<DbChatMessage, String> {
+    log::info!("Sending chat message in session {} to {:?}", session_id, tool_id);
+
+    let tool_id = match tool_id {
+        Some(tool_id) => tool_id,
+        None => project_default_tool(&state.db, &session_id)
+            .map_err(|e| format!("Failed to send chat message: {}", e))?,
+    };
+    let reply = chat_turn(app, &session_id, &tool_id, content, &attach_paths.unwrap_or_default()).await
+        .map_err(|e| format!("Failed to send chat message: {}", e))?;
+
+    Ok(reply)
+}
+
+/// Assembles the prompt for the next message in a session, by default for the tool that gave
+/// the latest reply, so precedence and budgeting can be checked without spending tokens.
+#[tauri::command]
+#[timed]
+pub async fn preview_prompt(state: State<'_, AppState>, session_id: String, tool_id: Option<String>, content: Option<String>) -> Result<PromptPreview, String> {
+    log::info!("Previewing prompt for session {}", session_id);
+
+    let preview = prompt_preview(&state, &session_id, tool_id, content.unwrap_or_default())
+        .map_err(|e| format!("Failed to preview prompt: {}", e))?;
+
+    Ok(preview)
+}
+
+/// Names the session after its first user message and reply, using the project's default tool.
+/// A session the user has renamed keeps its name.
+#[tauri::command]
+#[timed]
+pub async fn auto_title_session(app: AppHandle, session_id: String) -> Result<DbChatSession, String> {
+    log::info!("Generating a title for session {}", session_id);
+
+    auto_title(app, &session_id).await
+        .map_err(|e| format!("Failed to title session: {}", e))
+}
+
+#[tauri::command]
+#[timed]
+pub async fn parse_ai_response(state: State<'_, AppState>, response_text: String) -> Result<Vec<CodeBlock>, String> {
+    Ok(extract_code_blocks(&response_text))
+}
+
+/// Writes code block `block_index` of the given message (by default the session's latest
+/// assistant reply) to `target_path`, and notes it under `applied_code_blocks` in the message metadata.
+#[tauri::command]
+#[timed]
+pub async fn apply_code_block_to_file(app: AppHandle, state: State<'_, AppState>, session_id: String, block_index: usize, target_path: String, mode: WriteMode, message_id: Option<String>) -> Result<CodeBlock, String> {
+    log::info!("Applying code block {} from session {} to {}", block_index, session_id, target_path);
+
+    let (block, message) = apply_code_block(&state, &session_id, message_id.as_deref(), block_index, &target_path, mode)
+        .map_err(|e| format!("Failed to apply code block: {}", e))?;
+
+    let project_id = state.db.get_chat_session(&session_id).ok().flatten().and_then(|s| s.project_id);
+    DataChanged::new(Entity::File, Action::Updated, target_path.trim())
+        .in_project(project_id.as_deref())
+        .emit(&app);
+    DataChanged::new(Entity::ChatMessage, Action::Updated, &message.id)
+        .in_project(project_id.as_deref())
+        .with_data(&message)
+        .emit(&app);
+    Ok(block)
+}
+
+/// Stores the user message, sends it along with as much earlier conversation as fits the
+/// tool's budget, and stores the reply. The user message is kept even if the call fails.
+async fn chat_turn(app: AppHandle, session_id: &str, tool_id: &str, content: String, attach_paths: &[String]) -> Result<DbChatMessage> {
+    let db = app.state::<AppState>().db.clone();
+    if content.trim().is_empty() {
+        return Err(anyhow!("Message cannot be empty"));
+    }
+    let session = db.get_chat_session(session_id)?
+        .ok_or_else(|| anyhow!("Chat session not found: {}", session_id))?;
+
+    let history = history_with_snapshots(&db, session_id)?;
+
+    let user_message = DbChatMessage {
+        id: Uuid::new_v4().to_string(),
+        session_id: session_id.to_string(),
+        role: "user".to_string(),
+        content: content.clone(),
+        metadata: Some(MessageMetadata { estimated: true, ..Default::default() }),
+        timestamp: Utc::now(),
+        prompt_tokens: Some(estimate_tokens(&content)),
+        completion_tokens: Some(0),
+        attachments: None,
+    };
+    db.create_chat_message(&user_message)?;
+    emit_new_message(&app, &user_message);
+
+    let mut attached = Vec::new();
+    for path in attach_paths {
+        attached.push(attachments::attach(&app.state::<AppState>(), &user_message.id, path, session.project_id.as_deref(), true, None)?);
+    }
+    let prompt_content = attachments::with_snapshots(&content, &attached.iter().collect::<Vec<_>>());
+    let prompt = assemble_prompt(&app.state::<AppState>(), &session, &history, tool_id, &prompt_content);
+
+    let command = AICommand {
+        id: Uuid::new_v4().to_string(),
+        tool_id: tool_id.to_string(),
+        command_type: "chat".to_string(),
+        payload: prompt.payload.clone(),
+        timestamp: Utc::now(),
+        timeout_ms: None,
+        priority: 0,
+        session_id: Some(session_id.to_string()),
+        swarm_id: None,
+        task_id: None,
+        broadcast_id: None,
+    };
+    let command_id = command.id.clone();
+    let started = Instant::now();
+    let response = ai_tools::send_ai_command(app.clone(), tool_id.to_string(), command).await?;
+    let latency_ms = started.elapsed().as_millis() as i64;
+    if !response.success {
+        return Err(anyhow!(response.error.unwrap_or_else(|| format!("{} reported an error", tool_id))));
+    }
+
+    let data = response.data.unwrap_or_default();
+    let reported = usage_from_response(&data);
+    let usage = reported.unwrap_or_else(|| estimate_usage(&prompt.payload, &data));
+    let reply = DbChatMessage {
+        id: Uuid::new_v4().to_string(),
+        session_id: session_id.to_string(),
+        role: "assistant".to_string(),
+        content: reply_text(&data),
+        metadata: Some(MessageMetadata {
+            tool_id: Some(tool_id.to_string()),
+            command_id: Some(command_id),
+            model: data.get("model").and_then(|m| m.as_str()).map(str::to_string),
+            usage: Some(usage.into()),
+            estimated: reported.is_none(),
+            latency_ms: Some(latency_ms),
+            ..Default::default()
+        }),
+        timestamp: Utc::now(),
+        prompt_tokens: Some(usage.prompt_tokens),
+        completion_tokens: Some(usage.completion_tokens),
+        attachments: None,
+    };
+    db.create_chat_message(&reply)?;
+    emit_new_message(&app, &reply);
+
+    let first_reply = !history.iter().any(|m| m.role == "assistant");
+    if first_reply && session.name_source == "initial" && auto_title_enabled(&db, &session) {
+        let session_id = session_id.to_string();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = auto_title(app, &session_id).await {
+                log::warn!("Failed to title session {}: {}", session_id, e);
+            }
+        });
+    }
+
+    Ok(reply)
+}
+
+fn auto_title_enabled(db: &Database, session: &DbChatSession) -> bool {
+    let Some(project_id) = &session.project_id else {
+        return false;
+    };
+    match db.get_project(project_id) {
+        Ok(Some(row)) => project_settings(&row).auto_title,
+        _ => false,
+    }
+}
+
+async fn auto_title(app: AppHandle, session_id: &str) -> Result<DbChatSession> {
+    let db = app.state::<AppState>().db.clone();
+    let session = db.get_chat_session(session_id)?
+        .ok_or_else(|| anyhow!("Chat session not found: {}", session_id))?;
+    if session.name_source == "manual" {
+        return Ok(session);
+    }
+
+    let opening = db.get_chat_messages_page(session_id, 0, DEFAULT_CONTEXT_MESSAGES)?;
+    let question = opening.iter().find(|m| m.role == "user")
+        .ok_or_else(|| anyhow!("Session {} has no messages to title yet", session_id))?;
+    let excerpt = |text: &str| text.chars().take(TITLE_EXCERPT_CHARS).collect::<String>();
+    let mut conversation = format!("User: {}", excerpt(&question.content));
+    if let Some(answer) = opening.iter().find(|m| m.role == "assistant") {
+        conversation.push_str(&format!("\n\nAssistant: {}", excerpt(&answer.content)));
+    }
+
+    let tool_id = project_default_tool(&db, session_id)?;
+    let command = AICommand {
+        id: Uuid::new_v4().to_string(),
+        tool_id: tool_id.clone(),
+        command_type: "chat".to_string(),
+        payload: conversation_payload(&app.state::<AppState>().tools, &tool_id, Some(TITLE_PROMPT), None, &[], &conversation),
+        timestamp: Utc::now(),
+        timeout_ms: None,
+        priority: 0,
+        session_id: Some(session_id.to_string()),
+        swarm_id: None,
+        task_id: None,
+        broadcast_id: None,
+    };
+    let response = ai_tools::send_ai_command(app.clone(), tool_id.clone(), command).await?;
+    if !response.success {
+        return Err(anyhow!(response.error.unwrap_or_else(|| format!("{} reported an error", tool_id))));
+    }
+    let title = sanitize_title(&reply_text(&response.data.unwrap_or_default()))
+        .ok_or_else(|| anyhow!("{} did not suggest a usable title", tool_id))?;
+
+    if db.set_chat_session_auto_title(session_id, &title)? {
+        emit_chat_session_updated(&app, session_id);
+    }
+    db.get_chat_session(session_id)?
+        .ok_or_else(|| anyhow!("Chat session not found: {}", session_id))
+}
+
+/// The first line of a suggested title without a `Title:` label, quotes, markup or closing
+/// period, cut to `MAX_TITLE_CHARS` at a word boundary.
+fn sanitize_title(text: &str) -> Option<String> {
+    let line = text.lines().map(str::trim).find(|line| !line.is_empty())?;
+    let line = line.strip_prefix("Title:").or_else(|| line.strip_prefix("title:")).unwrap_or(line);
+    let line = line.trim_matches(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '`' | '*' | '#' | '“' | '”' | '‘' | '’'));
+    let line = line.trim_end_matches(['.', '!', '?', ':']);
+    let mut title = String::new();
+    for word in line.split_whitespace() {
+        if title.chars().count() + word.chars().count() + 1 > MAX_TITLE_CHARS {
+            break;
+        }
+        if !title.is_empty() {
+            title.push(' ');
+        }
+        title.push_str(word);
+    }
+    if title.is_empty() {
+        // A single word longer than the cap
+        title = line.chars().take(MAX_TITLE_CHARS).collect();
+    }
+    Some(title).filter(|t| !t.is_empty())
+}
+
+fn project_default_tool(db: &Database, session_id: &str) -> Result<String> {
+    let session = db.get_chat_session(session_id)?
+        .ok_or_else(|| anyhow!("Chat session not found: {}", session_id))?;
+    let policy = match &session.project_id {
+        Some(project_id) => ToolPolicy::load(db, project_id)?,
+        None => None,
+    };
+    policy.and_then(|policy| policy.default_tool())
+        .ok_or_else(|| anyhow!("Session {} has no project tool to default to; pass a tool_id", session_id))
+}
+
+/// The session's messages with the snapshots attached to each added to its content, as they
+/// go into a prompt.
+fn history_with_snapshots(db: &Database, session_id: &str) -> Result<Vec<DbChatMessage>> {
+    let mut history = db.get_chat_messages(session_id)?;
+    let attached = db.get_session_attachments(session_id)?;
+    if attached.iter().any(|a| a.snapshot_text.is_some()) {
+        for message in history.iter_mut() {
+            let snapshots: Vec<&DbMessageAttachment> = attached.iter()
+                .filter(|a| a.message_id == message.id)
+                .collect();
+            if !snapshots.is_empty() {
+                message.content = attachments::with_snapshots(&message.content, &snapshots);
+            }
+        }
+    }
+    Ok(history)
+}
+
+fn prompt_preview(state: &AppState, session_id: &str, tool_id: Option<String>, content: String) -> Result<PromptPreview> {
+    let db = &state.db;
+    let session = db.get_chat_session(session_id)?
+        .ok_or_else(|| anyhow!("Chat session not found: {}", session_id))?;
+    let history = history_with_snapshots(db, session_id)?;
+
+    let tool_id = match tool_id {
+        Some(tool_id) => tool_id,
+        None => history.iter().rev()
+            .filter(|m| m.role == "assistant")
+            .find_map(|m| m.metadata.as_ref()?.tool_id.clone())
+            .ok_or_else(|| anyhow!("Session {} has no replies yet; pass a tool_id", session_id))?,
+    };
+
+    Ok(assemble_prompt(state, &session, &history, &tool_id, &content))
+}
+
+/// Builds the payload for `content` with as much history as fits. The system prompt and the new
+/// message are counted against the token budget first.
+fn assemble_prompt(state: &AppState, session: &DbChatSession, history: &[DbChatMessage], tool_id: &str, content: &str) -> PromptPreview {
+    let config = ai_tools::tool_config(state, tool_id).unwrap_or_default();
+    let setting = |key: &str| config.additional_config.get(key).and_then(|v| v.as_i64());
+    let max_messages = setting("context_messages").map_or(DEFAULT_CONTEXT_MESSAGES, |n| n.max(0) as usize);
+    let token_budget = setting("context_token_budget").unwrap_or(DEFAULT_CONTEXT_TOKEN_BUDGET);
+
+    // A session attached to a swarm talks to its queen
+    let agent_type = session.swarm_id.as_ref().map(|_| "queen");
+    let system_prompt = resolve_system_prompt(session.system_prompt.as_deref(), agent_type, config.system_prompt.as_deref());
+    let fixed_tokens = estimate_tokens(content) + system_prompt.as_ref().map_or(0, |p| estimate_tokens(&p.text));
+    let (summary, turns) = select_context(history, max_messages, token_budget - fixed_tokens);
+
+    let start = history.iter().rposition(|m| m.role == SUMMARY_ROLE).map_or(0, |i| i + 1);
+    let estimated_tokens = fixed_tokens
+        + summary.map_or(0, estimate_tokens)
+        + turns.iter().map(|m| estimate_tokens(&m.content)).sum::<i64>();
+
+    PromptPreview {
+        tool_id: tool_id.to_string(),
+        payload: conversation_payload(&state.tools, tool_id, system_prompt.as_ref().map(|p| p.text.as_str()), summary, &turns, content),
+        system_prompt,
+        included_messages: turns.len(),
+        omitted_messages: history.len() - start - turns.len(),
+        summarized: summary.is_some(),
+        estimated_tokens,
+        token_budget,
+    }
+}
+
+/// The session's own prompt wins over the agent role's template, which wins over the tool's default.
+pub(crate) fn resolve_system_prompt(session_prompt: Option<&str>, agent_type: Option<&str>, tool_prompt: Option<&str>) -> Option<SystemPrompt> {
+    let set = |prompt: Option<&str>| prompt.map(str::trim).filter(|p| !p.is_empty()).map(str::to_string);
+    let (source, text) = set(session_prompt).map(|p| ("session", p))
+        .or_else(|| set(agent_type.and_then(swarm::role_system_prompt)).map(|p| ("role", p)))
+        .or_else(|| set(tool_prompt).map(|p| ("tool", p)))?;
+    Some(SystemPrompt { source: source.to_string(), text })
+}
+
+/// Picks the most recent turns that fit both limits, dropping the oldest first. Turns before
+/// the latest stored summary are never sent; the summary goes along instead.
+fn select_context(history: &[DbChatMessage], max_messages: usize, token_budget: i64) -> (Option<&str>, Vec<&DbChatMessage>) {
+    let summary_at = history.iter().rposition(|m| m.role == SUMMARY_ROLE);
+    let summary = summary_at.map(|i| history[i].content.as_str());
+    let mut budget = token_budget - summary.map_or(0, estimate_tokens);
+
+    let start = summary_at.map_or(0, |i| i + 1);
+    let mut turns = Vec::new();
+    for message in history[start..].iter().rev().take(max_messages) {
+        budget -= estimate_tokens(&message.content);
+        if budget < 0 {
+            break;
+        }
+        turns.push(message);
+    }
+    turns.reverse();
+
+    (summary, turns)
+}
+
+/// HTTP backends get structured turns (`messages` for Anthropic and Ollama, `history` plus
+/// `prompt` for Gemini, with the system prompt under `system`); CLIs read one prompt, so the
+/// conversation is written out as a transcript that opens with the system prompt.
+fn conversation_payload(tools: &ToolRegistry, tool_id: &str, system_prompt: Option<&str>, summary: Option<&str>, turns: &[&DbChatMessage], content: &str) -> serde_json::Value {
+    let system: Vec<String> = system_prompt.map(str::to_string)
+        .into_iter()
+        .chain(summary.map(|s| format!("Summary of the earlier conversation:\n{}", s)))
+        .chain(turns.iter().filter(|m| m.role == "system").map(|m| m.content.clone()))
+        .collect();
+    let dialogue: Vec<&&DbChatMessage> = turns.iter().filter(|m| m.role == "user" || m.role == "assistant").collect();
+
+    if ai_tools::is_http_tool(tools, tool_id) {
+        let history: Vec<serde_json::Value> = dialogue.iter()
+            .map(|m| json!({ "role": m.role, "content": m.content }))
+            .collect();
+        let mut messages = history.clone();
+        messages.push(json!({ "role": "user", "content": content }));
+
+        let mut payload = json!({ "prompt": content, "history": history, "messages": messages });
+        if !system.is_empty() {
+            payload["system"] = json!(system.join("\n\n"));
+        }
+        return payload;
+    }
+
+    let mut transcript: Vec<String> = system;
+    transcript.extend(dialogue.iter().map(|m| {
+        let speaker = if m.role == "user" { "User" } else { "Assistant" };
+        format!("{}: {}", speaker, m.content)
+    }));
+    if transcript.is_empty() {
+        return json!({ "prompt": content });
+    }
+    transcript.push(format!("User: {}", content));
+    json!({ "prompt": transcript.join("\n\n") })
+}
+
+// HTTP backends answer in `message`, Claude Code in `result`, other CLIs in `output`
+pub(crate) fn reply_text(data: &serde_json::Value) -> String {
+    ["message", "result", "output", "content"].iter()
+        .find_map(|key| data.get(*key).and_then(|v| v.as_str()))
+        .map(str::to_string)
+        .unwrap_or_else(|| data.to_string())
+}
+
+// Returns the block with the message as updated
+fn apply_code_block(state: &AppState, session_id: &str, message_id: Option<&str>, block_index: usize, target_path: &str, mode: WriteMode) -> Result<(CodeBlock, DbChatMessage)> {
+    let db = &state.db;
+    let target_path = target_path.trim();
+    if target_path.is_empty() {
+        return Err(anyhow!("Target path cannot be empty"));
+    }
+
+    let messages = db.get_chat_messages(session_id)?;
+    let message = match message_id {
+        Some(id) => messages.iter().find(|m| m.id == id)
+            .ok_or_else(|| anyhow!("Message {} not found in session {}", id, session_id))?,
+        None => messages.iter().rev().find(|m| m.role == "assistant")
+            .ok_or_else(|| anyhow!("Session {} has no assistant reply", session_id))?,
+    };
+    let block = extract_code_blocks(&message.content).into_iter()
+        .nth(block_index)
+        .ok_or_else(|| anyhow!("Message {} has no code block {}", message.id, block_index))?;
+
+    // The reply is the AI's, so where it may be written is judged like any file command
+    let project_id = db.get_chat_session(session_id)?.and_then(|session| session.project_id);
+    path_access::check(state, Path::new(target_path), project_id.as_deref())?;
+
+    let mut content = block.content.clone();
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    write_file(db, Path::new(target_path), &content, mode)?;
+
+    let mut metadata = message.metadata.clone().unwrap_or_default();
+    metadata.applied_code_blocks.push(AppliedCodeBlock {
+        block_index,
+        path: target_path.to_string(),
+        mode: format!("{:?}", mode).to_lowercase(),
+        applied_at: Utc::now(),
+    });
+    db.update_chat_message_metadata(&message.id, Some(&metadata))?;
+
+    Ok((block, DbChatMessage { metadata: Some(metadata), ..message.clone() }))
+}
+
+struct Fence {
+    indent: usize,
+    marker: char,
+    len: usize,
+    info: String,
+}
+
+/// A line opening or closing a fenced block: three or more backticks or tildes, indented by
+/// any number of spaces or tabs so fences inside list items count too.
+fn fence(line: &str) -> Option<Fence> {
+    let trimmed = line.trim_start_matches([' ', '\t']);
+    let marker = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = trimmed.chars().take_while(|c| *c == marker).count();
+    let info = trimmed[len..].trim();
+    // A backtick in the info string means inline code such as ```foo```
+    if len < 3 || (marker == '`' && info.contains('`')) {
+        return None;
+    }
+    Some(Fence { indent: line.len() - trimmed.len(), marker, len, info: info.to_string() })
+}
+
+/// Finds fenced code blocks. Content lines lose only the indentation the opening fence had, and
+/// an unclosed block runs to the end of the text. A fence with a shorter marker never closes a
+/// block; inside markdown blocks, fences with a language open nested blocks that must close first.
+pub(crate) fn extract_code_blocks(text: &str) -> Vec<CodeBlock> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut blocks = Vec::new();
+
+    let mut i = 0;
+    while i < lines.len() {
+        let Some(open) = fence(lines[i]) else {
+            i += 1;
+            continue;
+        };
+        let (language, info_path) = parse_info(&open.info);
+        let nests = language.as_deref().is_some_and(|l| MARKDOWN_LANGUAGES.contains(&l.to_ascii_lowercase().as_str()));
+
+        let mut depth = 0;
+        let mut body = Vec::new();
+        let mut end = i + 1;
+        while end < lines.len() {
+            let line = lines[end];
+            if let Some(inner) = fence(line).filter(|f| f.marker == open.marker && f.len >= open.len) {
+                if !inner.info.is_empty() && nests {
+                    depth += 1;
+                } else if inner.info.is_empty() {
+                    if depth == 0 {
+                        break;
+                    }
+                    depth -= 1;
+                }
+            }
+            let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+            body.push(&line[indent.min(open.indent)..]);
+            end += 1;
+        }
+
+        let preceding = lines[..i].iter().rev().find(|l| !l.trim().is_empty()).copied();
+        let path_hint = info_path
+            .or_else(|| body.first().and_then(|l| comment_path(l)))
+            .or_else(|| preceding.and_then(heading_path));
+        blocks.push(CodeBlock {
+            index: blocks.len(),
+            language,
+            path_hint,
+            content: body.join("\n"),
+        });
+        i = end + 1;
+    }
+
+    blocks
+}
+
+/// `rust`, `rust:src/main.rs`, `rust title="src/main.rs"`, `python file=app.py`, ...
+fn parse_info(info: &str) -> (Option<String>, Option<String>) {
+    let mut words = info.split_whitespace();
+    let Some(first) = words.next() else {
+        return (None, None);
+    };
+    let (language, mut path) = match first.split_once(':') {
+        Some((language, path)) => (language, clean_path(path)),
+        None => (first, None),
+    };
+    if path.is_none() {
+        path = words.find_map(|word| {
+            let (key, value) = word.split_once('=')?;
+            ["file", "filename", "path", "title"].contains(&key).then(|| clean_path(value)).flatten()
+        });
+    }
+    let language = language.trim_start_matches('{').trim_start_matches('.');
+    ((!language.is_empty()).then(|| language.to_string()), path)
+}
+
+// `// filename: src/main.rs`, `# file: app.py`, `<!-- path: index.html -->` as the first line
+fn comment_path(line: &str) -> Option<String> {
+    let text = line.trim()
+        .trim_start_matches(['/', '#', '-', ';', '*', '<', '!'])
+        .trim_end_matches(['/', '-', '*', '>'])
+        .trim();
+    labelled_path(text)
+}
+
+// A line right before the fence such as `File: src/main.rs` or `**src/main.rs**`
+fn heading_path(line: &str) -> Option<String> {
+    let text = line.trim()
+        .trim_start_matches(['#', '>', '-'])
+        .trim();
+    if let Some(path) = labelled_path(text.trim_start_matches('*')) {
+        return Some(path);
+    }
+    let text = text.trim_end_matches(':');
+    let decorated = (text.starts_with('`') && text.ends_with('`')) || (text.starts_with("**") && text.ends_with("**"));
+    clean_path(text).filter(|path| decorated && (path.contains('.') || path.contains('/')))
+}
+
+fn labelled_path(text: &str) -> Option<String> {
+    let lower = text.to_ascii_lowercase();
+    let label = PATH_LABELS.iter().find(|label| lower.starts_with(*label))?;
+    clean_path(&text[label.len()..])
+}
+
+fn clean_path(text: &str) -> Option<String> {
+    let path = text.trim()
+        .trim_matches(|c| matches!(c, '`' | '*' | '"' | '\'' | ':'))
+        .trim();
+    (!path.is_empty() && !path.contains(char::is_whitespace)).then(|| path.to_string())
+}
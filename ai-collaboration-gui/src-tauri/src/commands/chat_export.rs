@@ -0,0 +1,253 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use chrono::{DateTime, Utc};
+use anyhow::Result;
+use crate::database::{Database, DbChatMessage, DbChatSession, MessageMetadata};
+use crate::error::{typed_or, AppError};
+use crate::path_access;
+use crate::state::AppState;
+use crate::telemetry::timed;
+use tauri::State;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+    Html,
+}
+
+/// What `export_chat_session` produced: the file it wrote, or the rendered text when no
+/// destination was given.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatExport {
+    pub format: ExportFormat,
+    pub messages: usize,
+    pub path: Option<String>,
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ExportedMessage<'a> {
+    id: &'a str,
+    role: &'a str,
+    content: &'a str,
+    timestamp: DateTime<Utc>,
+    metadata: Option<&'a MessageMetadata>,
+}
+
+const EXPORT_SCHEMA_VERSION: u32 = 1;
+// Messages read from the database at a time, so a long session is never held in memory whole
+const EXPORT_PAGE_SIZE: usize = 200;
+
+/// Renders the session's messages in order, with role labels and timestamps, as Markdown, JSON or
+/// a self-contained HTML page. With `dest_path` the export is streamed to that file; without it
+/// the rendered text comes back in `content`, for a preview or the clipboard.
+///
+/// The JSON export is one object: `schema_version` (1), `exported_at`, `session` (the session
+/// row, including `system_prompt`, `pinned` and `archived`) and `messages`, each with `id`,
+/// `role`, `content`, `timestamp` and `metadata`.
+#[tauri::command]
+#[timed]
+pub async fn export_chat_session(state: State<'_, AppState>, session_id: String, format: ExportFormat, dest_path: Option<String>) -> Result<ChatExport, AppError> {
+    log::info!("Exporting chat session {} as {:?}", session_id, format);
+
+    let session = state.db.get_chat_session(&session_id)?
+        .ok_or_else(|| AppError::Internal(format!("Chat session not found: {}", session_id)))?;
+
+    let Some(dest_path) = dest_path else {
+        let mut out = Vec::new();
+        let messages = render(&state.db, &session, format, &mut out)
+            .map_err(|e| typed_or("Failed to export chat session", e))?;
+        let content = String::from_utf8(out).map_err(|e| AppError::Internal(e.to_string()))?;
+        return Ok(ChatExport { format, messages, path: None, content: Some(content) });
+    };
+
+    let dest = PathBuf::from(&dest_path);
+    path_access::check(&state, &dest, session.project_id.as_deref())?;
+    let file = fs::File::create(&dest)
+        .map_err(|e| AppError::Internal(format!("Failed to create {}: {}", dest_path, e)))?;
+    let mut out = BufWriter::new(file);
+    let written = render(&state.db, &session, format, &mut out).and_then(|messages| {
+        out.flush()?;
+        Ok(messages)
+    });
+    let messages = match written {
+        Ok(messages) => messages,
+        Err(e) => {
+            drop(out);
+            let _ = fs::remove_file(&dest);
+            return Err(typed_or(&format!("Failed to write {}", dest_path), e));
+        }
+    };
+
+    Ok(ChatExport { format, messages, path: Some(dest_path), content: None })
+}
+
+/// Writes the whole export to `out` a page of messages at a time and returns how many there were.
+fn render(db: &Database, session: &DbChatSession, format: ExportFormat, out: &mut impl Write) -> Result<usize> {
+    match format {
+        ExportFormat::Markdown => markdown_header(session, out)?,
+        ExportFormat::Json => json_header(session, out)?,
+        ExportFormat::Html => html_header(session, out)?,
+    }
+
+    let mut count = 0;
+    loop {
+        let page = db.get_chat_messages_page(&session.id, count, EXPORT_PAGE_SIZE)?;
+        for message in &page {
+            match format {
+                ExportFormat::Markdown => markdown_message(message, out)?,
+                ExportFormat::Json => json_message(message, count == 0, out)?,
+                ExportFormat::Html => html_message(message, out)?,
+            }
+            count += 1;
+        }
+        if page.len() < EXPORT_PAGE_SIZE {
+            break;
+        }
+    }
+
+    match format {
+        ExportFormat::Markdown => {}
+        ExportFormat::Json => writeln!(out, "\n]}}")?,
+        ExportFormat::Html => writeln!(out, "</body>\n</html>")?,
+    }
+    Ok(count)
+}
+
+fn role_label(role: &str) -> String {
+    let mut chars = role.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn format_time(time: &DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M UTC").to_string()
+}
+
+fn is_fence(line: &str) -> bool {
+    line.trim_start().starts_with("```")
+}
+
+fn markdown_header(session: &DbChatSession, out: &mut impl Write) -> Result<()> {
+    writeln!(out, "# {}\n", session.name)?;
+    writeln!(out, "_Exported {}_\n", format_time(&Utc::now()))?;
+    if let Some(system_prompt) = &session.system_prompt {
+        writeln!(out, "**System prompt**\n\n{}\n", system_prompt)?;
+    }
+    Ok(())
+}
+
+fn markdown_message(message: &DbChatMessage, out: &mut impl Write) -> Result<()> {
+    writeln!(out, "---\n\n**{}** · {}\n", role_label(&message.role), format_time(&message.timestamp))?;
+    writeln!(out, "{}", message.content.trim_end())?;
+    // An unterminated code block would swallow every message after it
+    if message.content.lines().filter(|line| is_fence(line)).count() % 2 == 1 {
+        writeln!(out, "```")?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+fn json_header(session: &DbChatSession, out: &mut impl Write) -> Result<()> {
+    write!(out, "{{\"schema_version\":{},\"exported_at\":", EXPORT_SCHEMA_VERSION)?;
+    serde_json::to_writer(&mut *out, &Utc::now())?;
+    write!(out, ",\"session\":")?;
+    serde_json::to_writer(&mut *out, session)?;
+    write!(out, ",\"messages\":[")?;
+    Ok(())
+}
+
+fn json_message(message: &DbChatMessage, first: bool, out: &mut impl Write) -> Result<()> {
+    let exported = ExportedMessage {
+        id: &message.id,
+        role: &message.role,
+        content: &message.content,
+        timestamp: message.timestamp,
+        metadata: message.metadata.as_ref(),
+    };
+    writeln!(out, "{}", if first { "" } else { "," })?;
+    serde_json::to_writer(&mut *out, &exported)?;
+    Ok(())
+}
+
+const HTML_STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:50rem;margin:2rem auto;padding:0 1rem;color:#222}\
+.message{border-top:1px solid #ddd;padding:.75rem 0}\
+.meta{color:#666;font-size:.85rem;margin-bottom:.5rem}\
+.role{font-weight:600;color:#222}\
+.text{white-space:pre-wrap}\
+pre{background:#f5f5f5;padding:.75rem;overflow-x:auto;border-radius:4px}";
+
+fn html_header(session: &DbChatSession, out: &mut impl Write) -> Result<()> {
+    let title = escape_html(&session.name);
+    writeln!(out, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>{}</title>\n<style>{}</style>\n</head>\n<body>", title, HTML_STYLE)?;
+    writeln!(out, "<h1>{}</h1>\n<p class=\"meta\">Exported {}</p>", title, format_time(&Utc::now()))?;
+    if let Some(system_prompt) = &session.system_prompt {
+        writeln!(out, "<div class=\"message\"><div class=\"meta\"><span class=\"role\">System prompt</span></div>")?;
+        writeln!(out, "<div class=\"text\">{}</div></div>", escape_html(system_prompt))?;
+    }
+    Ok(())
+}
+
+fn html_message(message: &DbChatMessage, out: &mut impl Write) -> Result<()> {
+    writeln!(
+        out,
+        "<div class=\"message\"><div class=\"meta\"><span class=\"role\">{}</span> · {}</div>",
+        escape_html(&role_label(&message.role)),
+        format_time(&message.timestamp)
+    )?;
+    write!(out, "{}", html_content(&message.content))?;
+    writeln!(out, "</div>")?;
+    Ok(())
+}
+
+// Fenced code blocks become <pre> blocks; everything else keeps its line breaks
+fn html_content(content: &str) -> String {
+    let mut html = String::new();
+    let mut block = Vec::new();
+    let mut in_code = false;
+    for line in content.lines() {
+        if is_fence(line) {
+            push_html_block(&mut html, &block, in_code);
+            block.clear();
+            in_code = !in_code;
+        } else {
+            block.push(line);
+        }
+    }
+    push_html_block(&mut html, &block, in_code);
+    html
+}
+
+fn push_html_block(html: &mut String, lines: &[&str], code: bool) {
+    if lines.is_empty() {
+        return;
+    }
+    let text = escape_html(&lines.join("\n"));
+    if code {
+        html.push_str(&format!("<pre><code>{}</code></pre>\n", text));
+    } else {
+        html.push_str(&format!("<div class=\"text\">{}</div>\n", text));
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+use crate::error::AppError;
+use crate::state::AppState;
+use crate::telemetry::timed;
+
+/// Payload of `clipboard:written`, emitted when the app itself puts text on the clipboard.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipboardWritten {
+    pub chars: usize,
+}
+
+const MAX_CLIPBOARD_BYTES: usize = 10 * 1024 * 1024;
+
+#[tauri::command]
+#[timed]
+pub async fn clipboard_write_text(app: AppHandle, text: String) -> Result<(), AppError> {
+    check_size(text.len())?;
+    let chars = text.chars().count();
+    tokio::task::spawn_blocking(move || platform::write_text(text))
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to write the clipboard: {}", e)))??;
+
+    if let Err(e) = app.emit("clipboard:written", ClipboardWritten { chars }) {
+        log::warn!("Failed to emit clipboard:written: {}", e);
+    }
+    Ok(())
+}
+
+/// The clipboard's text, or `None` when it is empty or holds something else, like an image.
+#[tauri::command]
+#[timed]
+pub async fn clipboard_read_text(state: State<'_, AppState>) -> Result<Option<String>, AppError> {
+    let text = tokio::task::spawn_blocking(platform::read_text)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to read the clipboard: {}", e)))??;
+    if let Some(text) = &text {
+        check_size(text.len())?;
+    }
+    Ok(text)
+}
+
+fn check_size(bytes: usize) -> Result<(), AppError> {
+    if bytes > MAX_CLIPBOARD_BYTES {
+        return Err(AppError::Clipboard(format!(
+            "text is too large for the clipboard ({} bytes; the limit is {})",
+            bytes, MAX_CLIPBOARD_BYTES
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+mod platform {
+    use std::sync::Mutex;
+    use arboard::Clipboard;
+    use once_cell::sync::Lazy;
+    use crate::error::AppError;
+
+    // Kept open: on X11 and Wayland the text the app copied is served by this instance,
+    // and would disappear with it
+    static CLIPBOARD: Lazy<Mutex<Option<Clipboard>>> = Lazy::new(|| Mutex::new(None));
+
+    pub fn write_text(text: String) -> Result<(), AppError> {
+        with_clipboard(|clipboard| clipboard.set_text(text)).map_err(clipboard_error)
+    }
+
+    pub fn read_text() -> Result<Option<String>, AppError> {
+        match with_clipboard(|clipboard| clipboard.get_text()) {
+            Ok(text) => Ok(Some(text)),
+            Err(arboard::Error::ContentNotAvailable) => Ok(None),
+            Err(e) => Err(clipboard_error(e)),
+        }
+    }
+
+    fn with_clipboard<T>(action: impl FnOnce(&mut Clipboard) -> Result<T, arboard::Error>) -> Result<T, arboard::Error> {
+        let mut guard = CLIPBOARD.lock().unwrap();
+        let clipboard = match guard.as_mut() {
+            Some(clipboard) => clipboard,
+            None => guard.insert(Clipboard::new()?),
+        };
+        let result = action(clipboard);
+        // Reconnect next time, in case the display connection was lost
+        if result.as_ref().is_err_and(|e| !matches!(e, arboard::Error::ContentNotAvailable)) {
+            *guard = None;
+        }
+        result
+    }
+
+    fn clipboard_error(error: arboard::Error) -> AppError {
+        AppError::Clipboard(format!("{}: {}", backend(), error))
+    }
+
+    /// The clipboard implementation in use, named in errors since each fails in its own ways.
+    fn backend() -> &'static str {
+        if cfg!(target_os = "macos") {
+            "macOS pasteboard"
+        } else if cfg!(windows) {
+            "Windows clipboard"
+        } else if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            // arboard uses the data-control protocol when the compositor has it, X11 otherwise
+            "Wayland clipboard (or X11 through XWayland)"
+        } else {
+            "X11 clipboard"
+        }
+    }
+}
+
+#[cfg(any(target_os = "android", target_os = "ios"))]
+mod platform {
+    use crate::error::AppError;
+
+    pub fn write_text(_text: String) -> Result<(), AppError> {
+        Err(AppError::Clipboard("this platform has no clipboard access".to_string()))
+    }
+
+    pub fn read_text() -> Result<Option<String>, AppError> {
+        Err(AppError::Clipboard("this platform has no clipboard access".to_string()))
+    }
+}
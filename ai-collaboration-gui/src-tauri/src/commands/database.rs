@@ -1,21 +1,28 @@
 use crate::database::*;
-use tauri::command;
+use crate::error::{typed_or, AppError};
+use crate::events::{Action, DataChanged, Entity};
+use crate::commands::attachments::mark_stale;
+use crate::commands::pricing::fill_session_token_totals;
+use crate::commands::swarm::emit_swarm_updated;
+use crate::secrets;
+use tauri::{command, AppHandle, Emitter, Manager, State};
 use serde::{Deserialize, Serialize};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
+use crate::state::AppState;
+use crate::telemetry::timed;
+use std::path::PathBuf;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ProjectCreateRequest {
-    pub name: String,
-    pub path: String,
-    pub description: Option<String>,
-}
+const DATABASE_FILE: &str = "ai_collaboration.db";
+const MAX_BACKUPS: usize = 5;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ChatSessionCreateRequest {
     pub name: String,
     pub project_id: Option<String>,
     pub swarm_id: Option<String>,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -23,7 +30,8 @@ pub struct ChatMessageCreateRequest {
     pub session_id: String,
     pub role: String,
     pub content: String,
-    pub metadata: Option<String>,
+    #[serde(default)]
+    pub metadata: Option<MessageMetadata>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -34,6 +42,15 @@ pub struct SwarmCreateRequest {
     pub config: String,
 }
 
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AIInteractionFilter {
+    pub tool_id: Option<String>,
+    pub session_id: Option<String>,
+    pub swarm_id: Option<String>,
+    pub broadcast_id: Option<String>,
+    pub since: Option<chrono::DateTime<Utc>>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AIToolConfigRequest {
     pub tool_name: String,
@@ -41,74 +58,224 @@ pub struct AIToolConfigRequest {
     pub is_connected: bool,
 }
 
-// 프로젝트 관련 명령어들
+const MESSAGE_ROLES: [&str; 4] = ["user", "assistant", "system", "tool"];
+
+/// Refuses a message that would be stored but never shown: one with an unknown role, with no
+/// content (only tool results may be empty), or for a session that is missing or archived.
+fn check_message_request(request: &ChatMessageCreateRequest, session: Option<&DbChatSession>) -> Result<(), AppError> {
+    if !MESSAGE_ROLES.contains(&request.role.as_str()) {
+        return Err(AppError::Validation(format!(
+            "Unknown message role {:?}; expected one of {}",
+            request.role,
+            MESSAGE_ROLES.join(", ")
+        )));
+    }
+    if request.content.trim().is_empty() && request.role != "tool" {
+        return Err(AppError::Validation("Message content cannot be empty".to_string()));
+    }
+    let session = session
+        .ok_or_else(|| AppError::NotFound(format!("Chat session not found: {}", request.session_id)))?;
+    if session.archived {
+        return Err(AppError::Validation("The chat session is archived; unarchive it first".to_string()));
+    }
+    Ok(())
+}
+
+/// The project and swarm a new session links to, if any, must exist.
+fn check_session_links(
+    request: &ChatSessionCreateRequest,
+    project_exists: impl Fn(&str) -> Result<bool, AppError>,
+    swarm_exists: impl Fn(&str) -> Result<bool, AppError>,
+) -> Result<(), AppError> {
+    if let Some(project_id) = &request.project_id {
+        if !project_exists(project_id)? {
+            return Err(AppError::NotFound(format!("Project not found: {}", project_id)));
+        }
+    }
+    if let Some(swarm_id) = &request.swarm_id {
+        if !swarm_exists(swarm_id)? {
+            return Err(AppError::NotFound(format!("Swarm not found: {}", swarm_id)));
+        }
+    }
+    Ok(())
+}
+
+// 채팅 세션 관련 명령어들
 #[command]
-pub async fn db_create_project(request: ProjectCreateRequest) -> Result<String, String> {
+#[timed]
+pub async fn db_create_chat_session(app: AppHandle, state: State<'_, AppState>, request: ChatSessionCreateRequest) -> Result<String, AppError> {
+    check_session_links(
+        &request,
+        |id| Ok(state.db.get_project(id)?.is_some()),
+        |id| Ok(state.db.get_swarm_by_id(id)?.is_some()),
+    )?;
     let now = Utc::now();
-    let project = DbProject {
+    let session = DbChatSession {
         id: Uuid::new_v4().to_string(),
         name: request.name,
-        path: request.path,
-        description: request.description,
+        project_id: request.project_id,
+        swarm_id: request.swarm_id,
+        system_prompt: request.system_prompt.filter(|p| !p.trim().is_empty()),
+        pinned: false,
+        archived: false,
+        name_source: "initial".to_string(),
+        last_read_message_at: None,
+        unread_count: 0,
         created_at: now,
         updated_at: now,
+        total_tokens: None,
+        estimated_cost: None,
     };
 
-    create_project(&project)
-        .map_err(|e| format!("Failed to create project: {}", e))?;
+    state.db.create_chat_session(&session)
+        .map_err(|e| typed_or("Failed to create chat session", e))?;
+    DataChanged::new(Entity::ChatSession, Action::Created, &session.id)
+        .in_project(session.project_id.as_deref())
+        .with_data(&session)
+        .emit(&app);
 
-    Ok(project.id)
+    Ok(session.id)
 }
 
+/// Archived sessions are left out unless `include_archived` is set; `pinned_first` sorts
+/// pinned sessions to the top. With `include_token_totals` each session carries its token
+/// total and estimated cost.
 #[command]
-pub async fn db_get_all_projects() -> Result<Vec<DbProject>, String> {
-    get_all_projects()
-        .map_err(|e| format!("Failed to get projects: {}", e))
+#[timed]
+pub async fn db_get_chat_sessions(
+    state: State<'_, AppState>,
+    project_id: Option<String>,
+    include_archived: Option<bool>,
+    pinned_first: Option<bool>,
+    include_token_totals: Option<bool>,
+) -> Result<Vec<DbChatSession>, String> {
+    let mut sessions = state.db.get_chat_sessions_by_project(project_id.as_deref(), include_archived.unwrap_or(false), pinned_first.unwrap_or(true))
+        .map_err(|e| format!("Failed to get chat sessions: {}", e))?;
+    if include_token_totals.unwrap_or(false) {
+        fill_session_token_totals(&state.db, &mut sessions)
+            .map_err(|e| format!("Failed to get session token totals: {}", e))?;
+    }
+    Ok(sessions)
 }
 
 #[command]
-pub async fn db_update_project(project: DbProject) -> Result<(), String> {
-    let mut updated_project = project;
-    updated_project.updated_at = Utc::now();
-    
-    update_project(&updated_project)
-        .map_err(|e| format!("Failed to update project: {}", e))
+#[timed]
+pub async fn db_set_chat_session_system_prompt(app: AppHandle, state: State<'_, AppState>, session_id: String, system_prompt: Option<String>) -> Result<(), String> {
+    let system_prompt = system_prompt.filter(|p| !p.trim().is_empty());
+    state.db.update_chat_session_system_prompt(&session_id, system_prompt.as_deref())
+        .map_err(|e| format!("Failed to set system prompt: {}", e))?;
+    emit_chat_session_updated(&app, &session_id);
+    Ok(())
 }
 
+/// Renames the session. A name given here is kept; automatic titles won't replace it.
 #[command]
-pub async fn db_delete_project(project_id: String) -> Result<(), String> {
-    delete_project(&project_id)
-        .map_err(|e| format!("Failed to delete project: {}", e))
+#[timed]
+pub async fn db_rename_chat_session(app: AppHandle, state: State<'_, AppState>, session_id: String, name: String) -> Result<(), AppError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::Validation("Session name cannot be empty".to_string()));
+    }
+    state.db.rename_chat_session(&session_id, name)
+        .map_err(|e| typed_or("Failed to rename chat session", e))?;
+    emit_chat_session_updated(&app, &session_id);
+    Ok(())
 }
 
-// 채팅 세션 관련 명령어들
 #[command]
-pub async fn db_create_chat_session(request: ChatSessionCreateRequest) -> Result<String, String> {
-    let now = Utc::now();
-    let session = DbChatSession {
-        id: Uuid::new_v4().to_string(),
-        name: request.name,
-        project_id: request.project_id,
-        swarm_id: request.swarm_id,
-        created_at: now,
-        updated_at: now,
-    };
-
-    create_chat_session(&session)
-        .map_err(|e| format!("Failed to create chat session: {}", e))?;
+#[timed]
+pub async fn db_set_chat_session_pinned(app: AppHandle, state: State<'_, AppState>, session_id: String, pinned: bool) -> Result<(), String> {
+    state.db.set_chat_session_pinned(&session_id, pinned)
+        .map_err(|e| format!("Failed to pin chat session: {}", e))?;
+    emit_chat_session_updated(&app, &session_id);
+    Ok(())
+}
 
-    Ok(session.id)
+#[command]
+#[timed]
+pub async fn db_archive_chat_session(app: AppHandle, state: State<'_, AppState>, session_id: String, archived: bool) -> Result<(), String> {
+    state.db.set_chat_session_archived(&session_id, archived)
+        .map_err(|e| format!("Failed to archive chat session: {}", e))?;
+    emit_chat_session_updated(&app, &session_id);
+    Ok(())
 }
 
+/// Marks the session read up to `up_to`, or up to its latest message, and returns how many
+/// messages are still unread.
 #[command]
-pub async fn db_get_chat_sessions(project_id: Option<String>) -> Result<Vec<DbChatSession>, String> {
-    get_chat_sessions_by_project(project_id.as_deref())
-        .map_err(|e| format!("Failed to get chat sessions: {}", e))
+#[timed]
+pub async fn mark_session_read(app: AppHandle, state: State<'_, AppState>, session_id: String, up_to: Option<DateTime<Utc>>) -> Result<i64, AppError> {
+    let unread = state.db.mark_chat_session_read(&session_id, up_to)
+        .map_err(|e| typed_or("Failed to mark chat session read", e))?;
+    emit_chat_session_updated(&app, &session_id);
+    Ok(unread)
+}
+
+/// Payload of `chat:new-message`, sent for each stored message the user didn't write.
+#[derive(Debug, Clone, Serialize)]
+pub struct NewMessageEvent {
+    pub session_id: String,
+    pub message_id: String,
+    pub role: String,
+    pub unread_count: i64,
+}
+
+/// Announces a stored message: `data:changed` for every message, `chat:new-message` for those
+/// the user didn't write.
+pub(crate) fn emit_new_message(app: &AppHandle, message: &DbChatMessage) {
+    let db = app.state::<AppState>().db.clone();
+    let project_id = db.get_chat_session(&message.session_id).ok().flatten().and_then(|s| s.project_id);
+    DataChanged::new(Entity::ChatMessage, Action::Created, &message.id)
+        .in_project(project_id.as_deref())
+        .with_data(message)
+        .emit(app);
+    if message.role == "user" {
+        return;
+    }
+    let unread_count = match db.get_chat_session_unread_count(&message.session_id) {
+        Ok(count) => count,
+        Err(e) => {
+            log::warn!("Failed to count unread messages in {}: {}", message.session_id, e);
+            return;
+        }
+    };
+    let event = NewMessageEvent {
+        session_id: message.session_id.clone(),
+        message_id: message.id.clone(),
+        role: message.role.clone(),
+        unread_count,
+    };
+    if let Err(e) = app.emit("chat:new-message", event) {
+        log::warn!("Failed to emit chat:new-message: {}", e);
+    }
+}
+
+/// Sends the session as it now stands, so other windows can update their lists.
+pub(crate) fn emit_chat_session_updated(app: &AppHandle, session_id: &str) {
+    let db = app.state::<AppState>().db.clone();
+    let session = match db.get_chat_session(session_id) {
+        Ok(Some(session)) => session,
+        Ok(None) => return,
+        Err(e) => {
+            log::warn!("Failed to load chat session {}: {}", session_id, e);
+            return;
+        }
+    };
+    if let Err(e) = app.emit("chat_session:updated", &session) {
+        log::warn!("Failed to emit chat_session:updated: {}", e);
+    }
+    DataChanged::new(Entity::ChatSession, Action::Updated, &session.id)
+        .in_project(session.project_id.as_deref())
+        .with_data(&session)
+        .emit(app);
 }
 
 // 채팅 메시지 관련 명령어들
 #[command]
-pub async fn db_create_chat_message(request: ChatMessageCreateRequest) -> Result<String, String> {
+#[timed]
+pub async fn db_create_chat_message(app: AppHandle, state: State<'_, AppState>, request: ChatMessageCreateRequest) -> Result<String, AppError> {
+    let session = state.db.get_chat_session(&request.session_id)?;
+    check_message_request(&request, session.as_ref())?;
     let message = DbChatMessage {
         id: Uuid::new_v4().to_string(),
         session_id: request.session_id,
@@ -116,23 +283,47 @@ pub async fn db_create_chat_message(request: ChatMessageCreateRequest) -> Result
         content: request.content,
         metadata: request.metadata,
         timestamp: Utc::now(),
+        prompt_tokens: None,
+        completion_tokens: None,
+        attachments: None,
     };
 
-    create_chat_message(&message)
-        .map_err(|e| format!("Failed to create chat message: {}", e))?;
+    state.db.create_chat_message(&message)
+        .map_err(|e| typed_or("Failed to create chat message", e))?;
+    emit_new_message(&app, &message);
 
     Ok(message.id)
 }
 
+/// With `include_attachments` each message carries the files attached to it, flagged `stale`
+/// where the file has changed since.
 #[command]
-pub async fn db_get_chat_messages(session_id: String) -> Result<Vec<DbChatMessage>, String> {
-    get_chat_messages(&session_id)
-        .map_err(|e| format!("Failed to get chat messages: {}", e))
+#[timed]
+pub async fn db_get_chat_messages(state: State<'_, AppState>, session_id: String, include_attachments: Option<bool>) -> Result<Vec<DbChatMessage>, String> {
+    let mut messages = state.db.get_chat_messages(&session_id)
+        .map_err(|e| format!("Failed to get chat messages: {}", e))?;
+    if !include_attachments.unwrap_or(false) {
+        return Ok(messages);
+    }
+
+    let mut attachments = state.db.get_session_attachments(&session_id)
+        .map_err(|e| format!("Failed to get message attachments: {}", e))?;
+    let attachments = tokio::task::spawn_blocking(move || {
+        mark_stale(&mut attachments);
+        attachments
+    })
+    .await
+    .map_err(|e| format!("Failed to check message attachments: {}", e))?;
+    for message in messages.iter_mut() {
+        message.attachments = Some(attachments.iter().filter(|a| a.message_id == message.id).cloned().collect());
+    }
+    Ok(messages)
 }
 
 // 스웜 관련 명령어들
 #[command]
-pub async fn db_create_swarm(request: SwarmCreateRequest) -> Result<String, String> {
+#[timed]
+pub async fn db_create_swarm(app: AppHandle, state: State<'_, AppState>, request: SwarmCreateRequest) -> Result<String, AppError> {
     let now = Utc::now();
     let swarm = DbSwarm {
         id: Uuid::new_v4().to_string(),
@@ -145,81 +336,213 @@ pub async fn db_create_swarm(request: SwarmCreateRequest) -> Result<String, Stri
         updated_at: now,
     };
 
-    create_swarm(&swarm)
-        .map_err(|e| format!("Failed to create swarm: {}", e))?;
+    state.db.create_swarm(&swarm)
+        .map_err(|e| typed_or("Failed to create swarm", e))?;
+    DataChanged::new(Entity::Swarm, Action::Created, &swarm.id)
+        .in_project(Some(&swarm.project_id))
+        .with_data(&swarm)
+        .emit(&app);
 
     Ok(swarm.id)
 }
 
 #[command]
-pub async fn db_get_swarms(project_id: String) -> Result<Vec<DbSwarm>, String> {
-    get_swarms_by_project(&project_id)
+#[timed]
+pub async fn db_get_swarms(state: State<'_, AppState>, project_id: String) -> Result<Vec<DbSwarm>, String> {
+    state.db.get_swarms_by_project(&project_id)
         .map_err(|e| format!("Failed to get swarms: {}", e))
 }
 
 #[command]
-pub async fn db_update_swarm_status(swarm_id: String, status: String) -> Result<(), String> {
-    // 먼저 스웜을 조회한 후 상태 업데이트
-    // 실제 구현에서는 더 효율적인 UPDATE 쿼리 사용
+#[timed]
+pub async fn db_update_swarm_status(app: AppHandle, state: State<'_, AppState>, swarm_id: String, status: String) -> Result<(), String> {
     log::info!("Updating swarm {} status to {}", swarm_id, status);
+    
+    state.db.update_swarm_status(&swarm_id, &status)
+        .map_err(|e| format!("Failed to update swarm status: {}", e))?;
+    emit_swarm_updated(&app, &state.db, &swarm_id);
+    Ok(())
+}
+
+#[command]
+#[timed]
+pub async fn db_set_swarm_pinned(app: AppHandle, state: State<'_, AppState>, swarm_id: String, pinned: bool) -> Result<(), String> {
+    state.db.set_swarm_pinned(&swarm_id, pinned)
+        .map_err(|e| format!("Failed to pin swarm: {}", e))?;
+    emit_swarm_updated(&app, &state.db, &swarm_id);
+    Ok(())
+}
+
+/// Deletes a swarm that isn't running, along with its workspaces.
+#[command]
+#[timed]
+pub async fn db_delete_swarm(app: AppHandle, state: State<'_, AppState>, swarm_id: String) -> Result<(), String> {
+    log::info!("Deleting swarm {}", swarm_id);
+    
+    let swarm = state.db.get_swarm_by_id(&swarm_id)
+        .map_err(|e| format!("Failed to get swarm: {}", e))?
+        .ok_or_else(|| format!("Swarm not found: {}", swarm_id))?;
+    if swarm.status == "running" {
+        return Err("Stop the swarm before deleting it".to_string());
+    }
+    
+    state.db.delete_swarm(&swarm_id)
+        .map_err(|e| format!("Failed to delete swarm: {}", e))?;
+    DataChanged::new(Entity::Swarm, Action::Deleted, &swarm_id)
+        .in_project(Some(&swarm.project_id))
+        .emit(&app);
+    if let Err(e) = crate::commands::cleanup_workspaces(state.clone(), Some(swarm_id), None).await {
+        log::warn!("Swarm deleted, but its workspaces remain: {}", e);
+    }
     Ok(())
 }
 
 // AI 도구 설정 관련 명령어들
 #[command]
-pub async fn db_save_ai_tool_config(request: AIToolConfigRequest) -> Result<String, String> {
+#[timed]
+pub async fn db_save_ai_tool_config(app: AppHandle, state: State<'_, AppState>, request: AIToolConfigRequest) -> Result<String, String> {
+    // The API key goes to the keychain; only a placeholder is written to the database
+    let mut blob: serde_json::Value = serde_json::from_str(&request.config)
+        .map_err(|e| format!("Invalid AI tool config: {}", e))?;
+    secrets::extract_api_key(&request.tool_name, &mut blob)
+        .map_err(|e| format!("Failed to save AI tool config: {}", e))?;
+    
     let now = Utc::now();
     let config = DbAIToolConfig {
         id: Uuid::new_v4().to_string(),
+        tool_id: None, // linked to the ai_tools row of the same type on save
         tool_name: request.tool_name,
-        config: request.config,
+        config: blob.to_string(),
         is_connected: request.is_connected,
+        has_api_key: false,
         created_at: now,
         updated_at: now,
     };
 
-    save_ai_tool_config(&config)
+    state.db.save_ai_tool_config(&config)
         .map_err(|e| format!("Failed to save AI tool config: {}", e))?;
+    DataChanged::new(Entity::AiTool, Action::Updated, &config.id)
+        .with_data(&serde_json::json!({ "tool_name": config.tool_name, "is_connected": config.is_connected }))
+        .emit(&app);
 
     Ok(config.id)
 }
 
 #[command]
-pub async fn db_get_ai_tool_configs() -> Result<Vec<DbAIToolConfig>, String> {
-    get_ai_tool_configs()
-        .map_err(|e| format!("Failed to get AI tool configs: {}", e))
+#[timed]
+pub async fn db_get_ai_tool_configs(state: State<'_, AppState>) -> Result<Vec<DbAIToolConfig>, String> {
+    let mut configs = state.db.get_ai_tool_configs()
+        .map_err(|e| format!("Failed to get AI tool configs: {}", e))?;
+    
+    // Keys never go back to the frontend, only whether one is set
+    for config in &mut configs {
+        if let Ok(mut blob) = serde_json::from_str::<serde_json::Value>(&config.config) {
+            config.has_api_key = secrets::strip_api_key(&mut blob);
+            config.config = blob.to_string();
+        }
+    }
+    
+    Ok(configs)
+}
+
+// AI 명령 기록 관련 명령어들
+#[command]
+#[timed]
+pub async fn db_get_ai_interactions(state: State<'_, AppState>, filter: Option<AIInteractionFilter>, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<DbAIInteraction>, String> {
+    let filter = filter.unwrap_or_default();
+    
+    state.db.get_ai_interactions(
+        filter.tool_id.as_deref(),
+        filter.session_id.as_deref(),
+        filter.swarm_id.as_deref(),
+        filter.broadcast_id.as_deref(),
+        filter.since,
+        limit.unwrap_or(100).clamp(1, 1000),
+        offset.unwrap_or(0).max(0),
+    )
+    .map_err(|e| format!("Failed to get AI interactions: {}", e))
+}
+
+#[command]
+#[timed]
+pub async fn db_purge_ai_interactions(state: State<'_, AppState>, older_than_days: i64) -> Result<usize, String> {
+    log::info!("Purging AI interactions older than {} days", older_than_days);
+    
+    if older_than_days < 0 {
+        return Err("older_than_days cannot be negative".to_string());
+    }
+    
+    state.db.purge_ai_interactions(Utc::now() - chrono::Duration::days(older_than_days))
+        .map_err(|e| format!("Failed to purge AI interactions: {}", e))
 }
 
 // 데이터베이스 초기화 명령어
+/// Kept for the frontend's startup call; the database is opened in setup, before any command runs.
 #[command]
-pub async fn db_initialize() -> Result<(), String> {
+#[timed]
+pub async fn db_initialize(state: State<'_, AppState>) -> Result<(), String> {
+    Ok(())
+}
+
+/// Where `open_app_database` keeps the database.
+pub fn database_path(app: &AppHandle) -> tauri::Result<PathBuf> {
+    Ok(app.path().app_data_dir()?.join(DATABASE_FILE))
+}
+
+/// Opens the database in the app data directory; called from the setup hook.
+pub fn open_app_database(app: &AppHandle) -> Result<Database, String> {
     // 애플리케이션 데이터 디렉토리에 데이터베이스 파일 생성
-    let app_data_dir = tauri::api::path::app_data_dir(&tauri::Config::default())
-        .ok_or("Failed to get app data directory")?;
+    let app_data_dir = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
     
     // 디렉토리가 없으면 생성
     std::fs::create_dir_all(&app_data_dir)
         .map_err(|e| format!("Failed to create app data directory: {}", e))?;
 
-    let db_path = app_data_dir.join("ai_collaboration.db");
+    let db_path = app_data_dir.join(DATABASE_FILE);
     
-    initialize_database(&db_path)
-        .map_err(|e| format!("Failed to initialize database: {}", e))?;
+    Database::open(&db_path)
+        .map_err(|e| format!("Failed to initialize database: {}", e))
+}
 
-    log::info!("Database initialized at: {:?}", db_path);
-    Ok(())
+/// Copies the database into `backups/` under the app data directory, keeping the newest
+/// `MAX_BACKUPS` copies. Run by the scheduler each `db_backup_interval_hours`.
+pub async fn backup_database(app: &AppHandle) -> anyhow::Result<PathBuf> {
+    let dir = app.path().app_data_dir()?.join("backups");
+    let db = app.state::<AppState>().db.clone();
+    tokio::task::spawn_blocking(move || {
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("ai_collaboration-{}.db", Utc::now().format("%Y%m%d-%H%M%S")));
+        db.backup_to(&path)?;
+
+        // The timestamped names sort oldest first
+        let mut backups: Vec<PathBuf> = std::fs::read_dir(&dir)?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with("ai_collaboration-") && n.ends_with(".db")))
+            .collect();
+        backups.sort();
+        for old in &backups[..backups.len().saturating_sub(MAX_BACKUPS)] {
+            if let Err(e) = std::fs::remove_file(old) {
+                log::warn!("Failed to remove old backup {}: {}", old.display(), e);
+            }
+        }
+        Ok(path)
+    })
+    .await?
 }
 
 // 데이터베이스 통계 조회
 #[command]
-pub async fn db_get_statistics() -> Result<DatabaseStatistics, String> {
-    let projects = get_all_projects()
+#[timed]
+pub async fn db_get_statistics(state: State<'_, AppState>) -> Result<DatabaseStatistics, String> {
+    let projects = state.db.get_all_projects(ProjectOrder::default(), true)
         .map_err(|e| format!("Failed to get projects: {}", e))?;
     
-    let chat_sessions = get_chat_sessions_by_project(None)
+    let chat_sessions = state.db.get_chat_sessions_by_project(None, true, false)
         .map_err(|e| format!("Failed to get chat sessions: {}", e))?;
     
-    let ai_configs = get_ai_tool_configs()
+    let ai_configs = state.db.get_ai_tool_configs()
         .map_err(|e| format!("Failed to get AI tool configs: {}", e))?;
 
     Ok(DatabaseStatistics {
@@ -236,4 +559,108 @@ pub struct DatabaseStatistics {
     pub total_chat_sessions: usize,
     pub total_ai_tools: usize,
     pub connected_ai_tools: usize,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: &str) -> ChatMessageCreateRequest {
+        ChatMessageCreateRequest {
+            session_id: "session-1".to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            metadata: None,
+        }
+    }
+
+    fn session(archived: bool) -> DbChatSession {
+        DbChatSession {
+            id: "session-1".to_string(),
+            name: "Session".to_string(),
+            project_id: None,
+            swarm_id: None,
+            system_prompt: None,
+            pinned: false,
+            archived,
+            name_source: "initial".to_string(),
+            last_read_message_at: None,
+            unread_count: 0,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            total_tokens: None,
+            estimated_cost: None,
+        }
+    }
+
+    fn session_request(project_id: Option<&str>, swarm_id: Option<&str>) -> ChatSessionCreateRequest {
+        ChatSessionCreateRequest {
+            name: "Session".to_string(),
+            project_id: project_id.map(str::to_string),
+            swarm_id: swarm_id.map(str::to_string),
+            system_prompt: None,
+        }
+    }
+
+    #[test]
+    fn accepts_a_message_for_an_open_session() {
+        assert!(check_message_request(&message("user", "hello"), Some(&session(false))).is_ok());
+    }
+
+    #[test]
+    fn rejects_an_unknown_role() {
+        let result = check_message_request(&message("assistannt", "hello"), Some(&session(false)));
+        assert!(matches!(result, Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn rejects_empty_content_except_for_tool_results() {
+        let result = check_message_request(&message("user", "  \n"), Some(&session(false)));
+        assert!(matches!(result, Err(AppError::Validation(_))));
+        assert!(check_message_request(&message("tool", ""), Some(&session(false))).is_ok());
+    }
+
+    #[test]
+    fn rejects_a_missing_session() {
+        let result = check_message_request(&message("user", "hello"), None);
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+
+    #[test]
+    fn rejects_an_archived_session() {
+        let result = check_message_request(&message("user", "hello"), Some(&session(true)));
+        assert!(matches!(result, Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn rejects_a_session_for_a_missing_project_or_swarm() {
+        let result = check_session_links(&session_request(Some("gone"), None), |_| Ok(false), |_| Ok(true));
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+        let result = check_session_links(&session_request(Some("p"), Some("gone")), |_| Ok(true), |_| Ok(false));
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+        assert!(check_session_links(&session_request(None, None), |_| Ok(false), |_| Ok(false)).is_ok());
+    }
+
+    #[test]
+    fn counts_replies_until_the_session_is_read() {
+        let state = AppState::new(Database::open_in_memory().unwrap());
+        state.db.create_chat_session(&session(false)).unwrap();
+        for role in ["user", "assistant", "assistant"] {
+            state.db.create_chat_message(&DbChatMessage {
+                id: Uuid::new_v4().to_string(),
+                session_id: "session-1".to_string(),
+                role: role.to_string(),
+                content: "hello".to_string(),
+                metadata: None,
+                timestamp: Utc::now(),
+                prompt_tokens: None,
+                completion_tokens: None,
+                attachments: None,
+            }).unwrap();
+        }
+
+        assert_eq!(state.db.get_chat_session_unread_count("session-1").unwrap(), 2);
+        state.db.mark_chat_session_read("session-1", None).unwrap();
+        assert_eq!(state.db.get_chat_session_unread_count("session-1").unwrap(), 0);
+    }
+}
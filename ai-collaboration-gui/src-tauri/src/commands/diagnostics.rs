@@ -0,0 +1,200 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use chrono::Utc;
+use tauri::{AppHandle, Manager, State};
+use crate::commands::env_vars::app_env_secrets;
+use crate::commands::{app_info, workspaces_root};
+use crate::database::Database;
+use crate::error::AppError;
+use crate::logging;
+use crate::path_access;
+use crate::providers::proxy;
+use crate::secrets;
+use crate::state::AppState;
+use crate::telemetry::timed;
+
+// Swarm events included per active swarm
+const SWARM_EVENT_LIMIT: i64 = 50;
+const ACTIVE_SWARM_STATUSES: [&str; 3] = ["initializing", "running", "paused"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticsExport {
+    pub path: String,
+    // Size of the zip; None on a dry run, when nothing is written
+    pub size_bytes: Option<u64>,
+    pub dry_run: bool,
+    pub files: Vec<DiagnosticsFile>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticsFile {
+    pub name: String,
+    pub description: String,
+    // Uncompressed, after redaction
+    pub size_bytes: u64,
+}
+
+struct Entry {
+    file: DiagnosticsFile,
+    content: Vec<u8>,
+}
+
+/// Zips what a bug report needs into `dest_path`: app and OS details, the data paths, the app
+/// log, command timings, database statistics, the AI tools without their keys, background jobs
+/// and the latest events of active swarms. Every file has known secrets redacted. With
+/// `dry_run` nothing is written and only the list of files is returned, for review first.
+#[tauri::command]
+#[timed]
+pub async fn export_diagnostics(app: AppHandle, state: State<'_, AppState>, dest_path: String, dry_run: Option<bool>) -> Result<DiagnosticsExport, AppError> {
+    let dry_run = dry_run.unwrap_or(false);
+    log::info!("Exporting diagnostics to {}{}", dest_path, if dry_run { " (dry run)" } else { "" });
+
+    let dest = PathBuf::from(&dest_path);
+    path_access::check(&state, &dest, None)?;
+    if dest.is_dir() {
+        return Err(AppError::Validation(format!("{} is a directory", dest_path)));
+    }
+
+    let entries = collect(&app, &state)?;
+    let files = entries.iter().map(|entry| entry.file.clone()).collect();
+    if dry_run {
+        return Ok(DiagnosticsExport { path: dest_path, size_bytes: None, dry_run, files });
+    }
+
+    let size = tokio::task::spawn_blocking(move || write_zip(&dest, &entries))
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to write diagnostics: {}", e)))?
+        .map_err(|e| AppError::Internal(format!("Failed to write diagnostics: {}", e)))?;
+    Ok(DiagnosticsExport { path: dest_path, size_bytes: Some(size), dry_run, files })
+}
+
+fn collect(app: &AppHandle, state: &AppState) -> Result<Vec<Entry>, AppError> {
+    let db = &state.db;
+    let known_secrets = known_secrets(state);
+    let mut entries = Vec::new();
+    let mut add = |name: String, description: &str, text: String| {
+        let content = secrets::redact(&text, known_secrets.iter().map(String::as_str)).into_bytes();
+        entries.push(Entry {
+            file: DiagnosticsFile { name, description: description.to_string(), size_bytes: content.len() as u64 },
+            content,
+        });
+    };
+
+    add("app.json".to_string(), "App version, build, operating system and data paths", to_json(&app_info(app, db)));
+    add("paths.json".to_string(), "Where the app keeps its other data", to_json(&data_paths(app)));
+    match logging::files() {
+        Ok(files) => {
+            for file in files {
+                let name = file.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                match fs::read(&file) {
+                    Ok(content) => add(format!("logs/{}", name), "App log", String::from_utf8_lossy(&content).into_owned()),
+                    Err(e) => log::warn!("Leaving {} out of the diagnostics: {}", file.display(), e),
+                }
+            }
+        }
+        Err(e) => log::warn!("Leaving the app log out of the diagnostics: {}", e),
+    }
+    add("command_metrics.json".to_string(), "Timings of the app's commands since startup", to_json(&state.telemetry.command_metrics()));
+    add("database.json".to_string(), "Schema version and row counts of the database", to_json(&database_info(db)?));
+    add("tools.json".to_string(), "AI tools and stored tool configs, without API keys", to_json(&tools(db)?));
+    add("jobs.json".to_string(), "Background jobs with their last and next runs", to_json(&state.jobs.list()));
+    for swarm in db.get_all_swarms()?.into_iter().filter(|s| ACTIVE_SWARM_STATUSES.contains(&s.status.as_str())) {
+        let events = db.get_latest_swarm_events(&swarm.id, SWARM_EVENT_LIMIT)?;
+        add(format!("swarms/{}.json", swarm.id), "Latest events of an active swarm", to_json(&serde_json::json!({
+            "id": swarm.id,
+            "status": swarm.status,
+            "events": events,
+        })));
+    }
+    Ok(entries)
+}
+
+// What `app_info` leaves out
+fn data_paths(app: &AppHandle) -> Value {
+    let paths = app.path();
+    serde_json::json!({
+        "app_cache": paths.app_cache_dir().ok(),
+        "backups": paths.app_data_dir().ok().map(|dir| dir.join("backups")),
+        "workspaces": workspaces_root(),
+        "generated_at": Utc::now(),
+    })
+}
+
+fn database_info(db: &Database) -> Result<Value, AppError> {
+    let tables: serde_json::Map<String, Value> = db.table_row_counts()?
+        .into_iter()
+        .map(|(table, rows)| (table, rows.into()))
+        .collect();
+    Ok(serde_json::json!({
+        "schema_version": db.schema_version()?,
+        "row_counts": tables,
+    }))
+}
+
+fn tools(db: &Database) -> Result<Value, AppError> {
+    let tools: Vec<Value> = db.get_ai_tools()?.iter().map(without_api_key).collect();
+    let configs: Vec<Value> = db.get_ai_tool_configs()?.iter().map(without_api_key).collect();
+    Ok(serde_json::json!({ "tools": tools, "configs": configs }))
+}
+
+// The row with its JSON `config` parsed and the key fields taken out
+fn without_api_key<T: Serialize>(row: &T) -> Value {
+    let mut row = serde_json::to_value(row).unwrap_or(Value::Null);
+    if let Some(config) = row.get("config").and_then(Value::as_str) {
+        let mut config = serde_json::from_str(config).unwrap_or(Value::Null);
+        secrets::strip_api_key(&mut config);
+        row["config"] = config;
+    }
+    row
+}
+
+// Every secret the app knows of: API keys in the keychain, secret environment variables and
+// the proxy password
+fn known_secrets(state: &AppState) -> Vec<String> {
+    let db = &state.db;
+    let mut names: Vec<String> = Vec::new();
+    if let Ok(tools) = db.get_ai_tools() {
+        names.extend(tools.into_iter().flat_map(|tool| [tool.id, tool.tool_type]));
+    }
+    if let Ok(configs) = db.get_ai_tool_configs() {
+        names.extend(configs.into_iter().map(|config| config.tool_name));
+    }
+    names.sort();
+    names.dedup();
+
+    let mut known: Vec<String> = names.iter()
+        .filter_map(|name| secrets::load_api_key(name).ok().flatten())
+        .collect();
+    known.extend(app_env_secrets(state));
+    if let Some(password) = proxy::global_settings().and_then(|settings| proxy::password(&settings).ok().flatten()) {
+        known.push(password);
+    }
+    known
+}
+
+fn to_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+fn write_zip(dest: &Path, entries: &[Entry]) -> anyhow::Result<u64> {
+    let file = fs::File::create(dest)?;
+    let mut zip = zip::ZipWriter::new(std::io::BufWriter::new(file));
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    let written = (|| -> anyhow::Result<()> {
+        for entry in entries {
+            zip.start_file(entry.file.name.as_str(), options)?;
+            zip.write_all(&entry.content)?;
+        }
+        zip.finish()?;
+        Ok(())
+    })();
+    if let Err(e) = written {
+        let _ = fs::remove_file(dest);
+        return Err(e);
+    }
+    Ok(fs::metadata(dest)?.len())
+}
@@ -0,0 +1,227 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use similar::{ChangeTag, DiffTag, TextDiff};
+use crate::commands::system::read_text;
+use crate::error::AppError;
+use crate::path_access;
+use crate::state::AppState;
+use crate::telemetry::timed;
+use tauri::State;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DiffOptions {
+    // Lines differing only in whitespace count as equal
+    #[serde(default)]
+    pub ignore_whitespace: bool,
+    // Mark the changed words within each changed line
+    #[serde(default)]
+    pub word_diff: bool,
+    #[serde(default)]
+    pub context_lines: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffLineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffSegment {
+    pub text: String,
+    pub changed: bool,
+}
+
+/// Line numbers are 1-based; a removed line has no `new_line` and an added one no `old_line`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    pub old_line: Option<usize>,
+    pub new_line: Option<usize>,
+    pub text: String,
+    // The line split into changed and unchanged words, with word_diff
+    pub segments: Option<Vec<DiffSegment>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextDiffResult {
+    pub hunks: Vec<DiffHunk>,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub identical: bool,
+}
+
+// Per side; beyond this the diff would stall the UI
+const MAX_DIFF_BYTES: usize = 4 * 1024 * 1024;
+const DEFAULT_CONTEXT_LINES: usize = 3;
+// Past the deadline the diff is still correct, just not minimal
+const DIFF_DEADLINE: Duration = Duration::from_secs(2);
+
+/// Diffs two text files line by line. Binary files and files over 4 MB are refused.
+#[tauri::command]
+#[timed]
+pub async fn diff_paths(state: State<'_, AppState>, path_a: String, path_b: String, options: Option<DiffOptions>, project_id: Option<String>) -> Result<TextDiffResult, AppError> {
+    log::info!("Diffing {} against {}", path_a, path_b);
+
+    let mut texts = Vec::new();
+    for path in [&path_a, &path_b] {
+        let path = Path::new(path);
+        path_access::check(&state, path, project_id.as_deref())?;
+        let content = read_text(path, MAX_DIFF_BYTES as u64)?;
+        if content.truncated {
+            return Err(too_large(content.size as usize));
+        }
+        texts.push(content.content);
+    }
+
+    let options = options.unwrap_or_default();
+    tokio::task::spawn_blocking(move || diff_lines(&texts[0], &texts[1], &options))
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to diff: {}", e)))
+}
+
+#[tauri::command]
+#[timed]
+pub async fn diff_text(state: State<'_, AppState>, old: String, new: String, options: Option<DiffOptions>) -> Result<TextDiffResult, AppError> {
+    for text in [&old, &new] {
+        if text.len() > MAX_DIFF_BYTES {
+            return Err(too_large(text.len()));
+        }
+    }
+
+    let options = options.unwrap_or_default();
+    tokio::task::spawn_blocking(move || diff_lines(&old, &new, &options))
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to diff: {}", e)))
+}
+
+fn too_large(size: usize) -> AppError {
+    AppError::Internal(format!("Input is too large to diff ({} bytes; the limit is {})", size, MAX_DIFF_BYTES))
+}
+
+/// Lines are compared by a key (the line itself, or with whitespace stripped) but reported
+/// with their original text, so ignoring whitespace still shows the lines as they are.
+fn diff_lines(old: &str, new: &str, options: &DiffOptions) -> TextDiffResult {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let key = |line: &&str| -> String {
+        if options.ignore_whitespace {
+            line.split_whitespace().collect()
+        } else {
+            line.to_string()
+        }
+    };
+    let old_keys: Vec<String> = old_lines.iter().map(key).collect();
+    let new_keys: Vec<String> = new_lines.iter().map(key).collect();
+
+    let deadline = Instant::now() + DIFF_DEADLINE;
+    let ops = similar::capture_diff_slices_deadline(similar::Algorithm::Myers, &old_keys, &new_keys, Some(deadline));
+    let groups = similar::group_diff_ops(ops, options.context_lines.unwrap_or(DEFAULT_CONTEXT_LINES));
+
+    let mut result = TextDiffResult {
+        hunks: Vec::new(),
+        insertions: 0,
+        deletions: 0,
+        identical: groups.is_empty(),
+    };
+    for group in groups {
+        let (Some(first), Some(last)) = (group.first(), group.last()) else {
+            continue;
+        };
+        let (old_start, new_start) = (first.old_range().start, first.new_range().start);
+        let mut hunk = DiffHunk {
+            old_start: old_start + 1,
+            old_lines: last.old_range().end - old_start,
+            new_start: new_start + 1,
+            new_lines: last.new_range().end - new_start,
+            lines: Vec::new(),
+        };
+
+        for op in &group {
+            let (tag, old_range, new_range) = op.as_tag_tuple();
+            if tag == DiffTag::Equal {
+                for (old_index, new_index) in old_range.zip(new_range) {
+                    hunk.lines.push(DiffLine {
+                        kind: DiffLineKind::Context,
+                        old_line: Some(old_index + 1),
+                        new_line: Some(new_index + 1),
+                        text: new_lines[new_index].to_string(),
+                        segments: None,
+                    });
+                }
+                continue;
+            }
+
+            result.deletions += old_range.len();
+            result.insertions += new_range.len();
+            // Replaced lines are paired up in order for the word diff
+            let pairs = if options.word_diff && tag == DiffTag::Replace {
+                old_range.len().min(new_range.len())
+            } else {
+                0
+            };
+            let word_diffs: Vec<(Vec<DiffSegment>, Vec<DiffSegment>)> = (0..pairs)
+                .map(|i| diff_words(old_lines[old_range.start + i], new_lines[new_range.start + i], deadline))
+                .collect();
+
+            for (i, old_index) in old_range.enumerate() {
+                hunk.lines.push(DiffLine {
+                    kind: DiffLineKind::Removed,
+                    old_line: Some(old_index + 1),
+                    new_line: None,
+                    text: old_lines[old_index].to_string(),
+                    segments: word_diffs.get(i).map(|(old, _)| old.clone()),
+                });
+            }
+            for (i, new_index) in new_range.enumerate() {
+                hunk.lines.push(DiffLine {
+                    kind: DiffLineKind::Added,
+                    old_line: None,
+                    new_line: Some(new_index + 1),
+                    text: new_lines[new_index].to_string(),
+                    segments: word_diffs.get(i).map(|(_, new)| new.clone()),
+                });
+            }
+        }
+        result.hunks.push(hunk);
+    }
+    result
+}
+
+/// The old and new line split into segments, the changed words marked.
+fn diff_words(old: &str, new: &str, deadline: Instant) -> (Vec<DiffSegment>, Vec<DiffSegment>) {
+    let diff = TextDiff::configure().deadline(deadline).diff_words(old, new);
+    let (mut old_segments, mut new_segments) = (Vec::new(), Vec::new());
+    for change in diff.iter_all_changes() {
+        let (side, changed): (&mut Vec<DiffSegment>, bool) = match change.tag() {
+            ChangeTag::Equal => {
+                push_segment(&mut old_segments, change.value(), false);
+                (&mut new_segments, false)
+            }
+            ChangeTag::Delete => (&mut old_segments, true),
+            ChangeTag::Insert => (&mut new_segments, true),
+        };
+        push_segment(side, change.value(), changed);
+    }
+    (old_segments, new_segments)
+}
+
+// Runs of the same kind are merged so the frontend gets few, long segments
+fn push_segment(segments: &mut Vec<DiffSegment>, text: &str, changed: bool) {
+    match segments.last_mut() {
+        Some(last) if last.changed == changed => last.text.push_str(text),
+        _ => segments.push(DiffSegment { text: text.to_string(), changed }),
+    }
+}
@@ -0,0 +1,226 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::Utc;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+use crate::commands::project::{emit_project_changed, read_marker, update_error, write_marker, Project, ProjectSettings};
+use crate::commands::project_stats::language_name;
+use crate::commands::search::SearchGuard;
+use crate::commands::get_project_by_id;
+use crate::database::{DbProject, ProjectOrder};
+use crate::error::AppError;
+use crate::events::Action;
+use crate::state::AppState;
+use crate::telemetry::timed;
+use tauri::{AppHandle, State};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscoveredProject {
+    pub name: String,
+    pub path: String,
+    // The most common language among the files near the top of the directory
+    pub language: Option<String>,
+    // What made it look like a project: 'git' | 'package.json' | 'Cargo.toml' | 'marker'
+    pub indicators: Vec<String>,
+    pub registered: bool,
+}
+
+const DEFAULT_DISCOVERY_DEPTH: usize = 4;
+// Files looked at to guess a candidate's language
+const LANGUAGE_SAMPLE_FILES: usize = 200;
+const LANGUAGE_SAMPLE_DEPTH: usize = 3;
+
+/// Looks under `root_path` for directories that look like projects: a git repository, a
+/// `package.json` or `Cargo.toml`, or a project marker. Ignored and hidden directories are
+/// skipped, and so is everything inside a project found, so a home directory scans quickly.
+/// `cancel_search` with the `operation_id` stops the scan.
+#[tauri::command]
+#[timed]
+pub async fn discover_projects(state: State<'_, AppState>, root_path: String, max_depth: Option<usize>, operation_id: Option<String>) -> Result<Vec<DiscoveredProject>, AppError> {
+    log::info!("Discovering projects under {}", root_path);
+
+    let root = PathBuf::from(&root_path);
+    if !root.is_dir() {
+        return Err(AppError::Internal("Path does not exist or is not a directory".to_string()));
+    }
+    let registered: HashSet<String> = state.db.get_all_projects(ProjectOrder::default(), true)?
+        .into_iter()
+        .map(|project| project.path)
+        .collect();
+
+    let operation = SearchGuard::start(&state.searches, operation_id);
+    let cancel = operation.cancel.clone();
+    let max_depth = max_depth.unwrap_or(DEFAULT_DISCOVERY_DEPTH);
+    tokio::task::spawn_blocking(move || discover(&root, max_depth, &registered, &cancel))
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to discover projects: {}", e)))?
+}
+
+/// Registers the directories at `paths` as projects, all of them or none. A directory carrying
+/// the marker of a project that isn't registered any more gets that project's id back.
+#[tauri::command]
+#[timed]
+pub async fn import_discovered_projects(app: AppHandle, state: State<'_, AppState>, paths: Vec<String>) -> Result<Vec<Project>, AppError> {
+    log::info!("Importing {} projects", paths.len());
+
+    let existing = state.db.get_all_projects(ProjectOrder::default(), true)?;
+    let settings = serde_json::to_string(&ProjectSettings::default())
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    let now = Utc::now();
+    let mut rows: Vec<DbProject> = Vec::new();
+    for path in paths {
+        if let Some(owner) = existing.iter().find(|project| project.path == path) {
+            return Err(AppError::Conflict {
+                message: format!("{} already belongs to the project {}", path, owner.name),
+                current_hash: None,
+            });
+        }
+        if rows.iter().any(|row| row.path == path) {
+            continue;
+        }
+        let dir = Path::new(&path);
+        if !dir.is_dir() {
+            return Err(AppError::Internal(format!("{} does not exist or is not a directory", path)));
+        }
+        let id = read_marker(dir)
+            .map(|marker| marker.project_id)
+            .filter(|id| !existing.iter().chain(&rows).any(|project| &project.id == id))
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        rows.push(DbProject {
+            id,
+            name: project_name(dir),
+            path,
+            description: None,
+            settings: Some(settings.clone()),
+            created_at: now,
+            updated_at: now,
+            last_opened_at: None,
+            archived: false,
+        });
+    }
+
+    state.db.create_projects(&rows)
+        .map_err(|e| update_error(e, "one of the imported paths"))?;
+
+    let mut projects = Vec::with_capacity(rows.len());
+    for row in rows {
+        // The projects are registered either way; the marker only helps find them after a move
+        if let Err(e) = write_marker(&row) {
+            log::warn!("Failed to write the project marker in {}: {}", row.path, e);
+        }
+        projects.extend(get_project_by_id(state.clone(), row.id).await.map_err(AppError::Internal)?);
+    }
+    for project in &projects {
+        emit_project_changed(&app, Action::Created, project);
+    }
+    Ok(projects)
+}
+
+fn discover(root: &Path, max_depth: usize, registered: &HashSet<String>, cancel: &CancellationToken) -> Result<Vec<DiscoveredProject>, AppError> {
+    let walker = ignore::WalkBuilder::new(root)
+        .require_git(false)
+        .max_depth(Some(max_depth))
+        .filter_entry(|entry| {
+            // Nothing inside a project is searched, though the root itself may be one
+            entry.file_type().is_some_and(|t| t.is_dir())
+                && (entry.depth() < 2 || entry.path().parent().is_none_or(|parent| indicators(parent).is_empty()))
+        })
+        .build();
+
+    let mut found = Vec::new();
+    for entry in walker {
+        if cancel.is_cancelled() {
+            return Err(AppError::Cancelled("project discovery was cancelled".to_string()));
+        }
+        let Ok(entry) = entry else {
+            continue;
+        };
+        let dir = entry.path();
+        let indicators = indicators(dir);
+        if indicators.is_empty() {
+            continue;
+        }
+        let path = dir.to_string_lossy().to_string();
+        found.push(DiscoveredProject {
+            name: project_name(dir),
+            language: primary_language(dir),
+            indicators: indicators.into_iter().map(str::to_string).collect(),
+            registered: registered.contains(&path),
+            path,
+        });
+    }
+    Ok(found)
+}
+
+fn indicators(dir: &Path) -> Vec<&'static str> {
+    let mut found = Vec::new();
+    if dir.join(".git").exists() {
+        found.push("git");
+    }
+    for manifest in ["package.json", "Cargo.toml"] {
+        if dir.join(manifest).is_file() {
+            found.push(manifest);
+        }
+    }
+    if read_marker(dir).is_some() {
+        found.push("marker");
+    }
+    found
+}
+
+/// The name from the project marker or manifest, or else the directory's own.
+fn project_name(dir: &Path) -> String {
+    read_marker(dir)
+        .map(|marker| marker.name)
+        .or_else(|| {
+            let manifest: serde_json::Value = serde_json::from_str(&fs::read_to_string(dir.join("package.json")).ok()?).ok()?;
+            Some(manifest.get("name")?.as_str()?.to_string())
+        })
+        .or_else(|| cargo_package_name(&fs::read_to_string(dir.join("Cargo.toml")).ok()?))
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| {
+            dir.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| dir.to_string_lossy().to_string())
+        })
+}
+
+/// `name` from the `[package]` table; a workspace manifest has none.
+fn cargo_package_name(manifest: &str) -> Option<String> {
+    let mut in_package = false;
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+        } else if in_package {
+            if let Some(value) = line.strip_prefix("name").map(str::trim_start).and_then(|rest| rest.strip_prefix('=')) {
+                return Some(value.trim().trim_matches(['"', '\'']).to_string());
+            }
+        }
+    }
+    None
+}
+
+fn primary_language(dir: &Path) -> Option<String> {
+    let mut counts: HashMap<&'static str, usize> = HashMap::new();
+    let walker = ignore::WalkBuilder::new(dir)
+        .require_git(false)
+        .max_depth(Some(LANGUAGE_SAMPLE_DEPTH))
+        .build();
+    let files = walker.flatten()
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .take(LANGUAGE_SAMPLE_FILES);
+    for entry in files {
+        let extension = entry.path().extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        // Data and docs say little about what a project is written in
+        if let Some(language) = language_name(&extension).filter(|l| !matches!(*l, "JSON" | "Markdown" | "YAML" | "TOML")) {
+            *counts.entry(language).or_default() += 1;
+        }
+    }
+    counts.into_iter()
+        .max_by_key(|(language, count)| (*count, std::cmp::Reverse(*language)))
+        .map(|(language, _)| language.to_string())
+}
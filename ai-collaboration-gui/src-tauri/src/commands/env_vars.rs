@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use chrono::{DateTime, Utc};
+use crate::database::DbEnvVar;
+use crate::secrets;
+use crate::state::AppState;
+use crate::telemetry::timed;
+use tauri::State;
+
+/// A user-defined variable as the frontend sees it; secret values come masked.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppEnvVar {
+    pub key: String,
+    pub value: String,
+    pub secret: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+struct ResolvedVar {
+    key: String,
+    value: String,
+    secret: bool,
+}
+
+// Checked by default by get_environment_variables
+const DEFAULT_CHECKED_VARS: [&str; 7] = ["ANTHROPIC_API_KEY", "OPENAI_API_KEY", "GOOGLE_API_KEY", "PATH", "HOME", "USER", "SHELL"];
+// Values of variables whose names contain one of these are masked
+const SENSITIVE_NAME_PARTS: [&str; 5] = ["KEY", "TOKEN", "SECRET", "PASSWORD", "CREDENTIAL"];
+
+/// The variables with secrets read from the keychain, loaded on first use so spawning
+/// processes doesn't go to the keychain each time.
+#[derive(Default)]
+pub struct ResolvedEnv(Mutex<Option<Vec<ResolvedVar>>>);
+
+/// Sets a variable for every process the app spawns from now on. A `secret` value is kept in
+/// the keychain rather than the database.
+#[tauri::command]
+#[timed]
+pub async fn set_app_env_var(state: State<'_, AppState>, key: String, value: String, secret: bool) -> Result<(), String> {
+    log::info!("Setting environment variable {}", key);
+    
+    validate_env_name(&key)?;
+    let was_secret = state.db.get_env_vars()
+        .map_err(|e| format!("Failed to load environment variables: {}", e))?
+        .iter()
+        .any(|var| var.key == key && var.secret);
+    
+    let stored = if secret {
+        secrets::store_env_secret(&key, &value).map_err(|e| e.to_string())?;
+        secrets::API_KEY_PLACEHOLDER.to_string()
+    } else {
+        if was_secret {
+            secrets::delete_env_secret(&key).map_err(|e| e.to_string())?;
+        }
+        value
+    };
+    state.db.save_env_var(&DbEnvVar { key, value: stored, secret, updated_at: Utc::now() })
+        .map_err(|e| format!("Failed to save environment variable: {}", e))?;
+    
+    *state.resolved_env.0.lock().unwrap() = None;
+    Ok(())
+}
+
+#[tauri::command]
+#[timed]
+pub async fn delete_app_env_var(state: State<'_, AppState>, key: String) -> Result<(), String> {
+    log::info!("Deleting environment variable {}", key);
+    
+    secrets::delete_env_secret(&key).map_err(|e| e.to_string())?;
+    state.db.delete_env_var(&key)
+        .map_err(|e| format!("Failed to delete environment variable: {}", e))?;
+    
+    *state.resolved_env.0.lock().unwrap() = None;
+    Ok(())
+}
+
+#[tauri::command]
+#[timed]
+pub async fn list_app_env_vars(state: State<'_, AppState>) -> Result<Vec<AppEnvVar>, String> {
+    let vars = state.db.get_env_vars()
+        .map_err(|e| format!("Failed to load environment variables: {}", e))?;
+    
+    let resolved = state.resolved_env.0.lock().unwrap();
+    Ok(vars.into_iter()
+        .map(|var| {
+            let value = if var.secret {
+                // Masked from the keychain value when it was already loaded; never loaded just for this
+                resolved.as_ref()
+                    .and_then(|vars| vars.iter().find(|v| v.key == var.key))
+                    .map(|v| secrets::mask_secret(&v.value))
+                    .unwrap_or_else(|| "***".to_string())
+            } else {
+                var.value
+            };
+            AppEnvVar { key: var.key, value, secret: var.secret, updated_at: var.updated_at }
+        })
+        .collect())
+}
+
+/// Values of the given variables in the app's own environment, or of a default set of
+/// AI-tool-related ones. Values of names that look sensitive are masked.
+#[tauri::command]
+#[timed]
+pub async fn get_environment_variables(state: State<'_, AppState>, names: Option<Vec<String>>) -> Result<serde_json::Value, String> {
+    log::info!("Getting environment variables");
+    
+    let names = names.unwrap_or_else(|| DEFAULT_CHECKED_VARS.iter().map(|name| name.to_string()).collect());
+    let mut env_vars = serde_json::Map::new();
+    for name in names {
+        if let Ok(value) = std::env::var(&name) {
+            let upper = name.to_ascii_uppercase();
+            let value = if SENSITIVE_NAME_PARTS.iter().any(|part| upper.contains(part)) {
+                secrets::mask_secret(&value)
+            } else {
+                value
+            };
+            env_vars.insert(name, serde_json::Value::String(value));
+        }
+    }
+    
+    Ok(serde_json::Value::Object(env_vars))
+}
+
+pub(crate) fn validate_env_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.contains(['=', '\0']) {
+        return Err(format!("Invalid environment variable name: {:?}", name));
+    }
+    Ok(())
+}
+
+/// The user-defined variables to set on a spawned process, over what it inherits. A secret
+/// the keychain can't provide is left out with a warning rather than failing the spawn.
+pub(crate) fn app_env(state: &AppState) -> Vec<(String, String)> {
+    with_resolved(state, |vars| vars.iter().map(|var| (var.key.clone(), var.value.clone())).collect())
+}
+
+/// The secret values among `app_env`, for redacting logs.
+pub(crate) fn app_env_secrets(state: &AppState) -> Vec<String> {
+    with_resolved(state, |vars| vars.iter().filter(|var| var.secret).map(|var| var.value.clone()).collect())
+}
+
+fn with_resolved<T>(state: &AppState, read: impl FnOnce(&[ResolvedVar]) -> T) -> T {
+    let mut resolved = state.resolved_env.0.lock().unwrap();
+    let vars = resolved.get_or_insert_with(|| match state.db.get_env_vars() {
+        Ok(vars) => vars.into_iter().filter_map(resolve).collect(),
+        Err(e) => {
+            log::warn!("Failed to load environment variables: {}", e);
+            Vec::new()
+        }
+    });
+    read(vars)
+}
+
+fn resolve(var: DbEnvVar) -> Option<ResolvedVar> {
+    if !var.secret {
+        return Some(ResolvedVar { key: var.key, value: var.value, secret: false });
+    }
+    match secrets::load_env_secret(&var.key) {
+        Ok(Some(value)) => Some(ResolvedVar { key: var.key, value, secret: true }),
+        Ok(None) => {
+            log::warn!("{} is missing from the keychain; not setting it", var.key);
+            None
+        }
+        Err(e) => {
+            log::warn!("{}; not setting {}", e, var.key);
+            None
+        }
+    }
+}
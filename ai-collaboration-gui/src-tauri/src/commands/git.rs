@@ -0,0 +1,457 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use crate::error::AppError;
+use crate::state::AppState;
+use crate::telemetry::timed;
+use tauri::State;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GitFileStatus {
+    Modified,
+    Added,
+    Deleted,
+    Renamed,
+    Untracked,
+    Conflicted,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GitStatus {
+    pub is_repo: bool,
+    // None on a detached HEAD
+    pub branch: Option<String>,
+    pub upstream: Option<String>,
+    pub ahead: u32,
+    pub behind: u32,
+    // Paths relative to the project; untracked directories end in `/`
+    pub files: HashMap<String, GitFileStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitFileDiff {
+    pub path: String,
+    // Unified diff; empty for binary files and unchanged ones
+    pub diff: String,
+    pub binary: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitDiffStat {
+    pub path: String,
+    // Set for renames
+    pub old_path: Option<String>,
+    // None for binary files, which git doesn't count lines of
+    pub insertions: Option<u32>,
+    pub deletions: Option<u32>,
+    pub binary: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitCommit {
+    pub hash: String,
+    pub author: String,
+    pub email: String,
+    pub date: DateTime<Utc>,
+    pub subject: String,
+}
+
+const DEFAULT_LOG_LIMIT: usize = 50;
+
+// Catches edits made outside the app; the file commands invalidate entries straight away
+const CACHE_TTL: Duration = Duration::from_secs(2);
+
+static STATUS_CACHE: Lazy<Mutex<HashMap<PathBuf, (Instant, GitStatus)>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Branch, ahead/behind counts and changed files of the repository `project_path` is in, from a
+/// single `git status` run. A project that isn't in a repository gets `is_repo: false`.
+#[tauri::command]
+#[timed]
+pub async fn git_status(state: State<'_, AppState>, project_path: String) -> Result<GitStatus, AppError> {
+    let path = PathBuf::from(&project_path);
+    if let Some((taken, status)) = STATUS_CACHE.lock().unwrap().get(&path) {
+        if taken.elapsed() < CACHE_TTL {
+            return Ok(status.clone());
+        }
+    }
+
+    log::info!("Reading git status of {}", project_path);
+    let status = read_status(&path).await?;
+
+    STATUS_CACHE.lock().unwrap().insert(path, (Instant::now(), status.clone()));
+    Ok(status)
+}
+
+/// The changes to one file in the working tree, or with `staged` those in the index.
+#[tauri::command]
+#[timed]
+pub async fn git_diff_file(state: State<'_, AppState>, project_path: String, file_path: String, staged: bool) -> Result<GitFileDiff, AppError> {
+    log::info!("Diffing {} in {}", file_path, project_path);
+
+    let mut args = vec!["diff", "--no-color", "--no-ext-diff"];
+    if staged {
+        args.push("--cached");
+    }
+    args.extend(["--", file_path.as_str()]);
+    let diff = String::from_utf8_lossy(&git_in_repo(Path::new(&project_path), &args).await?).to_string();
+
+    // Git prints "Binary files a/x and b/x differ" instead of a patch
+    let binary = diff.lines().any(|line| line.starts_with("Binary files ") && line.ends_with(" differ"));
+    Ok(GitFileDiff {
+        path: file_path,
+        diff: if binary { String::new() } else { diff },
+        binary,
+    })
+}
+
+/// Lines added and removed per file in the project since the last commit, staged or not. In a
+/// repository without commits, the unstaged changes.
+#[tauri::command]
+#[timed]
+pub async fn git_diff_summary(state: State<'_, AppState>, project_path: String) -> Result<Vec<GitDiffStat>, AppError> {
+    log::info!("Summarizing changes in {}", project_path);
+
+    let path = Path::new(&project_path);
+    let mut args = vec!["diff", "--numstat", "-z", "--relative", "--no-ext-diff"];
+    if has_head(path).await? {
+        args.push("HEAD");
+    }
+    let output = git_in_repo(path, &args).await?;
+    Ok(parse_numstat(&String::from_utf8_lossy(&output)))
+}
+
+/// The latest commits touching `file_path`, newest first, following it across renames.
+#[tauri::command]
+#[timed]
+pub async fn git_file_log(state: State<'_, AppState>, project_path: String, file_path: String, limit: Option<usize>) -> Result<Vec<GitCommit>, AppError> {
+    log::info!("Reading history of {} in {}", file_path, project_path);
+
+    let limit = format!("--max-count={}", limit.unwrap_or(DEFAULT_LOG_LIMIT));
+    let args = ["log", "--follow", "-z", limit.as_str(), "--format=%H%x1f%an%x1f%ae%x1f%aI%x1f%s", "--", file_path.as_str()];
+    let output = run_git(Path::new(&project_path), &args).await?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // A new repository has no history to show
+        if stderr.contains("does not have any commits") {
+            return Ok(Vec::new());
+        }
+        return Err(git_error(&args, &stderr));
+    }
+
+    let output = String::from_utf8_lossy(&output.stdout);
+    let commits = output.split('\0')
+        .filter_map(|record| {
+            let fields: Vec<&str> = record.trim_start_matches('\n').splitn(5, '\x1f').collect();
+            let [hash, author, email, date, subject] = fields[..] else {
+                return None;
+            };
+            Some(GitCommit {
+                hash: hash.to_string(),
+                author: author.to_string(),
+                email: email.to_string(),
+                date: DateTime::parse_from_rfc3339(date).ok()?.with_timezone(&Utc),
+                subject: subject.to_string(),
+            })
+        })
+        .collect();
+    Ok(commits)
+}
+
+/// Stages `paths` (relative to the project) as they are in the working tree, deletions included.
+#[tauri::command]
+#[timed]
+pub async fn git_stage(state: State<'_, AppState>, project_path: String, paths: Vec<String>) -> Result<(), AppError> {
+    log::info!("Staging {} path(s) in {}", paths.len(), project_path);
+
+    let path = Path::new(&project_path);
+    let mut args = vec!["add", "--all", "--"];
+    args.extend(paths.iter().map(String::as_str));
+    git_in_repo(path, &args).await?;
+    invalidate_status(path);
+    Ok(())
+}
+
+#[tauri::command]
+#[timed]
+pub async fn git_unstage(state: State<'_, AppState>, project_path: String, paths: Vec<String>) -> Result<(), AppError> {
+    log::info!("Unstaging {} path(s) in {}", paths.len(), project_path);
+
+    let path = Path::new(&project_path);
+    // Before the first commit there is no HEAD to reset to, so entries leave the index instead
+    let mut args = if has_head(path).await? {
+        vec!["reset", "--quiet", "HEAD", "--"]
+    } else {
+        vec!["rm", "--cached", "-r", "--quiet", "--ignore-unmatch", "--"]
+    };
+    args.extend(paths.iter().map(String::as_str));
+    git_in_repo(path, &args).await?;
+    invalidate_status(path);
+    Ok(())
+}
+
+/// Commits what is staged and returns the new commit's hash. `author_override` takes the
+/// `Name <email>` form. A failing hook fails the commit with the hook's output as the message.
+#[tauri::command]
+#[timed]
+pub async fn git_commit(state: State<'_, AppState>, project_path: String, message: String, author_override: Option<String>) -> Result<String, AppError> {
+    log::info!("Committing in {}", project_path);
+
+    if message.trim().is_empty() {
+        return Err(AppError::Git("commit message cannot be empty".to_string()));
+    }
+    let path = Path::new(&project_path);
+    // Exits 1 when something is staged; works before the first commit too
+    let staged = run_git(path, &["diff", "--cached", "--quiet"]).await?;
+    match staged.status.code() {
+        Some(1) => {}
+        Some(0) => return Err(AppError::Git("nothing is staged to commit".to_string())),
+        _ => return Err(git_error(&["diff"], &String::from_utf8_lossy(&staged.stderr))),
+    }
+
+    let author = author_override.map(|author| format!("--author={}", author.trim()));
+    if author.as_deref().is_some_and(|a| !(a.contains('<') && a.ends_with('>'))) {
+        return Err(AppError::Git("author must look like Name <email>".to_string()));
+    }
+    let mut args = vec!["commit", "--quiet", "--file=-"];
+    args.extend(author.as_deref());
+
+    let output = run_git_with_input(path, &args, &message).await?;
+    invalidate_status(path);
+    if !output.status.success() {
+        // Hooks write to either stream
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(git_error(&args, &format!("{}{}", stdout, stderr)));
+    }
+
+    let hash = git_in_repo(path, &["rev-parse", "HEAD"]).await?;
+    Ok(String::from_utf8_lossy(&hash).trim().to_string())
+}
+
+/// The checked-out branch, also before its first commit; `None` on a detached HEAD.
+#[tauri::command]
+#[timed]
+pub async fn git_current_branch(state: State<'_, AppState>, project_path: String) -> Result<Option<String>, AppError> {
+    let output = run_git(Path::new(&project_path), &["symbolic-ref", "--short", "--quiet", "HEAD"]).await?;
+    match output.status.code() {
+        Some(0) => Ok(Some(String::from_utf8_lossy(&output.stdout).trim().to_string())),
+        // Exit code 1 with no message means HEAD is detached
+        Some(1) if output.stderr.is_empty() => Ok(None),
+        _ => Err(git_error(&["symbolic-ref"], &String::from_utf8_lossy(&output.stderr))),
+    }
+}
+
+/// Creates branch `name` at HEAD, switching to it with `checkout`. Before the first commit a
+/// branch can only be created by switching to it.
+#[tauri::command]
+#[timed]
+pub async fn git_create_branch(state: State<'_, AppState>, project_path: String, name: String, checkout: bool) -> Result<(), AppError> {
+    log::info!("Creating branch {} in {}", name, project_path);
+
+    let path = Path::new(&project_path);
+    let valid = run_git(path, &["check-ref-format", "--branch", name.as_str()]).await?;
+    if !valid.status.success() {
+        return Err(AppError::Git(format!("{:?} is not a valid branch name", name)));
+    }
+
+    if checkout {
+        git_in_repo(path, &["checkout", "--quiet", "-b", name.as_str()]).await?;
+        invalidate_status(path);
+    } else if has_head(path).await? {
+        git_in_repo(path, &["branch", "--", name.as_str()]).await?;
+    } else {
+        return Err(AppError::Git("a branch can't be created before the first commit without checking it out".to_string()));
+    }
+    Ok(())
+}
+
+/// Runs `git init` in `dir`; in an existing repository this changes nothing that matters.
+pub(crate) async fn init_repository(dir: &Path) -> Result<(), AppError> {
+    let output = run_git(dir, &["init"]).await?;
+    if !output.status.success() {
+        return Err(git_error(&["init"], &String::from_utf8_lossy(&output.stderr)));
+    }
+    invalidate_status(dir);
+    Ok(())
+}
+
+/// Drops the cached status of every project containing `path`, after it was changed.
+pub(crate) fn invalidate_status(path: &Path) {
+    STATUS_CACHE.lock().unwrap().retain(|project, _| !path.starts_with(project));
+}
+
+async fn read_status(path: &Path) -> Result<GitStatus, AppError> {
+    if !path.is_dir() {
+        return Err(AppError::Internal("Path is not a directory".to_string()));
+    }
+
+    // Where the project sits in its repository; fails outside one
+    let prefix = match git(path, &["rev-parse", "--show-prefix"]).await? {
+        Some(output) => String::from_utf8_lossy(&output).trim_end().to_string(),
+        None => return Ok(GitStatus::default()),
+    };
+    let output = git_in_repo(path, &["status", "--porcelain=v2", "--branch", "-z", "--", "."]).await?;
+
+    Ok(parse_status(&String::from_utf8_lossy(&output), &prefix))
+}
+
+/// Runs git in `dir`; `None` when git reports that `dir` isn't in a repository.
+async fn git(dir: &Path, args: &[&str]) -> Result<Option<Vec<u8>>, AppError> {
+    let output = run_git(dir, args).await?;
+    if output.status.success() {
+        return Ok(Some(output.stdout));
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stderr.contains("not a git repository") {
+        return Ok(None);
+    }
+    Err(git_error(args, &stderr))
+}
+
+/// Like `git`, for commands that only make sense inside a repository.
+async fn git_in_repo(dir: &Path, args: &[&str]) -> Result<Vec<u8>, AppError> {
+    git(dir, args).await?
+        .ok_or_else(|| AppError::Git(format!("{} is not in a git repository", dir.display())))
+}
+
+async fn run_git(dir: &Path, args: &[&str]) -> Result<std::process::Output, AppError> {
+    tokio::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        // A background refresh must not take the index lock from a commit running in a terminal
+        .env("GIT_OPTIONAL_LOCKS", "0")
+        .output()
+        .await
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => AppError::GitNotInstalled,
+            _ => AppError::Git(format!("failed to run git: {}", e)),
+        })
+}
+
+async fn run_git_with_input(dir: &Path, args: &[&str], input: &str) -> Result<std::process::Output, AppError> {
+    use tokio::io::AsyncWriteExt;
+
+    let mut child = tokio::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => AppError::GitNotInstalled,
+            _ => AppError::Git(format!("failed to run git: {}", e)),
+        })?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes())
+            .await
+            .map_err(|e| AppError::Git(format!("failed to write to git: {}", e)))?;
+    }
+    child.wait_with_output()
+        .await
+        .map_err(|e| AppError::Git(format!("failed to run git: {}", e)))
+}
+
+async fn has_head(dir: &Path) -> Result<bool, AppError> {
+    Ok(run_git(dir, &["rev-parse", "--verify", "--quiet", "HEAD"]).await?.status.success())
+}
+
+fn git_error(args: &[&str], stderr: &str) -> AppError {
+    AppError::Git(format!("git {}: {}", args.first().unwrap_or(&""), stderr.trim()))
+}
+
+/// Parses `git status --porcelain=v2 --branch -z`. Entries are NUL-terminated; a rename is
+/// followed by a second entry holding its original path.
+fn parse_status(output: &str, prefix: &str) -> GitStatus {
+    let mut status = GitStatus {
+        is_repo: true,
+        ..GitStatus::default()
+    };
+
+    let mut entries = output.split('\0');
+    while let Some(entry) = entries.next() {
+        let (kind, rest) = entry.split_once(' ').unwrap_or((entry, ""));
+        let (file_status, path) = match kind {
+            "#" => {
+                parse_header(rest, &mut status);
+                continue;
+            }
+            "1" => (ordinary_status(rest), rest.splitn(8, ' ').nth(7)),
+            "2" => {
+                entries.next(); // original path
+                (GitFileStatus::Renamed, rest.splitn(9, ' ').nth(8))
+            }
+            "u" => (GitFileStatus::Conflicted, rest.splitn(10, ' ').nth(9)),
+            "?" => (GitFileStatus::Untracked, Some(rest)),
+            _ => continue,
+        };
+        if let Some(path) = path.and_then(|p| p.strip_prefix(prefix)) {
+            status.files.insert(path.to_string(), file_status);
+        }
+    }
+    status
+}
+
+fn parse_header(header: &str, status: &mut GitStatus) {
+    let (key, value) = header.split_once(' ').unwrap_or((header, ""));
+    match key {
+        "branch.head" if value != "(detached)" => status.branch = Some(value.to_string()),
+        "branch.upstream" => status.upstream = Some(value.to_string()),
+        "branch.ab" => {
+            for count in value.split(' ') {
+                if let Some(ahead) = count.strip_prefix('+') {
+                    status.ahead = ahead.parse().unwrap_or(0);
+                } else if let Some(behind) = count.strip_prefix('-') {
+                    status.behind = behind.parse().unwrap_or(0);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+// `XY` is the staged and unstaged state; `.` means unchanged
+fn ordinary_status(fields: &str) -> GitFileStatus {
+    let xy = fields.split(' ').next().unwrap_or_default();
+    if xy.contains('D') {
+        GitFileStatus::Deleted
+    } else if xy.starts_with('A') {
+        GitFileStatus::Added
+    } else {
+        GitFileStatus::Modified
+    }
+}
+
+/// Parses `git diff --numstat -z`: `added<TAB>deleted<TAB>path` per file, with `-` counts for
+/// binary files. For a rename the path is empty and the old and new paths follow as entries.
+fn parse_numstat(output: &str) -> Vec<GitDiffStat> {
+    let mut stats = Vec::new();
+    let mut entries = output.split('\0');
+    while let Some(entry) = entries.next() {
+        let mut fields = entry.splitn(3, '\t');
+        let (Some(insertions), Some(deletions), Some(path)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        let (old_path, path) = if path.is_empty() {
+            let old_path = entries.next().unwrap_or_default().to_string();
+            (Some(old_path), entries.next().unwrap_or_default().to_string())
+        } else {
+            (None, path.to_string())
+        };
+        stats.push(GitDiffStat {
+            path,
+            old_path,
+            insertions: insertions.parse().ok(),
+            deletions: deletions.parse().ok(),
+            binary: insertions == "-",
+        });
+    }
+    stats
+}
@@ -0,0 +1,22 @@
+use tauri::{AppHandle, State};
+use crate::error::AppError;
+use crate::scheduler::JobStatus;
+use crate::state::AppState;
+use crate::telemetry::timed;
+
+/// The periodic jobs with their last and next runs.
+#[tauri::command]
+#[timed]
+pub async fn list_background_jobs(state: State<'_, AppState>) -> Result<Vec<JobStatus>, AppError> {
+    Ok(state.jobs.list())
+}
+
+/// Starts a run of the job `name` now, without waiting for it to finish. Its schedule then
+/// counts from this run.
+#[tauri::command]
+#[timed]
+pub async fn run_background_job_now(app: AppHandle, state: State<'_, AppState>, name: String) -> Result<JobStatus, AppError> {
+    log::info!("Running the {} job on request", name);
+
+    state.jobs.run_now(&app, &name).map_err(|e| AppError::Validation(e.to_string()))
+}
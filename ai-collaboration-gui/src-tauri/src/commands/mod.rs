@@ -2,12 +2,57 @@
 pub mod project;
 pub mod ai_tools;
 pub mod swarm;
+pub mod pricing;
 pub mod system;
 pub mod database;
+pub mod chat;
+pub mod chat_export;
+pub mod attachments;
+pub mod search;
+pub mod git;
+pub mod diff;
+pub mod monitor;
+pub mod project_stats;
+pub mod archive;
+pub mod clipboard;
+pub mod tail;
+pub mod env_vars;
+pub mod workspaces;
+pub mod discovery;
+pub mod bundle;
+pub mod retention;
+pub mod activity;
+pub mod settings;
+pub mod jobs;
+pub mod diagnostics;
+pub mod undo;
+pub mod agent_actions;
 
 // Re-export all command functions for easy access
 pub use project::*;
 pub use ai_tools::*;
 pub use swarm::*;
+pub use pricing::*;
 pub use system::*;
-pub use database::*;
\ No newline at end of file
+pub use database::*;
+pub use chat::*;
+pub use chat_export::*;
+pub use attachments::*;
+pub use search::*;
+pub use git::*;
+pub use diff::*;
+pub use monitor::*;
+pub use project_stats::*;
+pub use archive::*;
+pub use clipboard::*;
+pub use tail::*;
+pub use env_vars::*;
+pub use workspaces::*;
+pub use discovery::*;
+pub use bundle::*;
+pub use retention::*;
+pub use activity::*;
+pub use settings::*;
+pub use jobs::*;
+pub use diagnostics::*;
+pub use undo::*;
\ No newline at end of file
@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use chrono::{DateTime, Utc};
+use sysinfo::{Disks, Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+use tauri::{AppHandle, Manager, State};
+use crate::commands::ai_tools::list_tool_processes;
+use crate::commands::system::list_processes;
+use crate::state::AppState;
+use crate::telemetry::{timed, CommandCall, CommandMetrics};
+
+/// Kept in managed state: sysinfo computes CPU usage as the change since the previous refresh,
+/// and reusing the instance avoids rescanning everything on each poll.
+pub struct SystemMonitor {
+    system: Mutex<System>,
+    disks: Mutex<Disks>,
+}
+
+impl SystemMonitor {
+    pub fn new() -> Self {
+        Self {
+            system: Mutex::new(System::new()),
+            disks: Mutex::new(Disks::new()),
+        }
+    }
+}
+
+impl Default for SystemMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Which parts of the metrics to sample; all of them when not given.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricsRequest {
+    #[serde(default)]
+    pub cpu: bool,
+    #[serde(default)]
+    pub memory: bool,
+    #[serde(default)]
+    pub disks: bool,
+    #[serde(default)]
+    pub processes: bool,
+}
+
+impl Default for MetricsRequest {
+    fn default() -> Self {
+        Self { cpu: true, memory: true, disks: true, processes: true }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CpuMetrics {
+    pub usage_percent: f32,
+    pub per_core_percent: Vec<f32>,
+    // Not available on Windows
+    pub load_average: Option<[f64; 3]>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryMetrics {
+    pub total_bytes: u64,
+    pub used_bytes: u64,
+    pub swap_total_bytes: u64,
+    pub swap_used_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiskMetrics {
+    pub label: String, // 'project' | 'app_data'
+    pub path: String,
+    pub mount_point: String,
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessMetrics {
+    pub label: String, // 'app', a tool id, or a managed process name
+    pub pid: u32,
+    // Of one core, so a busy multi-threaded process can exceed 100
+    pub cpu_percent: f32,
+    pub memory_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemMetrics {
+    pub timestamp: DateTime<Utc>,
+    pub cpu: Option<CpuMetrics>,
+    pub memory: Option<MemoryMetrics>,
+    pub disks: Option<Vec<DiskMetrics>>,
+    pub processes: Option<Vec<ProcessMetrics>>,
+}
+
+/// Cheap enough to poll every couple of seconds. CPU figures are averages since the previous
+/// call, so the first call reports zero usage.
+#[tauri::command]
+#[timed]
+pub async fn get_system_metrics(
+    app: AppHandle,
+    monitor: State<'_, SystemMonitor>,
+    project_path: Option<String>,
+    request: Option<MetricsRequest>,
+) -> Result<SystemMetrics, String> {
+    let request = request.unwrap_or_default();
+
+    // Gathered before taking the lock, as listing them awaits
+    let mut tracked: Vec<(String, u32)> = Vec::new();
+    if request.processes {
+        tracked.push(("app".to_string(), std::process::id()));
+        for tool in list_tool_processes(app.state()).await? {
+            tracked.extend(tool.pid.map(|pid| (tool.tool_id, pid)));
+        }
+        for process in list_processes(app.state()).await? {
+            if process.status == "running" {
+                tracked.extend(process.pid.map(|pid| (process.name, pid)));
+            }
+        }
+    }
+
+    let mut metrics = SystemMetrics {
+        timestamp: Utc::now(),
+        cpu: None,
+        memory: None,
+        disks: None,
+        processes: None,
+    };
+
+    {
+        let mut system = monitor.system.lock().unwrap();
+        if request.cpu {
+            system.refresh_cpu_usage();
+            let load = System::load_average();
+            metrics.cpu = Some(CpuMetrics {
+                usage_percent: system.global_cpu_usage(),
+                per_core_percent: system.cpus().iter().map(|cpu| cpu.cpu_usage()).collect(),
+                load_average: (!cfg!(windows)).then_some([load.one, load.five, load.fifteen]),
+            });
+        }
+        if request.memory {
+            system.refresh_memory();
+            metrics.memory = Some(MemoryMetrics {
+                total_bytes: system.total_memory(),
+                used_bytes: system.used_memory(),
+                swap_total_bytes: system.total_swap(),
+                swap_used_bytes: system.used_swap(),
+            });
+        }
+        if request.processes {
+            let pids: Vec<Pid> = tracked.iter().map(|(_, pid)| Pid::from_u32(*pid)).collect();
+            system.refresh_processes_specifics(
+                ProcessesToUpdate::Some(&pids),
+                true,
+                ProcessRefreshKind::nothing().with_cpu().with_memory(),
+            );
+            metrics.processes = Some(tracked.into_iter()
+                .filter_map(|(label, pid)| {
+                    let process = system.process(Pid::from_u32(pid))?;
+                    Some(ProcessMetrics {
+                        label,
+                        pid,
+                        cpu_percent: process.cpu_usage(),
+                        memory_bytes: process.memory(),
+                    })
+                })
+                .collect());
+        }
+    }
+
+    if request.disks {
+        let mut paths: Vec<(&str, PathBuf)> = Vec::new();
+        paths.extend(project_path.map(|p| ("project", PathBuf::from(p))));
+        if let Ok(app_data_dir) = app.path().app_data_dir() {
+            paths.push(("app_data", app_data_dir));
+        }
+
+        let mut disks = monitor.disks.lock().unwrap();
+        disks.refresh(true);
+        metrics.disks = Some(paths.into_iter()
+            .filter_map(|(label, path)| {
+                let disk = disk_for(&disks, &path)?;
+                Some(DiskMetrics {
+                    label: label.to_string(),
+                    path: path.to_string_lossy().to_string(),
+                    mount_point: disk.mount_point().to_string_lossy().to_string(),
+                    total_bytes: disk.total_space(),
+                    available_bytes: disk.available_space(),
+                })
+            })
+            .collect());
+    }
+
+    Ok(metrics)
+}
+
+/// Call counts, error counts and timings of each command called since startup.
+#[tauri::command]
+#[timed]
+pub async fn get_command_metrics(state: State<'_, AppState>) -> Result<Vec<CommandMetrics>, String> {
+    Ok(state.telemetry.command_metrics())
+}
+
+/// The recent command calls that took `threshold_ms` or longer, newest first.
+#[tauri::command]
+#[timed]
+pub async fn get_recent_slow_commands(state: State<'_, AppState>, threshold_ms: u64) -> Result<Vec<CommandCall>, String> {
+    Ok(state.telemetry.recent_slow(threshold_ms as f64))
+}
+
+/// The disk with the deepest mount point containing `path`.
+fn disk_for<'a>(disks: &'a Disks, path: &Path) -> Option<&'a sysinfo::Disk> {
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    disks.list()
+        .iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+}
@@ -0,0 +1,465 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Duration, Utc};
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+use crate::database::{Database, DbChatSession, DbDailyToolUsage, DbMessageTokenUsage, DbModelPricing, DbTokenUsage, MessageMetadata, MessageUsage};
+use crate::state::AppState;
+use crate::telemetry::timed;
+use tauri::State;
+
+/// Prices are in USD per million tokens.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelPricing {
+    pub model: String,
+    pub prompt_price: f64,
+    pub completion_price: f64,
+    pub is_override: bool,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+}
+
+impl From<TokenUsage> for MessageUsage {
+    fn from(usage: TokenUsage) -> Self {
+        MessageUsage { prompt_tokens: usage.prompt_tokens, completion_tokens: usage.completion_tokens }
+    }
+}
+
+/// Usage of one tool over a period, with one bucket per UTC day (days without calls included).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolUsageStats {
+    pub tool_id: String,
+    pub total_calls: i64,
+    pub error_count: i64,
+    pub error_rate: f64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    // Calls whose token counts were estimated rather than reported by the tool
+    pub estimated_token_calls: i64,
+    pub average_latency_ms: f64,
+    pub estimated_cost: Option<f64>,
+    pub days: Vec<DailyUsage>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DailyUsage {
+    pub day: String, // YYYY-MM-DD, UTC
+    pub calls: i64,
+    pub errors: i64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub average_latency_ms: f64,
+    pub estimated_cost: Option<f64>,
+    #[serde(skip)]
+    total_latency_ms: i64,
+}
+
+/// Token counts of a chat session. The totals are what its replies' calls used, which already
+/// include the user messages sent with them; `by_role` counts each role's messages on their own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionTokenUsage {
+    pub session_id: String,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub total_tokens: i64,
+    pub by_role: BTreeMap<String, RoleTokenUsage>,
+    // Messages whose counts are estimates, and those without counts until they are backfilled
+    pub estimated_messages: i64,
+    pub uncounted_messages: i64,
+    pub estimated_cost: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoleTokenUsage {
+    pub messages: i64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+}
+
+const DEFAULT_USAGE_PERIOD_DAYS: i64 = 7;
+// Every day in the range gets a bucket, so keep it to what a chart can show
+const MAX_USAGE_PERIOD_DAYS: i64 = 366;
+
+// Built-in price table; entries saved with set_model_pricing take precedence
+const DEFAULT_PRICING: [(&str, f64, f64); 10] = [
+    ("claude-3-opus", 15.0, 75.0),
+    ("claude-3-sonnet", 3.0, 15.0),
+    ("claude-3-haiku", 0.25, 1.25),
+    ("claude-3-5-sonnet", 3.0, 15.0),
+    ("gemini-pro", 0.5, 1.5),
+    ("gemini-1.5-pro", 3.5, 10.5),
+    ("gemini-1.5-flash", 0.35, 1.05),
+    ("gpt-4", 30.0, 60.0),
+    ("gpt-4o", 5.0, 15.0),
+    ("gpt-3.5-turbo", 0.5, 1.5),
+];
+
+#[tauri::command]
+#[timed]
+pub async fn get_model_pricing(state: State<'_, AppState>) -> Result<Vec<ModelPricing>, String> {
+    log::info!("Getting model pricing");
+
+    let pricing = load_pricing(&state.db)
+        .map_err(|e| format!("Failed to get model pricing: {}", e))?;
+
+    Ok(pricing)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn set_model_pricing(state: State<'_, AppState>, model: String, prompt_price: f64, completion_price: f64) -> Result<ModelPricing, String> {
+    log::info!("Setting pricing for model: {}", model);
+
+    let pricing = store_pricing(&state.db, model, prompt_price, completion_price)
+        .map_err(|e| format!("Failed to set model pricing: {}", e))?;
+
+    Ok(pricing)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn get_tool_usage_stats(state: State<'_, AppState>, tool_id: Option<String>, since: Option<DateTime<Utc>>, until: Option<DateTime<Utc>>) -> Result<Vec<ToolUsageStats>, String> {
+    log::info!("Getting usage stats for AI tool: {:?}", tool_id);
+
+    let stats = tool_usage_stats(&state.db, tool_id.as_deref(), since, until)
+        .map_err(|e| format!("Failed to get tool usage stats: {}", e))?;
+
+    Ok(stats)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn get_session_token_usage(state: State<'_, AppState>, session_id: String) -> Result<SessionTokenUsage, String> {
+    log::info!("Getting token usage of session {}", session_id);
+
+    let usage = session_token_usage(&state.db, &load_pricing(&state.db).map_err(|e| format!("Failed to load pricing: {}", e))?, &session_id)
+        .map_err(|e| format!("Failed to get session token usage: {}", e))?;
+
+    Ok(usage)
+}
+
+/// Gives messages stored without token counts, such as imported or older ones, counts from the
+/// usage their metadata recorded or else estimated from their text. Returns how many were filled in.
+#[tauri::command]
+#[timed]
+pub async fn backfill_token_counts(state: State<'_, AppState>, session_id: String) -> Result<usize, String> {
+    log::info!("Backfilling token counts of session {}", session_id);
+
+    let filled = backfill_session_tokens(&state.db, &session_id)
+        .map_err(|e| format!("Failed to backfill token counts: {}", e))?;
+
+    Ok(filled)
+}
+
+/// Sets `total_tokens` and `estimated_cost` on each session, for the session list.
+pub(crate) fn fill_session_token_totals(db: &Database, sessions: &mut [DbChatSession]) -> Result<()> {
+    let pricing = load_pricing(db)?;
+    for session in sessions.iter_mut() {
+        let usage = session_token_usage(db, &pricing, &session.id)?;
+        session.total_tokens = Some(usage.total_tokens);
+        session.estimated_cost = usage.estimated_cost;
+    }
+    Ok(())
+}
+
+fn session_token_usage(db: &Database, pricing: &[ModelPricing], session_id: &str) -> Result<SessionTokenUsage> {
+    let rows = db.get_session_token_usage(session_id)?;
+
+    let mut usage = SessionTokenUsage {
+        session_id: session_id.to_string(),
+        prompt_tokens: 0,
+        completion_tokens: 0,
+        total_tokens: 0,
+        by_role: BTreeMap::new(),
+        estimated_messages: 0,
+        uncounted_messages: 0,
+        estimated_cost: None,
+    };
+    for row in &rows {
+        let role = usage.by_role.entry(row.role.clone()).or_default();
+        role.messages += row.messages;
+        role.prompt_tokens += row.prompt_tokens;
+        role.completion_tokens += row.completion_tokens;
+        usage.estimated_messages += row.estimated;
+        usage.uncounted_messages += row.messages - row.counted;
+
+        // Only replies stand for calls that were billed
+        if row.role != "assistant" {
+            continue;
+        }
+        usage.prompt_tokens += row.prompt_tokens;
+        usage.completion_tokens += row.completion_tokens;
+        if let Some(cost) = message_cost(pricing, row) {
+            *usage.estimated_cost.get_or_insert(0.0) += cost;
+        }
+    }
+    usage.total_tokens = usage.prompt_tokens + usage.completion_tokens;
+
+    Ok(usage)
+}
+
+// Replies without a model are priced as their tool's default model
+fn message_cost(pricing: &[ModelPricing], row: &DbMessageTokenUsage) -> Option<f64> {
+    let model = row.model.as_deref().or_else(|| row.tool_id.as_deref().map(default_model_for_tool))?;
+    cost_of(pricing, model, row.prompt_tokens, row.completion_tokens)
+}
+
+/// A reply without recorded usage is counted as the user message before it plus its own text.
+fn backfill_session_tokens(db: &Database, session_id: &str) -> Result<usize> {
+    let messages = db.get_chat_messages(session_id)?;
+
+    let mut filled = Vec::new();
+    for (i, message) in messages.iter().enumerate() {
+        if message.prompt_tokens.is_some() {
+            continue;
+        }
+        let reported = message.metadata.as_ref()
+            .filter(|m| !m.estimated)
+            .and_then(|m| m.usage);
+        let usage = reported.unwrap_or_else(|| match message.role.as_str() {
+            "assistant" => MessageUsage {
+                prompt_tokens: messages[..i].iter().rev()
+                    .find(|m| m.role == "user")
+                    .map_or(0, |m| estimate_tokens(&m.content)),
+                completion_tokens: estimate_tokens(&message.content),
+            },
+            _ => MessageUsage { prompt_tokens: estimate_tokens(&message.content), completion_tokens: 0 },
+        });
+
+        let mut message = message.clone();
+        if reported.is_none() {
+            message.metadata.get_or_insert_with(MessageMetadata::default).estimated = true;
+        }
+        message.prompt_tokens = Some(usage.prompt_tokens);
+        message.completion_tokens = Some(usage.completion_tokens);
+        filled.push(message);
+    }
+    db.update_chat_message_token_counts(&filled)?;
+
+    Ok(filled.len())
+}
+
+/// Defaults to the last seven days. Grouping happens in SQL; only the per-model rows of each
+/// day are folded here, since prices live partly in code.
+fn tool_usage_stats(db: &Database, tool_id: Option<&str>, since: Option<DateTime<Utc>>, until: Option<DateTime<Utc>>) -> Result<Vec<ToolUsageStats>> {
+    let until = until.unwrap_or_else(Utc::now);
+    let since = since.unwrap_or(until - Duration::days(DEFAULT_USAGE_PERIOD_DAYS));
+    if since >= until {
+        return Err(anyhow!("`since` must be before `until`"));
+    }
+    if until - since > Duration::days(MAX_USAGE_PERIOD_DAYS) {
+        return Err(anyhow!("Usage stats cover at most {} days", MAX_USAGE_PERIOD_DAYS));
+    }
+
+    let pricing = load_pricing(db)?;
+    let rows = db.get_daily_tool_usage(tool_id, since, until)?;
+
+    let mut by_tool: BTreeMap<String, BTreeMap<String, DailyUsage>> = BTreeMap::new();
+    for row in &rows {
+        let days = by_tool.entry(row.tool_id.clone()).or_default();
+        let day = days.entry(row.day.clone()).or_insert_with(|| DailyUsage { day: row.day.clone(), ..Default::default() });
+        day.calls += row.calls;
+        day.errors += row.errors;
+        day.prompt_tokens += row.prompt_tokens;
+        day.completion_tokens += row.completion_tokens;
+        day.total_latency_ms += row.total_latency_ms;
+        if let Some(cost) = row_cost(&pricing, row) {
+            *day.estimated_cost.get_or_insert(0.0) += cost;
+        }
+    }
+
+    let stats = by_tool.into_iter()
+        .map(|(tool_id, mut days)| {
+            let mut date = since.date_naive();
+            while date <= until.date_naive() {
+                let day = date.format("%Y-%m-%d").to_string();
+                days.entry(day.clone()).or_insert_with(|| DailyUsage { day, ..Default::default() });
+                date += Duration::days(1);
+            }
+            let mut days: Vec<DailyUsage> = days.into_values().collect();
+            for day in days.iter_mut() {
+                day.average_latency_ms = average(day.total_latency_ms, day.calls);
+            }
+
+            let total_calls = days.iter().map(|d| d.calls).sum();
+            let error_count = days.iter().map(|d| d.errors).sum();
+            let total_latency_ms = days.iter().map(|d| d.total_latency_ms).sum();
+            let estimated_cost = days.iter()
+                .filter_map(|d| d.estimated_cost)
+                .fold(None, |total: Option<f64>, cost| Some(total.unwrap_or(0.0) + cost));
+            ToolUsageStats {
+                estimated_token_calls: rows.iter().filter(|r| r.tool_id == tool_id).map(|r| r.estimated_calls).sum(),
+                tool_id,
+                total_calls,
+                error_count,
+                error_rate: average(error_count, total_calls),
+                prompt_tokens: days.iter().map(|d| d.prompt_tokens).sum(),
+                completion_tokens: days.iter().map(|d| d.completion_tokens).sum(),
+                average_latency_ms: average(total_latency_ms, total_calls),
+                estimated_cost,
+                days,
+            }
+        })
+        .collect();
+
+    Ok(stats)
+}
+
+fn average(total: i64, count: i64) -> f64 {
+    if count == 0 { 0.0 } else { total as f64 / count as f64 }
+}
+
+// Interactions without a model are priced as the tool's default model
+fn row_cost(pricing: &[ModelPricing], row: &DbDailyToolUsage) -> Option<f64> {
+    let model = row.model.as_deref().unwrap_or_else(|| default_model_for_tool(&row.tool_id));
+    cost_of(pricing, model, row.prompt_tokens, row.completion_tokens)
+}
+
+fn load_pricing(db: &Database) -> Result<Vec<ModelPricing>> {
+    let overrides = db.get_model_pricing()?;
+
+    let mut pricing: Vec<ModelPricing> = DEFAULT_PRICING.iter()
+        .filter(|(model, _, _)| !overrides.iter().any(|o| o.model == *model))
+        .map(|(model, prompt_price, completion_price)| ModelPricing {
+            model: model.to_string(),
+            prompt_price: *prompt_price,
+            completion_price: *completion_price,
+            is_override: false,
+            updated_at: None,
+        })
+        .collect();
+
+    pricing.extend(overrides.into_iter().map(|o| ModelPricing {
+        model: o.model,
+        prompt_price: o.prompt_price,
+        completion_price: o.completion_price,
+        is_override: true,
+        updated_at: Some(o.updated_at),
+    }));
+    pricing.sort_by(|a, b| a.model.cmp(&b.model));
+
+    Ok(pricing)
+}
+
+fn store_pricing(db: &Database, model: String, prompt_price: f64, completion_price: f64) -> Result<ModelPricing> {
+    let model = model.trim().to_string();
+    if model.is_empty() {
+        return Err(anyhow!("Model name cannot be empty"));
+    }
+    for price in [prompt_price, completion_price] {
+        if !price.is_finite() || price < 0.0 {
+            return Err(anyhow!("Prices must be non-negative numbers"));
+        }
+    }
+
+    let row = DbModelPricing {
+        model,
+        prompt_price,
+        completion_price,
+        updated_at: Utc::now(),
+    };
+    db.save_model_pricing(&row)?;
+
+    Ok(ModelPricing {
+        model: row.model,
+        prompt_price: row.prompt_price,
+        completion_price: row.completion_price,
+        is_override: true,
+        updated_at: Some(row.updated_at),
+    })
+}
+
+/// Exact matches win; otherwise the longest known prefix, so dated variants
+/// like `claude-3-sonnet-20240229` pick up the family price.
+fn price_for<'a>(pricing: &'a [ModelPricing], model: &str) -> Option<&'a ModelPricing> {
+    pricing.iter()
+        .find(|p| p.model == model)
+        .or_else(|| {
+            pricing.iter()
+                .filter(|p| model.starts_with(&p.model))
+                .max_by_key(|p| p.model.len())
+        })
+}
+
+/// Sums the cost of the given usage rows. Returns `None` when no tokens were used
+/// or none of the models has a known price.
+pub(crate) fn estimate_cost(db: &Database, usage: &[DbTokenUsage]) -> Result<Option<f64>> {
+    let pricing = load_pricing(db)?;
+
+    let mut total = None;
+    for row in usage {
+        if row.prompt_tokens == 0 && row.completion_tokens == 0 {
+            continue;
+        }
+        let Some(cost) = row.model.as_deref().and_then(|m| cost_of(&pricing, m, row.prompt_tokens, row.completion_tokens)) else {
+            log::debug!("No price known for model {:?}", row.model);
+            continue;
+        };
+        *total.get_or_insert(0.0) += cost;
+    }
+
+    Ok(total)
+}
+
+fn cost_of(pricing: &[ModelPricing], model: &str, prompt_tokens: i64, completion_tokens: i64) -> Option<f64> {
+    let price = price_for(pricing, model)?;
+    Some((prompt_tokens as f64 * price.prompt_price + completion_tokens as f64 * price.completion_price) / 1_000_000.0)
+}
+
+/// Rough token count for text whose usage was not reported (about four characters per token).
+pub(crate) fn estimate_tokens(text: &str) -> i64 {
+    (text.chars().count() as i64 + 3) / 4
+}
+
+/// Usage counted from the prompt and the reply text, for tools that report none.
+pub(crate) fn estimate_usage(payload: &serde_json::Value, response: &serde_json::Value) -> TokenUsage {
+    let reply = ["message", "result", "output", "content"].iter()
+        .find_map(|key| response.get(*key).and_then(|v| v.as_str()))
+        .map(str::to_string)
+        .unwrap_or_else(|| response.to_string());
+    TokenUsage {
+        prompt_tokens: estimate_tokens(&crate::providers::prompt_from_payload(payload)),
+        completion_tokens: estimate_tokens(&reply),
+    }
+}
+
+/// Reads token usage from a tool response, understanding the Anthropic, OpenAI,
+/// Gemini and Ollama field names.
+pub(crate) fn usage_from_response(response: &serde_json::Value) -> Option<TokenUsage> {
+    let field = |value: &serde_json::Value, names: &[&str]| {
+        names.iter().find_map(|name| value.get(*name).and_then(|v| v.as_i64()))
+    };
+
+    if let Some(usage) = response.get("usage").or_else(|| response.get("usageMetadata")) {
+        let prompt = field(usage, &["input_tokens", "prompt_tokens", "promptTokenCount"]);
+        let completion = field(usage, &["output_tokens", "completion_tokens", "candidatesTokenCount"]);
+        if prompt.is_some() || completion.is_some() {
+            return Some(TokenUsage {
+                prompt_tokens: prompt.unwrap_or(0),
+                completion_tokens: completion.unwrap_or(0),
+            });
+        }
+    }
+
+    match (field(response, &["prompt_eval_count"]), field(response, &["eval_count"])) {
+        (None, None) => None,
+        (prompt, completion) => Some(TokenUsage {
+            prompt_tokens: prompt.unwrap_or(0),
+            completion_tokens: completion.unwrap_or(0),
+        }),
+    }
+}
+
+/// Model assumed for a tool when its response does not say which model answered.
+pub(crate) fn default_model_for_tool(tool: &str) -> &str {
+    match tool {
+        "claude-code" => "claude-3-sonnet",
+        "gemini-cli" => "gemini-pro",
+        "cursor-cli" => "gpt-4",
+        other => other,
+    }
+}
@@ -1,9 +1,19 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use tauri::{AppHandle, Emitter, State};
+use crate::commands::ai_tools::ToolSpecificConfig;
+use crate::commands::cleanup_workspaces;
+use crate::database::{Database, DbProject, DbProjectAITool, ProjectDeleteCounts, ProjectDeleteOptions, ProjectOrder};
+use crate::commands::git::init_repository;
+use crate::error::{typed_or, AppError};
+use crate::events::{Action, DataChanged, Entity};
+use crate::state::AppState;
+use crate::telemetry::timed;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Project {
@@ -13,25 +23,75 @@ pub struct Project {
     pub description: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub last_opened_at: Option<DateTime<Utc>>,
     pub settings: ProjectSettings,
     pub ai_tools: Vec<AIToolConfig>,
+    // The most recently active ones, when asked for; `session_count` has them all
     pub sessions: Vec<SessionSummary>,
+    #[serde(default)]
+    pub session_count: usize,
+    // The path is no longer a directory; the user can relocate or remove the project
+    #[serde(default)]
+    pub missing: bool,
+    #[serde(default)]
+    pub archived: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProjectSettings {
     pub default_ai_tool: String,
     pub auto_save: bool,
-    pub collaboration_mode: String, // 'single' | 'swarm' | 'sequential'
+    pub collaboration_mode: String, // one of COLLABORATION_MODES
     pub memory_retention: i32, // days
+    // Tools to fall back to, in order, when the chosen one is down
+    #[serde(default)]
+    pub fallback_order: Vec<String>,
+    // Title new chat sessions from their first exchange
+    #[serde(default)]
+    pub auto_title: bool,
+    // Ask the user before a swarm deletes, writes outside the project or runs a command
+    #[serde(default)]
+    pub require_approval: bool,
+    // Programs a swarm may run without asking, even with `require_approval` on
+    #[serde(default = "default_allowed_commands")]
+    pub allowed_commands: Vec<String>,
+}
+
+impl Default for ProjectSettings {
+    fn default() -> Self {
+        Self {
+            default_ai_tool: "claude-code".to_string(),
+            auto_save: true,
+            collaboration_mode: "single".to_string(),
+            memory_retention: 30,
+            fallback_order: vec![],
+            auto_title: false,
+            require_approval: false,
+            allowed_commands: default_allowed_commands(),
+        }
+    }
+}
+
+// Programs that only read, so running them needs no one's say-so
+fn default_allowed_commands() -> Vec<String> {
+    ["ls", "cat", "head", "tail", "wc", "grep", "rg", "diff", "pwd", "echo"]
+        .iter()
+        .map(|program| program.to_string())
+        .collect()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AIToolConfig {
     pub tool_id: String,
+    // A disabled tool can't be used in the project at all
     pub enabled: bool,
+    // Lower values are preferred when the project picks a tool
     pub priority: i32,
+    // Merged over the tool's own config for the project's requests; the project's values win
     pub custom_settings: HashMap<String, serde_json::Value>,
+    // Set on the tool's process when it is connected for this project
+    #[serde(default)]
+    pub env: HashMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,172 +105,686 @@ pub struct SessionSummary {
     pub status: String, // 'active' | 'completed' | 'paused'
 }
 
+/// Fields left out stay as they are; an empty `description` clears it. Unknown fields are refused.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectUpdate {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub settings: Option<ProjectSettings>,
+    // Accept a path that doesn't exist yet, e.g. on a drive that isn't mounted
+    #[serde(default)]
+    pub allow_missing_path: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectHealthStatus {
+    Ok,
+    Missing,
+    Relocated, // missing, but its marker was found under the search root
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectHealth {
+    pub project_id: String,
+    pub name: String,
+    pub path: String,
+    pub status: ProjectHealthStatus,
+    // Directories whose marker names this project
+    pub candidates: Vec<String>,
+}
+
+/// Written to `.clauder/project.json` in the project directory, so a moved project can be recognised.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ProjectMarker {
+    pub project_id: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    // Of the app that wrote it
+    #[serde(default)]
+    pub app_version: Option<String>,
+}
+
+const MARKER_DIR: &str = ".clauder";
+const MARKER_FILE: &str = "project.json";
+// How deep under the search root verify_projects looks for markers
+const MARKER_SEARCH_DEPTH: usize = 6;
+
+// Session summaries hydrated per project
+const MAX_SESSION_SUMMARIES: usize = 20;
+// Sessions used more recently than this are active, then paused, then completed
+const ACTIVE_SESSION_DAYS: i64 = 1;
+const PAUSED_SESSION_DAYS: i64 = 14;
+const COLLABORATION_MODES: [&str; 3] = ["single", "swarm", "sequential"];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProjectConfig {
     pub name: String,
     pub path: String,
     pub description: Option<String>,
     pub settings: Option<ProjectSettings>,
+    #[serde(default)]
+    pub create_directory_if_missing: bool,
+    #[serde(default)]
+    pub initialize_git: bool,
 }
 
+/// Every project, most recently updated first unless `order_by` says otherwise. Projects
+/// whose directory is gone are included with `missing` set; archived ones only with `include_archived`.
+/// Session summaries are left out unless `include_sessions`, as the list rarely needs them.
 #[tauri::command]
-pub async fn load_projects() -> Result<Vec<Project>, String> {
+#[timed]
+pub async fn load_projects(
+    state: State<'_, AppState>,
+    order_by: Option<ProjectOrder>,
+    include_archived: Option<bool>,
+    include_sessions: Option<bool>,
+) -> Result<Vec<Project>, String> {
     log::info!("Loading projects");
     
-    // TODO: Replace with actual database query
-    let projects = mock_load_projects().await
+    let include_sessions = include_sessions.unwrap_or(false);
+    let projects = state.db.get_all_projects(order_by.unwrap_or_default(), include_archived.unwrap_or(false))
+        .and_then(|rows| rows.into_iter().map(|row| assemble_project(&state.db, row, include_sessions)).collect())
         .map_err(|e| format!("Failed to load projects: {}", e))?;
     
     Ok(projects)
 }
 
+/// Registers a new project and drops a marker into its directory. With `create_directory_if_missing`
+/// a path that doesn't exist yet is created, and with `initialize_git` a repository is set up in it.
 #[tauri::command]
-pub async fn create_project(config: ProjectConfig) -> Result<Project, String> {
+#[timed]
+pub async fn create_project(app: AppHandle, state: State<'_, AppState>, config: ProjectConfig) -> Result<Project, AppError> {
     log::info!("Creating project: {}", config.name);
     
-    // Validate project path
-    let path = PathBuf::from(&config.path);
-    if !path.exists() {
-        return Err("Project path does not exist".to_string());
+    let retention = state.settings.borrow().default_memory_retention;
+    let row = register_project(&state.db, config, retention).await?;
+    let project = assemble_project(&state.db, row, true)?;
+    emit_project_changed(&app, Action::Created, &project);
+    Ok(project)
+}
+
+/// What `create_project` does short of the event. `memory_retention` applies when the config has no settings of its own.
+pub(crate) async fn register_project(db: &Database, config: ProjectConfig, memory_retention: i32) -> Result<DbProject, AppError> {
+    if config.name.trim().is_empty() {
+        return Err(AppError::Internal("Project name cannot be empty".to_string()));
+    }
+    if let Some(settings) = &config.settings {
+        validate_settings(settings)?;
+    }
+    let path = Path::new(&config.path);
+    if let Some(owner) = project_at(db, &config.path)? {
+        return Err(AppError::Conflict {
+            message: format!("{} already belongs to the project {}", config.path, owner.name),
+            current_hash: None,
+        });
+    }
+    if !path.is_dir() {
+        if !config.create_directory_if_missing || !path.is_absolute() {
+            return Err(AppError::Internal("Project path does not exist or is not a directory".to_string()));
+        }
+        fs::create_dir_all(path)
+            .map_err(|e| AppError::Internal(format!("Failed to create {}: {}", config.path, e)))?;
+    }
+    if config.initialize_git {
+        init_repository(path).await?;
     }
     
-    // TODO: Replace with actual database insertion
-    let project = mock_create_project(config).await
-        .map_err(|e| format!("Failed to create project: {}", e))?;
-    
-    Ok(project)
+    let now = Utc::now();
+    let settings = config.settings.unwrap_or_else(|| ProjectSettings {
+        memory_retention,
+        ..ProjectSettings::default()
+    });
+    let row = DbProject {
+        id: Uuid::new_v4().to_string(),
+        name: config.name,
+        path: config.path,
+        description: config.description,
+        settings: Some(serde_json::to_string(&settings).map_err(|e| AppError::Internal(e.to_string()))?),
+        created_at: now,
+        updated_at: now,
+        last_opened_at: None,
+        archived: false,
+    };
+    db.create_project(&row).map_err(|e| update_error(e, &row.path))?;
+    if let Err(e) = write_marker(&row) {
+        // Without its marker the project couldn't be found again after a move
+        db.delete_project(&row.id)?;
+        return Err(AppError::Internal(format!("Failed to write the project marker in {}: {}", row.path, e)));
+    }
+    Ok(row)
 }
 
+/// Changes the fields given in `update` and returns the updated project.
 #[tauri::command]
-pub async fn update_project(project_id: String, updates: HashMap<String, serde_json::Value>) -> Result<Project, String> {
+#[timed]
+pub async fn update_project(app: AppHandle, state: State<'_, AppState>, project_id: String, update: ProjectUpdate) -> Result<Project, AppError> {
     log::info!("Updating project: {}", project_id);
     
-    // TODO: Replace with actual database update
-    let project = mock_update_project(project_id, updates).await
-        .map_err(|e| format!("Failed to update project: {}", e))?;
+    let row = state.db.get_project(&project_id)?
+        .ok_or_else(|| AppError::NotFound(format!("Project not found: {}", project_id)))?;
     
+    if update.name.as_ref().is_some_and(|name| name.trim().is_empty()) {
+        return Err(AppError::Validation("Project name cannot be empty".to_string()));
+    }
+    let path = update.path.as_deref().filter(|path| *path != row.path);
+    if let Some(path) = path {
+        if !update.allow_missing_path && !Path::new(path).is_dir() {
+            return Err(AppError::Validation("Project path does not exist or is not a directory".to_string()));
+        }
+    }
+    if let Some(settings) = &update.settings {
+        validate_settings(settings)?;
+    }
+    let settings = update.settings
+        .map(|settings| serde_json::to_string(&settings))
+        .transpose()
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    
+    state.db.update_project_fields(&project_id, update.name.as_deref(), update.description.as_deref(), path, settings.as_deref())
+        .map_err(|e| update_error(e, path.unwrap_or_default()))?;
+    
+    let row = state.db.get_project(&project_id)?
+        .ok_or_else(|| AppError::NotFound(format!("Project not found: {}", project_id)))?;
+    let project = assemble_project(&state.db, row, true)?;
+    emit_project_changed(&app, Action::Updated, &project);
     Ok(project)
 }
 
+fn validate_settings(settings: &ProjectSettings) -> Result<(), AppError> {
+    if settings.memory_retention <= 0 {
+        return Err(AppError::Validation("Memory retention must be at least one day".to_string()));
+    }
+    if !COLLABORATION_MODES.contains(&settings.collaboration_mode.as_str()) {
+        return Err(AppError::Validation(format!(
+            "Unknown collaboration mode '{}'; expected one of {}",
+            settings.collaboration_mode,
+            COLLABORATION_MODES.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// Marks the project as opened now, for ordering by `last_opened`, and returns it. Check
+/// `missing` before using its path.
 #[tauri::command]
-pub async fn delete_project(project_id: String) -> Result<(), String> {
-    log::info!("Deleting project: {}", project_id);
+#[timed]
+pub async fn open_project(app: AppHandle, state: State<'_, AppState>, project_id: String) -> Result<Project, String> {
+    log::info!("Opening project: {}", project_id);
     
-    // TODO: Replace with actual database deletion
-    mock_delete_project(project_id).await
-        .map_err(|e| format!("Failed to delete project: {}", e))?;
+    let row = state.db.get_project(&project_id)
+        .map_err(|e| format!("Failed to load project: {}", e))?
+        .ok_or_else(|| format!("Project not found: {}", project_id))?;
+    state.db.mark_project_opened(&project_id)
+        .map_err(|e| format!("Failed to open project: {}", e))?;
     
-    Ok(())
+    let row = DbProject { last_opened_at: Some(Utc::now()), ..row };
+    let project = assemble_project(&state.db, row, true).map_err(|e| format!("Failed to open project: {}", e))?;
+    emit_project_changed(&app, Action::Updated, &project);
+    Ok(project)
 }
 
+/// Points the project at `new_path` after its directory was moved or renamed. With `verify`, the
+/// directory must hold the project's marker. Settings and tool configs that referred to paths
+/// under the old directory are rewritten to the new one.
 #[tauri::command]
-pub async fn get_project_by_id(project_id: String) -> Result<Option<Project>, String> {
-    log::info!("Getting project by ID: {}", project_id);
+#[timed]
+pub async fn relocate_project(app: AppHandle, state: State<'_, AppState>, project_id: String, new_path: String, verify: bool) -> Result<Project, AppError> {
+    log::info!("Relocating project {} to {}", project_id, new_path);
+    
+    let row = state.db.get_project(&project_id)?
+        .ok_or_else(|| AppError::Internal(format!("Project not found: {}", project_id)))?;
+    if !Path::new(&new_path).is_dir() {
+        return Err(AppError::Internal("Project path does not exist or is not a directory".to_string()));
+    }
+    match read_marker(Path::new(&new_path)) {
+        Some(marker) if marker.project_id != project_id => {
+            return Err(AppError::Conflict {
+                message: format!("{} belongs to another project ({})", new_path, marker.name),
+                current_hash: None,
+            });
+        }
+        None if verify => {
+            return Err(AppError::Internal(format!("{} has no marker for this project", new_path)));
+        }
+        _ => {}
+    }
     
-    // TODO: Replace with actual database query
-    let project = mock_get_project_by_id(project_id).await
-        .map_err(|e| format!("Failed to get project: {}", e))?;
+    let old_path = row.path.clone();
+    let settings = row.settings.as_deref()
+        .map(|settings| rebase_json(settings, &old_path, &new_path))
+        .transpose()
+        .map_err(|e| AppError::Internal(format!("Unreadable project settings: {}", e)))?;
+    state.db.update_project_fields(&project_id, None, None, Some(&new_path), settings.as_deref())
+        .map_err(|e| update_error(e, &new_path))?;
     
+    for tool in state.db.get_project_ai_tools(&project_id)? {
+        let custom_settings = rebase_json(&tool.custom_settings, &old_path, &new_path)?;
+        let env = rebase_json(&tool.env, &old_path, &new_path)?;
+        if custom_settings != tool.custom_settings || env != tool.env {
+            state.db.save_project_ai_tool(&DbProjectAITool { custom_settings, env, updated_at: Utc::now(), ..tool })?;
+        }
+    }
+    
+    let row = state.db.get_project(&project_id)?
+        .ok_or_else(|| AppError::Internal(format!("Project not found: {}", project_id)))?;
+    if read_marker(Path::new(&row.path)).is_none() {
+        if let Err(e) = write_marker(&row) {
+            log::warn!("Failed to write the project marker in {}: {}", row.path, e);
+        }
+    }
+    let project = assemble_project(&state.db, row, true)?;
+    emit_project_changed(&app, Action::Updated, &project);
     Ok(project)
 }
 
-// Mock implementations - these will be replaced with actual database operations
-async fn mock_load_projects() -> Result<Vec<Project>> {
-    tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+/// Reports which projects are missing. With `search_root`, the directories under it are searched
+/// for project markers, and a missing project whose marker turns up is reported as relocated
+/// with where it was found; `relocate_project` then moves it there.
+#[tauri::command]
+#[timed]
+pub async fn verify_projects(state: State<'_, AppState>, search_root: Option<String>) -> Result<Vec<ProjectHealth>, String> {
+    log::info!("Verifying projects");
     
-    let project = Project {
-        id: Uuid::new_v4().to_string(),
-        name: "Sample Project".to_string(),
-        path: "/tmp/sample".to_string(),
-        description: Some("A sample project for testing".to_string()),
-        created_at: Utc::now(),
-        updated_at: Utc::now(),
-        settings: ProjectSettings {
-            default_ai_tool: "claude-code".to_string(),
-            auto_save: true,
-            collaboration_mode: "swarm".to_string(),
-            memory_retention: 30,
-        },
-        ai_tools: vec![],
-        sessions: vec![],
+    let projects = state.db.get_all_projects(ProjectOrder::default(), true)
+        .map_err(|e| format!("Failed to load projects: {}", e))?;
+    let found = match search_root {
+        Some(root) => tokio::task::spawn_blocking(move || find_markers(Path::new(&root)))
+            .await
+            .map_err(|e| format!("Failed to search for projects: {}", e))?,
+        None => HashMap::new(),
     };
     
-    Ok(vec![project])
+    Ok(projects.into_iter()
+        .map(|project| {
+            let missing = !Path::new(&project.path).is_dir();
+            let candidates: Vec<String> = found.get(&project.id)
+                .into_iter()
+                .flatten()
+                .filter(|dir| dir.as_path() != Path::new(&project.path))
+                .map(|dir| dir.to_string_lossy().to_string())
+                .collect();
+            let status = match (missing, candidates.is_empty()) {
+                (false, _) => ProjectHealthStatus::Ok,
+                (true, true) => ProjectHealthStatus::Missing,
+                (true, false) => ProjectHealthStatus::Relocated,
+            };
+            ProjectHealth {
+                project_id: project.id,
+                name: project.name,
+                path: project.path,
+                status,
+                candidates,
+            }
+        })
+        .collect())
 }
 
-async fn mock_create_project(config: ProjectConfig) -> Result<Project> {
-    tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
+/// Hides the project from the list without deleting its history. No new swarms or chat
+/// sessions can be started in it until it is unarchived.
+#[tauri::command]
+#[timed]
+pub async fn archive_project(app: AppHandle, state: State<'_, AppState>, project_id: String) -> Result<Project, String> {
+    log::info!("Archiving project: {}", project_id);
+    let project = set_archived(&state.db, &project_id, true).map_err(|e| format!("Failed to archive project: {}", e))?;
+    emit_project_changed(&app, Action::Updated, &project);
+    Ok(project)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn unarchive_project(app: AppHandle, state: State<'_, AppState>, project_id: String) -> Result<Project, String> {
+    log::info!("Unarchiving project: {}", project_id);
+    let project = set_archived(&state.db, &project_id, false).map_err(|e| format!("Failed to unarchive project: {}", e))?;
+    emit_project_changed(&app, Action::Updated, &project);
+    Ok(project)
+}
+
+/// Deletes the project along with what `options` name, in one transaction, and emits
+/// `project:deleted`. With `dry_run` nothing is deleted; the counts say what would be.
+#[tauri::command]
+#[timed]
+pub async fn delete_project(app: AppHandle, state: State<'_, AppState>, project_id: String, options: Option<ProjectDeleteOptions>) -> Result<ProjectDeleteCounts, AppError> {
+    log::info!("Deleting project: {}", project_id);
     
-    let now = Utc::now();
-    let project = Project {
-        id: Uuid::new_v4().to_string(),
-        name: config.name,
-        path: config.path,
-        description: config.description,
-        created_at: now,
-        updated_at: now,
-        settings: config.settings.unwrap_or(ProjectSettings {
-            default_ai_tool: "claude-code".to_string(),
-            auto_save: true,
-            collaboration_mode: "single".to_string(),
-            memory_retention: 30,
-        }),
-        ai_tools: vec![],
-        sessions: vec![],
-    };
+    state.db.get_project(&project_id)?
+        .ok_or_else(|| AppError::Internal(format!("Project not found: {}", project_id)))?;
+    let options = options.unwrap_or_default();
+    let counts = state.db.delete_project_cascade(&project_id, &options)
+        .map_err(|e| typed_or("Failed to delete project", e))?;
+    if options.dry_run {
+        return Ok(counts);
+    }
     
-    Ok(project)
+    for swarm_id in &counts.swarm_ids {
+        if let Err(e) = cleanup_workspaces(state.clone(), Some(swarm_id.clone()), None).await {
+            log::warn!("Swarm {} deleted, but its workspaces remain: {}", swarm_id, e);
+        }
+    }
+    let payload = serde_json::json!({ "project_id": project_id, "counts": counts });
+    if let Err(e) = app.emit("project:deleted", payload) {
+        log::warn!("Failed to emit project:deleted: {}", e);
+    }
+    DataChanged::new(Entity::Project, Action::Deleted, &project_id)
+        .in_project(Some(&project_id))
+        .with_data(&counts)
+        .emit(&app);
+    Ok(counts)
 }
 
-async fn mock_update_project(project_id: String, _updates: HashMap<String, serde_json::Value>) -> Result<Project> {
-    tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+#[tauri::command]
+#[timed]
+pub async fn get_project_by_id(state: State<'_, AppState>, project_id: String) -> Result<Option<Project>, String> {
+    log::info!("Getting project by ID: {}", project_id);
     
-    // This is a simplified mock implementation
-    let now = Utc::now();
-    let project = Project {
-        id: project_id,
-        name: "Updated Project".to_string(),
-        path: "/tmp/updated".to_string(),
-        description: Some("Updated project".to_string()),
-        created_at: now,
-        updated_at: now,
-        settings: ProjectSettings {
-            default_ai_tool: "claude-code".to_string(),
-            auto_save: true,
-            collaboration_mode: "single".to_string(),
-            memory_retention: 30,
-        },
-        ai_tools: vec![],
-        sessions: vec![],
-    };
+    state.db.get_project(&project_id)
+        .and_then(|row| row.map(|row| assemble_project(&state.db, row, true)).transpose())
+        .map_err(|e| format!("Failed to get project: {}", e))
+}
+
+#[tauri::command]
+#[timed]
+pub async fn set_project_ai_tool(app: AppHandle, state: State<'_, AppState>, project_id: String, config: AIToolConfig) -> Result<AIToolConfig, String> {
+    log::info!("Setting AI tool {} for project: {}", config.tool_id, project_id);
     
-    Ok(project)
+    save_project_tool(&state.db, &project_id, &config)
+        .map_err(|e| format!("Failed to set project AI tool: {}", e))?;
+    
+    DataChanged::new(Entity::Project, Action::Updated, &project_id)
+        .in_project(Some(&project_id))
+        .with_data(&config)
+        .emit(&app);
+    Ok(config)
 }
 
-async fn mock_delete_project(_project_id: String) -> Result<()> {
-    tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
-    Ok(())
+/// The project's tool settings, most preferred first.
+#[tauri::command]
+#[timed]
+pub async fn get_project_ai_tools(state: State<'_, AppState>, project_id: String) -> Result<Vec<AIToolConfig>, String> {
+    log::info!("Getting AI tools of project: {}", project_id);
+    
+    project_tools(&state.db, &project_id).map_err(|e| format!("Failed to get project AI tools: {}", e))
 }
 
-async fn mock_get_project_by_id(project_id: String) -> Result<Option<Project>> {
-    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+fn save_project_tool(db: &Database, project_id: &str, config: &AIToolConfig) -> Result<()> {
+    db.get_project(project_id)?
+        .ok_or_else(|| anyhow!("Project not found: {}", project_id))?;
+    for name in config.env.keys() {
+        if name.is_empty() || name.contains(['=', '\0']) {
+            return Err(anyhow!("Invalid environment variable name: {:?}", name));
+        }
+    }
     
-    let project = Project {
-        id: project_id,
-        name: "Sample Project".to_string(),
-        path: "/tmp/sample".to_string(),
-        description: Some("A sample project".to_string()),
-        created_at: Utc::now(),
+    db.save_project_ai_tool(&DbProjectAITool {
+        project_id: project_id.to_string(),
+        tool_id: config.tool_id.clone(),
+        enabled: config.enabled,
+        priority: config.priority,
+        custom_settings: serde_json::to_string(&config.custom_settings)?,
+        env: serde_json::to_string(&config.env)?,
         updated_at: Utc::now(),
-        settings: ProjectSettings {
-            default_ai_tool: "claude-code".to_string(),
-            auto_save: true,
-            collaboration_mode: "single".to_string(),
-            memory_retention: 30,
-        },
-        ai_tools: vec![],
-        sessions: vec![],
+    })
+}
+
+pub(crate) fn emit_project_changed(app: &AppHandle, action: Action, project: &Project) {
+    DataChanged::new(Entity::Project, action, &project.id)
+        .in_project(Some(&project.id))
+        .with_data(project)
+        .emit(app);
+}
+
+fn set_archived(db: &Database, project_id: &str, archived: bool) -> Result<Project> {
+    if !db.set_project_archived(project_id, archived)? {
+        return Err(anyhow!("Project not found: {}", project_id));
+    }
+    let row = db.get_project(project_id)?
+        .ok_or_else(|| anyhow!("Project not found: {}", project_id))?;
+    assemble_project(db, row, true)
+}
+
+pub(crate) fn update_error(e: anyhow::Error, path: &str) -> AppError {
+    match e.downcast_ref::<rusqlite::Error>() {
+        Some(rusqlite::Error::SqliteFailure(error, _)) if error.code == rusqlite::ErrorCode::ConstraintViolation => {
+            AppError::Conflict {
+                message: format!("Another project is already at {}", path),
+                current_hash: None,
+            }
+        }
+        _ => AppError::Internal(format!("Failed to update project: {}", e)),
+    }
+}
+
+pub(crate) fn write_marker(row: &DbProject) -> Result<()> {
+    let dir = Path::new(&row.path).join(MARKER_DIR);
+    fs::create_dir_all(&dir)?;
+    let marker = ProjectMarker {
+        project_id: row.id.clone(),
+        name: row.name.clone(),
+        created_at: row.created_at,
+        app_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+    };
+    fs::write(dir.join(MARKER_FILE), serde_json::to_string_pretty(&marker)?)?;
+    Ok(())
+}
+
+pub(crate) fn read_marker(dir: &Path) -> Option<ProjectMarker> {
+    let content = fs::read_to_string(dir.join(MARKER_DIR).join(MARKER_FILE)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// The directories under `root` holding a project marker, by project id. Ignored and hidden
+/// directories are skipped, so dependency trees aren't searched.
+fn find_markers(root: &Path) -> HashMap<String, Vec<PathBuf>> {
+    let mut found: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    let walker = ignore::WalkBuilder::new(root)
+        .require_git(false)
+        .max_depth(Some(MARKER_SEARCH_DEPTH))
+        .filter_entry(|entry| entry.file_type().is_some_and(|t| t.is_dir()))
+        .build();
+    for entry in walker.flatten() {
+        if let Some(marker) = read_marker(entry.path()) {
+            found.entry(marker.project_id).or_default().push(entry.into_path());
+        }
+    }
+    found
+}
+
+/// `json` with every string that is `old_path` or a path under it moved to `new_path`.
+fn rebase_json(json: &str, old_path: &str, new_path: &str) -> Result<String> {
+    fn rebase(value: &mut serde_json::Value, old_path: &Path, new_path: &Path) {
+        match value {
+            serde_json::Value::String(text) => {
+                if let Ok(rest) = Path::new(text.as_str()).strip_prefix(old_path) {
+                    let rebased = if rest.as_os_str().is_empty() { new_path.to_path_buf() } else { new_path.join(rest) };
+                    *text = rebased.to_string_lossy().to_string();
+                }
+            }
+            serde_json::Value::Array(items) => items.iter_mut().for_each(|item| rebase(item, old_path, new_path)),
+            serde_json::Value::Object(map) => map.values_mut().for_each(|item| rebase(item, old_path, new_path)),
+            _ => {}
+        }
+    }
+    
+    let mut value: serde_json::Value = serde_json::from_str(json)?;
+    let before = value.clone();
+    rebase(&mut value, Path::new(old_path), Path::new(new_path));
+    // Unchanged settings are passed back as they were stored
+    if value == before {
+        return Ok(json.to_string());
+    }
+    Ok(serde_json::to_string(&value)?)
+}
+
+/// The project registered at exactly `path`, if any.
+pub(crate) fn project_at(db: &Database, path: &str) -> Result<Option<DbProject>> {
+    Ok(db.get_all_projects(ProjectOrder::default(), true)?
+        .into_iter()
+        .find(|project| project.path == path))
+}
+
+/// The stored settings, or the defaults for a project saved before it had any.
+pub(crate) fn project_settings(row: &DbProject) -> ProjectSettings {
+    row.settings.as_deref()
+        .and_then(|settings| serde_json::from_str(settings).ok())
+        .unwrap_or_default()
+}
+
+/// The full project: its row, with its tool settings and, with `include_sessions`, its most
+/// recently active chat sessions.
+fn assemble_project(db: &Database, row: DbProject, include_sessions: bool) -> Result<Project> {
+    let ai_tools = project_tools(db, &row.id)?;
+    let sessions = if include_sessions {
+        db.get_project_session_summaries(&row.id, MAX_SESSION_SUMMARIES)?
+    } else {
+        Vec::new()
     };
+    let session_count = db.count_project_sessions(&row.id)?;
+    let now = Utc::now();
+    let sessions = sessions.into_iter()
+        .map(|session| SessionSummary {
+            id: session.id,
+            project_id: session.project_id,
+            name: session.name,
+            created_at: session.created_at,
+            last_active: session.last_active,
+            message_count: session.message_count,
+            // Chat sessions don't end, so how recently they were used stands in for their state
+            status: match now - session.last_active {
+                idle if idle < chrono::Duration::days(ACTIVE_SESSION_DAYS) => "active",
+                idle if idle < chrono::Duration::days(PAUSED_SESSION_DAYS) => "paused",
+                _ => "completed",
+            }.to_string(),
+        })
+        .collect();
+    
+    Ok(Project {
+        settings: project_settings(&row),
+        missing: !Path::new(&row.path).is_dir(),
+        last_opened_at: row.last_opened_at,
+        archived: row.archived,
+        id: row.id,
+        name: row.name,
+        path: row.path,
+        description: row.description,
+        created_at: row.created_at,
+        updated_at: row.updated_at,
+        session_count,
+        ai_tools,
+        sessions,
+    })
+}
+
+fn project_tools(db: &Database, project_id: &str) -> Result<Vec<AIToolConfig>> {
+    db.get_project_ai_tools(project_id)?
+        .into_iter()
+        .map(|tool| {
+            Ok(AIToolConfig {
+                tool_id: tool.tool_id,
+                enabled: tool.enabled,
+                priority: tool.priority,
+                custom_settings: serde_json::from_str(&tool.custom_settings)?,
+                env: serde_json::from_str(&tool.env)?,
+            })
+        })
+        .collect()
+}
+
+/// What a project allows of its AI tools. A tool the project has no settings for is allowed;
+/// only one it disabled is refused.
+pub(crate) struct ToolPolicy {
+    project_name: String,
+    settings: ProjectSettings,
+    tools: Vec<AIToolConfig>,
+}
+
+impl ToolPolicy {
+    pub(crate) fn load(db: &Database, project_id: &str) -> Result<Option<Self>> {
+        let Some(row) = db.get_project(project_id)? else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            settings: project_settings(&row),
+            tools: project_tools(db, project_id)?,
+            project_name: row.name,
+        }))
+    }
     
-    Ok(Some(project))
-}
\ No newline at end of file
+    pub(crate) fn allows(&self, tool_id: &str) -> bool {
+        !self.tools.iter().any(|tool| tool.tool_id == tool_id && !tool.enabled)
+    }
+    
+    /// Fails with a validation error naming the project when it disabled the tool.
+    pub(crate) fn check(&self, tool_id: &str) -> Result<(), AppError> {
+        if self.allows(tool_id) {
+            Ok(())
+        } else {
+            Err(AppError::Validation(format!(
+                "{} is disabled in the project {}; enable it in the project's AI tool settings to use it",
+                tool_id, self.project_name
+            )))
+        }
+    }
+    
+    /// The tools the project allows in the order it prefers them: its default tool, then the
+    /// ones it has settings for by priority, then its fallback order.
+    pub(crate) fn preferred_tools(&self) -> Vec<String> {
+        let mut preferred: Vec<String> = Vec::new();
+        let candidates = std::iter::once(&self.settings.default_ai_tool)
+            .chain(self.tools.iter().filter(|tool| tool.enabled).map(|tool| &tool.tool_id))
+            .chain(&self.settings.fallback_order);
+        for tool_id in candidates {
+            if self.allows(tool_id) && !preferred.contains(tool_id) {
+                preferred.push(tool_id.clone());
+            }
+        }
+        preferred
+    }
+    
+    pub(crate) fn default_tool(&self) -> Option<String> {
+        self.preferred_tools().into_iter().next()
+    }
+    
+    /// `config` with the project's `custom_settings` for the tool merged over it. Keys that are
+    /// config fields replace them; anything else goes into `additional_config`. API keys stay
+    /// in the keychain, so one set here is ignored.
+    pub(crate) fn apply(&self, tool_id: &str, config: ToolSpecificConfig) -> ToolSpecificConfig {
+        let Some(tool) = self.tools.iter().find(|tool| tool.tool_id == tool_id) else {
+            return config;
+        };
+        let Ok(serde_json::Value::Object(mut merged)) = serde_json::to_value(&config) else {
+            return config;
+        };
+        let mut additional = match merged.remove("additional_config") {
+            Some(serde_json::Value::Object(additional)) => additional,
+            _ => serde_json::Map::new(),
+        };
+        for (key, value) in &tool.custom_settings {
+            match key.as_str() {
+                "api_key" => {}
+                "additional_config" => {
+                    if let serde_json::Value::Object(values) = value {
+                        additional.extend(values.clone());
+                    }
+                }
+                _ if merged.contains_key(key) => {
+                    merged.insert(key.clone(), value.clone());
+                }
+                _ => {
+                    additional.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        merged.insert("additional_config".to_string(), additional.into());
+        
+        serde_json::from_value(merged.into()).unwrap_or_else(|e| {
+            log::warn!("Ignoring the settings of project {} for {}: {}", self.project_name, tool_id, e);
+            config
+        })
+    }
+}
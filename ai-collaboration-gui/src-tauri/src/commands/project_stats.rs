@@ -0,0 +1,376 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use tokio_util::sync::CancellationToken;
+use crate::commands::search::SearchGuard;
+use crate::commands::system::looks_binary;
+use crate::state::AppState;
+use crate::telemetry::timed;
+use tauri::State;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtensionUsage {
+    pub extension: String, // without the dot; empty for files without one
+    pub bytes: u64,
+    pub file_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeavyDirUsage {
+    pub path: String, // relative to the project
+    pub bytes: u64,
+    pub file_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiskUsage {
+    pub project_id: String,
+    // Files the ignore rules let through; heavy directories are counted separately
+    pub total_bytes: u64,
+    pub file_count: usize,
+    pub dir_count: usize,
+    // The largest extensions by bytes
+    pub by_extension: Vec<ExtensionUsage>,
+    pub heavy_dirs: Vec<HeavyDirUsage>,
+    // The scan was stopped; the figures cover what was walked until then
+    pub cancelled: bool,
+    pub scanned_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LanguageStats {
+    pub language: String,
+    pub files: usize,
+    pub lines: usize,
+    // Code and comments are only told apart for languages with known comment syntax;
+    // for the rest every non-blank line counts as code
+    pub code: usize,
+    pub comments: usize,
+    pub blanks: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectLanguages {
+    pub project_id: String,
+    // By lines, largest first
+    pub languages: Vec<LanguageStats>,
+    pub total: LanguageStats,
+    // Over the size cap or binary; counted as files but not as lines
+    pub skipped_files: usize,
+    pub analyzed_at: DateTime<Utc>,
+}
+
+// Sized on their own even when ignored, since they are usually what fills the disk
+const HEAVY_DIRS: [&str; 7] = ["node_modules", "target", ".git", "dist", "build", ".venv", "__pycache__"];
+const TOP_EXTENSIONS: usize = 10;
+
+// Files past this are counted but not read
+const MAX_ANALYZED_FILE_BYTES: u64 = 1024 * 1024;
+
+// Complete scans by project id
+static DISK_USAGE_CACHE: Lazy<Mutex<HashMap<String, DiskUsage>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+// Language breakdowns by project id, with the project root they were taken of
+static LANGUAGE_CACHE: Lazy<Mutex<HashMap<String, (PathBuf, ProjectLanguages)>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Sizes a project: totals over the files its ignore rules keep, the top extensions by bytes,
+/// and the heavy directories (`node_modules`, `target`, ...) on their own. The last complete
+/// result is returned until `refresh` is set. A scan started with `scan_token` can be stopped
+/// with `cancel_search`, which returns what was counted so far with `cancelled` set.
+#[tauri::command]
+#[timed]
+pub async fn get_project_disk_usage(state: State<'_, AppState>, project_id: String, refresh: Option<bool>, scan_token: Option<String>) -> Result<DiskUsage, String> {
+    if !refresh.unwrap_or(false) {
+        if let Some(usage) = DISK_USAGE_CACHE.lock().unwrap().get(&project_id) {
+            return Ok(usage.clone());
+        }
+    }
+
+    log::info!("Measuring disk usage of project {}", project_id);
+    let project = state.db.get_project(&project_id)
+        .map_err(|e| format!("Failed to load project: {}", e))?
+        .ok_or_else(|| format!("Project not found: {}", project_id))?;
+
+    let scan = SearchGuard::start(&state.searches, scan_token);
+    let cancel = scan.cancel.clone();
+    let root = PathBuf::from(project.path);
+    let id = project_id.clone();
+    let usage = tokio::task::spawn_blocking(move || measure(&id, &root, &cancel))
+        .await
+        .map_err(|e| format!("Failed to measure disk usage: {}", e))?;
+
+    if !usage.cancelled {
+        DISK_USAGE_CACHE.lock().unwrap().insert(project_id, usage.clone());
+    }
+    Ok(usage)
+}
+
+/// Files and lines per language over the files the project's ignore rules keep, with code,
+/// comment and blank lines told apart for the common languages. Files are classified by
+/// extension; unknown ones are left out. Cached until a file in the project is written through
+/// the app or `refresh` is set.
+#[tauri::command]
+#[timed]
+pub async fn analyze_project_languages(state: State<'_, AppState>, project_id: String, refresh: Option<bool>) -> Result<ProjectLanguages, String> {
+    if !refresh.unwrap_or(false) {
+        if let Some((_, languages)) = LANGUAGE_CACHE.lock().unwrap().get(&project_id) {
+            return Ok(languages.clone());
+        }
+    }
+
+    log::info!("Analyzing languages of project {}", project_id);
+    let project = state.db.get_project(&project_id)
+        .map_err(|e| format!("Failed to load project: {}", e))?
+        .ok_or_else(|| format!("Project not found: {}", project_id))?;
+
+    let root = PathBuf::from(project.path);
+    let (id, walk_root) = (project_id.clone(), root.clone());
+    let languages = tokio::task::spawn_blocking(move || analyze(&id, &walk_root))
+        .await
+        .map_err(|e| format!("Failed to analyze languages: {}", e))?;
+
+    let root = std::fs::canonicalize(&root).unwrap_or(root);
+    LANGUAGE_CACHE.lock().unwrap().insert(project_id, (root, languages.clone()));
+    Ok(languages)
+}
+
+/// Drops the cached language breakdown of every project containing `path`, after it was changed.
+pub(crate) fn invalidate(path: &Path) {
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    LANGUAGE_CACHE.lock().unwrap().retain(|_, (root, _)| !path.starts_with(root));
+}
+
+fn measure(project_id: &str, root: &Path, cancel: &CancellationToken) -> DiskUsage {
+    let mut usage = DiskUsage {
+        project_id: project_id.to_string(),
+        total_bytes: 0,
+        file_count: 0,
+        dir_count: 0,
+        by_extension: Vec::new(),
+        heavy_dirs: Vec::new(),
+        cancelled: false,
+        scanned_at: Utc::now(),
+    };
+    let mut extensions: HashMap<String, ExtensionUsage> = HashMap::new();
+
+    let walker = ignore::WalkBuilder::new(root)
+        .require_git(false)
+        .filter_entry(|entry| !is_heavy(entry.file_name()))
+        .build();
+    for entry in walker {
+        if cancel.is_cancelled() {
+            usage.cancelled = true;
+            break;
+        }
+        let Ok(entry) = entry else {
+            continue;
+        };
+        let Some(file_type) = entry.file_type() else {
+            continue;
+        };
+
+        if file_type.is_dir() {
+            if entry.depth() > 0 {
+                usage.dir_count += 1;
+            }
+            // Heavy directories are skipped by the walk but sized here, wherever they are
+            for name in HEAVY_DIRS {
+                let dir = entry.path().join(name);
+                if dir.is_dir() {
+                    let (bytes, file_count) = dir_size(&dir, cancel);
+                    usage.heavy_dirs.push(HeavyDirUsage {
+                        path: dir.strip_prefix(root).unwrap_or(&dir).to_string_lossy().to_string(),
+                        bytes,
+                        file_count,
+                    });
+                }
+            }
+        } else if file_type.is_file() {
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            usage.total_bytes += size;
+            usage.file_count += 1;
+
+            let extension = entry.path().extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            let totals = extensions.entry(extension.clone()).or_insert(ExtensionUsage {
+                extension,
+                bytes: 0,
+                file_count: 0,
+            });
+            totals.bytes += size;
+            totals.file_count += 1;
+        }
+    }
+
+    let mut by_extension: Vec<ExtensionUsage> = extensions.into_values().collect();
+    by_extension.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.extension.cmp(&b.extension)));
+    by_extension.truncate(TOP_EXTENSIONS);
+    usage.by_extension = by_extension;
+    usage.heavy_dirs.sort_by_key(|dir| std::cmp::Reverse(dir.bytes));
+    usage.cancelled |= cancel.is_cancelled();
+    usage
+}
+
+fn is_heavy(name: &std::ffi::OsStr) -> bool {
+    HEAVY_DIRS.iter().any(|heavy| name == *heavy)
+}
+
+/// Bytes and files under `dir`, ignore rules and all. Symlinks aren't followed.
+fn dir_size(dir: &Path, cancel: &CancellationToken) -> (u64, usize) {
+    let (mut bytes, mut file_count) = (0, 0);
+    for entry in ignore::WalkBuilder::new(dir).standard_filters(false).build() {
+        if cancel.is_cancelled() {
+            break;
+        }
+        let Ok(entry) = entry else {
+            continue;
+        };
+        if entry.file_type().is_some_and(|t| t.is_file()) {
+            bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+            file_count += 1;
+        }
+    }
+    (bytes, file_count)
+}
+
+/// How a language marks comments: line prefixes and an optional block delimiter pair.
+struct CommentSyntax {
+    line: &'static [&'static str],
+    block: Option<(&'static str, &'static str)>,
+}
+
+const C_LIKE: CommentSyntax = CommentSyntax { line: &["//"], block: Some(("/*", "*/")) };
+const HASH: CommentSyntax = CommentSyntax { line: &["#"], block: None };
+const PYTHON: CommentSyntax = CommentSyntax { line: &["#"], block: Some(("\"\"\"", "\"\"\"")) };
+const SQL: CommentSyntax = CommentSyntax { line: &["--"], block: Some(("/*", "*/")) };
+const MARKUP: CommentSyntax = CommentSyntax { line: &[], block: Some(("<!--", "-->")) };
+
+/// The language files with this extension are written in, by `analyze_project_languages`' names.
+pub(crate) fn language_name(extension: &str) -> Option<&'static str> {
+    language_of(extension).map(|(language, _)| language)
+}
+
+fn language_of(extension: &str) -> Option<(&'static str, Option<&'static CommentSyntax>)> {
+    let language = match extension {
+        "rs" => ("Rust", Some(&C_LIKE)),
+        "ts" | "tsx" | "mts" | "cts" => ("TypeScript", Some(&C_LIKE)),
+        "js" | "jsx" | "mjs" | "cjs" => ("JavaScript", Some(&C_LIKE)),
+        "py" | "pyi" => ("Python", Some(&PYTHON)),
+        "go" => ("Go", Some(&C_LIKE)),
+        "java" => ("Java", Some(&C_LIKE)),
+        "kt" | "kts" => ("Kotlin", Some(&C_LIKE)),
+        "swift" => ("Swift", Some(&C_LIKE)),
+        "c" | "h" => ("C", Some(&C_LIKE)),
+        "cpp" | "cc" | "cxx" | "hpp" | "hh" => ("C++", Some(&C_LIKE)),
+        "cs" => ("C#", Some(&C_LIKE)),
+        "css" | "scss" | "less" => ("CSS", Some(&C_LIKE)),
+        "rb" => ("Ruby", Some(&HASH)),
+        "sh" | "bash" | "zsh" => ("Shell", Some(&HASH)),
+        "toml" => ("TOML", Some(&HASH)),
+        "yml" | "yaml" => ("YAML", Some(&HASH)),
+        "sql" => ("SQL", Some(&SQL)),
+        "html" | "htm" | "xml" | "svg" => ("HTML", Some(&MARKUP)),
+        "vue" => ("Vue", None),
+        "svelte" => ("Svelte", None),
+        "php" => ("PHP", None),
+        "json" => ("JSON", None),
+        "md" | "mdx" => ("Markdown", None),
+        _ => return None,
+    };
+    Some(language)
+}
+
+fn analyze(project_id: &str, root: &Path) -> ProjectLanguages {
+    let mut languages: HashMap<&'static str, LanguageStats> = HashMap::new();
+    let mut skipped_files = 0;
+
+    let walker = ignore::WalkBuilder::new(root).require_git(false).build();
+    for entry in walker.flatten() {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let extension = entry.path().extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let Some((language, syntax)) = language_of(&extension) else {
+            continue;
+        };
+        let stats = languages.entry(language).or_insert_with(|| LanguageStats {
+            language: language.to_string(),
+            ..Default::default()
+        });
+        stats.files += 1;
+
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        if size > MAX_ANALYZED_FILE_BYTES {
+            skipped_files += 1;
+            continue;
+        }
+        let content = match std::fs::read(entry.path()) {
+            Ok(content) if !looks_binary(&content) => content,
+            _ => {
+                skipped_files += 1;
+                continue;
+            }
+        };
+        count_lines(&String::from_utf8_lossy(&content), syntax, stats);
+    }
+
+    let mut total = LanguageStats { language: "Total".to_string(), ..Default::default() };
+    for stats in languages.values() {
+        total.files += stats.files;
+        total.lines += stats.lines;
+        total.code += stats.code;
+        total.comments += stats.comments;
+        total.blanks += stats.blanks;
+    }
+    let mut languages: Vec<LanguageStats> = languages.into_values().collect();
+    languages.sort_by(|a, b| b.lines.cmp(&a.lines).then_with(|| a.language.cmp(&b.language)));
+
+    ProjectLanguages {
+        project_id: project_id.to_string(),
+        languages,
+        total,
+        skipped_files,
+        analyzed_at: Utc::now(),
+    }
+}
+
+/// Approximate: comment markers inside string literals are taken for comments, and a line
+/// with code before a comment counts as code.
+fn count_lines(text: &str, syntax: Option<&CommentSyntax>, stats: &mut LanguageStats) {
+    let mut in_block: Option<&str> = None;
+    for line in text.lines() {
+        stats.lines += 1;
+        let trimmed = line.trim();
+        if let Some(end) = in_block {
+            stats.comments += 1;
+            if trimmed.contains(end) {
+                in_block = None;
+            }
+            continue;
+        }
+        if trimmed.is_empty() {
+            stats.blanks += 1;
+            continue;
+        }
+        let Some(syntax) = syntax else {
+            stats.code += 1;
+            continue;
+        };
+
+        if syntax.line.iter().any(|prefix| trimmed.starts_with(prefix)) {
+            stats.comments += 1;
+        } else if let Some((start, end)) = syntax.block.filter(|(start, _)| trimmed.starts_with(start)) {
+            stats.comments += 1;
+            if !trimmed[start.len()..].contains(end) {
+                in_block = Some(end);
+            }
+        } else {
+            stats.code += 1;
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use chrono::{DateTime, Duration, Utc};
+use crate::commands::project::project_settings;
+use crate::database::{Database, DbProject, ProjectOrder, RetentionCounts};
+use crate::error::AppError;
+use crate::state::AppState;
+use crate::telemetry::timed;
+use tauri::State;
+
+/// Deletes chat messages with their attachments, AI interaction logs, swarm events and memory
+/// entries older than each project's `memory_retention` days, or only the given project's. Pinned
+/// sessions and swarms are kept whole, and a retention of 0 keeps a project's data forever.
+#[tauri::command]
+#[timed]
+pub async fn run_retention_cleanup(state: State<'_, AppState>, project_id: Option<String>) -> Result<RetentionCounts, AppError> {
+    log::info!("Running retention cleanup for {}", project_id.as_deref().unwrap_or("all projects"));
+
+    run_cleanup(state.db.clone(), project_id).await
+}
+
+pub async fn run_cleanup(db: Database, project_id: Option<String>) -> Result<RetentionCounts, AppError> {
+    let projects = match project_id {
+        Some(id) => vec![db.get_project(&id)?
+            .ok_or_else(|| AppError::Internal(format!("Project not found: {}", id)))?],
+        None => db.get_all_projects(ProjectOrder::default(), true)?,
+    };
+    let now = Utc::now();
+    tokio::task::spawn_blocking(move || cleanup(&db, &projects, now))
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to run retention cleanup: {}", e)))?
+}
+
+fn cleanup(db: &Database, projects: &[DbProject], now: DateTime<Utc>) -> Result<RetentionCounts, AppError> {
+    let mut total = RetentionCounts::default();
+    for project in projects {
+        let days = project_settings(project).memory_retention;
+        if days <= 0 {
+            continue;
+        }
+        let counts = db.prune_project_data(&project.id, now - Duration::days(days.into()))?;
+        log::debug!("Retention cleanup of project {}: {:?}", project.id, counts);
+        total.add(&counts);
+    }
+    Ok(total)
+}
@@ -0,0 +1,333 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use anyhow::{anyhow, Result};
+use tokio_util::sync::CancellationToken;
+use crate::commands::system::{looks_binary, path_item, FileItem};
+use crate::state::AppState;
+use crate::telemetry::timed;
+use tauri::State;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileSearchResult {
+    pub items: Vec<FileItem>,
+    pub has_more: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContentSearchOptions {
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(default)]
+    pub case_insensitive: bool,
+    #[serde(default)]
+    pub whole_word: bool,
+    #[serde(default)]
+    pub max_matches: Option<usize>,
+    #[serde(default)]
+    pub search_token: Option<String>,
+}
+
+/// One occurrence; `byte_range` is the match's position within `line_text`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentMatch {
+    pub path: String,
+    pub line_number: usize,
+    pub line_text: String,
+    pub byte_range: std::ops::Range<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentSearchResult {
+    pub matches: Vec<ContentMatch>,
+    // Stopped at max_matches
+    pub truncated: bool,
+    pub files_searched: usize,
+    pub binary_files: usize,
+    // Not valid UTF-8
+    pub skipped_files: usize,
+}
+
+const DEFAULT_MAX_RESULTS: usize = 100;
+const DEFAULT_MAX_MATCHES: usize = 1000;
+
+/// Searches still running, by the token the frontend passed; a new search with the same token
+/// cancels the previous one.
+#[derive(Default, Clone)]
+pub struct SearchRegistry(Arc<Mutex<RunningSearches>>);
+
+#[derive(Default)]
+struct RunningSearches {
+    next_id: u64,
+    by_token: HashMap<String, (u64, CancellationToken)>,
+}
+
+impl SearchRegistry {
+    /// Cancels every search and archive operation still running. Used on shutdown.
+    pub fn cancel_all(&self) {
+        for (_, cancel) in self.0.lock().unwrap().by_token.values() {
+            cancel.cancel();
+        }
+    }
+
+    fn cancel(&self, token: &str) {
+        if let Some((_, cancel)) = self.0.lock().unwrap().by_token.remove(token) {
+            cancel.cancel();
+        }
+    }
+}
+
+/// Registers a search under its token for as long as it is alive.
+pub(crate) struct SearchGuard {
+    searches: SearchRegistry,
+    token: Option<String>,
+    id: u64,
+    pub cancel: CancellationToken,
+}
+
+impl SearchGuard {
+    pub(crate) fn start(searches: &SearchRegistry, token: Option<String>) -> Self {
+        let cancel = CancellationToken::new();
+        let mut running = searches.0.lock().unwrap();
+        let id = running.next_id;
+        running.next_id += 1;
+        if let Some(token) = &token {
+            if let Some((_, previous)) = running.by_token.insert(token.clone(), (id, cancel.clone())) {
+                previous.cancel();
+            }
+        }
+        drop(running);
+        SearchGuard { searches: searches.clone(), token, id, cancel }
+    }
+}
+
+impl Drop for SearchGuard {
+    // Leaves the entry alone if a newer search has taken the token over
+    fn drop(&mut self) {
+        let Some(token) = &self.token else {
+            return;
+        };
+        let mut running = self.searches.0.lock().unwrap();
+        if running.by_token.get(token).is_some_and(|(id, _)| *id == self.id) {
+            running.by_token.remove(token);
+        }
+    }
+}
+
+/// Finds files and directories under `root` whose name matches `pattern`, honouring ignore
+/// files. Patterns with `*`, `?`, `[` or `{` are globs, matched against the path relative to
+/// `root` when they contain a `/` and against the name otherwise; anything else is matched
+/// case-insensitively against names, ranked exact > prefix > substring > in-order letters,
+/// then shallower paths first.
+#[tauri::command]
+#[timed]
+pub async fn search_files(state: State<'_, AppState>, root: String, pattern: String, max_results: Option<usize>, search_token: Option<String>) -> Result<FileSearchResult, String> {
+    log::info!("Searching files in {} for {}", root, pattern);
+
+    let search = SearchGuard::start(&state.searches, search_token);
+    let cancel = search.cancel.clone();
+    let root = PathBuf::from(root);
+    let max_results = max_results.unwrap_or(DEFAULT_MAX_RESULTS);
+    let result = tokio::task::spawn_blocking(move || find_files(&root, &pattern, max_results, &cancel))
+        .await
+        .map_err(|e| format!("Failed to search files: {}", e))?
+        .map_err(|e| format!("Failed to search files: {}", e))?;
+
+    Ok(result)
+}
+
+/// Looks for `query` (a literal unless `options.regex`) in the text files under `root`, skipping
+/// ignored paths and binary files. Files are read line by line, so size doesn't matter.
+#[tauri::command]
+#[timed]
+pub async fn search_file_contents(state: State<'_, AppState>, root: String, query: String, options: Option<ContentSearchOptions>) -> Result<ContentSearchResult, String> {
+    log::info!("Searching file contents in {} for {}", root, query);
+
+    let options = options.unwrap_or_default();
+    let search = SearchGuard::start(&state.searches, options.search_token.clone());
+    let cancel = search.cancel.clone();
+    let root = PathBuf::from(root);
+    let result = tokio::task::spawn_blocking(move || grep(&root, &query, &options, &cancel))
+        .await
+        .map_err(|e| format!("Failed to search file contents: {}", e))?
+        .map_err(|e| format!("Failed to search file contents: {}", e))?;
+
+    Ok(result)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn cancel_search(state: State<'_, AppState>, search_token: String) -> Result<(), String> {
+    state.searches.cancel(&search_token);
+    Ok(())
+}
+
+enum NameMatcher {
+    Glob { matcher: globset::GlobMatcher, whole_path: bool },
+    Fuzzy(String),
+}
+
+impl NameMatcher {
+    fn new(pattern: &str) -> Result<Self> {
+        if !pattern.contains(['*', '?', '[', '{']) {
+            return Ok(NameMatcher::Fuzzy(pattern.to_lowercase()));
+        }
+        let glob = globset::GlobBuilder::new(pattern)
+            .case_insensitive(true)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| anyhow!("Invalid glob pattern {:?}: {}", pattern, e))?;
+        Ok(NameMatcher::Glob { matcher: glob.compile_matcher(), whole_path: pattern.contains('/') })
+    }
+
+    /// Lower is better; `None` means no match.
+    fn rank(&self, relative: &Path, name: &str) -> Option<u8> {
+        match self {
+            NameMatcher::Glob { matcher, whole_path } => {
+                let matched = if *whole_path { matcher.is_match(relative) } else { matcher.is_match(name) };
+                matched.then_some(0)
+            }
+            NameMatcher::Fuzzy(query) => {
+                let name = name.to_lowercase();
+                if name == *query {
+                    Some(0)
+                } else if name.starts_with(query.as_str()) {
+                    Some(1)
+                } else if name.contains(query.as_str()) {
+                    Some(2)
+                } else {
+                    let mut letters = name.chars();
+                    query.chars().all(|c| letters.any(|n| n == c)).then_some(3)
+                }
+            }
+        }
+    }
+}
+
+fn find_files(root: &Path, pattern: &str, max_results: usize, cancel: &CancellationToken) -> Result<FileSearchResult> {
+    if !root.is_dir() {
+        return Err(anyhow!("Path is not a directory"));
+    }
+    if pattern.trim().is_empty() {
+        return Err(anyhow!("Search pattern cannot be empty"));
+    }
+    let matcher = NameMatcher::new(pattern.trim())?;
+
+    let mut matches: Vec<(u8, usize, PathBuf)> = Vec::new();
+    for entry in ignore::WalkBuilder::new(root).require_git(false).build() {
+        if cancel.is_cancelled() {
+            return Err(anyhow!("Search cancelled"));
+        }
+        let Ok(entry) = entry else {
+            continue;
+        };
+        if entry.depth() == 0 {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+        let name = entry.file_name().to_string_lossy();
+        if let Some(rank) = matcher.rank(relative, &name) {
+            matches.push((rank, entry.depth(), entry.into_path()));
+        }
+    }
+    matches.sort();
+
+    let has_more = matches.len() > max_results;
+    let items = matches.into_iter()
+        .take(max_results)
+        .filter_map(|(_, _, path)| path_item(&path).ok())
+        .collect();
+    Ok(FileSearchResult { items, has_more })
+}
+
+fn grep(root: &Path, query: &str, options: &ContentSearchOptions, cancel: &CancellationToken) -> Result<ContentSearchResult> {
+    if !root.is_dir() {
+        return Err(anyhow!("Path is not a directory"));
+    }
+    if query.is_empty() {
+        return Err(anyhow!("Search query cannot be empty"));
+    }
+    let pattern = if options.regex { query.to_string() } else { regex::escape(query) };
+    let pattern = if options.whole_word { format!(r"\b(?:{})\b", pattern) } else { pattern };
+    let regex = regex::RegexBuilder::new(&pattern)
+        .case_insensitive(options.case_insensitive)
+        .build()
+        .map_err(|e| anyhow!("Invalid search pattern: {}", e))?;
+    let max_matches = options.max_matches.unwrap_or(DEFAULT_MAX_MATCHES);
+
+    let mut result = ContentSearchResult {
+        matches: Vec::new(),
+        truncated: false,
+        files_searched: 0,
+        binary_files: 0,
+        skipped_files: 0,
+    };
+    for entry in ignore::WalkBuilder::new(root).require_git(false).build() {
+        if cancel.is_cancelled() {
+            return Err(anyhow!("Search cancelled"));
+        }
+        let Ok(entry) = entry else {
+            continue;
+        };
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        match grep_file(entry.path(), &regex, max_matches - result.matches.len(), cancel) {
+            Ok(FileScan::Text(matches, complete)) => {
+                result.files_searched += 1;
+                result.matches.extend(matches);
+                if !complete {
+                    result.truncated = true;
+                    break;
+                }
+            }
+            Ok(FileScan::Binary) => result.binary_files += 1,
+            Ok(FileScan::NotUtf8) => result.skipped_files += 1,
+            Err(e) => log::debug!("Skipping {}: {}", entry.path().display(), e),
+        }
+    }
+    Ok(result)
+}
+
+enum FileScan {
+    // Matches, and whether the file was read to the end before hitting the limit
+    Text(Vec<ContentMatch>, bool),
+    Binary,
+    NotUtf8,
+}
+
+fn grep_file(path: &Path, regex: &regex::Regex, limit: usize, cancel: &CancellationToken) -> std::io::Result<FileScan> {
+    use std::io::BufRead;
+
+    let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+    if looks_binary(reader.fill_buf()?) {
+        return Ok(FileScan::Binary);
+    }
+
+    let mut matches = Vec::new();
+    let mut buffer = Vec::new();
+    let mut line_number = 0;
+    loop {
+        buffer.clear();
+        if reader.read_until(b'\n', &mut buffer)? == 0 || cancel.is_cancelled() {
+            return Ok(FileScan::Text(matches, true));
+        }
+        line_number += 1;
+        let Ok(line) = std::str::from_utf8(&buffer) else {
+            return Ok(FileScan::NotUtf8);
+        };
+        let line = line.trim_end_matches(['\n', '\r']);
+        for found in regex.find_iter(line) {
+            if matches.len() >= limit {
+                return Ok(FileScan::Text(matches, false));
+            }
+            matches.push(ContentMatch {
+                path: path.to_string_lossy().to_string(),
+                line_number,
+                line_text: line.to_string(),
+                byte_range: found.range(),
+            });
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! App-wide preferences. Each field is its own row in `app_settings`, under the field's name, so
+//! rows written by other versions are left alone and a field missing from the table takes its
+//! default. The current values are kept in `AppState`, where background jobs watch them.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::Path;
+use std::str::FromStr;
+use tauri::{AppHandle, Emitter, State};
+use crate::database::Database;
+use crate::error::AppError;
+use crate::state::AppState;
+use crate::telemetry::timed;
+
+const MAX_JOB_INTERVAL_HOURS: i64 = 24 * 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    pub theme: Theme,
+    // Where the frontend proposes new projects
+    pub default_project_dir: Option<String>,
+    // One of off, error, warn, info, debug, trace
+    pub log_level: String,
+    pub telemetry_enabled: bool,
+    // Show replies as they arrive instead of all at once
+    pub streaming: bool,
+    // `memory_retention` of new projects, in days; 0 keeps their data forever
+    pub default_memory_retention: i32,
+    pub retention_interval_hours: i64,
+    pub tool_health_interval_secs: u64,
+    pub db_backup_interval_hours: i64,
+    pub workspace_cleanup_interval_hours: i64,
+    // Swarm workspaces older than this are removed; 0 keeps them until removed by hand
+    pub workspace_max_age_days: u32,
+    // Closing the main window hides it to the tray instead of quitting
+    pub close_to_tray: bool,
+    // Silences every notification, whatever `notify_on` says
+    pub notifications_muted: bool,
+    // Replaced as a whole when updated
+    pub notify_on: NotifyOn,
+}
+
+/// What gets an OS notification when it finishes while the app is in the background.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotifyOn {
+    pub swarm_complete: bool,
+    pub task_failed: bool,
+    // AI commands that take at least this long; 0 turns them off
+    pub command_complete_over_seconds: u64,
+}
+
+impl Default for NotifyOn {
+    fn default() -> Self {
+        Self {
+            swarm_complete: true,
+            task_failed: true,
+            command_complete_over_seconds: 60,
+        }
+    }
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            theme: Theme::System,
+            default_project_dir: None,
+            log_level: "info".to_string(),
+            telemetry_enabled: false,
+            streaming: true,
+            default_memory_retention: 30,
+            retention_interval_hours: 24,
+            tool_health_interval_secs: 30,
+            db_backup_interval_hours: 24,
+            workspace_cleanup_interval_hours: 24,
+            workspace_max_age_days: 30,
+            close_to_tray: false,
+            notifications_muted: false,
+            notify_on: NotifyOn::default(),
+        }
+    }
+}
+
+impl AppSettings {
+    pub fn log_filter(&self) -> log::LevelFilter {
+        log::LevelFilter::from_str(&self.log_level).unwrap_or(log::LevelFilter::Info)
+    }
+
+    fn validate(&self) -> Result<(), AppError> {
+        if log::LevelFilter::from_str(&self.log_level).is_err() {
+            return Err(AppError::Validation(format!("Unknown log level: {}", self.log_level)));
+        }
+        if let Some(dir) = self.default_project_dir.as_deref().filter(|dir| !dir.is_empty()) {
+            if !Path::new(dir).is_absolute() {
+                return Err(AppError::Validation("The default project directory must be an absolute path".to_string()));
+            }
+        }
+        if self.default_memory_retention < 0 {
+            return Err(AppError::Validation("The default memory retention cannot be negative".to_string()));
+        }
+        for (job, hours) in [
+            ("retention", self.retention_interval_hours),
+            ("database backup", self.db_backup_interval_hours),
+            ("workspace cleanup", self.workspace_cleanup_interval_hours),
+        ] {
+            if !(1..=MAX_JOB_INTERVAL_HOURS).contains(&hours) {
+                return Err(AppError::Validation(format!("The {} interval must be between 1 and {} hours", job, MAX_JOB_INTERVAL_HOURS)));
+            }
+        }
+        if !(5..=3600).contains(&self.tool_health_interval_secs) {
+            return Err(AppError::Validation("The health check interval must be between 5 and 3600 seconds".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[tauri::command]
+#[timed]
+pub async fn get_app_settings(state: State<'_, AppState>) -> Result<AppSettings, AppError> {
+    Ok(state.settings.borrow().clone())
+}
+
+/// Sets the fields given in `partial`, leaving the others as they are, and returns the result.
+/// Nothing is stored unless all of them are valid. Emits `settings:changed`.
+#[tauri::command]
+#[timed]
+pub async fn update_app_settings(app: AppHandle, state: State<'_, AppState>, partial: Map<String, Value>) -> Result<AppSettings, AppError> {
+    log::info!("Updating app settings: {:?}", partial.keys().collect::<Vec<_>>());
+
+    update(&app, &state, partial)
+}
+
+pub(crate) fn update(app: &AppHandle, state: &AppState, partial: Map<String, Value>) -> Result<AppSettings, AppError> {
+    let current = to_map(&state.settings.borrow());
+    let mut merged = current.clone();
+    for (key, value) in partial {
+        if !merged.contains_key(&key) {
+            return Err(AppError::Validation(format!("Unknown setting: {}", key)));
+        }
+        merged.insert(key, value);
+    }
+    let settings: AppSettings = serde_json::from_value(Value::Object(merged.clone()))
+        .map_err(|e| AppError::Validation(format!("Invalid settings: {}", e)))?;
+    settings.validate()?;
+
+    let changed: Vec<(String, String)> = merged.iter()
+        .filter(|(key, value)| current.get(*key) != Some(value))
+        .map(|(key, value)| (key.clone(), value.to_string()))
+        .collect();
+    if changed.is_empty() {
+        return Ok(settings);
+    }
+    state.db.set_settings(&changed)?;
+
+    apply_app_settings(&settings);
+    state.settings.send_replace(settings.clone());
+    if let Err(e) = app.emit("settings:changed", &settings) {
+        log::warn!("Failed to emit settings:changed: {}", e);
+    }
+    Ok(settings)
+}
+
+/// The stored settings over the defaults. A stored value that doesn't fit its field is skipped
+/// with a warning, so one bad row can't reset the rest.
+pub fn load_app_settings(db: &Database) -> AppSettings {
+    let mut merged = to_map(&AppSettings::default());
+    let keys: Vec<String> = merged.keys().cloned().collect();
+    for key in keys {
+        let stored = match db.get_setting(&key) {
+            Ok(Some(stored)) => stored,
+            Ok(None) => continue,
+            Err(e) => {
+                log::warn!("Failed to read setting {}: {}", key, e);
+                continue;
+            }
+        };
+        let mut candidate = merged.clone();
+        candidate.insert(key.clone(), serde_json::from_str(&stored).unwrap_or(Value::String(stored)));
+        match serde_json::from_value::<AppSettings>(Value::Object(candidate.clone())) {
+            Ok(settings) if settings.validate().is_ok() => merged = candidate,
+            _ => log::warn!("Ignoring the stored value of setting {}", key),
+        }
+    }
+    serde_json::from_value(Value::Object(merged)).unwrap_or_default()
+}
+
+/// What takes effect outside the settings themselves; run at startup and after each change.
+pub fn apply_app_settings(settings: &AppSettings) {
+    log::set_max_level(settings.log_filter());
+}
+
+fn to_map(settings: &AppSettings) -> Map<String, Value> {
+    match serde_json::to_value(settings) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    }
+}
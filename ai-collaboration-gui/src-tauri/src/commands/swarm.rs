@@ -1,8 +1,21 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Mutex as StdMutex;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use crate::approvals;
+use crate::commands::{agent_actions, ai_tools, chat};
+use crate::commands::ai_tools::AICommand;
+use crate::commands::project::ToolPolicy;
+use crate::commands::pricing::{self, TokenUsage};
+use crate::database::{Database, DbMemoryEntry, DbSwarm, DbSwarmTaskStats, DbSwarmAgent, DbSwarmEvent, DbSwarmTask, DbSwarmTemplate, DbTaskResult, DbWorkflowNode};
+use crate::error::{typed_or, AppError};
+use crate::events::{Action, DataChanged, Entity};
+use crate::notifications;
+use crate::state::AppState;
+use crate::telemetry::timed;
+use tauri::{AppHandle, Emitter, Manager, State};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Swarm {
@@ -15,6 +28,10 @@ pub struct Swarm {
     pub workflow: Vec<WorkflowNode>,
     pub memory: SwarmMemory,
     pub metrics: SwarmMetrics,
+    #[serde(default)]
+    pub strategy: Option<String>,
+    #[serde(default)]
+    pub task_summary: TaskSummary,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -42,6 +59,130 @@ pub struct SwarmConfig {
     pub strategy: Option<String>, // 'collaborative' | 'hierarchical' | 'competitive'
 }
 
+/// Partial update for `update_swarm`; `None` leaves the field unchanged.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SwarmUpdate {
+    pub name: Option<String>,
+    pub objective: Option<String>,
+    pub strategy: Option<String>,
+    pub memory_capacity: Option<i32>,
+    pub retention_policy: Option<String>,
+    pub max_agents: Option<usize>,
+}
+
+/// Settings persisted in the `config` column of the swarms table.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SwarmSettings {
+    pub strategy: Option<String>,
+    pub namespace: Option<String>,
+    pub agent_types: Vec<String>,
+    pub memory_capacity: i32,
+    pub retention_policy: String,
+    pub max_agents: usize,
+}
+
+impl Default for SwarmSettings {
+    fn default() -> Self {
+        Self {
+            strategy: None,
+            namespace: None,
+            agent_types: vec![],
+            memory_capacity: 1000,
+            retention_policy: "lru".to_string(),
+            max_agents: DEFAULT_MAX_AGENTS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaskSummary {
+    pub total: i32,
+    pub pending: i32,
+    pub in_progress: i32,
+    pub completed: i32,
+    pub failed: i32,
+    pub cancelled: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwarmProgress {
+    pub swarm_id: String,
+    pub status: String,
+    pub tasks: TaskSummary,
+    pub percent_complete: f32, // Weighted by estimated_duration where known
+    pub running: Vec<RunningTask>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub elapsed_seconds: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunningTask {
+    pub task_id: String,
+    pub title: String,
+    pub agent_id: Option<String>,
+    pub agent_type: Option<String>,
+    pub started_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwarmTemplate {
+    pub id: String,
+    pub name: String,
+    pub config: SwarmConfig,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwarmEventType {
+    Created,
+    Updated,
+    StatusChanged,
+    TaskAssigned,
+    TaskStarted,
+    TaskCompleted,
+    TaskFailed,
+    AgentAdded,
+    AgentRemoved,
+    MemoryWritten,
+    WorkflowUpdated,
+    TaskReassigned,
+    Warning,
+}
+
+impl SwarmEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SwarmEventType::Created => "created",
+            SwarmEventType::Updated => "updated",
+            SwarmEventType::StatusChanged => "status_changed",
+            SwarmEventType::TaskAssigned => "task_assigned",
+            SwarmEventType::TaskStarted => "task_started",
+            SwarmEventType::TaskCompleted => "task_completed",
+            SwarmEventType::TaskFailed => "task_failed",
+            SwarmEventType::AgentAdded => "agent_added",
+            SwarmEventType::AgentRemoved => "agent_removed",
+            SwarmEventType::MemoryWritten => "memory_written",
+            SwarmEventType::WorkflowUpdated => "workflow_updated",
+            SwarmEventType::TaskReassigned => "task_reassigned",
+            SwarmEventType::Warning => "warning",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwarmEvent {
+    pub id: String,
+    pub swarm_id: String,
+    pub event_type: String,
+    pub task_id: Option<String>,
+    pub agent_id: Option<String>,
+    pub payload: serde_json::Value,
+    pub timestamp: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Task {
     pub id: String,
@@ -65,6 +206,10 @@ pub struct TaskResult {
     pub agent_id: String,
     pub output: serde_json::Value,
     pub confidence: f32,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub usage: TokenUsage,
     pub timestamp: DateTime<Utc>,
 }
 
@@ -94,6 +239,12 @@ pub struct SwarmMetrics {
     pub collaboration_score: f32,
     pub total_execution_time: i32,
     pub cost_estimate: Option<f32>,
+    #[serde(default)]
+    pub prompt_tokens: i64,
+    #[serde(default)]
+    pub completion_tokens: i64,
+    #[serde(default)]
+    pub total_tokens: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -122,6 +273,12 @@ pub struct Position {
     pub y: f32,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowLayoutResult {
+    pub updated: usize,
+    pub skipped: Vec<String>, // Node IDs not found in the workflow
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Connection {
     pub id: String,
@@ -131,226 +288,1830 @@ pub struct Connection {
     pub label: Option<String>,
 }
 
+
+// Each entry added to a swarm's timeline, as it is recorded
+const TIMELINE_EVENT: &str = "swarm:event";
+const RETENTION_POLICIES: [&str; 3] = ["fifo", "lru", "priority"];
+const AGENT_TYPES: [&str; 5] = ["queen", "architect", "developer", "reviewer", "tester"];
+const DEFAULT_MAX_AGENTS: usize = 12;
+const STRATEGIES: [&str; 3] = ["collaborative", "hierarchical", "competitive"];
+/// How many recent agent messages are handed to an agent along with its task.
+const PROMPT_MESSAGE_CONTEXT: i64 = 10;
+/// System prompt templates per agent type; they win over a tool's default system prompt.
+const ROLE_SYSTEM_PROMPTS: [(&str, &str); 5] = [
+    ("queen", "You coordinate a swarm of AI agents. Break the objective into concrete tasks, hand each to the agent best suited for it, and combine their results into one answer."),
+    ("architect", "You are the architect of a software project. Propose designs that fit the existing code, explain the trade-offs, and keep interfaces small."),
+    ("developer", "You are a software developer. Write working, idiomatic code that follows the conventions already in the project, and say what you changed."),
+    ("reviewer", "You are a code reviewer. Look for bugs, unclear code and missing edge cases, and give specific, actionable feedback."),
+    ("tester", "You are a tester. Work out how the change could fail, write or describe tests that would catch it, and report what you verified."),
+];
+
 #[tauri::command]
-pub async fn create_swarm(config: SwarmConfig, project_id: String) -> Result<Swarm, String> {
+#[timed]
+pub async fn create_swarm(app: AppHandle, state: State<'_, AppState>, config: SwarmConfig, project_id: String) -> Result<Swarm, AppError> {
     log::info!("Creating swarm: {}", config.name);
     
-    // TODO: Replace with actual Claude-Flow integration
-    let swarm = mock_create_swarm(config, project_id).await
-        .map_err(|e| format!("Failed to create swarm: {}", e))?;
+    let swarm = persist_new_swarm(&app, &state.db, config, project_id)
+        .map_err(|e| typed_or("Failed to create swarm", e))?;
+    emit_swarm(&app, Action::Created, &swarm);
     
     Ok(swarm)
 }
 
 #[tauri::command]
-pub async fn get_swarms(project_id: Option<String>) -> Result<Vec<Swarm>, String> {
+#[timed]
+pub async fn get_swarms(state: State<'_, AppState>, project_id: Option<String>) -> Result<Vec<Swarm>, String> {
     log::info!("Getting swarms for project: {:?}", project_id);
     
-    // TODO: Replace with actual database query
-    let swarms = mock_get_swarms(project_id).await
+    let rows = match &project_id {
+        Some(pid) => state.db.get_swarms_by_project(pid),
+        None => state.db.get_all_swarms(),
+    }.map_err(|e| format!("Failed to get swarms: {}", e))?;
+    
+    let swarms = rows.into_iter()
+        .map(|row| hydrate_swarm(&state.db, row))
+        .collect::<Result<Vec<_>>>()
         .map_err(|e| format!("Failed to get swarms: {}", e))?;
     
     Ok(swarms)
 }
 
 #[tauri::command]
-pub async fn execute_swarm_task(swarm_id: String, task: Task) -> Result<TaskResult, String> {
+#[timed]
+pub async fn get_swarm_by_id(state: State<'_, AppState>, swarm_id: String) -> Result<Option<Swarm>, String> {
+    log::info!("Getting swarm by ID: {}", swarm_id);
+    
+    let swarm = state.db.get_swarm_by_id(&swarm_id)
+        .map_err(|e| format!("Failed to get swarm: {}", e))?
+        .map(|row| hydrate_swarm(&state.db, row))
+        .transpose()
+        .map_err(|e| format!("Failed to get swarm: {}", e))?;
+    
+    Ok(swarm)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn get_swarm_metrics(state: State<'_, AppState>, swarm_id: String) -> Result<SwarmMetrics, String> {
+    log::info!("Getting metrics for swarm: {}", swarm_id);
+    
+    let metrics = state.db.get_swarm_task_stats(&swarm_id)
+        .and_then(|stats| compute_metrics(&state.db, &swarm_id, &stats))
+        .map_err(|e| format!("Failed to get swarm metrics: {}", e))?;
+    
+    Ok(metrics)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn get_swarm_progress(state: State<'_, AppState>, swarm_id: String) -> Result<SwarmProgress, String> {
+    log::debug!("Getting progress for swarm: {}", swarm_id);
+    
+    let progress = compute_progress(&state.db, &swarm_id)
+        .map_err(|e| format!("Failed to get swarm progress: {}", e))?;
+    
+    Ok(progress)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn update_swarm(app: AppHandle, state: State<'_, AppState>, swarm_id: String, updates: SwarmUpdate, force: Option<bool>) -> Result<Swarm, String> {
+    log::info!("Updating swarm: {}", swarm_id);
+    
+    let swarm = apply_swarm_update(&app, &state.db, &swarm_id, updates, force.unwrap_or(false))
+        .map_err(|e| format!("Failed to update swarm: {}", e))?;
+    emit_swarm(&app, Action::Updated, &swarm);
+    
+    Ok(swarm)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn execute_swarm_task(app: AppHandle, state: State<'_, AppState>, swarm_id: String, task: Task) -> Result<TaskResult, String> {
     log::info!("Executing task in swarm: {} - {}", swarm_id, task.title);
     
-    // TODO: Replace with actual Claude-Flow integration
-    let result = mock_execute_task(swarm_id, task).await
+    let result = run_task(&app, &state.db, &swarm_id, task).await
         .map_err(|e| format!("Failed to execute task: {}", e))?;
+    DataChanged::new(Entity::SwarmTask, Action::Created, &result.task_id)
+        .in_project(swarm_project(&state.db, &swarm_id).as_deref())
+        .with_data(&result)
+        .emit(&app);
     
     Ok(result)
 }
 
 #[tauri::command]
-pub async fn pause_swarm(swarm_id: String) -> Result<(), String> {
+#[timed]
+pub async fn enqueue_swarm_task(app: AppHandle, state: State<'_, AppState>, swarm_id: String, task: Task) -> Result<Task, String> {
+    log::info!("Queueing task in swarm: {} - {}", swarm_id, task.title);
+    
+    let queued = queue_task(&app, &state.db, &swarm_id, &task)
+        .map_err(|e| format!("Failed to queue task: {}", e))?;
+    DataChanged::new(Entity::SwarmTask, Action::Created, &queued.id)
+        .in_project(swarm_project(&state.db, &swarm_id).as_deref())
+        .with_data(&queued)
+        .emit(&app);
+    
+    Ok(queued)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn snapshot_swarm(state: State<'_, AppState>, swarm_id: String) -> Result<SchedulerSnapshot, String> {
+    log::info!("Snapshotting swarm: {}", swarm_id);
+    
+    let snapshot = save_snapshot(&state, &swarm_id)
+        .map_err(|e| format!("Failed to snapshot swarm: {}", e))?;
+    
+    Ok(snapshot)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn pause_swarm(app: AppHandle, state: State<'_, AppState>, swarm_id: String) -> Result<(), String> {
     log::info!("Pausing swarm: {}", swarm_id);
     
-    // TODO: Replace with actual swarm control
-    mock_pause_swarm(swarm_id).await
+    transition_swarm(&app, &state.db, &swarm_id, &["initializing", "running"], "paused")
         .map_err(|e| format!("Failed to pause swarm: {}", e))?;
     
+    save_snapshot(&state, &swarm_id)
+        .map_err(|e| format!("Failed to snapshot swarm: {}", e))?;
+    emit_swarm_updated(&app, &state.db, &swarm_id);
+    
     Ok(())
 }
 
 #[tauri::command]
-pub async fn resume_swarm(swarm_id: String) -> Result<(), String> {
+#[timed]
+pub async fn resume_swarm(app: AppHandle, state: State<'_, AppState>, swarm_id: String) -> Result<(), String> {
     log::info!("Resuming swarm: {}", swarm_id);
     
-    // TODO: Replace with actual swarm control
-    mock_resume_swarm(swarm_id).await
+    // Only a paused swarm has a snapshot to restore; anything else is refused before its tasks
+    // are touched
+    transition_swarm(&app, &state.db, &swarm_id, &["paused"], "running")
         .map_err(|e| format!("Failed to resume swarm: {}", e))?;
+    if let Err(e) = restore_snapshot(&app, &state.db, &swarm_id) {
+        if let Err(e) = set_swarm_status(&app, &state.db, &swarm_id, "running", "paused") {
+            log::warn!("Failed to pause swarm {} again: {}", swarm_id, e);
+        }
+        return Err(format!("Failed to resume swarm: {}", e));
+    }
+    
+    start_scheduler(&app, &state.db, &swarm_id);
+    emit_swarm_updated(&app, &state.db, &swarm_id);
     
     Ok(())
 }
 
 #[tauri::command]
-pub async fn stop_swarm(swarm_id: String) -> Result<(), String> {
+#[timed]
+pub async fn stop_swarm(app: AppHandle, state: State<'_, AppState>, swarm_id: String) -> Result<(), String> {
     log::info!("Stopping swarm: {}", swarm_id);
     
-    // TODO: Replace with actual swarm control
-    mock_stop_swarm(swarm_id).await
+    halt_swarm(&app, &state.db, &swarm_id)
         .map_err(|e| format!("Failed to stop swarm: {}", e))?;
+    emit_swarm_updated(&app, &state.db, &swarm_id);
     
     Ok(())
 }
 
 #[tauri::command]
-pub async fn add_agent_to_swarm(swarm_id: String, agent: Agent) -> Result<Agent, String> {
+#[timed]
+pub async fn add_agent_to_swarm(app: AppHandle, state: State<'_, AppState>, swarm_id: String, agent: Agent) -> Result<Agent, String> {
     log::info!("Adding agent to swarm: {} - {}", swarm_id, agent.agent_type);
     
-    // TODO: Replace with actual agent management
-    let added_agent = mock_add_agent(swarm_id, agent).await
+    let added_agent = persist_agent(&app, &state.db, &swarm_id, agent)
         .map_err(|e| format!("Failed to add agent: {}", e))?;
+    emit_agent_changed(&app, &state.db, Action::Created, &swarm_id, &added_agent.id);
     
     Ok(added_agent)
 }
 
 #[tauri::command]
-pub async fn remove_agent_from_swarm(swarm_id: String, agent_id: String) -> Result<(), String> {
+#[timed]
+pub async fn remove_agent_from_swarm(app: AppHandle, state: State<'_, AppState>, swarm_id: String, agent_id: String) -> Result<(), String> {
     log::info!("Removing agent from swarm: {} - {}", swarm_id, agent_id);
     
-    // TODO: Replace with actual agent management
-    mock_remove_agent(swarm_id, agent_id).await
+    detach_agent(&app, &state.db, &swarm_id, &agent_id)
         .map_err(|e| format!("Failed to remove agent: {}", e))?;
+    emit_agent_changed(&app, &state.db, Action::Deleted, &swarm_id, &agent_id);
     
     Ok(())
 }
 
 #[tauri::command]
-pub async fn query_swarm_memory(namespace: String, query: String) -> Result<Vec<MemoryEntry>, String> {
+#[timed]
+pub async fn save_swarm_workflow(app: AppHandle, state: State<'_, AppState>, swarm_id: String, nodes: Vec<WorkflowNode>) -> Result<Vec<WorkflowNode>, String> {
+    log::info!("Saving workflow for swarm: {} ({} nodes)", swarm_id, nodes.len());
+    
+    store_workflow(&app, &state.db, &swarm_id, &nodes)
+        .map_err(|e| format!("Failed to save workflow: {}", e))?;
+    emit_swarm_updated(&app, &state.db, &swarm_id);
+    
+    Ok(nodes)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn save_workflow_layout(app: AppHandle, state: State<'_, AppState>, swarm_id: String, positions: Vec<(String, Position)>) -> Result<WorkflowLayoutResult, String> {
+    log::debug!("Saving workflow layout for swarm: {} ({} nodes)", swarm_id, positions.len());
+    
+    let result = store_layout(&state.db, &swarm_id, positions)
+        .map_err(|e| format!("Failed to save workflow layout: {}", e))?;
+    emit_swarm_updated(&app, &state.db, &swarm_id);
+    
+    Ok(result)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn save_swarm_template(state: State<'_, AppState>, name: String, config: serde_json::Value) -> Result<SwarmTemplate, String> {
+    log::info!("Saving swarm template: {}", name);
+    
+    let template = store_template(&state.db, name, config)
+        .map_err(|e| format!("Failed to save swarm template: {}", e))?;
+    
+    Ok(template)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn list_swarm_templates(state: State<'_, AppState>) -> Result<Vec<SwarmTemplate>, String> {
+    log::info!("Listing swarm templates");
+    
+    let templates = state.db.get_swarm_templates()
+        .map_err(|e| format!("Failed to list swarm templates: {}", e))?
+        .into_iter()
+        .filter_map(|row| match template_from_row(row) {
+            Ok(template) => Some(template),
+            Err(e) => {
+                log::warn!("Skipping unreadable swarm template: {}", e);
+                None
+            }
+        })
+        .collect();
+    
+    Ok(templates)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn clone_swarm(app: AppHandle, state: State<'_, AppState>, swarm_id: String, new_project_id: String, new_name: String) -> Result<Swarm, String> {
+    log::info!("Cloning swarm {} into project {} as {}", swarm_id, new_project_id, new_name);
+    
+    let swarm = copy_swarm(&app, &state.db, &swarm_id, new_project_id, new_name)
+        .map_err(|e| format!("Failed to clone swarm: {}", e))?;
+    emit_swarm(&app, Action::Created, &swarm);
+    
+    Ok(swarm)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn get_swarm_timeline(state: State<'_, AppState>, swarm_id: String, since: Option<DateTime<Utc>>, limit: Option<i64>) -> Result<Vec<SwarmEvent>, String> {
+    log::info!("Getting timeline for swarm: {}", swarm_id);
+    
+    let events = state.db.get_swarm_events(&swarm_id, since, limit.unwrap_or(500))
+        .map_err(|e| format!("Failed to get swarm timeline: {}", e))?
+        .into_iter()
+        .map(swarm_event_from_row)
+        .collect();
+    
+    Ok(events)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn query_swarm_memory(state: State<'_, AppState>, namespace: String, query: String) -> Result<Vec<MemoryEntry>, String> {
     log::info!("Querying swarm memory: {} - {}", namespace, query);
     
-    // TODO: Replace with actual memory query
-    let entries = mock_query_memory(namespace, query).await
-        .map_err(|e| format!("Failed to query memory: {}", e))?;
+    let entries = state.db.search_memory(&namespace, &query, 50)
+        .map_err(|e| format!("Failed to query memory: {}", e))?
+        .into_iter()
+        .map(memory_entry_from_row)
+        .collect();
+    
+    Ok(entries)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn post_agent_message(app: AppHandle, state: State<'_, AppState>, swarm_id: String, from_agent: String, to_agent: Option<String>, content: String) -> Result<MemoryEntry, String> {
+    log::info!("Posting message in swarm {} from agent {}", swarm_id, from_agent);
+    
+    let entry = store_agent_message(&app, &state.db, &swarm_id, &from_agent, to_agent.as_deref(), &content)
+        .map_err(|e| format!("Failed to post agent message: {}", e))?;
+    
+    Ok(entry)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn get_agent_messages(state: State<'_, AppState>, swarm_id: String, agent_id: String, limit: Option<i64>) -> Result<Vec<MemoryEntry>, String> {
+    log::info!("Getting messages for agent {} in swarm {}", agent_id, swarm_id);
+    
+    let mut entries: Vec<MemoryEntry> = state.db.get_agent_messages(&swarm_id, &agent_id, limit.unwrap_or(50))
+        .map_err(|e| format!("Failed to get agent messages: {}", e))?
+        .into_iter()
+        .map(memory_entry_from_row)
+        .collect();
+    // Oldest first, the way a conversation reads
+    entries.reverse();
     
     Ok(entries)
 }
 
-// Mock implementations - these will be replaced with actual Claude-Flow integration
-async fn mock_create_swarm(config: SwarmConfig, project_id: String) -> Result<Swarm> {
-    tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
+pub(crate) fn persist_new_swarm(app: &AppHandle, db: &Database, config: SwarmConfig, project_id: String) -> Result<Swarm> {
+    if let Some(unknown) = config.agent_types.iter().find(|t| !AGENT_TYPES.contains(&t.as_str())) {
+        return Err(anyhow!("Unknown agent type: {}", unknown));
+    }
+    if config.agent_types.iter().filter(|t| *t == "queen").count() > 1 {
+        return Err(anyhow!("A swarm can only have one queen"));
+    }
+    if config.agent_types.len() > DEFAULT_MAX_AGENTS {
+        return Err(anyhow!("A swarm can have at most {} agents", DEFAULT_MAX_AGENTS));
+    }
+    
+    // Agents start on the tool the project prefers
+    let agent_tool = ToolPolicy::load(db, &project_id)?
+        .and_then(|policy| policy.default_tool())
+        .unwrap_or_else(|| "claude-code".to_string());
     
     let now = Utc::now();
     let swarm_id = Uuid::new_v4().to_string();
     
-    // Create mock agents based on config
-    let agents: Vec<Agent> = config.agent_types.iter().enumerate().map(|(index, agent_type)| {
-        Agent {
-            id: Uuid::new_v4().to_string(),
-            agent_type: agent_type.clone(),
-            ai_tool: "claude-code".to_string(), // Default tool
-            role: if agent_type == "queen" { "coordinator".to_string() } else { "executor".to_string() },
-            specialization: vec![agent_type.clone()],
-            current_task: None,
-            performance: AgentMetrics {
-                tasks_completed: 0,
-                success_rate: 0.0,
-                average_response_time: 0.0,
-                collaboration_rating: 0.0,
-                specialty_score: HashMap::new(),
-            },
-            is_active: true,
-            swarm_id: swarm_id.clone(),
-        }
-    }).collect();
+    let settings = SwarmSettings {
+        strategy: config.strategy.clone(),
+        namespace: config.namespace.clone(),
+        agent_types: config.agent_types.clone(),
+        ..SwarmSettings::default()
+    };
     
-    let swarm = Swarm {
+    db.create_swarm(&DbSwarm {
         id: swarm_id.clone(),
         name: config.name,
         project_id,
         objective: config.objective,
         status: "initializing".to_string(),
-        agents,
-        workflow: vec![],
+        config: serde_json::to_string(&settings)?,
+        created_at: now,
+        updated_at: now,
+    })?;
+    
+    for agent_type in &config.agent_types {
+        db.create_swarm_agent(&DbSwarmAgent {
+            id: Uuid::new_v4().to_string(),
+            swarm_id: swarm_id.clone(),
+            agent_type: agent_type.clone(),
+            ai_tool: agent_tool.clone(),
+            role: default_role(agent_type).to_string(),
+            specialization: serde_json::to_string(&vec![agent_type.clone()])?,
+            current_task_id: None,
+            performance: serde_json::to_string(&empty_agent_metrics())?,
+            is_active: true,
+            created_at: now,
+        })?;
+    }
+    
+    log_event(app, db, &swarm_id, SwarmEventType::Created, None, None, serde_json::json!({ "agent_types": config.agent_types }));
+    
+    load_swarm(db, &swarm_id)
+}
+
+pub(crate) fn load_swarm(db: &Database, swarm_id: &str) -> Result<Swarm> {
+    let row = db.get_swarm_by_id(swarm_id)?
+        .ok_or_else(|| anyhow!("Swarm not found: {}", swarm_id))?;
+    hydrate_swarm(db, row)
+}
+
+fn emit_swarm(app: &AppHandle, action: Action, swarm: &Swarm) {
+    swarm_change(action, swarm).emit(app);
+}
+
+fn swarm_change(action: Action, swarm: &Swarm) -> DataChanged {
+    DataChanged::new(Entity::Swarm, action, &swarm.id)
+        .in_project(Some(&swarm.project_id))
+        .with_data(swarm)
+}
+
+/// Emits `data:changed` with the swarm as it now stands.
+pub(crate) fn emit_swarm_updated(app: &AppHandle, db: &Database, swarm_id: &str) {
+    if let Some(change) = swarm_updated(db, swarm_id) {
+        change.emit(app);
+    }
+}
+
+fn swarm_updated(db: &Database, swarm_id: &str) -> Option<DataChanged> {
+    match db.get_swarm_by_id(swarm_id).and_then(|row| row.map(|row| hydrate_swarm(db, row)).transpose()) {
+        Ok(swarm) => swarm.map(|swarm| swarm_change(Action::Updated, &swarm)),
+        Err(e) => {
+            log::warn!("Failed to load swarm {} for its change event: {}", swarm_id, e);
+            None
+        }
+    }
+}
+
+fn emit_agent_changed(app: &AppHandle, db: &Database, action: Action, swarm_id: &str, agent_id: &str) {
+    let swarm = db.get_swarm_by_id(swarm_id).ok().flatten().and_then(|row| hydrate_swarm(db, row).ok());
+    let mut change = DataChanged::new(Entity::SwarmAgent, action, agent_id)
+        .in_project(swarm.as_ref().map(|swarm| swarm.project_id.as_str()));
+    if let Some(swarm) = &swarm {
+        change = change.with_data(swarm);
+    }
+    change.emit(app);
+}
+
+fn swarm_project(db: &Database, swarm_id: &str) -> Option<String> {
+    db.get_swarm_by_id(swarm_id).ok().flatten().map(|row| row.project_id)
+}
+
+/// Assembles the full `Swarm` view from the swarm row, its agents and task statistics.
+fn hydrate_swarm(db: &Database, row: DbSwarm) -> Result<Swarm> {
+    let settings = parse_settings(&row.config);
+    let stats = db.get_swarm_task_stats(&row.id)?;
+    
+    let agents = db.get_swarm_agents(&row.id)?
+        .into_iter()
+        .map(|row| agent_from_row(db, row))
+        .collect::<Result<Vec<_>>>()?;
+    
+    let workflow = db.get_swarm_workflow(&row.id)?
+        .into_iter()
+        .map(node_from_row)
+        .collect();
+    
+    let entries = db.get_swarm_memory(&row.id, settings.memory_capacity as i64)?
+        .into_iter()
+        .map(memory_entry_from_row)
+        .collect();
+    
+    let metrics = compute_metrics(db, &row.id, &stats)?;
+    
+    Ok(Swarm {
         memory: SwarmMemory {
-            namespace: config.namespace.unwrap_or(swarm_id.clone()),
-            entries: vec![],
-            capacity: 1000,
-            retention_policy: "lru".to_string(),
+            namespace: memory_namespace(&settings, &row.id),
+            entries,
+            capacity: settings.memory_capacity,
+            retention_policy: settings.retention_policy.clone(),
         },
-        metrics: SwarmMetrics {
-            tasks_completed: 0,
-            average_task_duration: 0.0,
-            success_rate: 0.0,
-            collaboration_score: 0.0,
-            total_execution_time: 0,
-            cost_estimate: None,
+        task_summary: TaskSummary {
+            total: stats.total,
+            pending: stats.pending,
+            in_progress: stats.in_progress,
+            completed: stats.completed,
+            failed: stats.failed,
+            cancelled: stats.cancelled,
+        },
+        strategy: settings.strategy,
+        id: row.id,
+        name: row.name,
+        project_id: row.project_id,
+        objective: row.objective,
+        status: row.status,
+        agents,
+        workflow,
+        metrics,
+        created_at: row.created_at,
+        updated_at: row.updated_at,
+    })
+}
+
+fn compute_metrics(db: &Database, swarm_id: &str, stats: &DbSwarmTaskStats) -> Result<SwarmMetrics> {
+    let usage = db.get_swarm_token_usage(swarm_id)?;
+    let prompt_tokens: i64 = usage.iter().map(|u| u.prompt_tokens).sum();
+    let completion_tokens: i64 = usage.iter().map(|u| u.completion_tokens).sum();
+    
+    let finished = stats.completed + stats.failed;
+    Ok(SwarmMetrics {
+        tasks_completed: stats.completed,
+        average_task_duration: stats.average_duration.unwrap_or(0.0) as f32,
+        success_rate: if finished > 0 { stats.completed as f32 / finished as f32 } else { 0.0 },
+        collaboration_score: 0.0,
+        total_execution_time: stats.total_duration as i32,
+        cost_estimate: pricing::estimate_cost(db, &usage)?.map(|c| c as f32),
+        prompt_tokens,
+        completion_tokens,
+        total_tokens: prompt_tokens + completion_tokens,
+    })
+}
+
+fn compute_progress(db: &Database, swarm_id: &str) -> Result<SwarmProgress> {
+    let swarm = db.get_swarm_by_id(swarm_id)?
+        .ok_or_else(|| anyhow!("Swarm not found: {}", swarm_id))?;
+    let progress = db.get_swarm_progress(swarm_id)?;
+    let stats = &progress.stats;
+    
+    // Tasks without an estimate count as an average task
+    let average = if progress.estimated_count > 0 {
+        progress.estimated_total as f64 / progress.estimated_count as f64
+    } else {
+        1.0
+    };
+    let total_weight = progress.estimated_total as f64 + (stats.total - progress.estimated_count) as f64 * average;
+    let finished_weight = progress.estimated_finished as f64 + progress.unestimated_finished as f64 * average;
+    let percent_complete = if total_weight > 0.0 {
+        (finished_weight / total_weight * 100.0).min(100.0) as f32
+    } else {
+        0.0
+    };
+    
+    let running = db.get_running_tasks(swarm_id)?
+        .into_iter()
+        .map(|t| RunningTask {
+            task_id: t.task_id,
+            title: t.title,
+            agent_id: t.agent_id,
+            agent_type: t.agent_type,
+            started_at: t.started_at,
+        })
+        .collect();
+    
+    let end_statuses: &[&str] = match swarm.status.as_str() {
+        "completed" | "failed" => &["completed", "failed"],
+        _ => &[],
+    };
+    let (started_at, ended_at) = db.get_swarm_run_window(swarm_id, end_statuses)?;
+    let elapsed_seconds = started_at.map(|start| (ended_at.unwrap_or_else(Utc::now) - start).num_seconds().max(0));
+    
+    Ok(SwarmProgress {
+        swarm_id: swarm.id,
+        status: swarm.status,
+        tasks: TaskSummary {
+            total: stats.total,
+            pending: stats.pending,
+            in_progress: stats.in_progress,
+            completed: stats.completed,
+            failed: stats.failed,
+            cancelled: stats.cancelled,
         },
+        percent_complete,
+        running,
+        started_at,
+        elapsed_seconds,
+    })
+}
+
+fn parse_settings(config: &str) -> SwarmSettings {
+    serde_json::from_str(config).unwrap_or_else(|e| {
+        log::warn!("Ignoring unreadable swarm config: {}", e);
+        SwarmSettings::default()
+    })
+}
+
+fn agent_from_row(db: &Database, row: DbSwarmAgent) -> Result<Agent> {
+    let current_task = match &row.current_task_id {
+        Some(task_id) => db.get_swarm_task(task_id)?.map(|t| task_from_row(t, vec![])),
+        None => None,
+    };
+    
+    Ok(Agent {
+        id: row.id,
+        agent_type: row.agent_type,
+        ai_tool: row.ai_tool,
+        role: row.role,
+        specialization: serde_json::from_str(&row.specialization).unwrap_or_default(),
+        current_task,
+        performance: serde_json::from_str(&row.performance).unwrap_or_else(|_| empty_agent_metrics()),
+        is_active: row.is_active,
+        swarm_id: row.swarm_id,
+    })
+}
+
+fn task_from_row(row: DbSwarmTask, results: Vec<TaskResult>) -> Task {
+    Task {
+        id: row.id,
+        title: row.title,
+        description: row.description,
+        status: row.status,
+        priority: row.priority,
+        assigned_to: row.assigned_to,
+        dependencies: serde_json::from_str(&row.dependencies).unwrap_or_default(),
+        estimated_duration: row.estimated_duration,
+        actual_duration: row.actual_duration,
+        results,
+        created_at: row.created_at,
+        updated_at: row.updated_at,
+    }
+}
+
+fn swarm_event_from_row(row: DbSwarmEvent) -> SwarmEvent {
+    SwarmEvent {
+        payload: serde_json::from_str(&row.payload).unwrap_or(serde_json::Value::Null),
+        id: row.id,
+        swarm_id: row.swarm_id,
+        event_type: row.event_type,
+        task_id: row.task_id,
+        agent_id: row.agent_id,
+        timestamp: row.timestamp,
+    }
+}
+
+fn memory_entry_from_row(row: DbMemoryEntry) -> MemoryEntry {
+    MemoryEntry {
+        id: row.id,
+        entry_type: row.entry_type,
+        content: serde_json::from_str(&row.content).unwrap_or(serde_json::Value::Null),
+        metadata: serde_json::from_str(&row.metadata).unwrap_or_default(),
+        importance: row.importance,
+        timestamp: row.timestamp,
+    }
+}
+
+fn memory_namespace(settings: &SwarmSettings, swarm_id: &str) -> String {
+    settings.namespace.clone().unwrap_or_else(|| swarm_id.to_string())
+}
+
+fn node_from_row(row: DbWorkflowNode) -> WorkflowNode {
+    WorkflowNode {
+        id: row.id,
+        node_type: row.node_type,
+        name: row.name,
+        position: Position { x: row.position_x, y: row.position_y },
+        data: serde_json::from_str(&row.data).unwrap_or(serde_json::Value::Null),
+        connections: serde_json::from_str(&row.connections).unwrap_or_default(),
+        status: row.status,
+    }
+}
+
+fn store_workflow(app: &AppHandle, db: &Database, swarm_id: &str, nodes: &[WorkflowNode]) -> Result<()> {
+    db.get_swarm_by_id(swarm_id)?
+        .ok_or_else(|| anyhow!("Swarm not found: {}", swarm_id))?;
+    
+    let node_ids: std::collections::HashSet<&str> = nodes.iter().map(|n| n.id.as_str()).collect();
+    if node_ids.len() != nodes.len() {
+        return Err(anyhow!("Workflow contains duplicate node ids"));
+    }
+    for connection in nodes.iter().flat_map(|n| &n.connections) {
+        if !node_ids.contains(connection.source_id.as_str()) || !node_ids.contains(connection.target_id.as_str()) {
+            return Err(anyhow!("Connection {} references an unknown node", connection.id));
+        }
+    }
+    
+    let rows = nodes.iter()
+        .map(|node| Ok(DbWorkflowNode {
+            id: node.id.clone(),
+            swarm_id: swarm_id.to_string(),
+            node_type: node.node_type.clone(),
+            name: node.name.clone(),
+            position_x: node.position.x,
+            position_y: node.position.y,
+            data: node.data.to_string(),
+            connections: serde_json::to_string(&node.connections)?,
+            status: node.status.clone(),
+        }))
+        .collect::<Result<Vec<_>>>()?;
+    
+    db.replace_swarm_workflow(swarm_id, &rows)?;
+    log_event(app, db, swarm_id, SwarmEventType::WorkflowUpdated, None, None, serde_json::json!({ "nodes": rows.len() }));
+    
+    Ok(())
+}
+
+fn store_layout(db: &Database, swarm_id: &str, positions: Vec<(String, Position)>) -> Result<WorkflowLayoutResult> {
+    db.get_swarm_by_id(swarm_id)?
+        .ok_or_else(|| anyhow!("Swarm not found: {}", swarm_id))?;
+    
+    if let Some((node_id, _)) = positions.iter().find(|(_, p)| !p.x.is_finite() || !p.y.is_finite()) {
+        return Err(anyhow!("Invalid position for node {}", node_id));
+    }
+    
+    let rows: Vec<(String, f32, f32)> = positions.into_iter()
+        .map(|(node_id, position)| (node_id, position.x, position.y))
+        .collect();
+    let skipped = db.update_workflow_positions(swarm_id, &rows)?;
+    
+    Ok(WorkflowLayoutResult {
+        updated: rows.len() - skipped.len(),
+        skipped,
+    })
+}
+
+fn store_template(db: &Database, name: String, config: serde_json::Value) -> Result<SwarmTemplate> {
+    if name.trim().is_empty() {
+        return Err(anyhow!("Template name cannot be empty"));
+    }
+    
+    // Round-trip through SwarmConfig so only well-formed templates are ever stored
+    let config: SwarmConfig = serde_json::from_value(config)
+        .map_err(|e| anyhow!("Template is not a valid swarm config: {}", e))?;
+    
+    let now = Utc::now();
+    db.save_swarm_template(&DbSwarmTemplate {
+        id: Uuid::new_v4().to_string(),
+        name: name.clone(),
+        config: serde_json::to_string(&config)?,
+        created_at: now,
+        updated_at: now,
+    })?;
+    
+    db.get_swarm_templates()?
+        .into_iter()
+        .find(|t| t.name == name)
+        .ok_or_else(|| anyhow!("Template was not saved"))
+        .and_then(template_from_row)
+}
+
+fn template_from_row(row: DbSwarmTemplate) -> Result<SwarmTemplate> {
+    Ok(SwarmTemplate {
+        config: serde_json::from_str(&row.config)?,
+        id: row.id,
+        name: row.name,
+        created_at: row.created_at,
+        updated_at: row.updated_at,
+    })
+}
+
+/// Copies agent composition, workflow graph and settings into a new swarm.
+/// Tasks, results and memory stay with the original.
+fn copy_swarm(app: &AppHandle, db: &Database, swarm_id: &str, new_project_id: String, new_name: String) -> Result<Swarm> {
+    let source = db.get_swarm_by_id(swarm_id)?
+        .ok_or_else(|| anyhow!("Swarm not found: {}", swarm_id))?;
+    
+    if new_name.trim().is_empty() {
+        return Err(anyhow!("Swarm name cannot be empty"));
+    }
+    
+    let now = Utc::now();
+    let new_swarm_id = Uuid::new_v4().to_string();
+    
+    // A namespace shared with the source would make the clone read the original's memory
+    let mut settings = parse_settings(&source.config);
+    settings.namespace = None;
+    
+    let swarm = DbSwarm {
+        id: new_swarm_id.clone(),
+        name: new_name,
+        project_id: new_project_id,
+        objective: source.objective.clone(),
+        status: "initializing".to_string(),
+        config: serde_json::to_string(&settings)?,
         created_at: now,
         updated_at: now,
     };
     
-    Ok(swarm)
+    let mut id_map: HashMap<String, String> = HashMap::new();
+    
+    let mut agents = Vec::new();
+    for agent in db.get_swarm_agents(swarm_id)? {
+        let new_agent_id = Uuid::new_v4().to_string();
+        id_map.insert(agent.id.clone(), new_agent_id.clone());
+        agents.push(DbSwarmAgent {
+            id: new_agent_id,
+            swarm_id: new_swarm_id.clone(),
+            current_task_id: None,
+            performance: serde_json::to_string(&empty_agent_metrics())?,
+            created_at: now,
+            ..agent
+        });
+    }
+    
+    let nodes = db.get_swarm_workflow(swarm_id)?;
+    for node in &nodes {
+        id_map.insert(node.id.clone(), Uuid::new_v4().to_string());
+    }
+    
+    let cloned_nodes = nodes.into_iter()
+        .map(|node| {
+            let connections: Vec<Connection> = serde_json::from_str(&node.connections).unwrap_or_default();
+            let connections: Vec<Connection> = connections.into_iter()
+                .map(|c| Connection {
+                    id: Uuid::new_v4().to_string(),
+                    source_id: remap_id(&id_map, c.source_id),
+                    target_id: remap_id(&id_map, c.target_id),
+                    ..c
+                })
+                .collect();
+            let mut data: serde_json::Value = serde_json::from_str(&node.data).unwrap_or(serde_json::Value::Null);
+            remap_ids_in_value(&id_map, &mut data);
+            
+            Ok(DbWorkflowNode {
+                id: remap_id(&id_map, node.id),
+                swarm_id: new_swarm_id.clone(),
+                data: data.to_string(),
+                connections: serde_json::to_string(&connections)?,
+                status: "idle".to_string(),
+                ..node
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    // In one go, so a failure part-way leaves no half-cloned swarm behind
+    db.create_swarm_with(&swarm, &agents, &cloned_nodes)?;
+    
+    log_event(app, db, &new_swarm_id, SwarmEventType::Created, None, None, serde_json::json!({ "cloned_from": swarm_id }));
+    
+    load_swarm(db, &new_swarm_id)
+}
+
+fn remap_id(id_map: &HashMap<String, String>, id: String) -> String {
+    id_map.get(&id).cloned().unwrap_or(id)
+}
+
+/// Node data may reference agents or other nodes by id (e.g. an assigned agent).
+fn remap_ids_in_value(id_map: &HashMap<String, String>, value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(s) => {
+            if let Some(new_id) = id_map.get(s.as_str()) {
+                *s = new_id.clone();
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(|v| remap_ids_in_value(id_map, v)),
+        serde_json::Value::Object(map) => map.values_mut().for_each(|v| remap_ids_in_value(id_map, v)),
+        _ => {}
+    }
+}
+
+fn apply_swarm_update(app: &AppHandle, db: &Database, swarm_id: &str, updates: SwarmUpdate, force: bool) -> Result<Swarm> {
+    let mut row = db.get_swarm_by_id(swarm_id)?
+        .ok_or_else(|| anyhow!("Swarm not found: {}", swarm_id))?;
+    let mut settings = parse_settings(&row.config);
+    
+    if let Some(name) = updates.name {
+        if name.trim().is_empty() {
+            return Err(anyhow!("Swarm name cannot be empty"));
+        }
+        row.name = name;
+    }
+    
+    if let Some(objective) = updates.objective {
+        if objective.trim().is_empty() {
+            return Err(anyhow!("Swarm objective cannot be empty"));
+        }
+        if objective != row.objective && row.status == "running" && !force {
+            return Err(anyhow!("Cannot change the objective of a running swarm without force"));
+        }
+        row.objective = objective;
+    }
+    
+    if let Some(strategy) = updates.strategy {
+        if !STRATEGIES.contains(&strategy.as_str()) {
+            return Err(anyhow!("Unknown strategy: {}", strategy));
+        }
+        settings.strategy = Some(strategy);
+    }
+    
+    if let Some(capacity) = updates.memory_capacity {
+        if capacity <= 0 {
+            return Err(anyhow!("Memory capacity must be positive"));
+        }
+        settings.memory_capacity = capacity;
+    }
+    
+    if let Some(policy) = updates.retention_policy {
+        if !RETENTION_POLICIES.contains(&policy.as_str()) {
+            return Err(anyhow!("Unknown retention policy: {}", policy));
+        }
+        settings.retention_policy = policy;
+    }
+    
+    if let Some(max_agents) = updates.max_agents {
+        let current = db.get_swarm_agents(swarm_id)?.len();
+        if max_agents == 0 || max_agents < current {
+            return Err(anyhow!("Max agents must be at least {} (current agent count)", current.max(1)));
+        }
+        settings.max_agents = max_agents;
+    }
+    
+    row.config = serde_json::to_string(&settings)?;
+    row.updated_at = Utc::now();
+    db.update_swarm(&row)?;
+    log_event(app, db, &row.id, SwarmEventType::Updated, None, None, serde_json::json!({
+        "name": row.name,
+        "objective": row.objective,
+        "settings": settings,
+    }));
+    
+    hydrate_swarm(db, row)
+}
+
+fn transition_swarm(app: &AppHandle, db: &Database, swarm_id: &str, allowed_from: &[&str], status: &str) -> Result<()> {
+    let swarm = db.get_swarm_by_id(swarm_id)?
+        .ok_or_else(|| anyhow!("Swarm not found: {}", swarm_id))?;
+    
+    if !allowed_from.contains(&swarm.status.as_str()) {
+        return Err(anyhow!("Cannot move swarm from '{}' to '{}'", swarm.status, status));
+    }
+    
+    set_swarm_status(app, db, swarm_id, &swarm.status, status)
+}
+
+/// Completes the swarm where it stands and cancels the tasks it hasn't finished.
+pub(crate) fn halt_swarm(app: &AppHandle, db: &Database, swarm_id: &str) -> Result<()> {
+    transition_swarm(app, db, swarm_id, &["initializing", "running", "paused"], "completed")?;
+    
+    // Anything that never got to run is cancelled rather than left dangling
+    let cancelled = db.update_swarm_tasks_status(swarm_id, &["pending", "in_progress"], "cancelled")?;
+    if cancelled > 0 {
+        log_event(app, db, swarm_id, SwarmEventType::Updated, None, None, serde_json::json!({ "cancelled_tasks": cancelled }));
+    }
+    
+    Ok(())
+}
+
+fn set_swarm_status(app: &AppHandle, db: &Database, swarm_id: &str, from: &str, to: &str) -> Result<()> {
+    db.update_swarm_status(swarm_id, to)?;
+    log_event(app, db, swarm_id, SwarmEventType::StatusChanged, None, None, serde_json::json!({ "from": from, "to": to }));
+    Ok(())
+}
+
+/// Appends to the swarm timeline. Failing to log must never fail the operation itself.
+fn log_event(app: &AppHandle, db: &Database, swarm_id: &str, event_type: SwarmEventType, task_id: Option<&str>, agent_id: Option<&str>, payload: serde_json::Value) {
+    let event = DbSwarmEvent {
+        id: Uuid::new_v4().to_string(),
+        swarm_id: swarm_id.to_string(),
+        event_type: event_type.as_str().to_string(),
+        task_id: task_id.map(str::to_string),
+        agent_id: agent_id.map(str::to_string),
+        payload: payload.to_string(),
+        timestamp: Utc::now(),
+    };
+    
+    if let Err(e) = db.create_swarm_event(&event) {
+        log::warn!("Failed to record swarm event {}: {}", event.event_type, e);
+        return;
+    }
+    if let Err(e) = app.emit(TIMELINE_EVENT, swarm_event_from_row(event)) {
+        log::warn!("Failed to emit {}: {}", TIMELINE_EVENT, e);
+    }
+}
+
+fn persist_agent(app: &AppHandle, db: &Database, swarm_id: &str, agent: Agent) -> Result<Agent> {
+    validate_new_agent(db, swarm_id, &agent)?;
+    
+    let agent_id = if agent.id.is_empty() { Uuid::new_v4().to_string() } else { agent.id };
+    
+    db.create_swarm_agent(&DbSwarmAgent {
+        id: agent_id.clone(),
+        swarm_id: swarm_id.to_string(),
+        agent_type: agent.agent_type.clone(),
+        ai_tool: agent.ai_tool.clone(),
+        role: agent.role.clone(),
+        specialization: serde_json::to_string(&agent.specialization)?,
+        current_task_id: None,
+        performance: serde_json::to_string(&agent.performance)?,
+        is_active: agent.is_active,
+        created_at: Utc::now(),
+    })?;
+    log_event(app, db, swarm_id, SwarmEventType::AgentAdded, None, Some(&agent_id), serde_json::json!({
+        "agent_type": agent.agent_type,
+        "ai_tool": agent.ai_tool,
+    }));
+    
+    Ok(Agent {
+        id: agent_id,
+        swarm_id: swarm_id.to_string(),
+        current_task: None,
+        ..agent
+    })
+}
+
+fn validate_new_agent(db: &Database, swarm_id: &str, agent: &Agent) -> Result<()> {
+    let swarm = db.get_swarm_by_id(swarm_id)?
+        .ok_or_else(|| anyhow!("Swarm not found: {}", swarm_id))?;
+    
+    if swarm.status == "completed" || swarm.status == "failed" {
+        return Err(anyhow!("Cannot add agents to a {} swarm", swarm.status));
+    }
+    if !agent.swarm_id.is_empty() && agent.swarm_id != swarm_id {
+        return Err(anyhow!("Agent belongs to swarm {}", agent.swarm_id));
+    }
+    if !AGENT_TYPES.contains(&agent.agent_type.as_str()) {
+        return Err(anyhow!("Unknown agent type: {}", agent.agent_type));
+    }
+    if !is_configured_tool(db, &agent.ai_tool)? {
+        return Err(anyhow!("AI tool is not configured: {}", agent.ai_tool));
+    }
+    if let Some(policy) = ToolPolicy::load(db, &swarm.project_id)? {
+        policy.check(&agent.ai_tool)?;
+    }
+    
+    let agents = db.get_swarm_agents(swarm_id)?;
+    if agent.agent_type == "queen" && agents.iter().any(|a| a.agent_type == "queen") {
+        return Err(anyhow!("Swarm already has a queen"));
+    }
+    if !agent.id.is_empty() && agents.iter().any(|a| a.id == agent.id) {
+        return Err(anyhow!("Agent already exists: {}", agent.id));
+    }
+    
+    let max_agents = parse_settings(&swarm.config).max_agents;
+    if agents.len() >= max_agents {
+        return Err(anyhow!("Swarm already has the maximum of {} agents", max_agents));
+    }
+    
+    Ok(())
+}
+
+// Tasks are sent to `ai_tool` as a tool id, so a type or a config name won't do
+fn is_configured_tool(db: &Database, tool_id: &str) -> Result<bool> {
+    Ok(db.get_ai_tool(tool_id)?.is_some())
+}
+
+/// Removes an agent. A task it was working on goes to the best remaining agent,
+/// or back to pending when nobody suitable is left.
+fn detach_agent(app: &AppHandle, db: &Database, swarm_id: &str, agent_id: &str) -> Result<()> {
+    let agents = db.get_swarm_agents(swarm_id)?;
+    let agent = agents.iter()
+        .find(|a| a.id == agent_id)
+        .ok_or_else(|| anyhow!("Agent not found in swarm: {}", agent_id))?;
+    
+    let in_progress = match &agent.current_task_id {
+        Some(task_id) => db.get_swarm_task(task_id)?.filter(|t| t.status == "in_progress"),
+        None => None,
+    };
+    
+    db.delete_swarm_agent(swarm_id, agent_id)?;
+    log_event(app, db, swarm_id, SwarmEventType::AgentRemoved, None, Some(agent_id), serde_json::Value::Null);
+    
+    if let Some(mut task) = in_progress {
+        let successor = best_replacement(agent, &agents);
+        
+        task.status = "pending".to_string();
+        task.assigned_to = successor.map(|a| a.id.clone());
+        task.updated_at = Utc::now();
+        db.save_swarm_task(&task)?;
+        let runs = &app.state::<AppState>().inner().runs;
+        set_in_flight(runs, swarm_id, &task.id, false);
+        requeue_front(runs, swarm_id, &task.id);
+        
+        log_event(app, db, swarm_id, SwarmEventType::TaskReassigned, Some(&task.id), task.assigned_to.as_deref(), serde_json::json!({
+            "from_agent": agent_id,
+            "to_agent": task.assigned_to,
+        }));
+        
+        let running = db.get_swarm_by_id(swarm_id)?.map(|s| s.status == "running").unwrap_or(false);
+        if running {
+            start_scheduler(app, db, swarm_id);
+        }
+    }
+    
+    Ok(())
+}
+
+/// Prefers an active agent of the same type, then idle agents, then the best track record.
+/// The queen is only used when no worker is left.
+fn best_replacement<'a>(removed: &DbSwarmAgent, agents: &'a [DbSwarmAgent]) -> Option<&'a DbSwarmAgent> {
+    agents.iter()
+        .filter(|a| a.id != removed.id && a.is_active)
+        .max_by(|a, b| {
+            let score = |agent: &DbSwarmAgent| {
+                let metrics: AgentMetrics = serde_json::from_str(&agent.performance)
+                    .unwrap_or_else(|_| empty_agent_metrics());
+                (
+                    agent.agent_type != "queen",
+                    agent.agent_type == removed.agent_type,
+                    agent.current_task_id.is_none(),
+                    (metrics.success_rate * 1000.0) as i32,
+                )
+            };
+            score(a).cmp(&score(b))
+        })
+}
+
+/// Stores a message between agents as a 'conversation' memory entry; no recipient means a broadcast.
+fn store_agent_message(app: &AppHandle, db: &Database, swarm_id: &str, from_agent: &str, to_agent: Option<&str>, content: &str) -> Result<MemoryEntry> {
+    if content.trim().is_empty() {
+        return Err(anyhow!("Message content cannot be empty"));
+    }
+    
+    let agents = db.get_swarm_agents(swarm_id)?;
+    for agent_id in std::iter::once(from_agent).chain(to_agent) {
+        if !agents.iter().any(|a| a.id == agent_id) {
+            return Err(anyhow!("Agent not found in swarm: {}", agent_id));
+        }
+    }
+    
+    let mut metadata = HashMap::new();
+    metadata.insert("from_agent".to_string(), serde_json::json!(from_agent));
+    metadata.insert("to_agent".to_string(), serde_json::json!(to_agent));
+    
+    write_memory(app, db, swarm_id, "conversation", serde_json::json!({ "message": content }), metadata, Some(from_agent), to_agent, 5)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn write_memory(
+    app: &AppHandle,
+    db: &Database,
+    swarm_id: &str,
+    entry_type: &str,
+    content: serde_json::Value,
+    metadata: HashMap<String, serde_json::Value>,
+    sender_id: Option<&str>,
+    recipient_id: Option<&str>,
+    importance: i32,
+) -> Result<MemoryEntry> {
+    let swarm = db.get_swarm_by_id(swarm_id)?
+        .ok_or_else(|| anyhow!("Swarm not found: {}", swarm_id))?;
+    let settings = parse_settings(&swarm.config);
+    
+    let entry = MemoryEntry {
+        id: Uuid::new_v4().to_string(),
+        entry_type: entry_type.to_string(),
+        content,
+        metadata,
+        importance,
+        timestamp: Utc::now(),
+    };
+    
+    db.create_memory_entry(&DbMemoryEntry {
+        id: entry.id.clone(),
+        swarm_id: swarm_id.to_string(),
+        namespace: memory_namespace(&settings, swarm_id),
+        entry_type: entry.entry_type.clone(),
+        content: entry.content.to_string(),
+        metadata: serde_json::to_string(&entry.metadata)?,
+        sender_id: sender_id.map(str::to_string),
+        recipient_id: recipient_id.map(str::to_string),
+        importance: entry.importance,
+        timestamp: entry.timestamp,
+    })?;
+    
+    log_event(app, db, swarm_id, SwarmEventType::MemoryWritten, None, sender_id, serde_json::json!({
+        "entry_id": entry.id,
+        "entry_type": entry.entry_type,
+        "to_agent": recipient_id,
+    }));
+    
+    Ok(entry)
+}
+
+/// The text handed to the agent's AI tool: swarm objective, the task and recent messages from teammates.
+fn build_agent_prompt(app: &AppHandle, db: &Database, swarm_id: &str, agent: &DbSwarmAgent, task: &Task) -> Result<String> {
+    let swarm = db.get_swarm_by_id(swarm_id)?
+        .ok_or_else(|| anyhow!("Swarm not found: {}", swarm_id))?;
+    
+    // CLIs take a single prompt, so the system prompt goes in front of it
+    let tool_prompt = ai_tools::tool_config(&app.state::<AppState>(), &agent.ai_tool).and_then(|c| c.system_prompt);
+    let mut prompt = chat::resolve_system_prompt(None, Some(&agent.agent_type), tool_prompt.as_deref())
+        .map(|p| format!("{}\n\n", p.text))
+        .unwrap_or_default();
+    prompt.push_str(&format!(
+        "You are the {} ({}) of a swarm working on: {}\n\nTask: {}\n{}\n",
+        agent.agent_type, agent.role, swarm.objective, task.title, task.description
+    ));
+    prompt.push_str(&format!("\n{}\n", agent_actions::ACTION_INSTRUCTIONS));
+    
+    let mut messages = db.get_agent_messages(swarm_id, &agent.id, PROMPT_MESSAGE_CONTEXT)?;
+    if !messages.is_empty() {
+        messages.reverse();
+        prompt.push_str("\nMessages from other agents:\n");
+        for message in messages {
+            let content: serde_json::Value = serde_json::from_str(&message.content).unwrap_or(serde_json::Value::Null);
+            let text = content.get("message").and_then(|m| m.as_str()).unwrap_or_default();
+            let scope = if message.recipient_id.is_some() { "to you" } else { "to everyone" };
+            prompt.push_str(&format!("- {} {}: {}\n", message.sender_id.as_deref().unwrap_or("system"), scope, text));
+        }
+    }
+    
+    Ok(prompt)
+}
+
+/// Under the collaborative strategy finished work is shared with the rest of the swarm.
+fn share_task_outcome(app: &AppHandle, db: &Database, swarm_id: &str, agent_id: &str, task: &DbSwarmTask, output: &serde_json::Value) -> Result<()> {
+    let swarm = db.get_swarm_by_id(swarm_id)?
+        .ok_or_else(|| anyhow!("Swarm not found: {}", swarm_id))?;
+    if parse_settings(&swarm.config).strategy.as_deref() != Some("collaborative") {
+        return Ok(());
+    }
+    
+    let summary = chat::reply_text(output);
+    store_agent_message(app, db, swarm_id, agent_id, None, &format!("Finished '{}': {}", task.title, summary))?;
+    
+    Ok(())
+}
+
+/// Persists the task, assigns it to an agent, executes it and records the result.
+async fn run_task(app: &AppHandle, db: &Database, swarm_id: &str, task: Task) -> Result<TaskResult> {
+    ensure_accepting_tasks(app, db, swarm_id)?;
+    
+    let task_row = task_row_from(swarm_id, &task)?;
+    db.save_swarm_task(&task_row)?;
+    
+    let (task_row, agent) = claim_task(app, db, swarm_id, task_row)?;
+    perform_task(app, db, swarm_id, task_row, agent).await
+}
+
+pub(crate) fn queue_task(app: &AppHandle, db: &Database, swarm_id: &str, task: &Task) -> Result<Task> {
+    let status = db.get_swarm_by_id(swarm_id)?
+        .ok_or_else(|| anyhow!("Swarm not found: {}", swarm_id))?
+        .status;
+    if status == "completed" || status == "failed" {
+        return Err(anyhow!("Swarm is {}", status));
+    }
+    
+    let task_row = task_row_from(swarm_id, task)?;
+    db.save_swarm_task(&task_row)?;
+    app.state::<AppState>().inner().runs.0.lock().unwrap()
+        .entry(swarm_id.to_string())
+        .or_default()
+        .queue
+        .push_back(task_row.id.clone());
+    
+    // A paused swarm keeps the task queued until it is resumed
+    if status != "paused" {
+        ensure_accepting_tasks(app, db, swarm_id)?;
+        start_scheduler(app, db, swarm_id);
+    }
+    
+    Ok(task_from_row(task_row, vec![]))
+}
+
+fn ensure_accepting_tasks(app: &AppHandle, db: &Database, swarm_id: &str) -> Result<()> {
+    let swarm = db.get_swarm_by_id(swarm_id)?
+        .ok_or_else(|| anyhow!("Swarm not found: {}", swarm_id))?;
+    
+    if swarm.status == "paused" || swarm.status == "completed" || swarm.status == "failed" {
+        return Err(anyhow!("Swarm is {}", swarm.status));
+    }
+    if swarm.status == "initializing" {
+        set_swarm_status(app, db, swarm_id, &swarm.status, "running")?;
+    }
+    
+    Ok(())
+}
+
+fn task_row_from(swarm_id: &str, task: &Task) -> Result<DbSwarmTask> {
+    let now = Utc::now();
+    Ok(DbSwarmTask {
+        id: if task.id.is_empty() { Uuid::new_v4().to_string() } else { task.id.clone() },
+        swarm_id: swarm_id.to_string(),
+        title: task.title.clone(),
+        description: task.description.clone(),
+        status: "pending".to_string(),
+        priority: task.priority,
+        assigned_to: task.assigned_to.clone(),
+        dependencies: serde_json::to_string(&task.dependencies)?,
+        estimated_duration: task.estimated_duration,
+        actual_duration: None,
+        created_at: now,
+        updated_at: now,
+    })
 }
 
-async fn mock_get_swarms(_project_id: Option<String>) -> Result<Vec<Swarm>> {
-    tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
+/// Binds a pending task to an agent and marks both as busy. Synchronous so the
+/// scheduler can't hand the same idle agent two tasks.
+fn claim_task(app: &AppHandle, db: &Database, swarm_id: &str, mut task_row: DbSwarmTask) -> Result<(DbSwarmTask, DbSwarmAgent)> {
+    let mut agent = select_agent(db, swarm_id, task_row.assigned_to.as_deref())?;
+    
+    task_row.status = "in_progress".to_string();
+    task_row.assigned_to = Some(agent.id.clone());
+    task_row.updated_at = Utc::now();
+    db.save_swarm_task(&task_row)?;
+    
+    agent.current_task_id = Some(task_row.id.clone());
+    db.update_swarm_agent(&agent)?;
+    
+    set_in_flight(&app.state::<AppState>().inner().runs, swarm_id, &task_row.id, true);
+    set_task_node_status(db, swarm_id, &task_row.id, "running");
+    log_event(app, db, swarm_id, SwarmEventType::TaskAssigned, Some(&task_row.id), Some(&agent.id), serde_json::json!({ "title": task_row.title }));
     
-    // Return empty list for now
-    Ok(vec![])
+    Ok((task_row, agent))
 }
 
-async fn mock_execute_task(swarm_id: String, task: Task) -> Result<TaskResult> {
-    tokio::time::sleep(tokio::time::Duration::from_millis(3000)).await;
+async fn perform_task(app: &AppHandle, db: &Database, swarm_id: &str, mut task_row: DbSwarmTask, mut agent: DbSwarmAgent) -> Result<TaskResult> {
+    log_event(app, db, swarm_id, SwarmEventType::TaskStarted, Some(&task_row.id), Some(&agent.id), serde_json::Value::Null);
+    
+    let task = task_from_row(task_row.clone(), vec![]);
+    let started = std::time::Instant::now();
+    // A prompt that can't be built fails the task like a failed run, so the agent is released.
+    // The project may also have disabled the agent's tool since the agent was added
+    let prompt = build_agent_prompt(app, db, swarm_id, &agent, &task)
+        .and_then(|prompt| check_agent_tool(db, swarm_id, &agent).map(|()| prompt));
+    let output = match prompt {
+        Ok(prompt) => approvals::on_behalf_of(swarm_id, execute_with_agent(app, swarm_id, &agent, &task, &prompt)).await
+            .map(|output| (prompt, output)),
+        Err(e) => Err(e),
+    };
+    let elapsed = started.elapsed().as_secs() as i32;
+    
+    // Cut short by the app shutting down: the task stays in progress, so the swarm's snapshot
+    // queues it again on resume
+    if output.is_err() && !app.state::<AppState>().inner().shutdown.accepting_commands() {
+        return Err(anyhow!("Task {} was interrupted by shutdown", task_row.id));
+    }
+    
+    // The agent may have been removed while it worked; the task then belongs to someone else
+    let still_assigned = db.get_swarm_task(&task_row.id)?
+        .map(|t| t.assigned_to.as_deref() == Some(agent.id.as_str()) && t.status == "in_progress")
+        .unwrap_or(false);
+    if !still_assigned {
+        return Err(anyhow!("Task {} was reassigned while agent {} was running it", task_row.id, agent.id));
+    }
+    
+    task_row.status = if output.is_ok() { "completed" } else { "failed" }.to_string();
+    task_row.actual_duration = Some(elapsed);
+    task_row.updated_at = Utc::now();
+    db.save_swarm_task(&task_row)?;
+    set_in_flight(&app.state::<AppState>().inner().runs, swarm_id, &task_row.id, false);
+    
+    match &output {
+        Ok(_) => {
+            set_task_node_status(db, swarm_id, &task_row.id, "completed");
+            log_event(app, db, swarm_id, SwarmEventType::TaskCompleted, Some(&task_row.id), Some(&agent.id), serde_json::json!({ "duration": elapsed }));
+        }
+        Err(e) => {
+            set_task_node_status(db, swarm_id, &task_row.id, "error");
+            log_event(app, db, swarm_id, SwarmEventType::TaskFailed, Some(&task_row.id), Some(&agent.id), serde_json::json!({ "duration": elapsed, "error": e.to_string() }));
+            notifications::swarm_task_failed(app, swarm_id);
+        }
+    }
+    
+    agent.current_task_id = None;
+    record_agent_outcome(db, &mut agent, output.is_ok(), started.elapsed().as_secs_f32())?;
+    
+    let (prompt, output) = output?;
+    if let Err(e) = share_task_outcome(app, db, swarm_id, &agent.id, &task_row, &output) {
+        log::warn!("Failed to share outcome of task {}: {}", task_row.id, e);
+    }
+    
+    // What the provider counted for this call; only an answer without usage is estimated, from
+    // the prompt and the reply text rather than the response as a whole
+    let usage = pricing::usage_from_response(&output)
+        .unwrap_or_else(|| pricing::estimate_usage(&serde_json::json!({ "prompt": prompt }), &output));
+    let model = output.get("model")
+        .and_then(|m| m.as_str())
+        .map(str::to_string)
+        .or_else(|| ai_tools::tool_config(&app.state::<AppState>(), &agent.ai_tool).and_then(|c| c.model))
+        .unwrap_or_else(|| pricing::default_model_for_tool(&agent.ai_tool).to_string());
     
     let result = TaskResult {
         id: Uuid::new_v4().to_string(),
-        task_id: task.id,
-        agent_id: format!("agent_{}_0", swarm_id), // Mock agent
-        output: serde_json::json!({
-            "message": format!("Task '{}' completed successfully", task.title),
-            "details": "Mock task execution result"
-        }),
+        task_id: task_row.id.clone(),
+        agent_id: agent.id.clone(),
+        output,
         confidence: 0.95,
+        model: Some(model),
+        usage,
         timestamp: Utc::now(),
     };
     
+    db.create_task_result(&DbTaskResult {
+        id: result.id.clone(),
+        task_id: result.task_id.clone(),
+        agent_id: result.agent_id.clone(),
+        output: result.output.to_string(),
+        confidence: result.confidence,
+        model: result.model.clone(),
+        prompt_tokens: usage.prompt_tokens,
+        completion_tokens: usage.completion_tokens,
+        timestamp: result.timestamp,
+    })?;
+    
     Ok(result)
 }
 
-async fn mock_pause_swarm(_swarm_id: String) -> Result<()> {
-    tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-    Ok(())
+/// Workflow nodes are linked to tasks through a `task_id` field in their data.
+fn set_task_node_status(db: &Database, swarm_id: &str, task_id: &str, status: &str) {
+    let nodes = match db.get_swarm_workflow(swarm_id) {
+        Ok(nodes) => nodes,
+        Err(e) => {
+            log::warn!("Failed to load workflow for swarm {}: {}", swarm_id, e);
+            return;
+        }
+    };
+    
+    for node in nodes {
+        let data: serde_json::Value = serde_json::from_str(&node.data).unwrap_or(serde_json::Value::Null);
+        if data.get("task_id").and_then(|v| v.as_str()) == Some(task_id) {
+            if let Err(e) = db.update_workflow_node_status(swarm_id, &node.id, status) {
+                log::warn!("Failed to update workflow node {}: {}", node.id, e);
+            }
+        }
+    }
+}
+
+// Scheduler: queued tasks are dispatched to idle agents while the swarm is running.
+// The in-memory state is what `snapshot_swarm` persists so a run survives restarts.
+#[derive(Debug, Default)]
+struct SwarmRunState {
+    queue: VecDeque<String>,
+    in_flight: HashSet<String>,
+    active: bool,
+}
+
+/// The scheduler state of each swarm that has run since the app started. Kept in `AppState`.
+#[derive(Default)]
+pub struct SwarmRuns(StdMutex<HashMap<String, SwarmRunState>>);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulerSnapshot {
+    pub in_flight: Vec<String>,
+    pub queue: Vec<String>,
+    pub node_statuses: HashMap<String, String>,
+    pub taken_at: DateTime<Utc>,
+}
+
+fn set_in_flight(runs: &SwarmRuns, swarm_id: &str, task_id: &str, in_flight: bool) {
+    let mut runs = runs.0.lock().unwrap();
+    let run = runs.entry(swarm_id.to_string()).or_default();
+    if in_flight {
+        run.in_flight.insert(task_id.to_string());
+    } else {
+        run.in_flight.remove(task_id);
+    }
+}
+
+fn start_scheduler(app: &AppHandle, db: &Database, swarm_id: &str) {
+    {
+        let mut runs = app.state::<AppState>().inner().runs.0.lock().unwrap();
+        let run = runs.entry(swarm_id.to_string()).or_default();
+        if run.active {
+            return;
+        }
+        run.active = true;
+    }
+    
+    let app = app.clone();
+    let db = db.clone();
+    let swarm_id = swarm_id.to_string();
+    tokio::spawn(async move {
+        drive_swarm(&app, &db, &swarm_id).await;
+        if let Some(run) = app.state::<AppState>().inner().runs.0.lock().unwrap().get_mut(&swarm_id) {
+            run.active = false;
+        }
+    });
+}
+
+async fn drive_swarm(app: &AppHandle, db: &Database, swarm_id: &str) {
+    let mut running = tokio::task::JoinSet::new();
+    
+    loop {
+        let status = match db.get_swarm_by_id(swarm_id) {
+            Ok(Some(swarm)) => swarm.status,
+            Ok(None) => break,
+            Err(e) => {
+                log::error!("Scheduler for swarm {} stopped: {}", swarm_id, e);
+                break;
+            }
+        };
+        if status != "running" {
+            break;
+        }
+        
+        match next_ready_task(app, db, swarm_id) {
+            Some(task_row) => match claim_task(app, db, swarm_id, task_row.clone()) {
+                Ok((task_row, agent)) => {
+                    let app = app.clone();
+                    let db = db.clone();
+                    let owned_id = swarm_id.to_string();
+                    running.spawn(async move { perform_task(&app, &db, &owned_id, task_row, agent).await });
+                }
+                Err(_) => {
+                    // No idle agent: put it back and wait for a running task to free one
+                    requeue_front(&app.state::<AppState>().inner().runs, swarm_id, &task_row.id);
+                    if running.join_next().await.is_none() {
+                        log::warn!("Swarm {} has queued work but no usable agents", swarm_id);
+                        break;
+                    }
+                }
+            },
+            None => {
+                if running.join_next().await.is_none() {
+                    finish_run(app, db, swarm_id);
+                    break;
+                }
+            }
+        }
+    }
+    
+    // Let in-flight tasks finish; a paused swarm keeps their results
+    while running.join_next().await.is_some() {}
+}
+
+/// Pops the first queued task whose dependencies have all completed. Tasks whose
+/// dependencies failed or were cancelled can never run and are failed in place.
+fn next_ready_task(app: &AppHandle, db: &Database, swarm_id: &str) -> Option<DbSwarmTask> {
+    let runs = &app.state::<AppState>().inner().runs;
+    let queued: Vec<String> = runs.0.lock().unwrap()
+        .get(swarm_id)
+        .map(|run| run.queue.iter().cloned().collect())
+        .unwrap_or_default();
+    
+    for task_id in queued {
+        let task = match db.get_swarm_task(&task_id) {
+            Ok(Some(task)) if task.status == "pending" => task,
+            _ => {
+                remove_from_queue(runs, swarm_id, &task_id);
+                continue;
+            }
+        };
+        
+        let dependencies: Vec<String> = serde_json::from_str(&task.dependencies).unwrap_or_default();
+        let mut ready = true;
+        let mut blocked_by = None;
+        for dependency in &dependencies {
+            match db.get_swarm_task(dependency).ok().flatten().map(|t| t.status) {
+                Some(status) if status == "completed" => {}
+                Some(status) if status == "failed" || status == "cancelled" => blocked_by = Some(dependency.clone()),
+                _ => ready = false,
+            }
+        }
+        
+        if let Some(dependency) = blocked_by {
+            remove_from_queue(runs, swarm_id, &task.id);
+            let mut failed = task;
+            failed.status = "failed".to_string();
+            failed.updated_at = Utc::now();
+            if let Err(e) = db.save_swarm_task(&failed) {
+                log::warn!("Failed to fail blocked task {}: {}", failed.id, e);
+            }
+            log_event(app, db, swarm_id, SwarmEventType::TaskFailed, Some(&failed.id), None, serde_json::json!({ "error": format!("Dependency {} did not complete", dependency) }));
+            continue;
+        }
+        
+        if ready {
+            remove_from_queue(runs, swarm_id, &task.id);
+            return Some(task);
+        }
+    }
+    
+    None
+}
+
+fn remove_from_queue(runs: &SwarmRuns, swarm_id: &str, task_id: &str) {
+    if let Some(run) = runs.0.lock().unwrap().get_mut(swarm_id) {
+        run.queue.retain(|id| id != task_id);
+    }
+}
+
+fn requeue_front(runs: &SwarmRuns, swarm_id: &str, task_id: &str) {
+    let mut runs = runs.0.lock().unwrap();
+    let run = runs.entry(swarm_id.to_string()).or_default();
+    run.queue.retain(|id| id != task_id);
+    run.queue.push_front(task_id.to_string());
+}
+
+fn finish_run(app: &AppHandle, db: &Database, swarm_id: &str) {
+    let stats = match db.get_swarm_task_stats(swarm_id) {
+        Ok(stats) => stats,
+        Err(e) => {
+            log::warn!("Failed to finish swarm {}: {}", swarm_id, e);
+            return;
+        }
+    };
+    if stats.pending > 0 || stats.in_progress > 0 {
+        return;
+    }
+    
+    let status = if stats.failed > 0 { "failed" } else { "completed" };
+    match set_swarm_status(app, db, swarm_id, "running", status) {
+        Ok(()) => {
+            emit_swarm_updated(app, db, swarm_id);
+            notifications::swarm_finished(app, swarm_id, status);
+        }
+        Err(e) => log::warn!("Failed to finish swarm {}: {}", swarm_id, e),
+    }
+}
+
+fn take_snapshot(state: &AppState, swarm_id: &str) -> Result<SchedulerSnapshot> {
+    let db = &state.db;
+    let from_memory = state.runs.0.lock().unwrap()
+        .get(swarm_id)
+        .map(|run| (run.in_flight.iter().cloned().collect::<Vec<_>>(), run.queue.iter().cloned().collect::<Vec<_>>()));
+    
+    // Nothing in memory (e.g. after a restart): the task table is the best record
+    let (in_flight, queue) = match from_memory {
+        Some(state) => state,
+        None => {
+            let tasks = db.get_swarm_tasks(swarm_id)?;
+            (
+                tasks.iter().filter(|t| t.status == "in_progress").map(|t| t.id.clone()).collect(),
+                tasks.iter().filter(|t| t.status == "pending").map(|t| t.id.clone()).collect(),
+            )
+        }
+    };
+    
+    let node_statuses = db.get_swarm_workflow(swarm_id)?
+        .into_iter()
+        .map(|node| (node.id, node.status))
+        .collect();
+    
+    Ok(SchedulerSnapshot { in_flight, queue, node_statuses, taken_at: Utc::now() })
+}
+
+fn save_snapshot(state: &AppState, swarm_id: &str) -> Result<SchedulerSnapshot> {
+    let snapshot = take_snapshot(state, swarm_id)?;
+    state.db.set_swarm_snapshot(swarm_id, Some(&serde_json::to_string(&snapshot)?))?;
+    Ok(snapshot)
 }
 
-async fn mock_resume_swarm(_swarm_id: String) -> Result<()> {
-    tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+/// Rebuilds the run queue from a saved snapshot. In-flight tasks go back to pending
+/// at the front of the queue; anything the snapshot references that no longer matches
+/// the task table invalidates the whole snapshot.
+fn restore_snapshot(app: &AppHandle, db: &Database, swarm_id: &str) -> Result<()> {
+    // After a quick pause and resume some tasks may still be running; they keep their agent and
+    // their result counts, so they aren't queued again
+    let running: HashSet<String> = app.state::<AppState>().inner().runs.0.lock().unwrap()
+        .get(swarm_id)
+        .map(|run| run.in_flight.clone())
+        .unwrap_or_default();
+    let raw = db.get_swarm_snapshot(swarm_id)?;
+    db.set_swarm_snapshot(swarm_id, None)?;
+    
+    let tasks: HashMap<String, DbSwarmTask> = db.get_swarm_tasks(swarm_id)?
+        .into_iter()
+        .map(|t| (t.id.clone(), t))
+        .collect();
+    
+    let snapshot = match raw.map(|raw| serde_json::from_str::<SchedulerSnapshot>(&raw)) {
+        None => None,
+        Some(Ok(snapshot)) => match validate_snapshot(&snapshot, &tasks) {
+            Ok(()) => Some(snapshot),
+            Err(reason) => {
+                discard_snapshot(app, db, swarm_id, &reason);
+                None
+            }
+        },
+        Some(Err(e)) => {
+            discard_snapshot(app, db, swarm_id, &format!("unreadable snapshot: {}", e));
+            None
+        }
+    };
+    
+    let mut queue: VecDeque<String> = VecDeque::new();
+    match snapshot {
+        Some(snapshot) => {
+            for task_id in &snapshot.in_flight {
+                if let Some(task) = tasks.get(task_id) {
+                    if task.status == "in_progress" && !running.contains(task_id) {
+                        let mut pending = task.clone();
+                        pending.status = "pending".to_string();
+                        pending.updated_at = Utc::now();
+                        db.save_swarm_task(&pending)?;
+                        queue.push_back(task_id.clone());
+                    }
+                }
+            }
+            queue.extend(snapshot.queue.iter().filter(|id| tasks[*id].status == "pending").cloned());
+            
+            // Tasks queued while the swarm was paused aren't part of the snapshot
+            let mut late: Vec<&DbSwarmTask> = tasks.values()
+                .filter(|t| t.status == "pending" && !queue.contains(&t.id))
+                .collect();
+            late.sort_by(|a, b| b.priority.cmp(&a.priority).then(a.created_at.cmp(&b.created_at)));
+            queue.extend(late.into_iter().map(|t| t.id.clone()));
+            
+            for (node_id, status) in &snapshot.node_statuses {
+                let status = if status == "running" { "idle" } else { status.as_str() };
+                db.update_workflow_node_status(swarm_id, node_id, status)?;
+            }
+        }
+        None => {
+            let mut pending: Vec<&DbSwarmTask> = tasks.values()
+                .filter(|t| t.status == "pending" || (t.status == "in_progress" && !running.contains(&t.id)))
+                .collect();
+            for task in pending.iter().filter(|t| t.status == "in_progress") {
+                db.save_swarm_task(&DbSwarmTask { status: "pending".to_string(), updated_at: Utc::now(), ..(*task).clone() })?;
+            }
+            pending.sort_by(|a, b| b.priority.cmp(&a.priority).then(a.created_at.cmp(&b.created_at)));
+            queue.extend(pending.into_iter().map(|t| t.id.clone()));
+        }
+    }
+    
+    for agent in db.get_swarm_agents(swarm_id)? {
+        if agent.current_task_id.as_ref().is_some_and(|task_id| !running.contains(task_id)) {
+            db.update_swarm_agent(&DbSwarmAgent { current_task_id: None, ..agent })?;
+        }
+    }
+    
+    let mut runs = app.state::<AppState>().inner().runs.0.lock().unwrap();
+    let run = runs.entry(swarm_id.to_string()).or_default();
+    run.in_flight.retain(|task_id| running.contains(task_id));
+    run.queue = queue;
+    
     Ok(())
 }
 
-async fn mock_stop_swarm(_swarm_id: String) -> Result<()> {
-    tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+fn validate_snapshot(snapshot: &SchedulerSnapshot, tasks: &HashMap<String, DbSwarmTask>) -> std::result::Result<(), String> {
+    for task_id in snapshot.in_flight.iter().chain(snapshot.queue.iter()) {
+        if !tasks.contains_key(task_id) {
+            return Err(format!("snapshot references unknown task {}", task_id));
+        }
+    }
+    if snapshot.taken_at > Utc::now() {
+        return Err("snapshot is dated in the future".to_string());
+    }
     Ok(())
 }
 
-async fn mock_add_agent(_swarm_id: String, agent: Agent) -> Result<Agent> {
-    tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
-    Ok(agent)
+fn discard_snapshot(app: &AppHandle, db: &Database, swarm_id: &str, reason: &str) {
+    log::warn!("Discarding snapshot for swarm {}: {}", swarm_id, reason);
+    log_event(app, db, swarm_id, SwarmEventType::Warning, None, None, serde_json::json!({ "message": "Snapshot discarded", "reason": reason }));
 }
 
-async fn mock_remove_agent(_swarm_id: String, _agent_id: String) -> Result<()> {
-    tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
-    Ok(())
+/// Called on app shutdown: every running swarm is snapshotted and paused so it can be resumed.
+pub fn snapshot_running_swarms(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let db = &state.db;
+    let swarms = match db.get_all_swarms() {
+        Ok(swarms) => swarms,
+        Err(e) => {
+            log::warn!("Skipping swarm snapshots: {}", e);
+            return;
+        }
+    };
+    
+    for swarm in swarms.into_iter().filter(|s| s.status == "running") {
+        match save_snapshot(&state, &swarm.id).and_then(|_| set_swarm_status(app, db, &swarm.id, "running", "paused")) {
+            Ok(()) => log::info!("Snapshotted swarm {} for shutdown", swarm.id),
+            Err(e) => log::warn!("Failed to snapshot swarm {}: {}", swarm.id, e),
+        }
+    }
 }
 
-async fn mock_query_memory(_namespace: String, _query: String) -> Result<Vec<MemoryEntry>> {
-    tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+/// Picks the requested agent if given, otherwise the first idle worker (queens coordinate last).
+fn select_agent(db: &Database, swarm_id: &str, requested: Option<&str>) -> Result<DbSwarmAgent> {
+    let agents = db.get_swarm_agents(swarm_id)?;
     
-    let entry = MemoryEntry {
+    if let Some(agent_id) = requested {
+        return agents.into_iter()
+            .find(|a| a.id == agent_id)
+            .ok_or_else(|| anyhow!("Agent not found in swarm: {}", agent_id));
+    }
+    
+    let mut idle: Vec<DbSwarmAgent> = agents.into_iter()
+        .filter(|a| a.is_active && a.current_task_id.is_none())
+        .collect();
+    idle.sort_by_key(|a| a.agent_type == "queen");
+    
+    idle.into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("No idle agent available in swarm {}", swarm_id))
+}
+
+fn record_agent_outcome(db: &Database, agent: &mut DbSwarmAgent, success: bool, response_time: f32) -> Result<()> {
+    let mut metrics: AgentMetrics = serde_json::from_str(&agent.performance)
+        .unwrap_or_else(|_| empty_agent_metrics());
+    
+    let previous = metrics.tasks_completed as f32;
+    let successes = metrics.success_rate * previous + if success { 1.0 } else { 0.0 };
+    metrics.average_response_time = (metrics.average_response_time * previous + response_time) / (previous + 1.0);
+    metrics.tasks_completed += 1;
+    metrics.success_rate = successes / metrics.tasks_completed as f32;
+    
+    agent.performance = serde_json::to_string(&metrics)?;
+    db.update_swarm_agent(agent)
+}
+
+fn check_agent_tool(db: &Database, swarm_id: &str, agent: &DbSwarmAgent) -> Result<()> {
+    let swarm = db.get_swarm_by_id(swarm_id)?
+        .ok_or_else(|| anyhow!("Swarm not found: {}", swarm_id))?;
+    if let Some(policy) = ToolPolicy::load(db, &swarm.project_id)? {
+        policy.check(&agent.ai_tool)?;
+    }
+    Ok(())
+}
+
+/// Sends the prompt to the agent's AI tool and returns what the tool answered. The command is
+/// tagged with the swarm and task, so it shows up with them in the tool's history.
+async fn execute_with_agent(app: &AppHandle, swarm_id: &str, agent: &DbSwarmAgent, task: &Task, prompt: &str) -> Result<serde_json::Value> {
+    let command = AICommand {
         id: Uuid::new_v4().to_string(),
-        entry_type: "conversation".to_string(),
-        content: serde_json::json!({
-            "message": "Mock memory entry",
-            "context": "This is a sample memory entry for testing"
-        }),
-        metadata: HashMap::new(),
-        importance: 5,
+        tool_id: agent.ai_tool.clone(),
+        command_type: "chat".to_string(),
+        payload: serde_json::json!({ "prompt": prompt }),
         timestamp: Utc::now(),
+        timeout_ms: None,
+        priority: 0,
+        session_id: None,
+        swarm_id: Some(swarm_id.to_string()),
+        task_id: Some(task.id.clone()),
+        broadcast_id: None,
     };
+    let response = ai_tools::send_ai_command(app.clone(), agent.ai_tool.clone(), command).await?;
+    if !response.success {
+        return Err(anyhow!(response.error.unwrap_or_else(|| format!("{} reported an error", agent.ai_tool))));
+    }
+    let mut output = response.data.unwrap_or_default();
+    
+    // Still on behalf of the swarm, so what the agent asks to touch or run goes through approval
+    let db = &app.state::<AppState>().inner().db;
+    let project_id = db.get_swarm_by_id(swarm_id)?
+        .ok_or_else(|| anyhow!("Swarm not found: {}", swarm_id))?
+        .project_id;
+    let project = db.get_project(&project_id)?
+        .ok_or_else(|| anyhow!("Project not found: {}", project_id))?;
+    let actions = agent_actions::perform(app, &project, &chat::reply_text(&output)).await?;
+    if let Some(fields) = output.as_object_mut().filter(|_| !actions.is_empty()) {
+        fields.insert("actions".to_string(), serde_json::to_value(&actions)?);
+    }
     
-    Ok(vec![entry])
-}
\ No newline at end of file
+    Ok(output)
+}
+
+/// The system prompt template for an agent type; unknown types have none.
+pub(crate) fn role_system_prompt(agent_type: &str) -> Option<&'static str> {
+    ROLE_SYSTEM_PROMPTS.iter().find(|(t, _)| *t == agent_type).map(|(_, prompt)| *prompt)
+}
+
+fn default_role(agent_type: &str) -> &'static str {
+    if agent_type == "queen" { "coordinator" } else { "executor" }
+}
+
+fn empty_agent_metrics() -> AgentMetrics {
+    AgentMetrics {
+        tasks_completed: 0,
+        success_rate: 0.0,
+        average_response_time: 0.0,
+        collaboration_rating: 0.0,
+        specialty_score: HashMap::new(),
+    }
+}
@@ -1,9 +1,28 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
-use std::process::Command;
-use anyhow::{Result, Context};
+use std::io::{BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::sync::Mutex;
+use anyhow::{anyhow, Result, Context};
 use chrono::{DateTime, Utc};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio_util::sync::CancellationToken;
+use crate::approvals::{self, Operation};
+use crate::commands::env_vars::{app_env, validate_env_name};
+use crate::commands::workspaces::check_quota;
+use crate::commands::{database_path, git, project_stats, undo};
+use crate::database::{self, Database};
+use crate::error::{typed_or, AppError};
+use crate::events::{Action, DataChanged, Entity};
+use crate::file_backups::{self, BackupSettings, FileBackup};
+use crate::logging;
+use crate::path_access::{self, PathScope};
+use crate::state::AppState;
+use crate::telemetry::timed;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileItem {
@@ -15,6 +34,66 @@ pub struct FileItem {
     pub modified: Option<DateTime<Utc>>,
     pub children: Option<Vec<FileItem>>,
     pub expanded: Option<bool>,
+    // Set when `children` was cut short by an entry limit
+    #[serde(default)]
+    pub truncated: Option<bool>,
+    // Matched by an ignore file; only listed when asked for
+    #[serde(default)]
+    pub ignored: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolAvailability {
+    pub name: String,
+    pub found: bool,
+    pub path: Option<String>,
+    // First line of `--version`, if it answered in time
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileContent {
+    pub path: String,
+    pub content: String,
+    pub size: u64,
+    // Only the first max_bytes were read
+    pub truncated: bool,
+    // WHATWG name, e.g. "UTF-8", "Shift_JIS", "UTF-16LE"
+    pub encoding: String,
+    pub bom: bool,
+    // Some bytes couldn't be decoded and were replaced with U+FFFD
+    pub lossy: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    #[default]
+    Sha256,
+    Blake3,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileHash {
+    pub algorithm: HashAlgorithm,
+    pub hash: String, // lowercase hex
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileRange {
+    pub path: String,
+    pub start_line: usize,
+    pub lines: Vec<String>,
+    pub has_more: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WriteMode {
+    Create, // fails if the file exists
+    Overwrite,
+    Append,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,14 +101,85 @@ pub struct ProcessInfo {
     pub id: String,
     pub name: String,
     pub command: String,
-    pub status: String, // 'running' | 'stopped' | 'failed'
+    pub status: String, // 'running' | 'completed' | 'failed' | 'stopped' | 'timeout'
     pub pid: Option<u32>,
+    #[serde(default)]
+    pub exit_code: Option<i32>,
     pub started_at: DateTime<Utc>,
+    #[serde(default)]
+    pub ended_at: Option<DateTime<Utc>>,
+    // Until now, or until it ended
+    #[serde(default)]
+    pub uptime_ms: i64,
     pub output: Vec<String>,
 }
 
+/// A slice of a managed process's output. Line numbers count from the first line the process
+/// printed, so they stay valid after older lines are dropped from the buffer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessOutput {
+    pub process_id: String,
+    pub from_line: usize,
+    pub lines: Vec<String>,
+    pub next_line: usize,
+    // Lines before this one are no longer buffered
+    pub first_buffered_line: usize,
+}
+
+/// A process started by `execute_command_streaming`; cancelling `kill` stops it. Keeps the last
+/// `MAX_BUFFERED_LINES` lines of output, stderr lines prefixed with `ERROR: ` as in `execute_command`.
+struct ManagedProcess {
+    info: ProcessInfo,
+    kill: CancellationToken,
+    output: VecDeque<String>,
+    dropped_lines: usize,
+}
+
+impl ManagedProcess {
+    fn snapshot(&self) -> ProcessInfo {
+        let mut info = self.info.clone();
+        info.uptime_ms = (info.ended_at.unwrap_or_else(Utc::now) - info.started_at).num_milliseconds();
+        info
+    }
+}
+
+/// The processes started by `execute_command_streaming`, by process id. Kept in `AppState`.
+#[derive(Default)]
+pub struct ManagedProcesses(Mutex<HashMap<String, ManagedProcess>>);
+
+impl ManagedProcesses {
+    /// Kills every process that is still running. Used on shutdown; their exit events go out
+    /// as usual.
+    pub fn stop_all(&self) {
+        for process in self.0.lock().unwrap().values() {
+            process.kill.cancel();
+        }
+    }
+}
+
+const PROCESS_STDOUT_EVENT: &str = "process:stdout";
+const PROCESS_STDERR_EVENT: &str = "process:stderr";
+const PROCESS_EXIT_EVENT: &str = "process:exit";
+// Output still buffered after exit is forwarded for this long before the exit event goes out
+const OUTPUT_DRAIN_TIMEOUT: tokio::time::Duration = tokio::time::Duration::from_secs(2);
+const MAX_BUFFERED_LINES: usize = 5000;
+const DEFAULT_TREE_DEPTH: usize = 3;
+const DEFAULT_READ_LIMIT: u64 = 2 * 1024 * 1024;
+const MAX_RANGE_LINES: usize = 10_000;
+const BINARY_SNIFF_BYTES: usize = 8192;
+// Share of replacement characters above which decoded bytes are treated as binary
+const MAX_LOSSY_PERCENT: usize = 5;
+const DEFAULT_DELETION_LIMIT: i64 = 50;
+const VERSION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+const DEFAULT_TREE_ENTRIES: usize = 5000;
+const DEFAULT_TREE_IGNORES: [&str; 3] = ["node_modules", ".git", "target"];
+const DEFAULT_OUTPUT_PAGE: usize = 500;
+const DEFAULT_APP_LOG_LINES: usize = 200;
+const MAX_APP_LOG_LINES: usize = 10_000;
+
 #[tauri::command]
-pub async fn read_directory(path: String) -> Result<Vec<FileItem>, String> {
+#[timed]
+pub async fn read_directory(state: State<'_, AppState>, path: String) -> Result<Vec<FileItem>, String> {
     log::info!("Reading directory: {}", path);
     
     let dir_path = PathBuf::from(&path);
@@ -48,48 +198,195 @@ pub async fn read_directory(path: String) -> Result<Vec<FileItem>, String> {
     
     for entry in entries {
         let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
-        let metadata = entry.metadata()
+        let item = path_item(&entry.path())
             .map_err(|e| format!("Failed to read metadata: {}", e))?;
+        items.push(item);
+    }
+    
+    sort_items(&mut items);
+    
+    Ok(items)
+}
+
+/// Like `read_directory`, but honours `.gitignore` files (nested ones and those of parent
+/// directories), `.git/info/exclude` and the global git excludes. With `include_ignored` the
+/// ignored entries are listed too, marked `ignored`. Dotfiles are left out unless `include_hidden`.
+#[tauri::command]
+#[timed]
+pub async fn read_directory_filtered(state: State<'_, AppState>, path: String, include_ignored: Option<bool>, include_hidden: Option<bool>) -> Result<Vec<FileItem>, String> {
+    log::info!("Reading directory with ignore rules: {}", path);
+    
+    let dir_path = PathBuf::from(&path);
+    if !dir_path.is_dir() {
+        return Err("Path is not a directory".to_string());
+    }
+    
+    let items = list_filtered(&dir_path, include_ignored.unwrap_or(false), include_hidden.unwrap_or(false))
+        .map_err(|e| format!("Failed to read directory: {}", e))?;
+    
+    Ok(items)
+}
+
+fn list_filtered(dir: &Path, include_ignored: bool, include_hidden: bool) -> Result<Vec<FileItem>> {
+    // What the walker yields is what the ignore rules keep; hidden files are filtered below
+    let kept: HashSet<PathBuf> = ignore::WalkBuilder::new(dir)
+        .max_depth(Some(1))
+        .hidden(false)
+        .require_git(false)
+        .build()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.depth() == 1)
+        .map(|entry| entry.into_path())
+        .collect();
+    
+    let mut items = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if !include_hidden && entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let ignored = !kept.contains(&entry.path());
+        if ignored && !include_ignored {
+            continue;
+        }
+        let mut item = path_item(&entry.path())?;
+        item.ignored = Some(ignored);
+        items.push(item);
+    }
+    sort_items(&mut items);
+    
+    Ok(items)
+}
+
+/// The directory with its subdirectories filled in through `children`, down to `max_depth`
+/// levels. Directories named in `ignore` (by default `node_modules`, `.git` and `target`) are
+/// left out. Once `max_entries` items are listed, listings are cut short and marked `truncated`.
+#[tauri::command]
+#[timed]
+pub async fn read_directory_tree(state: State<'_, AppState>, path: String, max_depth: Option<usize>, max_entries: Option<usize>, ignore: Option<Vec<String>>) -> Result<FileItem, String> {
+    log::info!("Reading directory tree: {}", path);
+    
+    let dir_path = PathBuf::from(&path);
+    if !dir_path.is_dir() {
+        return Err("Path is not a directory".to_string());
+    }
+    let max_depth = max_depth.unwrap_or(DEFAULT_TREE_DEPTH);
+    if max_depth == 0 {
+        return Err("max_depth must be at least 1".to_string());
+    }
+    
+    let mut walk = TreeWalk {
+        ignore: ignore.unwrap_or_else(|| DEFAULT_TREE_IGNORES.iter().map(|s| s.to_string()).collect()),
+        max_depth,
+        max_entries: max_entries.unwrap_or(DEFAULT_TREE_ENTRIES),
+        entries: 0,
+        visited: HashSet::new(),
+    };
+    tokio::task::spawn_blocking(move || walk.read(&dir_path))
+        .await
+        .map_err(|e| format!("Failed to read directory tree: {}", e))?
+        .map_err(|e| format!("Failed to read directory tree: {}", e))
+}
+
+struct TreeWalk {
+    ignore: Vec<String>,
+    max_depth: usize,
+    max_entries: usize,
+    entries: usize,
+    // Canonical paths already listed, so symlinked directories can't loop
+    visited: HashSet<PathBuf>,
+}
+
+impl TreeWalk {
+    fn read(&mut self, root: &Path) -> Result<FileItem> {
+        let mut item = path_item(root)?;
+        item.expanded = Some(true);
+        self.fill(&mut item, root, 1)?;
+        Ok(item)
+    }
+    
+    fn fill(&mut self, item: &mut FileItem, dir: &Path, depth: usize) -> Result<()> {
+        if !self.visited.insert(fs::canonicalize(dir)?) {
+            return Ok(());
+        }
         
-        let file_name = entry.file_name().to_string_lossy().to_string();
-        let file_path = entry.path().to_string_lossy().to_string();
-        
-        let file_type = if metadata.is_dir() {
-            "directory".to_string()
-        } else {
-            "file".to_string()
-        };
-        
-        let size = if metadata.is_file() {
-            Some(metadata.len())
-        } else {
-            None
-        };
-        
-        let modified = metadata.modified()
-            .ok()
-            .and_then(|time| {
-                time.duration_since(std::time::UNIX_EPOCH)
-                    .ok()
-                    .map(|duration| {
-                        DateTime::from_timestamp(duration.as_secs() as i64, 0)
-                            .unwrap_or_else(|| Utc::now())
-                    })
-            });
+        let mut children = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            if self.ignore.iter().any(|name| entry.file_name() == name.as_str()) {
+                continue;
+            }
+            if self.entries >= self.max_entries {
+                item.truncated = Some(true);
+                break;
+            }
+            children.push(path_item(&entry.path())?);
+            self.entries += 1;
+        }
+        sort_items(&mut children);
         
-        items.push(FileItem {
-            id: uuid::Uuid::new_v4().to_string(),
-            name: file_name,
-            path: file_path,
-            file_type,
-            size,
-            modified,
-            children: None,
-            expanded: Some(false),
-        });
+        if depth < self.max_depth {
+            for child in children.iter_mut().filter(|c| c.file_type == "directory") {
+                let path = PathBuf::from(&child.path);
+                // An unreadable subdirectory stays collapsed instead of failing the whole tree
+                if let Err(e) = self.fill(child, &path, depth + 1) {
+                    log::warn!("Skipping {}: {}", path.display(), e);
+                }
+            }
+        }
+        item.children = Some(children);
+        Ok(())
     }
+}
+
+/// Symlinks are followed, so a link to a directory is listed as one; broken links show up as files.
+pub(crate) fn path_item(path: &Path) -> std::io::Result<FileItem> {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => fs::symlink_metadata(path)?,
+    };
+    
+    let file_type = if metadata.is_dir() {
+        "directory".to_string()
+    } else {
+        "file".to_string()
+    };
+    
+    let size = if metadata.is_file() {
+        Some(metadata.len())
+    } else {
+        None
+    };
     
-    // Sort: directories first, then files, both alphabetically
+    Ok(FileItem {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: path.file_name().map_or_else(|| path.to_string_lossy().to_string(), |n| n.to_string_lossy().to_string()),
+        path: path.to_string_lossy().to_string(),
+        file_type,
+        size,
+        modified: modified_time(&metadata),
+        children: None,
+        expanded: Some(false),
+        truncated: None,
+        ignored: None,
+    })
+}
+
+fn modified_time(metadata: &fs::Metadata) -> Option<DateTime<Utc>> {
+    metadata.modified()
+        .ok()
+        .and_then(|time| {
+            time.duration_since(std::time::UNIX_EPOCH)
+                .ok()
+                .map(|duration| {
+                    DateTime::from_timestamp(duration.as_secs() as i64, 0)
+                        .unwrap_or_else(Utc::now)
+                })
+        })
+}
+
+// Directories first, then files, both alphabetically
+fn sort_items(items: &mut [FileItem]) {
     items.sort_by(|a, b| {
         match (a.file_type.as_str(), b.file_type.as_str()) {
             ("directory", "file") => std::cmp::Ordering::Less,
@@ -97,126 +394,1138 @@ pub async fn read_directory(path: String) -> Result<Vec<FileItem>, String> {
             _ => a.name.cmp(&b.name),
         }
     });
-    
-    Ok(items)
 }
 
+/// Reads a text file, or its first `max_bytes` (2 MB by default) with `truncated` set. Files that
+/// aren't UTF-8 are decoded from the encoding named by their BOM or, failing that, the most
+/// likely one; `encoding` and `bom` should be passed back to `write_file_content` when saving.
+/// Binary and undecodable files are refused with a `binary_file` error.
 #[tauri::command]
-pub async fn read_file_content(path: String) -> Result<String, String> {
+#[timed]
+pub async fn read_file_content(state: State<'_, AppState>, path: String, max_bytes: Option<u64>, project_id: Option<String>) -> Result<FileContent, AppError> {
     log::info!("Reading file content: {}", path);
     
     let file_path = PathBuf::from(&path);
+    path_access::check(&state, &file_path, project_id.as_deref())?;
     if !file_path.exists() {
-        return Err("File does not exist".to_string());
+        return Err(AppError::Internal("File does not exist".to_string()));
     }
     
     if !file_path.is_file() {
-        return Err("Path is not a file".to_string());
+        return Err(AppError::Internal("Path is not a file".to_string()));
+    }
+    
+    read_text(&file_path, max_bytes.unwrap_or(DEFAULT_READ_LIMIT))
+}
+
+/// Lines `start_line` to `end_line` (1-based, inclusive) of a text file, read without loading
+/// the rest; `has_more` tells whether the file goes on past the range.
+#[tauri::command]
+#[timed]
+pub async fn read_file_range(state: State<'_, AppState>, path: String, start_line: usize, end_line: usize, project_id: Option<String>) -> Result<FileRange, AppError> {
+    log::info!("Reading lines {}-{} of {}", start_line, end_line, path);
+    
+    path_access::check(&state, Path::new(&path), project_id.as_deref())?;
+    if start_line == 0 || end_line < start_line {
+        return Err(AppError::Internal("Line range must start at 1 and not end before it starts".to_string()));
+    }
+    if end_line - start_line >= MAX_RANGE_LINES {
+        return Err(AppError::Internal(format!("Line range cannot exceed {} lines", MAX_RANGE_LINES)));
     }
     
-    let content = fs::read_to_string(&file_path)
-        .map_err(|e| format!("Failed to read file: {}", e))?;
+    read_lines(Path::new(&path), start_line, end_line)
+        .map_err(|e| match e.downcast::<AppError>() {
+            Ok(app_error) => app_error,
+            Err(e) => AppError::Internal(format!("Failed to read file: {}", e)),
+        })
+}
+
+pub(crate) fn read_text(path: &Path, max_bytes: u64) -> Result<FileContent, AppError> {
+    let read_error = |e: std::io::Error| AppError::Internal(format!("Failed to read file: {}", e));
+    let size = fs::metadata(path).map_err(read_error)?.len();
+    
+    let mut bytes = Vec::new();
+    fs::File::open(path).map_err(read_error)?
+        .take(max_bytes)
+        .read_to_end(&mut bytes)
+        .map_err(read_error)?;
+    let truncated = size > bytes.len() as u64;
+    let decoded = decode_text(&bytes, truncated).ok_or(AppError::BinaryFile { size })?;
+    
+    Ok(FileContent {
+        path: path.to_string_lossy().to_string(),
+        content: decoded.text,
+        size,
+        truncated,
+        encoding: decoded.encoding.name().to_string(),
+        bom: decoded.bom,
+        lossy: decoded.lossy,
+    })
+}
+
+struct DecodedText {
+    text: String,
+    encoding: &'static encoding_rs::Encoding,
+    bom: bool,
+    // Some bytes didn't decode and were replaced with U+FFFD
+    lossy: bool,
+}
+
+/// Decodes by BOM, then as UTF-8, then by guessing the legacy encoding. `None` means the bytes
+/// aren't text. `partial` says more of the file follows, so a character cut off at the end is
+/// dropped rather than counted as an error.
+fn decode_text(bytes: &[u8], partial: bool) -> Option<DecodedText> {
+    let (encoding, bom_length) = match encoding_rs::Encoding::for_bom(bytes) {
+        Some(found) => found,
+        None if looks_binary(bytes) => return None,
+        None => {
+            let valid = match std::str::from_utf8(bytes) {
+                Ok(_) => true,
+                Err(e) => partial && e.error_len().is_none(),
+            };
+            if valid {
+                (encoding_rs::UTF_8, 0)
+            } else {
+                let mut detector = chardetng::EncodingDetector::new();
+                detector.feed(bytes, !partial);
+                (detector.guess(None, true), 0)
+            }
+        }
+    };
+    
+    let bytes = &bytes[bom_length..];
+    let mut decoder = encoding.new_decoder_without_bom_handling();
+    let mut text = String::with_capacity(decoder.max_utf8_buffer_length(bytes.len())?);
+    let (_, _, lossy) = decoder.decode_to_string(bytes, &mut text, !partial);
+    
+    // Mostly replacement characters means it was never text in any encoding we know
+    if lossy && text.chars().filter(|c| *c == char::REPLACEMENT_CHARACTER).count() * 100 > text.chars().count() * MAX_LOSSY_PERCENT {
+        return None;
+    }
+    Some(DecodedText { text, encoding, bom: bom_length > 0, lossy })
+}
+
+/// Encodes `content` for writing back in the encoding it was read in. Characters the encoding
+/// can't represent are an error rather than being replaced.
+fn encode_text(content: &str, label: &str, bom: bool) -> Result<Vec<u8>> {
+    let encoding = encoding_rs::Encoding::for_label(label.trim().as_bytes())
+        .ok_or_else(|| anyhow!("Unknown encoding: {}", label))?;
+    
+    // encoding_rs only decodes UTF-16, so it is encoded here
+    if encoding == encoding_rs::UTF_16LE || encoding == encoding_rs::UTF_16BE {
+        let big_endian = encoding == encoding_rs::UTF_16BE;
+        let units = bom.then_some('\u{feff}' as u16).into_iter().chain(content.encode_utf16());
+        return Ok(units
+            .flat_map(|unit| if big_endian { unit.to_be_bytes() } else { unit.to_le_bytes() })
+            .collect());
+    }
+    
+    let (encoded, _, unmappable) = encoding.encode(content);
+    if unmappable {
+        return Err(anyhow!("The content has characters that {} cannot represent", encoding.name()));
+    }
+    let mut bytes = if bom && encoding == encoding_rs::UTF_8 { vec![0xEF, 0xBB, 0xBF] } else { Vec::new() };
+    bytes.extend_from_slice(&encoded);
+    Ok(bytes)
+}
+
+fn read_lines(path: &Path, start_line: usize, end_line: usize) -> Result<FileRange> {
+    let mut reader = std::io::BufReader::new(fs::File::open(path)?);
+    if looks_binary(reader.fill_buf()?) {
+        return Err(AppError::BinaryFile { size: fs::metadata(path)?.len() }.into());
+    }
+    
+    let mut lines = Vec::new();
+    let mut buffer = Vec::new();
+    let mut line_number = 0;
+    let has_more = loop {
+        buffer.clear();
+        if reader.read_until(b'\n', &mut buffer)? == 0 {
+            break false;
+        }
+        line_number += 1;
+        if line_number > end_line {
+            break true;
+        }
+        if line_number >= start_line {
+            lines.push(String::from_utf8_lossy(&buffer).trim_end_matches(['\n', '\r']).to_string());
+        }
+    };
+    
+    Ok(FileRange {
+        path: path.to_string_lossy().to_string(),
+        start_line,
+        lines,
+        has_more,
+    })
+}
+
+/// A NUL byte in the first 8 KB. Only UTF-16 text contains them, and that is recognised by its BOM first.
+pub(crate) fn looks_binary(bytes: &[u8]) -> bool {
+    bytes.iter().take(BINARY_SNIFF_BYTES).any(|b| *b == 0)
+}
+
+/// Hashes a file without reading it into memory, so it can be compared with what was read
+/// earlier or passed to `write_file_content` as `expected_hash`.
+#[tauri::command]
+#[timed]
+pub async fn hash_file(state: State<'_, AppState>, path: String, algorithm: Option<HashAlgorithm>, project_id: Option<String>) -> Result<FileHash, AppError> {
+    let file_path = PathBuf::from(&path);
+    path_access::check(&state, &file_path, project_id.as_deref())?;
     
-    Ok(content)
+    let algorithm = algorithm.unwrap_or_default();
+    tokio::task::spawn_blocking(move || digest_file(&file_path, algorithm))
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to hash file: {}", e)))?
+        .map_err(|e| AppError::Internal(format!("Failed to hash file: {}", e)))
+}
+
+pub(crate) fn digest_file(path: &Path, algorithm: HashAlgorithm) -> std::io::Result<FileHash> {
+    let mut file = fs::File::open(path)?;
+    let (hash, size) = match algorithm {
+        HashAlgorithm::Sha256 => {
+            let mut hasher = sha2::Sha256::default();
+            let size = std::io::copy(&mut file, &mut hasher)?;
+            (format!("{:x}", sha2::Digest::finalize(hasher)), size)
+        }
+        HashAlgorithm::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+            let size = std::io::copy(&mut file, &mut hasher)?;
+            (hasher.finalize().to_hex().to_string(), size)
+        }
+    };
+    Ok(FileHash { algorithm, hash, size })
 }
 
+/// Writes `content` as UTF-8, or in `encoding` (a name `read_file_content` returned) so files
+/// that were read in another encoding keep it. `bom` defaults to on for UTF-16 and off otherwise.
+/// With `expected_hash` (from `hash_file`, by `hash_algorithm`), the write is refused with a
+/// `conflict` error if the file no longer has that hash, i.e. someone changed it since.
 #[tauri::command]
-pub async fn write_file_content(path: String, content: String) -> Result<(), String> {
+#[timed]
+#[allow(clippy::too_many_arguments)]
+pub async fn write_file_content(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    content: String,
+    encoding: Option<String>,
+    bom: Option<bool>,
+    expected_hash: Option<String>,
+    hash_algorithm: Option<HashAlgorithm>,
+    project_id: Option<String>,
+) -> Result<(), AppError> {
     log::info!("Writing file content: {}", path);
     
     let file_path = PathBuf::from(&path);
+    path_access::check(&state, &file_path, project_id.as_deref())?;
+    approvals::authorize(&app, Operation::Write { path: path.clone() }).await?;
+    
+    if let Some(expected) = expected_hash {
+        let current = digest_file(&file_path, hash_algorithm.unwrap_or_default())
+            .map_err(|e| AppError::Internal(format!("Failed to hash file: {}", e)))?;
+        if !current.hash.eq_ignore_ascii_case(expected.trim()) {
+            return Err(AppError::Conflict {
+                message: format!("{} changed since it was read", path),
+                current_hash: Some(current.hash),
+            });
+        }
+    }
+    
+    let bytes = match encoding.as_deref() {
+        Some(label) => {
+            let bom = bom.unwrap_or_else(|| label.to_ascii_lowercase().starts_with("utf-16"));
+            encode_text(&content, label, bom).map_err(write_error)?
+        }
+        None if bom == Some(true) => encode_text(&content, "utf-8", true).map_err(write_error)?,
+        None => content.into_bytes(),
+    };
+    let before = undo::snapshot(&file_path);
+    let backup = write_file(&state.db, &file_path, &bytes, WriteMode::Overwrite).map_err(write_error)?;
+    undo::record_change(&state.db, project_id.as_deref(), "write", &file_path, before, backup);
+    emit_file_changed(&app, Action::Updated, &path, project_id.as_deref());
+    
+    Ok(())
+}
+
+/// Appends `content` to `path`, creating it if needed. Other writers' appends are never lost,
+/// as each write goes to the end of the file as it is at that moment.
+#[tauri::command]
+#[timed]
+pub async fn append_file_content(app: AppHandle, state: State<'_, AppState>, path: String, content: String, project_id: Option<String>) -> Result<(), AppError> {
+    log::info!("Appending to file: {}", path);
+    
+    let file_path = PathBuf::from(&path);
+    path_access::check(&state, &file_path, project_id.as_deref())?;
+    approvals::authorize(&app, Operation::Write { path: path.clone() }).await?;
     
+    let before = undo::snapshot(&file_path);
+    write_file(&state.db, &file_path, content, WriteMode::Append)
+        .map_err(|e| typed_or("Failed to append to file", e))?;
+    undo::record_change(&state.db, project_id.as_deref(), "append", &file_path, before, None);
+    emit_file_changed(&app, Action::Updated, &path, project_id.as_deref());
+    Ok(())
+}
+
+/// Replaces lines `start_line` to `end_line` (1-based, inclusive) of a UTF-8 file with
+/// `replacement` and returns the file's new line count. The other lines are kept byte for byte,
+/// line endings included; the replacement is written as given. An empty replacement deletes the lines.
+#[tauri::command]
+#[timed]
+#[allow(clippy::too_many_arguments)]
+pub async fn write_file_range(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    start_line: usize,
+    end_line: usize,
+    replacement: String,
+    project_id: Option<String>,
+) -> Result<usize, AppError> {
+    log::info!("Replacing lines {}-{} of {}", start_line, end_line, path);
+    
+    let file_path = PathBuf::from(&path);
+    path_access::check(&state, &file_path, project_id.as_deref())?;
+    approvals::authorize(&app, Operation::Write { path: path.clone() }).await?;
+    if start_line == 0 || end_line < start_line {
+        return Err(AppError::Internal("Line range must start at 1 and not end before it starts".to_string()));
+    }
+    
+    let content = fs::read(&file_path)
+        .map_err(|e| AppError::Internal(format!("Failed to read file: {}", e)))?;
+    if looks_binary(&content) {
+        return Err(AppError::BinaryFile { size: content.len() as u64 });
+    }
+    if std::str::from_utf8(&content).is_err() {
+        return Err(AppError::Internal("Only UTF-8 files can be edited by line range".to_string()));
+    }
+    
+    let lines: Vec<&[u8]> = content.split_inclusive(|b| *b == b'\n').collect();
+    if end_line > lines.len() {
+        return Err(AppError::Internal(format!(
+            "Line range {}-{} is past the end of the file, which has {} lines",
+            start_line, end_line, lines.len()
+        )));
+    }
+    
+    let mut updated = Vec::with_capacity(content.len() + replacement.len());
+    for line in &lines[..start_line - 1] {
+        updated.extend_from_slice(line);
+    }
+    if !replacement.is_empty() {
+        updated.extend_from_slice(replacement.as_bytes());
+        // Ends the way the last replaced line did, so it doesn't run into the next one
+        let last = lines[end_line - 1];
+        if last.ends_with(b"\n") && !replacement.ends_with('\n') {
+            updated.extend_from_slice(if last.ends_with(b"\r\n") { b"\r\n" } else { b"\n" });
+        }
+    }
+    for line in &lines[end_line..] {
+        updated.extend_from_slice(line);
+    }
+    
+    let before = undo::snapshot(&file_path);
+    let backup = write_file(&state.db, &file_path, &updated, WriteMode::Overwrite).map_err(write_error)?;
+    undo::record_change(&state.db, project_id.as_deref(), "write_range", &file_path, before, backup);
+    emit_file_changed(&app, Action::Updated, &path, project_id.as_deref());
+    Ok(updated.split_inclusive(|b| *b == b'\n').count())
+}
+
+/// The saved earlier versions of `path`, newest (index 0) first.
+#[tauri::command]
+#[timed]
+pub async fn list_file_backups(state: State<'_, AppState>, path: String, project_id: Option<String>) -> Result<Vec<FileBackup>, AppError> {
+    let file_path = PathBuf::from(&path);
+    path_access::check(&state, &file_path, project_id.as_deref())?;
+    
+    file_backups::list(&file_path)
+        .map_err(|e| AppError::Internal(format!("Failed to list backups: {}", e)))
+}
+
+/// Puts version `version_index` of `path` back. The content it replaces is backed up in turn,
+/// so a restore can itself be undone.
+#[tauri::command]
+#[timed]
+pub async fn restore_file_backup(app: AppHandle, state: State<'_, AppState>, path: String, version_index: usize, project_id: Option<String>) -> Result<(), AppError> {
+    log::info!("Restoring backup {} of {}", version_index, path);
+    
+    let file_path = PathBuf::from(&path);
+    path_access::check(&state, &file_path, project_id.as_deref())?;
+    
+    let content = file_backups::read(&file_path, version_index)
+        .map_err(|e| AppError::Internal(format!("Failed to restore backup: {}", e)))?;
+    write_file(&state.db, &file_path, content, WriteMode::Overwrite)
+        .map_err(|e| typed_or("Failed to restore backup", e))?;
+    emit_file_changed(&app, Action::Updated, &path, project_id.as_deref());
+    Ok(())
+}
+
+#[tauri::command]
+#[timed]
+pub async fn get_file_backup_settings(state: State<'_, AppState>) -> Result<BackupSettings, String> {
+    Ok(file_backups::settings(&state.db))
+}
+
+#[tauri::command]
+#[timed]
+pub async fn set_file_backup_settings(state: State<'_, AppState>, settings: BackupSettings) -> Result<(), String> {
+    log::info!("Setting file backups: {:?}", settings);
+    
+    file_backups::save_settings(&state.db, &settings)
+        .map_err(|e| format!("Failed to save file backup settings: {}", e))
+}
+
+/// Writes `content` to `path`, creating parent directories as needed. Overwrites back up the
+/// old content first and replace the file atomically, returning the backup's version. Fails
+/// with `QuotaExceeded` past an agent workspace's quota.
+pub(crate) fn write_file(db: &Database, path: &Path, content: impl AsRef<[u8]>, mode: WriteMode) -> Result<Option<i64>> {
     // Create parent directories if they don't exist
-    if let Some(parent) = file_path.parent() {
+    if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create parent directories: {}", e))?;
+            .with_context(|| format!("Failed to create parent directories of {}", path.display()))?;
     }
     
-    fs::write(&file_path, content)
-        .map_err(|e| format!("Failed to write file: {}", e))?;
+    let new_size = match mode {
+        WriteMode::Append => fs::metadata(path).map(|m| m.len()).unwrap_or(0) + content.as_ref().len() as u64,
+        _ => content.as_ref().len() as u64,
+    };
+    check_quota(db, path, new_size)?;
+    
+    let mut options = fs::OpenOptions::new();
+    match mode {
+        WriteMode::Create => options.write(true).create_new(true),
+        WriteMode::Overwrite => {
+            let backup = file_backups::save(db, path)
+                .with_context(|| format!("Failed to back up {}", path.display()))?;
+            replace_file(path, content.as_ref())?;
+            path_changed(path);
+            return Ok(backup);
+        }
+        WriteMode::Append => options.append(true).create(true),
+    };
+    let mut file = options.open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    file.write_all(content.as_ref())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    path_changed(path);
+    
+    Ok(None)
+}
+
+fn write_error(e: anyhow::Error) -> AppError {
+    typed_or("Failed to write file", e)
+}
+
+/// Drops what is cached about the projects containing `path` after the app changed it.
+pub(crate) fn path_changed(path: &Path) {
+    git::invalidate_status(path);
+    project_stats::invalidate(path);
+}
+
+/// Writes to a temporary file beside `path`, flushes it to disk and renames it over `path`, so
+/// a crash leaves either the old content or the new, never a mix. A symlink is written through.
+fn replace_file(path: &Path, content: &[u8]) -> Result<()> {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let name = path.file_name()
+        .ok_or_else(|| anyhow!("Invalid file path: {}", path.display()))?;
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let temp = dir.join(format!(".{}.{}.tmp", name.to_string_lossy(), uuid::Uuid::new_v4().simple()));
+    
+    let written = write_temp(&temp, content, &path).and_then(|_| fs::rename(&temp, &path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written.with_context(|| format!("Failed to write {}", path.display()))?;
     
+    // The rename itself is only durable once the directory is flushed
+    #[cfg(unix)]
+    if let Ok(dir) = fs::File::open(dir) {
+        let _ = dir.sync_all();
+    }
     Ok(())
 }
 
+// Takes over the permissions of the file it will replace
+fn write_temp(temp: &Path, content: &[u8], replacing: &Path) -> std::io::Result<()> {
+    let mut file = fs::OpenOptions::new().write(true).create_new(true).open(temp)?;
+    if let Ok(metadata) = fs::metadata(replacing) {
+        file.set_permissions(metadata.permissions())?;
+    }
+    file.write_all(content)?;
+    file.sync_all()
+}
+
 #[tauri::command]
-pub async fn create_directory(path: String) -> Result<(), String> {
+#[timed]
+pub async fn create_directory(app: AppHandle, path: String) -> Result<(), String> {
     log::info!("Creating directory: {}", path);
     
     let dir_path = PathBuf::from(&path);
     
     fs::create_dir_all(&dir_path)
         .map_err(|e| format!("Failed to create directory: {}", e))?;
+    emit_file_changed(&app, Action::Created, &path, None);
     
     Ok(())
 }
 
+/// Moves a file or directory to the OS trash, or removes it for good with `permanent`. Where
+/// there is no trash this fails with `trash_unavailable` rather than quietly deleting; the UI
+/// may then ask and retry with `permanent`. Every deletion is recorded in `file_operations`.
 #[tauri::command]
-pub async fn delete_file_or_directory(path: String) -> Result<(), String> {
+#[timed]
+pub async fn delete_file_or_directory(app: AppHandle, state: State<'_, AppState>, path: String, project_id: Option<String>, permanent: Option<bool>) -> Result<(), AppError> {
     log::info!("Deleting file or directory: {}", path);
     
     let target_path = PathBuf::from(&path);
     
-    if !target_path.exists() {
-        return Err("Path does not exist".to_string());
+    if fs::symlink_metadata(&target_path).is_err() {
+        return Err(AppError::Internal("Path does not exist".to_string()));
     }
+    path_access::check(&state, &target_path, project_id.as_deref())?;
+    approvals::authorize(&app, Operation::Delete { path: path.clone() }).await?;
     
-    if target_path.is_dir() {
-        fs::remove_dir_all(&target_path)
-            .map_err(|e| format!("Failed to delete directory: {}", e))?;
+    let permanent = permanent.unwrap_or(false);
+    if permanent {
+        remove_path(&target_path)
+            .map_err(|e| AppError::Internal(format!("Failed to delete: {}", e)))?;
     } else {
-        fs::remove_file(&target_path)
-            .map_err(|e| format!("Failed to delete file: {}", e))?;
+        move_to_trash(&target_path)?;
+        undo::record_trash(&state.db, project_id.as_deref(), &target_path);
+    }
+    path_changed(&target_path);
+    emit_file_changed(&app, Action::Deleted, &path, project_id.as_deref());
+    
+    let operation = database::DbFileOperation {
+        id: uuid::Uuid::new_v4().to_string(),
+        operation: "delete".to_string(),
+        path,
+        project_id,
+        permanent,
+        created_at: Utc::now(),
+    };
+    if let Err(e) = state.db.record_file_operation(&operation) {
+        log::warn!("Failed to record deletion of {}: {}", operation.path, e);
     }
     
     Ok(())
 }
 
+/// Deletions made through `delete_file_or_directory`, newest first.
 #[tauri::command]
-pub async fn execute_command(command: String, args: Vec<String>, working_dir: Option<String>) -> Result<ProcessInfo, String> {
-    log::info!("Executing command: {} {:?}", command, args);
+#[timed]
+pub async fn list_recent_deletions(state: State<'_, AppState>, limit: Option<i64>) -> Result<Vec<database::DbFileOperation>, String> {
+    state.db.get_recent_file_operations("delete", limit.unwrap_or(DEFAULT_DELETION_LIMIT))
+        .map_err(|e| format!("Failed to list deletions: {}", e))
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+fn move_to_trash(path: &Path) -> Result<(), AppError> {
+    trash::delete(path).map_err(|e| AppError::TrashUnavailable(e.to_string()))
+}
+
+#[cfg(any(target_os = "android", target_os = "ios"))]
+fn move_to_trash(_path: &Path) -> Result<(), AppError> {
+    Err(AppError::TrashUnavailable("this platform has no trash".to_string()))
+}
+
+/// Renames or moves `from` to `to`, copying and then deleting when they are on different
+/// volumes. An existing `to` is only replaced with `overwrite`.
+#[tauri::command]
+#[timed]
+pub async fn rename_path(app: AppHandle, state: State<'_, AppState>, from: String, to: String, overwrite: Option<bool>, project_id: Option<String>) -> Result<FileItem, AppError> {
+    log::info!("Renaming {} to {}", from, to);
+    
+    let (from, to) = (PathBuf::from(from), PathBuf::from(to));
+    path_access::check(&state, &from, project_id.as_deref())?;
+    path_access::check(&state, &to, project_id.as_deref())?;
+    approvals::authorize(&app, Operation::Write { path: to.to_string_lossy().to_string() }).await?;
+    let rename_error = |e: anyhow::Error| AppError::Internal(format!("Failed to rename: {}", e));
+    move_path(&from, &to, overwrite.unwrap_or(false)).map_err(rename_error)?;
+    undo::record_rename(&state.db, project_id.as_deref(), &from, &to);
+    path_changed(&from);
+    path_changed(&to);
+    let item = path_item(&to).map_err(|e| rename_error(e.into()))?;
+    emit_file_changed(&app, Action::Deleted, &from.to_string_lossy(), project_id.as_deref());
+    emit_moved_file(&app, &from, &item, project_id.as_deref());
+    Ok(item)
+}
+
+/// Copies a file, or a directory with everything in it, to `to`. An existing `to` is only
+/// replaced with `overwrite`, and a directory can't be copied into itself.
+#[tauri::command]
+#[timed]
+pub async fn copy_path(app: AppHandle, state: State<'_, AppState>, from: String, to: String, overwrite: bool, project_id: Option<String>) -> Result<FileItem, AppError> {
+    log::info!("Copying {} to {}", from, to);
     
-    let mut cmd = Command::new(&command);
-    cmd.args(&args);
+    let (from, to) = (PathBuf::from(from), PathBuf::from(to));
+    path_access::check(&state, &from, project_id.as_deref())?;
+    path_access::check(&state, &to, project_id.as_deref())?;
+    approvals::authorize(&app, Operation::Write { path: to.to_string_lossy().to_string() }).await?;
+    let source = from.clone();
+    let item = tokio::task::spawn_blocking(move || {
+        check_destination(&from, &to, overwrite)?;
+        copy_recursive(&from, &to)?;
+        path_changed(&to);
+        Ok(path_item(&to)?)
+    })
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to copy: {}", e)))?
+        .map_err(|e: anyhow::Error| AppError::Internal(format!("Failed to copy: {}", e)))?;
+    emit_moved_file(&app, &source, &item, project_id.as_deref());
+    Ok(item)
+}
+
+pub(crate) fn emit_file_changed(app: &AppHandle, action: Action, path: &str, project_id: Option<&str>) {
+    DataChanged::new(Entity::File, action, path).in_project(project_id).emit(app);
+}
+
+// The new path of a rename or copy, with where it came from
+fn emit_moved_file(app: &AppHandle, from: &Path, item: &FileItem, project_id: Option<&str>) {
+    DataChanged::new(Entity::File, Action::Created, item.path.clone())
+        .in_project(project_id)
+        .with_data(&serde_json::json!({ "from": from }))
+        .emit(app);
+}
+
+/// Called after the user confirmed access to a path a file command was refused, so the
+/// command can be retried. `always` keeps the grant across restarts.
+#[tauri::command]
+#[timed]
+pub async fn authorize_path(state: State<'_, AppState>, path: String, scope: PathScope) -> Result<(), AppError> {
+    log::info!("Authorizing {:?} access to {}", scope, path);
     
-    if let Some(dir) = working_dir {
-        cmd.current_dir(dir);
+    path_access::grant(&state, Path::new(&path), scope)
+}
+
+pub(crate) fn move_path(from: &Path, to: &Path, overwrite: bool) -> Result<()> {
+    check_destination(from, to, overwrite)?;
+    match fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            copy_recursive(from, to)?;
+            remove_path(from)
+        }
+        Err(e) => Err(e.into()),
     }
+}
+
+/// Fails unless `from` exists and `to` is free, or may be replaced and isn't `from` or a folder
+/// holding it. An existing `to` that may be replaced is deleted here. Paths are compared
+/// canonicalized so links and `..` can't sneak a directory into itself.
+fn check_destination(from: &Path, to: &Path, overwrite: bool) -> Result<()> {
+    let source = fs::canonicalize(from)
+        .with_context(|| format!("{} does not exist", from.display()))?;
+    let name = to.file_name().ok_or_else(|| anyhow!("Invalid destination: {}", to.display()))?;
+    let parent = match to.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let destination = fs::canonicalize(parent)
+        .with_context(|| format!("{} does not exist", parent.display()))?
+        .join(name);
     
-    let output = cmd.output()
-        .map_err(|e| format!("Failed to execute command: {}", e))?;
+    if destination == source {
+        return Err(anyhow!("Source and destination are the same"));
+    }
+    if source.is_dir() && destination.starts_with(&source) {
+        return Err(anyhow!("Cannot copy or move a directory into itself"));
+    }
+    if fs::symlink_metadata(&destination).is_ok() {
+        if !overwrite {
+            return Err(anyhow!("{} already exists", to.display()));
+        }
+        if source.starts_with(&destination) {
+            return Err(anyhow!("Cannot replace a directory that contains the source"));
+        }
+        remove_path(&destination)?;
+    }
+    Ok(())
+}
+
+// Symlinks are copied as links on Unix rather than followed
+fn copy_recursive(from: &Path, to: &Path) -> Result<()> {
+    let metadata = fs::symlink_metadata(from)?;
+    if metadata.is_dir() {
+        fs::create_dir(to).with_context(|| format!("Failed to create {}", to.display()))?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        return Ok(());
+    }
+    #[cfg(unix)]
+    if metadata.file_type().is_symlink() {
+        std::os::unix::fs::symlink(fs::read_link(from)?, to)
+            .with_context(|| format!("Failed to create {}", to.display()))?;
+        return Ok(());
+    }
+    fs::copy(from, to).with_context(|| format!("Failed to copy {}", from.display()))?;
+    Ok(())
+}
+
+fn remove_path(path: &Path) -> Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)?;
+    } else {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Runs a program with an argument vector; nothing goes through a shell, so `;`, `|`, `$(...)`,
+/// globs and quotes in `args` reach the program as literal text. `env` is applied on top of
+/// the inherited environment and the app's own variables, or of an empty one with `clear_env`.
+/// `stdin` is written to the program and then closed. After `timeout_ms` the program is killed
+/// and reported as `timeout`.
+#[tauri::command]
+#[timed]
+#[allow(clippy::too_many_arguments)]
+pub async fn execute_command(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    command: String,
+    args: Vec<String>,
+    working_dir: Option<String>,
+    timeout_ms: Option<u64>,
+    stdin: Option<String>,
+    env: Option<HashMap<String, String>>,
+    clear_env: Option<bool>,
+) -> Result<ProcessInfo, AppError> {
+    log::info!("Executing command: {} {:?}", command, args);
     
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    let env = env.unwrap_or_default();
+    for name in env.keys() {
+        validate_env_name(name).map_err(AppError::Validation)?;
+    }
+    approvals::authorize(&app, Operation::ExecuteCommand { command: command.clone(), args: args.clone() }).await?;
     
-    let mut output_lines = Vec::new();
-    if !stdout.is_empty() {
-        output_lines.extend(stdout.lines().map(|s| s.to_string()));
+    let mut cmd = program_command(&command, &args, stdin.is_some());
+    if clear_env.unwrap_or(false) {
+        cmd.env_clear();
+    } else {
+        cmd.envs(app_env(&state));
     }
-    if !stderr.is_empty() {
-        output_lines.extend(stderr.lines().map(|s| format!("ERROR: {}", s)));
+    cmd.envs(&env);
+    
+    if let Some(dir) = working_dir {
+        cmd.current_dir(dir);
     }
     
-    let status = if output.status.success() {
-        "completed".to_string()
-    } else {
-        "failed".to_string()
-    };
+    let started_at = Utc::now();
+    let (status, exit_code, pid, output_lines) = run_to_completion(cmd, stdin, timeout_ms).await
+        .map_err(|e| AppError::Internal(format!("Failed to execute command: {}", e)))?;
     
     let process_info = ProcessInfo {
         id: uuid::Uuid::new_v4().to_string(),
         name: command.clone(),
         command: format!("{} {}", command, args.join(" ")),
-        status,
-        pid: None, // Not available for completed processes
-        started_at: Utc::now(),
+        status: status.to_string(),
+        pid,
+        exit_code,
+        started_at,
+        ended_at: Some(Utc::now()),
+        uptime_ms: (Utc::now() - started_at).num_milliseconds(),
         output: output_lines,
     };
     
     Ok(process_info)
 }
 
+// The program with `args` as its argument vector, output captured
+fn program_command(command: &str, args: &[String], with_stdin: bool) -> tokio::process::Command {
+    let mut cmd = tokio::process::Command::new(command);
+    cmd.args(args)
+        .stdin(if with_stdin { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    cmd
+}
+
+/// Returns the status (`completed`, `failed` or `timeout`), exit code, pid and output lines,
+/// stdout first and then stderr prefixed with `ERROR: `.
+async fn run_to_completion(mut cmd: tokio::process::Command, stdin: Option<String>, timeout_ms: Option<u64>) -> Result<(&'static str, Option<i32>, Option<u32>, Vec<String>)> {
+    let mut child = cmd.spawn()?;
+    let pid = child.id();
+    
+    // Output is read while stdin is written, so a program filling its pipes can't stall either side
+    let mut stdout = child.stdout.take().ok_or_else(|| anyhow!("stdout not captured"))?;
+    let mut stderr = child.stderr.take().ok_or_else(|| anyhow!("stderr not captured"))?;
+    let stdout = tokio::spawn(async move {
+        let mut buffer = Vec::new();
+        let _ = tokio::io::AsyncReadExt::read_to_end(&mut stdout, &mut buffer).await;
+        buffer
+    });
+    let stderr = tokio::spawn(async move {
+        let mut buffer = Vec::new();
+        let _ = tokio::io::AsyncReadExt::read_to_end(&mut stderr, &mut buffer).await;
+        buffer
+    });
+    if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        tokio::spawn(async move {
+            // A program that exits without reading its input closes the pipe; that's not an error
+            let _ = tokio::io::AsyncWriteExt::write_all(&mut pipe, input.as_bytes()).await;
+        });
+    }
+    
+    let waited = match timeout_ms {
+        Some(ms) => tokio::time::timeout(tokio::time::Duration::from_millis(ms), child.wait()).await.ok(),
+        None => Some(child.wait().await),
+    };
+    let (status, exit_code) = match waited {
+        Some(exited) => {
+            let exited = exited?;
+            (if exited.success() { "completed" } else { "failed" }, exited.code())
+        }
+        None => {
+            let _ = child.kill().await;
+            ("timeout", None)
+        }
+    };
+    
+    // A daemonized grandchild can hold the pipes open, so don't wait for EOF forever
+    let stdout = tokio::time::timeout(OUTPUT_DRAIN_TIMEOUT, stdout).await.ok().and_then(|r| r.ok()).unwrap_or_default();
+    let stderr = tokio::time::timeout(OUTPUT_DRAIN_TIMEOUT, stderr).await.ok().and_then(|r| r.ok()).unwrap_or_default();
+    let mut output_lines: Vec<String> = String::from_utf8_lossy(&stdout).lines().map(str::to_string).collect();
+    output_lines.extend(String::from_utf8_lossy(&stderr).lines().map(|s| format!("ERROR: {}", s)));
+    
+    Ok((status, exit_code, pid, output_lines))
+}
+
+/// Starts the command and returns right away. Output arrives line by line as `process:stdout` and
+/// `process:stderr` events and the end as `process:exit`, all tagged with the returned process id.
+#[tauri::command]
+#[timed]
+pub async fn execute_command_streaming(app: AppHandle, command: String, args: Vec<String>, working_dir: Option<String>) -> Result<ProcessInfo, AppError> {
+    log::info!("Executing command with streaming output: {} {:?}", command, args);
+    
+    approvals::authorize(&app, Operation::ExecuteCommand { command: command.clone(), args: args.clone() }).await?;
+    let info = spawn_managed_process(app, &command, &args, working_dir.as_deref())
+        .map_err(|e| AppError::Internal(format!("Failed to execute command: {}", e)))?;
+    
+    Ok(info)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn kill_process(state: State<'_, AppState>, process_id: String) -> Result<(), String> {
+    log::info!("Killing process: {}", process_id);
+    
+    let processes = state.managed_processes.0.lock().unwrap();
+    let process = processes.get(&process_id)
+        .ok_or_else(|| format!("Process not found: {}", process_id))?;
+    if process.info.status != "running" {
+        return Err(format!("Process {} is not running", process_id));
+    }
+    process.kill.cancel();
+    
+    Ok(())
+}
+
+#[tauri::command]
+#[timed]
+pub async fn get_process_status(state: State<'_, AppState>, process_id: String) -> Result<ProcessInfo, String> {
+    state.managed_processes.0.lock().unwrap()
+        .get(&process_id)
+        .map(ManagedProcess::snapshot)
+        .ok_or_else(|| format!("Process not found: {}", process_id))
+}
+
+/// Every tracked process, newest first; output is left out, see `get_process_output`.
+#[tauri::command]
+#[timed]
+pub async fn list_processes(state: State<'_, AppState>) -> Result<Vec<ProcessInfo>, String> {
+    let mut processes: Vec<ProcessInfo> = state.managed_processes.0.lock().unwrap()
+        .values()
+        .map(ManagedProcess::snapshot)
+        .collect();
+    processes.sort_by_key(|process| std::cmp::Reverse(process.started_at));
+    
+    Ok(processes)
+}
+
+/// Buffered output from `from_line` on (by default the oldest line still buffered).
+#[tauri::command]
+#[timed]
+pub async fn get_process_output(state: State<'_, AppState>, process_id: String, from_line: Option<usize>, max_lines: Option<usize>) -> Result<ProcessOutput, String> {
+    let processes = state.managed_processes.0.lock().unwrap();
+    let process = processes.get(&process_id)
+        .ok_or_else(|| format!("Process not found: {}", process_id))?;
+    
+    let from_line = from_line.unwrap_or(process.dropped_lines).max(process.dropped_lines);
+    let lines: Vec<String> = process.output.iter()
+        .skip(from_line - process.dropped_lines)
+        .take(max_lines.unwrap_or(DEFAULT_OUTPUT_PAGE))
+        .cloned()
+        .collect();
+    
+    Ok(ProcessOutput {
+        process_id,
+        from_line,
+        next_line: from_line + lines.len(),
+        lines,
+        first_buffered_line: process.dropped_lines,
+    })
+}
+
+/// Forgets a finished process and its output.
+#[tauri::command]
+#[timed]
+pub async fn clear_process(state: State<'_, AppState>, process_id: String) -> Result<(), String> {
+    log::info!("Clearing process: {}", process_id);
+    
+    let mut processes = state.managed_processes.0.lock().unwrap();
+    let process = processes.get(&process_id)
+        .ok_or_else(|| format!("Process not found: {}", process_id))?;
+    if process.info.status == "running" {
+        return Err(format!("Process {} is still running", process_id));
+    }
+    processes.remove(&process_id);
+    
+    Ok(())
+}
+
+fn spawn_managed_process(app: AppHandle, command: &str, args: &[String], working_dir: Option<&str>) -> Result<ProcessInfo> {
+    let mut cmd = tokio::process::Command::new(command);
+    cmd.args(args)
+        .envs(app_env(&app.state::<AppState>()))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(dir) = working_dir {
+        cmd.current_dir(dir);
+    }
+    
+    let mut child = cmd.spawn()?;
+    let stdout = child.stdout.take().ok_or_else(|| anyhow!("stdout not captured"))?;
+    let stderr = child.stderr.take().ok_or_else(|| anyhow!("stderr not captured"))?;
+    
+    let info = ProcessInfo {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: command.to_string(),
+        command: format!("{} {}", command, args.join(" ")),
+        status: "running".to_string(),
+        pid: child.id(),
+        exit_code: None,
+        started_at: Utc::now(),
+        ended_at: None,
+        uptime_ms: 0,
+        output: Vec::new(),
+    };
+    let kill = CancellationToken::new();
+    app.state::<AppState>().managed_processes.0.lock().unwrap().insert(info.id.clone(), ManagedProcess {
+        info: info.clone(),
+        kill: kill.clone(),
+        output: VecDeque::new(),
+        dropped_lines: 0,
+    });
+    
+    let process_id = info.id.clone();
+    let readers = [
+        tokio::spawn(forward_lines(app.clone(), process_id.clone(), stdout, PROCESS_STDOUT_EVENT)),
+        tokio::spawn(forward_lines(app.clone(), process_id.clone(), stderr, PROCESS_STDERR_EVENT)),
+    ];
+    tokio::spawn(async move {
+        let (exited, killed) = tokio::select! {
+            exited = child.wait() => (exited, false),
+            _ = kill.cancelled() => {
+                let _ = child.start_kill();
+                (child.wait().await, true)
+            }
+        };
+        // A daemonized grandchild can hold the pipes open, so don't wait for EOF forever
+        let _ = tokio::time::timeout(OUTPUT_DRAIN_TIMEOUT, join_readers(readers)).await;
+        
+        let exit_code = exited.as_ref().ok().and_then(|status| status.code());
+        let status = match &exited {
+            _ if killed => "stopped",
+            Ok(status) if status.success() => "completed",
+            _ => "failed",
+        };
+        if let Some(process) = app.state::<AppState>().managed_processes.0.lock().unwrap().get_mut(&process_id) {
+            process.info.status = status.to_string();
+            process.info.exit_code = exit_code;
+            process.info.ended_at = Some(Utc::now());
+        }
+        emit_process_event(&app, PROCESS_EXIT_EVENT, serde_json::json!({
+            "process_id": process_id,
+            "status": status,
+            "exit_code": exit_code,
+            "error": exited.err().map(|e| e.to_string()),
+        }));
+    });
+    
+    Ok(info)
+}
+
+async fn join_readers(readers: [tokio::task::JoinHandle<()>; 2]) {
+    for reader in readers {
+        let _ = reader.await;
+    }
+}
+
+// Lines that aren't valid UTF-8 are passed on lossily rather than ending the stream
+async fn forward_lines(app: AppHandle, process_id: String, reader: impl AsyncRead + Unpin, event: &'static str) {
+    let mut reader = BufReader::new(reader);
+    let mut buffer = Vec::new();
+    loop {
+        buffer.clear();
+        match reader.read_until(b'\n', &mut buffer).await {
+            Ok(0) => break,
+            Ok(_) => {
+                let line = String::from_utf8_lossy(&buffer);
+                let line = line.trim_end_matches(['\n', '\r']);
+                buffer_line(&app, &process_id, event, line);
+                emit_process_event(&app, event, serde_json::json!({ "process_id": process_id, "line": line }));
+            }
+            Err(e) => {
+                log::warn!("Failed to read output of process {}: {}", process_id, e);
+                break;
+            }
+        }
+    }
+}
+
+fn buffer_line(app: &AppHandle, process_id: &str, event: &str, line: &str) {
+    let state = app.state::<AppState>();
+    let mut processes = state.managed_processes.0.lock().unwrap();
+    let Some(process) = processes.get_mut(process_id) else {
+        return;
+    };
+    let line = if event == PROCESS_STDERR_EVENT { format!("ERROR: {}", line) } else { line.to_string() };
+    process.output.push_back(line);
+    if process.output.len() > MAX_BUFFERED_LINES {
+        process.output.pop_front();
+        process.dropped_lines += 1;
+    }
+}
+
+fn emit_process_event(app: &AppHandle, event: &str, payload: serde_json::Value) {
+    if let Err(e) = app.emit(event, payload) {
+        log::warn!("Failed to emit {}: {}", event, e);
+    }
+}
+
+/// Shows `path` selected in Finder, Explorer or the desktop's file manager. File managers on
+/// Linux that don't implement the FileManager1 D-Bus interface get the parent folder opened.
+#[tauri::command]
+#[timed]
+pub async fn reveal_in_file_manager(state: State<'_, AppState>, path: String, project_id: Option<String>) -> Result<(), AppError> {
+    log::info!("Revealing {} in the file manager", path);
+    
+    let target = PathBuf::from(&path);
+    if fs::symlink_metadata(&target).is_err() {
+        return Err(AppError::Internal("Path does not exist".to_string()));
+    }
+    path_access::check(&state, &target, project_id.as_deref())?;
+    
+    reveal(&target).map_err(|e| AppError::Internal(format!("Failed to reveal {}: {}", path, e)))
+}
+
+/// Opens `path` in the application the OS associates with it.
+#[tauri::command]
+#[timed]
+pub async fn open_with_default_app(state: State<'_, AppState>, path: String, project_id: Option<String>) -> Result<(), AppError> {
+    log::info!("Opening {} with its default application", path);
+    
+    let target = PathBuf::from(&path);
+    if fs::symlink_metadata(&target).is_err() {
+        return Err(AppError::Internal("Path does not exist".to_string()));
+    }
+    path_access::check(&state, &target, project_id.as_deref())?;
+    
+    // Fails when no application is registered for the file type
+    tauri_plugin_opener::open_path(&target, None::<&str>)
+        .map_err(|e| AppError::Internal(format!("No application could open {}: {}", path, e)))
+}
+
+/// The app's own log, newest last: the last `tail_lines` entries (200 by default) at
+/// `level_filter` or above (`info` by default). An entry can span several lines.
+#[tauri::command]
+#[timed]
+pub async fn get_app_logs(state: State<'_, AppState>, level_filter: Option<String>, tail_lines: Option<usize>) -> Result<Vec<String>, AppError> {
+    let level = match level_filter.as_deref() {
+        Some(level) => log::LevelFilter::from_str(level)
+            .map_err(|_| AppError::Validation(format!("Unknown log level: {}", level)))?,
+        None => log::LevelFilter::Info,
+    };
+    let count = tail_lines.unwrap_or(DEFAULT_APP_LOG_LINES).min(MAX_APP_LOG_LINES);
+
+    tokio::task::spawn_blocking(move || logging::tail(level, count))
+        .await
+        .map_err(|e| AppError::Internal(format!("Reading the log failed: {}", e)))?
+        .map_err(AppError::from)
+}
+
+/// Opens the folder holding the app's log files in the file manager.
+#[tauri::command]
+#[timed]
+pub async fn open_log_directory(state: State<'_, AppState>) -> Result<(), AppError> {
+    let dir = logging::log_dir()?;
+    tauri_plugin_opener::open_path(dir, None::<&str>)
+        .map_err(|e| AppError::Internal(format!("Could not open {}: {}", dir.display(), e)))
+}
+
+#[cfg(target_os = "macos")]
+fn reveal(path: &Path) -> Result<()> {
+    run_opener(Command::new("open").arg("-R").arg(path))
+}
+
+#[cfg(windows)]
+fn reveal(path: &Path) -> Result<()> {
+    use std::os::windows::process::CommandExt;
+    
+    // Explorer only understands the path quoted inside the switch, and exits with 1 even
+    // when it worked, so its status is not checked
+    Command::new("explorer")
+        .raw_arg(format!("/select,\"{}\"", path.display()))
+        .spawn()
+        .context("Failed to start Explorer")?;
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn reveal(path: &Path) -> Result<()> {
+    if let Ok(uri) = reqwest::Url::from_file_path(path) {
+        let shown = Command::new("dbus-send")
+            .args([
+                "--session",
+                "--print-reply",
+                "--reply-timeout=2000",
+                "--dest=org.freedesktop.FileManager1",
+                "--type=method_call",
+                "/org/freedesktop/FileManager1",
+                "org.freedesktop.FileManager1.ShowItems",
+            ])
+            .arg(format!("array:string:{}", uri))
+            .arg("string:")
+            .output();
+        if shown.is_ok_and(|output| output.status.success()) {
+            return Ok(());
+        }
+    }
+    
+    let folder = if path.is_dir() { path } else { path.parent().unwrap_or(path) };
+    run_opener(Command::new("xdg-open").arg(folder))
+}
+
+#[cfg(unix)]
+fn run_opener(command: &mut Command) -> Result<()> {
+    let output = command.output().context("Failed to start the file manager")?;
+    if !output.status.success() {
+        return Err(anyhow!("{}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
+
 #[tauri::command]
-pub async fn get_system_info() -> Result<serde_json::Value, String> {
+#[timed]
+pub async fn get_system_info(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
     log::info!("Getting system info");
     
     let system_info = serde_json::json!({
@@ -231,62 +1540,170 @@ pub async fn get_system_info() -> Result<serde_json::Value, String> {
     Ok(system_info)
 }
 
+/// What the about screen shows: the build, what it runs on and where the app keeps its data.
+/// A directory or version that can't be resolved is `None`.
+#[derive(Debug, Clone, Serialize)]
+pub struct AppInfo {
+    pub name: String,
+    pub version: String,
+    pub git_commit: Option<String>,
+    pub build_timestamp: Option<DateTime<Utc>>,
+    pub debug_build: bool,
+    pub tauri_version: String,
+    pub webview_version: Option<String>,
+    pub os: String,
+    pub os_version: Option<String>,
+    pub arch: String,
+    pub app_data_dir: Option<PathBuf>,
+    pub app_config_dir: Option<PathBuf>,
+    pub log_dir: Option<PathBuf>,
+    pub database_path: Option<PathBuf>,
+    pub schema_version: Option<i64>,
+}
+
+#[tauri::command]
+#[timed]
+pub async fn get_app_info(app: AppHandle, state: State<'_, AppState>) -> Result<AppInfo, AppError> {
+    Ok(app_info(&app, &state.db))
+}
+
+pub(crate) fn app_info(app: &AppHandle, db: &Database) -> AppInfo {
+    let package = app.package_info();
+    let paths = app.path();
+    AppInfo {
+        name: package.name.clone(),
+        version: package.version.to_string(),
+        git_commit: Some(env!("CLAUDER_GIT_COMMIT")).filter(|commit| !commit.is_empty()).map(str::to_string),
+        build_timestamp: env!("CLAUDER_BUILD_TIMESTAMP").parse().ok().and_then(|secs| DateTime::from_timestamp(secs, 0)),
+        debug_build: cfg!(debug_assertions),
+        tauri_version: tauri::VERSION.to_string(),
+        webview_version: tauri::webview_version().ok(),
+        os: std::env::consts::OS.to_string(),
+        os_version: sysinfo::System::long_os_version(),
+        arch: std::env::consts::ARCH.to_string(),
+        app_data_dir: paths.app_data_dir().ok(),
+        app_config_dir: paths.app_config_dir().ok(),
+        log_dir: logging::log_dir().ok().map(Path::to_path_buf),
+        database_path: database_path(app).ok(),
+        schema_version: db.schema_version().ok(),
+    }
+}
+
+/// Whether a CLI tool is installed, where, and the first line of its `--version` output.
 #[tauri::command]
-pub async fn check_tool_availability(tool_name: String) -> Result<bool, String> {
+#[timed]
+pub async fn check_tool_availability(state: State<'_, AppState>, tool_name: String) -> Result<ToolAvailability, String> {
     log::info!("Checking tool availability: {}", tool_name);
     
-    let output = Command::new("which")
-        .arg(&tool_name)
-        .output();
-    
-    match output {
-        Ok(output) => Ok(output.status.success()),
-        Err(_) => {
-            // Try with 'where' on Windows
-            let output = Command::new("where")
-                .arg(&tool_name)
-                .output();
-            
-            match output {
-                Ok(output) => Ok(output.status.success()),
-                Err(_) => Ok(false),
-            }
-        }
-    }
+    Ok(tool_availability(tool_name).await)
 }
 
+/// `check_tool_availability` for several tools at once, checked concurrently, in the order given.
 #[tauri::command]
-pub async fn get_environment_variables() -> Result<serde_json::Value, String> {
-    log::info!("Getting environment variables");
+#[timed]
+pub async fn check_tools_availability(state: State<'_, AppState>, names: Vec<String>) -> Result<Vec<ToolAvailability>, String> {
+    log::info!("Checking availability of {} tools", names.len());
     
-    let mut env_vars = serde_json::Map::new();
+    let checks: Vec<_> = names.into_iter()
+        .map(|name| tokio::spawn(tool_availability(name)))
+        .collect();
+    let mut results = Vec::with_capacity(checks.len());
+    for check in checks {
+        results.push(check.await.map_err(|e| format!("Failed to check tool: {}", e))?);
+    }
     
-    // Only include relevant environment variables for AI tools
-    let relevant_vars = [
-        "ANTHROPIC_API_KEY",
-        "OPENAI_API_KEY", 
-        "GOOGLE_API_KEY",
-        "PATH",
-        "HOME",
-        "USER",
-        "SHELL",
-    ];
+    Ok(results)
+}
+
+async fn tool_availability(name: String) -> ToolAvailability {
+    let path = resolve_binary(&name);
+    let version = match &path {
+        Some(path) => binary_version(path).await,
+        None => None,
+    };
+    ToolAvailability {
+        name,
+        found: path.is_some(),
+        path: path.map(|p| p.to_string_lossy().to_string()),
+        version,
+    }
+}
+
+/// Absolute path of a binary, from PATH or, failing that, the places installers commonly put
+/// it. Apps started from the macOS dock get a minimal PATH without most of those.
+pub(crate) fn resolve_binary(tool_name: &str) -> Option<PathBuf> {
+    if let Ok(path) = which::which(tool_name) {
+        return Some(path);
+    }
     
-    for var in relevant_vars.iter() {
-        if let Ok(value) = std::env::var(var) {
-            // Mask sensitive values
-            let masked_value = if var.contains("API_KEY") {
-                if value.len() > 8 {
-                    format!("{}...{}", &value[..4], &value[value.len()-4..])
-                } else {
-                    "***".to_string()
-                }
-            } else {
-                value
-            };
-            env_vars.insert(var.to_string(), serde_json::Value::String(masked_value));
+    let dirs = std::env::join_paths(extra_bin_dirs(tool_name)).ok()?;
+    let cwd = std::env::current_dir().ok()?;
+    which::which_in(tool_name, Some(dirs), cwd).ok()
+}
+
+fn extra_bin_dirs(tool_name: &str) -> Vec<PathBuf> {
+    let home = std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from);
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    
+    if let Some(home) = &home {
+        // Claude Code's own installer
+        if tool_name == "claude" {
+            dirs.push(home.join(".claude").join("local"));
+        }
+        for dir in [".local/bin", ".cargo/bin", ".npm-global/bin", ".volta/bin", ".bun/bin", ".deno/bin"] {
+            dirs.push(home.join(dir));
         }
+        // Every Node version nvm manages, since npm installs global tools per version
+        if let Ok(versions) = fs::read_dir(home.join(".nvm").join("versions").join("node")) {
+            dirs.extend(versions.flatten().map(|v| v.path().join("bin")));
+        }
+    }
+    if let Some(prefix) = std::env::var_os("NPM_CONFIG_PREFIX") {
+        dirs.push(PathBuf::from(prefix).join("bin"));
     }
+    if let Some(app_data) = std::env::var_os("APPDATA") {
+        dirs.push(PathBuf::from(app_data).join("npm"));
+    }
+    dirs.extend(["/opt/homebrew/bin", "/usr/local/bin", "/usr/bin"].map(PathBuf::from));
+    
+    dirs.retain(|dir| dir.is_dir());
+    dirs
+}
+
+async fn binary_version(path: &Path) -> Option<String> {
+    let output = tokio::process::Command::new(path)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(VERSION_TIMEOUT, output).await.ok()?.ok()?;
     
-    Ok(serde_json::Value::Object(env_vars))
-}
\ No newline at end of file
+    // Some tools print their version to stderr
+    [output.stdout, output.stderr].iter()
+        .find_map(|bytes| {
+            String::from_utf8_lossy(bytes).lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+                .map(str::to_string)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn shell_metacharacters_in_args_stay_literal() {
+        let args = ["; echo pwned", "$(id)", "`id` | cat > /tmp/x"];
+        let argv: Vec<String> = std::iter::once("%s\\n").chain(args).map(str::to_string).collect();
+        let cmd = program_command("printf", &argv, false);
+
+        let (status, _, _, output) = run_to_completion(cmd, None, Some(5000)).await.unwrap();
+
+        assert_eq!(status, "completed");
+        assert_eq!(output, args);
+    }
+}
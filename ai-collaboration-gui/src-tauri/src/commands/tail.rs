@@ -0,0 +1,242 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+use tokio_util::sync::CancellationToken;
+use crate::commands::system::looks_binary;
+use crate::error::AppError;
+use crate::path_access;
+use crate::state::AppState;
+use crate::telemetry::timed;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TailStart {
+    pub tail_id: String,
+    pub lines: Vec<String>,
+}
+
+/// Payload of `file:appended`. `rotated` is set on the first batch after the file was
+/// truncated or replaced; its lines are from the start of the new file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileAppended {
+    pub tail_id: String,
+    pub lines: Vec<String>,
+    pub rotated: bool,
+}
+
+struct Tail {
+    window: String,
+    cancel: CancellationToken,
+}
+
+const DEFAULT_INITIAL_LINES: usize = 100;
+const MAX_INITIAL_LINES: usize = 10_000;
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+// Read per poll at most, so a burst of output arrives as several events
+const MAX_READ_BYTES: u64 = 1024 * 1024;
+const BACKWARD_CHUNK: u64 = 64 * 1024;
+
+/// Running followers by tail id.
+#[derive(Default)]
+pub struct TailRegistry(Mutex<HashMap<String, Tail>>);
+
+impl TailRegistry {
+    /// Stops the tails a window started, once it is gone.
+    pub fn stop_window(&self, window: &str) {
+        self.0.lock().unwrap().retain(|_, tail| {
+            let keep = tail.window != window;
+            if !keep {
+                tail.cancel.cancel();
+            }
+            keep
+        });
+    }
+
+    pub fn stop_all(&self) {
+        for (_, tail) in self.0.lock().unwrap().drain() {
+            tail.cancel.cancel();
+        }
+    }
+}
+
+/// Returns the last `initial_lines` lines of `path` and keeps following it, emitting
+/// `file:appended` with each batch of complete new lines. Follows across log rotation. Runs
+/// until `stop_tail`, or until the window that started it closes.
+#[tauri::command]
+#[timed]
+pub async fn tail_file(
+    app: AppHandle,
+    window: Window,
+    state: State<'_, AppState>,
+    path: String,
+    initial_lines: Option<usize>,
+    project_id: Option<String>,
+) -> Result<TailStart, AppError> {
+    log::info!("Tailing {}", path);
+
+    let file_path = PathBuf::from(&path);
+    path_access::check(&state, &file_path, project_id.as_deref())?;
+    let count = initial_lines.unwrap_or(DEFAULT_INITIAL_LINES).min(MAX_INITIAL_LINES);
+    let read_error = |e: std::io::Error| AppError::Internal(format!("Failed to read file: {}", e));
+
+    let mut file = fs::File::open(&file_path).map_err(read_error)?;
+    let metadata = file.metadata().map_err(read_error)?;
+    let mut head = vec![0; BACKWARD_CHUNK.min(metadata.len()) as usize];
+    file.read_exact(&mut head).map_err(read_error)?;
+    if looks_binary(&head) {
+        return Err(AppError::BinaryFile { size: metadata.len() });
+    }
+    let lines = last_lines(&mut file, metadata.len(), count).map_err(read_error)?;
+
+    let tail_id = uuid::Uuid::new_v4().to_string();
+    let cancel = CancellationToken::new();
+    state.tails.0.lock().unwrap().insert(tail_id.clone(), Tail {
+        window: window.label().to_string(),
+        cancel: cancel.clone(),
+    });
+    let follower = Follower {
+        app,
+        tail_id: tail_id.clone(),
+        path: file_path,
+        identity: file_identity(&metadata),
+        offset: metadata.len(),
+        pending: Vec::new(),
+    };
+    std::thread::spawn(move || follower.run(cancel));
+
+    Ok(TailStart { tail_id, lines })
+}
+
+#[tauri::command]
+#[timed]
+pub async fn stop_tail(state: State<'_, AppState>, tail_id: String) -> Result<(), String> {
+    if let Some(tail) = state.tails.0.lock().unwrap().remove(&tail_id) {
+        log::info!("Stopped tail {}", tail_id);
+        tail.cancel.cancel();
+    }
+    Ok(())
+}
+
+/// The last `count` lines before `end`, read backwards a chunk at a time.
+fn last_lines(file: &mut fs::File, end: u64, count: usize) -> std::io::Result<Vec<String>> {
+    let mut start = end;
+    let mut buffer: Vec<u8> = Vec::new();
+    // A newline ending the file doesn't start another line
+    let wanted = count + 1;
+    while start > 0 && buffer.iter().filter(|b| **b == b'\n').count() < wanted {
+        let chunk = BACKWARD_CHUNK.min(start);
+        start -= chunk;
+        let mut block = vec![0; chunk as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut block)?;
+        block.extend_from_slice(&buffer);
+        buffer = block;
+    }
+
+    let text = String::from_utf8_lossy(&buffer);
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+    // The first line is cut off unless the read reached the start of the file
+    if start > 0 && !lines.is_empty() {
+        lines.remove(0);
+    }
+    let skip = lines.len().saturating_sub(count);
+    Ok(lines.split_off(skip))
+}
+
+struct Follower {
+    app: AppHandle,
+    tail_id: String,
+    path: PathBuf,
+    identity: Option<FileIdentity>,
+    offset: u64,
+    // Bytes of a line that hasn't been finished yet
+    pending: Vec<u8>,
+}
+
+impl Follower {
+    fn run(mut self, cancel: CancellationToken) {
+        while !cancel.is_cancelled() {
+            std::thread::sleep(POLL_INTERVAL);
+            if cancel.is_cancelled() {
+                break;
+            }
+            if let Err(e) = self.poll() {
+                // Usually mid-rotation, when the file is briefly missing
+                log::debug!("Tail {} of {}: {}", self.tail_id, self.path.display(), e);
+            }
+        }
+        self.app.state::<AppState>().tails.0.lock().unwrap().remove(&self.tail_id);
+    }
+
+    fn poll(&mut self) -> std::io::Result<()> {
+        let mut file = fs::File::open(&self.path)?;
+        let metadata = file.metadata()?;
+        let identity = file_identity(&metadata);
+
+        // Replaced by a new file, or truncated in place: start over from the top
+        let rotated = identity != self.identity || metadata.len() < self.offset;
+        if rotated {
+            log::info!("{} was rotated; following the new file", self.path.display());
+            self.identity = identity;
+            self.offset = 0;
+            self.pending.clear();
+        }
+        if metadata.len() == self.offset {
+            if rotated {
+                self.emit(Vec::new(), true);
+            }
+            return Ok(());
+        }
+
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut bytes = Vec::new();
+        let read = file.take(MAX_READ_BYTES).read_to_end(&mut bytes)?;
+        self.offset += read as u64;
+        self.pending.extend_from_slice(&bytes);
+
+        // A line that long is passed on as it is rather than held indefinitely
+        let end = match self.pending.iter().rposition(|b| *b == b'\n') {
+            Some(last_newline) => Some(last_newline + 1),
+            None if self.pending.len() as u64 >= MAX_READ_BYTES => Some(self.pending.len()),
+            None => None,
+        };
+        let Some(end) = end else {
+            if rotated {
+                self.emit(Vec::new(), true);
+            }
+            return Ok(());
+        };
+        let complete: Vec<u8> = self.pending.drain(..end).collect();
+        let lines = String::from_utf8_lossy(&complete).lines().map(str::to_string).collect();
+        self.emit(lines, rotated);
+        Ok(())
+    }
+
+    fn emit(&self, lines: Vec<String>, rotated: bool) {
+        let payload = FileAppended { tail_id: self.tail_id.clone(), lines, rotated };
+        if let Err(e) = self.app.emit("file:appended", payload) {
+            log::warn!("Failed to emit file:appended: {}", e);
+        }
+    }
+}
+
+/// What tells one file at a path from the next: the inode on Unix, the creation time elsewhere.
+#[cfg(unix)]
+type FileIdentity = (u64, u64);
+#[cfg(not(unix))]
+type FileIdentity = std::time::SystemTime;
+
+#[cfg(unix)]
+fn file_identity(metadata: &fs::Metadata) -> Option<FileIdentity> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_identity(metadata: &fs::Metadata) -> Option<FileIdentity> {
+    metadata.created().ok()
+}
@@ -0,0 +1,255 @@
+//! Operation-level undo for file changes made through the backend. Writes, appends, line-range
+//! edits, renames and moves to the trash made for a project push how to reverse them onto the
+//! project's `undo_stack`: the content hash before and after, the backup holding the old content,
+//! the old name or the trash entry. Undoing only goes ahead while the file is still what the
+//! operation left, so later changes are never overwritten; otherwise it fails with a `conflict`.
+
+use chrono::Utc;
+use sha2::Digest;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, State};
+use crate::commands::system::{digest_file, emit_file_changed, move_path, path_changed, write_file, FileHash, HashAlgorithm, WriteMode};
+use crate::database::{Database, DbUndoEntry};
+use crate::error::{typed_or, AppError};
+use crate::events::Action;
+use crate::file_backups;
+use crate::path_access;
+use crate::state::AppState;
+use crate::telemetry::timed;
+
+// Older entries are dropped as new ones are pushed
+const MAX_UNDO_DEPTH: i64 = 50;
+const DEFAULT_HISTORY_LIMIT: i64 = 20;
+
+/// Reverses the newest operation on the project's undo stack and returns it. Fails with
+/// `conflict` if the file has changed since, and with `not_found` when there is nothing to undo.
+#[tauri::command]
+#[timed]
+pub async fn undo_last_file_operation(app: AppHandle, state: State<'_, AppState>, project_id: String) -> Result<DbUndoEntry, AppError> {
+    let entry = state.db.get_undo_entries(&project_id, 1)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::NotFound("There is nothing to undo".to_string()))?;
+    log::info!("Undoing {} of {}", entry.operation, entry.path);
+
+    let path = PathBuf::from(&entry.path);
+    path_access::check(&state, &path, Some(&project_id))?;
+    match entry.operation.as_str() {
+        "rename" => {
+            let previous = PathBuf::from(entry.previous_path.as_deref().unwrap_or_default());
+            path_access::check(&state, &previous, Some(&project_id))?;
+            undo_rename(&entry, &path, &previous)?;
+            emit_file_changed(&app, Action::Deleted, &entry.path, Some(&project_id));
+            emit_file_changed(&app, Action::Created, &previous.to_string_lossy(), Some(&project_id));
+        }
+        "trash" => {
+            undo_trash(&entry, &path)?;
+            emit_file_changed(&app, Action::Created, &entry.path, Some(&project_id));
+        }
+        _ => {
+            let action = undo_change(&state.db, &entry, &path)?;
+            emit_file_changed(&app, action, &entry.path, Some(&project_id));
+        }
+    }
+
+    state.db.delete_undo_entry(&entry.id)?;
+    Ok(entry)
+}
+
+/// The project's undoable operations, newest (the next to be undone) first.
+#[tauri::command]
+#[timed]
+pub async fn get_undo_history(state: State<'_, AppState>, project_id: String, limit: Option<i64>) -> Result<Vec<DbUndoEntry>, AppError> {
+    Ok(state.db.get_undo_entries(&project_id, limit.unwrap_or(DEFAULT_HISTORY_LIMIT).clamp(1, MAX_UNDO_DEPTH))?)
+}
+
+/// What `path` is before an operation changes it, to pass to `record_change` afterwards.
+/// None when it doesn't exist yet.
+pub(crate) fn snapshot(path: &Path) -> Option<FileHash> {
+    digest_file(path, HashAlgorithm::Sha256).ok()
+}
+
+/// Records a write, line-range edit or append (`operation`) that changed `path` from `before`.
+/// `backup_version` is the backup `write_file` took of the old content, if any.
+pub(crate) fn record_change(db: &Database, project_id: Option<&str>, operation: &str, path: &Path, before: Option<FileHash>, backup_version: Option<i64>) {
+    let Some(project_id) = project_id else {
+        return;
+    };
+    push(db, DbUndoEntry {
+        id: uuid::Uuid::new_v4().to_string(),
+        project_id: project_id.to_string(),
+        operation: operation.to_string(),
+        path: path.to_string_lossy().to_string(),
+        previous_path: None,
+        previous_size: before.as_ref().map(|before| before.size as i64),
+        previous_hash: before.map(|before| before.hash),
+        result_hash: hash_of(path),
+        backup_version,
+        trash_id: None,
+        created_at: Utc::now(),
+    });
+}
+
+pub(crate) fn record_rename(db: &Database, project_id: Option<&str>, from: &Path, to: &Path) {
+    let Some(project_id) = project_id else {
+        return;
+    };
+    push(db, DbUndoEntry {
+        id: uuid::Uuid::new_v4().to_string(),
+        project_id: project_id.to_string(),
+        operation: "rename".to_string(),
+        path: to.to_string_lossy().to_string(),
+        previous_path: Some(from.to_string_lossy().to_string()),
+        previous_hash: None,
+        previous_size: None,
+        result_hash: hash_of(to),
+        backup_version: None,
+        trash_id: None,
+        created_at: Utc::now(),
+    });
+}
+
+/// Records that `path` was just moved to the trash, with the trash's id for it where the
+/// platform lets the app look into the trash.
+pub(crate) fn record_trash(db: &Database, project_id: Option<&str>, path: &Path) {
+    let Some(project_id) = project_id else {
+        return;
+    };
+    push(db, DbUndoEntry {
+        id: uuid::Uuid::new_v4().to_string(),
+        project_id: project_id.to_string(),
+        operation: "trash".to_string(),
+        path: path.to_string_lossy().to_string(),
+        previous_path: None,
+        previous_hash: None,
+        previous_size: None,
+        result_hash: None,
+        backup_version: None,
+        trash_id: trash_id(path),
+        created_at: Utc::now(),
+    });
+}
+
+fn push(db: &Database, entry: DbUndoEntry) {
+    if let Err(e) = db.push_undo_entry(&entry, MAX_UNDO_DEPTH) {
+        log::warn!("Failed to record how to undo the {} of {}: {}", entry.operation, entry.path, e);
+    }
+}
+
+// None for directories and missing files
+fn hash_of(path: &Path) -> Option<String> {
+    snapshot(path).map(|hash| hash.hash)
+}
+
+fn check_unchanged(entry: &DbUndoEntry, path: &Path) -> Result<(), AppError> {
+    let current = hash_of(path);
+    if fs::symlink_metadata(path).is_err() || current != entry.result_hash {
+        return Err(AppError::Conflict {
+            message: format!("{} has changed since the {}, so undoing it would lose those changes", path.display(), entry.operation),
+            current_hash: current,
+        });
+    }
+    Ok(())
+}
+
+// Returns how the file changed
+fn undo_change(db: &Database, entry: &DbUndoEntry, path: &Path) -> Result<Action, AppError> {
+    check_unchanged(entry, path)?;
+    let Some(previous_hash) = &entry.previous_hash else {
+        // The operation created the file
+        fs::remove_file(path).map_err(|e| AppError::Internal(format!("Failed to undo: {}", e)))?;
+        path_changed(path);
+        return Ok(Action::Deleted);
+    };
+
+    if entry.operation == "append" {
+        let size = entry.previous_size.unwrap_or_default().max(0) as u64;
+        fs::OpenOptions::new().write(true).open(path)
+            .and_then(|file| file.set_len(size))
+            .map_err(|e| AppError::Internal(format!("Failed to undo: {}", e)))?;
+        path_changed(path);
+        return Ok(Action::Updated);
+    }
+
+    let version = entry.backup_version.ok_or_else(|| AppError::Validation(format!(
+        "No backup of {} was kept when it was written, so the write can't be undone", path.display()
+    )))?;
+    let content = file_backups::read_version(path, version)
+        .map_err(|e| AppError::Validation(format!("Can't undo the write: {}", e)))?;
+    if format!("{:x}", sha2::Sha256::digest(&content)) != *previous_hash {
+        return Err(AppError::Internal(format!("The backup of {} doesn't match what was overwritten", path.display())));
+    }
+    write_file(db, path, content, WriteMode::Overwrite)
+        .map_err(|e| typed_or("Failed to undo", e))?;
+    Ok(Action::Updated)
+}
+
+fn undo_rename(entry: &DbUndoEntry, path: &Path, previous: &Path) -> Result<(), AppError> {
+    check_unchanged(entry, path)?;
+    if fs::symlink_metadata(previous).is_ok() {
+        return Err(AppError::Conflict {
+            message: format!("{} exists again, so {} can't be renamed back", previous.display(), path.display()),
+            current_hash: None,
+        });
+    }
+    move_path(path, previous, false).map_err(|e| AppError::Internal(format!("Failed to undo: {}", e)))?;
+    path_changed(path);
+    path_changed(previous);
+    Ok(())
+}
+
+fn undo_trash(entry: &DbUndoEntry, path: &Path) -> Result<(), AppError> {
+    if fs::symlink_metadata(path).is_ok() {
+        return Err(AppError::Conflict {
+            message: format!("{} was created again since it was deleted", path.display()),
+            current_hash: hash_of(path),
+        });
+    }
+    let id = entry.trash_id.as_deref().ok_or_else(|| AppError::TrashUnavailable(format!(
+        "{} can't be found in the trash, restore it from there by hand", path.display()
+    )))?;
+    restore_from_trash(id, path)?;
+    path_changed(path);
+    Ok(())
+}
+
+#[cfg(any(target_os = "windows", all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))))]
+fn trash_id(path: &Path) -> Option<String> {
+    // The trash keeps the resolved path; the item itself is gone, but its folder isn't
+    let original = path.parent()
+        .and_then(|parent| fs::canonicalize(parent).ok())
+        .zip(path.file_name())
+        .map(|(parent, name)| parent.join(name))
+        .unwrap_or_else(|| path.to_path_buf());
+    match trash::os_limited::list() {
+        Ok(items) => items.into_iter()
+            .filter(|item| item.original_path() == original || item.original_path() == path)
+            .max_by_key(|item| item.time_deleted)
+            .map(|item| item.id.to_string_lossy().to_string()),
+        Err(e) => {
+            log::warn!("Failed to look for {} in the trash: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "windows", all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))))]
+fn trash_id(_path: &Path) -> Option<String> {
+    None
+}
+
+#[cfg(any(target_os = "windows", all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))))]
+fn restore_from_trash(id: &str, path: &Path) -> Result<(), AppError> {
+    let item = trash::os_limited::list()
+        .map_err(|e| AppError::TrashUnavailable(e.to_string()))?
+        .into_iter()
+        .find(|item| item.id.to_string_lossy() == id)
+        .ok_or_else(|| AppError::NotFound(format!("{} is no longer in the trash", path.display())))?;
+    trash::os_limited::restore_all([item]).map_err(|e| AppError::TrashUnavailable(e.to_string()))
+}
+
+#[cfg(not(any(target_os = "windows", all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))))]
+fn restore_from_trash(_id: &str, path: &Path) -> Result<(), AppError> {
+    Err(AppError::TrashUnavailable(format!("restoring {} from the trash isn't supported on this platform", path.display())))
+}
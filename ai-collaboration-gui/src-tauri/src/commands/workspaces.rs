@@ -0,0 +1,219 @@
+//! Scratch directories agents can write to without touching the project, one or more per swarm
+//! under `<app data>/workspaces/<swarm id>/<uuid>`. The file commands may always touch them,
+//! and writes through them are held to a per-workspace size quota.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use once_cell::sync::OnceCell;
+use crate::database::Database;
+use crate::error::AppError;
+use crate::state::AppState;
+use crate::telemetry::timed;
+use tauri::State;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workspace {
+    pub id: String,
+    pub swarm_id: String,
+    pub path: String,
+    pub created_at: DateTime<Utc>,
+    pub size_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceSettings {
+    // Per workspace; writes that would go past it fail
+    pub quota_bytes: u64,
+}
+
+impl Default for WorkspaceSettings {
+    fn default() -> Self {
+        Self { quota_bytes: DEFAULT_QUOTA_BYTES }
+    }
+}
+
+const SETTING: &str = "workspaces";
+const DEFAULT_QUOTA_BYTES: u64 = 500 * 1024 * 1024;
+
+static WORKSPACES_DIR: OnceCell<PathBuf> = OnceCell::new();
+
+/// Set once at startup; until then no workspace can be created.
+pub fn init_workspaces(app_data_dir: &Path) {
+    let _ = WORKSPACES_DIR.set(app_data_dir.join("workspaces"));
+}
+
+#[tauri::command]
+#[timed]
+pub async fn create_workspace(state: State<'_, AppState>, swarm_id: String) -> Result<Workspace, String> {
+    log::info!("Creating workspace for swarm {}", swarm_id);
+    
+    let dir = swarm_dir(&swarm_id).map_err(|e| e.to_string())?;
+    let id = uuid::Uuid::new_v4().to_string();
+    let path = dir.join(&id);
+    fs::create_dir_all(&path).map_err(|e| format!("Failed to create workspace: {}", e))?;
+    
+    Ok(Workspace {
+        id,
+        swarm_id,
+        path: path.to_string_lossy().to_string(),
+        created_at: Utc::now(),
+        size_bytes: 0,
+    })
+}
+
+/// The swarm's workspaces, oldest first.
+#[tauri::command]
+#[timed]
+pub async fn list_workspaces(state: State<'_, AppState>, swarm_id: String) -> Result<Vec<Workspace>, String> {
+    let dir = swarm_dir(&swarm_id).map_err(|e| e.to_string())?;
+    let mut workspaces = read_workspaces(&swarm_id, &dir)
+        .map_err(|e| format!("Failed to list workspaces: {}", e))?;
+    workspaces.sort_by_key(|workspace| workspace.created_at);
+    Ok(workspaces)
+}
+
+/// Removes the workspaces of `swarm_id`, or those older than `older_than_days`, or with both
+/// the swarm's workspaces past that age. Returns how many were removed.
+#[tauri::command]
+#[timed]
+pub async fn cleanup_workspaces(state: State<'_, AppState>, swarm_id: Option<String>, older_than_days: Option<u32>) -> Result<usize, String> {
+    log::info!("Cleaning up workspaces (swarm {:?}, older than {:?} days)", swarm_id, older_than_days);
+    
+    if swarm_id.is_none() && older_than_days.is_none() {
+        return Err("Either a swarm or an age is required".to_string());
+    }
+    let root = WORKSPACES_DIR.get().ok_or("Workspaces are not initialized")?;
+    let cutoff = older_than_days.map(|days| Utc::now() - Duration::days(days as i64));
+    
+    let swarm_ids: Vec<String> = match swarm_id {
+        Some(swarm_id) => vec![swarm_id],
+        None => fs::read_dir(root)
+            .map(|entries| entries.flatten().filter_map(|e| e.file_name().into_string().ok()).collect())
+            .unwrap_or_default(),
+    };
+    
+    let mut removed = 0;
+    for swarm_id in swarm_ids {
+        let dir = swarm_dir(&swarm_id).map_err(|e| e.to_string())?;
+        let workspaces = read_workspaces(&swarm_id, &dir)
+            .map_err(|e| format!("Failed to list workspaces: {}", e))?;
+        for workspace in workspaces {
+            if cutoff.is_some_and(|cutoff| workspace.created_at >= cutoff) {
+                continue;
+            }
+            fs::remove_dir_all(&workspace.path)
+                .map_err(|e| format!("Failed to remove workspace {}: {}", workspace.path, e))?;
+            removed += 1;
+        }
+        // Gone along with its last workspace
+        let _ = fs::remove_dir(&dir);
+    }
+    Ok(removed)
+}
+
+#[tauri::command]
+#[timed]
+pub async fn get_workspace_settings(state: State<'_, AppState>) -> Result<WorkspaceSettings, String> {
+    Ok(workspace_settings(&state.db))
+}
+
+#[tauri::command]
+#[timed]
+pub async fn set_workspace_settings(state: State<'_, AppState>, settings: WorkspaceSettings) -> Result<(), String> {
+    log::info!("Setting workspaces: {:?}", settings);
+    
+    if settings.quota_bytes == 0 {
+        return Err("The quota must be above zero".to_string());
+    }
+    let value = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+    state.db.set_setting(SETTING, &value)
+        .map_err(|e| format!("Failed to save workspace settings: {}", e))
+}
+
+/// Every workspace lives under this, so the file sandbox lets the commands touch it.
+pub(crate) fn workspaces_root() -> Option<&'static Path> {
+    WORKSPACES_DIR.get().map(PathBuf::as_path)
+}
+
+/// Fails with `QuotaExceeded` if writing `new_size` bytes to `path` would take the workspace
+/// it is in over the quota. Paths outside the workspaces pass.
+pub(crate) fn check_quota(db: &Database, path: &Path, new_size: u64) -> Result<(), AppError> {
+    let Some(workspace) = workspace_of(path) else {
+        return Ok(());
+    };
+    let quota = workspace_settings(db).quota_bytes;
+    let replaced = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let size = dir_size(&workspace).saturating_sub(replaced) + new_size;
+    if size > quota {
+        return Err(AppError::QuotaExceeded(format!(
+            "writing {} would bring workspace {} to {} bytes; the quota is {}",
+            path.display(), workspace.display(), size, quota
+        )));
+    }
+    Ok(())
+}
+
+fn workspace_settings(db: &Database) -> WorkspaceSettings {
+    db.get_setting(SETTING)
+        .ok()
+        .flatten()
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
+fn swarm_dir(swarm_id: &str) -> Result<PathBuf> {
+    let root = WORKSPACES_DIR.get().ok_or_else(|| anyhow!("Workspaces are not initialized"))?;
+    // The id becomes a directory name, so it may not climb out of the root
+    if swarm_id.is_empty() || swarm_id.contains(['/', '\\']) || swarm_id == "." || swarm_id == ".." {
+        return Err(anyhow!("Invalid swarm id: {:?}", swarm_id));
+    }
+    Ok(root.join(swarm_id))
+}
+
+fn read_workspaces(swarm_id: &str, dir: &Path) -> std::io::Result<Vec<Workspace>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut workspaces = Vec::new();
+    for entry in entries.flatten() {
+        let metadata = entry.metadata()?;
+        if !metadata.is_dir() {
+            continue;
+        }
+        let created = metadata.created().or_else(|_| metadata.modified())?;
+        workspaces.push(Workspace {
+            id: entry.file_name().to_string_lossy().to_string(),
+            swarm_id: swarm_id.to_string(),
+            path: entry.path().to_string_lossy().to_string(),
+            created_at: created.into(),
+            size_bytes: dir_size(&entry.path()),
+        });
+    }
+    Ok(workspaces)
+}
+
+/// The workspace directory `path` is in, two levels below the root.
+fn workspace_of(path: &Path) -> Option<PathBuf> {
+    let root = fs::canonicalize(WORKSPACES_DIR.get()?).ok()?;
+    let path = path.parent().and_then(|parent| fs::canonicalize(parent).ok())?;
+    let relative = path.strip_prefix(&root).ok()?;
+    let mut components = relative.components();
+    let (swarm, workspace) = (components.next()?, components.next()?);
+    Some(root.join(swarm).join(workspace))
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    ignore::WalkBuilder::new(dir)
+        .standard_filters(false)
+        .build()
+        .flatten()
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
@@ -1,14 +1,18 @@
-use rusqlite::{Connection, Result, params};
+use rusqlite::{Connection, OptionalExtension, Result, params};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 use chrono::{DateTime, Utc};
-use uuid::Uuid;
-use once_cell::sync::Lazy;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use anyhow::anyhow;
+use crate::error::AppError;
 
-// 데이터베이스 연결을 위한 전역 변수
-static DB_CONNECTION: Lazy<Mutex<Option<Connection>>> = Lazy::new(|| Mutex::new(None));
+/// The app's SQLite database, open and migrated for as long as a handle to it lives. Clones
+/// share the connection, so background tasks can keep their own. The app keeps one in
+/// `AppState`; tests can use `open_in_memory` instead.
+#[derive(Clone)]
+pub struct Database {
+    conn: Arc<Mutex<Connection>>,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DbProject {
@@ -16,7 +20,46 @@ pub struct DbProject {
     pub name: String,
     pub path: String,
     pub description: Option<String>,
+    pub settings: Option<String>, // JSON; the defaults apply while unset
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub last_opened_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub archived: bool,
+}
+
+/// How `get_all_projects` sorts. Never-opened projects come last by `LastOpened`, and archived
+/// ones after those.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectOrder {
+    LastOpened,
+    #[default]
+    Updated,
+    Name,
+}
+
+/// A chat session of a project with its message count and the time of its last message.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbSessionSummary {
+    pub id: String,
+    pub project_id: String,
+    pub name: String,
     pub created_at: DateTime<Utc>,
+    pub last_active: DateTime<Utc>,
+    pub message_count: i32,
+}
+
+/// A project's settings for one AI tool; `custom_settings` and `env` are JSON objects.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbProjectAITool {
+    pub project_id: String,
+    pub tool_id: String,
+    pub enabled: bool,
+    pub priority: i32,
+    pub custom_settings: String,
+    pub env: String,
     pub updated_at: DateTime<Utc>,
 }
 
@@ -26,18 +69,126 @@ pub struct DbChatSession {
     pub name: String,
     pub project_id: Option<String>,
     pub swarm_id: Option<String>,
+    pub system_prompt: Option<String>,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub archived: bool,
+    #[serde(default = "initial_name_source")]
+    pub name_source: String, // 'initial', 'auto', 'manual'
+    #[serde(default)]
+    pub last_read_message_at: Option<DateTime<Utc>>,
+    // Messages after the read marker that the user didn't write
+    #[serde(default)]
+    pub unread_count: i64,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    // Filled in only when the session list is asked for token totals
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_tokens: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_cost: Option<f64>,
+}
+
+fn initial_name_source() -> String {
+    "initial".to_string()
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DbChatMessage {
     pub id: String,
     pub session_id: String,
-    pub role: String, // 'user', 'assistant', 'system'
+    pub role: String, // 'user', 'assistant', 'system', 'tool'
     pub content: String,
-    pub metadata: Option<String>, // JSON string
+    pub metadata: Option<MessageMetadata>,
     pub timestamp: DateTime<Utc>,
+    // Tokens billed for the call that produced a reply; a user message counts its own text
+    #[serde(default)]
+    pub prompt_tokens: Option<i64>,
+    #[serde(default)]
+    pub completion_tokens: Option<i64>,
+    // Filled in only when messages are asked for with their attachments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attachments: Option<Vec<DbMessageAttachment>>,
+}
+
+/// What the app records about a chat message, stored as JSON in `chat_messages.metadata`. Every
+/// field may be missing, so older rows still parse; fields this version doesn't know land in
+/// `custom` and are written back unchanged.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct MessageMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<MessageUsage>,
+    // The token counts are estimates rather than reported by the tool
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub estimated: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub swarm_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<String>,
+    // Paths of the files attached to the message
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub applied_code_blocks: Vec<AppliedCodeBlock>,
+    #[serde(flatten)]
+    pub custom: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub struct MessageUsage {
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+}
+
+/// A code block of the message written to a file with `apply_code_block_to_file`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AppliedCodeBlock {
+    pub block_index: usize,
+    pub path: String,
+    pub mode: String, // 'create', 'overwrite', 'append'
+    pub applied_at: DateTime<Utc>,
+}
+
+impl MessageMetadata {
+    /// Metadata that isn't a JSON object, from before it was typed, is kept under `custom.raw`.
+    fn from_column(raw: Option<String>) -> Option<Self> {
+        let raw = raw?;
+        Some(serde_json::from_str(&raw).unwrap_or_else(|_| {
+            let mut custom = serde_json::Map::new();
+            custom.insert("raw".to_string(), raw.into());
+            MessageMetadata { custom, ..Default::default() }
+        }))
+    }
+    
+    fn to_column(metadata: Option<&Self>) -> Result<Option<String>, serde_json::Error> {
+        metadata.map(serde_json::to_string).transpose()
+    }
+}
+
+/// A file a chat message refers to, with the hash of what it held then and optionally a copy of
+/// it. The range is in bytes, `range_end` exclusive.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbMessageAttachment {
+    pub id: String,
+    pub message_id: String,
+    pub path: String,
+    pub content_hash: String, // SHA-256, lowercase hex
+    pub range_start: Option<i64>,
+    pub range_end: Option<i64>,
+    pub snapshot_text: Option<String>,
+    pub created_at: DateTime<Utc>,
+    // The file no longer has the attached content; worked out when read, not stored
+    #[serde(default)]
+    pub stale: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -52,29 +203,290 @@ pub struct DbSwarm {
     pub updated_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbSwarmAgent {
+    pub id: String,
+    pub swarm_id: String,
+    pub agent_type: String,
+    pub ai_tool: String,
+    pub role: String,
+    pub specialization: String, // JSON array
+    pub current_task_id: Option<String>,
+    pub performance: String, // JSON string
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbSwarmTask {
+    pub id: String,
+    pub swarm_id: String,
+    pub title: String,
+    pub description: String,
+    pub status: String, // 'pending' | 'in_progress' | 'completed' | 'failed' | 'cancelled'
+    pub priority: i32,
+    pub assigned_to: Option<String>,
+    pub dependencies: String, // JSON array of task ids
+    pub estimated_duration: Option<i32>,
+    pub actual_duration: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbTaskResult {
+    pub id: String,
+    pub task_id: String,
+    pub agent_id: String,
+    pub output: String, // JSON string
+    pub confidence: f32,
+    pub model: Option<String>,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbWorkflowNode {
+    pub id: String,
+    pub swarm_id: String,
+    pub node_type: String,
+    pub name: String,
+    pub position_x: f32,
+    pub position_y: f32,
+    pub data: String, // JSON string
+    pub connections: String, // JSON array
+    pub status: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbSwarmTemplate {
+    pub id: String,
+    pub name: String,
+    pub config: String, // JSON string (SwarmConfig)
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbAIInteraction {
+    pub id: String,
+    pub tool_id: String,
+    pub command_id: String,
+    pub command_type: String,
+    pub payload: String, // JSON string, secrets redacted
+    pub response: Option<String>, // JSON string
+    pub error: Option<String>,
+    pub success: bool,
+    pub model: Option<String>,
+    pub prompt_tokens: Option<i64>,
+    pub completion_tokens: Option<i64>,
+    // Token counts were estimated from the text because the tool reported none
+    #[serde(default)]
+    pub tokens_estimated: bool,
+    pub latency_ms: i64,
+    pub session_id: Option<String>,
+    pub swarm_id: Option<String>,
+    pub task_id: Option<String>,
+    // Shared by every tool's entry when one prompt was broadcast to several tools
+    pub broadcast_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbSwarmEvent {
+    pub id: String,
+    pub swarm_id: String,
+    pub event_type: String,
+    pub task_id: Option<String>,
+    pub agent_id: Option<String>,
+    pub payload: String, // JSON string
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbMemoryEntry {
+    pub id: String,
+    pub swarm_id: String,
+    pub namespace: String,
+    pub entry_type: String,
+    pub content: String, // JSON string
+    pub metadata: String, // JSON object
+    pub sender_id: Option<String>,
+    pub recipient_id: Option<String>, // None이면 전체 브로드캐스트
+    pub importance: i32,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbModelPricing {
+    pub model: String,
+    pub prompt_price: f64, // USD per 1M tokens
+    pub completion_price: f64, // USD per 1M tokens
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One (day, tool, model) group of ai_interactions; `day` is the UTC date as YYYY-MM-DD.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbDailyToolUsage {
+    pub day: String,
+    pub tool_id: String,
+    pub model: Option<String>,
+    pub calls: i64,
+    pub errors: i64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub estimated_calls: i64,
+    pub total_latency_ms: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbTokenUsage {
+    pub model: Option<String>,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DbSwarmTaskStats {
+    pub total: i32,
+    pub pending: i32,
+    pub in_progress: i32,
+    pub completed: i32,
+    pub failed: i32,
+    pub cancelled: i32,
+    pub average_duration: Option<f64>,
+    pub total_duration: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DbSwarmProgress {
+    pub stats: DbSwarmTaskStats,
+    pub estimated_count: i32, // 예상 소요 시간이 있는 작업 수
+    pub estimated_total: i64,
+    pub estimated_finished: i64,
+    pub unestimated_finished: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbRunningTask {
+    pub task_id: String,
+    pub title: String,
+    pub agent_id: Option<String>,
+    pub agent_type: Option<String>,
+    pub started_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbAITool {
+    pub id: String,
+    pub tool_type: String,
+    pub name: String,
+    pub version: String,
+    pub status: String,
+    pub config: String, // JSON string (ToolSpecificConfig)
+    pub last_used: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DbAIToolConfig {
     pub id: String,
+    #[serde(default)]
+    pub tool_id: Option<String>, // ai_tools.id
     pub tool_name: String,
     pub config: String, // JSON string
     pub is_connected: bool,
+    #[serde(default)]
+    pub has_api_key: bool, // not a column; set when configs are handed to the frontend
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A change made through the file commands, kept so the UI can show what an agent did.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbFileOperation {
+    pub id: String,
+    pub operation: String, // delete
+    pub path: String,
+    pub project_id: Option<String>,
+    pub permanent: bool, // false when moved to the trash
+    pub created_at: DateTime<Utc>,
+}
+
+/// How to reverse a file operation made in a project; see `commands::undo`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbUndoEntry {
+    pub id: String,
+    pub project_id: String,
+    pub operation: String, // 'write' | 'write_range' | 'append' | 'rename' | 'trash'
+    pub path: String, // after a rename, the new path
+    pub previous_path: Option<String>, // the old path of a rename
+    pub previous_hash: Option<String>, // None when the operation created the file
+    pub result_hash: Option<String>, // what the file hashed to right after; None for directories
+    pub backup_version: Option<i64>, // the file_backups version holding the old content
+    pub previous_size: Option<i64>, // the length before an append
+    pub trash_id: Option<String>, // the trash's id for a deleted item
     pub created_at: DateTime<Utc>,
+}
+
+/// One row of the cross-project activity feed. `kind` is 'chat_message', 'swarm_event',
+/// 'task_completed' or 'file_operation'; the ids say where to open it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbActivityItem {
+    pub kind: String,
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub project_id: Option<String>,
+    pub project_name: Option<String>,
+    pub session_id: Option<String>,
+    pub session_name: Option<String>,
+    pub swarm_id: Option<String>,
+    pub swarm_name: Option<String>,
+    pub task_id: Option<String>,
+    pub detail: Option<String>,  // role, event type, agent id or file operation
+    pub summary: Option<String>, // start of the message, task title or path
+}
+
+/// An environment variable the user set for every spawned process. Secret values live in the
+/// keychain and the row holds the keychain placeholder instead.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbEnvVar {
+    pub key: String,
+    pub value: String,
+    pub secret: bool,
     pub updated_at: DateTime<Utc>,
 }
 
 // 데이터베이스 초기화
-pub fn initialize_database(db_path: &Path) -> Result<(), anyhow::Error> {
-    let conn = Connection::open(db_path)?;
-    
-    // 테이블 생성
-    create_tables(&conn)?;
+impl Database {
+    pub fn open(db_path: &Path) -> Result<Self, anyhow::Error> {
+        let database = Self::prepare(Connection::open(db_path)?)?;
+        log::info!("Database initialized at: {:?}", db_path);
+        Ok(database)
+    }
     
-    // 전역 연결 설정
-    let mut db_conn = DB_CONNECTION.lock().unwrap();
-    *db_conn = Some(conn);
+    /// A fresh, empty database that lives only as long as the value.
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<Self, anyhow::Error> {
+        Self::prepare(Connection::open_in_memory()?)
+    }
     
-    log::info!("Database initialized at: {:?}", db_path);
-    Ok(())
+    fn prepare(conn: Connection) -> Result<Self, anyhow::Error> {
+        // 테이블 생성
+        create_tables(&conn)?;
+        run_migrations(&conn)?;
+        
+        // 이전 실행의 프로세스는 남아 있지 않으므로 연결 상태를 초기화
+        conn.execute(
+            "UPDATE ai_tools SET status = 'disconnected' WHERE status IN ('connected', 'connecting')",
+            [],
+        )?;
+        
+        Ok(Self { conn: Arc::new(Mutex::new(conn)) })
+    }
 }
 
 fn create_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
@@ -135,6 +547,159 @@ fn create_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
         [],
     )?;
 
+    // Swarm Agents 테이블
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS swarm_agents (
+            id TEXT PRIMARY KEY,
+            swarm_id TEXT NOT NULL,
+            agent_type TEXT NOT NULL,
+            ai_tool TEXT NOT NULL,
+            role TEXT NOT NULL,
+            specialization TEXT NOT NULL,
+            current_task_id TEXT,
+            performance TEXT NOT NULL,
+            is_active BOOLEAN NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY(swarm_id) REFERENCES swarms(id)
+        )",
+        [],
+    )?;
+
+    // Swarm Tasks 테이블
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS swarm_tasks (
+            id TEXT PRIMARY KEY,
+            swarm_id TEXT NOT NULL,
+            title TEXT NOT NULL,
+            description TEXT NOT NULL,
+            status TEXT NOT NULL,
+            priority INTEGER NOT NULL DEFAULT 0,
+            assigned_to TEXT,
+            dependencies TEXT NOT NULL,
+            estimated_duration INTEGER,
+            actual_duration INTEGER,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY(swarm_id) REFERENCES swarms(id)
+        )",
+        [],
+    )?;
+
+    // Task Results 테이블
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS task_results (
+            id TEXT PRIMARY KEY,
+            task_id TEXT NOT NULL,
+            agent_id TEXT NOT NULL,
+            output TEXT NOT NULL,
+            confidence REAL NOT NULL,
+            timestamp TEXT NOT NULL,
+            FOREIGN KEY(task_id) REFERENCES swarm_tasks(id)
+        )",
+        [],
+    )?;
+
+    // Workflow Nodes 테이블
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS swarm_workflow_nodes (
+            id TEXT NOT NULL,
+            swarm_id TEXT NOT NULL,
+            node_type TEXT NOT NULL,
+            name TEXT NOT NULL,
+            position_x REAL NOT NULL,
+            position_y REAL NOT NULL,
+            data TEXT NOT NULL,
+            connections TEXT NOT NULL,
+            status TEXT NOT NULL,
+            sort_order INTEGER NOT NULL,
+            PRIMARY KEY(swarm_id, id),
+            FOREIGN KEY(swarm_id) REFERENCES swarms(id)
+        )",
+        [],
+    )?;
+
+    // Swarm Templates 테이블
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS swarm_templates (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            config TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // Swarm Events 테이블
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS swarm_events (
+            id TEXT PRIMARY KEY,
+            swarm_id TEXT NOT NULL,
+            event_type TEXT NOT NULL,
+            task_id TEXT,
+            agent_id TEXT,
+            payload TEXT NOT NULL,
+            timestamp TEXT NOT NULL,
+            FOREIGN KEY(swarm_id) REFERENCES swarms(id)
+        )",
+        [],
+    )?;
+
+    // Swarm Memory 테이블
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS swarm_memory (
+            id TEXT PRIMARY KEY,
+            swarm_id TEXT NOT NULL,
+            namespace TEXT NOT NULL,
+            entry_type TEXT NOT NULL,
+            content TEXT NOT NULL,
+            metadata TEXT NOT NULL,
+            sender_id TEXT,
+            recipient_id TEXT,
+            importance INTEGER NOT NULL DEFAULT 5,
+            timestamp TEXT NOT NULL,
+            FOREIGN KEY(swarm_id) REFERENCES swarms(id)
+        )",
+        [],
+    )?;
+
+    // Model Pricing 테이블 (기본 가격표를 덮어쓰는 값)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS model_pricing (
+            model TEXT PRIMARY KEY,
+            prompt_price REAL NOT NULL,
+            completion_price REAL NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // App Settings 테이블 (키-값)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS app_settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // AI Tools 테이블
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS ai_tools (
+            id TEXT PRIMARY KEY,
+            tool_type TEXT NOT NULL,
+            name TEXT NOT NULL,
+            version TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'disconnected',
+            config TEXT NOT NULL,
+            last_used TEXT,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
     // AI Tool Configurations 테이블
     conn.execute(
         "CREATE TABLE IF NOT EXISTS ai_tool_configs (
@@ -148,231 +713,661 @@ fn create_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
         [],
     )?;
 
+    // 프로젝트별 AI 도구 설정 테이블
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS project_ai_tools (
+            project_id TEXT NOT NULL,
+            tool_id TEXT NOT NULL,
+            enabled BOOLEAN NOT NULL DEFAULT 1,
+            priority INTEGER NOT NULL DEFAULT 0,
+            custom_settings TEXT NOT NULL DEFAULT '{}',
+            env TEXT NOT NULL DEFAULT '{}',
+            updated_at TEXT NOT NULL,
+            PRIMARY KEY(project_id, tool_id),
+            FOREIGN KEY(project_id) REFERENCES projects(id)
+        )",
+        [],
+    )?;
+
+    // AI 명령/응답 기록 테이블
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS ai_interactions (
+            id TEXT PRIMARY KEY,
+            tool_id TEXT NOT NULL,
+            command_id TEXT NOT NULL,
+            command_type TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            response TEXT,
+            error TEXT,
+            success BOOLEAN NOT NULL,
+            model TEXT,
+            prompt_tokens INTEGER,
+            completion_tokens INTEGER,
+            latency_ms INTEGER NOT NULL,
+            session_id TEXT,
+            swarm_id TEXT,
+            task_id TEXT,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
     // 인덱스 생성
     conn.execute("CREATE INDEX IF NOT EXISTS idx_projects_name ON projects(name)", [])?;
     conn.execute("CREATE INDEX IF NOT EXISTS idx_chat_sessions_project ON chat_sessions(project_id)", [])?;
     conn.execute("CREATE INDEX IF NOT EXISTS idx_chat_messages_session ON chat_messages(session_id)", [])?;
     conn.execute("CREATE INDEX IF NOT EXISTS idx_swarms_project ON swarms(project_id)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_swarm_agents_swarm ON swarm_agents(swarm_id)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_swarm_tasks_swarm ON swarm_tasks(swarm_id, status)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_task_results_task ON task_results(task_id)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_swarm_events_swarm ON swarm_events(swarm_id, timestamp)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_swarm_memory_swarm ON swarm_memory(swarm_id, entry_type, timestamp)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_swarm_memory_namespace ON swarm_memory(namespace, timestamp)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_ai_interactions_tool ON ai_interactions(tool_id, created_at)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_ai_interactions_session ON ai_interactions(session_id, created_at)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_ai_interactions_swarm ON ai_interactions(swarm_id, created_at)", [])?;
     
     log::info!("Database tables created successfully");
     Ok(())
 }
 
-// 프로젝트 관련 함수들
-pub fn create_project(project: &DbProject) -> Result<(), anyhow::Error> {
-    let db_conn = DB_CONNECTION.lock().unwrap();
-    let conn = db_conn.as_ref().ok_or_else(|| anyhow!("Database not initialized"))?;
+// 스키마 마이그레이션: 적용된 개수는 PRAGMA user_version 으로 추적
+const MIGRATIONS: &[&str] = &[
+    // 1: 스웜 스케줄러 스냅샷
+    "ALTER TABLE swarms ADD COLUMN snapshot TEXT",
+    // 2: 작업 결과별 토큰 사용량
+    "ALTER TABLE task_results ADD COLUMN model TEXT;
+     ALTER TABLE task_results ADD COLUMN prompt_tokens INTEGER NOT NULL DEFAULT 0;
+     ALTER TABLE task_results ADD COLUMN completion_tokens INTEGER NOT NULL DEFAULT 0;",
+    // 3: 기본 AI 도구 등록 (최초 1회) 및 기존 설정을 도구에 연결
+    "INSERT OR IGNORE INTO ai_tools (id, tool_type, name, version, status, config, created_at, updated_at) VALUES
+        ('claude-code', 'claude-code', 'Claude Code', '1.0.0', 'disconnected',
+         '{\"api_key\":null,\"endpoint\":\"https://api.anthropic.com\",\"max_tokens\":4096,\"temperature\":0.7,\"model\":\"claude-3-sonnet\",\"additional_config\":{}}',
+         strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+        ('gemini-cli', 'gemini-cli', 'Gemini CLI', '1.0.0', 'disconnected',
+         '{\"api_key\":null,\"endpoint\":\"https://generativelanguage.googleapis.com\",\"max_tokens\":8192,\"temperature\":0.9,\"model\":\"gemini-pro\",\"additional_config\":{}}',
+         strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), strftime('%Y-%m-%dT%H:%M:%fZ', 'now'));
+     ALTER TABLE ai_tool_configs ADD COLUMN tool_id TEXT REFERENCES ai_tools(id);
+     UPDATE ai_tool_configs SET tool_id = (SELECT id FROM ai_tools WHERE ai_tools.tool_type = ai_tool_configs.tool_name);",
+    // 4: 도구 오류 사유
+    "ALTER TABLE ai_tools ADD COLUMN last_error TEXT",
+    // 5: 브로드캐스트 묶음 ID
+    "ALTER TABLE ai_interactions ADD COLUMN broadcast_id TEXT;
+     CREATE INDEX IF NOT EXISTS idx_ai_interactions_broadcast ON ai_interactions(broadcast_id);",
+    // 6: 추정 토큰 여부 및 기간별 집계용 인덱스
+    "ALTER TABLE ai_interactions ADD COLUMN tokens_estimated BOOLEAN NOT NULL DEFAULT 0;
+     CREATE INDEX IF NOT EXISTS idx_ai_interactions_created ON ai_interactions(created_at);",
+    // 7: 세션별 시스템 프롬프트
+    "ALTER TABLE chat_sessions ADD COLUMN system_prompt TEXT",
+    // 8: 파일 작업 감사 기록
+    "CREATE TABLE IF NOT EXISTS file_operations (
+        id TEXT PRIMARY KEY,
+        operation TEXT NOT NULL,
+        path TEXT NOT NULL,
+        project_id TEXT,
+        permanent BOOLEAN NOT NULL,
+        created_at TEXT NOT NULL
+     );
+     CREATE INDEX IF NOT EXISTS idx_file_operations_created ON file_operations(operation, created_at);",
+    // 9: 사용자 정의 환경 변수
+    "CREATE TABLE IF NOT EXISTS env_vars (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL,
+        secret BOOLEAN NOT NULL,
+        updated_at TEXT NOT NULL
+     );",
+    // 10: 프로젝트 설정
+    "ALTER TABLE projects ADD COLUMN settings TEXT",
+    // 11: 프로젝트 마지막 열람 시각
+    "ALTER TABLE projects ADD COLUMN last_opened_at TEXT",
+    // 12: 프로젝트 보관
+    "ALTER TABLE projects ADD COLUMN archived BOOLEAN NOT NULL DEFAULT 0",
+    // 13: 보존 기간 정리에서 제외할 세션/스웜 고정
+    "ALTER TABLE chat_sessions ADD COLUMN pinned BOOLEAN NOT NULL DEFAULT 0;
+     ALTER TABLE swarms ADD COLUMN pinned BOOLEAN NOT NULL DEFAULT 0;",
+    // 14: 채팅 세션 보관
+    "ALTER TABLE chat_sessions ADD COLUMN archived BOOLEAN NOT NULL DEFAULT 0",
+    // 15: 세션 이름이 자동 생성인지 사용자가 지은 것인지
+    "ALTER TABLE chat_sessions ADD COLUMN name_source TEXT NOT NULL DEFAULT 'initial'",
+    // 16: 메시지별 토큰 수
+    "ALTER TABLE chat_messages ADD COLUMN prompt_tokens INTEGER;
+     ALTER TABLE chat_messages ADD COLUMN completion_tokens INTEGER;",
+    // 17: 메시지 첨부 파일
+    "CREATE TABLE IF NOT EXISTS message_attachments (
+        id TEXT PRIMARY KEY,
+        message_id TEXT NOT NULL,
+        path TEXT NOT NULL,
+        content_hash TEXT NOT NULL,
+        range_start INTEGER,
+        range_end INTEGER,
+        snapshot_text TEXT,
+        created_at TEXT NOT NULL,
+        FOREIGN KEY(message_id) REFERENCES chat_messages(id)
+     );
+     CREATE INDEX IF NOT EXISTS idx_message_attachments_message ON message_attachments(message_id);",
+    // 18: 최근 활동 피드용 시간 인덱스
+    "CREATE INDEX IF NOT EXISTS idx_chat_messages_timestamp ON chat_messages(timestamp);
+     CREATE INDEX IF NOT EXISTS idx_swarm_events_timestamp ON swarm_events(timestamp);
+     CREATE INDEX IF NOT EXISTS idx_task_results_timestamp ON task_results(timestamp);
+     CREATE INDEX IF NOT EXISTS idx_file_operations_time ON file_operations(created_at);",
+    // 19: 세션 읽음 표시
+    "ALTER TABLE chat_sessions ADD COLUMN last_read_message_at TEXT;
+     CREATE INDEX IF NOT EXISTS idx_chat_messages_session_time ON chat_messages(session_id, timestamp);",
+    // 20: 파일 작업 되돌리기 기록
+    "CREATE TABLE IF NOT EXISTS undo_stack (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL,
+        operation TEXT NOT NULL,
+        path TEXT NOT NULL,
+        previous_path TEXT,
+        previous_hash TEXT,
+        result_hash TEXT,
+        backup_version INTEGER,
+        previous_size INTEGER,
+        trash_id TEXT,
+        created_at TEXT NOT NULL
+     );
+     CREATE INDEX IF NOT EXISTS idx_undo_stack_project ON undo_stack(project_id, created_at);",
+];
+
+fn run_migrations(conn: &Connection) -> Result<(), rusqlite::Error> {
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
     
-    conn.execute(
-        "INSERT INTO projects (id, name, path, description, created_at, updated_at) 
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-        params![
-            project.id,
-            project.name,
-            project.path,
-            project.description,
-            project.created_at.to_rfc3339(),
-            project.updated_at.to_rfc3339()
-        ],
-    )?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version.max(0) as usize) {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute_batch(migration)?;
+        tx.execute_batch(&format!("PRAGMA user_version = {}", index + 1))?;
+        tx.commit()?;
+        log::info!("Applied database migration {}", index + 1);
+    }
     
-    log::info!("Project created: {}", project.name);
     Ok(())
 }
 
-pub fn get_all_projects() -> Result<Vec<DbProject>, anyhow::Error> {
-    let db_conn = DB_CONNECTION.lock().unwrap();
-    let conn = db_conn.as_ref().ok_or_else(|| anyhow!("Database not initialized"))?;
-    
-    let mut stmt = conn.prepare(
-        "SELECT id, name, path, description, created_at, updated_at FROM projects ORDER BY updated_at DESC"
-    )?;
-    
-    let project_iter = stmt.query_map([], |row| {
-        Ok(DbProject {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            path: row.get(2)?,
-            description: row.get(3)?,
-            created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?)
-                .map_err(|e| rusqlite::Error::InvalidColumnType(4, "created_at".to_string(), rusqlite::types::Type::Text))?
-                .with_timezone(&Utc),
-            updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(5)?)
-                .map_err(|e| rusqlite::Error::InvalidColumnType(5, "updated_at".to_string(), rusqlite::types::Type::Text))?
-                .with_timezone(&Utc),
-        })
-    })?;
-    
-    let mut projects = Vec::new();
-    for project in project_iter {
-        projects.push(project?);
-    }
-    
-    Ok(projects)
+// 프로젝트 관련 함수들
+/// A table's rows as column → value maps, the form project bundles carry them in.
+pub type TableRows = Vec<serde_json::Map<String, serde_json::Value>>;
+
+/// When a swarm started running and when it ended, each if known.
+pub type RunWindow = (Option<DateTime<Utc>>, Option<DateTime<Utc>>);
+
+const SWARM_OF_PROJECT: &str = "swarm_id IN (SELECT id FROM swarms WHERE project_id = ?1)";
+
+// What belongs to a project, parents before the rows referring to them
+const PROJECT_TABLES: &[(&str, &str)] = &[
+    ("projects", "id = ?1"),
+    ("project_ai_tools", "project_id = ?1"),
+    ("swarms", "project_id = ?1"),
+    ("chat_sessions", "project_id = ?1"),
+    ("chat_messages", "session_id IN (SELECT id FROM chat_sessions WHERE project_id = ?1)"),
+    ("message_attachments", "message_id IN (SELECT id FROM chat_messages WHERE session_id IN (SELECT id FROM chat_sessions WHERE project_id = ?1))"),
+    ("swarm_agents", SWARM_OF_PROJECT),
+    ("swarm_tasks", SWARM_OF_PROJECT),
+    ("task_results", "task_id IN (SELECT id FROM swarm_tasks WHERE swarm_id IN (SELECT id FROM swarms WHERE project_id = ?1))"),
+    ("swarm_workflow_nodes", SWARM_OF_PROJECT),
+    ("swarm_events", SWARM_OF_PROJECT),
+    ("swarm_memory", SWARM_OF_PROJECT),
+];
+
+/// What else goes with a project. Sessions that are kept lose their project; swarms can't exist
+/// without one, so a project that has any can only be deleted with `delete_swarms`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProjectDeleteOptions {
+    // Chat sessions and their messages
+    #[serde(default)]
+    pub delete_sessions: bool,
+    // Swarms with their agents, tasks, results, workflow and events
+    #[serde(default)]
+    pub delete_swarms: bool,
+    // The memory the swarms wrote; kept, it stays searchable through its namespace
+    #[serde(default)]
+    pub delete_memory: bool,
+    // Count what would go without deleting anything
+    #[serde(default)]
+    pub dry_run: bool,
 }
 
-pub fn update_project(project: &DbProject) -> Result<(), anyhow::Error> {
-    let db_conn = DB_CONNECTION.lock().unwrap();
-    let conn = db_conn.as_ref().ok_or_else(|| anyhow!("Database not initialized"))?;
-    
-    conn.execute(
-        "UPDATE projects SET name = ?1, path = ?2, description = ?3, updated_at = ?4 WHERE id = ?5",
-        params![
-            project.name,
-            project.path,
-            project.description,
-            project.updated_at.to_rfc3339(),
-            project.id
-        ],
-    )?;
-    
-    log::info!("Project updated: {}", project.name);
-    Ok(())
+/// Rows removed, or that would be with `dry_run`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProjectDeleteCounts {
+    pub sessions: usize,
+    pub messages: usize,
+    pub detached_sessions: usize,
+    pub swarms: usize,
+    pub agents: usize,
+    pub tasks: usize,
+    pub task_results: usize,
+    pub workflow_nodes: usize,
+    pub events: usize,
+    pub memory_entries: usize,
+    // The swarms deleted, so their workspaces can be cleaned up
+    pub swarm_ids: Vec<String>,
 }
 
-pub fn delete_project(project_id: &str) -> Result<(), anyhow::Error> {
-    let db_conn = DB_CONNECTION.lock().unwrap();
-    let conn = db_conn.as_ref().ok_or_else(|| anyhow!("Database not initialized"))?;
-    
-    conn.execute("DELETE FROM projects WHERE id = ?1", params![project_id])?;
-    
-    log::info!("Project deleted: {}", project_id);
-    Ok(())
-}
+impl Database {
+    pub fn create_project(&self, project: &DbProject) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        insert_project(conn, project)?;
+        
+        log::info!("Project created: {}", project.name);
+        Ok(())
+    }
 
-// 채팅 세션 관련 함수들
-pub fn create_chat_session(session: &DbChatSession) -> Result<(), anyhow::Error> {
-    let db_conn = DB_CONNECTION.lock().unwrap();
-    let conn = db_conn.as_ref().ok_or_else(|| anyhow!("Database not initialized"))?;
-    
-    conn.execute(
-        "INSERT INTO chat_sessions (id, name, project_id, swarm_id, created_at, updated_at) 
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-        params![
-            session.id,
-            session.name,
-            session.project_id,
-            session.swarm_id,
-            session.created_at.to_rfc3339(),
-            session.updated_at.to_rfc3339()
-        ],
-    )?;
-    
-    Ok(())
-}
+    /// Creates all of `projects` or, if any fails, none of them.
+    pub fn create_projects(&self, projects: &[DbProject]) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let tx = conn.unchecked_transaction()?;
+        for project in projects {
+            insert_project(&tx, project)?;
+        }
+        tx.commit()?;
+        
+        log::info!("{} projects created", projects.len());
+        Ok(())
+    }
 
-pub fn get_chat_sessions_by_project(project_id: Option<&str>) -> Result<Vec<DbChatSession>, anyhow::Error> {
-    let db_conn = DB_CONNECTION.lock().unwrap();
-    let conn = db_conn.as_ref().ok_or_else(|| anyhow!("Database not initialized"))?;
-    
-    let mut stmt = if let Some(pid) = project_id {
-        conn.prepare(
-            "SELECT id, name, project_id, swarm_id, created_at, updated_at 
-             FROM chat_sessions WHERE project_id = ? ORDER BY updated_at DESC"
-        )?
-    } else {
-        conn.prepare(
-            "SELECT id, name, project_id, swarm_id, created_at, updated_at 
-             FROM chat_sessions ORDER BY updated_at DESC"
-        )?
-    };
-    
-    let session_iter = if let Some(pid) = project_id {
-        stmt.query_map(params![pid], |row| {
-            Ok(DbChatSession {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                project_id: row.get(2)?,
-                swarm_id: row.get(3)?,
-                created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?)
-                    .map_err(|_| rusqlite::Error::InvalidColumnType(4, "created_at".to_string(), rusqlite::types::Type::Text))?
-                    .with_timezone(&Utc),
-                updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(5)?)
-                    .map_err(|_| rusqlite::Error::InvalidColumnType(5, "updated_at".to_string(), rusqlite::types::Type::Text))?
-                    .with_timezone(&Utc),
+    /// Every row belonging to the project, by table, in the order they can be inserted back.
+    pub fn export_project_data(&self, project_id: &str) -> Result<Vec<(String, TableRows)>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut tables = Vec::new();
+        for (table, condition) in PROJECT_TABLES {
+            let mut stmt = conn.prepare(&format!("SELECT * FROM {} WHERE {} ORDER BY rowid", table, condition))?;
+            let columns: Vec<String> = stmt.column_names().into_iter().map(str::to_string).collect();
+            let rows = stmt.query_map(params![project_id], |row| {
+                let mut values = serde_json::Map::new();
+                for (index, column) in columns.iter().enumerate() {
+                    values.insert(column.clone(), json_from_sql(row.get_ref(index)?));
+                }
+                Ok(values)
+            })?.collect::<Result<TableRows, _>>()?;
+            tables.push((table.to_string(), rows));
+        }
+        
+        Ok(tables)
+    }
+
+    /// Inserts exported rows in one transaction. Only the project tables are accepted, and columns
+    /// this database doesn't have are dropped, so a bundle from an older schema still goes in.
+    pub fn import_project_data(&self, tables: &[(String, TableRows)]) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let tx = conn.unchecked_transaction()?;
+        for (table, rows) in tables {
+            if !PROJECT_TABLES.iter().any(|(known, _)| known == table) {
+                return Err(anyhow!("Unknown table in bundle: {}", table));
+            }
+            let known_columns: Vec<String> = tx.prepare(&format!("SELECT name FROM pragma_table_info('{}')", table))?
+                .query_map([], |row| row.get(0))?
+                .collect::<Result<_, _>>()?;
+            for row in rows {
+                let (columns, values): (Vec<&String>, Vec<rusqlite::types::Value>) = row.iter()
+                    .filter(|(column, _)| known_columns.contains(column))
+                    .map(|(column, value)| (column, sql_from_json(value)))
+                    .unzip();
+                let placeholders = (1..=columns.len()).map(|i| format!("?{}", i)).collect::<Vec<_>>().join(", ");
+                let columns = columns.iter().map(|c| c.as_str()).collect::<Vec<_>>().join(", ");
+                tx.execute(
+                    &format!("INSERT INTO {} ({}) VALUES ({})", table, columns, placeholders),
+                    rusqlite::params_from_iter(values),
+                )?;
+            }
+        }
+        tx.commit()?;
+        
+        Ok(())
+    }
+
+    pub fn get_all_projects(&self, order: ProjectOrder, include_archived: bool) -> Result<Vec<DbProject>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let order_by = match order {
+            ProjectOrder::LastOpened => "archived, last_opened_at IS NULL, last_opened_at DESC, updated_at DESC",
+            ProjectOrder::Updated => "updated_at DESC",
+            ProjectOrder::Name => "name COLLATE NOCASE, id",
+        };
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, name, path, description, settings, created_at, updated_at, last_opened_at, archived FROM projects {} ORDER BY {}",
+            if include_archived { "" } else { "WHERE archived = 0" },
+            order_by
+        ))?;
+        
+        let project_iter = stmt.query_map([], project_from_row)?;
+        
+        let mut projects = Vec::new();
+        for project in project_iter {
+            projects.push(project?);
+        }
+        
+        Ok(projects)
+    }
+
+    pub fn get_project(&self, project_id: &str) -> Result<Option<DbProject>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let project = conn.query_row(
+            "SELECT id, name, path, description, settings, created_at, updated_at, last_opened_at, archived FROM projects WHERE id = ?1",
+            params![project_id],
+            project_from_row,
+        ).optional()?;
+        
+        Ok(project)
+    }
+
+    pub fn save_project_ai_tool(&self, tool: &DbProjectAITool) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute(
+            "INSERT INTO project_ai_tools (project_id, tool_id, enabled, priority, custom_settings, env, updated_at) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7) 
+             ON CONFLICT(project_id, tool_id) DO UPDATE SET enabled = excluded.enabled, priority = excluded.priority, 
+                 custom_settings = excluded.custom_settings, env = excluded.env, updated_at = excluded.updated_at",
+            params![
+                tool.project_id,
+                tool.tool_id,
+                tool.enabled,
+                tool.priority,
+                tool.custom_settings,
+                tool.env,
+                tool.updated_at.to_rfc3339()
+            ],
+        )?;
+        
+        Ok(())
+    }
+
+    pub fn get_project_ai_tool(&self, project_id: &str, tool_id: &str) -> Result<Option<DbProjectAITool>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let tool = conn.query_row(
+            "SELECT project_id, tool_id, enabled, priority, custom_settings, env, updated_at 
+             FROM project_ai_tools WHERE project_id = ?1 AND tool_id = ?2",
+            params![project_id, tool_id],
+            |row| {
+                Ok(DbProjectAITool {
+                    project_id: row.get(0)?,
+                    tool_id: row.get(1)?,
+                    enabled: row.get(2)?,
+                    priority: row.get(3)?,
+                    custom_settings: row.get(4)?,
+                    env: row.get(5)?,
+                    updated_at: parse_timestamp(row.get(6)?, 6, "updated_at")?,
+                })
+            },
+        ).optional()?;
+        
+        Ok(tool)
+    }
+
+    pub fn get_project_ai_tools(&self, project_id: &str) -> Result<Vec<DbProjectAITool>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut stmt = conn.prepare(
+            "SELECT project_id, tool_id, enabled, priority, custom_settings, env, updated_at 
+             FROM project_ai_tools WHERE project_id = ?1 ORDER BY priority, tool_id"
+        )?;
+        let tool_iter = stmt.query_map(params![project_id], |row| {
+            Ok(DbProjectAITool {
+                project_id: row.get(0)?,
+                tool_id: row.get(1)?,
+                enabled: row.get(2)?,
+                priority: row.get(3)?,
+                custom_settings: row.get(4)?,
+                env: row.get(5)?,
+                updated_at: parse_timestamp(row.get(6)?, 6, "updated_at")?,
             })
-        })?
-    } else {
-        stmt.query_map([], |row| {
-            Ok(DbChatSession {
+        })?;
+        
+        let mut tools = Vec::new();
+        for tool in tool_iter {
+            tools.push(tool?);
+        }
+        
+        Ok(tools)
+    }
+
+    /// The project's `limit` most recently active chat sessions. A session without messages was
+    /// last active when it was last updated.
+    pub fn get_project_session_summaries(&self, project_id: &str, limit: usize) -> Result<Vec<DbSessionSummary>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut stmt = conn.prepare(
+            "SELECT s.id, s.project_id, s.name, s.created_at, COALESCE(MAX(m.timestamp), s.updated_at), COUNT(m.id) 
+             FROM chat_sessions s LEFT JOIN chat_messages m ON m.session_id = s.id 
+             WHERE s.project_id = ?1 GROUP BY s.id ORDER BY 5 DESC LIMIT ?2"
+        )?;
+        let summary_iter = stmt.query_map(params![project_id, limit as i64], |row| {
+            Ok(DbSessionSummary {
                 id: row.get(0)?,
-                name: row.get(1)?,
-                project_id: row.get(2)?,
-                swarm_id: row.get(3)?,
-                created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?)
-                    .map_err(|_| rusqlite::Error::InvalidColumnType(4, "created_at".to_string(), rusqlite::types::Type::Text))?
-                    .with_timezone(&Utc),
-                updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(5)?)
-                    .map_err(|_| rusqlite::Error::InvalidColumnType(5, "updated_at".to_string(), rusqlite::types::Type::Text))?
-                    .with_timezone(&Utc),
+                project_id: row.get(1)?,
+                name: row.get(2)?,
+                created_at: parse_timestamp(row.get(3)?, 3, "created_at")?,
+                last_active: parse_timestamp(row.get(4)?, 4, "last_active")?,
+                message_count: row.get(5)?,
             })
-        })?
-    };
-    
-    let mut sessions = Vec::new();
-    for session in session_iter {
-        sessions.push(session?);
+        })?;
+        
+        let mut summaries = Vec::new();
+        for summary in summary_iter {
+            summaries.push(summary?);
+        }
+        
+        Ok(summaries)
+    }
+
+    pub fn count_project_sessions(&self, project_id: &str) -> Result<usize, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM chat_sessions WHERE project_id = ?1",
+            params![project_id],
+            |row| row.get(0),
+        )?;
+        
+        Ok(count as usize)
+    }
+
+    /// Records that the project was opened now; unlike an edit, this leaves `updated_at` alone.
+    pub fn mark_project_opened(&self, project_id: &str) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute(
+            "UPDATE projects SET last_opened_at = ?1 WHERE id = ?2",
+            params![Utc::now().to_rfc3339(), project_id],
+        )?;
+        
+        Ok(())
+    }
+
+    /// Returns whether the project exists.
+    pub fn set_project_archived(&self, project_id: &str, archived: bool) -> Result<bool, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let changed = conn.execute(
+            "UPDATE projects SET archived = ?1 WHERE id = ?2",
+            params![archived, project_id],
+        )?;
+        
+        Ok(changed > 0)
+    }
+
+    /// Changes only the given fields, in one statement. An empty `description` clears it.
+    pub fn update_project_fields(
+        &self,
+        project_id: &str,
+        name: Option<&str>,
+        description: Option<&str>,
+        path: Option<&str>,
+        settings: Option<&str>,
+    ) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute(
+            "UPDATE projects SET name = COALESCE(?1, name), 
+                 description = CASE WHEN ?2 IS NULL THEN description WHEN ?2 = '' THEN NULL ELSE ?2 END, 
+                 path = COALESCE(?3, path), settings = COALESCE(?4, settings), updated_at = ?5 
+             WHERE id = ?6",
+            params![name, description, path, settings, Utc::now().to_rfc3339(), project_id],
+        )?;
+        
+        Ok(())
+    }
+
+    pub fn delete_project(&self, project_id: &str) -> Result<(), anyhow::Error> {
+        self.delete_project_cascade(project_id, &ProjectDeleteOptions::default()).map(|_| ())
+    }
+
+    /// Deletes the project and, as `options` say, what belongs to it, in one transaction. A dry run
+    /// performs the same deletions and rolls them back, so its counts are exact.
+    pub fn delete_project_cascade(&self, project_id: &str, options: &ProjectDeleteOptions) -> Result<ProjectDeleteCounts, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let tx = conn.unchecked_transaction()?;
+        let mut counts = ProjectDeleteCounts {
+            swarm_ids: tx.prepare("SELECT id FROM swarms WHERE project_id = ?1")?
+                .query_map(params![project_id], |row| row.get(0))?
+                .collect::<Result<_, _>>()?,
+            ..Default::default()
+        };
+        if !counts.swarm_ids.is_empty() && !options.delete_swarms {
+            return Err(AppError::Validation(format!(
+                "The project has {} swarms; delete them along with it",
+                counts.swarm_ids.len()
+            )).into());
+        }
+        let running: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM swarms WHERE project_id = ?1 AND status = 'running'",
+            params![project_id],
+            |row| row.get(0),
+        )?;
+        if running > 0 {
+            return Err(AppError::Validation("Stop the project's running swarms before deleting it".to_string()).into());
+        }
+        
+        if options.delete_sessions {
+            tx.execute(
+                "DELETE FROM message_attachments WHERE message_id IN 
+                 (SELECT id FROM chat_messages WHERE session_id IN (SELECT id FROM chat_sessions WHERE project_id = ?1))",
+                params![project_id],
+            )?;
+            counts.messages = tx.execute(
+                "DELETE FROM chat_messages WHERE session_id IN (SELECT id FROM chat_sessions WHERE project_id = ?1)",
+                params![project_id],
+            )?;
+            counts.sessions = tx.execute("DELETE FROM chat_sessions WHERE project_id = ?1", params![project_id])?;
+        } else {
+            counts.detached_sessions = tx.execute(
+                "UPDATE chat_sessions SET project_id = NULL, swarm_id = NULL WHERE project_id = ?1",
+                params![project_id],
+            )?;
+        }
+        
+        let of_swarms = "swarm_id IN (SELECT id FROM swarms WHERE project_id = ?1)";
+        counts.task_results = tx.execute(
+            &format!("DELETE FROM task_results WHERE task_id IN (SELECT id FROM swarm_tasks WHERE {})", of_swarms),
+            params![project_id],
+        )?;
+        counts.tasks = tx.execute(&format!("DELETE FROM swarm_tasks WHERE {}", of_swarms), params![project_id])?;
+        counts.agents = tx.execute(&format!("DELETE FROM swarm_agents WHERE {}", of_swarms), params![project_id])?;
+        counts.workflow_nodes = tx.execute(&format!("DELETE FROM swarm_workflow_nodes WHERE {}", of_swarms), params![project_id])?;
+        counts.events = tx.execute(&format!("DELETE FROM swarm_events WHERE {}", of_swarms), params![project_id])?;
+        if options.delete_memory {
+            counts.memory_entries = tx.execute(&format!("DELETE FROM swarm_memory WHERE {}", of_swarms), params![project_id])?;
+        }
+        // Sessions of other projects may still point at these swarms
+        tx.execute(&format!("UPDATE chat_sessions SET swarm_id = NULL WHERE {}", of_swarms), params![project_id])?;
+        counts.swarms = tx.execute("DELETE FROM swarms WHERE project_id = ?1", params![project_id])?;
+        
+        tx.execute("DELETE FROM project_ai_tools WHERE project_id = ?1", params![project_id])?;
+        tx.execute("DELETE FROM undo_stack WHERE project_id = ?1", params![project_id])?;
+        tx.execute("DELETE FROM projects WHERE id = ?1", params![project_id])?;
+        
+        if options.dry_run {
+            tx.rollback()?;
+        } else {
+            tx.commit()?;
+            log::info!("Project deleted: {}", project_id);
+        }
+        Ok(counts)
     }
-    
-    Ok(sessions)
 }
 
-// 채팅 메시지 관련 함수들
-pub fn create_chat_message(message: &DbChatMessage) -> Result<(), anyhow::Error> {
-    let db_conn = DB_CONNECTION.lock().unwrap();
-    let conn = db_conn.as_ref().ok_or_else(|| anyhow!("Database not initialized"))?;
-    
+fn json_from_sql(value: rusqlite::types::ValueRef) -> serde_json::Value {
+    use rusqlite::types::ValueRef;
+    match value {
+        ValueRef::Null => serde_json::Value::Null,
+        ValueRef::Integer(i) => i.into(),
+        ValueRef::Real(f) => serde_json::Number::from_f64(f).map_or(serde_json::Value::Null, Into::into),
+        ValueRef::Text(text) => String::from_utf8_lossy(text).into_owned().into(),
+        ValueRef::Blob(bytes) => bytes.iter().map(|b| serde_json::Value::from(*b)).collect(),
+    }
+}
+
+fn sql_from_json(value: &serde_json::Value) -> rusqlite::types::Value {
+    use rusqlite::types::Value;
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Integer(*b as i64),
+        serde_json::Value::Number(n) => n.as_i64().map_or_else(|| Value::Real(n.as_f64().unwrap_or_default()), Value::Integer),
+        serde_json::Value::String(s) => Value::Text(s.clone()),
+        // Blobs are exported as byte arrays
+        serde_json::Value::Array(items) => Value::Blob(items.iter().filter_map(|b| b.as_u64()).map(|b| b as u8).collect()),
+        serde_json::Value::Object(_) => Value::Text(value.to_string()),
+    }
+}
+
+fn insert_project(conn: &Connection, project: &DbProject) -> Result<(), anyhow::Error> {
     conn.execute(
-        "INSERT INTO chat_messages (id, session_id, role, content, metadata, timestamp) 
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        "INSERT INTO projects (id, name, path, description, settings, created_at, updated_at) 
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
         params![
-            message.id,
-            message.session_id,
-            message.role,
-            message.content,
-            message.metadata,
-            message.timestamp.to_rfc3339()
+            project.id,
+            project.name,
+            project.path,
+            project.description,
+            project.settings,
+            project.created_at.to_rfc3339(),
+            project.updated_at.to_rfc3339()
         ],
     )?;
-    
     Ok(())
 }
 
-pub fn get_chat_messages(session_id: &str) -> Result<Vec<DbChatMessage>, anyhow::Error> {
-    let db_conn = DB_CONNECTION.lock().unwrap();
-    let conn = db_conn.as_ref().ok_or_else(|| anyhow!("Database not initialized"))?;
-    
-    let mut stmt = conn.prepare(
-        "SELECT id, session_id, role, content, metadata, timestamp 
-         FROM chat_messages WHERE session_id = ? ORDER BY timestamp ASC"
-    )?;
-    
-    let message_iter = stmt.query_map(params![session_id], |row| {
-        Ok(DbChatMessage {
-            id: row.get(0)?,
-            session_id: row.get(1)?,
-            role: row.get(2)?,
-            content: row.get(3)?,
-            metadata: row.get(4)?,
-            timestamp: DateTime::parse_from_rfc3339(&row.get::<_, String>(5)?)
-                .map_err(|_| rusqlite::Error::InvalidColumnType(5, "timestamp".to_string(), rusqlite::types::Type::Text))?
-                .with_timezone(&Utc),
-        })
-    })?;
-    
-    let mut messages = Vec::new();
-    for message in message_iter {
-        messages.push(message?);
-    }
-    
-    Ok(messages)
+fn project_from_row(row: &rusqlite::Row) -> Result<DbProject> {
+    Ok(DbProject {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        path: row.get(2)?,
+        description: row.get(3)?,
+        settings: row.get(4)?,
+        created_at: parse_timestamp(row.get(5)?, 5, "created_at")?,
+        updated_at: parse_timestamp(row.get(6)?, 6, "updated_at")?,
+        last_opened_at: row.get::<_, Option<String>>(7)?
+            .map(|value| parse_timestamp(value, 7, "last_opened_at"))
+            .transpose()?,
+        archived: row.get(8)?,
+    })
 }
 
-// 스웜 관련 함수들
-pub fn create_swarm(swarm: &DbSwarm) -> Result<(), anyhow::Error> {
-    let db_conn = DB_CONNECTION.lock().unwrap();
-    let conn = db_conn.as_ref().ok_or_else(|| anyhow!("Database not initialized"))?;
-    
+fn insert_swarm(conn: &Connection, swarm: &DbSwarm) -> Result<(), anyhow::Error> {
+    ensure_not_archived(conn, &swarm.project_id)?;
     conn.execute(
         "INSERT INTO swarms (id, name, project_id, objective, status, config, created_at, updated_at) 
          VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
@@ -387,93 +1382,2189 @@ pub fn create_swarm(swarm: &DbSwarm) -> Result<(), anyhow::Error> {
             swarm.updated_at.to_rfc3339()
         ],
     )?;
-    
     Ok(())
 }
 
-pub fn get_swarms_by_project(project_id: &str) -> Result<Vec<DbSwarm>, anyhow::Error> {
-    let db_conn = DB_CONNECTION.lock().unwrap();
-    let conn = db_conn.as_ref().ok_or_else(|| anyhow!("Database not initialized"))?;
-    
-    let mut stmt = conn.prepare(
-        "SELECT id, name, project_id, objective, status, config, created_at, updated_at 
-         FROM swarms WHERE project_id = ? ORDER BY updated_at DESC"
-    )?;
-    
-    let swarm_iter = stmt.query_map(params![project_id], |row| {
-        Ok(DbSwarm {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            project_id: row.get(2)?,
-            objective: row.get(3)?,
-            status: row.get(4)?,
-            config: row.get(5)?,
-            created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(6)?)
-                .map_err(|_| rusqlite::Error::InvalidColumnType(6, "created_at".to_string(), rusqlite::types::Type::Text))?
-                .with_timezone(&Utc),
-            updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(7)?)
-                .map_err(|_| rusqlite::Error::InvalidColumnType(7, "updated_at".to_string(), rusqlite::types::Type::Text))?
-                .with_timezone(&Utc),
-        })
-    })?;
-    
-    let mut swarms = Vec::new();
-    for swarm in swarm_iter {
-        swarms.push(swarm?);
-    }
-    
-    Ok(swarms)
-}
-
-// AI 도구 설정 관련 함수들
-pub fn save_ai_tool_config(config: &DbAIToolConfig) -> Result<(), anyhow::Error> {
-    let db_conn = DB_CONNECTION.lock().unwrap();
-    let conn = db_conn.as_ref().ok_or_else(|| anyhow!("Database not initialized"))?;
-    
+fn insert_swarm_agent(conn: &Connection, agent: &DbSwarmAgent) -> Result<(), anyhow::Error> {
     conn.execute(
-        "INSERT OR REPLACE INTO ai_tool_configs (id, tool_name, config, is_connected, created_at, updated_at) 
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        "INSERT INTO swarm_agents (id, swarm_id, agent_type, ai_tool, role, specialization, current_task_id, performance, is_active, created_at) 
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
         params![
-            config.id,
-            config.tool_name,
-            config.config,
-            config.is_connected,
-            config.created_at.to_rfc3339(),
-            config.updated_at.to_rfc3339()
+            agent.id,
+            agent.swarm_id,
+            agent.agent_type,
+            agent.ai_tool,
+            agent.role,
+            agent.specialization,
+            agent.current_task_id,
+            agent.performance,
+            agent.is_active,
+            agent.created_at.to_rfc3339()
         ],
     )?;
-    
     Ok(())
 }
 
-pub fn get_ai_tool_configs() -> Result<Vec<DbAIToolConfig>, anyhow::Error> {
-    let db_conn = DB_CONNECTION.lock().unwrap();
-    let conn = db_conn.as_ref().ok_or_else(|| anyhow!("Database not initialized"))?;
-    
-    let mut stmt = conn.prepare(
-        "SELECT id, tool_name, config, is_connected, created_at, updated_at 
-         FROM ai_tool_configs ORDER BY tool_name"
+// In the given order, which is the order the workflow is read back in
+fn insert_workflow_nodes(conn: &Connection, swarm_id: &str, nodes: &[DbWorkflowNode]) -> Result<(), anyhow::Error> {
+    for (index, node) in nodes.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO swarm_workflow_nodes (id, swarm_id, node_type, name, position_x, position_y, data, connections, status, sort_order) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                node.id,
+                swarm_id,
+                node.node_type,
+                node.name,
+                node.position_x,
+                node.position_y,
+                node.data,
+                node.connections,
+                node.status,
+                index as i64
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Nothing new may be started in an archived project.
+fn ensure_not_archived(conn: &Connection, project_id: &str) -> Result<(), anyhow::Error> {
+    let archived: Option<bool> = conn.query_row(
+        "SELECT archived FROM projects WHERE id = ?1",
+        params![project_id],
+        |row| row.get(0),
+    ).optional()?;
+    if archived == Some(true) {
+        return Err(AppError::Validation("The project is archived; unarchive it first".to_string()).into());
+    }
+    Ok(())
+}
+
+// 채팅 세션 관련 함수들
+// Unread messages are the ones after the read marker that the user didn't write
+const CHAT_SESSION_COLUMNS: &str = "id, name, project_id, swarm_id, system_prompt, pinned, archived, name_source, \
+    last_read_message_at, created_at, updated_at, \
+    (SELECT COUNT(*) FROM chat_messages m WHERE m.session_id = chat_sessions.id AND m.role != 'user' \
+     AND (chat_sessions.last_read_message_at IS NULL OR m.timestamp > chat_sessions.last_read_message_at))";
+
+impl Database {
+    pub fn create_chat_session(&self, session: &DbChatSession) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        if let Some(project_id) = &session.project_id {
+            ensure_not_archived(conn, project_id)?;
+        }
+        conn.execute(
+            "INSERT INTO chat_sessions (id, name, project_id, swarm_id, system_prompt, name_source, created_at, updated_at) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                session.id,
+                session.name,
+                session.project_id,
+                session.swarm_id,
+                session.system_prompt,
+                session.name_source,
+                session.created_at.to_rfc3339(),
+                session.updated_at.to_rfc3339()
+            ],
+        )?;
+        
+        Ok(())
+    }
+
+    /// Sessions newest first, or pinned ones ahead of the rest with `pinned_first`. Archived
+    /// sessions are left out unless `include_archived` is set.
+    pub fn get_chat_sessions_by_project(&self, project_id: Option<&str>, include_archived: bool, pinned_first: bool) -> Result<Vec<DbChatSession>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut conditions = Vec::new();
+        if project_id.is_some() {
+            conditions.push("project_id = ?1");
+        }
+        if !include_archived {
+            conditions.push("archived = 0");
+        }
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM chat_sessions {} ORDER BY {}updated_at DESC",
+            CHAT_SESSION_COLUMNS,
+            where_clause,
+            if pinned_first { "pinned DESC, " } else { "" }
+        ))?;
+        
+        let session_iter = if let Some(pid) = project_id {
+            stmt.query_map(params![pid], chat_session_from_row)?
+        } else {
+            stmt.query_map([], chat_session_from_row)?
+        };
+        
+        let mut sessions = Vec::new();
+        for session in session_iter {
+            sessions.push(session?);
+        }
+        
+        Ok(sessions)
+    }
+
+    pub fn get_chat_session(&self, session_id: &str) -> Result<Option<DbChatSession>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let session = conn.query_row(
+            &format!("SELECT {} FROM chat_sessions WHERE id = ?1", CHAT_SESSION_COLUMNS),
+            params![session_id],
+            chat_session_from_row,
+        ).optional()?;
+        
+        Ok(session)
+    }
+
+    /// Moves the session's read marker to `up_to`, or to its latest message. Returns the number of
+    /// messages still unread after it.
+    pub fn mark_chat_session_read(&self, session_id: &str, up_to: Option<DateTime<Utc>>) -> Result<i64, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let updated = conn.execute(
+            "UPDATE chat_sessions SET last_read_message_at = COALESCE(?2, 
+                (SELECT MAX(timestamp) FROM chat_messages WHERE session_id = ?1), ?3) 
+             WHERE id = ?1",
+            params![session_id, up_to.map(|t| t.to_rfc3339()), Utc::now().to_rfc3339()],
+        )?;
+        if updated == 0 {
+            return Err(AppError::NotFound(format!("Chat session not found: {}", session_id)).into());
+        }
+        
+        unread_count(conn, session_id)
+    }
+
+    pub fn get_chat_session_unread_count(&self, session_id: &str) -> Result<i64, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        unread_count(conn, session_id)
+    }
+
+    pub fn update_chat_session_system_prompt(&self, session_id: &str, system_prompt: Option<&str>) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let updated = conn.execute(
+            "UPDATE chat_sessions SET system_prompt = ?1, updated_at = ?2 WHERE id = ?3",
+            params![system_prompt, Utc::now().to_rfc3339(), session_id],
+        )?;
+        if updated == 0 {
+            return Err(anyhow!("Chat session not found: {}", session_id));
+        }
+        
+        Ok(())
+    }
+
+    /// A name given by the user; generated titles never replace it.
+    pub fn rename_chat_session(&self, session_id: &str, name: &str) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let updated = conn.execute(
+            "UPDATE chat_sessions SET name = ?1, name_source = 'manual', updated_at = ?2 WHERE id = ?3",
+            params![name, Utc::now().to_rfc3339(), session_id],
+        )?;
+        if updated == 0 {
+            return Err(anyhow!("Chat session not found: {}", session_id));
+        }
+        
+        Ok(())
+    }
+
+    /// Sets a generated title unless the user has named the session; returns whether it was set.
+    pub fn set_chat_session_auto_title(&self, session_id: &str, name: &str) -> Result<bool, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let updated = conn.execute(
+            "UPDATE chat_sessions SET name = ?1, name_source = 'auto' WHERE id = ?2 AND name_source != 'manual'",
+            params![name, session_id],
+        )?;
+        
+        Ok(updated > 0)
+    }
+
+    /// A pinned session keeps its messages past the project's retention window.
+    pub fn set_chat_session_pinned(&self, session_id: &str, pinned: bool) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let updated = conn.execute(
+            "UPDATE chat_sessions SET pinned = ?1 WHERE id = ?2",
+            params![pinned, session_id],
+        )?;
+        if updated == 0 {
+            return Err(anyhow!("Chat session not found: {}", session_id));
+        }
+        
+        Ok(())
+    }
+
+    /// An archived session is hidden from the session list but keeps its history.
+    pub fn set_chat_session_archived(&self, session_id: &str, archived: bool) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let updated = conn.execute(
+            "UPDATE chat_sessions SET archived = ?1 WHERE id = ?2",
+            params![archived, session_id],
+        )?;
+        if updated == 0 {
+            return Err(anyhow!("Chat session not found: {}", session_id));
+        }
+        
+        Ok(())
+    }
+}
+
+fn chat_session_from_row(row: &rusqlite::Row) -> Result<DbChatSession> {
+    Ok(DbChatSession {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        project_id: row.get(2)?,
+        swarm_id: row.get(3)?,
+        system_prompt: row.get(4)?,
+        pinned: row.get(5)?,
+        archived: row.get(6)?,
+        name_source: row.get(7)?,
+        last_read_message_at: row.get::<_, Option<String>>(8)?
+            .map(|value| parse_timestamp(value, 8, "last_read_message_at"))
+            .transpose()?,
+        created_at: parse_timestamp(row.get(9)?, 9, "created_at")?,
+        updated_at: parse_timestamp(row.get(10)?, 10, "updated_at")?,
+        unread_count: row.get(11)?,
+        total_tokens: None,
+        estimated_cost: None,
+    })
+}
+
+fn unread_count(conn: &Connection, session_id: &str) -> Result<i64, anyhow::Error> {
+    let count = conn.query_row(
+        "SELECT COUNT(*) FROM chat_messages m JOIN chat_sessions s ON s.id = m.session_id 
+         WHERE m.session_id = ?1 AND m.role != 'user' 
+           AND (s.last_read_message_at IS NULL OR m.timestamp > s.last_read_message_at)",
+        params![session_id],
+        |row| row.get(0),
     )?;
     
-    let config_iter = stmt.query_map([], |row| {
-        Ok(DbAIToolConfig {
-            id: row.get(0)?,
-            tool_name: row.get(1)?,
-            config: row.get(2)?,
-            is_connected: row.get(3)?,
-            created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?)
-                .map_err(|_| rusqlite::Error::InvalidColumnType(4, "created_at".to_string(), rusqlite::types::Type::Text))?
-                .with_timezone(&Utc),
-            updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(5)?)
-                .map_err(|_| rusqlite::Error::InvalidColumnType(5, "updated_at".to_string(), rusqlite::types::Type::Text))?
-                .with_timezone(&Utc),
-        })
-    })?;
+    Ok(count)
+}
+
+// 채팅 메시지 관련 함수들
+impl Database {
+    pub fn create_chat_message(&self, message: &DbChatMessage) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute(
+            "INSERT INTO chat_messages (id, session_id, role, content, metadata, timestamp, prompt_tokens, completion_tokens) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                message.id,
+                message.session_id,
+                message.role,
+                message.content,
+                MessageMetadata::to_column(message.metadata.as_ref())?,
+                message.timestamp.to_rfc3339(),
+                message.prompt_tokens,
+                message.completion_tokens
+            ],
+        )?;
+        
+        Ok(())
+    }
+
+    pub fn get_chat_messages(&self, session_id: &str) -> Result<Vec<DbChatMessage>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut stmt = conn.prepare(
+            "SELECT id, session_id, role, content, metadata, timestamp, prompt_tokens, completion_tokens 
+             FROM chat_messages WHERE session_id = ? ORDER BY timestamp ASC"
+        )?;
+        
+        let message_iter = stmt.query_map(params![session_id], chat_message_from_row)?;
+        
+        let mut messages = Vec::new();
+        for message in message_iter {
+            messages.push(message?);
+        }
+        
+        Ok(messages)
+    }
+
+    /// Up to `limit` of the session's messages in order, starting after the first `offset`.
+    pub fn get_chat_messages_page(&self, session_id: &str, offset: usize, limit: usize) -> Result<Vec<DbChatMessage>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut stmt = conn.prepare(
+            "SELECT id, session_id, role, content, metadata, timestamp, prompt_tokens, completion_tokens 
+             FROM chat_messages WHERE session_id = ?1 ORDER BY timestamp ASC, rowid ASC LIMIT ?2 OFFSET ?3"
+        )?;
+        
+        let messages = stmt.query_map(params![session_id, limit as i64, offset as i64], chat_message_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        
+        Ok(messages)
+    }
+
+    pub fn get_chat_message(&self, message_id: &str) -> Result<Option<DbChatMessage>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let message = conn.query_row(
+            "SELECT id, session_id, role, content, metadata, timestamp, prompt_tokens, completion_tokens 
+             FROM chat_messages WHERE id = ?1",
+            params![message_id],
+            chat_message_from_row,
+        ).optional()?;
+        
+        Ok(message)
+    }
+}
+
+fn chat_message_from_row(row: &rusqlite::Row) -> Result<DbChatMessage> {
+    Ok(DbChatMessage {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        role: row.get(2)?,
+        content: row.get(3)?,
+        metadata: MessageMetadata::from_column(row.get(4)?),
+        timestamp: parse_timestamp(row.get(5)?, 5, "timestamp")?,
+        prompt_tokens: row.get(6)?,
+        completion_tokens: row.get(7)?,
+        attachments: None,
+    })
+}
+
+// 메시지 첨부 파일 관련 함수들
+/// Token counts of a session's messages, grouped by role and by the tool and model that answered.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DbMessageTokenUsage {
+    pub role: String,
+    pub tool_id: Option<String>,
+    pub model: Option<String>,
+    pub messages: i64,
+    // Messages that have counts at all, and those whose counts are estimates
+    pub counted: i64,
+    pub estimated: i64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+}
+
+impl Database {
+    pub fn create_message_attachment(&self, attachment: &DbMessageAttachment) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute(
+            "INSERT INTO message_attachments (id, message_id, path, content_hash, range_start, range_end, snapshot_text, created_at) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                attachment.id,
+                attachment.message_id,
+                attachment.path,
+                attachment.content_hash,
+                attachment.range_start,
+                attachment.range_end,
+                attachment.snapshot_text,
+                attachment.created_at.to_rfc3339()
+            ],
+        )?;
+        
+        Ok(())
+    }
+
+    /// Attachments of every message in the session, in the order they were attached.
+    pub fn get_session_attachments(&self, session_id: &str) -> Result<Vec<DbMessageAttachment>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut stmt = conn.prepare(
+            "SELECT a.id, a.message_id, a.path, a.content_hash, a.range_start, a.range_end, a.snapshot_text, a.created_at 
+             FROM message_attachments a JOIN chat_messages m ON m.id = a.message_id 
+             WHERE m.session_id = ?1 ORDER BY a.created_at ASC, a.rowid ASC"
+        )?;
+        
+        let attachments = stmt.query_map(params![session_id], |row| {
+            Ok(DbMessageAttachment {
+                id: row.get(0)?,
+                message_id: row.get(1)?,
+                path: row.get(2)?,
+                content_hash: row.get(3)?,
+                range_start: row.get(4)?,
+                range_end: row.get(5)?,
+                snapshot_text: row.get(6)?,
+                created_at: parse_timestamp(row.get(7)?, 7, "created_at")?,
+                stale: false,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+        
+        Ok(attachments)
+    }
+
+    /// Stores the token counts and metadata of the given messages in one transaction.
+    pub fn update_chat_message_token_counts(&self, messages: &[DbChatMessage]) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let tx = conn.unchecked_transaction()?;
+        for message in messages {
+            tx.execute(
+                "UPDATE chat_messages SET prompt_tokens = ?1, completion_tokens = ?2, metadata = ?3 WHERE id = ?4",
+                params![
+                    message.prompt_tokens,
+                    message.completion_tokens,
+                    MessageMetadata::to_column(message.metadata.as_ref())?,
+                    message.id
+                ],
+            )?;
+        }
+        tx.commit()?;
+        
+        Ok(())
+    }
+
+    pub fn get_session_token_usage(&self, session_id: &str) -> Result<Vec<DbMessageTokenUsage>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut stmt = conn.prepare(&format!(
+            "SELECT role, {tool_id}, {model}, COUNT(*), COUNT(prompt_tokens), 
+                    COALESCE(SUM(CASE WHEN prompt_tokens IS NOT NULL AND {estimated} THEN 1 ELSE 0 END), 0), 
+                    COALESCE(SUM(prompt_tokens), 0), COALESCE(SUM(completion_tokens), 0) 
+             FROM chat_messages WHERE session_id = ?1 GROUP BY 1, 2, 3",
+            tool_id = metadata_field("tool_id"),
+            model = metadata_field("model"),
+            estimated = metadata_field("estimated"),
+        ))?;
+        
+        let usage = stmt.query_map(params![session_id], |row| {
+            Ok(DbMessageTokenUsage {
+                role: row.get(0)?,
+                tool_id: row.get(1)?,
+                model: row.get(2)?,
+                messages: row.get(3)?,
+                counted: row.get(4)?,
+                estimated: row.get(5)?,
+                prompt_tokens: row.get(6)?,
+                completion_tokens: row.get(7)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+        
+        Ok(usage)
+    }
+
+    pub fn update_chat_message_metadata(&self, message_id: &str, metadata: Option<&MessageMetadata>) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let updated = conn.execute(
+            "UPDATE chat_messages SET metadata = ?1 WHERE id = ?2",
+            params![MessageMetadata::to_column(metadata)?, message_id],
+        )?;
+        if updated == 0 {
+            return Err(anyhow!("Chat message not found: {}", message_id));
+        }
+        
+        Ok(())
+    }
+}
+
+// Metadata is free-form, so only valid JSON is looked into
+fn metadata_field(field: &str) -> String {
+    format!("CASE WHEN json_valid(metadata) THEN json_extract(metadata, '$.{}') END", field)
+}
+
+// 스웜 관련 함수들
+impl Database {
+    pub fn create_swarm(&self, swarm: &DbSwarm) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        insert_swarm(conn, swarm)
+    }
+
+    /// Creates a swarm together with its agents and workflow, all or nothing.
+    pub fn create_swarm_with(&self, swarm: &DbSwarm, agents: &[DbSwarmAgent], nodes: &[DbWorkflowNode]) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let tx = conn.unchecked_transaction()?;
+        insert_swarm(&tx, swarm)?;
+        for agent in agents {
+            insert_swarm_agent(&tx, agent)?;
+        }
+        insert_workflow_nodes(&tx, &swarm.id, nodes)?;
+        tx.commit()?;
+        
+        Ok(())
+    }
+
+    pub fn get_swarms_by_project(&self, project_id: &str) -> Result<Vec<DbSwarm>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut stmt = conn.prepare(
+            "SELECT id, name, project_id, objective, status, config, created_at, updated_at 
+             FROM swarms WHERE project_id = ? ORDER BY updated_at DESC"
+        )?;
+        
+        let swarm_iter = stmt.query_map(params![project_id], |row| {
+            Ok(DbSwarm {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                project_id: row.get(2)?,
+                objective: row.get(3)?,
+                status: row.get(4)?,
+                config: row.get(5)?,
+                created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(6)?)
+                    .map_err(|_| rusqlite::Error::InvalidColumnType(6, "created_at".to_string(), rusqlite::types::Type::Text))?
+                    .with_timezone(&Utc),
+                updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(7)?)
+                    .map_err(|_| rusqlite::Error::InvalidColumnType(7, "updated_at".to_string(), rusqlite::types::Type::Text))?
+                    .with_timezone(&Utc),
+            })
+        })?;
+        
+        let mut swarms = Vec::new();
+        for swarm in swarm_iter {
+            swarms.push(swarm?);
+        }
+        
+        Ok(swarms)
+    }
+
+    pub fn get_all_swarms(&self) -> Result<Vec<DbSwarm>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut stmt = conn.prepare(
+            "SELECT id, name, project_id, objective, status, config, created_at, updated_at 
+             FROM swarms ORDER BY updated_at DESC"
+        )?;
+        
+        let swarm_iter = stmt.query_map([], swarm_from_row)?;
+        
+        let mut swarms = Vec::new();
+        for swarm in swarm_iter {
+            swarms.push(swarm?);
+        }
+        
+        Ok(swarms)
+    }
+
+    pub fn get_swarm_by_id(&self, swarm_id: &str) -> Result<Option<DbSwarm>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let swarm = conn.query_row(
+            "SELECT id, name, project_id, objective, status, config, created_at, updated_at 
+             FROM swarms WHERE id = ?1",
+            params![swarm_id],
+            swarm_from_row,
+        ).optional()?;
+        
+        Ok(swarm)
+    }
+
+    /// Deletes the swarm with its agents, tasks and their results, workflow, events and memory.
+    /// Chat sessions and interaction logs that referred to it are kept.
+    pub fn delete_swarm(&self, swarm_id: &str) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "DELETE FROM task_results WHERE task_id IN (SELECT id FROM swarm_tasks WHERE swarm_id = ?1)",
+            params![swarm_id],
+        )?;
+        for table in ["swarm_tasks", "swarm_agents", "swarm_workflow_nodes", "swarm_events", "swarm_memory"] {
+            tx.execute(&format!("DELETE FROM {} WHERE swarm_id = ?1", table), params![swarm_id])?;
+        }
+        tx.execute("UPDATE chat_sessions SET swarm_id = NULL WHERE swarm_id = ?1", params![swarm_id])?;
+        tx.execute("DELETE FROM swarms WHERE id = ?1", params![swarm_id])?;
+        tx.commit()?;
+        
+        log::info!("Swarm deleted: {}", swarm_id);
+        Ok(())
+    }
+
+    pub fn update_swarm(&self, swarm: &DbSwarm) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute(
+            "UPDATE swarms SET name = ?1, objective = ?2, status = ?3, config = ?4, updated_at = ?5 WHERE id = ?6",
+            params![
+                swarm.name,
+                swarm.objective,
+                swarm.status,
+                swarm.config,
+                swarm.updated_at.to_rfc3339(),
+                swarm.id
+            ],
+        )?;
+        
+        log::info!("Swarm updated: {}", swarm.name);
+        Ok(())
+    }
+
+    /// A pinned swarm keeps its events, memory and interaction log past the project's retention window.
+    pub fn set_swarm_pinned(&self, swarm_id: &str, pinned: bool) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let updated = conn.execute(
+            "UPDATE swarms SET pinned = ?1 WHERE id = ?2",
+            params![pinned, swarm_id],
+        )?;
+        if updated == 0 {
+            return Err(anyhow!("Swarm not found: {}", swarm_id));
+        }
+        
+        Ok(())
+    }
+
+    pub fn update_swarm_status(&self, swarm_id: &str, status: &str) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let updated = conn.execute(
+            "UPDATE swarms SET status = ?1, updated_at = ?2 WHERE id = ?3",
+            params![status, Utc::now().to_rfc3339(), swarm_id],
+        )?;
+        
+        if updated == 0 {
+            return Err(anyhow!("Swarm not found: {}", swarm_id));
+        }
+        
+        Ok(())
+    }
+
+    pub fn set_swarm_snapshot(&self, swarm_id: &str, snapshot: Option<&str>) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute(
+            "UPDATE swarms SET snapshot = ?1 WHERE id = ?2",
+            params![snapshot, swarm_id],
+        )?;
+        
+        Ok(())
+    }
+
+    pub fn get_swarm_snapshot(&self, swarm_id: &str) -> Result<Option<String>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let snapshot = conn.query_row(
+            "SELECT snapshot FROM swarms WHERE id = ?1",
+            params![swarm_id],
+            |row| row.get::<_, Option<String>>(0),
+        ).optional()?;
+        
+        Ok(snapshot.flatten())
+    }
+}
+
+fn swarm_from_row(row: &rusqlite::Row) -> Result<DbSwarm> {
+    Ok(DbSwarm {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        project_id: row.get(2)?,
+        objective: row.get(3)?,
+        status: row.get(4)?,
+        config: row.get(5)?,
+        created_at: parse_timestamp(row.get(6)?, 6, "created_at")?,
+        updated_at: parse_timestamp(row.get(7)?, 7, "updated_at")?,
+    })
+}
+
+fn parse_timestamp(value: String, index: usize, column: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(&value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|_| rusqlite::Error::InvalidColumnType(index, column.to_string(), rusqlite::types::Type::Text))
+}
+
+// 스웜 에이전트 관련 함수들
+impl Database {
+    pub fn create_swarm_agent(&self, agent: &DbSwarmAgent) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        insert_swarm_agent(conn, agent)
+    }
+
+    pub fn get_swarm_agents(&self, swarm_id: &str) -> Result<Vec<DbSwarmAgent>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut stmt = conn.prepare(
+            "SELECT id, swarm_id, agent_type, ai_tool, role, specialization, current_task_id, performance, is_active, created_at 
+             FROM swarm_agents WHERE swarm_id = ? ORDER BY created_at ASC"
+        )?;
+        
+        let agent_iter = stmt.query_map(params![swarm_id], |row| {
+            Ok(DbSwarmAgent {
+                id: row.get(0)?,
+                swarm_id: row.get(1)?,
+                agent_type: row.get(2)?,
+                ai_tool: row.get(3)?,
+                role: row.get(4)?,
+                specialization: row.get(5)?,
+                current_task_id: row.get(6)?,
+                performance: row.get(7)?,
+                is_active: row.get(8)?,
+                created_at: parse_timestamp(row.get(9)?, 9, "created_at")?,
+            })
+        })?;
+        
+        let mut agents = Vec::new();
+        for agent in agent_iter {
+            agents.push(agent?);
+        }
+        
+        Ok(agents)
+    }
+
+    pub fn update_swarm_agent(&self, agent: &DbSwarmAgent) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute(
+            "UPDATE swarm_agents SET ai_tool = ?1, role = ?2, specialization = ?3, current_task_id = ?4, performance = ?5, is_active = ?6 WHERE id = ?7",
+            params![
+                agent.ai_tool,
+                agent.role,
+                agent.specialization,
+                agent.current_task_id,
+                agent.performance,
+                agent.is_active,
+                agent.id
+            ],
+        )?;
+        
+        Ok(())
+    }
+
+    pub fn delete_swarm_agent(&self, swarm_id: &str, agent_id: &str) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute(
+            "DELETE FROM swarm_agents WHERE id = ?1 AND swarm_id = ?2",
+            params![agent_id, swarm_id],
+        )?;
+        
+        Ok(())
+    }
+}
+
+// 스웜 작업 관련 함수들
+impl Database {
+    pub fn save_swarm_task(&self, task: &DbSwarmTask) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute(
+            "INSERT OR REPLACE INTO swarm_tasks (id, swarm_id, title, description, status, priority, assigned_to, dependencies, estimated_duration, actual_duration, created_at, updated_at) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                task.id,
+                task.swarm_id,
+                task.title,
+                task.description,
+                task.status,
+                task.priority,
+                task.assigned_to,
+                task.dependencies,
+                task.estimated_duration,
+                task.actual_duration,
+                task.created_at.to_rfc3339(),
+                task.updated_at.to_rfc3339()
+            ],
+        )?;
+        
+        Ok(())
+    }
+
+    pub fn get_swarm_task(&self, task_id: &str) -> Result<Option<DbSwarmTask>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let task = conn.query_row(
+            "SELECT id, swarm_id, title, description, status, priority, assigned_to, dependencies, estimated_duration, actual_duration, created_at, updated_at 
+             FROM swarm_tasks WHERE id = ?1",
+            params![task_id],
+            swarm_task_from_row,
+        ).optional()?;
+        
+        Ok(task)
+    }
+
+    pub fn get_swarm_tasks(&self, swarm_id: &str) -> Result<Vec<DbSwarmTask>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut stmt = conn.prepare(
+            "SELECT id, swarm_id, title, description, status, priority, assigned_to, dependencies, estimated_duration, actual_duration, created_at, updated_at 
+             FROM swarm_tasks WHERE swarm_id = ? ORDER BY priority DESC, created_at ASC"
+        )?;
+        
+        let task_iter = stmt.query_map(params![swarm_id], swarm_task_from_row)?;
+        
+        let mut tasks = Vec::new();
+        for task in task_iter {
+            tasks.push(task?);
+        }
+        
+        Ok(tasks)
+    }
+
+    pub fn update_swarm_tasks_status(&self, swarm_id: &str, from_statuses: &[&str], status: &str) -> Result<usize, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let now = Utc::now().to_rfc3339();
+        let mut updated = 0;
+        for from in from_statuses {
+            updated += conn.execute(
+                "UPDATE swarm_tasks SET status = ?1, updated_at = ?2 WHERE swarm_id = ?3 AND status = ?4",
+                params![status, now, swarm_id, from],
+            )?;
+        }
+        
+        Ok(updated)
+    }
+
+    pub fn get_swarm_task_stats(&self, swarm_id: &str) -> Result<DbSwarmTaskStats, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let stats = conn.query_row(
+            "SELECT COUNT(*),
+                    COALESCE(SUM(status = 'pending'), 0),
+                    COALESCE(SUM(status = 'in_progress'), 0),
+                    COALESCE(SUM(status = 'completed'), 0),
+                    COALESCE(SUM(status = 'failed'), 0),
+                    COALESCE(SUM(status = 'cancelled'), 0),
+                    AVG(CASE WHEN status = 'completed' THEN actual_duration END),
+                    COALESCE(SUM(actual_duration), 0)
+             FROM swarm_tasks WHERE swarm_id = ?1",
+            params![swarm_id],
+            |row| {
+                Ok(DbSwarmTaskStats {
+                    total: row.get(0)?,
+                    pending: row.get(1)?,
+                    in_progress: row.get(2)?,
+                    completed: row.get(3)?,
+                    failed: row.get(4)?,
+                    cancelled: row.get(5)?,
+                    average_duration: row.get(6)?,
+                    total_duration: row.get(7)?,
+                })
+            },
+        )?;
+        
+        Ok(stats)
+    }
+
+    /// Aggregate-only view for progress polling; never loads task bodies.
+    pub fn get_swarm_progress(&self, swarm_id: &str) -> Result<DbSwarmProgress, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let progress = conn.query_row(
+            "SELECT COUNT(*),
+                    COALESCE(SUM(status = 'pending'), 0),
+                    COALESCE(SUM(status = 'in_progress'), 0),
+                    COALESCE(SUM(status = 'completed'), 0),
+                    COALESCE(SUM(status = 'failed'), 0),
+                    COALESCE(SUM(status = 'cancelled'), 0),
+                    COUNT(estimated_duration),
+                    COALESCE(SUM(estimated_duration), 0),
+                    COALESCE(SUM(CASE WHEN status IN ('completed', 'failed', 'cancelled') THEN estimated_duration END), 0),
+                    COALESCE(SUM(status IN ('completed', 'failed', 'cancelled') AND estimated_duration IS NULL), 0)
+             FROM swarm_tasks WHERE swarm_id = ?1",
+            params![swarm_id],
+            |row| {
+                Ok(DbSwarmProgress {
+                    stats: DbSwarmTaskStats {
+                        total: row.get(0)?,
+                        pending: row.get(1)?,
+                        in_progress: row.get(2)?,
+                        completed: row.get(3)?,
+                        failed: row.get(4)?,
+                        cancelled: row.get(5)?,
+                        ..DbSwarmTaskStats::default()
+                    },
+                    estimated_count: row.get(6)?,
+                    estimated_total: row.get(7)?,
+                    estimated_finished: row.get(8)?,
+                    unestimated_finished: row.get(9)?,
+                })
+            },
+        )?;
+        
+        Ok(progress)
+    }
+
+    pub fn get_running_tasks(&self, swarm_id: &str) -> Result<Vec<DbRunningTask>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut stmt = conn.prepare(
+            "SELECT t.id, t.title, t.assigned_to, a.agent_type, t.updated_at 
+             FROM swarm_tasks t LEFT JOIN swarm_agents a ON a.id = t.assigned_to 
+             WHERE t.swarm_id = ?1 AND t.status = 'in_progress' 
+             ORDER BY t.updated_at ASC"
+        )?;
+        
+        let task_iter = stmt.query_map(params![swarm_id], |row| {
+            Ok(DbRunningTask {
+                task_id: row.get(0)?,
+                title: row.get(1)?,
+                agent_id: row.get(2)?,
+                agent_type: row.get(3)?,
+                started_at: parse_timestamp(row.get(4)?, 4, "updated_at")?,
+            })
+        })?;
+        
+        let mut tasks = Vec::new();
+        for task in task_iter {
+            tasks.push(task?);
+        }
+        
+        Ok(tasks)
+    }
+
+    pub fn create_task_result(&self, result: &DbTaskResult) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute(
+            "INSERT INTO task_results (id, task_id, agent_id, output, confidence, model, prompt_tokens, completion_tokens, timestamp) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                result.id,
+                result.task_id,
+                result.agent_id,
+                result.output,
+                result.confidence,
+                result.model,
+                result.prompt_tokens,
+                result.completion_tokens,
+                result.timestamp.to_rfc3339()
+            ],
+        )?;
+        
+        Ok(())
+    }
+
+    /// Token totals of a swarm's task results, grouped by model.
+    pub fn get_swarm_token_usage(&self, swarm_id: &str) -> Result<Vec<DbTokenUsage>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut stmt = conn.prepare(
+            "SELECT r.model, COALESCE(SUM(r.prompt_tokens), 0), COALESCE(SUM(r.completion_tokens), 0) 
+             FROM task_results r JOIN swarm_tasks t ON t.id = r.task_id 
+             WHERE t.swarm_id = ? GROUP BY r.model"
+        )?;
+        
+        let usage_iter = stmt.query_map(params![swarm_id], |row| {
+            Ok(DbTokenUsage {
+                model: row.get(0)?,
+                prompt_tokens: row.get(1)?,
+                completion_tokens: row.get(2)?,
+            })
+        })?;
+        
+        let mut usage = Vec::new();
+        for row in usage_iter {
+            usage.push(row?);
+        }
+        
+        Ok(usage)
+    }
+}
+
+fn swarm_event_from_row(row: &rusqlite::Row) -> Result<DbSwarmEvent> {
+    Ok(DbSwarmEvent {
+        id: row.get(0)?,
+        swarm_id: row.get(1)?,
+        event_type: row.get(2)?,
+        task_id: row.get(3)?,
+        agent_id: row.get(4)?,
+        payload: row.get(5)?,
+        timestamp: parse_timestamp(row.get(6)?, 6, "timestamp")?,
+    })
+}
+
+fn swarm_task_from_row(row: &rusqlite::Row) -> Result<DbSwarmTask> {
+    Ok(DbSwarmTask {
+        id: row.get(0)?,
+        swarm_id: row.get(1)?,
+        title: row.get(2)?,
+        description: row.get(3)?,
+        status: row.get(4)?,
+        priority: row.get(5)?,
+        assigned_to: row.get(6)?,
+        dependencies: row.get(7)?,
+        estimated_duration: row.get(8)?,
+        actual_duration: row.get(9)?,
+        created_at: parse_timestamp(row.get(10)?, 10, "created_at")?,
+        updated_at: parse_timestamp(row.get(11)?, 11, "updated_at")?,
+    })
+}
+
+// 워크플로우 관련 함수들
+impl Database {
+    pub fn replace_swarm_workflow(&self, swarm_id: &str, nodes: &[DbWorkflowNode]) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let tx = conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM swarm_workflow_nodes WHERE swarm_id = ?1", params![swarm_id])?;
+        insert_workflow_nodes(&tx, swarm_id, nodes)?;
+        tx.execute(
+            "UPDATE swarms SET updated_at = ?1 WHERE id = ?2",
+            params![Utc::now().to_rfc3339(), swarm_id],
+        )?;
+        tx.commit()?;
+        
+        Ok(())
+    }
+
+    pub fn get_swarm_workflow(&self, swarm_id: &str) -> Result<Vec<DbWorkflowNode>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut stmt = conn.prepare(
+            "SELECT id, swarm_id, node_type, name, position_x, position_y, data, connections, status 
+             FROM swarm_workflow_nodes WHERE swarm_id = ? ORDER BY sort_order ASC"
+        )?;
+        
+        let node_iter = stmt.query_map(params![swarm_id], |row| {
+            Ok(DbWorkflowNode {
+                id: row.get(0)?,
+                swarm_id: row.get(1)?,
+                node_type: row.get(2)?,
+                name: row.get(3)?,
+                position_x: row.get(4)?,
+                position_y: row.get(5)?,
+                data: row.get(6)?,
+                connections: row.get(7)?,
+                status: row.get(8)?,
+            })
+        })?;
+        
+        let mut nodes = Vec::new();
+        for node in node_iter {
+            nodes.push(node?);
+        }
+        
+        Ok(nodes)
+    }
+
+    /// Moves the given nodes in one transaction and returns the ids that matched no node.
+    /// The swarm's `updated_at` is bumped once, and only when something moved.
+    pub fn update_workflow_positions(&self, swarm_id: &str, positions: &[(String, f32, f32)]) -> Result<Vec<String>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let tx = conn.unchecked_transaction()?;
+        let mut skipped = Vec::new();
+        {
+            let mut stmt = tx.prepare(
+                "UPDATE swarm_workflow_nodes SET position_x = ?1, position_y = ?2 WHERE swarm_id = ?3 AND id = ?4"
+            )?;
+            for (node_id, x, y) in positions {
+                if stmt.execute(params![x, y, swarm_id, node_id])? == 0 {
+                    skipped.push(node_id.clone());
+                }
+            }
+        }
+        if skipped.len() < positions.len() {
+            tx.execute(
+                "UPDATE swarms SET updated_at = ?1 WHERE id = ?2",
+                params![Utc::now().to_rfc3339(), swarm_id],
+            )?;
+        }
+        tx.commit()?;
+        
+        Ok(skipped)
+    }
+
+    pub fn update_workflow_node_status(&self, swarm_id: &str, node_id: &str, status: &str) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute(
+            "UPDATE swarm_workflow_nodes SET status = ?1 WHERE swarm_id = ?2 AND id = ?3",
+            params![status, swarm_id, node_id],
+        )?;
+        
+        Ok(())
+    }
+}
+
+// 스웜 템플릿 관련 함수들
+impl Database {
+    pub fn save_swarm_template(&self, template: &DbSwarmTemplate) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute(
+            "INSERT INTO swarm_templates (id, name, config, created_at, updated_at) 
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(name) DO UPDATE SET config = excluded.config, updated_at = excluded.updated_at",
+            params![
+                template.id,
+                template.name,
+                template.config,
+                template.created_at.to_rfc3339(),
+                template.updated_at.to_rfc3339()
+            ],
+        )?;
+        
+        log::info!("Swarm template saved: {}", template.name);
+        Ok(())
+    }
+
+    pub fn get_swarm_templates(&self) -> Result<Vec<DbSwarmTemplate>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut stmt = conn.prepare(
+            "SELECT id, name, config, created_at, updated_at FROM swarm_templates ORDER BY name"
+        )?;
+        
+        let template_iter = stmt.query_map([], |row| {
+            Ok(DbSwarmTemplate {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                config: row.get(2)?,
+                created_at: parse_timestamp(row.get(3)?, 3, "created_at")?,
+                updated_at: parse_timestamp(row.get(4)?, 4, "updated_at")?,
+            })
+        })?;
+        
+        let mut templates = Vec::new();
+        for template in template_iter {
+            templates.push(template?);
+        }
+        
+        Ok(templates)
+    }
+}
+
+// 스웜 이벤트 관련 함수들
+impl Database {
+    pub fn create_swarm_event(&self, event: &DbSwarmEvent) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute(
+            "INSERT INTO swarm_events (id, swarm_id, event_type, task_id, agent_id, payload, timestamp) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                event.id,
+                event.swarm_id,
+                event.event_type,
+                event.task_id,
+                event.agent_id,
+                event.payload,
+                event.timestamp.to_rfc3339()
+            ],
+        )?;
+        
+        Ok(())
+    }
+
+    pub fn get_swarm_events(&self, swarm_id: &str, since: Option<DateTime<Utc>>, limit: i64) -> Result<Vec<DbSwarmEvent>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        // rowid breaks ties between events recorded within the same timestamp
+        let mut stmt = conn.prepare(
+            "SELECT id, swarm_id, event_type, task_id, agent_id, payload, timestamp 
+             FROM swarm_events WHERE swarm_id = ?1 AND (?2 IS NULL OR timestamp > ?2) 
+             ORDER BY timestamp ASC, rowid ASC LIMIT ?3"
+        )?;
+        
+        let event_iter = stmt.query_map(
+            params![swarm_id, since.map(|s| s.to_rfc3339()), limit],
+            swarm_event_from_row,
+        )?;
+        
+        let mut events = Vec::new();
+        for event in event_iter {
+            events.push(event?);
+        }
+        
+        Ok(events)
+    }
+
+    /// The newest `limit` events of a swarm, oldest first.
+    pub fn get_latest_swarm_events(&self, swarm_id: &str, limit: i64) -> Result<Vec<DbSwarmEvent>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut stmt = conn.prepare(
+            "SELECT id, swarm_id, event_type, task_id, agent_id, payload, timestamp FROM (
+                SELECT id, swarm_id, event_type, task_id, agent_id, payload, timestamp, rowid AS seq
+                FROM swarm_events WHERE swarm_id = ?1 
+                ORDER BY timestamp DESC, rowid DESC LIMIT ?2
+             ) ORDER BY timestamp ASC, seq ASC"
+        )?;
+        
+        let events = stmt.query_map(params![swarm_id, limit], swarm_event_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        
+        Ok(events)
+    }
+
+    /// When the swarm first started running and, if given, when it last entered one of `end_statuses`.
+    pub fn get_swarm_run_window(&self, swarm_id: &str, end_statuses: &[&str]) -> Result<RunWindow, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let timestamp_of = |sql: &str, to: &str| -> Result<Option<DateTime<Utc>>, anyhow::Error> {
+            let value: Option<String> = conn.query_row(sql, params![swarm_id, to], |row| row.get(0))?;
+            Ok(value
+                .map(|v| DateTime::parse_from_rfc3339(&v).map(|dt| dt.with_timezone(&Utc)))
+                .transpose()?)
+        };
+        
+        let started = timestamp_of(
+            "SELECT MIN(timestamp) FROM swarm_events 
+             WHERE swarm_id = ?1 AND event_type = 'status_changed' AND json_extract(payload, '$.to') = ?2",
+            "running",
+        )?;
+        
+        let mut ended = None;
+        for status in end_statuses {
+            let at = timestamp_of(
+                "SELECT MAX(timestamp) FROM swarm_events 
+                 WHERE swarm_id = ?1 AND event_type = 'status_changed' AND json_extract(payload, '$.to') = ?2",
+                status,
+            )?;
+            ended = ended.max(at);
+        }
+        
+        Ok((started, ended))
+    }
+}
+
+// 스웜 메모리 관련 함수들
+impl Database {
+    pub fn create_memory_entry(&self, entry: &DbMemoryEntry) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute(
+            "INSERT INTO swarm_memory (id, swarm_id, namespace, entry_type, content, metadata, sender_id, recipient_id, importance, timestamp) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                entry.id,
+                entry.swarm_id,
+                entry.namespace,
+                entry.entry_type,
+                entry.content,
+                entry.metadata,
+                entry.sender_id,
+                entry.recipient_id,
+                entry.importance,
+                entry.timestamp.to_rfc3339()
+            ],
+        )?;
+        
+        Ok(())
+    }
+
+    /// Latest entries of a swarm, newest first.
+    pub fn get_swarm_memory(&self, swarm_id: &str, limit: i64) -> Result<Vec<DbMemoryEntry>, anyhow::Error> {
+        self.query_memory_entries(
+            "SELECT id, swarm_id, namespace, entry_type, content, metadata, sender_id, recipient_id, importance, timestamp 
+             FROM swarm_memory WHERE swarm_id = ?1 
+             ORDER BY timestamp DESC, rowid DESC LIMIT ?2",
+            params![swarm_id, limit],
+        )
+    }
+
+    /// Conversation entries addressed to the agent, plus broadcasts from other agents, newest first.
+    pub fn get_agent_messages(&self, swarm_id: &str, agent_id: &str, limit: i64) -> Result<Vec<DbMemoryEntry>, anyhow::Error> {
+        self.query_memory_entries(
+            "SELECT id, swarm_id, namespace, entry_type, content, metadata, sender_id, recipient_id, importance, timestamp 
+             FROM swarm_memory 
+             WHERE swarm_id = ?1 AND entry_type = 'conversation' 
+               AND (recipient_id = ?2 OR (recipient_id IS NULL AND (sender_id IS NULL OR sender_id != ?2))) 
+             ORDER BY timestamp DESC, rowid DESC LIMIT ?3",
+            params![swarm_id, agent_id, limit],
+        )
+    }
+
+    pub fn search_memory(&self, namespace: &str, query: &str, limit: i64) -> Result<Vec<DbMemoryEntry>, anyhow::Error> {
+        self.query_memory_entries(
+            "SELECT id, swarm_id, namespace, entry_type, content, metadata, sender_id, recipient_id, importance, timestamp 
+             FROM swarm_memory WHERE namespace = ?1 AND content LIKE '%' || ?2 || '%' 
+             ORDER BY importance DESC, timestamp DESC LIMIT ?3",
+            params![namespace, query, limit],
+        )
+    }
+
+    fn query_memory_entries(&self, sql: &str, params: &[&dyn rusqlite::ToSql]) -> Result<Vec<DbMemoryEntry>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut stmt = conn.prepare(sql)?;
+        let entry_iter = stmt.query_map(params, |row| {
+            Ok(DbMemoryEntry {
+                id: row.get(0)?,
+                swarm_id: row.get(1)?,
+                namespace: row.get(2)?,
+                entry_type: row.get(3)?,
+                content: row.get(4)?,
+                metadata: row.get(5)?,
+                sender_id: row.get(6)?,
+                recipient_id: row.get(7)?,
+                importance: row.get(8)?,
+                timestamp: parse_timestamp(row.get(9)?, 9, "timestamp")?,
+            })
+        })?;
+        
+        let mut entries = Vec::new();
+        for entry in entry_iter {
+            entries.push(entry?);
+        }
+        
+        Ok(entries)
+    }
+}
+
+// 모델 가격 관련 함수들
+impl Database {
+    pub fn save_model_pricing(&self, pricing: &DbModelPricing) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute(
+            "INSERT OR REPLACE INTO model_pricing (model, prompt_price, completion_price, updated_at) 
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                pricing.model,
+                pricing.prompt_price,
+                pricing.completion_price,
+                pricing.updated_at.to_rfc3339()
+            ],
+        )?;
+        
+        Ok(())
+    }
+
+    pub fn get_model_pricing(&self) -> Result<Vec<DbModelPricing>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut stmt = conn.prepare(
+            "SELECT model, prompt_price, completion_price, updated_at FROM model_pricing ORDER BY model"
+        )?;
+        
+        let pricing_iter = stmt.query_map([], |row| {
+            Ok(DbModelPricing {
+                model: row.get(0)?,
+                prompt_price: row.get(1)?,
+                completion_price: row.get(2)?,
+                updated_at: parse_timestamp(row.get(3)?, 3, "updated_at")?,
+            })
+        })?;
+        
+        let mut pricing = Vec::new();
+        for row in pricing_iter {
+            pricing.push(row?);
+        }
+        
+        Ok(pricing)
+    }
+
+    /// Rewrites the file so deleted values don't linger in free pages.
+    pub fn vacuum_database(&self) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute_batch("VACUUM")?;
+        
+        Ok(())
+    }
+
+    /// Writes a consistent copy of the database to `path`, which must not exist yet.
+    pub fn backup_to(&self, path: &Path) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute("VACUUM INTO ?1", params![path.to_string_lossy()])?;
+        
+        Ok(())
+    }
+    
+    /// Waits for a write in progress to finish, then lets SQLite tidy up its statistics. Every
+    /// write is committed before its call returns, so nothing else is left to flush.
+    pub fn flush(&self) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute_batch("PRAGMA optimize")?;
+        
+        Ok(())
+    }
     
-    let mut configs = Vec::new();
-    for config in config_iter {
-        configs.push(config?);
+    /// The number of migrations applied.
+    pub fn schema_version(&self) -> Result<i64, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        Ok(conn.query_row("PRAGMA user_version", [], |row| row.get(0))?)
     }
     
-    Ok(configs)
-}
\ No newline at end of file
+    /// How many rows each table holds, by table name.
+    pub fn table_row_counts(&self) -> Result<Vec<(String, i64)>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let tables: Vec<String> = conn
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name")?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_>>()?;
+        let mut counts = Vec::with_capacity(tables.len());
+        for table in tables {
+            let count: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM \"{}\"", table), [], |row| row.get(0))?;
+            counts.push((table, count));
+        }
+        
+        Ok(counts)
+    }
+}
+
+// 앱 설정 관련 함수들
+impl Database {
+    pub fn get_setting(&self, key: &str) -> Result<Option<String>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let value = conn.query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            params![key],
+            |row| row.get(0),
+        ).optional()?;
+        
+        Ok(value)
+    }
+
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute(
+            "INSERT OR REPLACE INTO app_settings (key, value, updated_at) VALUES (?1, ?2, ?3)",
+            params![key, value, Utc::now().to_rfc3339()],
+        )?;
+        
+        Ok(())
+    }
+
+    /// Stores all of `values` or, on failure, none of them.
+    pub fn set_settings(&self, values: &[(String, String)]) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let tx = conn.unchecked_transaction()?;
+        let now = Utc::now().to_rfc3339();
+        for (key, value) in values {
+            tx.execute(
+                "INSERT OR REPLACE INTO app_settings (key, value, updated_at) VALUES (?1, ?2, ?3)",
+                params![key, value, now],
+            )?;
+        }
+        tx.commit()?;
+        
+        Ok(())
+    }
+}
+
+// AI 도구 관련 함수들
+impl Database {
+    pub fn save_ai_tool(&self, tool: &DbAITool) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute(
+            "INSERT INTO ai_tools (id, tool_type, name, version, status, config, last_used, last_error, created_at, updated_at) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10) 
+             ON CONFLICT(id) DO UPDATE SET tool_type = excluded.tool_type, name = excluded.name, version = excluded.version, 
+                 status = excluded.status, config = excluded.config, last_used = excluded.last_used, 
+                 last_error = excluded.last_error, updated_at = excluded.updated_at",
+            params![
+                tool.id,
+                tool.tool_type,
+                tool.name,
+                tool.version,
+                tool.status,
+                tool.config,
+                tool.last_used.map(|t| t.to_rfc3339()),
+                tool.last_error,
+                tool.created_at.to_rfc3339(),
+                tool.updated_at.to_rfc3339()
+            ],
+        )?;
+        
+        Ok(())
+    }
+
+    pub fn get_ai_tools(&self) -> Result<Vec<DbAITool>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut stmt = conn.prepare(
+            "SELECT id, tool_type, name, version, status, config, last_used, created_at, updated_at, last_error 
+             FROM ai_tools ORDER BY created_at, name"
+        )?;
+        
+        let tool_iter = stmt.query_map([], ai_tool_from_row)?;
+        
+        let mut tools = Vec::new();
+        for tool in tool_iter {
+            tools.push(tool?);
+        }
+        
+        Ok(tools)
+    }
+
+    pub fn get_ai_tool(&self, tool_id: &str) -> Result<Option<DbAITool>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let tool = conn.query_row(
+            "SELECT id, tool_type, name, version, status, config, last_used, created_at, updated_at, last_error 
+             FROM ai_tools WHERE id = ?1",
+            params![tool_id],
+            ai_tool_from_row,
+        ).optional()?;
+        
+        Ok(tool)
+    }
+
+    /// `last_error` explains an 'error' status; it is cleared by any other update.
+    pub fn update_ai_tool_status(&self, tool_id: &str, status: &str, last_error: Option<&str>) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let updated = conn.execute(
+            "UPDATE ai_tools SET status = ?1, last_error = ?2, updated_at = ?3 WHERE id = ?4",
+            params![status, last_error, Utc::now().to_rfc3339(), tool_id],
+        )?;
+        if updated == 0 {
+            return Err(anyhow!("AI tool not found: {}", tool_id));
+        }
+        
+        Ok(())
+    }
+
+    pub fn touch_ai_tool(&self, tool_id: &str) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute(
+            "UPDATE ai_tools SET last_used = ?1 WHERE id = ?2",
+            params![Utc::now().to_rfc3339(), tool_id],
+        )?;
+        
+        Ok(())
+    }
+}
+
+fn ai_tool_from_row(row: &rusqlite::Row) -> Result<DbAITool> {
+    Ok(DbAITool {
+        id: row.get(0)?,
+        tool_type: row.get(1)?,
+        name: row.get(2)?,
+        version: row.get(3)?,
+        status: row.get(4)?,
+        config: row.get(5)?,
+        last_used: row.get::<_, Option<String>>(6)?
+            .map(|v| parse_timestamp(v, 6, "last_used"))
+            .transpose()?,
+        created_at: parse_timestamp(row.get(7)?, 7, "created_at")?,
+        updated_at: parse_timestamp(row.get(8)?, 8, "updated_at")?,
+        last_error: row.get(9)?,
+    })
+}
+
+// AI 명령 기록 관련 함수들
+impl Database {
+    pub fn create_ai_interaction(&self, interaction: &DbAIInteraction) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute(
+            "INSERT INTO ai_interactions (id, tool_id, command_id, command_type, payload, response, error, success, 
+                 model, prompt_tokens, completion_tokens, tokens_estimated, latency_ms, session_id, swarm_id, task_id, broadcast_id, created_at) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+            params![
+                interaction.id,
+                interaction.tool_id,
+                interaction.command_id,
+                interaction.command_type,
+                interaction.payload,
+                interaction.response,
+                interaction.error,
+                interaction.success,
+                interaction.model,
+                interaction.prompt_tokens,
+                interaction.completion_tokens,
+                interaction.tokens_estimated,
+                interaction.latency_ms,
+                interaction.session_id,
+                interaction.swarm_id,
+                interaction.task_id,
+                interaction.broadcast_id,
+                interaction.created_at.to_rfc3339()
+            ],
+        )?;
+        
+        Ok(())
+    }
+
+    /// Newest first; every filter is optional.
+    #[allow(clippy::too_many_arguments)]
+    pub fn get_ai_interactions(
+        &self,
+        tool_id: Option<&str>,
+        session_id: Option<&str>,
+        swarm_id: Option<&str>,
+        broadcast_id: Option<&str>,
+        since: Option<DateTime<Utc>>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<DbAIInteraction>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut stmt = conn.prepare(
+            "SELECT id, tool_id, command_id, command_type, payload, response, error, success, model, 
+                 prompt_tokens, completion_tokens, tokens_estimated, latency_ms, session_id, swarm_id, task_id, broadcast_id, created_at 
+             FROM ai_interactions 
+             WHERE (?1 IS NULL OR tool_id = ?1) AND (?2 IS NULL OR session_id = ?2) 
+                 AND (?3 IS NULL OR swarm_id = ?3) AND (?4 IS NULL OR broadcast_id = ?4) 
+                 AND (?5 IS NULL OR created_at >= ?5) 
+             ORDER BY created_at DESC, rowid DESC LIMIT ?6 OFFSET ?7"
+        )?;
+        
+        let interaction_iter = stmt.query_map(
+            params![tool_id, session_id, swarm_id, broadcast_id, since.map(|s| s.to_rfc3339()), limit, offset],
+            |row| {
+                Ok(DbAIInteraction {
+                    id: row.get(0)?,
+                    tool_id: row.get(1)?,
+                    command_id: row.get(2)?,
+                    command_type: row.get(3)?,
+                    payload: row.get(4)?,
+                    response: row.get(5)?,
+                    error: row.get(6)?,
+                    success: row.get(7)?,
+                    model: row.get(8)?,
+                    prompt_tokens: row.get(9)?,
+                    completion_tokens: row.get(10)?,
+                    tokens_estimated: row.get(11)?,
+                    latency_ms: row.get(12)?,
+                    session_id: row.get(13)?,
+                    swarm_id: row.get(14)?,
+                    task_id: row.get(15)?,
+                    broadcast_id: row.get(16)?,
+                    created_at: parse_timestamp(row.get(17)?, 17, "created_at")?,
+                })
+            },
+        )?;
+        
+        let mut interactions = Vec::new();
+        for interaction in interaction_iter {
+            interactions.push(interaction?);
+        }
+        
+        Ok(interactions)
+    }
+
+    /// Totals per UTC day, tool and model for `since <= created_at < until`, ordered by day.
+    pub fn get_daily_tool_usage(&self, tool_id: Option<&str>, since: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<DbDailyToolUsage>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        // created_at is stored as UTC RFC 3339, so its first ten characters are the UTC date
+        let mut stmt = conn.prepare(
+            "SELECT substr(created_at, 1, 10) AS day, tool_id, model, COUNT(*), 
+                 SUM(CASE WHEN success THEN 0 ELSE 1 END), COALESCE(SUM(prompt_tokens), 0), 
+                 COALESCE(SUM(completion_tokens), 0), SUM(tokens_estimated), COALESCE(SUM(latency_ms), 0) 
+             FROM ai_interactions 
+             WHERE (?1 IS NULL OR tool_id = ?1) AND created_at >= ?2 AND created_at < ?3 
+             GROUP BY day, tool_id, model 
+             ORDER BY day ASC, tool_id ASC"
+        )?;
+        
+        let usage_iter = stmt.query_map(params![tool_id, since.to_rfc3339(), until.to_rfc3339()], |row| {
+            Ok(DbDailyToolUsage {
+                day: row.get(0)?,
+                tool_id: row.get(1)?,
+                model: row.get(2)?,
+                calls: row.get(3)?,
+                errors: row.get(4)?,
+                prompt_tokens: row.get(5)?,
+                completion_tokens: row.get(6)?,
+                estimated_calls: row.get(7)?,
+                total_latency_ms: row.get(8)?,
+            })
+        })?;
+        
+        let mut usage = Vec::new();
+        for row in usage_iter {
+            usage.push(row?);
+        }
+        
+        Ok(usage)
+    }
+
+    pub fn purge_ai_interactions(&self, older_than: DateTime<Utc>) -> Result<usize, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let deleted = conn.execute(
+            "DELETE FROM ai_interactions WHERE created_at < ?1",
+            params![older_than.to_rfc3339()],
+        )?;
+        
+        Ok(deleted)
+    }
+}
+
+// AI 도구 설정 관련 함수들
+impl Database {
+    pub fn save_ai_tool_config(&self, config: &DbAIToolConfig) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute(
+            "INSERT OR REPLACE INTO ai_tool_configs (id, tool_id, tool_name, config, is_connected, created_at, updated_at) 
+             VALUES (?1, COALESCE(?2, (SELECT id FROM ai_tools WHERE tool_type = ?3 ORDER BY created_at LIMIT 1)), ?3, ?4, ?5, ?6, ?7)",
+            params![
+                config.id,
+                config.tool_id,
+                config.tool_name,
+                config.config,
+                config.is_connected,
+                config.created_at.to_rfc3339(),
+                config.updated_at.to_rfc3339()
+            ],
+        )?;
+        
+        Ok(())
+    }
+
+    pub fn get_ai_tool_configs(&self) -> Result<Vec<DbAIToolConfig>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut stmt = conn.prepare(
+            "SELECT id, tool_id, tool_name, config, is_connected, created_at, updated_at 
+             FROM ai_tool_configs ORDER BY tool_name"
+        )?;
+        
+        let config_iter = stmt.query_map([], |row| {
+            Ok(DbAIToolConfig {
+                id: row.get(0)?,
+                tool_id: row.get(1)?,
+                tool_name: row.get(2)?,
+                config: row.get(3)?,
+                is_connected: row.get(4)?,
+                has_api_key: false,
+                created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(5)?)
+                    .map_err(|_| rusqlite::Error::InvalidColumnType(5, "created_at".to_string(), rusqlite::types::Type::Text))?
+                    .with_timezone(&Utc),
+                updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(6)?)
+                    .map_err(|_| rusqlite::Error::InvalidColumnType(6, "updated_at".to_string(), rusqlite::types::Type::Text))?
+                    .with_timezone(&Utc),
+            })
+        })?;
+        
+        let mut configs = Vec::new();
+        for config in config_iter {
+            configs.push(config?);
+        }
+        
+        Ok(configs)
+    }
+}
+
+// 파일 작업 기록 관련 함수들
+impl Database {
+    pub fn record_file_operation(&self, operation: &DbFileOperation) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute(
+            "INSERT INTO file_operations (id, operation, path, project_id, permanent, created_at) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                operation.id,
+                operation.operation,
+                operation.path,
+                operation.project_id,
+                operation.permanent,
+                operation.created_at.to_rfc3339()
+            ],
+        )?;
+        
+        Ok(())
+    }
+
+    /// Pushes `entry` onto its project's undo stack, dropping the oldest entries past `max_depth`.
+    pub fn push_undo_entry(&self, entry: &DbUndoEntry, max_depth: i64) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO undo_stack (id, project_id, operation, path, previous_path, previous_hash, result_hash, backup_version, previous_size, trash_id, created_at) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                entry.id,
+                entry.project_id,
+                entry.operation,
+                entry.path,
+                entry.previous_path,
+                entry.previous_hash,
+                entry.result_hash,
+                entry.backup_version,
+                entry.previous_size,
+                entry.trash_id,
+                entry.created_at.to_rfc3339()
+            ],
+        )?;
+        tx.execute(
+            "DELETE FROM undo_stack WHERE project_id = ?1 AND rowid NOT IN (
+                SELECT rowid FROM undo_stack WHERE project_id = ?1 ORDER BY created_at DESC, rowid DESC LIMIT ?2
+             )",
+            params![entry.project_id, max_depth],
+        )?;
+        tx.commit()?;
+        
+        Ok(())
+    }
+
+    /// A project's undo stack, newest first.
+    pub fn get_undo_entries(&self, project_id: &str, limit: i64) -> Result<Vec<DbUndoEntry>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut stmt = conn.prepare(
+            "SELECT id, project_id, operation, path, previous_path, previous_hash, result_hash, backup_version, previous_size, trash_id, created_at 
+             FROM undo_stack WHERE project_id = ?1 ORDER BY created_at DESC, rowid DESC LIMIT ?2"
+        )?;
+        
+        let entry_iter = stmt.query_map(params![project_id, limit], |row| {
+            Ok(DbUndoEntry {
+                id: row.get(0)?,
+                project_id: row.get(1)?,
+                operation: row.get(2)?,
+                path: row.get(3)?,
+                previous_path: row.get(4)?,
+                previous_hash: row.get(5)?,
+                result_hash: row.get(6)?,
+                backup_version: row.get(7)?,
+                previous_size: row.get(8)?,
+                trash_id: row.get(9)?,
+                created_at: parse_timestamp(row.get(10)?, 10, "created_at")?,
+            })
+        })?;
+        
+        let mut entries = Vec::new();
+        for entry in entry_iter {
+            entries.push(entry?);
+        }
+        
+        Ok(entries)
+    }
+
+    pub fn delete_undo_entry(&self, entry_id: &str) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute("DELETE FROM undo_stack WHERE id = ?1", params![entry_id])?;
+        
+        Ok(())
+    }
+
+    /// The latest operations of one kind, newest first.
+    pub fn get_recent_file_operations(&self, operation: &str, limit: i64) -> Result<Vec<DbFileOperation>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut stmt = conn.prepare(
+            "SELECT id, operation, path, project_id, permanent, created_at 
+             FROM file_operations WHERE operation = ?1 ORDER BY created_at DESC, rowid DESC LIMIT ?2"
+        )?;
+        
+        let operation_iter = stmt.query_map(params![operation, limit], |row| {
+            Ok(DbFileOperation {
+                id: row.get(0)?,
+                operation: row.get(1)?,
+                path: row.get(2)?,
+                project_id: row.get(3)?,
+                permanent: row.get(4)?,
+                created_at: parse_timestamp(row.get(5)?, 5, "created_at")?,
+            })
+        })?;
+        
+        let mut operations = Vec::new();
+        for operation in operation_iter {
+            operations.push(operation?);
+        }
+        
+        Ok(operations)
+    }
+}
+
+// 활동 피드 관련 함수들
+// Each source is cut to `limit` rows before the union, so no table is read further back than
+// the page needs. Completed tasks come from task_results, so their swarm event is left out.
+const ACTIVITY_SQL: &str = "
+    SELECT * FROM (
+        SELECT 'chat_message', m.id, m.timestamp, s.project_id, p.name, m.session_id, s.name,
+               s.swarm_id, w.name, NULL, m.role, substr(m.content, 1, ?3)
+        FROM chat_messages m
+        JOIN chat_sessions s ON s.id = m.session_id
+        LEFT JOIN projects p ON p.id = s.project_id
+        LEFT JOIN swarms w ON w.id = s.swarm_id
+        WHERE ?1 IS NULL OR m.timestamp < ?1
+        ORDER BY m.timestamp DESC LIMIT ?2
+    )
+    UNION ALL
+    SELECT * FROM (
+        SELECT 'swarm_event', e.id, e.timestamp, w.project_id, p.name, NULL, NULL,
+               e.swarm_id, w.name, e.task_id, e.event_type, t.title
+        FROM swarm_events e
+        JOIN swarms w ON w.id = e.swarm_id
+        LEFT JOIN projects p ON p.id = w.project_id
+        LEFT JOIN swarm_tasks t ON t.id = e.task_id
+        WHERE e.event_type != 'task_completed' AND (?1 IS NULL OR e.timestamp < ?1)
+        ORDER BY e.timestamp DESC LIMIT ?2
+    )
+    UNION ALL
+    SELECT * FROM (
+        SELECT 'task_completed', r.id, r.timestamp, w.project_id, p.name, NULL, NULL,
+               t.swarm_id, w.name, r.task_id, r.agent_id, t.title
+        FROM task_results r
+        JOIN swarm_tasks t ON t.id = r.task_id
+        JOIN swarms w ON w.id = t.swarm_id
+        LEFT JOIN projects p ON p.id = w.project_id
+        WHERE ?1 IS NULL OR r.timestamp < ?1
+        ORDER BY r.timestamp DESC LIMIT ?2
+    )
+    UNION ALL
+    SELECT * FROM (
+        SELECT 'file_operation', f.id, f.created_at, f.project_id, p.name, NULL, NULL,
+               NULL, NULL, NULL, f.operation, f.path
+        FROM file_operations f
+        LEFT JOIN projects p ON p.id = f.project_id
+        WHERE ?1 IS NULL OR f.created_at < ?1
+        ORDER BY f.created_at DESC LIMIT ?2
+    )
+    ORDER BY 3 DESC, 2 DESC LIMIT ?2";
+
+impl Database {
+    /// Chat messages, swarm events, completed tasks and file operations from every project, newest
+    /// first, all older than `before` when it is given. Message text is cut to `summary_chars`.
+    pub fn get_recent_activity(&self, before: Option<DateTime<Utc>>, limit: i64, summary_chars: i64) -> Result<Vec<DbActivityItem>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut stmt = conn.prepare(ACTIVITY_SQL)?;
+        let item_iter = stmt.query_map(params![before.map(|b| b.to_rfc3339()), limit, summary_chars], |row| {
+            Ok(DbActivityItem {
+                kind: row.get(0)?,
+                id: row.get(1)?,
+                timestamp: parse_timestamp(row.get(2)?, 2, "timestamp")?,
+                project_id: row.get(3)?,
+                project_name: row.get(4)?,
+                session_id: row.get(5)?,
+                session_name: row.get(6)?,
+                swarm_id: row.get(7)?,
+                swarm_name: row.get(8)?,
+                task_id: row.get(9)?,
+                detail: row.get(10)?,
+                summary: row.get(11)?,
+            })
+        })?;
+        
+        let mut items = Vec::new();
+        for item in item_iter {
+            items.push(item?);
+        }
+        
+        Ok(items)
+    }
+}
+
+// 보존 기간 정리 관련 함수들
+const UNPINNED_SESSION_OF_PROJECT: &str = "SELECT id FROM chat_sessions WHERE project_id = ?1 AND NOT pinned";
+
+const UNPINNED_SWARM_OF_PROJECT: &str = "SELECT id FROM swarms WHERE project_id = ?1 AND NOT pinned";
+
+// Rows deleted per transaction, so the lock is released between batches
+const RETENTION_BATCH_SIZE: usize = 500;
+
+/// Rows removed by a retention cleanup, by table.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RetentionCounts {
+    pub chat_messages: usize,
+    pub message_attachments: usize,
+    pub ai_interactions: usize,
+    pub swarm_events: usize,
+    pub swarm_memory: usize,
+}
+
+impl RetentionCounts {
+    pub fn add(&mut self, other: &RetentionCounts) {
+        self.chat_messages += other.chat_messages;
+        self.message_attachments += other.message_attachments;
+        self.ai_interactions += other.ai_interactions;
+        self.swarm_events += other.swarm_events;
+        self.swarm_memory += other.swarm_memory;
+    }
+}
+
+impl Database {
+    /// Deletes the project's messages, interaction log, swarm events and memory entries older than
+    /// `cutoff`, leaving pinned sessions and swarms alone. Rows go in batches, each in its own
+    /// transaction, so other writers aren't held up for the length of the whole cleanup.
+    pub fn prune_project_data(&self, project_id: &str, cutoff: DateTime<Utc>) -> Result<RetentionCounts, anyhow::Error> {
+        let cutoff = cutoff.to_rfc3339();
+        let interactions = format!(
+            "(session_id IN ({}) OR swarm_id IN ({})) AND created_at < ?2",
+            UNPINNED_SESSION_OF_PROJECT, UNPINNED_SWARM_OF_PROJECT
+        );
+        Ok(RetentionCounts {
+            // Before the messages they belong to
+            message_attachments: self.delete_in_batches(
+                "message_attachments",
+                &format!(
+                    "message_id IN (SELECT id FROM chat_messages WHERE session_id IN ({}) AND timestamp < ?2)",
+                    UNPINNED_SESSION_OF_PROJECT
+                ),
+                project_id,
+                &cutoff,
+            )?,
+            chat_messages: self.delete_in_batches(
+                "chat_messages",
+                &format!("session_id IN ({}) AND timestamp < ?2", UNPINNED_SESSION_OF_PROJECT),
+                project_id,
+                &cutoff,
+            )?,
+            ai_interactions: self.delete_in_batches("ai_interactions", &interactions, project_id, &cutoff)?,
+            swarm_events: self.delete_in_batches(
+                "swarm_events",
+                &format!("swarm_id IN ({}) AND timestamp < ?2", UNPINNED_SWARM_OF_PROJECT),
+                project_id,
+                &cutoff,
+            )?,
+            swarm_memory: self.delete_in_batches(
+                "swarm_memory",
+                &format!("swarm_id IN ({}) AND timestamp < ?2", UNPINNED_SWARM_OF_PROJECT),
+                project_id,
+                &cutoff,
+            )?,
+        })
+    }
+
+    fn delete_in_batches(&self, table: &str, condition: &str, project_id: &str, cutoff: &str) -> Result<usize, anyhow::Error> {
+        let sql = format!(
+            "DELETE FROM {table} WHERE rowid IN (SELECT rowid FROM {table} WHERE {condition} LIMIT {RETENTION_BATCH_SIZE})"
+        );
+        let mut total = 0;
+        loop {
+            // The connection is locked per batch only
+            let deleted = {
+                let db_conn = self.conn.lock().unwrap();
+                let conn: &Connection = &db_conn;
+                let tx = conn.unchecked_transaction()?;
+                let deleted = tx.execute(&sql, params![project_id, cutoff])?;
+                tx.commit()?;
+                deleted
+            };
+            total += deleted;
+            if deleted < RETENTION_BATCH_SIZE {
+                return Ok(total);
+            }
+        }
+    }
+}
+
+// 환경 변수 관련 함수들
+impl Database {
+    pub fn save_env_var(&self, env_var: &DbEnvVar) -> Result<(), anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        conn.execute(
+            "INSERT OR REPLACE INTO env_vars (key, value, secret, updated_at) VALUES (?1, ?2, ?3, ?4)",
+            params![env_var.key, env_var.value, env_var.secret, env_var.updated_at.to_rfc3339()],
+        )?;
+        
+        Ok(())
+    }
+
+    /// Returns whether the variable existed.
+    pub fn delete_env_var(&self, key: &str) -> Result<bool, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let deleted = conn.execute("DELETE FROM env_vars WHERE key = ?1", params![key])?;
+        Ok(deleted > 0)
+    }
+
+    pub fn get_env_vars(&self) -> Result<Vec<DbEnvVar>, anyhow::Error> {
+        let db_conn = self.conn.lock().unwrap();
+        let conn: &Connection = &db_conn;
+        
+        let mut stmt = conn.prepare("SELECT key, value, secret, updated_at FROM env_vars ORDER BY key")?;
+        let env_var_iter = stmt.query_map([], |row| {
+            Ok(DbEnvVar {
+                key: row.get(0)?,
+                value: row.get(1)?,
+                secret: row.get(2)?,
+                updated_at: parse_timestamp(row.get(3)?, 3, "updated_at")?,
+            })
+        })?;
+        
+        let mut env_vars = Vec::new();
+        for env_var in env_var_iter {
+            env_vars.push(env_var?);
+        }
+        
+        Ok(env_vars)
+    }
+}
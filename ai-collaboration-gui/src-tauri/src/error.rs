@@ -0,0 +1,139 @@
+use serde::{Serialize, Serializer};
+use serde::ser::SerializeStruct;
+use thiserror::Error;
+
+/// Errors returned to the frontend as `{ kind, message }` so the UI can react to the kind;
+/// `binary_file` errors also carry the file's `size`, `permission_denied` ones for a path outside
+/// the allowed roots the `path` (those for an operation the user didn't approve have none), and
+/// `conflict` ones about a file the `current_hash` of the file that changed underneath the caller.
+#[derive(Debug, Error)]
+pub enum AppError {
+    #[error("AI tool unavailable: {0}")]
+    ToolUnavailable(String),
+    #[error("Timed out: {0}")]
+    Timeout(String),
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
+    #[error("Invalid API key: {0}")]
+    InvalidApiKey(String),
+    #[error("Rate limited: {message}")]
+    RateLimited { message: String, retry_after_secs: Option<u64> },
+    #[error("Provider overloaded: {0}")]
+    Overloaded(String),
+    #[error("Provider server error: {0}")]
+    ServerError(String),
+    #[error("Blocked by the provider: {0}")]
+    ContentBlocked(String),
+    #[error("Provider error: {0}")]
+    Provider(String),
+    #[error("Network error: {0}")]
+    Network(String),
+    #[error("Proxy error: {0}")]
+    Proxy(String),
+    #[error("Binary file ({size} bytes); open it in an external application instead")]
+    BinaryFile { size: u64 },
+    #[error("{0} is outside the project; access needs the user's permission")]
+    PermissionDenied(String),
+    #[error("Not approved: {0}")]
+    NotApproved(String),
+    #[error("Could not move to the trash: {0}")]
+    TrashUnavailable(String),
+    #[error("git is not installed or not on the PATH")]
+    GitNotInstalled,
+    #[error("git failed: {0}")]
+    Git(String),
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
+    #[error("Clipboard unavailable: {0}")]
+    Clipboard(String),
+    #[error("{0}")]
+    Validation(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{message}")]
+    Conflict { message: String, current_hash: Option<String> },
+    #[error("The app is shutting down")]
+    ShuttingDown,
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl AppError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::ToolUnavailable(_) => "tool_unavailable",
+            AppError::Timeout(_) => "timeout",
+            AppError::Cancelled(_) => "cancelled",
+            AppError::InvalidApiKey(_) => "invalid_api_key",
+            AppError::RateLimited { .. } => "rate_limited",
+            AppError::Overloaded(_) => "overloaded",
+            AppError::ServerError(_) => "server_error",
+            AppError::ContentBlocked(_) => "content_blocked",
+            AppError::Provider(_) => "provider_error",
+            AppError::Network(_) => "network_error",
+            AppError::Proxy(_) => "proxy_error",
+            AppError::BinaryFile { .. } => "binary_file",
+            AppError::PermissionDenied(_) | AppError::NotApproved(_) => "permission_denied",
+            AppError::TrashUnavailable(_) => "trash_unavailable",
+            AppError::GitNotInstalled => "git_not_installed",
+            AppError::Git(_) => "git_error",
+            AppError::QuotaExceeded(_) => "quota_exceeded",
+            AppError::Clipboard(_) => "clipboard_error",
+            AppError::Validation(_) => "validation_error",
+            AppError::NotFound(_) => "not_found",
+            AppError::Conflict { .. } => "conflict",
+            AppError::ShuttingDown => "shutting_down",
+            AppError::Internal(_) => "internal",
+        }
+    }
+
+    /// Whether the tool itself failed, so another tool may serve the same request. Problems
+    /// with the request (blocked content, bad key, provider-side validation) would fail anywhere.
+    pub fn allows_fallback(&self) -> bool {
+        matches!(
+            self,
+            AppError::ToolUnavailable(_)
+                | AppError::Timeout(_)
+                | AppError::Network(_)
+                | AppError::Overloaded(_)
+                | AppError::ServerError(_)
+        )
+    }
+}
+
+impl From<anyhow::Error> for AppError {
+    fn from(error: anyhow::Error) -> Self {
+        // Keep a typed error that was wrapped on its way up
+        match error.downcast::<AppError>() {
+            Ok(app_error) => app_error,
+            Err(error) => AppError::Internal(error.to_string()),
+        }
+    }
+}
+
+/// The `AppError` inside `e` if there is one, so its kind reaches the frontend, otherwise an
+/// internal error saying what failed.
+pub fn typed_or(context: &str, e: anyhow::Error) -> AppError {
+    match e.downcast::<AppError>() {
+        Ok(app_error) => app_error,
+        Err(e) => AppError::Internal(format!("{}: {}", context, e)),
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let detail: Option<(&'static str, serde_json::Value)> = match self {
+            AppError::BinaryFile { size } => Some(("size", (*size).into())),
+            AppError::PermissionDenied(path) => Some(("path", path.clone().into())),
+            AppError::Conflict { current_hash: Some(hash), .. } => Some(("current_hash", hash.clone().into())),
+            _ => None,
+        };
+        let mut state = serializer.serialize_struct("AppError", if detail.is_some() { 3 } else { 2 })?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        if let Some((name, value)) = detail {
+            state.serialize_field(name, &value)?;
+        }
+        state.end()
+    }
+}
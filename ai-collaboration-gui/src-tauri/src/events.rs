@@ -0,0 +1,79 @@
+//! `data:changed`, emitted by every command that changes stored data or files, once the change
+//! has been committed. Windows listen to it to keep their lists current without refetching;
+//! the older, more specific events such as `chat:new-message` are still emitted alongside.
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+pub const DATA_CHANGED_EVENT: &str = "data:changed";
+
+/// What changed. Each variant lists the commands that emit it; `id` is the entity's id unless
+/// noted otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Entity {
+    /// create, update, delete, archive, unarchive, relocate, open, import and AI tool
+    /// assignment of projects. `data` is the project, except on delete.
+    Project,
+    /// Chat session create, rename, system prompt, pin, archive, read and auto title. `data` is
+    /// the session.
+    ChatSession,
+    /// New chat messages, from the user or a reply, code blocks applied and attachments added.
+    /// `data` is the message, or the attachment when one was added.
+    ChatMessage,
+    /// Swarm create, update, status changes, pin, clone, delete and saved workflows. `data` is
+    /// the swarm, except on delete.
+    Swarm,
+    /// Agents added to or removed from a swarm; `id` is the agent's, `data` the swarm.
+    SwarmAgent,
+    /// Tasks run by `execute_swarm_task` and queued by `enqueue_swarm_task`. `data` is the
+    /// task's result or the queued task.
+    SwarmTask,
+    /// Tool registration, connection, status, API keys and stored configs. `id` is the tool's,
+    /// or the config's for stored configs; `data` is the tool when registered and otherwise
+    /// what changed.
+    AiTool,
+    /// Files and directories written, created, deleted, renamed, copied, restored or
+    /// extracted; `id` is the path. `data` has `{ from }` for renames and copies.
+    File,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Created,
+    Updated,
+    Deleted,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DataChanged {
+    pub entity: Entity,
+    pub action: Action,
+    pub id: String,
+    pub project_id: Option<String>,
+    // The entity as it is after the change, when the command has it at hand
+    pub data: Option<serde_json::Value>,
+}
+
+impl DataChanged {
+    pub fn new(entity: Entity, action: Action, id: impl Into<String>) -> Self {
+        Self { entity, action, id: id.into(), project_id: None, data: None }
+    }
+
+    pub fn in_project(mut self, project_id: Option<&str>) -> Self {
+        self.project_id = project_id.map(str::to_string);
+        self
+    }
+
+    pub fn with_data<T: Serialize>(mut self, data: &T) -> Self {
+        self.data = serde_json::to_value(data).ok();
+        self
+    }
+
+    pub fn emit(self, app: &AppHandle) {
+        if let Err(e) = app.emit(DATA_CHANGED_EVENT, &self) {
+            log::warn!("Failed to emit {} for {:?} {}: {}", DATA_CHANGED_EVENT, self.entity, self.id, e);
+        }
+    }
+}
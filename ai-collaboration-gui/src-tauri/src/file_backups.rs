@@ -0,0 +1,151 @@
+//! Earlier versions of files overwritten through the file commands, so a bad edit can be rolled
+//! back. Each file gets `<app data>/backups/<sha256 of its path>/`, holding one copy per
+//! overwrite named by the time it was taken, plus `path.txt` naming the file it belongs to.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::database::Database;
+
+const SETTING: &str = "file_backups";
+const DEFAULT_RETENTION: usize = 10;
+const MAX_RETENTION: usize = 100;
+// Files larger than this are overwritten without a backup
+const MAX_BACKUP_BYTES: u64 = 20 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupSettings {
+    pub enabled: bool,
+    // Versions kept per file; older ones are dropped
+    pub retention: usize,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            retention: DEFAULT_RETENTION,
+        }
+    }
+}
+
+/// One saved version; index 0 is the most recent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileBackup {
+    pub index: usize,
+    pub created_at: DateTime<Utc>,
+    pub size: u64,
+}
+
+static BACKUP_DIR: OnceCell<PathBuf> = OnceCell::new();
+
+/// Set once at startup; until then nothing is backed up.
+pub fn init(app_data_dir: &Path) {
+    let _ = BACKUP_DIR.set(app_data_dir.join("backups"));
+}
+
+pub fn settings(db: &Database) -> BackupSettings {
+    db.get_setting(SETTING)
+        .ok()
+        .flatten()
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_settings(db: &Database, settings: &BackupSettings) -> Result<()> {
+    if !(1..=MAX_RETENTION).contains(&settings.retention) {
+        return Err(anyhow!("retention must be between 1 and {}", MAX_RETENTION));
+    }
+    db.set_setting(SETTING, &serde_json::to_string(settings)?)
+}
+
+/// Copies the current content of `path` into its backups, if it exists and backups are on.
+/// Returns the version taken, for `read_version`.
+pub fn save(db: &Database, path: &Path) -> Result<Option<i64>> {
+    let settings = settings(db);
+    if !settings.enabled || BACKUP_DIR.get().is_none() {
+        return Ok(None);
+    }
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) if metadata.is_file() => metadata,
+        _ => return Ok(None),
+    };
+    if metadata.len() > MAX_BACKUP_BYTES {
+        log::info!("Not backing up {}: larger than {} bytes", path.display(), MAX_BACKUP_BYTES);
+        return Ok(None);
+    }
+
+    let dir = backup_dir(path)?;
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("path.txt"), path.to_string_lossy().as_bytes())?;
+
+    // Millisecond names, bumped past any taken within the same millisecond
+    let mut millis = Utc::now().timestamp_millis();
+    while dir.join(millis.to_string()).exists() {
+        millis += 1;
+    }
+    fs::copy(path, dir.join(millis.to_string()))?;
+
+    for stale in versions(&dir)?.into_iter().skip(settings.retention.min(MAX_RETENTION)) {
+        fs::remove_file(dir.join(stale.to_string()))?;
+    }
+    Ok(Some(millis))
+}
+
+pub fn list(path: &Path) -> Result<Vec<FileBackup>> {
+    let dir = backup_dir(path)?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    versions(&dir)?
+        .into_iter()
+        .enumerate()
+        .map(|(index, millis)| {
+            Ok(FileBackup {
+                index,
+                created_at: Utc.timestamp_millis_opt(millis).single().unwrap_or_default(),
+                size: fs::metadata(dir.join(millis.to_string()))?.len(),
+            })
+        })
+        .collect()
+}
+
+/// The content of version `index` of `path`.
+pub fn read(path: &Path, index: usize) -> Result<Vec<u8>> {
+    let dir = backup_dir(path)?;
+    let millis = versions(&dir)
+        .ok()
+        .and_then(|versions| versions.get(index).copied())
+        .ok_or_else(|| anyhow!("No backup {} of {}", index, path.display()))?;
+    Ok(fs::read(dir.join(millis.to_string()))?)
+}
+
+/// The content of the version `save` returned, unless retention has dropped it since.
+pub fn read_version(path: &Path, version: i64) -> Result<Vec<u8>> {
+    let file = backup_dir(path)?.join(version.to_string());
+    if !file.is_file() {
+        return Err(anyhow!("The backup of {} is no longer kept", path.display()));
+    }
+    Ok(fs::read(file)?)
+}
+
+// Keyed by the resolved path, so the same file reached through a link shares its backups
+fn backup_dir(path: &Path) -> Result<PathBuf> {
+    let root = BACKUP_DIR.get().ok_or_else(|| anyhow!("File backups are not initialized"))?;
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let hash = Sha256::digest(path.to_string_lossy().as_bytes());
+    Ok(root.join(format!("{:x}", hash)))
+}
+
+/// Version names in `dir`, newest first.
+fn versions(dir: &Path) -> Result<Vec<i64>> {
+    let mut versions: Vec<i64> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .collect();
+    versions.sort_unstable_by(|a, b| b.cmp(a));
+    Ok(versions)
+}
@@ -1,8 +1,28 @@
 use log::info;
-use env_logger;
+use std::sync::Arc;
+use tauri::Manager;
+use tauri_plugin_deep_link::DeepLinkExt;
+use state::AppState;
 
+mod approvals;
 mod commands;
 mod database;
+mod error;
+mod events;
+mod file_backups;
+mod logging;
+mod navigation;
+mod notifications;
+mod path_access;
+mod providers;
+mod scheduler;
+mod secrets;
+mod shutdown;
+mod state;
+mod telemetry;
+mod tool_io;
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+mod tray;
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
@@ -12,14 +32,102 @@ fn greet(name: &str) -> String {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    // Initialize logger
-    env_logger::init();
+    // Initialize logger. It lets everything through; the `log_level` setting caps it
+    logging::init();
+    commands::apply_app_settings(&commands::AppSettings::default());
     
     info!("Starting AI Collaboration GUI");
     
-    tauri::Builder::default()
+    // Kills leftover tool processes if run() unwinds
+    let processes = Arc::new(commands::ProcessRegistry::default());
+    let _process_guard = commands::ToolProcessGuard(processes.clone());
+    
+    let builder = tauri::Builder::default();
+    // Registered first, so a second launch hands over its arguments and exits before anything
+    // else starts
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    let builder = builder.plugin(tauri_plugin_single_instance::init(|app, args, cwd| {
+        tray::show_main_window(app);
+        navigation::handle_args(app, &args, std::path::Path::new(&cwd));
+    }));
+    builder
         .plugin(tauri_plugin_opener::init())
-        .invoke_handler(tauri::generate_handler![
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_deep_link::init())
+        .manage(commands::SystemMonitor::new())
+        .setup(|app| {
+            if let Ok(app_data_dir) = app.path().app_data_dir() {
+                if let Err(e) = logging::init_file(&app_data_dir) {
+                    log::warn!("Could not open the log file: {}", e);
+                }
+                tool_io::init(&app_data_dir);
+                file_backups::init(&app_data_dir);
+                commands::init_workspaces(&app_data_dir);
+            }
+            // Opened before any command can run, since they all go through the state
+            let db = commands::open_app_database(app.handle())?;
+            if let Err(e) = providers::proxy::load_global_settings(&db) {
+                log::warn!("Could not load the proxy settings: {}", e);
+            }
+            app.manage(AppState::with_processes(db.clone(), processes));
+            #[cfg(not(any(target_os = "android", target_os = "ios")))]
+            if let Err(e) = tray::init(app.handle()) {
+                log::warn!("Could not create the tray icon: {}", e);
+            }
+
+            // On Linux and Windows a link starts the app with the link as its argument; elsewhere
+            // it comes through the deep link plugin
+            let cwd = std::env::current_dir().unwrap_or_default();
+            navigation::handle_args(app.handle(), &std::env::args().collect::<Vec<_>>(), &cwd);
+            #[cfg(any(windows, target_os = "linux"))]
+            if let Err(e) = app.deep_link().register_all() {
+                log::warn!("Could not register the {}:// link scheme: {}", navigation::URL_SCHEME, e);
+            }
+            #[cfg(not(any(windows, target_os = "linux")))]
+            {
+                let handle = app.handle().clone();
+                app.deep_link().on_open_url(move |event| {
+                    for url in event.urls() {
+                        navigation::handle_url(&handle, url.as_str());
+                    }
+                });
+            }
+
+            let state = app.state::<AppState>();
+            commands::apply_app_settings(&state.settings.borrow());
+            state.jobs.start(app.handle(), &state.tasks);
+            state.tasks.spawn("tool detection", async move {
+                match secrets::migrate_plaintext_keys(&db) {
+                    Ok(0) => {}
+                    Ok(moved) => info!("Moved {} stored API key(s) to the keychain", moved),
+                    Err(e) => log::warn!("Could not move stored API keys to the keychain: {}", e),
+                }
+                match commands::detect_and_register_tools(&db).await {
+                    Ok(tools) => info!("Detected {} AI tool(s)", tools.len()),
+                    Err(e) => log::warn!("AI tool detection failed: {}", e),
+                }
+            });
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            match event {
+                // Closing the main window ends the app, so it waits for the shutdown
+                tauri::WindowEvent::CloseRequested { api, .. } if window.label() == "main" && !shutdown::finished(window.app_handle()) => {
+                    api.prevent_close();
+                    if hides_on_close(window.app_handle()) {
+                        if let Err(e) = window.hide() {
+                            log::warn!("Failed to hide the main window: {}", e);
+                        }
+                    } else {
+                        shutdown::begin(window.app_handle(), 0);
+                    }
+                }
+                tauri::WindowEvent::Destroyed => window.state::<AppState>().tails.stop_window(window.label()),
+                tauri::WindowEvent::Focused(true) => notifications::window_focused(window.app_handle()),
+                _ => {}
+            }
+        })
+        .invoke_handler(shutdown::refuse_when_stopping(tauri::generate_handler![
             // Original commands
             greet,
             
@@ -29,69 +137,204 @@ pub fn run() {
             commands::update_project,
             commands::delete_project,
             commands::get_project_by_id,
+            commands::open_project,
+            commands::archive_project,
+            commands::unarchive_project,
+            commands::relocate_project,
+            commands::verify_projects,
+            commands::discover_projects,
+            commands::import_discovered_projects,
+            commands::export_project_bundle,
+            commands::import_project_bundle,
+            commands::run_retention_cleanup,
+            commands::set_project_ai_tool,
+            commands::get_project_ai_tools,
             
             // AI Tools commands
             commands::initialize_ai_tool,
             commands::connect_ai_tool,
             commands::disconnect_ai_tool,
             commands::send_ai_command,
+            commands::send_ai_command_with_fallback,
+            commands::broadcast_ai_command,
+            commands::cancel_ai_command,
+            commands::send_chat_message,
+            commands::preview_prompt,
+            commands::parse_ai_response,
+            commands::apply_code_block_to_file,
+            commands::export_chat_session,
+            commands::attach_file_to_message,
+            commands::auto_title_session,
+            commands::get_tool_queue_status,
+            commands::list_tool_processes,
+            commands::force_kill_tool_process,
             commands::get_ai_tools,
             commands::update_ai_tool_status,
+            commands::detect_ai_tools,
+            commands::check_tool_health,
+            commands::set_health_check_interval,
+            commands::get_available_models,
+            commands::get_proxy_settings,
+            commands::set_proxy_settings,
+            commands::get_tool_io_logging,
+            commands::set_tool_io_logging,
+            commands::get_tool_io_log,
+            commands::clear_tool_io_logs,
+            commands::test_ai_tool_connection,
+            commands::set_tool_api_key,
+            commands::clear_tool_api_key,
             
             // Swarm management commands
             commands::create_swarm,
             commands::get_swarms,
+            commands::get_swarm_by_id,
+            commands::get_swarm_metrics,
+            commands::get_swarm_progress,
+            commands::update_swarm,
             commands::execute_swarm_task,
+            commands::enqueue_swarm_task,
+            commands::snapshot_swarm,
             commands::pause_swarm,
             commands::resume_swarm,
             commands::stop_swarm,
             commands::add_agent_to_swarm,
             commands::remove_agent_from_swarm,
             commands::query_swarm_memory,
+            commands::post_agent_message,
+            commands::get_agent_messages,
+            commands::get_model_pricing,
+            commands::set_model_pricing,
+            commands::get_tool_usage_stats,
+            commands::get_session_token_usage,
+            commands::backfill_token_counts,
+            commands::save_swarm_workflow,
+            commands::save_workflow_layout,
+            commands::save_swarm_template,
+            commands::list_swarm_templates,
+            commands::clone_swarm,
+            commands::get_swarm_timeline,
+            commands::create_workspace,
+            commands::list_workspaces,
+            commands::cleanup_workspaces,
+            commands::get_workspace_settings,
+            commands::set_workspace_settings,
             
             // System commands
             commands::read_directory,
+            commands::read_directory_tree,
+            commands::read_directory_filtered,
+            commands::search_files,
+            commands::search_file_contents,
+            commands::cancel_search,
+            commands::git_status,
+            commands::git_diff_file,
+            commands::git_diff_summary,
+            commands::git_file_log,
+            commands::git_stage,
+            commands::git_unstage,
+            commands::git_commit,
+            commands::git_current_branch,
+            commands::git_create_branch,
+            commands::diff_paths,
+            commands::diff_text,
             commands::read_file_content,
+            commands::read_file_range,
+            commands::tail_file,
+            commands::stop_tail,
             commands::write_file_content,
+            commands::hash_file,
+            commands::append_file_content,
+            commands::write_file_range,
+            commands::list_file_backups,
+            commands::restore_file_backup,
+            commands::undo_last_file_operation,
+            commands::get_undo_history,
+            commands::get_file_backup_settings,
+            commands::set_file_backup_settings,
             commands::create_directory,
             commands::delete_file_or_directory,
+            commands::list_recent_deletions,
+            commands::rename_path,
+            commands::copy_path,
+            commands::authorize_path,
             commands::execute_command,
+            commands::execute_command_streaming,
+            commands::kill_process,
+            commands::get_process_status,
+            commands::list_processes,
+            commands::get_process_output,
+            commands::clear_process,
             commands::get_system_info,
+            commands::get_app_info,
+            commands::reveal_in_file_manager,
+            commands::open_with_default_app,
+            commands::get_app_logs,
+            commands::open_log_directory,
+            commands::get_system_metrics,
+            commands::get_command_metrics,
+            commands::get_recent_slow_commands,
+            commands::get_project_disk_usage,
+            commands::analyze_project_languages,
+            commands::create_archive,
+            commands::extract_archive,
+            commands::clipboard_write_text,
+            commands::clipboard_read_text,
             commands::check_tool_availability,
+            commands::check_tools_availability,
             commands::get_environment_variables,
+            commands::set_app_env_var,
+            commands::delete_app_env_var,
+            commands::list_app_env_vars,
             
             // Database commands
             commands::db_initialize,
-            commands::db_create_project,
-            commands::db_get_projects,
-            commands::db_update_project,
-            commands::db_delete_project,
-            commands::db_create_chat_session,
-            commands::db_get_chat_sessions,
-            commands::db_create_chat_message,
-            commands::db_get_chat_messages,
-            commands::db_create_swarm,
-            commands::db_get_swarms,
-            commands::db_update_swarm,
-            commands::db_delete_swarm,
-            commands::db_create_ai_tool_config,
-            commands::db_get_ai_tool_configs,
-            commands::db_update_ai_tool_config,
-            commands::db_delete_ai_tool_config,
-            commands::db_get_all_projects,
-            commands::db_update_project,
-            commands::db_delete_project,
             commands::db_create_chat_session,
             commands::db_get_chat_sessions,
+            commands::db_set_chat_session_system_prompt,
+            commands::db_rename_chat_session,
+            commands::db_set_chat_session_pinned,
+            commands::db_archive_chat_session,
+            commands::mark_session_read,
             commands::db_create_chat_message,
             commands::db_get_chat_messages,
             commands::db_create_swarm,
             commands::db_get_swarms,
             commands::db_update_swarm_status,
+            commands::db_set_swarm_pinned,
+            commands::db_delete_swarm,
             commands::db_save_ai_tool_config,
             commands::db_get_ai_tool_configs,
+            commands::db_get_ai_interactions,
+            commands::db_purge_ai_interactions,
             commands::db_get_statistics,
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+            commands::get_recent_activity,
+            commands::get_app_settings,
+            commands::update_app_settings,
+            commands::list_background_jobs,
+            commands::run_background_job_now,
+            commands::export_diagnostics,
+            navigation::navigation_ready,
+            approvals::resolve_approval,
+            approvals::get_pending_approvals,
+        ]))
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| match event {
+            tauri::RunEvent::ExitRequested { api, code, .. } if !shutdown::finished(app) => {
+                api.prevent_exit();
+                shutdown::begin(app, code.unwrap_or(0));
+            }
+            // Only reached without the shutdown having run if the exit could not be held back
+            tauri::RunEvent::Exit => shutdown::run_blocking(app),
+            _ => {}
+        });
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+use tray::hides_on_close;
+
+// There is no tray on mobile
+#[cfg(any(target_os = "android", target_os = "ios"))]
+fn hides_on_close(_app: &tauri::AppHandle) -> bool {
+    false
 }
@@ -0,0 +1,204 @@
+//! The app's own log, `<app data>/logs/clauder.log`, rotated by size to `clauder.log.1`,
+//! `clauder.log.2`, ... Debug builds print it to stderr as well. What is logged before the
+//! directory is known is held in memory and written out once it is.
+//!
+//! Each line is `<time> <level> <target>: <message>`, followed by the record's key-value
+//! fields as `key=value`, e.g. `log::info!(tool_id = id, duration_ms = ms; "...")`.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use once_cell::sync::{Lazy, OnceCell};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+const FILE_NAME: &str = "clauder.log";
+const MAX_FILE_BYTES: u64 = 5 * 1024 * 1024;
+// Rotated files kept besides the current one
+const MAX_ROTATIONS: usize = 4;
+// Startup lines kept for the file, at most
+const MAX_PENDING_BYTES: usize = 256 * 1024;
+
+static LOG_DIR: OnceCell<PathBuf> = OnceCell::new();
+static SINK: Lazy<Mutex<Sink>> = Lazy::new(|| Mutex::new(Sink::default()));
+
+#[derive(Default)]
+struct Sink {
+    file: Option<File>,
+    size: u64,
+    pending: Vec<u8>,
+}
+
+/// Installs the logger. Everything is let through here; `log::set_max_level`, driven by the
+/// `log_level` setting, decides what is actually written.
+pub fn init() {
+    let mut dispatch = fern::Dispatch::new()
+        .level(log::LevelFilter::Trace)
+        .format(|out, message, record| {
+            out.finish(format_args!(
+                "{} {:<5} {}: {}{}",
+                Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+                record.level(),
+                record.target(),
+                message,
+                fields(record),
+            ))
+        })
+        .chain(fern::Output::call(|record| write_line(&format!("{}\n", record.args()))));
+    if cfg!(debug_assertions) {
+        dispatch = dispatch.chain(std::io::stderr());
+    }
+    if let Err(e) = dispatch.apply() {
+        eprintln!("Failed to install the logger: {}", e);
+    }
+}
+
+/// Opens the log file under `app_data_dir` and writes out what was logged so far. Called once
+/// at startup.
+pub fn init_file(app_data_dir: &Path) -> Result<()> {
+    let dir = app_data_dir.join("logs");
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(FILE_NAME);
+    let file = OpenOptions::new().create(true).append(true).open(&path)?;
+    let _ = LOG_DIR.set(dir);
+
+    let mut sink = SINK.lock().unwrap();
+    sink.size = file.metadata()?.len();
+    sink.file = Some(file);
+    let pending = std::mem::take(&mut sink.pending);
+    sink.write(&pending);
+    Ok(())
+}
+
+pub fn log_dir() -> Result<&'static Path> {
+    LOG_DIR.get().map(PathBuf::as_path).ok_or_else(|| anyhow!("The log file is not open"))
+}
+
+/// The log file and its rotations that exist, newest first.
+pub fn files() -> Result<Vec<PathBuf>> {
+    let path = log_dir()?.join(FILE_NAME);
+    Ok((0..=MAX_ROTATIONS)
+        .map(|rotation| rotated_path(&path, rotation))
+        .take_while(|file| file.exists())
+        .collect())
+}
+
+/// The last `count` entries at `min_level` or above, oldest first, reading into older rotations
+/// as needed. Lines that don't start an entry, such as the rest of a multi-line message, stay
+/// with the entry before them.
+pub fn tail(min_level: log::LevelFilter, count: usize) -> Result<Vec<String>> {
+    let path = log_dir()?.join(FILE_NAME);
+    let mut tail: Vec<String> = Vec::new();
+    for rotation in 0..=MAX_ROTATIONS {
+        if tail.len() >= count {
+            break;
+        }
+        let file = rotated_path(&path, rotation);
+        let content = match fs::read_to_string(&file) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => break,
+            Err(e) => return Err(anyhow!("Failed to read {}: {}", file.display(), e)),
+        };
+        let older: Vec<String> = entries(&content)
+            .into_iter()
+            .filter(|entry| entry_level(entry).is_some_and(|level| level <= min_level))
+            .collect();
+        let take = (count - tail.len()).min(older.len());
+        tail.splice(0..0, older[older.len() - take..].iter().cloned());
+    }
+    Ok(tail)
+}
+
+impl Sink {
+    // A failed write is dropped: there is nowhere left to report it
+    fn write(&mut self, bytes: &[u8]) {
+        let Some(file) = self.file.as_mut() else {
+            if self.pending.len() + bytes.len() <= MAX_PENDING_BYTES {
+                self.pending.extend_from_slice(bytes);
+            }
+            return;
+        };
+        if file.write_all(bytes).is_ok() {
+            self.size += bytes.len() as u64;
+        }
+        if self.size > MAX_FILE_BYTES {
+            self.rotate();
+        }
+    }
+
+    fn rotate(&mut self) {
+        let Some(dir) = LOG_DIR.get() else {
+            return;
+        };
+        let path = dir.join(FILE_NAME);
+        self.file = None;
+        let _ = fs::remove_file(rotated_path(&path, MAX_ROTATIONS));
+        for rotation in (1..MAX_ROTATIONS).rev() {
+            let from = rotated_path(&path, rotation);
+            if from.exists() {
+                let _ = fs::rename(from, rotated_path(&path, rotation + 1));
+            }
+        }
+        let _ = fs::rename(&path, rotated_path(&path, 1));
+        self.file = OpenOptions::new().create(true).append(true).open(&path).ok();
+        self.size = 0;
+    }
+}
+
+fn write_line(line: &str) {
+    SINK.lock().unwrap().write(line.as_bytes());
+}
+
+// The record's key-value fields as ` key=value` pairs
+fn fields(record: &log::Record) -> String {
+    struct Collect(String);
+
+    impl<'kvs> log::kv::VisitSource<'kvs> for Collect {
+        fn visit_pair(
+            &mut self,
+            key: log::kv::Key<'kvs>,
+            value: log::kv::Value<'kvs>,
+        ) -> Result<(), log::kv::Error> {
+            self.0.push_str(&format!(" {}={}", key, value));
+            Ok(())
+        }
+    }
+
+    let mut collect = Collect(String::new());
+    let _ = record.key_values().visit(&mut collect);
+    collect.0
+}
+
+fn entries(content: &str) -> Vec<String> {
+    let mut entries: Vec<String> = Vec::new();
+    for line in content.lines() {
+        match entries.last_mut() {
+            Some(entry) if entry_time(line).is_none() => {
+                entry.push('\n');
+                entry.push_str(line);
+            }
+            _ => entries.push(line.to_string()),
+        }
+    }
+    entries
+}
+
+fn entry_time(line: &str) -> Option<DateTime<Utc>> {
+    let time = line.split(' ').next()?;
+    DateTime::parse_from_rfc3339(time).ok().map(|t| t.with_timezone(&Utc))
+}
+
+fn entry_level(entry: &str) -> Option<log::Level> {
+    entry.split_whitespace().nth(1).and_then(|level| log::Level::from_str(level).ok())
+}
+
+fn rotated_path(path: &Path, rotation: usize) -> PathBuf {
+    if rotation == 0 {
+        return path.to_path_buf();
+    }
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", rotation));
+    PathBuf::from(name)
+}
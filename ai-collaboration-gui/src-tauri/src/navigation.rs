@@ -0,0 +1,227 @@
+//! Opening a project, session or swarm from outside the app: `--project <path>`, `--session <id>`
+//! and `--swarm <id>` on the command line, and `clauder://project|session|swarm/<id>` links.
+//! Both arrive at startup and, through the single-instance plugin, from later launches. Targets
+//! are resolved against the database and sent as `app:navigate`; those that can't be resolved
+//! are sent as `app:navigation-error` instead. Until the frontend calls `navigation_ready` both
+//! are held back, since nothing is listening yet.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+use crate::commands::project_at;
+use crate::database::Database;
+use crate::error::AppError;
+use crate::state::AppState;
+use crate::telemetry::timed;
+
+pub const NAVIGATE_EVENT: &str = "app:navigate";
+pub const NAVIGATION_ERROR_EVENT: &str = "app:navigation-error";
+pub const URL_SCHEME: &str = "clauder";
+
+/// Payload of `app:navigate`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "target", rename_all = "snake_case")]
+pub enum Navigation {
+    Project { id: String },
+    /// A directory that isn't a project yet; the frontend asks before creating one for it.
+    RegisterProject { path: String },
+    Session { id: String, project_id: Option<String> },
+    Swarm { id: String, project_id: String },
+}
+
+/// Payload of `app:navigation-error`.
+#[derive(Debug, Clone, Serialize)]
+pub struct NavigationError {
+    // The argument or link as given
+    pub input: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    ProjectPath(PathBuf),
+    Project(String),
+    Session(String),
+    Swarm(String),
+}
+
+enum Outcome {
+    Navigate(Navigation),
+    Error(NavigationError),
+}
+
+/// What arrived before the frontend called `navigation_ready`.
+#[derive(Default)]
+pub struct NavigationQueue(Mutex<Queue>);
+
+#[derive(Default)]
+struct Queue {
+    ready: bool,
+    outcomes: Vec<Outcome>,
+}
+
+/// Called by the frontend once it listens for `app:navigate` and `app:navigation-error`; sends
+/// whatever arrived before then.
+#[tauri::command]
+#[timed]
+pub async fn navigation_ready(app: AppHandle, state: State<'_, AppState>) -> Result<(), AppError> {
+    let queued = {
+        let mut queue = state.navigation.0.lock().unwrap();
+        queue.ready = true;
+        std::mem::take(&mut queue.outcomes)
+    };
+    for outcome in queued {
+        send(&app, outcome);
+    }
+    Ok(())
+}
+
+/// Handles a command line, the app's own or one forwarded from a second launch. `args` includes
+/// the binary name; relative project paths are resolved against `cwd`.
+pub fn handle_args(app: &AppHandle, args: &[String], cwd: &Path) {
+    for target in parse_args(args.iter().skip(1).map(String::as_str)) {
+        match target {
+            (input, Ok(target)) => open(app, &input, absolute(target, cwd)),
+            (input, Err(message)) => dispatch(app, Outcome::Error(NavigationError { input, message })),
+        }
+    }
+}
+
+/// Handles a `clauder://` link opened by the OS. On Linux and Windows links arrive as arguments.
+#[cfg(not(any(windows, target_os = "linux")))]
+pub fn handle_url(app: &AppHandle, url: &str) {
+    match parse_url(url) {
+        Ok(target) => open(app, url, target),
+        Err(message) => dispatch(app, Outcome::Error(NavigationError { input: url.to_string(), message })),
+    }
+}
+
+/// Sends `navigation` now if the frontend is ready, and otherwise once it is.
+pub fn navigate(app: &AppHandle, navigation: Navigation) {
+    dispatch(app, Outcome::Navigate(navigation));
+}
+
+fn open(app: &AppHandle, input: &str, target: Target) {
+    let db = &app.state::<AppState>().db;
+    let outcome = match resolve(db, target) {
+        Ok(navigation) => Outcome::Navigate(navigation),
+        Err(message) => {
+            log::warn!("Can't open {}: {}", input, message);
+            Outcome::Error(NavigationError { input: input.to_string(), message })
+        }
+    };
+    dispatch(app, outcome);
+}
+
+fn dispatch(app: &AppHandle, outcome: Outcome) {
+    {
+        let state = app.state::<AppState>();
+        let mut queue = state.navigation.0.lock().unwrap();
+        if !queue.ready {
+            queue.outcomes.push(outcome);
+            return;
+        }
+    }
+    send(app, outcome);
+}
+
+fn send(app: &AppHandle, outcome: Outcome) {
+    let sent = match &outcome {
+        Outcome::Navigate(navigation) => app.emit(NAVIGATE_EVENT, navigation),
+        Outcome::Error(error) => app.emit(NAVIGATION_ERROR_EVENT, error),
+    };
+    if let Err(e) = sent {
+        log::warn!("Failed to emit a navigation event: {}", e);
+    }
+}
+
+fn resolve(db: &Database, target: Target) -> Result<Navigation, String> {
+    let lookup_failed = |e: anyhow::Error| format!("Failed to look it up: {}", e);
+    match target {
+        Target::ProjectPath(path) => {
+            if !path.is_dir() {
+                return Err(format!("{} is not a directory", path.display()));
+            }
+            let given = path.to_string_lossy().to_string();
+            let canonical = std::fs::canonicalize(&path)
+                .map(|path| path.to_string_lossy().to_string())
+                .unwrap_or_else(|_| given.clone());
+            for candidate in [&given, &canonical] {
+                if let Some(project) = project_at(db, candidate).map_err(lookup_failed)? {
+                    return Ok(Navigation::Project { id: project.id });
+                }
+            }
+            Ok(Navigation::RegisterProject { path: canonical })
+        }
+        Target::Project(id) => match db.get_project(&id).map_err(lookup_failed)? {
+            Some(project) => Ok(Navigation::Project { id: project.id }),
+            None => Err(format!("No project with id {}", id)),
+        },
+        Target::Session(id) => match db.get_chat_session(&id).map_err(lookup_failed)? {
+            Some(session) => Ok(Navigation::Session { id: session.id, project_id: session.project_id }),
+            None => Err(format!("No chat session with id {}", id)),
+        },
+        Target::Swarm(id) => match db.get_swarm_by_id(&id).map_err(lookup_failed)? {
+            Some(swarm) => Ok(Navigation::Swarm { id: swarm.id, project_id: swarm.project_id }),
+            None => Err(format!("No swarm with id {}", id)),
+        },
+    }
+}
+
+// Each target with the argument it came from. Unknown arguments are left alone: they may be
+// meant for the webview or the OS
+fn parse_args<'a>(args: impl Iterator<Item = &'a str>) -> Vec<(String, Result<Target, String>)> {
+    let mut targets = Vec::new();
+    let mut args = args.peekable();
+    while let Some(arg) = args.next() {
+        if arg.starts_with(&format!("{}://", URL_SCHEME)) {
+            targets.push((arg.to_string(), parse_url(arg)));
+            continue;
+        }
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) => (flag, Some(value.to_string())),
+            None => (arg, None),
+        };
+        if !["--project", "--session", "--swarm"].contains(&flag) {
+            continue;
+        }
+        let value = inline.or_else(|| args.next_if(|next| !next.starts_with("--")).map(str::to_string));
+        let input = match &value {
+            Some(value) => format!("{} {}", flag, value),
+            None => flag.to_string(),
+        };
+        let target = match value.filter(|value| !value.is_empty()) {
+            None => Err(format!("{} needs a value", flag)),
+            Some(value) if flag == "--project" => Ok(Target::ProjectPath(PathBuf::from(value))),
+            Some(value) if flag == "--session" => Ok(Target::Session(value)),
+            Some(value) => Ok(Target::Swarm(value)),
+        };
+        targets.push((input, target));
+    }
+    targets
+}
+
+fn parse_url(url: &str) -> Result<Target, String> {
+    let rest = url.strip_prefix(&format!("{}://", URL_SCHEME))
+        .ok_or_else(|| format!("Not a {}:// link", URL_SCHEME))?;
+    // Query strings and fragments aren't used
+    let rest = rest.split(['?', '#']).next().unwrap_or_default().trim_end_matches('/');
+    let (kind, id) = rest.split_once('/').unwrap_or((rest, ""));
+    if id.is_empty() || id.contains('/') {
+        return Err(format!("Expected {}://<project|session|swarm>/<id>", URL_SCHEME));
+    }
+    match kind {
+        "project" => Ok(Target::Project(id.to_string())),
+        "session" => Ok(Target::Session(id.to_string())),
+        "swarm" => Ok(Target::Swarm(id.to_string())),
+        _ => Err(format!("Unknown link target '{}'", kind)),
+    }
+}
+
+fn absolute(target: Target, cwd: &Path) -> Target {
+    match target {
+        Target::ProjectPath(path) if path.is_relative() => Target::ProjectPath(cwd.join(path)),
+        target => target,
+    }
+}
@@ -0,0 +1,127 @@
+//! OS notifications for work that finishes while the app is in the background: swarm runs,
+//! failed swarm tasks and slow AI commands, as chosen in the `notify_on` setting and silenced by
+//! `notifications_muted`. They name the project, swarm or tool and the outcome, never message
+//! content or errors. Desktop notifications can't report clicks, so the place a notification is
+//! about is sent as `app:navigate` the next time the window gets focus.
+
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+use crate::commands::NotifyOn;
+use crate::database::{Database, DbSwarm};
+use crate::navigation::{self, Navigation};
+use crate::state::AppState;
+
+const MAIN_WINDOW: &str = "main";
+
+/// Where the last notification leads, until the window is focused.
+#[derive(Default)]
+pub struct NotificationTarget(Mutex<Option<Navigation>>);
+
+/// Called when the main window gains focus.
+pub fn window_focused(app: &AppHandle) {
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    let Some(navigation) = state.notification_target.0.lock().unwrap().take() else {
+        return;
+    };
+    navigation::navigate(app, navigation);
+}
+
+/// A swarm run ended with every task done; `status` is `completed` or `failed`.
+pub fn swarm_finished(app: &AppHandle, swarm_id: &str, status: &str) {
+    if !enabled(app, |notify_on| notify_on.swarm_complete) {
+        return;
+    }
+    let Some((swarm, project)) = swarm_names(&app.state::<AppState>().db, swarm_id) else {
+        return;
+    };
+    let title = if status == "failed" { "Swarm failed" } else { "Swarm completed" };
+    post(app, title, &format!("{} in {} has {}", swarm.name, project, status), Some(Navigation::Swarm {
+        id: swarm.id,
+        project_id: swarm.project_id,
+    }));
+}
+
+pub fn swarm_task_failed(app: &AppHandle, swarm_id: &str) {
+    if !enabled(app, |notify_on| notify_on.task_failed) {
+        return;
+    }
+    let Some((swarm, project)) = swarm_names(&app.state::<AppState>().db, swarm_id) else {
+        return;
+    };
+    post(app, "Swarm task failed", &format!("A task of {} in {} failed", swarm.name, project), Some(Navigation::Swarm {
+        id: swarm.id,
+        project_id: swarm.project_id,
+    }));
+}
+
+/// An AI command finished; only those that took `command_complete_over_seconds` or longer are
+/// notified.
+pub fn command_finished(app: &AppHandle, tool_id: &str, session_id: Option<&str>, success: bool, elapsed: Duration) {
+    if !enabled(app, |notify_on| {
+        notify_on.command_complete_over_seconds > 0 && elapsed.as_secs() >= notify_on.command_complete_over_seconds
+    }) {
+        return;
+    }
+    let db = &app.state::<AppState>().db;
+    let session = session_id.and_then(|id| db.get_chat_session(id).ok().flatten());
+    let project = session.as_ref()
+        .and_then(|session| session.project_id.as_deref())
+        .and_then(|id| project_name(db, id));
+
+    let title = if success { format!("{} finished", tool_id) } else { format!("{} failed", tool_id) };
+    let body = match project {
+        Some(project) => format!("Command in {} took {}", project, format_elapsed(elapsed)),
+        None => format!("Command took {}", format_elapsed(elapsed)),
+    };
+    post(app, &title, &body, session.map(|session| Navigation::Session {
+        id: session.id,
+        project_id: session.project_id,
+    }));
+}
+
+fn enabled(app: &AppHandle, wanted: impl FnOnce(&NotifyOn) -> bool) -> bool {
+    let Some(state) = app.try_state::<AppState>() else {
+        return false;
+    };
+    let settings = state.settings.borrow();
+    !settings.notifications_muted && wanted(&settings.notify_on)
+}
+
+fn post(app: &AppHandle, title: &str, body: &str, navigation: Option<Navigation>) {
+    if in_foreground(app) {
+        return;
+    }
+    *app.state::<AppState>().inner().notification_target.0.lock().unwrap() = navigation;
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        log::warn!("Failed to show notification '{}': {}", title, e);
+    }
+}
+
+fn in_foreground(app: &AppHandle) -> bool {
+    app.get_webview_window(MAIN_WINDOW)
+        .map(|window| window.is_focused().unwrap_or(false) && !window.is_minimized().unwrap_or(false))
+        .unwrap_or(false)
+}
+
+fn swarm_names(db: &Database, swarm_id: &str) -> Option<(DbSwarm, String)> {
+    let swarm = db.get_swarm_by_id(swarm_id).ok().flatten()?;
+    let project = project_name(db, &swarm.project_id).unwrap_or_else(|| "an unknown project".to_string());
+    Some((swarm, project))
+}
+
+fn project_name(db: &Database, project_id: &str) -> Option<String> {
+    db.get_project(project_id).ok().flatten().map(|project| project.name)
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
@@ -0,0 +1,152 @@
+//! Which paths the file commands may touch: anything under the registered path of the project
+//! they act for, under a root allow-listed in the `allowed_roots` setting, under a path the
+//! user granted through `authorize_path` since the app started, or in an agent workspace. Paths are judged after symlinks
+//! are resolved, so a link inside a project that points elsewhere is judged by where it points.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use crate::commands::workspaces_root;
+use crate::database::Database;
+use crate::error::AppError;
+use crate::state::AppState;
+
+const SETTING: &str = "allowed_roots";
+// Links followed while resolving one path; more than the OS itself allows means a loop
+const MAX_LINKS: usize = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PathScope {
+    Session, // until the app restarts
+    Always,  // saved to the allowed_roots setting
+}
+
+/// Paths granted with `PathScope::Session`, forgotten when the app exits.
+#[derive(Default)]
+pub struct SessionRoots(Mutex<Vec<PathBuf>>);
+
+/// Fails with `PermissionDenied` unless `path` resolves to somewhere the commands may touch.
+/// The path itself may not exist yet, e.g. a file about to be written.
+pub fn check(state: &AppState, path: &Path, project_id: Option<&str>) -> Result<(), AppError> {
+    let resolved = resolve(path)?;
+    
+    let db = &state.db;
+    let mut roots = allowed_roots(db)?;
+    roots.extend(state.session_roots.0.lock().unwrap().iter().cloned());
+    roots.extend(workspaces_root().map(Path::to_path_buf));
+    if let Some(project_id) = project_id {
+        let project = db.get_project(project_id)?
+            .ok_or_else(|| AppError::Internal(format!("Project not found: {}", project_id)))?;
+        roots.push(PathBuf::from(project.path));
+    }
+    
+    // Roots are resolved too, in case they were stored through a link
+    let allowed = roots.iter()
+        .filter_map(|root| fs::canonicalize(root).ok())
+        .any(|root| resolved.starts_with(root));
+    if allowed {
+        Ok(())
+    } else {
+        Err(AppError::PermissionDenied(path.to_string_lossy().to_string()))
+    }
+}
+
+/// Whether `path` is `root` or somewhere under it, both resolved as `check` does. Unresolvable
+/// paths are outside.
+pub fn is_within(path: &Path, root: &Path) -> bool {
+    match (resolve(path), fs::canonicalize(root)) {
+        (Ok(path), Ok(root)) => path.starts_with(root),
+        _ => false,
+    }
+}
+
+/// Lets the commands touch `path` and everything under it, after the user confirmed it.
+pub fn grant(state: &AppState, path: &Path, scope: PathScope) -> Result<(), AppError> {
+    let resolved = resolve(path)?;
+    match scope {
+        PathScope::Session => state.session_roots.0.lock().unwrap().push(resolved),
+        PathScope::Always => {
+            let db = &state.db;
+            let mut roots = allowed_roots(db)?;
+            if !roots.contains(&resolved) {
+                roots.push(resolved);
+            }
+            let value = serde_json::to_string(&roots).map_err(|e| AppError::Internal(e.to_string()))?;
+            db.set_setting(SETTING, &value)?;
+        }
+    }
+    Ok(())
+}
+
+fn allowed_roots(db: &Database) -> Result<Vec<PathBuf>, AppError> {
+    let Some(value) = db.get_setting(SETTING)? else {
+        return Ok(Vec::new());
+    };
+    serde_json::from_str(&value)
+        .map_err(|e| AppError::Internal(format!("Unreadable allowed roots: {}", e)))
+}
+
+/// The canonical form of `path`. For a path that doesn't exist yet, its nearest existing
+/// ancestor is canonicalized and the rest appended, which must then be plain names. A link
+/// whose target doesn't exist is resolved to that target, since writing through the link
+/// creates it there.
+fn resolve(path: &Path) -> Result<PathBuf, AppError> {
+    resolve_following(path, 0)
+}
+
+fn resolve_following(path: &Path, links_followed: usize) -> Result<PathBuf, AppError> {
+    if !path.is_absolute() {
+        return Err(AppError::Internal(format!("Path must be absolute: {}", path.display())));
+    }
+    
+    let mut missing = Vec::new();
+    let mut existing = path;
+    loop {
+        if let Ok(resolved) = fs::canonicalize(existing) {
+            return Ok(missing.iter().rev().fold(resolved, |resolved, name| resolved.join(name)));
+        }
+        if fs::symlink_metadata(existing).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+            if links_followed >= MAX_LINKS {
+                return Err(AppError::Internal(format!("Too many levels of symbolic links: {}", path.display())));
+            }
+            let target = fs::read_link(existing)
+                .map_err(|e| AppError::Internal(format!("Failed to read link {}: {}", existing.display(), e)))?;
+            // A relative target is relative to the link's directory; an absolute one replaces it
+            let target = existing.parent().map_or_else(|| target.clone(), |dir| dir.join(&target));
+            let resolved = resolve_following(&target, links_followed + 1)?;
+            return Ok(missing.iter().rev().fold(resolved, |resolved, name| resolved.join(name)));
+        }
+        // None for `..`, which can't be resolved against a directory that isn't there
+        let name = existing.file_name()
+            .ok_or_else(|| AppError::Internal(format!("Invalid path: {}", path.display())))?;
+        missing.push(name);
+        existing = existing.parent()
+            .ok_or_else(|| AppError::Internal(format!("Invalid path: {}", path.display())))?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn a_dangling_link_is_judged_by_its_target() {
+        let base = std::env::temp_dir().join(format!("clauder-path-access-{}", uuid::Uuid::new_v4()));
+        let project = base.join("project");
+        let outside = base.join("outside");
+        fs::create_dir_all(&project).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        std::os::unix::fs::symlink(outside.join("profile_new"), project.join("x")).unwrap();
+
+        let resolved = resolve(&project.join("x"));
+        let within = is_within(&project.join("x"), &project);
+        let expected = fs::canonicalize(&outside).unwrap().join("profile_new");
+        fs::remove_dir_all(&base).unwrap();
+
+        assert_eq!(resolved.unwrap(), expected);
+        assert!(!within);
+    }
+}
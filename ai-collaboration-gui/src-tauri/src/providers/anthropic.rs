@@ -0,0 +1,333 @@
+use serde_json::{json, Value};
+use crate::commands::ai_tools::ToolSpecificConfig;
+use crate::error::AppError;
+use super::{drain_sse_events, network_error, proxy, send_error, prompt_from_payload, retry_after, status_error, DeltaSink, ModelInfo};
+
+const DEFAULT_ENDPOINT: &str = "https://api.anthropic.com";
+const API_VERSION: &str = "2023-06-01";
+const DEFAULT_MODEL: &str = "claude-3-5-sonnet-latest";
+const DEFAULT_MAX_TOKENS: i32 = 4096;
+// The models endpoint doesn't report context sizes; every current Claude model has this one
+const CONTEXT_WINDOW: u32 = 200_000;
+
+/// Sends the payload to the Messages API. With `on_delta` the response is streamed over SSE
+/// and each text delta is passed on as it arrives; either way the full reply is returned as
+/// `{ id, model, message, stop_reason, usage }`.
+pub(crate) async fn send_message(config: &ToolSpecificConfig, payload: &Value, on_delta: Option<DeltaSink<'_>>) -> Result<Value, AppError> {
+    let body = request_body(config, payload, on_delta.is_some());
+    let response = request(config, reqwest::Method::POST, "/v1/messages")?
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| send_error(config, e))?;
+    if !response.status().is_success() {
+        return Err(error_from_response(response).await);
+    }
+
+    match on_delta {
+        Some(on_delta) => read_stream(response, on_delta).await,
+        None => {
+            let message: Value = response.json().await.map_err(network_error)?;
+            Ok(reply_from_message(&message))
+        }
+    }
+}
+
+// The reply as `send_message` returns it, from a whole Messages API response
+fn reply_from_message(message: &Value) -> Value {
+    let text = message.get("content")
+        .and_then(|c| c.as_array())
+        .map(|blocks| blocks.iter()
+            .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("text"))
+            .filter_map(|b| b.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join(""))
+        .unwrap_or_default();
+    json!({
+        "id": message.get("id"),
+        "model": message.get("model"),
+        "message": text,
+        "stop_reason": message.get("stop_reason"),
+        "usage": message.get("usage"),
+    })
+}
+
+/// Cheap authenticated request used to validate the key on connect and in health checks.
+pub(crate) async fn ping(config: &ToolSpecificConfig) -> Result<(), AppError> {
+    let response = request(config, reqwest::Method::GET, "/v1/models?limit=1")?
+        .send()
+        .await
+        .map_err(|e| send_error(config, e))?;
+    if !response.status().is_success() {
+        return Err(error_from_response(response).await);
+    }
+    Ok(())
+}
+
+/// Lists the models the key has access to via `/v1/models`, following pagination.
+pub(crate) async fn list_models(config: &ToolSpecificConfig) -> Result<Vec<ModelInfo>, AppError> {
+    let mut models = Vec::new();
+    let mut after: Option<String> = None;
+    loop {
+        let path = match &after {
+            Some(after) => format!("/v1/models?limit=1000&after_id={}", after),
+            None => "/v1/models?limit=1000".to_string(),
+        };
+        let response = request(config, reqwest::Method::GET, &path)?
+            .send()
+            .await
+            .map_err(|e| send_error(config, e))?;
+        if !response.status().is_success() {
+            return Err(error_from_response(response).await);
+        }
+
+        let page: Value = response.json().await.map_err(network_error)?;
+        models.extend(page["data"].as_array().into_iter().flatten().filter_map(|m| {
+            let id = m["id"].as_str()?;
+            Some(ModelInfo {
+                id: id.to_string(),
+                display_name: m["display_name"].as_str().unwrap_or(id).to_string(),
+                context_window: Some(CONTEXT_WINDOW),
+                supports_streaming: true,
+            })
+        }));
+        after = page["last_id"].as_str().filter(|_| page["has_more"].as_bool() == Some(true)).map(str::to_string);
+        if after.is_none() {
+            return Ok(models);
+        }
+    }
+}
+
+fn request(config: &ToolSpecificConfig, method: reqwest::Method, path: &str) -> Result<reqwest::RequestBuilder, AppError> {
+    let api_key = config.api_key.clone()
+        .filter(|key| !key.is_empty())
+        .or_else(|| std::env::var("ANTHROPIC_API_KEY").ok())
+        .ok_or_else(|| AppError::InvalidApiKey("no API key configured".to_string()))?;
+    let endpoint = config.endpoint.as_deref()
+        .filter(|e| !e.is_empty())
+        .unwrap_or(DEFAULT_ENDPOINT)
+        .trim_end_matches('/');
+
+    Ok(proxy::client_for(config)?.request(method, format!("{}{}", endpoint, path))
+        .header("x-api-key", api_key)
+        .header("anthropic-version", API_VERSION))
+}
+
+/// Uses `payload.messages` when the caller built the conversation, otherwise sends the prompt as one user turn.
+fn request_body(config: &ToolSpecificConfig, payload: &Value, stream: bool) -> Value {
+    let messages = payload.get("messages")
+        .filter(|m| m.is_array())
+        .cloned()
+        .unwrap_or_else(|| json!([{ "role": "user", "content": prompt_from_payload(payload) }]));
+
+    let mut body = json!({
+        "model": config.model.as_deref().unwrap_or(DEFAULT_MODEL),
+        "max_tokens": config.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+        "messages": messages,
+    });
+    if let Some(temperature) = config.temperature {
+        body["temperature"] = json!(temperature);
+    }
+    if let Some(system) = payload.get("system").and_then(|s| s.as_str()) {
+        body["system"] = json!(system);
+    }
+    if stream {
+        body["stream"] = json!(true);
+    }
+    body
+}
+
+async fn read_stream(mut response: reqwest::Response, on_delta: DeltaSink<'_>) -> Result<Value, AppError> {
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut reply = StreamedReply::default();
+
+    while let Some(chunk) = response.chunk().await.map_err(network_error)? {
+        buffer.extend_from_slice(&chunk);
+        for data in drain_sse_events(&mut buffer) {
+            let Ok(event) = serde_json::from_str::<Value>(&data) else {
+                log::debug!("Skipping malformed stream event: {}", data);
+                continue;
+            };
+            reply.apply(&event, on_delta)?;
+        }
+    }
+
+    Ok(reply.finish())
+}
+
+/// A streamed reply as far as its events have come.
+struct StreamedReply {
+    text: String,
+    id: Value,
+    model: Value,
+    stop_reason: Value,
+    usage: Value,
+}
+
+impl Default for StreamedReply {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            id: Value::Null,
+            model: Value::Null,
+            stop_reason: Value::Null,
+            usage: json!({ "input_tokens": 0, "output_tokens": 0 }),
+        }
+    }
+}
+
+impl StreamedReply {
+    fn apply(&mut self, event: &Value, on_delta: DeltaSink<'_>) -> Result<(), AppError> {
+        match event.get("type").and_then(|t| t.as_str()) {
+            Some("message_start") => {
+                let message = &event["message"];
+                self.id = message["id"].clone();
+                self.model = message["model"].clone();
+                if let Some(input) = message["usage"]["input_tokens"].as_i64() {
+                    self.usage["input_tokens"] = json!(input);
+                }
+            }
+            Some("content_block_delta") => {
+                if let Some(delta) = event["delta"]["text"].as_str() {
+                    self.text.push_str(delta);
+                    on_delta(delta);
+                }
+            }
+            Some("message_delta") => {
+                self.stop_reason = event["delta"]["stop_reason"].clone();
+                if let Some(output) = event["usage"]["output_tokens"].as_i64() {
+                    self.usage["output_tokens"] = json!(output);
+                }
+            }
+            Some("error") => {
+                let kind = event["error"]["type"].as_str().unwrap_or_default();
+                let message = event["error"]["message"].as_str().unwrap_or("stream error").to_string();
+                return Err(api_error(None, kind, message, None));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Value {
+        json!({
+            "id": self.id,
+            "model": self.model,
+            "message": self.text,
+            "stop_reason": self.stop_reason,
+            "usage": self.usage,
+        })
+    }
+}
+
+async fn error_from_response(response: reqwest::Response) -> AppError {
+    let status = response.status();
+    let retry_after = retry_after(response.headers());
+    let body = response.text().await.unwrap_or_default();
+    error_from_body(status, body, retry_after)
+}
+
+/// Error bodies look like `{"type":"error","error":{"type":"overloaded_error","message":"..."}}`.
+fn error_from_body(status: reqwest::StatusCode, body: String, retry_after: Option<u64>) -> AppError {
+    let parsed: Option<Value> = serde_json::from_str(&body).ok();
+    let error = parsed.as_ref().map(|b| &b["error"]);
+
+    let kind = error.and_then(|e| e["type"].as_str()).unwrap_or_default();
+    let message = error.and_then(|e| e["message"].as_str())
+        .map(str::to_string)
+        .unwrap_or(body);
+    api_error(Some(status), kind, message, retry_after)
+}
+
+fn api_error(status: Option<reqwest::StatusCode>, kind: &str, message: String, retry_after_secs: Option<u64>) -> AppError {
+    match kind {
+        "authentication_error" | "permission_error" => AppError::InvalidApiKey(message),
+        "rate_limit_error" => AppError::RateLimited { message, retry_after_secs },
+        "overloaded_error" => AppError::Overloaded(message),
+        _ => match status {
+            Some(status) => status_error(status, message, retry_after_secs),
+            None => AppError::Provider(message),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    fn error_body(kind: &str, message: &str) -> String {
+        json!({ "type": "error", "error": { "type": kind, "message": message } }).to_string()
+    }
+
+    #[test]
+    fn a_rejected_key_is_an_invalid_api_key() {
+        let error = error_from_body(StatusCode::UNAUTHORIZED, error_body("authentication_error", "invalid x-api-key"), None);
+        assert!(matches!(error, AppError::InvalidApiKey(message) if message == "invalid x-api-key"));
+    }
+
+    #[test]
+    fn a_rate_limit_keeps_when_to_retry() {
+        let error = error_from_body(StatusCode::TOO_MANY_REQUESTS, error_body("rate_limit_error", "slow down"), Some(30));
+        assert!(matches!(error, AppError::RateLimited { retry_after_secs: Some(30), .. }));
+    }
+
+    #[test]
+    fn an_overloaded_api_is_told_apart_from_other_server_errors() {
+        let overloaded = error_from_body(StatusCode::from_u16(529).unwrap(), error_body("overloaded_error", "Overloaded"), None);
+        assert!(matches!(overloaded, AppError::Overloaded(_)));
+        let failed = error_from_body(StatusCode::INTERNAL_SERVER_ERROR, error_body("api_error", "oops"), None);
+        assert!(matches!(failed, AppError::ServerError(_)));
+    }
+
+    #[test]
+    fn a_body_that_isnt_json_falls_back_to_the_status() {
+        let error = error_from_body(StatusCode::TOO_MANY_REQUESTS, "<html>Too Many Requests</html>".to_string(), None);
+        assert!(matches!(error, AppError::RateLimited { message, .. } if message.contains("Too Many Requests")));
+    }
+
+    #[test]
+    fn a_reply_carries_its_text_and_usage() {
+        let message = json!({
+            "id": "msg_1",
+            "model": "claude-3-5-sonnet-latest",
+            "content": [{ "type": "text", "text": "Hello" }, { "type": "tool_use", "id": "t" }, { "type": "text", "text": " there" }],
+            "stop_reason": "end_turn",
+            "usage": { "input_tokens": 12, "output_tokens": 5 },
+        });
+
+        let reply = reply_from_message(&message);
+
+        assert_eq!(reply["message"], "Hello there");
+        assert_eq!(reply["usage"]["input_tokens"], 12);
+        assert_eq!(reply["usage"]["output_tokens"], 5);
+    }
+
+    #[test]
+    fn a_streamed_reply_counts_usage_from_its_start_and_delta_events() {
+        let events = [
+            json!({ "type": "message_start", "message": { "id": "msg_1", "model": "m", "usage": { "input_tokens": 20, "output_tokens": 1 } } }),
+            json!({ "type": "content_block_delta", "delta": { "type": "text_delta", "text": "Hi" } }),
+            json!({ "type": "message_delta", "delta": { "stop_reason": "end_turn" }, "usage": { "output_tokens": 7 } }),
+        ];
+        let mut deltas = Vec::new();
+        let mut on_delta = |delta: &str| deltas.push(delta.to_string());
+        let mut reply = StreamedReply::default();
+        for event in &events {
+            reply.apply(event, &mut on_delta).unwrap();
+        }
+
+        let reply = reply.finish();
+        assert_eq!(reply["message"], "Hi");
+        assert_eq!(reply["usage"]["input_tokens"], 20);
+        assert_eq!(reply["usage"]["output_tokens"], 7);
+        assert_eq!(deltas, ["Hi"]);
+    }
+
+    #[test]
+    fn an_error_event_mid_stream_is_typed_too() {
+        let event = json!({ "type": "error", "error": { "type": "overloaded_error", "message": "Overloaded" } });
+        let result = StreamedReply::default().apply(&event, &mut |_: &str| {});
+        assert!(matches!(result, Err(AppError::Overloaded(_))));
+    }
+}
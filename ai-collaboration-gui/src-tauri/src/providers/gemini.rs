@@ -0,0 +1,237 @@
+use serde_json::{json, Value};
+use crate::commands::ai_tools::ToolSpecificConfig;
+use crate::error::AppError;
+use super::{drain_sse_events, network_error, proxy, send_error, prompt_from_payload, retry_after, status_error, DeltaSink, ModelInfo};
+
+const DEFAULT_ENDPOINT: &str = "https://generativelanguage.googleapis.com";
+const DEFAULT_MODEL: &str = "gemini-1.5-flash";
+// Finish reasons that mean the candidate was withheld rather than completed
+const BLOCKING_FINISH_REASONS: [&str; 5] = ["SAFETY", "RECITATION", "BLOCKLIST", "PROHIBITED_CONTENT", "SPII"];
+
+/// Calls `generateContent` (or `streamGenerateContent` over SSE when `on_delta` is given) and
+/// returns `{ model, message, finish_reason, usage }`. Blocked prompts and candidates are errors.
+pub(crate) async fn generate_content(config: &ToolSpecificConfig, payload: &Value, on_delta: Option<DeltaSink<'_>>) -> Result<Value, AppError> {
+    let model = config.model.as_deref().unwrap_or(DEFAULT_MODEL);
+    let path = match on_delta {
+        Some(_) => format!("/v1beta/models/{}:streamGenerateContent?alt=sse", model),
+        None => format!("/v1beta/models/{}:generateContent", model),
+    };
+    let response = request(config, reqwest::Method::POST, &path)?
+        .json(&request_body(config, payload))
+        .send()
+        .await
+        .map_err(|e| send_error(config, e))?;
+    if !response.status().is_success() {
+        return Err(error_from_response(response).await);
+    }
+
+    let mut reply = Reply::default();
+    match on_delta {
+        Some(on_delta) => {
+            let mut response = response;
+            let mut buffer: Vec<u8> = Vec::new();
+            while let Some(chunk) = response.chunk().await.map_err(network_error)? {
+                buffer.extend_from_slice(&chunk);
+                for data in drain_sse_events(&mut buffer) {
+                    let Ok(chunk) = serde_json::from_str::<Value>(&data) else {
+                        log::debug!("Skipping malformed stream event: {}", data);
+                        continue;
+                    };
+                    let delta = reply.absorb(&chunk)?;
+                    if !delta.is_empty() {
+                        on_delta(&delta);
+                    }
+                }
+            }
+        }
+        None => {
+            let body: Value = response.json().await.map_err(network_error)?;
+            reply.absorb(&body)?;
+        }
+    }
+
+    Ok(json!({
+        "model": reply.model.unwrap_or_else(|| model.to_string()),
+        "message": reply.text,
+        "finish_reason": reply.finish_reason,
+        "usage": {
+            "input_tokens": reply.prompt_tokens,
+            "output_tokens": reply.completion_tokens,
+        },
+    }))
+}
+
+/// Lists one model to check that the key is accepted.
+pub(crate) async fn ping(config: &ToolSpecificConfig) -> Result<(), AppError> {
+    let response = request(config, reqwest::Method::GET, "/v1beta/models?pageSize=1")?
+        .send()
+        .await
+        .map_err(|e| send_error(config, e))?;
+    if !response.status().is_success() {
+        return Err(error_from_response(response).await);
+    }
+    Ok(())
+}
+
+/// Lists the models that can generate content, following pagination.
+pub(crate) async fn list_models(config: &ToolSpecificConfig) -> Result<Vec<ModelInfo>, AppError> {
+    let mut models = Vec::new();
+    let mut page_token: Option<String> = None;
+    loop {
+        let path = match &page_token {
+            Some(token) => format!("/v1beta/models?pageSize=1000&pageToken={}", token),
+            None => "/v1beta/models?pageSize=1000".to_string(),
+        };
+        let response = request(config, reqwest::Method::GET, &path)?
+            .send()
+            .await
+            .map_err(|e| send_error(config, e))?;
+        if !response.status().is_success() {
+            return Err(error_from_response(response).await);
+        }
+
+        let page: Value = response.json().await.map_err(network_error)?;
+        models.extend(page["models"].as_array().into_iter().flatten().filter_map(|m| {
+            let methods: Vec<&str> = m["supportedGenerationMethods"].as_array()?
+                .iter()
+                .filter_map(|method| method.as_str())
+                .collect();
+            if !methods.contains(&"generateContent") {
+                return None; // embedding and other non-chat models
+            }
+            let id = m["name"].as_str()?.trim_start_matches("models/");
+            Some(ModelInfo {
+                id: id.to_string(),
+                display_name: m["displayName"].as_str().unwrap_or(id).to_string(),
+                context_window: m["inputTokenLimit"].as_u64().map(|n| n as u32),
+                supports_streaming: methods.contains(&"streamGenerateContent"),
+            })
+        }));
+        page_token = page["nextPageToken"].as_str().filter(|t| !t.is_empty()).map(str::to_string);
+        if page_token.is_none() {
+            return Ok(models);
+        }
+    }
+}
+
+#[derive(Default)]
+struct Reply {
+    text: String,
+    model: Option<String>,
+    finish_reason: Option<String>,
+    prompt_tokens: i64,
+    completion_tokens: i64,
+}
+
+impl Reply {
+    /// Folds one response (or stream chunk) in and returns the text it added.
+    fn absorb(&mut self, response: &Value) -> Result<String, AppError> {
+        if let Some(reason) = response["promptFeedback"]["blockReason"].as_str() {
+            return Err(AppError::ContentBlocked(format!("prompt blocked ({})", reason)));
+        }
+        if let Some(model) = response["modelVersion"].as_str() {
+            self.model = Some(model.to_string());
+        }
+        // Streamed chunks repeat the running totals, so the last one wins
+        let usage = &response["usageMetadata"];
+        if let Some(prompt) = usage["promptTokenCount"].as_i64() {
+            self.prompt_tokens = prompt;
+        }
+        if let Some(completion) = usage["candidatesTokenCount"].as_i64() {
+            self.completion_tokens = completion;
+        }
+
+        let candidate = &response["candidates"][0];
+        if let Some(reason) = candidate["finishReason"].as_str() {
+            if BLOCKING_FINISH_REASONS.contains(&reason) {
+                return Err(AppError::ContentBlocked(format!("response withheld ({})", reason)));
+            }
+            self.finish_reason = Some(reason.to_string());
+        }
+        let delta: String = candidate["content"]["parts"].as_array()
+            .map(|parts| parts.iter().filter_map(|p| p["text"].as_str()).collect())
+            .unwrap_or_default();
+        self.text.push_str(&delta);
+        Ok(delta)
+    }
+}
+
+fn request(config: &ToolSpecificConfig, method: reqwest::Method, path: &str) -> Result<reqwest::RequestBuilder, AppError> {
+    let api_key = config.api_key.clone()
+        .filter(|key| !key.is_empty())
+        .or_else(|| std::env::var("GOOGLE_API_KEY").ok())
+        .ok_or_else(|| AppError::InvalidApiKey("no API key configured".to_string()))?;
+    let endpoint = config.endpoint.as_deref()
+        .filter(|e| !e.is_empty())
+        .unwrap_or(DEFAULT_ENDPOINT)
+        .trim_end_matches('/');
+
+    Ok(proxy::client_for(config)?.request(method, format!("{}{}", endpoint, path))
+        .header("x-goog-api-key", api_key))
+}
+
+/// `payload.history` holds earlier turns as `{ role, content }` with role `user` or
+/// `assistant`/`model`; the prompt is appended as the last user turn.
+fn request_body(config: &ToolSpecificConfig, payload: &Value) -> Value {
+    let mut contents: Vec<Value> = payload.get("history")
+        .and_then(|h| h.as_array())
+        .map(|turns| turns.iter()
+            .filter_map(|turn| {
+                let text = turn["content"].as_str()?;
+                let role = match turn["role"].as_str() {
+                    Some("assistant") | Some("model") => "model",
+                    _ => "user",
+                };
+                Some(json!({ "role": role, "parts": [{ "text": text }] }))
+            })
+            .collect())
+        .unwrap_or_default();
+    contents.push(json!({ "role": "user", "parts": [{ "text": prompt_from_payload(payload) }] }));
+
+    let mut generation_config = json!({});
+    if let Some(max_tokens) = config.max_tokens {
+        generation_config["maxOutputTokens"] = json!(max_tokens);
+    }
+    if let Some(temperature) = config.temperature {
+        generation_config["temperature"] = json!(temperature);
+    }
+
+    let mut body = json!({
+        "contents": contents,
+        "generationConfig": generation_config,
+    });
+    if let Some(system) = payload.get("system").and_then(|s| s.as_str()) {
+        body["systemInstruction"] = json!({ "parts": [{ "text": system }] });
+    }
+    body
+}
+
+/// Error bodies look like `{"error":{"code":400,"status":"INVALID_ARGUMENT","message":"...","details":[...]}}`.
+async fn error_from_response(response: reqwest::Response) -> AppError {
+    let status = response.status();
+    let header_retry_after = retry_after(response.headers());
+    let body = response.text().await.unwrap_or_default();
+    let parsed: Value = serde_json::from_str(&body).unwrap_or(Value::Null);
+    let error = &parsed["error"];
+
+    let message = error["message"].as_str().map(str::to_string).unwrap_or(body);
+    // An invalid key is reported as a plain 400 with this reason in the details
+    let invalid_key = error["details"].as_array()
+        .is_some_and(|details| details.iter().any(|d| d["reason"].as_str() == Some("API_KEY_INVALID")));
+    if invalid_key {
+        return AppError::InvalidApiKey(message);
+    }
+    // Quota errors carry a RetryInfo detail such as `"retryDelay": "27s"`
+    let retry_after_secs = error["details"].as_array()
+        .and_then(|details| details.iter().find_map(|d| d["retryDelay"].as_str()))
+        .and_then(|delay| delay.trim_end_matches('s').parse::<f64>().ok())
+        .map(|secs| secs.ceil() as u64)
+        .or(header_retry_after);
+
+    match error["status"].as_str() {
+        Some("UNAUTHENTICATED") | Some("PERMISSION_DENIED") => AppError::InvalidApiKey(message),
+        Some("RESOURCE_EXHAUSTED") => AppError::RateLimited { message, retry_after_secs },
+        Some("UNAVAILABLE") => AppError::Overloaded(message),
+        _ => status_error(status, message, retry_after_secs),
+    }
+}
@@ -0,0 +1,106 @@
+//! HTTP backends for tools configured with `transport: "http"`.
+
+pub mod anthropic;
+pub mod gemini;
+pub mod ollama;
+pub mod proxy;
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use crate::commands::ai_tools::ToolSpecificConfig;
+use crate::error::AppError;
+
+/// A model a tool can be configured with, as listed by its provider or a built-in list.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelInfo {
+    pub id: String,
+    pub display_name: String,
+    pub context_window: Option<u32>,
+    pub supports_streaming: bool,
+}
+
+// One client for every provider so connections are pooled; tools behind a proxy get their own
+pub(crate) static HTTP_CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .connect_timeout(std::time::Duration::from_secs(10))
+        .build()
+        .expect("failed to build HTTP client")
+});
+
+/// Called with each piece of text as a streamed response arrives.
+pub(crate) type DeltaSink<'a> = &'a mut (dyn FnMut(&str) + Send);
+
+/// Maps an HTTP error status to the error kind the frontend reacts to.
+pub(crate) fn status_error(status: reqwest::StatusCode, message: String, retry_after_secs: Option<u64>) -> AppError {
+    match status.as_u16() {
+        401 | 403 => AppError::InvalidApiKey(message),
+        407 => AppError::Proxy(format!("proxy authentication failed: {}", message)),
+        429 => AppError::RateLimited { message, retry_after_secs },
+        503 | 529 => AppError::Overloaded(message),
+        500..=599 => AppError::ServerError(format!("{}: {}", status, message)),
+        _ => AppError::Provider(format!("{}: {}", status, message)),
+    }
+}
+
+/// The `retry-after` header in seconds (the HTTP-date form is not used by these APIs).
+pub(crate) fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<u64> {
+    headers.get(reqwest::header::RETRY_AFTER)?
+        .to_str().ok()?
+        .trim()
+        .parse().ok()
+}
+
+/// For errors sending a request: when it went through a proxy, failing to connect or to open
+/// the tunnel means the proxy is unreachable or misconfigured.
+pub(crate) fn send_error(config: &ToolSpecificConfig, error: reqwest::Error) -> AppError {
+    let proxied = error.url().is_some_and(|url| proxy::is_proxied(config, url));
+    if proxied && (error.is_connect() || error_chain(&error).contains("tunnel")) {
+        return AppError::Proxy(format!("could not connect through the proxy: {}", error_chain(&error)));
+    }
+    network_error(error)
+}
+
+// reqwest's own message leaves out the cause, such as the proxy's refusal
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut text = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        text.push_str(": ");
+        text.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    text
+}
+
+pub(crate) fn network_error(error: reqwest::Error) -> AppError {
+    if error.is_timeout() {
+        AppError::Timeout(error.to_string())
+    } else {
+        AppError::Network(error.to_string())
+    }
+}
+
+/// Text of the prompt in a command payload, for callers that did not send a message list.
+pub(crate) fn prompt_from_payload(payload: &serde_json::Value) -> String {
+    ["prompt", "message", "content"].iter()
+        .find_map(|key| payload.get(*key).and_then(|v| v.as_str()))
+        .map(str::to_string)
+        .unwrap_or_else(|| payload.to_string())
+}
+
+/// Splits complete server-sent events off the front of `buffer`, returning the `data:` line of each.
+pub(crate) fn drain_sse_events(buffer: &mut Vec<u8>) -> Vec<String> {
+    let mut events = Vec::new();
+    while let Some(end) = buffer.windows(2).position(|w| w == b"\n\n") {
+        let event: Vec<u8> = buffer.drain(..end + 2).collect();
+        let data: Vec<&str> = std::str::from_utf8(&event).unwrap_or_default()
+            .lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .map(str::trim_start)
+            .collect();
+        if !data.is_empty() {
+            events.push(data.join("\n"));
+        }
+    }
+    events
+}
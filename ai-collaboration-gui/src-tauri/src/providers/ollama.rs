@@ -0,0 +1,199 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::commands::ai_tools::ToolSpecificConfig;
+use crate::error::AppError;
+use super::{network_error, proxy, send_error, prompt_from_payload, retry_after, status_error, DeltaSink, ModelInfo};
+
+pub(crate) const DEFAULT_ENDPOINT: &str = "http://localhost:11434";
+
+/// A model installed in the local Ollama server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaModel {
+    pub name: String,
+    pub size: Option<u64>,
+    pub family: Option<String>,
+    pub parameter_size: Option<String>,
+    pub quantization_level: Option<String>,
+}
+
+impl From<&OllamaModel> for ModelInfo {
+    fn from(model: &OllamaModel) -> Self {
+        ModelInfo {
+            id: model.name.clone(),
+            display_name: match &model.parameter_size {
+                Some(size) => format!("{} ({})", model.name, size),
+                None => model.name.clone(),
+            },
+            // Depends on how the model is run (num_ctx), not on the model alone
+            context_window: None,
+            supports_streaming: true,
+        }
+    }
+}
+
+/// Lists installed models via `/api/tags`; also how a connection is verified.
+pub(crate) async fn list_models(config: &ToolSpecificConfig) -> Result<Vec<OllamaModel>, AppError> {
+    let response = proxy::client_for(config)?.get(format!("{}/api/tags", endpoint(config)))
+        .send()
+        .await
+        .map_err(|e| send_error(config, e))?;
+    if !response.status().is_success() {
+        return Err(error_from_response(response).await);
+    }
+
+    let body: Value = response.json().await.map_err(network_error)?;
+    let models = body["models"].as_array()
+        .map(|models| models.iter()
+            .filter_map(|m| Some(OllamaModel {
+                name: m["name"].as_str()?.to_string(),
+                size: m["size"].as_u64(),
+                family: m["details"]["family"].as_str().map(str::to_string),
+                parameter_size: m["details"]["parameter_size"].as_str().map(str::to_string),
+                quantization_level: m["details"]["quantization_level"].as_str().map(str::to_string),
+            }))
+            .collect())
+        .unwrap_or_default();
+    Ok(models)
+}
+
+/// Payloads with `messages` or `history` go to `/api/chat`, plain prompts to `/api/generate`.
+/// Streaming responses are newline-delimited JSON; each piece of text is passed to `on_delta`.
+/// Returns `{ model, message, done_reason, usage }`.
+pub(crate) async fn generate(config: &ToolSpecificConfig, payload: &Value, on_delta: Option<DeltaSink<'_>>) -> Result<Value, AppError> {
+    let model = match config.model.clone().filter(|m| !m.is_empty()) {
+        Some(model) => model,
+        None => list_models(config).await?
+            .into_iter()
+            .next()
+            .map(|m| m.name)
+            .ok_or_else(|| AppError::Provider("no models installed; run `ollama pull <model>` first".to_string()))?,
+    };
+    let chat = payload.get("messages").is_some() || payload.get("history").is_some();
+
+    let mut body = json!({
+        "model": model,
+        "stream": on_delta.is_some(),
+        "options": options(config),
+    });
+    if chat {
+        body["messages"] = json!(chat_messages(payload));
+    } else {
+        body["prompt"] = json!(prompt_from_payload(payload));
+        if let Some(system) = payload.get("system").and_then(|s| s.as_str()) {
+            body["system"] = json!(system);
+        }
+    }
+
+    let path = if chat { "/api/chat" } else { "/api/generate" };
+    let mut response = proxy::client_for(config)?.post(format!("{}{}", endpoint(config), path))
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| send_error(config, e))?;
+    if !response.status().is_success() {
+        return Err(error_from_response(response).await);
+    }
+
+    let mut text = String::new();
+    let mut last = Value::Null;
+    match on_delta {
+        Some(on_delta) => {
+            let mut buffer: Vec<u8> = Vec::new();
+            while let Some(chunk) = response.chunk().await.map_err(network_error)? {
+                buffer.extend_from_slice(&chunk);
+                while let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+                    let line: Vec<u8> = buffer.drain(..=end).collect();
+                    let Ok(part) = serde_json::from_slice::<Value>(&line) else {
+                        continue;
+                    };
+                    if let Some(error) = part["error"].as_str() {
+                        return Err(AppError::Provider(error.to_string()));
+                    }
+                    let delta = response_text(&part);
+                    if !delta.is_empty() {
+                        text.push_str(delta);
+                        on_delta(delta);
+                    }
+                    last = part;
+                }
+            }
+        }
+        None => {
+            last = response.json().await.map_err(network_error)?;
+            text = response_text(&last).to_string();
+        }
+    }
+
+    // The final (`done`) object carries the counters
+    Ok(json!({
+        "model": last["model"].as_str().unwrap_or(&model),
+        "message": text,
+        "done_reason": last["done_reason"],
+        "usage": {
+            "input_tokens": last["prompt_eval_count"].as_i64().unwrap_or(0),
+            "output_tokens": last["eval_count"].as_i64().unwrap_or(0),
+        },
+    }))
+}
+
+pub(crate) fn endpoint(config: &ToolSpecificConfig) -> String {
+    config.endpoint.as_deref()
+        .filter(|e| !e.is_empty())
+        .unwrap_or(DEFAULT_ENDPOINT)
+        .trim_end_matches('/')
+        .to_string()
+}
+
+fn options(config: &ToolSpecificConfig) -> Value {
+    let mut options = json!({});
+    if let Some(max_tokens) = config.max_tokens {
+        options["num_predict"] = json!(max_tokens);
+    }
+    if let Some(temperature) = config.temperature {
+        options["temperature"] = json!(temperature);
+    }
+    options
+}
+
+/// `messages` is passed through as is; otherwise `system`, `history` and the prompt are combined.
+fn chat_messages(payload: &Value) -> Vec<Value> {
+    if let Some(messages) = payload.get("messages").and_then(|m| m.as_array()) {
+        return messages.clone();
+    }
+
+    let mut messages = Vec::new();
+    if let Some(system) = payload.get("system").and_then(|s| s.as_str()) {
+        messages.push(json!({ "role": "system", "content": system }));
+    }
+    if let Some(history) = payload.get("history").and_then(|h| h.as_array()) {
+        for turn in history {
+            let Some(content) = turn["content"].as_str() else {
+                continue;
+            };
+            let role = match turn["role"].as_str() {
+                Some("assistant") | Some("model") => "assistant",
+                _ => "user",
+            };
+            messages.push(json!({ "role": role, "content": content }));
+        }
+    }
+    messages.push(json!({ "role": "user", "content": prompt_from_payload(payload) }));
+    messages
+}
+
+// `/api/generate` answers in `response`, `/api/chat` in `message.content`
+fn response_text(part: &Value) -> &str {
+    part["response"].as_str()
+        .or_else(|| part["message"]["content"].as_str())
+        .unwrap_or_default()
+}
+
+async fn error_from_response(response: reqwest::Response) -> AppError {
+    let status = response.status();
+    let retry_after = retry_after(response.headers());
+    let body = response.text().await.unwrap_or_default();
+    let message = serde_json::from_str::<Value>(&body).ok()
+        .and_then(|b| b["error"].as_str().map(str::to_string))
+        .unwrap_or(body);
+    status_error(status, message, retry_after)
+}
@@ -0,0 +1,188 @@
+//! Outbound proxy for tool backends. Set globally in the `proxy` app setting or per tool under
+//! `additional_config.proxy`, which replaces the global one. Passwords stay in the keychain.
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use crate::commands::ai_tools::ToolSpecificConfig;
+use crate::database::Database;
+use crate::error::AppError;
+use crate::secrets;
+
+const SETTING: &str = "proxy";
+const CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+type ProxyFor = fn(&str) -> reqwest::Result<reqwest::Proxy>;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ProxySettings {
+    #[serde(default)]
+    pub http_proxy: Option<String>,
+    #[serde(default)]
+    pub https_proxy: Option<String>,
+    // Comma-separated hosts and domains reached directly, or `*` for all
+    #[serde(default)]
+    pub no_proxy: Option<String>,
+    // Basic auth; the password is kept in the keychain under `proxy:<username>`
+    #[serde(default)]
+    pub username: Option<String>,
+}
+
+impl ProxySettings {
+    fn is_empty(&self) -> bool {
+        self.http_proxy.is_none() && self.https_proxy.is_none()
+    }
+
+    fn validate(&self) -> Result<(), AppError> {
+        for url in self.http_proxy.iter().chain(&self.https_proxy) {
+            let parsed = reqwest::Url::parse(url)
+                .map_err(|e| AppError::Proxy(format!("invalid proxy URL {}: {}", url, e)))?;
+            if !matches!(parsed.scheme(), "http" | "https") {
+                return Err(AppError::Proxy(format!("unsupported proxy scheme in {}", url)));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Clients are pooled per proxy setup; cleared when settings or passwords change
+static CLIENTS: Lazy<Mutex<HashMap<ProxySettings, reqwest::Client>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+// The stored global proxy, read once at startup so building a client needs no database
+static GLOBAL: Lazy<Mutex<Option<ProxySettings>>> = Lazy::new(|| Mutex::new(None));
+
+/// Reads the global proxy from the settings table; called once the database is open.
+pub fn load_global_settings(db: &Database) -> Result<(), AppError> {
+    let settings = match db.get_setting(SETTING)? {
+        Some(value) => serde_json::from_str::<Option<ProxySettings>>(&value)
+            .map_err(|e| AppError::Proxy(format!("unreadable proxy settings: {}", e)))?,
+        None => None,
+    };
+    *GLOBAL.lock().unwrap() = settings.filter(|s| !s.is_empty());
+    Ok(())
+}
+
+pub fn global_settings() -> Option<ProxySettings> {
+    GLOBAL.lock().unwrap().clone()
+}
+
+/// `None` removes the global proxy. The password, if given, needs a username to be stored under.
+pub fn save_global_settings(db: &Database, settings: Option<&ProxySettings>, password: Option<&str>) -> Result<(), AppError> {
+    if let Some(settings) = settings {
+        settings.validate()?;
+        if let Some(password) = password {
+            let username = settings.username.as_deref()
+                .ok_or_else(|| AppError::Proxy("a proxy password needs a username".to_string()))?;
+            secrets::store_api_key(&keychain_name(username), password)?;
+        }
+    }
+    let value = serde_json::to_string(&settings).map_err(|e| AppError::Internal(e.to_string()))?;
+    db.set_setting(SETTING, &value)?;
+    *GLOBAL.lock().unwrap() = settings.filter(|s| !s.is_empty()).cloned();
+    CLIENTS.lock().unwrap().clear();
+    Ok(())
+}
+
+/// The tool's own proxy when its config has one, otherwise the global proxy.
+pub fn settings_for(config: &ToolSpecificConfig) -> Result<Option<ProxySettings>, AppError> {
+    let Some(value) = config.additional_config.get("proxy") else {
+        return Ok(global_settings());
+    };
+    let settings: ProxySettings = serde_json::from_value(value.clone())
+        .map_err(|e| AppError::Proxy(format!("invalid proxy in tool config: {}", e)))?;
+    settings.validate()?;
+    Ok(Some(settings).filter(|s| !s.is_empty()))
+}
+
+/// The client for a tool's requests: the shared one, or one that goes through its proxy.
+pub(crate) fn client_for(config: &ToolSpecificConfig) -> Result<reqwest::Client, AppError> {
+    let Some(settings) = settings_for(config)? else {
+        return Ok(super::HTTP_CLIENT.clone());
+    };
+    if let Some(client) = CLIENTS.lock().unwrap().get(&settings) {
+        return Ok(client.clone());
+    }
+
+    let password = password(&settings)?;
+    let no_proxy = settings.no_proxy.as_deref().and_then(reqwest::NoProxy::from_string);
+    let mut builder = reqwest::Client::builder().connect_timeout(CONNECT_TIMEOUT);
+    let schemes: [(&Option<String>, ProxyFor); 2] = [
+        (&settings.http_proxy, |url| reqwest::Proxy::http(url)),
+        (&settings.https_proxy, |url| reqwest::Proxy::https(url)),
+    ];
+    for (url, proxy) in schemes {
+        let Some(url) = url else {
+            continue;
+        };
+        let mut proxy = proxy(url)
+            .map_err(|e| AppError::Proxy(format!("invalid proxy URL {}: {}", url, e)))?
+            .no_proxy(no_proxy.clone());
+        if let Some(username) = &settings.username {
+            proxy = proxy.basic_auth(username, password.as_deref().unwrap_or_default());
+        }
+        builder = builder.proxy(proxy);
+    }
+    let client = builder.build()
+        .map_err(|e| AppError::Proxy(format!("failed to set up proxy: {}", e)))?;
+
+    CLIENTS.lock().unwrap().insert(settings, client.clone());
+    Ok(client)
+}
+
+/// `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY` (and their lowercase forms, which some CLIs read
+/// instead) for a spawned tool, with the credentials in the proxy URLs.
+pub(crate) fn env_vars(config: &ToolSpecificConfig) -> Result<Vec<(String, String)>, AppError> {
+    let Some(settings) = settings_for(config)? else {
+        return Ok(Vec::new());
+    };
+    let password = password(&settings)?;
+
+    let mut vars = Vec::new();
+    for (name, url) in [("HTTP_PROXY", &settings.http_proxy), ("HTTPS_PROXY", &settings.https_proxy)] {
+        let Some(url) = url else {
+            continue;
+        };
+        let mut url = reqwest::Url::parse(url)
+            .map_err(|e| AppError::Proxy(format!("invalid proxy URL {}: {}", url, e)))?;
+        if let Some(username) = &settings.username {
+            // Only fails for URLs without a host, which validate() has already rejected
+            let _ = url.set_username(username);
+            let _ = url.set_password(password.as_deref());
+        }
+        vars.push((name.to_string(), url.to_string()));
+    }
+    if let Some(no_proxy) = &settings.no_proxy {
+        vars.push(("NO_PROXY".to_string(), no_proxy.clone()));
+    }
+    let lowercase: Vec<(String, String)> = vars.iter().map(|(name, value)| (name.to_lowercase(), value.clone())).collect();
+    vars.extend(lowercase);
+    Ok(vars)
+}
+
+/// Whether a request to `url` goes through the tool's proxy, so a failed connection was one to the proxy.
+pub(crate) fn is_proxied(config: &ToolSpecificConfig, url: &reqwest::Url) -> bool {
+    let Ok(Some(settings)) = settings_for(config) else {
+        return false;
+    };
+    let proxy = if url.scheme() == "https" { &settings.https_proxy } else { &settings.http_proxy };
+    let host = url.host_str().unwrap_or_default();
+    proxy.is_some() && !settings.no_proxy.as_deref().is_some_and(|no_proxy| bypasses(no_proxy, host))
+}
+
+fn bypasses(no_proxy: &str, host: &str) -> bool {
+    no_proxy.split(',')
+        .map(|entry| entry.trim().trim_start_matches('.'))
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| entry == "*" || host == entry || host.ends_with(&format!(".{}", entry)))
+}
+
+pub(crate) fn password(settings: &ProxySettings) -> Result<Option<String>, AppError> {
+    match &settings.username {
+        Some(username) => Ok(secrets::load_api_key(&keychain_name(username))?),
+        None => Ok(None),
+    }
+}
+
+fn keychain_name(username: &str) -> String {
+    format!("proxy:{}", username)
+}
@@ -0,0 +1,260 @@
+//! Periodic jobs started from setup. Each job runs on its own task, never overlapping with
+//! itself, and its interval comes from the app settings, so a new value takes effect as soon as
+//! it is saved. A run that fails or panics is recorded on the job and the next one goes ahead.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tauri::{AppHandle, Manager};
+use tokio::sync::Notify;
+use tokio::task::AbortHandle;
+use tokio_util::sync::CancellationToken;
+use crate::commands::{self, AppSettings};
+use crate::state::{AppState, BackgroundTasks};
+
+pub type JobFuture = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
+
+pub struct JobSpec {
+    pub name: &'static str,
+    // None while the job is turned off
+    pub interval: fn(&AppSettings) -> Option<Duration>,
+    // Where the last run is stored, so a restart doesn't run the job again early. Jobs without
+    // one first run an interval after startup
+    pub last_run_setting: Option<&'static str>,
+    pub run: fn(AppHandle) -> JobFuture,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    pub name: String,
+    pub interval_secs: Option<i64>,
+    pub running: bool,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub last_duration_ms: Option<u64>,
+    pub last_error: Option<String>,
+    pub next_run_at: Option<DateTime<Utc>>,
+}
+
+struct Job {
+    spec: JobSpec,
+    status: Mutex<JobStatus>,
+    current: Mutex<Option<AbortHandle>>,
+    // Wakes the schedule to work out the next run again after a manual one
+    rescheduled: Notify,
+}
+
+/// Kept in `AppState`; `start` is called once from setup.
+pub struct Scheduler {
+    jobs: Vec<Arc<Job>>,
+    shutdown: CancellationToken,
+}
+
+impl Scheduler {
+    pub fn new(specs: Vec<JobSpec>) -> Self {
+        let jobs = specs.into_iter()
+            .map(|spec| Arc::new(Job {
+                status: Mutex::new(JobStatus {
+                    name: spec.name.to_string(),
+                    interval_secs: None,
+                    running: false,
+                    last_run_at: None,
+                    last_duration_ms: None,
+                    last_error: None,
+                    next_run_at: None,
+                }),
+                spec,
+                current: Mutex::new(None),
+                rescheduled: Notify::new(),
+            }))
+            .collect();
+        Self { jobs, shutdown: CancellationToken::new() }
+    }
+
+    pub fn start(&self, app: &AppHandle, tasks: &BackgroundTasks) {
+        let state = app.state::<AppState>();
+        let started_at = Utc::now();
+        for job in &self.jobs {
+            let stored = job.spec.last_run_setting
+                .and_then(|key| state.db.get_setting(key).ok().flatten())
+                .and_then(|value| DateTime::parse_from_rfc3339(&value).ok())
+                .map(|t| t.with_timezone(&Utc));
+            job.status.lock().unwrap().last_run_at = stored;
+            // Counted from startup when there is no last run to go by
+            let due_from = match job.spec.last_run_setting {
+                Some(_) => stored,
+                None => Some(started_at),
+            };
+            tasks.spawn(job.spec.name, run_schedule(app.clone(), job.clone(), due_from, self.shutdown.clone()));
+        }
+    }
+
+    pub fn list(&self) -> Vec<JobStatus> {
+        self.jobs.iter().map(|job| job.status.lock().unwrap().clone()).collect()
+    }
+
+    /// Starts a run of `name` outside its schedule and returns the job as it is then. Fails if
+    /// the job is already running.
+    pub fn run_now(&self, app: &AppHandle, name: &str) -> Result<JobStatus> {
+        let job = self.jobs.iter()
+            .find(|job| job.spec.name == name)
+            .ok_or_else(|| anyhow!("No background job named {}", name))?
+            .clone();
+        if !job.claim() {
+            return Err(anyhow!("{} is already running", name));
+        }
+        let status = job.status.lock().unwrap().clone();
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            execute(&app, &job).await;
+            job.rescheduled.notify_one();
+        });
+        Ok(status)
+    }
+
+    /// Stops the schedules and aborts the runs in progress. Called when the app exits.
+    pub fn shutdown(&self) {
+        self.shutdown.cancel();
+        for job in &self.jobs {
+            if let Some(run) = job.current.lock().unwrap().take() {
+                log::info!("Aborting the running {} job", job.spec.name);
+                run.abort();
+            }
+        }
+    }
+}
+
+impl Job {
+    // Marks the job running, unless it already is
+    fn claim(&self) -> bool {
+        let mut status = self.status.lock().unwrap();
+        if status.running {
+            return false;
+        }
+        status.running = true;
+        true
+    }
+}
+
+async fn run_schedule(app: AppHandle, job: Arc<Job>, due_from: Option<DateTime<Utc>>, shutdown: CancellationToken) {
+    let mut settings = app.state::<AppState>().settings.subscribe();
+    loop {
+        let interval = (job.spec.interval)(&settings.borrow_and_update());
+        let next_run = interval.map(|interval| {
+            let last_run = job.status.lock().unwrap().last_run_at.or(due_from);
+            last_run.map_or_else(Utc::now, |t| t + interval)
+        });
+        {
+            let mut status = job.status.lock().unwrap();
+            status.interval_secs = interval.map(|interval| interval.num_seconds());
+            status.next_run_at = next_run;
+        }
+        let wait = next_run
+            .map(|t| (t - Utc::now()).to_std().unwrap_or_default())
+            .unwrap_or(std::time::Duration::MAX);
+
+        tokio::select! {
+            _ = tokio::time::sleep(wait), if next_run.is_some() => {
+                if job.claim() {
+                    execute(&app, &job).await;
+                } else {
+                    // Only a manual run can be going; it reschedules when it finishes
+                    log::debug!("Skipping the {} job: the previous run is still going", job.spec.name);
+                    tokio::select! {
+                        _ = job.rescheduled.notified() => {}
+                        _ = shutdown.cancelled() => return,
+                    }
+                }
+            }
+            _ = job.rescheduled.notified() => {}
+            changed = settings.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+            }
+            _ = shutdown.cancelled() => return,
+        }
+    }
+}
+
+// Runs a claimed job on its own task, so a panic ends that run only
+async fn execute(app: &AppHandle, job: &Job) {
+    let name = job.spec.name;
+    log::debug!("Running the {} job", name);
+    let started_at = Utc::now();
+    let start = Instant::now();
+
+    let run = tauri::async_runtime::spawn((job.spec.run)(app.clone()));
+    *job.current.lock().unwrap() = Some(run.inner().abort_handle());
+    let error = match run.await {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(tauri::Error::JoinError(e)) if e.is_panic() => Some("The job panicked".to_string()),
+        Err(e) => Some(e.to_string()),
+    };
+    *job.current.lock().unwrap() = None;
+    let duration_ms = start.elapsed().as_millis() as u64;
+
+    match &error {
+        None => log::debug!(job = name, duration_ms = duration_ms; "The {} job finished", name),
+        Some(e) => log::warn!(job = name, duration_ms = duration_ms; "The {} job failed: {}", name, e),
+    }
+    if let Some(key) = job.spec.last_run_setting {
+        let db = &app.state::<AppState>().db;
+        if let Err(e) = db.set_setting(key, &started_at.to_rfc3339()) {
+            log::warn!("Failed to record the run of the {} job: {}", name, e);
+        }
+    }
+    let mut status = job.status.lock().unwrap();
+    status.running = false;
+    status.last_run_at = Some(started_at);
+    status.last_duration_ms = Some(duration_ms);
+    status.last_error = error;
+}
+
+/// The jobs the app runs.
+pub fn app_jobs() -> Vec<JobSpec> {
+    vec![
+        JobSpec {
+            name: "retention cleanup",
+            interval: |settings| Some(Duration::hours(settings.retention_interval_hours)),
+            last_run_setting: Some("retention_last_run"),
+            run: |app| Box::pin(async move {
+                let counts = commands::run_cleanup(app.state::<AppState>().db.clone(), None).await?;
+                log::info!("Retention cleanup finished: {:?}", counts);
+                Ok(())
+            }),
+        },
+        JobSpec {
+            name: "tool health checks",
+            interval: |settings| Some(Duration::seconds(settings.tool_health_interval_secs as i64)),
+            last_run_setting: None,
+            run: |app| Box::pin(commands::check_connected_tools(app)),
+        },
+        JobSpec {
+            name: "database backup",
+            interval: |settings| Some(Duration::hours(settings.db_backup_interval_hours)),
+            last_run_setting: Some("db_backup_last_run"),
+            run: |app| Box::pin(async move {
+                let path = commands::backup_database(&app).await?;
+                log::info!("Backed up the database to {}", path.display());
+                Ok(())
+            }),
+        },
+        JobSpec {
+            name: "workspace cleanup",
+            interval: |settings| (settings.workspace_max_age_days > 0)
+                .then(|| Duration::hours(settings.workspace_cleanup_interval_hours)),
+            last_run_setting: Some("workspace_cleanup_last_run"),
+            run: |app| Box::pin(async move {
+                let days = app.state::<AppState>().settings.borrow().workspace_max_age_days;
+                let removed = commands::cleanup_workspaces(app.state(), None, Some(days)).await.map_err(|e| anyhow!(e))?;
+                log::info!("Removed {} workspace(s) older than {} days", removed, days);
+                Ok(())
+            }),
+        },
+    ]
+}
@@ -0,0 +1,193 @@
+//! API keys live in the OS keychain under `clauder/<tool name>`, and secret environment variables
+//! under `clauder/env:<name>`; the database only keeps a placeholder.
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use crate::database::Database;
+
+const KEYCHAIN_SERVICE: &str = "clauder";
+/// Stored in a config in place of a key that is kept in the keychain.
+pub const API_KEY_PLACEHOLDER: &str = "<keychain>";
+// Config blobs saved by the frontend use camelCase
+const API_KEY_FIELDS: [&str; 2] = ["api_key", "apiKey"];
+const MIGRATED_SETTING: &str = "api_keys_in_keychain";
+// Environment variables whose values are scrubbed from logs even when no config mentions them
+const API_KEY_ENV_VARS: [&str; 3] = ["ANTHROPIC_API_KEY", "GOOGLE_API_KEY", "OPENAI_API_KEY"];
+
+pub fn store_api_key(tool_name: &str, key: &str) -> Result<()> {
+    entry(tool_name)?
+        .set_password(key)
+        .map_err(|e| anyhow!("Failed to store API key of {} in the keychain: {}", tool_name, e))
+}
+
+pub fn load_api_key(tool_name: &str) -> Result<Option<String>> {
+    match entry(tool_name)?.get_password() {
+        Ok(key) => Ok(Some(key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(anyhow!("Failed to read API key of {} from the keychain: {}", tool_name, e)),
+    }
+}
+
+pub fn delete_api_key(tool_name: &str) -> Result<()> {
+    match entry(tool_name)?.delete_password() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(anyhow!("Failed to delete API key of {} from the keychain: {}", tool_name, e)),
+    }
+}
+
+pub fn store_env_secret(name: &str, value: &str) -> Result<()> {
+    entry(&env_account(name))?
+        .set_password(value)
+        .map_err(|e| anyhow!("Failed to store {} in the keychain: {}", name, e))
+}
+
+pub fn load_env_secret(name: &str) -> Result<Option<String>> {
+    match entry(&env_account(name))?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(anyhow!("Failed to read {} from the keychain: {}", name, e)),
+    }
+}
+
+pub fn delete_env_secret(name: &str) -> Result<()> {
+    match entry(&env_account(name))?.delete_password() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(anyhow!("Failed to delete {} from the keychain: {}", name, e)),
+    }
+}
+
+// Kept apart from the tool names API keys are stored under
+fn env_account(name: &str) -> String {
+    format!("env:{}", name)
+}
+
+fn entry(tool_name: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, tool_name)
+        .map_err(|e| anyhow!("Failed to open keychain entry for {}: {}", tool_name, e))
+}
+
+/// True for a missing key, one that only points at the keychain, or a masked copy the
+/// frontend echoed back; none of these should replace the stored key.
+pub fn is_placeholder(key: Option<&str>) -> bool {
+    key.is_none_or(|k| k.is_empty() || k == API_KEY_PLACEHOLDER || k == "***" || k.contains("..."))
+}
+
+/// Shows only the ends of a secret, e.g. `sk-a...f00d`; short values are fully hidden.
+pub fn mask_secret(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() > 8 {
+        let head: String = chars[..4].iter().collect();
+        let tail: String = chars[chars.len() - 4..].iter().collect();
+        format!("{}...{}", head, tail)
+    } else {
+        "***".to_string()
+    }
+}
+
+/// Every log line that may contain a config, payload or spawn environment goes through this.
+/// Replaces the given secrets and the values of the API key environment variables.
+pub fn redact<'a>(text: &str, secrets: impl IntoIterator<Item = &'a str>) -> String {
+    let mut secrets: Vec<String> = secrets.into_iter().map(str::to_string).collect();
+    secrets.extend(API_KEY_ENV_VARS.iter().filter_map(|var| std::env::var(var).ok()));
+
+    secrets.iter()
+        .filter(|secret| !is_placeholder(Some(secret.as_str())))
+        .fold(text.to_string(), |text, secret| text.replace(secret.as_str(), "[REDACTED]"))
+}
+
+/// Moves a plaintext key out of a JSON config into the keychain, leaving the placeholder behind.
+/// Returns whether anything was moved.
+pub fn extract_api_key(tool_name: &str, config: &mut Value) -> Result<bool> {
+    let Some(object) = config.as_object_mut() else {
+        return Ok(false);
+    };
+
+    let mut moved = false;
+    for field in API_KEY_FIELDS {
+        let Some(key) = object.get(field).and_then(|v| v.as_str()).map(str::to_string) else {
+            continue;
+        };
+        if is_placeholder(Some(&key)) {
+            continue;
+        }
+        store_api_key(tool_name, &key)?;
+        object.insert(field.to_string(), Value::from(API_KEY_PLACEHOLDER));
+        moved = true;
+    }
+    Ok(moved)
+}
+
+/// Removes the key fields from a config on its way to the frontend; returns whether a key was set.
+pub fn strip_api_key(config: &mut Value) -> bool {
+    let Some(object) = config.as_object_mut() else {
+        return false;
+    };
+    API_KEY_FIELDS.iter()
+        .filter_map(|field| object.remove(*field))
+        .any(|value| value.as_str().is_some_and(|key| !key.is_empty()))
+}
+
+/// Points the stored configs of the tool at the keychain entry, or clears them when `present` is false.
+pub fn mark_stored_configs(db: &Database, tool_name: &str, present: bool) -> Result<()> {
+    let mark = |config: &str| -> String {
+        let Ok(mut config) = serde_json::from_str::<Value>(config) else {
+            return config.to_string();
+        };
+        strip_api_key(&mut config);
+        if present {
+            if let Some(object) = config.as_object_mut() {
+                object.insert("api_key".to_string(), Value::from(API_KEY_PLACEHOLDER));
+            }
+        }
+        config.to_string()
+    };
+
+    if let Some(mut tool) = db.get_ai_tool(tool_name)? {
+        tool.config = mark(&tool.config);
+        tool.updated_at = chrono::Utc::now();
+        db.save_ai_tool(&tool)?;
+    }
+    for mut row in db.get_ai_tool_configs()?.into_iter().filter(|c| c.tool_name == tool_name) {
+        row.config = mark(&row.config);
+        row.updated_at = chrono::Utc::now();
+        db.save_ai_tool_config(&row)?;
+    }
+    Ok(())
+}
+
+/// One-time move of keys saved in plaintext before the keychain was used. Tools are keyed by
+/// id, stored configs by tool name. Left unmarked if the keychain fails so the next start retries.
+pub fn migrate_plaintext_keys(db: &Database) -> Result<usize> {
+    if db.get_setting(MIGRATED_SETTING)?.is_some() {
+        return Ok(0);
+    }
+
+    let mut moved = 0;
+    for mut tool in db.get_ai_tools()? {
+        let Ok(mut config) = serde_json::from_str::<Value>(&tool.config) else {
+            continue;
+        };
+        if extract_api_key(&tool.id, &mut config)? {
+            tool.config = config.to_string();
+            db.save_ai_tool(&tool)?;
+            moved += 1;
+        }
+    }
+    for mut row in db.get_ai_tool_configs()? {
+        let Ok(mut config) = serde_json::from_str::<Value>(&row.config) else {
+            continue;
+        };
+        if extract_api_key(&row.tool_name, &mut config)? {
+            row.config = config.to_string();
+            db.save_ai_tool_config(&row)?;
+            moved += 1;
+        }
+    }
+
+    if moved > 0 {
+        // Rewritten rows leave the old values in free pages until the file is vacuumed
+        db.vacuum_database()?;
+    }
+    db.set_setting(MIGRATED_SETTING, "1")?;
+    Ok(moved)
+}
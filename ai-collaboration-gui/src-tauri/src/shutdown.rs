@@ -0,0 +1,139 @@
+//! Ordered shutdown, run once when the main window is closed or the app is asked to exit.
+//! Exit is held back until every phase has run, or until `SHUTDOWN_TIMEOUT` has passed, when
+//! the app exits regardless. `app:shutting-down` goes out first so the UI can show that it is
+//! busy; from then on every command is refused with a `shutting_down` error.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::{Duration, Instant};
+use tauri::ipc::Invoke;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+use crate::commands;
+use crate::error::AppError;
+use crate::state::AppState;
+
+pub const SHUTTING_DOWN_EVENT: &str = "app:shutting-down";
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+const RUNNING: u8 = 0;
+const STOPPING: u8 = 1;
+const STOPPED: u8 = 2;
+
+/// How far the shutdown has got.
+#[derive(Default)]
+pub struct ShutdownStage(AtomicU8);
+
+impl ShutdownStage {
+    pub fn accepting_commands(&self) -> bool {
+        self.0.load(Ordering::SeqCst) == RUNNING
+    }
+
+    // False if the shutdown had already begun
+    fn start(&self) -> bool {
+        self.0.compare_exchange(RUNNING, STOPPING, Ordering::SeqCst, Ordering::SeqCst).is_ok()
+    }
+
+    fn finish(&self) {
+        self.0.store(STOPPED, Ordering::SeqCst);
+    }
+}
+
+/// Whether the phases are over, so the exit can go ahead. Without a state there is nothing to
+/// shut down.
+pub fn finished(app: &AppHandle) -> bool {
+    app.try_state::<AppState>().is_none_or(|state| state.shutdown.0.load(Ordering::SeqCst) == STOPPED)
+}
+
+/// Starts the shutdown unless it has already begun; the app exits with `code` once it is done.
+/// The caller prevents the close or exit that asked for it.
+pub fn begin(app: &AppHandle, code: i32) {
+    if !app.state::<AppState>().shutdown.start() {
+        return;
+    }
+    log::info!("Shutting down; new commands are refused");
+    if let Err(e) = app.emit(SHUTTING_DOWN_EVENT, ()) {
+        log::warn!("Failed to emit {}: {}", SHUTTING_DOWN_EVENT, e);
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let start = Instant::now();
+        match tokio::time::timeout(SHUTDOWN_TIMEOUT, run(&app)).await {
+            Ok(()) => log::info!("Shut down in {} ms", start.elapsed().as_millis()),
+            Err(_) => log::warn!("Shutdown did not finish within {} s; exiting anyway", SHUTDOWN_TIMEOUT.as_secs()),
+        }
+        app.state::<AppState>().shutdown.finish();
+        app.exit(code);
+    });
+}
+
+/// For an exit that did not go through `begin`: runs the phases to the end, bounded by the
+/// same timeout.
+pub fn run_blocking(app: &AppHandle) {
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    if !state.shutdown.start() {
+        return;
+    }
+    tauri::async_runtime::block_on(async {
+        if tokio::time::timeout(SHUTDOWN_TIMEOUT, run(app)).await.is_err() {
+            log::warn!("Shutdown did not finish within {} s", SHUTDOWN_TIMEOUT.as_secs());
+        }
+    });
+    state.shutdown.finish();
+}
+
+async fn run(app: &AppHandle) {
+    let state = app.state::<AppState>();
+
+    // Before the work is cancelled, so the snapshots still list the tasks that were running
+    phase("snapshot swarms", async {
+        commands::snapshot_running_swarms(app);
+    }).await;
+    phase("cancel running work", async {
+        state.tools.cancel_all_commands();
+        state.searches.cancel_all();
+    }).await;
+    phase("flush pending writes", async {
+        if let Err(e) = state.db.flush() {
+            log::warn!("Failed to flush the database: {}", e);
+        }
+        log::logger().flush();
+    }).await;
+    phase("stop watchers and background jobs", async {
+        state.tails.stop_all();
+        state.jobs.shutdown();
+        state.tasks.abort_all();
+    }).await;
+    phase("stop child processes", async {
+        state.managed_processes.stop_all();
+        commands::shutdown_tool_processes(&state).await;
+    }).await;
+}
+
+// Logged on the way in as well, so a phase that hangs shows in the log
+async fn phase(name: &str, work: impl Future<Output = ()>) {
+    log::info!("Shutdown: {}", name);
+    let start = Instant::now();
+    work.await;
+    log::debug!(phase = name, duration_ms = start.elapsed().as_millis() as u64; "Shutdown phase finished");
+}
+
+/// Wraps the app's command handler so commands invoked once shutdown has begun are refused.
+pub fn refuse_when_stopping<R, H>(handler: H) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static
+where
+    R: Runtime,
+    H: Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+{
+    move |invoke| {
+        let accepting = invoke.message.webview().try_state::<AppState>()
+            .is_none_or(|state| state.shutdown.accepting_commands());
+        if accepting {
+            return handler(invoke);
+        }
+        log::debug!("Refusing {} during shutdown", invoke.message.command());
+        invoke.resolver.reject(AppError::ShuttingDown);
+        true
+    }
+}
@@ -0,0 +1,100 @@
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tauri::async_runtime::JoinHandle;
+use tokio::sync::watch;
+use crate::approvals::PendingApprovals;
+use crate::commands::{load_app_settings, AppSettings, ManagedProcesses, ProcessRegistry, ResolvedEnv, SearchRegistry, SwarmRuns, TailRegistry, ToolRegistry};
+use crate::database::Database;
+use crate::navigation::NavigationQueue;
+use crate::notifications::NotificationTarget;
+use crate::path_access::SessionRoots;
+use crate::scheduler::{self, Scheduler};
+use crate::shutdown::ShutdownStage;
+use crate::telemetry::Telemetry;
+
+/// What the commands share. Built in `run`'s setup and registered with `app.manage`, so a
+/// command reaches it as `State<'_, AppState>`.
+pub struct AppState {
+    pub db: Database,
+    // Background jobs subscribe to pick up new intervals as soon as they are saved
+    pub settings: watch::Sender<AppSettings>,
+    pub processes: Arc<ProcessRegistry>,
+    pub tools: ToolRegistry,
+    // Started by execute_command_streaming
+    pub managed_processes: ManagedProcesses,
+    pub runs: SwarmRuns,
+    pub tails: TailRegistry,
+    pub tasks: BackgroundTasks,
+    pub jobs: Scheduler,
+    // Timings of the commands, recorded by `#[timed]`
+    pub telemetry: Telemetry,
+    // Operations of swarms waiting for the user to allow them
+    pub approvals: PendingApprovals,
+    // Held back until the frontend listens for `app:navigate`
+    pub navigation: NavigationQueue,
+    pub notification_target: NotificationTarget,
+    // Commands are refused once it has begun
+    pub shutdown: ShutdownStage,
+    // Paths the user let the file commands touch until the app exits
+    pub session_roots: SessionRoots,
+    // Searches and archive operations still running, cancelled by token or on shutdown
+    pub searches: SearchRegistry,
+    // The app's environment variables, with secrets loaded from the keychain
+    pub resolved_env: ResolvedEnv,
+}
+
+impl AppState {
+    /// State around `db` with no tool processes, tails or tasks, and no job started: all a test needs to run the
+    /// command logic without a Tauri app.
+    #[cfg(test)]
+    pub fn new(db: Database) -> Self {
+        Self::with_processes(db, Arc::default())
+    }
+
+    /// `run` keeps its own handle on the process registry, to kill leftover tools if it unwinds.
+    pub fn with_processes(db: Database, processes: Arc<ProcessRegistry>) -> Self {
+        let (settings, _) = watch::channel(load_app_settings(&db));
+        Self {
+            db,
+            settings,
+            processes,
+            tools: ToolRegistry::default(),
+            managed_processes: ManagedProcesses::default(),
+            runs: SwarmRuns::default(),
+            tails: TailRegistry::default(),
+            tasks: BackgroundTasks::default(),
+            jobs: Scheduler::new(scheduler::app_jobs()),
+            telemetry: Telemetry::default(),
+            approvals: PendingApprovals::default(),
+            navigation: NavigationQueue::default(),
+            notification_target: NotificationTarget::default(),
+            shutdown: ShutdownStage::default(),
+            session_roots: SessionRoots::default(),
+            searches: SearchRegistry::default(),
+            resolved_env: ResolvedEnv::default(),
+        }
+    }
+}
+
+/// Long-running tasks started with the app, kept so they can be stopped when it exits.
+#[derive(Default)]
+pub struct BackgroundTasks {
+    handles: Mutex<Vec<(&'static str, JoinHandle<()>)>>,
+}
+
+impl BackgroundTasks {
+    pub fn spawn<F>(&self, name: &'static str, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let handle = tauri::async_runtime::spawn(task);
+        self.handles.lock().unwrap().push((name, handle));
+    }
+
+    pub fn abort_all(&self) {
+        for (name, handle) in self.handles.lock().unwrap().drain(..) {
+            log::info!("Stopping {}", name);
+            handle.abort();
+        }
+    }
+}
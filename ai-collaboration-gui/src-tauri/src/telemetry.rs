@@ -0,0 +1,145 @@
+//! Timings of the app's own commands, kept in memory for the diagnostics screen. Only names,
+//! durations and outcomes are recorded, never arguments or results. Commands opt in with
+//! `#[timed]` below `#[tauri::command]`.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Instant;
+
+pub use ai_collaboration_gui_macros::timed;
+
+// Calls kept for `recent_slow`, across all commands
+const MAX_RECENT_CALLS: usize = 1000;
+// Durations kept per command for its percentiles
+const MAX_SAMPLES: usize = 512;
+
+/// The recorded calls. Kept in `AppState`, which is where `#[timed]` records into.
+#[derive(Default)]
+pub struct Telemetry(Mutex<Metrics>);
+
+#[derive(Default)]
+struct Metrics {
+    recent: VecDeque<CommandCall>,
+    commands: HashMap<&'static str, Counters>,
+}
+
+#[derive(Default)]
+struct Counters {
+    calls: u64,
+    errors: u64,
+    total_ms: f64,
+    max_ms: f64,
+    samples: VecDeque<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandCall {
+    pub command: &'static str,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: f64,
+    pub success: bool,
+}
+
+/// Totals since startup. The percentiles cover the last calls only.
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandMetrics {
+    pub command: String,
+    pub calls: u64,
+    pub errors: u64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+/// What a command's return value says about whether it worked.
+pub trait Outcome {
+    fn succeeded(&self) -> bool;
+}
+
+impl<T, E> Outcome for Result<T, E> {
+    fn succeeded(&self) -> bool {
+        self.is_ok()
+    }
+}
+
+pub async fn track<F, T>(telemetry: &Telemetry, command: &'static str, call: F) -> T
+where
+    F: Future<Output = T>,
+    T: Outcome,
+{
+    let started_at = Utc::now();
+    let start = Instant::now();
+    let result = call.await;
+    telemetry.record(command, started_at, start, result.succeeded());
+    result
+}
+
+impl Telemetry {
+    /// Per-command figures, sorted by command name.
+    pub fn command_metrics(&self) -> Vec<CommandMetrics> {
+        let metrics = self.0.lock().unwrap();
+        let mut all: Vec<CommandMetrics> = metrics.commands.iter()
+            .map(|(command, counters)| {
+                let mut samples: Vec<f64> = counters.samples.iter().copied().collect();
+                samples.sort_by(f64::total_cmp);
+                CommandMetrics {
+                    command: command.to_string(),
+                    calls: counters.calls,
+                    errors: counters.errors,
+                    mean_ms: counters.total_ms / counters.calls.max(1) as f64,
+                    p50_ms: percentile(&samples, 50),
+                    p95_ms: percentile(&samples, 95),
+                    max_ms: counters.max_ms,
+                }
+            })
+            .collect();
+        all.sort_by(|a, b| a.command.cmp(&b.command));
+        all
+    }
+
+    /// The recent calls that took at least `threshold_ms`, newest first.
+    pub fn recent_slow(&self, threshold_ms: f64) -> Vec<CommandCall> {
+        let metrics = self.0.lock().unwrap();
+        metrics.recent.iter()
+            .rev()
+            .filter(|call| call.duration_ms >= threshold_ms)
+            .cloned()
+            .collect()
+    }
+
+    fn record(&self, command: &'static str, started_at: DateTime<Utc>, start: Instant, success: bool) {
+        let duration_ms = start.elapsed().as_secs_f64() * 1000.0;
+        log::debug!(command = command, duration_ms = duration_ms, success = success; "Command finished");
+
+        let mut metrics = self.0.lock().unwrap();
+        let counters = metrics.commands.entry(command).or_default();
+        counters.calls += 1;
+        if !success {
+            counters.errors += 1;
+        }
+        counters.total_ms += duration_ms;
+        counters.max_ms = counters.max_ms.max(duration_ms);
+        if counters.samples.len() == MAX_SAMPLES {
+            counters.samples.pop_front();
+        }
+        counters.samples.push_back(duration_ms);
+
+        if metrics.recent.len() == MAX_RECENT_CALLS {
+            metrics.recent.pop_front();
+        }
+        metrics.recent.push_back(CommandCall { command, started_at, duration_ms, success });
+    }
+}
+
+// Nearest-rank percentile of sorted samples
+fn percentile(sorted: &[f64], percent: usize) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
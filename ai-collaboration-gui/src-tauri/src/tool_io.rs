@@ -0,0 +1,193 @@
+//! Raw request/response log of tool commands, off unless the `tool_io_logging` setting enables it.
+//! Each chat session or swarm gets `<app data>/logs/tool-io/<id>.log`, rotated by size to
+//! `<id>.log.1`, `<id>.log.2`, ...
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use once_cell::sync::{Lazy, OnceCell};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use crate::database::Database;
+
+const SETTING: &str = "tool_io_logging";
+const DEFAULT_MAX_FILE_BYTES: u64 = 5 * 1024 * 1024;
+const DEFAULT_MAX_ROTATIONS: usize = 3;
+// Upper bounds so a runaway swarm can't fill the disk
+const MAX_FILE_BYTES_LIMIT: u64 = 100 * 1024 * 1024;
+const MAX_ROTATIONS_LIMIT: usize = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IoLoggingSettings {
+    pub enabled: bool,
+    pub max_file_bytes: u64,
+    // Rotated files kept besides the current one
+    pub max_rotations: usize,
+}
+
+impl Default for IoLoggingSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_file_bytes: DEFAULT_MAX_FILE_BYTES,
+            max_rotations: DEFAULT_MAX_ROTATIONS,
+        }
+    }
+}
+
+static LOG_DIR: OnceCell<PathBuf> = OnceCell::new();
+// Appends and rotations of the same file must not interleave
+static WRITE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// One logged exchange. Callers redact secrets before building it.
+pub struct Exchange<'a> {
+    pub tool_id: &'a str,
+    pub command_id: &'a str,
+    pub command_type: &'a str,
+    pub request: &'a str,
+    pub response: &'a str,
+    pub success: bool,
+    pub latency_ms: i64,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Set once at startup; until then nothing is logged.
+pub fn init(app_data_dir: &Path) {
+    let _ = LOG_DIR.set(app_data_dir.join("logs").join("tool-io"));
+}
+
+/// Unreadable or missing settings mean logging is off.
+pub fn settings(db: &Database) -> IoLoggingSettings {
+    db.get_setting(SETTING)
+        .ok()
+        .flatten()
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_settings(db: &Database, settings: &IoLoggingSettings) -> Result<()> {
+    if !(1024..=MAX_FILE_BYTES_LIMIT).contains(&settings.max_file_bytes) {
+        return Err(anyhow!("max_file_bytes must be between 1024 and {}", MAX_FILE_BYTES_LIMIT));
+    }
+    if settings.max_rotations > MAX_ROTATIONS_LIMIT {
+        return Err(anyhow!("max_rotations cannot exceed {}", MAX_ROTATIONS_LIMIT));
+    }
+    db.set_setting(SETTING, &serde_json::to_string(settings)?)
+}
+
+pub fn append(id: &str, exchange: &Exchange, settings: &IoLoggingSettings) -> Result<()> {
+    let path = log_path(id)?;
+
+    let entry = format!(
+        "=== {} tool={} command={} type={} status={} latency_ms={}\n>>> request\n{}\n<<< response\n{}\n\n",
+        exchange.timestamp.to_rfc3339(),
+        exchange.tool_id,
+        exchange.command_id,
+        exchange.command_type,
+        if exchange.success { "ok" } else { "error" },
+        exchange.latency_ms,
+        exchange.request.trim_end(),
+        exchange.response.trim_end(),
+    );
+
+    let _lock = WRITE_LOCK.lock().unwrap();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    if size > 0 && size + entry.len() as u64 > settings.max_file_bytes {
+        rotate(&path, settings.max_rotations.min(MAX_ROTATIONS_LIMIT))?;
+    }
+    OpenOptions::new().create(true).append(true).open(&path)?
+        .write_all(entry.as_bytes())?;
+    Ok(())
+}
+
+/// The last `lines` lines logged for a session or swarm, reading into older rotations as needed.
+pub fn tail(id: &str, lines: usize) -> Result<Vec<String>> {
+    let path = log_path(id)?;
+    let mut tail: Vec<String> = Vec::new();
+    for rotation in 0..=MAX_ROTATIONS_LIMIT {
+        if tail.len() >= lines {
+            break;
+        }
+        let file = rotated_path(&path, rotation);
+        let content = match fs::read_to_string(&file) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => break,
+            Err(e) => return Err(anyhow!("Failed to read {}: {}", file.display(), e)),
+        };
+        let older: Vec<String> = content.lines().map(str::to_string).collect();
+        let take = (lines - tail.len()).min(older.len());
+        tail.splice(0..0, older[older.len() - take..].iter().cloned());
+    }
+    Ok(tail)
+}
+
+/// Deletes log files, rotations included, last written before the cutoff. Returns how many were removed.
+pub fn clear_older_than(cutoff: DateTime<Utc>) -> Result<usize> {
+    let dir = LOG_DIR.get().ok_or_else(|| anyhow!("Tool I/O logging is not initialized"))?;
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+
+    let _lock = WRITE_LOCK.lock().unwrap();
+    let mut removed = 0;
+    for entry in entries {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        let modified: DateTime<Utc> = metadata.modified()?.into();
+        if modified < cutoff {
+            fs::remove_file(entry.path())?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+// Ids come from the frontend, so only plain names are accepted
+fn log_path(id: &str) -> Result<PathBuf> {
+    let dir = LOG_DIR.get().ok_or_else(|| anyhow!("Tool I/O logging is not initialized"))?;
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(anyhow!("Invalid log id: {:?}", id));
+    }
+    Ok(dir.join(format!("{}.log", id)))
+}
+
+fn rotated_path(path: &Path, rotation: usize) -> PathBuf {
+    if rotation == 0 {
+        return path.to_path_buf();
+    }
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", rotation));
+    PathBuf::from(name)
+}
+
+/// `<id>.log` becomes `<id>.log.1`, shifting older rotations up and dropping any past `max_rotations`.
+fn rotate(path: &Path, max_rotations: usize) -> Result<()> {
+    if max_rotations == 0 {
+        fs::remove_file(path)?;
+        return Ok(());
+    }
+    for rotation in (max_rotations..=MAX_ROTATIONS_LIMIT).rev() {
+        let stale = rotated_path(path, rotation);
+        if stale.exists() {
+            fs::remove_file(stale)?;
+        }
+    }
+    for rotation in (1..max_rotations).rev() {
+        let from = rotated_path(path, rotation);
+        if from.exists() {
+            fs::rename(from, rotated_path(path, rotation + 1))?;
+        }
+    }
+    fs::rename(path, rotated_path(path, 1))?;
+    Ok(())
+}
@@ -0,0 +1,147 @@
+//! The tray icon. Its tooltip counts the running swarms and connected tools, and its menu shows
+//! the window, pauses or stops each running swarm, and quits through the graceful shutdown. Both
+//! are rebuilt whenever `data:changed` reports a change to swarms, their tasks or tools.
+
+use serde_json::Value;
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::tray::TrayIconBuilder;
+use tauri::{AppHandle, Listener, Manager};
+use crate::commands;
+use crate::events::DATA_CHANGED_EVENT;
+use crate::shutdown;
+use crate::state::AppState;
+
+const TRAY_ID: &str = "main";
+const MAIN_WINDOW: &str = "main";
+const SHOW_ITEM: &str = "show";
+const QUIT_ITEM: &str = "quit";
+// Followed by the swarm's id
+const PAUSE_PREFIX: &str = "pause:";
+const STOP_PREFIX: &str = "stop:";
+// The `data:changed` entities that can change what the tray shows
+const WATCHED_ENTITIES: [&str; 3] = ["swarm", "swarm_task", "ai_tool"];
+
+struct TrayStatus {
+    running_swarms: Vec<(String, String)>,
+    connected_tools: usize,
+}
+
+pub fn init(app: &AppHandle) -> tauri::Result<()> {
+    let status = status(app);
+    let mut tray = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip(tooltip(&status))
+        .menu(&build_menu(app, &status)?)
+        .on_menu_event(|app, event| on_menu_item(app, event.id().as_ref()));
+    if let Some(icon) = app.default_window_icon() {
+        tray = tray.icon(icon.clone());
+    }
+    tray.build(app)?;
+
+    let handle = app.clone();
+    app.listen_any(DATA_CHANGED_EVENT, move |event| {
+        let entity = serde_json::from_str::<Value>(event.payload())
+            .ok()
+            .and_then(|change| change["entity"].as_str().map(str::to_string));
+        if entity.is_some_and(|entity| WATCHED_ENTITIES.contains(&entity.as_str())) {
+            refresh(&handle);
+        }
+    });
+    Ok(())
+}
+
+/// Whether closing the main window should hide it rather than quit: the `close_to_tray`
+/// setting is on and there is a tray icon to bring it back from.
+pub fn hides_on_close(app: &AppHandle) -> bool {
+    app.state::<AppState>().settings.borrow().close_to_tray && app.tray_by_id(TRAY_ID).is_some()
+}
+
+fn refresh(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    let status = status(app);
+    let updated = build_menu(app, &status)
+        .and_then(|menu| tray.set_menu(Some(menu)))
+        .and_then(|_| tray.set_tooltip(Some(tooltip(&status))));
+    if let Err(e) = updated {
+        log::warn!("Failed to update the tray: {}", e);
+    }
+}
+
+fn status(app: &AppHandle) -> TrayStatus {
+    let db = &app.state::<AppState>().db;
+    let running_swarms = match db.get_all_swarms() {
+        Ok(swarms) => swarms.into_iter()
+            .filter(|swarm| swarm.status == "running")
+            .map(|swarm| (swarm.id, swarm.name))
+            .collect(),
+        Err(e) => {
+            log::warn!("Failed to load swarms for the tray: {}", e);
+            Vec::new()
+        }
+    };
+    let connected_tools = db.get_ai_tools()
+        .map(|tools| tools.iter().filter(|tool| tool.status == "connected").count())
+        .unwrap_or_default();
+    TrayStatus { running_swarms, connected_tools }
+}
+
+fn tooltip(status: &TrayStatus) -> String {
+    let plural = |count: usize, noun: &str| format!("{} {}{}", count, noun, if count == 1 { "" } else { "s" });
+    format!(
+        "Clauder: {} running, {} connected",
+        plural(status.running_swarms.len(), "swarm"),
+        plural(status.connected_tools, "tool"),
+    )
+}
+
+fn build_menu(app: &AppHandle, status: &TrayStatus) -> tauri::Result<Menu<tauri::Wry>> {
+    let menu = Menu::new(app)?;
+    menu.append(&MenuItem::with_id(app, SHOW_ITEM, "Show window", true, None::<&str>)?)?;
+    if !status.running_swarms.is_empty() {
+        menu.append(&PredefinedMenuItem::separator(app)?)?;
+        for (id, name) in &status.running_swarms {
+            let swarm = Submenu::new(app, name, true)?;
+            swarm.append(&MenuItem::with_id(app, format!("{}{}", PAUSE_PREFIX, id), "Pause", true, None::<&str>)?)?;
+            swarm.append(&MenuItem::with_id(app, format!("{}{}", STOP_PREFIX, id), "Stop", true, None::<&str>)?)?;
+            menu.append(&swarm)?;
+        }
+    }
+    menu.append(&PredefinedMenuItem::separator(app)?)?;
+    menu.append(&MenuItem::with_id(app, QUIT_ITEM, "Quit", true, None::<&str>)?)?;
+    Ok(menu)
+}
+
+fn on_menu_item(app: &AppHandle, id: &str) {
+    if id == SHOW_ITEM {
+        show_main_window(app);
+    } else if id == QUIT_ITEM {
+        shutdown::begin(app, 0);
+    } else if let Some(swarm_id) = id.strip_prefix(PAUSE_PREFIX) {
+        let (app, swarm_id) = (app.clone(), swarm_id.to_string());
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = commands::pause_swarm(app.clone(), app.state(), swarm_id).await {
+                log::warn!("{}", e);
+            }
+        });
+    } else if let Some(swarm_id) = id.strip_prefix(STOP_PREFIX) {
+        let (app, swarm_id) = (app.clone(), swarm_id.to_string());
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = commands::stop_swarm(app.clone(), app.state(), swarm_id).await {
+                log::warn!("{}", e);
+            }
+        });
+    }
+}
+
+pub fn show_main_window(app: &AppHandle) {
+    let Some(window) = app.get_webview_window(MAIN_WINDOW) else {
+        return;
+    };
+    let shown = window.show()
+        .and_then(|_| window.unminimize())
+        .and_then(|_| window.set_focus());
+    if let Err(e) = shown {
+        log::warn!("Failed to show the main window: {}", e);
+    }
+}
@@ -1,7 +1,7 @@
 import React, { useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { useUIStore, useProjectStore, useSwarmStore } from '@/stores';
-import { MainLayout, ProjectList, AIToolsList, SwarmManager, FlowOrchestrator, ChatInterface, Workspace } from '@/components';
+import { MainLayout, ProjectList, AIToolsList, SwarmManager, FlowOrchestrator, ChatInterface, Workspace, NavigationHandler } from '@/components';
 import { ThemeProvider } from '@/components/ThemeProvider';
 import './App.css';
 
@@ -58,6 +58,7 @@ function App() {
       <MainLayout>
         {renderContent()}
       </MainLayout>
+      <NavigationHandler />
     </ThemeProvider>
   );
 }
@@ -0,0 +1,147 @@
+import React, { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import { useProjectStore, useSwarmStore, useUIStore } from '@/stores';
+import { Button } from '@/components/ui/button';
+import {
+  Dialog,
+  DialogContent,
+  DialogDescription,
+  DialogFooter,
+  DialogHeader,
+  DialogTitle,
+} from '@/components/ui/dialog';
+import { FolderOpen, Loader2 } from 'lucide-react';
+import type { Navigation, NavigationError } from '@/types';
+
+// Makes sure the project is loaded before selecting it; it may have been created since
+const openProject = async (projectId: string) => {
+  if (!useProjectStore.getState().projects.some(project => project.id === projectId)) {
+    await useProjectStore.getState().loadProjects();
+  }
+  useProjectStore.getState().selectProject(projectId);
+};
+
+const folderName = (path: string) =>
+  path.split(/[\\/]/).filter(Boolean).pop() || path;
+
+// Opens what `--project`, `--session`, `--swarm` or a clauder:// link asked for
+export const NavigationHandler: React.FC = () => {
+  const { addNotification, setActiveView } = useUIStore();
+  const { createProject, isLoading } = useProjectStore();
+  const [pathToRegister, setPathToRegister] = useState<string | null>(null);
+
+  useEffect(() => {
+    const route = async (navigation: Navigation) => {
+      switch (navigation.target) {
+        case 'project':
+          await openProject(navigation.id);
+          setActiveView('workspace');
+          break;
+        case 'register_project':
+          setPathToRegister(navigation.path);
+          break;
+        case 'session': {
+          if (navigation.project_id) {
+            await openProject(navigation.project_id);
+          }
+          const ui = useUIStore.getState();
+          if (!ui.chatSessions.some(session => session.id === navigation.id)) {
+            await ui.loadChatSessions();
+          }
+          useUIStore.getState().selectChatSession(navigation.id);
+          setActiveView('chat');
+          break;
+        }
+        case 'swarm':
+          await openProject(navigation.project_id);
+          if (!useSwarmStore.getState().getSwarmById(navigation.id)) {
+            await useSwarmStore.getState().loadSwarms();
+          }
+          useSwarmStore.getState().selectSwarm(navigation.id);
+          setActiveView('swarm');
+          break;
+      }
+    };
+
+    let unmounted = false;
+    const unlisten = Promise.all([
+      listen<Navigation>('app:navigate', event => {
+        route(event.payload).catch(error => console.error('Failed to navigate:', error));
+      }),
+      listen<NavigationError>('app:navigation-error', event => {
+        addNotification({
+          type: 'error',
+          title: '열 수 없음',
+          message: `${event.payload.input}: ${event.payload.message}`,
+        });
+      }),
+    ]);
+    // Only once both listeners are in place, or what arrived at startup would be lost
+    unlisten
+      .then(() => {
+        if (!unmounted) {
+          return invoke('navigation_ready');
+        }
+      })
+      .catch(error => console.error('Failed to listen for navigation:', error));
+
+    return () => {
+      unmounted = true;
+      unlisten.then(unlisteners => unlisteners.forEach(stop => stop()));
+    };
+  }, [addNotification, setActiveView]);
+
+  const registerProject = async () => {
+    if (!pathToRegister) {
+      return;
+    }
+    const name = folderName(pathToRegister);
+    try {
+      await createProject({ name, path: pathToRegister });
+      addNotification({
+        type: 'success',
+        title: '프로젝트 생성 완료',
+        message: `${name} 프로젝트가 성공적으로 생성되었습니다.`,
+      });
+      setActiveView('workspace');
+    } catch (error) {
+      addNotification({
+        type: 'error',
+        title: '프로젝트 생성 실패',
+        message: '프로젝트 생성 중 오류가 발생했습니다.',
+      });
+    }
+    setPathToRegister(null);
+  };
+
+  return (
+    <Dialog open={pathToRegister !== null} onOpenChange={open => !open && setPathToRegister(null)}>
+      <DialogContent className="sm:max-w-[500px]">
+        <DialogHeader>
+          <DialogTitle className="flex items-center space-x-2">
+            <FolderOpen className="h-5 w-5" />
+            <span>프로젝트로 등록</span>
+          </DialogTitle>
+          <DialogDescription>
+            {pathToRegister} 폴더는 아직 프로젝트가 아닙니다. 새 프로젝트로 등록할까요?
+          </DialogDescription>
+        </DialogHeader>
+        <DialogFooter>
+          <Button
+            type="button"
+            variant="outline"
+            onClick={() => setPathToRegister(null)}
+            disabled={isLoading}
+          >
+            취소
+          </Button>
+          <Button type="button" onClick={registerProject} disabled={isLoading}>
+            {isLoading && <Loader2 className="h-4 w-4 mr-2 animate-spin" />}
+            등록
+          </Button>
+        </DialogFooter>
+      </DialogContent>
+    </Dialog>
+  );
+};
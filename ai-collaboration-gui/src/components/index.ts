@@ -25,6 +25,9 @@ export { FileExplorer } from './FileExplorer';
 export { CodeEditor } from './CodeEditor';
 export { Terminal } from './Terminal';
 
+// Opening a project, session or swarm from outside the app
+export { NavigationHandler } from './NavigationHandler';
+
 // Theme components
 export { ThemeProvider } from './ThemeProvider';
 export { ThemeToggle, ThemeSettings, ThemePreview } from './ThemeToggle';
@@ -83,10 +83,10 @@ export const ProjectCreateForm: React.FC<ProjectCreateFormProps> = ({
         path: formData.path.trim(),
         description: formData.description.trim() || undefined,
         settings: {
-          defaultAITool: 'claude-code',
-          autoSave: formData.autoSave,
-          collaborationMode: formData.collaborationMode as any,
-          memoryRetention: formData.memoryRetention,
+          default_ai_tool: 'claude-code',
+          auto_save: formData.autoSave,
+          collaboration_mode: formData.collaborationMode as any,
+          memory_retention: formData.memoryRetention,
         },
       };
 
@@ -217,7 +217,7 @@ export const ProjectList: React.FC<ProjectListProps> = ({
                       </div>
                     </div>
                     <Badge variant="secondary" className="text-xs">
-                      {project.settings.collaborationMode}
+                      {project.settings.collaboration_mode}
                     </Badge>
                   </div>
 
@@ -45,10 +45,10 @@ describe('useProjectStore', () => {
           createdAt: new Date('2024-01-01'),
           updatedAt: new Date(),
           settings: {
-            defaultAITool: 'claude-code',
-            autoSave: true,
-            collaborationMode: 'single',
-            memoryRetention: 30,
+            default_ai_tool: 'claude-code',
+            auto_save: true,
+            collaboration_mode: 'single',
+            memory_retention: 30,
           },
           aiTools: [],
           sessions: [],
@@ -63,7 +63,7 @@ describe('useProjectStore', () => {
         await result.current.loadProjects();
       });
 
-      expect(mockInvoke).toHaveBeenCalledWith('db_get_projects');
+      expect(mockInvoke).toHaveBeenCalledWith('load_projects');
       expect(result.current.projects).toEqual(mockProjects);
       expect(result.current.isLoading).toBe(false);
       expect(result.current.error).toBeNull();
@@ -92,10 +92,10 @@ describe('useProjectStore', () => {
         path: '/new/project/path',
         description: 'New project description',
         settings: {
-          defaultAITool: 'gemini-cli',
-          autoSave: false,
-          collaborationMode: 'swarm',
-          memoryRetention: 60,
+          default_ai_tool: 'gemini-cli',
+          auto_save: false,
+          collaboration_mode: 'swarm',
+          memory_retention: 60,
         },
       };
 
@@ -117,11 +117,13 @@ describe('useProjectStore', () => {
         createdProject = await result.current.createProject(projectConfig);
       });
 
-      expect(mockInvoke).toHaveBeenCalledWith('db_create_project', {
-        name: projectConfig.name,
-        path: projectConfig.path,
-        description: projectConfig.description,
-        settings: projectConfig.settings,
+      expect(mockInvoke).toHaveBeenCalledWith('create_project', {
+        config: {
+          name: projectConfig.name,
+          path: projectConfig.path,
+          description: projectConfig.description,
+          settings: projectConfig.settings,
+        },
       });
       expect(result.current.projects).toContain(mockNewProject);
       expect(result.current.currentProject).toEqual(mockNewProject);
@@ -164,10 +166,10 @@ describe('useProjectStore', () => {
         createdAt: new Date(),
         updatedAt: new Date(),
         settings: {
-          defaultAITool: 'claude-code',
-          autoSave: true,
-          collaborationMode: 'single',
-          memoryRetention: 30,
+          default_ai_tool: 'claude-code',
+          auto_save: true,
+          collaboration_mode: 'single',
+          memory_retention: 30,
         },
         aiTools: [],
         sessions: [],
@@ -204,10 +206,10 @@ describe('useProjectStore', () => {
         createdAt: new Date(),
         updatedAt: new Date(),
         settings: {
-          defaultAITool: 'claude-code',
-          autoSave: true,
-          collaborationMode: 'single',
-          memoryRetention: 30,
+          default_ai_tool: 'claude-code',
+          auto_save: true,
+          collaboration_mode: 'single',
+          memory_retention: 30,
         },
         aiTools: [],
         sessions: [],
@@ -239,13 +241,44 @@ describe('useProjectStore', () => {
         await result.current.updateProject('1', updates);
       });
 
-      expect(mockInvoke).toHaveBeenCalledWith('db_update_project', {
+      expect(mockInvoke).toHaveBeenCalledWith('update_project', {
         projectId: '1',
-        updates,
+        update: updates,
       });
       expect(result.current.projects[0]).toEqual(updatedProject);
       expect(result.current.currentProject).toEqual(updatedProject);
     });
+
+    it('백엔드가 받는 필드만 보내야 함', async () => {
+      const project: Project = {
+        id: '1',
+        name: 'Project',
+        path: '/project/path',
+        createdAt: new Date(),
+        updatedAt: new Date(),
+        settings: {
+          default_ai_tool: 'claude-code',
+          auto_save: true,
+          collaboration_mode: 'single',
+          memory_retention: 30,
+        },
+        aiTools: [],
+        sessions: [],
+      };
+
+      mockInvoke.mockResolvedValueOnce(project);
+
+      const { result } = renderHook(() => useProjectStore());
+
+      await act(async () => {
+        await result.current.updateProject('1', { ...project, name: 'Renamed' });
+      });
+
+      const { update } = mockInvoke.mock.calls[0][1] as { update: Record<string, unknown> };
+      expect(Object.keys(update).filter(key => update[key] !== undefined).sort())
+        .toEqual(['name', 'path', 'settings']);
+      expect(update.name).toBe('Renamed');
+    });
   });
 
   describe('deleteProject', () => {
@@ -258,10 +291,10 @@ describe('useProjectStore', () => {
         createdAt: new Date(),
         updatedAt: new Date(),
         settings: {
-          defaultAITool: 'claude-code',
-          autoSave: true,
-          collaborationMode: 'single',
-          memoryRetention: 30,
+          default_ai_tool: 'claude-code',
+          auto_save: true,
+          collaboration_mode: 'single',
+          memory_retention: 30,
         },
         aiTools: [],
         sessions: [],
@@ -282,7 +315,7 @@ describe('useProjectStore', () => {
         await result.current.deleteProject('1');
       });
 
-      expect(mockInvoke).toHaveBeenCalledWith('db_delete_project', { projectId: '1' });
+      expect(mockInvoke).toHaveBeenCalledWith('delete_project', { projectId: '1' });
       expect(result.current.projects).toEqual([]);
       expect(result.current.currentProject).toBeNull();
     });
@@ -1,7 +1,23 @@
 import { create } from 'zustand';
 import { devtools, persist } from 'zustand/middleware';
 import { invoke } from '@tauri-apps/api/core';
-import type { Project, ProjectConfig, SessionSummary } from '../types';
+import type { Project, ProjectConfig, ProjectDeleteOptions, ProjectSettings, SessionSummary } from '../types';
+
+// The backend needs these whenever settings are sent; the rest have defaults there
+const DEFAULT_PROJECT_SETTINGS: ProjectSettings = {
+  default_ai_tool: 'claude-code',
+  auto_save: true,
+  collaboration_mode: 'single',
+  memory_retention: 30,
+};
+
+// `update_project` refuses fields it doesn't know, so only the ones it can change are sent
+const toProjectUpdate = ({ name, description, path, settings }: Partial<Project>) => ({
+  name,
+  description,
+  path,
+  settings,
+});
 
 interface ProjectState {
   // State
@@ -15,7 +31,7 @@ interface ProjectState {
   selectProject: (projectId: string) => void;
   createProject: (config: ProjectConfig) => Promise<Project>;
   updateProject: (projectId: string, updates: Partial<Project>) => Promise<void>;
-  deleteProject: (projectId: string) => Promise<void>;
+  deleteProject: (projectId: string, options?: ProjectDeleteOptions) => Promise<void>;
   clearError: () => void;
 }
 
@@ -33,7 +49,7 @@ export const useProjectStore = create<ProjectState>()(
         loadProjects: async () => {
           set({ isLoading: true, error: null });
           try {
-            const projects = await invoke<Project[]>('db_get_projects');
+            const projects = await invoke<Project[]>('load_projects');
             set({ projects, isLoading: false });
           } catch (error) {
             set({ 
@@ -54,15 +70,12 @@ export const useProjectStore = create<ProjectState>()(
         createProject: async (config: ProjectConfig) => {
           set({ isLoading: true, error: null });
           try {
-            const newProject = await invoke<Project>('db_create_project', {
-              name: config.name,
-              path: config.path,
-              description: config.description || '',
-              settings: config.settings || {
-                defaultAITool: 'claude-code',
-                autoSave: true,
-                collaborationMode: 'single',
-                memoryRetention: 30,
+            const newProject = await invoke<Project>('create_project', {
+              config: {
+                name: config.name,
+                path: config.path,
+                description: config.description || '',
+                settings: { ...DEFAULT_PROJECT_SETTINGS, ...config.settings },
               },
             });
             set(state => ({
@@ -83,9 +96,9 @@ export const useProjectStore = create<ProjectState>()(
         updateProject: async (projectId: string, updates: Partial<Project>) => {
           set({ isLoading: true, error: null });
           try {
-            const updatedProject = await invoke<Project>('db_update_project', {
+            const updatedProject = await invoke<Project>('update_project', {
               projectId,
-              updates,
+              update: toProjectUpdate(updates),
             });
             set(state => ({
               projects: state.projects.map(p => 
@@ -104,10 +117,10 @@ export const useProjectStore = create<ProjectState>()(
           }
         },
 
-        deleteProject: async (projectId: string) => {
+        deleteProject: async (projectId: string, options?: ProjectDeleteOptions) => {
           set({ isLoading: true, error: null });
           try {
-            await invoke('db_delete_project', { projectId });
+            await invoke('delete_project', { projectId, options });
             set(state => ({
               projects: state.projects.filter(p => p.id !== projectId),
               currentProject: state.currentProject?.id === projectId 
@@ -38,10 +38,10 @@ describe('프로젝트 관리 워크플로우 통합 테스트', () => {
       createdAt: new Date(),
       updatedAt: new Date(),
       settings: {
-        defaultAITool: 'claude-code',
-        autoSave: true,
-        collaborationMode: 'swarm',
-        memoryRetention: 30,
+        default_ai_tool: 'claude-code',
+        auto_save: true,
+        collaboration_mode: 'swarm',
+        memory_retention: 30,
       },
       aiTools: [],
       sessions: [],
@@ -56,10 +56,10 @@ describe('프로젝트 관리 워크플로우 통합 테스트', () => {
       path: '/test/ai-project',
       description: 'AI 도구들이 협업하여 개발하는 프로젝트',
       settings: {
-        defaultAITool: 'claude-code',
-        autoSave: true,
-        collaborationMode: 'swarm',
-        memoryRetention: 30,
+        default_ai_tool: 'claude-code',
+        auto_save: true,
+        collaboration_mode: 'swarm',
+        memory_retention: 30,
       },
     };
 
@@ -316,10 +316,10 @@ describe('Button', () => {
 
     const finalProject = projectStore.current.currentProject;
     expect(finalProject?.id).toBe('project_1');
-    expect(finalProject?.settings.collaborationMode).toBe('swarm');
+    expect(finalProject?.settings.collaboration_mode).toBe('swarm');
 
     // 모든 Tauri 호출이 올바르게 이루어졌는지 확인
-    expect(mockInvoke).toHaveBeenCalledWith('db_create_project', expect.any(Object));
+    expect(mockInvoke).toHaveBeenCalledWith('create_project', expect.any(Object));
     expect(mockInvoke).toHaveBeenCalledWith('create_swarm', expect.any(Object));
     expect(mockInvoke).toHaveBeenCalledWith('db_create_chat_session', expect.any(Object));
     expect(mockInvoke).toHaveBeenCalledWith('db_create_chat_message', expect.any(Object));
@@ -335,7 +335,7 @@ describe('Button', () => {
       description: 'Test',
       createdAt: new Date(),
       updatedAt: new Date(),
-      settings: { defaultAITool: 'claude-code', autoSave: true, collaborationMode: 'single', memoryRetention: 30 },
+      settings: { default_ai_tool: 'claude-code', auto_save: true, collaboration_mode: 'single', memory_retention: 30 },
       aiTools: [],
       sessions: [],
     };
@@ -6,16 +6,27 @@ export interface Project {
   description?: string;
   createdAt: Date;
   updatedAt: Date;
+  lastOpenedAt?: Date;
   settings: ProjectSettings;
   aiTools: AIToolConfig[];
-  sessions: SessionSummary[];
+  sessions: SessionSummary[]; // the 20 most recent, when requested
+  sessionCount?: number;
+  missing?: boolean; // the project directory no longer exists
+  archived?: boolean;
 }
 
+export type ProjectOrder = 'last_opened' | 'updated' | 'name';
+
+// Field names match the backend, which doesn't rename nested fields
 export interface ProjectSettings {
-  defaultAITool: string;
-  autoSave: boolean;
-  collaborationMode: 'single' | 'swarm' | 'sequential';
-  memoryRetention: number; // days
+  default_ai_tool: string;
+  auto_save: boolean;
+  collaboration_mode: 'single' | 'swarm' | 'sequential';
+  memory_retention: number; // days
+  fallback_order?: string[]; // tools to fall back to, in order, when the chosen one is down
+  auto_title?: boolean; // title new sessions from their first exchange
+  require_approval?: boolean; // ask before a swarm deletes, writes outside the project or runs a command
+  allowed_commands?: string[]; // programs a swarm may run without asking
 }
 
 export interface ProjectConfig {
@@ -23,6 +34,8 @@ export interface ProjectConfig {
   path: string;
   description?: string;
   settings?: Partial<ProjectSettings>;
+  createDirectoryIfMissing?: boolean;
+  initializeGit?: boolean;
 }
 
 export interface SessionSummary {
@@ -33,4 +46,11 @@ export interface SessionSummary {
   lastActive: Date;
   messageCount: number;
   status: 'active' | 'completed' | 'paused';
-}
\ No newline at end of file
+}
+// Field names match the backend, which doesn't rename nested fields
+export interface ProjectDeleteOptions {
+  delete_sessions?: boolean;
+  delete_swarms?: boolean;
+  delete_memory?: boolean;
+  dry_run?: boolean;
+}
@@ -60,12 +60,14 @@ export interface DialogAction {
 // Chat interface types
 export interface ChatMessage {
   id: string;
-  role: 'user' | 'assistant' | 'system';
+  role: 'user' | 'assistant' | 'system' | 'tool';
   content: string;
   timestamp: Date;
   agentId?: string;
   attachments?: MessageAttachment[];
   metadata?: Record<string, any>;
+  promptTokens?: number;
+  completionTokens?: number;
 }
 
 export interface MessageAttachment {
@@ -84,6 +86,13 @@ export interface ChatSession {
   projectId?: string;
   aiTool?: string;
   swarmId?: string;
+  pinned?: boolean;
+  archived?: boolean;
+  nameSource?: 'initial' | 'auto' | 'manual';
+  lastReadMessageAt?: Date;
+  unreadCount?: number;
+  totalTokens?: number; // when the list is loaded with token totals
+  estimatedCost?: number;
   messages: ChatMessage[];
   participants: Participant[];
   createdAt: Date;
@@ -117,4 +126,15 @@ export interface EditorState {
   fileContents: Record<string, string>;
   cursorPosition: Record<string, { line: number; column: number }>;
   modifications: Record<string, boolean>;
-}
\ No newline at end of file
+}
+// Opening a project, session or swarm from outside the app; field names match the backend
+export type Navigation =
+  | { target: 'project'; id: string }
+  | { target: 'register_project'; path: string } // a directory that isn't a project yet
+  | { target: 'session'; id: string; project_id: string | null }
+  | { target: 'swarm'; id: string; project_id: string };
+
+export interface NavigationError {
+  input: string; // the argument or link as given
+  message: string;
+}